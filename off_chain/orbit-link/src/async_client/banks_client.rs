@@ -1,3 +1,4 @@
+use anchor_client::solana_client::rpc_response::RpcResponseContext;
 use async_trait::async_trait;
 use solana_banks_client::{BanksClient, TransactionStatus as BankTransactionStatus};
 use solana_banks_interface::TransactionConfirmationStatus as BankTransactionConfirmationStatus;
@@ -36,15 +37,31 @@ fn bank_status_to_transaction_status(bank_status: BankTransactionStatus) -> Tran
 impl AsyncClient for Mutex<BanksClient> {
     async fn simulate_transaction(
         &self,
-        _transaction: &VersionedTransaction,
+        transaction: &VersionedTransaction,
     ) -> Result<Response<RpcSimulateTransactionResult>> {
-        unimplemented!("Versioned transaction simulations are not supported by BanksClient yet (wait for solana 1.15.0)")
+        let mut bank = self.lock().await;
+        let slot = bank.get_root_slot().await?;
+        let simulation = bank.simulate_transaction(transaction.clone()).await?;
+        let details = simulation.simulation_details;
+        Ok(Response {
+            context: RpcResponseContext::new(slot),
+            value: RpcSimulateTransactionResult {
+                err: simulation.result.and_then(|res| res.err()),
+                logs: details.as_ref().map(|d| d.logs.clone()),
+                accounts: None,
+                units_consumed: details.as_ref().map(|d| d.units_consumed),
+                return_data: None,
+            },
+        })
     }
 
-    async fn send_transaction(&self, _transaction: &VersionedTransaction) -> Result<Signature> {
-        unimplemented!(
-            "Versioned transactions are not supported by BanksClient yet (wait for solana 1.15.0)"
-        )
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        let mut bank = self.lock().await;
+        let signature = *transaction.signatures.first().ok_or(
+            solana_banks_client::BanksClientError::ClientError("Transaction is not signed"),
+        )?;
+        bank.send_transaction(transaction.clone()).await?;
+        Ok(signature)
     }
 
     async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64> {
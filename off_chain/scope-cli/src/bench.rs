@@ -0,0 +1,133 @@
+//! Statistics of a refresh throughput benchmark, see the `bench-refresh` command
+
+use std::time::Duration;
+
+use crate::scope_client::{ChunkRefresh, RefreshOutcome};
+
+/// Refresh transactions sent during a benchmark
+#[derive(Debug, Default, Clone)]
+pub struct BenchStats {
+    /// Confirmation latency of the successful transactions
+    latencies: Vec<Duration>,
+    /// Number of failed transactions
+    failures: usize,
+}
+
+/// Summary of a benchmark, see [`BenchStats::report`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// Number of transactions sent, failed ones included
+    pub transactions: usize,
+    /// Number of failed transactions
+    pub failures: usize,
+    /// Sent transactions per second
+    pub tx_per_sec: f64,
+    /// Ratio of failed transactions, between 0 and 1
+    pub failure_rate: f64,
+    /// Median confirmation latency of the successful transactions
+    pub latency_p50: Option<Duration>,
+    /// 90th percentile of the confirmation latency
+    pub latency_p90: Option<Duration>,
+    /// 99th percentile of the confirmation latency
+    pub latency_p99: Option<Duration>,
+}
+
+impl BenchStats {
+    /// Record a sent transaction
+    pub fn record(&mut self, latency: Duration, success: bool) {
+        if success {
+            self.latencies.push(latency);
+        } else {
+            self.failures += 1;
+        }
+    }
+
+    /// Record the transactions of a refresh, simulated ones are ignored
+    pub fn record_chunks(&mut self, chunks: &[ChunkRefresh]) {
+        for chunk in chunks {
+            match &chunk.result {
+                Ok(RefreshOutcome::Sent(_)) => self.record(chunk.elapsed, true),
+                Ok(RefreshOutcome::Simulated(_)) => {}
+                Err(_) => self.record(chunk.elapsed, false),
+            }
+        }
+    }
+
+    /// Summarize the transactions sent during `duration`
+    pub fn report(&self, duration: Duration) -> BenchReport {
+        let transactions = self.latencies.len() + self.failures;
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        let secs = duration.as_secs_f64();
+        BenchReport {
+            transactions,
+            failures: self.failures,
+            tx_per_sec: if secs > 0.0 {
+                transactions as f64 / secs
+            } else {
+                0.0
+            },
+            failure_rate: if transactions > 0 {
+                self.failures as f64 / transactions as f64
+            } else {
+                0.0
+            },
+            latency_p50: percentile(&latencies, 50),
+            latency_p90: percentile(&latencies, 90),
+            latency_p99: percentile(&latencies, 99),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values, `None` if there is none
+fn percentile(sorted: &[Duration], percent: usize) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percent * sorted.len() + 99) / 100;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_from_synthetic_timings() {
+        let mut stats = BenchStats::default();
+        // 1ms to 100ms confirmations and 25 failures
+        for ms in 1..=100 {
+            stats.record(Duration::from_millis(ms), true);
+        }
+        for _ in 0..25 {
+            stats.record(Duration::from_secs(30), false);
+        }
+
+        let report = stats.report(Duration::from_secs(50));
+        assert_eq!(report.transactions, 125);
+        assert_eq!(report.failures, 25);
+        assert_eq!(report.tx_per_sec, 2.5);
+        assert_eq!(report.failure_rate, 0.2);
+        // Failed transactions do not count in the latencies
+        assert_eq!(report.latency_p50, Some(Duration::from_millis(50)));
+        assert_eq!(report.latency_p90, Some(Duration::from_millis(90)));
+        assert_eq!(report.latency_p99, Some(Duration::from_millis(99)));
+    }
+
+    #[test]
+    fn report_without_transactions() {
+        let report = BenchStats::default().report(Duration::ZERO);
+        assert_eq!(report.transactions, 0);
+        assert_eq!(report.tx_per_sec, 0.0);
+        assert_eq!(report.failure_rate, 0.0);
+        assert_eq!(report.latency_p50, None);
+    }
+
+    #[test]
+    fn percentile_of_few_values() {
+        let sorted = [1, 2, 3].map(Duration::from_millis);
+        assert_eq!(percentile(&sorted, 50), Some(Duration::from_millis(2)));
+        assert_eq!(percentile(&sorted, 99), Some(Duration::from_millis(3)));
+        assert_eq!(percentile(&sorted, 0), Some(Duration::from_millis(1)));
+    }
+}
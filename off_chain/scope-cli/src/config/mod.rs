@@ -1,6 +1,6 @@
 pub mod scope_config;
 pub mod token_config;
 pub use scope_config::*;
-pub use token_config::TokenConfig;
+pub use token_config::{PriceBounds, TokenConfig};
 
 mod utils;
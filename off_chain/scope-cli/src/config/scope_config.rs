@@ -53,9 +53,13 @@ mod tests {
             TokenConfig {
                 label: "SOL/USD".to_string(),
                 max_age: None,
+                price_max_age: None,
                 oracle_mapping: Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix")
                     .unwrap(),
                 oracle_type: OracleType::Pyth,
+                share_mint: None,
+                sources: vec![],
+                price_bounds: None,
             },
         );
         token_conf_list.tokens.insert(
@@ -63,9 +67,13 @@ mod tests {
             TokenConfig {
                 label: "ETH/USD".to_string(),
                 max_age: None,
+                price_max_age: None,
                 oracle_mapping: Pubkey::from_str("EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw")
                     .unwrap(),
                 oracle_type: OracleType::SwitchboardV1,
+                share_mint: None,
+                sources: vec![],
+                price_bounds: None,
             },
         );
         token_conf_list.tokens.insert(
@@ -73,9 +81,13 @@ mod tests {
             TokenConfig {
                 label: "STSOL/USD".to_string(),
                 max_age: None,
+                price_max_age: None,
                 oracle_mapping: Pubkey::from_str("9LNYQZLJG5DAyeACCTzBFG6H3sDhehP5xtYLdhrZtQkA")
                     .unwrap(),
                 oracle_type: OracleType::SwitchboardV2,
+                share_mint: None,
+                sources: vec![],
+                price_bounds: None,
             },
         );
         token_conf_list.tokens.insert(
@@ -83,9 +95,13 @@ mod tests {
             TokenConfig {
                 label: "cSOL/SOL".to_string(),
                 max_age: None,
+                price_max_age: None,
                 oracle_mapping: Pubkey::from_str("9LNYQZLJG5DAyeACCTzBFG6H3sDhehP5xtYLdhrZtQkA")
                     .unwrap(),
                 oracle_type: OracleType::CToken,
+                share_mint: None,
+                sources: vec![],
+                price_bounds: None,
             },
         );
         token_conf_list.tokens.insert(
@@ -93,9 +109,13 @@ mod tests {
             TokenConfig {
                 label: "kUSDHUSDCOrca/USD".to_string(),
                 max_age: None,
+                price_max_age: None,
                 oracle_mapping: Pubkey::from_str("VF45TSF5WPAay9qy2zr1hPYgieBv7r17vYLRK6v1RmB")
                     .unwrap(),
                 oracle_type: OracleType::KToken,
+                share_mint: None,
+                sources: vec![],
+                price_bounds: None,
             },
         );
 
@@ -3,7 +3,7 @@ use std::num::NonZeroU64;
 use scope::{anchor_lang::prelude::Pubkey, oracles::OracleType};
 use serde::{Deserialize, Serialize};
 
-use super::utils::serde_string;
+use super::utils::{serde_option_string, serde_string, serde_string_vec};
 
 /// Configuration of the tokens
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -16,9 +16,39 @@ pub struct TokenConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Optional specific token max age (in number of slot).
     pub max_age: Option<NonZeroU64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Optional max age (in number of slot) of the source price, enforced on-chain at refresh.
+    pub price_max_age: Option<NonZeroU64>,
     /// Onchain account used as source for the exchange rate.
     #[serde(with = "serde_string")] // Use bs58 for serialization
     pub oracle_mapping: Pubkey,
+    /// Share mint of the vault used as mapping, only for `SharePrice` tokens.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "serde_option_string"
+    )]
+    pub share_mint: Option<Pubkey>,
+    /// Sources of a `MostRecentOf` token, the mapping is derived from them.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        with = "serde_string_vec"
+    )]
+    pub sources: Vec<Pubkey>,
+    /// Optional range of the accepted prices, enforced on-chain at refresh.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_bounds: Option<PriceBounds>,
+}
+
+/// Lowest and highest price of a token, expressed with the same exponent
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PriceBounds {
+    /// Lowest accepted value, 0 for no lower bound
+    pub min: u64,
+    /// Highest accepted value, 0 or `u64::MAX` for no upper bound
+    pub max: u64,
+    pub exp: u64,
 }
 
 #[cfg(test)]
@@ -33,9 +63,13 @@ mod tests {
         let token_conf = TokenConfig {
             label: "SOL/USD".to_string(),
             max_age: None,
+            price_max_age: None,
             oracle_mapping: Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix")
                 .unwrap(),
             oracle_type: OracleType::Pyth,
+            share_mint: None,
+            sources: vec![],
+            price_bounds: None,
         };
 
         let json = r#"{
@@ -51,4 +85,136 @@ mod tests {
         let deserialized = serde_json::to_string(&token_conf).unwrap();
         assert_eq!(remove_whitespace(&deserialized), remove_whitespace(json));
     }
+
+    #[test]
+    fn share_price_conf_de_ser() {
+        let token_conf = TokenConfig {
+            label: "wSOL shares".to_string(),
+            max_age: None,
+            price_max_age: None,
+            oracle_mapping: Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix")
+                .unwrap(),
+            oracle_type: OracleType::SharePrice,
+            share_mint: Some(
+                Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap(),
+            ),
+            sources: vec![],
+            price_bounds: None,
+        };
+
+        let json = r#"{
+              "label": "wSOL shares",
+              "oracle_type": "SharePrice",
+              "oracle_mapping": "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix",
+              "share_mint": "So11111111111111111111111111111111111111112"
+            }
+            "#;
+
+        let serialized: TokenConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(token_conf, serialized);
+
+        let deserialized = serde_json::to_string(&token_conf).unwrap();
+        assert_eq!(remove_whitespace(&deserialized), remove_whitespace(json));
+    }
+
+    #[test]
+    fn most_recent_of_conf_de_ser() {
+        let token_conf = TokenConfig {
+            label: "SOL/USD".to_string(),
+            max_age: None,
+            price_max_age: None,
+            oracle_mapping: Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix")
+                .unwrap(),
+            oracle_type: OracleType::MostRecentOf,
+            share_mint: None,
+            sources: vec![
+                Pubkey::from_str("H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG").unwrap(),
+                Pubkey::from_str("GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR").unwrap(),
+            ],
+            price_bounds: None,
+        };
+
+        let json = r#"{
+              "label": "SOL/USD",
+              "oracle_type": "MostRecentOf",
+              "oracle_mapping": "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix",
+              "sources": [
+                "H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG",
+                "GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR"
+              ]
+            }
+            "#;
+
+        let serialized: TokenConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(token_conf, serialized);
+
+        let deserialized = serde_json::to_string(&token_conf).unwrap();
+        assert_eq!(remove_whitespace(&deserialized), remove_whitespace(json));
+    }
+
+    #[test]
+    fn price_max_age_conf_de_ser() {
+        let token_conf = TokenConfig {
+            label: "SOL/USD".to_string(),
+            max_age: None,
+            price_max_age: NonZeroU64::new(150),
+            oracle_mapping: Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix")
+                .unwrap(),
+            oracle_type: OracleType::Pyth,
+            share_mint: None,
+            sources: vec![],
+            price_bounds: None,
+        };
+
+        let json = r#"{
+              "label": "SOL/USD",
+              "oracle_type": "Pyth",
+              "price_max_age": 150,
+              "oracle_mapping": "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix"
+            }
+            "#;
+
+        let serialized: TokenConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(token_conf, serialized);
+
+        let deserialized = serde_json::to_string(&token_conf).unwrap();
+        assert_eq!(remove_whitespace(&deserialized), remove_whitespace(json));
+    }
+
+    #[test]
+    fn price_bounds_conf_de_ser() {
+        let token_conf = TokenConfig {
+            label: "SOL/USD".to_string(),
+            max_age: None,
+            price_max_age: None,
+            oracle_mapping: Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix")
+                .unwrap(),
+            oracle_type: OracleType::Pyth,
+            share_mint: None,
+            sources: vec![],
+            price_bounds: Some(PriceBounds {
+                min: 1_000,
+                max: 100_000,
+                exp: 2,
+            }),
+        };
+
+        let json = r#"{
+              "label": "SOL/USD",
+              "oracle_type": "Pyth",
+              "oracle_mapping": "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix",
+              "price_bounds": {
+                "min": 1000,
+                "max": 100000,
+                "exp": 2
+              }
+            }
+            "#;
+
+        let serialized: TokenConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(token_conf, serialized);
+
+        let deserialized = serde_json::to_string(&token_conf).unwrap();
+        assert_eq!(remove_whitespace(&deserialized), remove_whitespace(json));
+    }
 }
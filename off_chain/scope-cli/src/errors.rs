@@ -0,0 +1,182 @@
+use anchor_client::solana_sdk::{signature::Signature, transaction::TransactionError};
+use orbit_link::errors::ErrorKind;
+use thiserror::Error;
+
+pub type Result<T, E = ScopeClientError> = std::result::Result<T, E>;
+
+/// Messages of rpc errors that are expected to go away on retry (lowercase)
+const TRANSIENT_RPC_ERRORS: [&str; 8] = [
+    "blockhash not found",
+    "blockhashnotfound",
+    "node is behind",
+    "node is unhealthy",
+    "nodeunhealthy",
+    "timed out",
+    "too many requests",
+    "connection",
+];
+
+/// Errors returned by the [`crate::ScopeClient`]
+#[derive(Error, Debug)]
+pub enum ScopeClientError {
+    #[error("Scope program is not initialized for this price feed")]
+    NotInitialized,
+
+    #[error("Token index {0} is out of range")]
+    TokenOutOfRange(u64),
+
+    #[error("Token {0} is not in the local oracle mapping")]
+    UnknownToken(u16),
+
+    #[error("Pair {0} is not in the local oracle mapping")]
+    UnknownPair(String),
+
+    #[error("Pair {pair} (token {token}) is not mapped to an oracle")]
+    UnmappedPair { pair: String, token: u16 },
+
+    #[error("Pair {pair} matches several tokens: {tokens:?}")]
+    AmbiguousPair { pair: String, tokens: Vec<u16> },
+
+    #[error("Invalid token range: start ({start}) is greater than end ({end})")]
+    InvalidRange { start: u16, end: u16 },
+
+    #[error("Invalid refresh chunk size {0}, must be between 1 and {max}", max = crate::scope_client::MAX_REFRESH_CHUNK_SIZE)]
+    InvalidChunkSize(usize),
+
+    #[error("Invalid oracle type {0}")]
+    InvalidOracleType(u8),
+
+    #[error("Price of token {token} cannot be recomputed: {err}")]
+    PriceRecomputation {
+        token: u16,
+        err: scope::anchor_lang::error::Error,
+    },
+
+    #[error("Transaction {signature} failed: {err}")]
+    TransactionFailed {
+        signature: Signature,
+        err: TransactionError,
+    },
+
+    #[error("Transaction {0} could not be confirmed")]
+    TransactionNotConfirmed(Signature),
+
+    #[error("Transaction simulation failed: {0}")]
+    SimulationFailed(TransactionError),
+
+    #[error("Rpc error: {0}")]
+    Rpc(#[from] ErrorKind),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl ScopeClientError {
+    /// Map the result of a sent transaction as returned by [`orbit_link::OrbitLink`]
+    pub fn from_tx_result(
+        signature: Signature,
+        res: Option<std::result::Result<(), TransactionError>>,
+    ) -> Result<()> {
+        match res {
+            Some(Ok(())) => Ok(()),
+            Some(Err(err)) => Err(ScopeClientError::TransactionFailed { signature, err }),
+            None => Err(ScopeClientError::TransactionNotConfirmed(signature)),
+        }
+    }
+
+    /// Whether the error is expected to go away when sending the transaction again
+    ///
+    /// Transactions failing this way did not execute, they can be rebuilt and sent again
+    /// (see [`crate::retry`]).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ScopeClientError::TransactionNotConfirmed(_) => true,
+            ScopeClientError::TransactionFailed { err, .. } => {
+                matches!(err, TransactionError::BlockhashNotFound)
+            }
+            ScopeClientError::Rpc(err) => {
+                let msg = err.to_string().to_lowercase();
+                TRANSIENT_RPC_ERRORS.iter().any(|pat| msg.contains(pat))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tx_result_mapping() {
+        let signature = Signature::default();
+        assert!(ScopeClientError::from_tx_result(signature, Some(Ok(()))).is_ok());
+        assert!(matches!(
+            ScopeClientError::from_tx_result(
+                signature,
+                Some(Err(TransactionError::AccountNotFound))
+            ),
+            Err(ScopeClientError::TransactionFailed {
+                err: TransactionError::AccountNotFound,
+                ..
+            })
+        ));
+        assert!(matches!(
+            ScopeClientError::from_tx_result(signature, None),
+            Err(ScopeClientError::TransactionNotConfirmed(_))
+        ));
+    }
+
+    #[test]
+    fn rpc_error_mapping() {
+        let err: ScopeClientError = ErrorKind::NoInstructions.into();
+        assert!(matches!(
+            err,
+            ScopeClientError::Rpc(ErrorKind::NoInstructions)
+        ));
+    }
+
+    #[test]
+    fn transient_errors() {
+        let signature = Signature::default();
+        assert!(ScopeClientError::TransactionNotConfirmed(signature).is_transient());
+        assert!(ScopeClientError::TransactionFailed {
+            signature,
+            err: TransactionError::BlockhashNotFound,
+        }
+        .is_transient());
+        assert!(!ScopeClientError::TransactionFailed {
+            signature,
+            err: TransactionError::AccountNotFound,
+        }
+        .is_transient());
+        assert!(!ScopeClientError::Rpc(ErrorKind::NoInstructions).is_transient());
+        assert!(!ScopeClientError::UnknownToken(0).is_transient());
+
+        #[cfg(feature = "rpc-client")]
+        {
+            use anchor_client::solana_client::client_error::{ClientError, ClientErrorKind};
+            let rpc_err = |msg: &str| -> ScopeClientError {
+                ErrorKind::from(ClientError::from(ClientErrorKind::Custom(msg.to_string()))).into()
+            };
+            assert!(rpc_err("Node is behind by 150 slots").is_transient());
+            assert!(rpc_err("Transaction simulation failed: Blockhash not found").is_transient());
+            assert!(!rpc_err("custom program error: 0x1771").is_transient());
+        }
+    }
+
+    #[test]
+    fn anyhow_round_trip() {
+        // Errors from helpers using anyhow are kept as is
+        let err: ScopeClientError = anyhow::anyhow!("some helper error").into();
+        assert!(matches!(err, ScopeClientError::Other(_)));
+        assert_eq!(err.to_string(), "some helper error");
+
+        // At the binary boundary the structured error can still be recovered
+        let err: anyhow::Error = ScopeClientError::TokenOutOfRange(600).into();
+        assert!(matches!(
+            err.downcast_ref::<ScopeClientError>(),
+            Some(ScopeClientError::TokenOutOfRange(600))
+        ));
+    }
+}
@@ -1,8 +1,12 @@
+pub mod bench;
 pub mod config;
+pub mod errors;
 pub mod oracle_helpers;
+pub mod retry;
 pub mod scope_client;
 pub mod utils;
 
 pub use config::ScopeConfig;
+pub use errors::ScopeClientError;
 
 pub use crate::scope_client::ScopeClient;
@@ -1,4 +1,5 @@
 use std::{
+    num::{NonZeroU32, NonZeroUsize},
     ops::Neg,
     path::{Path, PathBuf},
     time::{Duration, Instant},
@@ -12,10 +13,16 @@ use anchor_client::{
     },
     Cluster,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::{Parser, Subcommand};
 use orbit_link::{async_client::AsyncClient, OrbitLink};
-use scope_client::{utils::get_clock, ScopeClient, ScopeConfig};
+use scope_client::{
+    bench::{BenchReport, BenchStats},
+    retry::RetryPolicy,
+    scope_client::{MappingDiff, PriceDivergence, VerifyResult},
+    utils::get_clock,
+    ScopeClient, ScopeConfig,
+};
 use tokio::time::sleep;
 use tracing::{error, info, trace, warn};
 
@@ -72,6 +79,24 @@ enum Actions {
         mapping: PathBuf,
     },
 
+    /// Print the differences between the provided oracle mapping and the on-chain one.
+    /// Nothing is updated on chain
+    #[clap(arg_required_else_help = true)]
+    Diff {
+        /// Where is stored the mapping to compare
+        #[clap(long, env, parse(from_os_str))]
+        mapping: PathBuf,
+    },
+
+    /// Check that the accounts of the provided oracle mapping exist and are valid sources for
+    /// their oracle type. Nothing is updated on chain
+    #[clap(arg_required_else_help = true)]
+    Verify {
+        /// Where is stored the mapping to check
+        #[clap(long, env, parse(from_os_str))]
+        mapping: PathBuf,
+    },
+
     /// Initialize the program accounts
     /// This requires initial program deploy account and enough funds
     #[clap()]
@@ -81,6 +106,34 @@ enum Actions {
         mapping: Option<PathBuf>,
     },
 
+    /// Close the accounts of the price feed and send their rent to the destination.
+    /// This requires the admin of the feed, the feed cannot be used anymore
+    #[clap()]
+    Close {
+        /// Account receiving the rent of the closed accounts, the payer by default
+        #[clap(long, env, parse(try_from_str))]
+        destination: Option<Pubkey>,
+    },
+
+    /// Upgrade the accounts of the price feed created by a previous version of the program.
+    /// This requires the admin of the feed, it pays the rent of the grown accounts
+    #[clap()]
+    Migrate,
+
+    /// Propose a new admin of the price feed, it has to run `accept-admin` to become the admin.
+    /// This requires the admin of the feed
+    #[clap(arg_required_else_help = true)]
+    SetAdmin {
+        /// Proposed admin, the default pubkey cancels the pending proposal
+        #[clap(long, env, parse(try_from_str))]
+        pending_admin: Pubkey,
+    },
+
+    /// Become the admin of the price feed.
+    /// This requires the pending admin proposed with `set-admin`
+    #[clap()]
+    AcceptAdmin,
+
     /// Display the all prices from the oracle
     #[clap()]
     Show {
@@ -89,6 +142,24 @@ enum Actions {
         /// If provided only the prices listed in configuration file are displayed
         #[clap(long, env, parse(from_os_str))]
         mapping: Option<PathBuf>,
+
+        /// Optional exponent to display all the prices with
+        #[clap(long, env)]
+        uniform_exp: Option<u8>,
+    },
+
+    /// Display the decoded prices account as a table, one row per populated token
+    #[clap()]
+    Prices {
+        /// Optional configuration file to provide association between
+        /// entries number and a price name.
+        /// The onchain mapping is used if not provided
+        #[clap(long, env, parse(from_os_str))]
+        mapping: Option<PathBuf>,
+
+        /// Only display the prices older than this number of seconds
+        #[clap(long, env)]
+        stale_only: Option<u64>,
     },
 
     /// Automatically refresh the prices
@@ -119,6 +190,46 @@ enum Actions {
         /// Log old prices as errors when prices are still too old after all retries
         #[clap(long, env)]
         old_price_is_error: bool,
+        /// Max number of refresh transactions sent concurrently
+        #[clap(long, env, default_value = "4")]
+        max_in_flight_refreshes: NonZeroUsize,
+        /// Max number of sends of a refresh transaction failing on transient errors
+        #[clap(long, env, default_value = "3")]
+        max_refresh_attempts: NonZeroU32,
+        /// Max number of accounts of the tokens refreshed per transaction (1 to 24)
+        #[clap(long, env, default_value = "24")]
+        refresh_chunk_size: usize,
+    },
+
+    /// Periodically recompute the prices from their sources and alert when a stored price
+    /// diverges from its recomputation
+    #[clap()]
+    Watchdog {
+        /// Where is stored the mapping to check, the onchain mapping is used if not provided
+        #[clap(long, env, parse(from_os_str))]
+        mapping: Option<PathBuf>,
+        /// Max difference in bps between a stored price and its recomputation before alerting
+        #[clap(long, env, default_value = "100")]
+        tolerance_bps: u64,
+        /// Period in seconds between two checks
+        #[clap(long, env, default_value = "60")]
+        check_period_s: u64,
+    },
+
+    /// Refresh all the prices of the feed in a loop for the given duration and report the
+    /// throughput, confirmation latency and failure rate of the refresh transactions.
+    /// Only refresh transactions are sent, as the crank would do
+    #[clap()]
+    BenchRefresh {
+        /// Where is stored the mapping to use, the onchain mapping is used if not provided
+        #[clap(long, env, parse(from_os_str))]
+        mapping: Option<PathBuf>,
+        /// Duration of the benchmark in seconds
+        #[clap(long = "duration", env = "BENCH_DURATION", default_value = "60")]
+        duration_s: u64,
+        /// Max number of refresh transactions sent concurrently
+        #[clap(long, env, default_value = "4")]
+        max_in_flight_refreshes: NonZeroUsize,
     },
 
     /// Get a list of all pubkeys that are needed for price refreshed according to the configuration.
@@ -171,8 +282,29 @@ async fn main() -> Result<()> {
         match args.action {
             Actions::Download { mapping } => download(&mut scope, &mapping).await,
             Actions::Upload { mapping } => upload(&mut scope, &mapping).await,
+            Actions::Diff { mapping } => diff(&mut scope, &mapping).await,
+            Actions::Verify { mapping } => verify(&mut scope, &mapping).await,
             Actions::Init { .. } => unreachable!(),
-            Actions::Show { mapping } => show(&mut scope, &mapping).await,
+            Actions::Close { destination } => close(&scope, destination).await,
+            Actions::Migrate => Ok(scope.ix_migrate_feed().await?),
+            Actions::SetAdmin { pending_admin } => {
+                scope.ix_set_admin(pending_admin).await?;
+                info!(%pending_admin, "Pending admin proposed");
+                Ok(())
+            }
+            Actions::AcceptAdmin => {
+                scope.ix_accept_admin().await?;
+                info!(admin = %scope.get_payer(), "Admin accepted");
+                Ok(())
+            }
+            Actions::Show {
+                mapping,
+                uniform_exp,
+            } => show(&mut scope, &mapping, uniform_exp).await,
+            Actions::Prices {
+                mapping,
+                stale_only,
+            } => prices(&mut scope, &mapping, stale_only).await,
             Actions::Crank {
                 refresh_interval_slot,
                 mapping,
@@ -182,7 +314,16 @@ async fn main() -> Result<()> {
                 old_price_alert_snooze_time_s,
                 alert_old_price_after_slots,
                 old_price_is_error,
+                max_in_flight_refreshes,
+                max_refresh_attempts,
+                refresh_chunk_size,
             } => {
+                scope.set_max_in_flight_refreshes(max_in_flight_refreshes);
+                scope.set_refresh_chunk_size(refresh_chunk_size)?;
+                scope.set_retry_policy(RetryPolicy {
+                    max_attempts: max_refresh_attempts,
+                    ..Default::default()
+                });
                 let _server_handle = if server {
                     Some(web::server::thread_start(server_port).await)
                 } else {
@@ -199,6 +340,19 @@ async fn main() -> Result<()> {
                 )
                 .await
             }
+            Actions::Watchdog {
+                mapping,
+                tolerance_bps,
+                check_period_s,
+            } => watchdog(&mut scope, &mapping, tolerance_bps, check_period_s).await,
+            Actions::BenchRefresh {
+                mapping,
+                duration_s,
+                max_in_flight_refreshes,
+            } => {
+                scope.set_max_in_flight_refreshes(max_in_flight_refreshes);
+                bench_refresh(&mut scope, &mapping, duration_s).await
+            }
             Actions::GetPubkeys { mapping } => get_pubkeys(&mut scope, &mapping).await,
         }
     }
@@ -221,13 +375,71 @@ async fn init<T: AsyncClient, S: Signer>(
     Ok(())
 }
 
+async fn close<T: AsyncClient, S: Signer>(
+    scope: &ScopeClient<T, S>,
+    destination: Option<Pubkey>,
+) -> Result<()> {
+    let destination = destination.unwrap_or_else(|| scope.get_payer());
+    scope.ix_close(&destination).await?;
+    info!(%destination, "Price feed closed");
+    Ok(())
+}
+
 async fn upload<T: AsyncClient, S: Signer>(
     scope: &mut ScopeClient<T, S>,
     mapping: &impl AsRef<Path>,
 ) -> Result<()> {
     let token_list = ScopeConfig::read_from_file(&mapping)?;
     scope.set_local_mapping(&token_list).await?;
-    scope.upload_oracle_mapping().await
+    Ok(scope.upload_oracle_mapping().await?)
+}
+
+async fn diff<T: AsyncClient, S: Signer>(
+    scope: &mut ScopeClient<T, S>,
+    mapping: &impl AsRef<Path>,
+) -> Result<()> {
+    let token_list = ScopeConfig::read_from_file(&mapping)?;
+    scope.set_local_mapping(&token_list).await?;
+    let diffs = scope.diff_oracle_mapping().await?;
+
+    // For easier parsing of the output don't use tracing here.
+    println!("{:>5} | {:<44} | {:<44}", "token", "local", "remote");
+    for MappingDiff {
+        token,
+        local,
+        remote,
+    } in &diffs
+    {
+        let local = local.map_or_else(|| "-".to_string(), |pk| pk.to_string());
+        println!("{token:>5} | {local:<44} | {remote:<44}");
+    }
+    info!("{} token(s) differ from the on-chain mapping", diffs.len());
+    Ok(())
+}
+
+async fn verify<T: AsyncClient, S: Signer>(
+    scope: &mut ScopeClient<T, S>,
+    mapping: &impl AsRef<Path>,
+) -> Result<()> {
+    let token_list = ScopeConfig::read_from_file(&mapping)?;
+    scope.set_local_mapping(&token_list).await?;
+    let results = scope.verify_mapping_accounts().await?;
+
+    // For easier parsing of the output don't use tracing here.
+    println!("{:>5} | {:<44} | result", "token", "account");
+    let mut invalid = 0;
+    for (token, result) in &results {
+        if *result != VerifyResult::Ok {
+            invalid += 1;
+            let account = token_list.tokens[token].oracle_mapping;
+            println!("{token:>5} | {account:<44} | {result:?}");
+        }
+    }
+    if invalid > 0 {
+        bail!("{invalid} token(s) have an invalid mapping account");
+    }
+    info!("{} token(s) checked, all valid", results.len());
+    Ok(())
 }
 
 async fn download<T: AsyncClient, S: Signer>(
@@ -242,6 +454,7 @@ async fn download<T: AsyncClient, S: Signer>(
 async fn show<T: AsyncClient, S: Signer>(
     scope: &mut ScopeClient<T, S>,
     mapping_op: &Option<impl AsRef<Path>>,
+    uniform_exp: Option<u8>,
 ) -> Result<()> {
     if let Some(mapping) = mapping_op {
         let token_list = ScopeConfig::read_from_file(&mapping)?;
@@ -254,7 +467,24 @@ async fn show<T: AsyncClient, S: Signer>(
 
     info!(current_slot);
 
-    scope.log_prices(current_slot).await
+    Ok(scope.log_prices(current_slot, uniform_exp).await?)
+}
+
+async fn prices<T: AsyncClient, S: Signer>(
+    scope: &mut ScopeClient<T, S>,
+    mapping_op: &Option<impl AsRef<Path>>,
+    stale_after_s: Option<u64>,
+) -> Result<()> {
+    if let Some(mapping) = mapping_op {
+        let token_list = ScopeConfig::read_from_file(&mapping)?;
+        scope.set_local_mapping(&token_list).await?;
+    } else {
+        scope.download_oracle_mapping(0).await?;
+    }
+
+    // For easier parsing of the table don't use tracing here.
+    print!("{}", scope.prices_table(stale_after_s).await?);
+    Ok(())
 }
 
 async fn get_pubkeys<T: AsyncClient, S: Signer>(
@@ -268,7 +498,85 @@ async fn get_pubkeys<T: AsyncClient, S: Signer>(
         scope.download_oracle_mapping(0).await?;
     }
 
-    scope.print_pubkeys().await
+    Ok(scope.print_pubkeys().await?)
+}
+
+async fn watchdog<T: AsyncClient, S: Signer>(
+    scope: &mut ScopeClient<T, S>,
+    mapping_op: &Option<impl AsRef<Path>>,
+    tolerance_bps: u64,
+    check_period_s: u64,
+) -> Result<()> {
+    if let Some(mapping) = mapping_op {
+        let token_list = ScopeConfig::read_from_file(&mapping)?;
+        scope.set_local_mapping(&token_list).await?;
+    } else {
+        scope.download_oracle_mapping(0).await?;
+    }
+
+    let check_period = Duration::from_secs(check_period_s);
+    loop {
+        match scope.check_prices(tolerance_bps).await {
+            Ok(divergences) => {
+                for PriceDivergence {
+                    token,
+                    stored,
+                    recomputed,
+                    deviation_bps,
+                } in divergences
+                {
+                    error!(
+                        token,
+                        ?stored,
+                        ?recomputed,
+                        ?deviation_bps,
+                        "Stored price diverges from its sources"
+                    );
+                }
+            }
+            Err(e) => warn!("Error while checking prices {:?}", e),
+        }
+        sleep(check_period).await;
+    }
+}
+
+async fn bench_refresh<T: AsyncClient, S: Signer>(
+    scope: &mut ScopeClient<T, S>,
+    mapping_op: &Option<impl AsRef<Path>>,
+    duration_s: u64,
+) -> Result<()> {
+    if let Some(mapping) = mapping_op {
+        let token_list = ScopeConfig::read_from_file(&mapping)?;
+        scope.set_local_mapping(&token_list).await?;
+    } else {
+        scope.download_oracle_mapping(0).await?;
+    }
+
+    let duration = Duration::from_secs(duration_s);
+    let mut stats = BenchStats::default();
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        let chunks = scope.refresh_all_prices().await?;
+        stats.record_chunks(&chunks);
+    }
+    let BenchReport {
+        transactions,
+        failures,
+        tx_per_sec,
+        failure_rate,
+        latency_p50,
+        latency_p90,
+        latency_p99,
+    } = stats.report(start.elapsed());
+
+    // For easier parsing of the output don't use tracing here.
+    println!("transactions: {transactions} ({failures} failed)");
+    println!("throughput:   {tx_per_sec:.2} tx/s");
+    println!("failure rate: {:.2}%", failure_rate * 100.0);
+    println!("latency p50:  {latency_p50:?}");
+    println!("latency p90:  {latency_p90:?}");
+    println!("latency p99:  {latency_p99:?}");
+    Ok(())
 }
 
 async fn crank<T: AsyncClient, S: Signer>(
@@ -302,7 +610,7 @@ async fn crank<T: AsyncClient, S: Signer>(
             let current_slot = get_clock(scope.get_rpc()).await.unwrap_or_default().slot;
 
             info!(current_slot);
-            let _ = scope.log_prices(current_slot).await;
+            let _ = scope.log_prices(current_slot, None).await;
             sleep(print_period).await;
         }
     };
@@ -0,0 +1,103 @@
+//! Implementation of helper for constant product LP tokens
+
+use std::fmt::{Debug, Display};
+
+use anchor_client::solana_sdk::clock;
+use anyhow::{anyhow, Context, Result};
+use orbit_link::async_client::AsyncClient;
+use scope::{
+    anchor_lang::prelude::Pubkey,
+    oracles::{constant_product_lp::TokenSwap, OracleType},
+    DatedPrice,
+};
+
+use super::{OracleHelper, TokenEntry};
+use crate::config::TokenConfig;
+
+const NB_EXTRA_ACCOUNT: usize = 5;
+
+#[derive(Debug)]
+pub struct ConstantProductLpOracle {
+    label: String,
+    oracle_type: OracleType,
+    /// Pubkey of the token-swap pool
+    mapping: Pubkey,
+
+    /// Extra accounts are:
+    /// 0. The vault of the token A of the pool.
+    /// 1. The vault of the token B of the pool.
+    /// 2. The LP token mint.
+    /// 3. The mint of the token A of the pool.
+    /// 4. The mint of the token B of the pool.
+    extra_accounts: [Pubkey; NB_EXTRA_ACCOUNT],
+
+    /// Configured max age
+    max_age: clock::Slot,
+}
+
+impl ConstantProductLpOracle {
+    pub async fn new(
+        conf: &TokenConfig,
+        default_max_age: clock::Slot,
+        rpc: &dyn AsyncClient,
+    ) -> Result<Self> {
+        let mapping = conf.oracle_mapping;
+        let pool_account_raw = rpc
+            .get_account(&mapping)
+            .await
+            .context("Retrieving token-swap pool account")?;
+        let pool = TokenSwap::from_account_data(&pool_account_raw.data)
+            .map_err(|e| anyhow!("Invalid token-swap pool account {mapping}: {e:?}"))?;
+
+        Ok(Self {
+            label: conf.label.clone(),
+            oracle_type: conf.oracle_type,
+            mapping,
+            extra_accounts: pool.extra_accounts(),
+            max_age: conf.max_age.map(|nz| nz.into()).unwrap_or(default_max_age),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl OracleHelper for ConstantProductLpOracle {
+    fn get_type(&self) -> OracleType {
+        self.oracle_type
+    }
+
+    fn get_number_of_extra_accounts(&self) -> usize {
+        NB_EXTRA_ACCOUNT
+    }
+
+    fn get_mapping_account(&self) -> &Pubkey {
+        &self.mapping
+    }
+
+    async fn get_extra_accounts(&self, _rpc: Option<&dyn AsyncClient>) -> Result<Vec<Pubkey>> {
+        Ok(self.extra_accounts.to_vec())
+    }
+
+    fn get_max_age(&self) -> clock::Slot {
+        self.max_age
+    }
+
+    fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn need_refresh(
+        &self,
+        _scope_price: &DatedPrice,
+        _rpc: &dyn AsyncClient,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Display for ConstantProductLpOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl TokenEntry for ConstantProductLpOracle {}
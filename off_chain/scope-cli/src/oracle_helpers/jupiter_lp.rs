@@ -0,0 +1,85 @@
+//! Implementation of helper for Jupiter perpetuals LP tokens (JLP)
+
+use std::fmt::{Debug, Display};
+
+use anchor_client::solana_sdk::clock;
+use anyhow::Result;
+use orbit_link::async_client::AsyncClient;
+use scope::{
+    anchor_lang::prelude::Pubkey,
+    oracles::{jupiter_lp, OracleType},
+    DatedPrice,
+};
+
+use super::{OracleHelper, TokenEntry};
+use crate::config::TokenConfig;
+
+#[derive(Debug)]
+pub struct JupiterLpOracle {
+    label: String,
+    oracle_type: OracleType,
+    /// Pubkey of the Jupiter perpetuals pool
+    mapping: Pubkey,
+
+    /// The LP token mint of the pool, the only extra account
+    lp_mint: Pubkey,
+
+    /// Configured max age
+    max_age: clock::Slot,
+}
+
+impl JupiterLpOracle {
+    pub fn new(conf: &TokenConfig, default_max_age: clock::Slot) -> Self {
+        let mapping = conf.oracle_mapping;
+        Self {
+            label: conf.label.clone(),
+            oracle_type: conf.oracle_type,
+            mapping,
+            lp_mint: jupiter_lp::lp_mint_address(&mapping),
+            max_age: conf.max_age.map(|nz| nz.into()).unwrap_or(default_max_age),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OracleHelper for JupiterLpOracle {
+    fn get_type(&self) -> OracleType {
+        self.oracle_type
+    }
+
+    fn get_number_of_extra_accounts(&self) -> usize {
+        1
+    }
+
+    fn get_mapping_account(&self) -> &Pubkey {
+        &self.mapping
+    }
+
+    async fn get_extra_accounts(&self, _rpc: Option<&dyn AsyncClient>) -> Result<Vec<Pubkey>> {
+        Ok(vec![self.lp_mint])
+    }
+
+    fn get_max_age(&self) -> clock::Slot {
+        self.max_age
+    }
+
+    fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn need_refresh(
+        &self,
+        _scope_price: &DatedPrice,
+        _rpc: &dyn AsyncClient,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Display for JupiterLpOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl TokenEntry for JupiterLpOracle {}
@@ -0,0 +1,100 @@
+//! Implementation of helper for Meteora DLMM pools
+
+use std::fmt::{Debug, Display};
+
+use anchor_client::solana_sdk::clock;
+use anyhow::{anyhow, Context, Result};
+use orbit_link::async_client::AsyncClient;
+use scope::{
+    anchor_lang::prelude::Pubkey,
+    oracles::{meteora_dlmm::LbPair, OracleType},
+    DatedPrice,
+};
+
+use super::{OracleHelper, TokenEntry};
+use crate::config::TokenConfig;
+
+const NB_EXTRA_ACCOUNT: usize = 2;
+
+#[derive(Debug)]
+pub struct MeteoraDlmmOracle {
+    label: String,
+    oracle_type: OracleType,
+    /// Pubkey of the Meteora DLMM pool (`LbPair` account)
+    mapping: Pubkey,
+
+    /// Extra accounts are:
+    /// 0. The mint of the token X of the pool.
+    /// 1. The mint of the token Y of the pool.
+    extra_accounts: [Pubkey; NB_EXTRA_ACCOUNT],
+
+    /// Configured max age
+    max_age: clock::Slot,
+}
+
+impl MeteoraDlmmOracle {
+    pub async fn new(
+        conf: &TokenConfig,
+        default_max_age: clock::Slot,
+        rpc: &dyn AsyncClient,
+    ) -> Result<Self> {
+        let mapping = conf.oracle_mapping;
+        let lb_pair_account_raw = rpc
+            .get_account(&mapping)
+            .await
+            .context("Retrieving Meteora DLMM pool account")?;
+        let lb_pair = LbPair::from_account_data(&lb_pair_account_raw.data)
+            .map_err(|e| anyhow!("Invalid Meteora DLMM pool account {mapping}: {e:?}"))?;
+
+        Ok(Self {
+            label: conf.label.clone(),
+            oracle_type: conf.oracle_type,
+            mapping,
+            extra_accounts: [lb_pair.token_x_mint, lb_pair.token_y_mint],
+            max_age: conf.max_age.map(|nz| nz.into()).unwrap_or(default_max_age),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl OracleHelper for MeteoraDlmmOracle {
+    fn get_type(&self) -> OracleType {
+        self.oracle_type
+    }
+
+    fn get_number_of_extra_accounts(&self) -> usize {
+        NB_EXTRA_ACCOUNT
+    }
+
+    fn get_mapping_account(&self) -> &Pubkey {
+        &self.mapping
+    }
+
+    async fn get_extra_accounts(&self, _rpc: Option<&dyn AsyncClient>) -> Result<Vec<Pubkey>> {
+        Ok(self.extra_accounts.to_vec())
+    }
+
+    fn get_max_age(&self) -> clock::Slot {
+        self.max_age
+    }
+
+    fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn need_refresh(
+        &self,
+        _scope_price: &DatedPrice,
+        _rpc: &dyn AsyncClient,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Display for MeteoraDlmmOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl TokenEntry for MeteoraDlmmOracle {}
@@ -13,11 +13,27 @@ use anyhow::Result;
 use orbit_link::async_client::AsyncClient;
 use scope::{anchor_lang::prelude::Pubkey, oracles::OracleType, DatedPrice};
 
+pub mod constant_product_lp;
+pub mod jupiter_lp;
 #[cfg(feature = "yvaults")]
 pub mod ktokens;
+pub mod meteora_dlmm;
+pub mod most_recent_of;
+pub mod saber_stable_swap;
+pub mod share_price;
 pub mod single_account_oracle;
+pub mod spl_stake;
+pub mod whirlpool;
 
+pub use constant_product_lp::ConstantProductLpOracle;
+pub use jupiter_lp::JupiterLpOracle;
+pub use meteora_dlmm::MeteoraDlmmOracle;
+pub use most_recent_of::MostRecentOfOracle;
+pub use saber_stable_swap::SaberStableSwapOracle;
+pub use share_price::SharePriceOracle;
 pub use single_account_oracle::SingleAccountOracle;
+pub use spl_stake::SplStakeOracle;
+pub use whirlpool::WhirlpoolOracle;
 
 use crate::config::TokenConfig;
 
@@ -76,9 +92,45 @@ pub async fn entry_from_config(
         | OracleType::SwitchboardV1
         | OracleType::SwitchboardV2
         | OracleType::CToken
-        | OracleType::SplStake
         | OracleType::MsolStake
-        | OracleType::PythEMA => Box::new(SingleAccountOracle::new(token_conf, default_max_age)),
+        | OracleType::StsolStake
+        | OracleType::FixedPrice
+        | OracleType::SwitchboardOnDemand
+        | OracleType::Chainlink
+        | OracleType::ScopeChainProduct
+        | OracleType::ScopeChainQuotient
+        | OracleType::ScopeChainInverse
+        | OracleType::ScopeMedian
+        | OracleType::CappedFloored
+        | OracleType::ScopeTwap
+        | OracleType::Discounted
+        | OracleType::Alias
+        | OracleType::PythEMA
+        | OracleType::PythConservativeLower
+        | OracleType::PythConservativeUpper
+        | OracleType::IndexWeighted
+        | OracleType::PythLazer
+        | OracleType::RedStone => Box::new(SingleAccountOracle::new(token_conf, default_max_age)),
+        OracleType::MeteoraDlmmXtoY | OracleType::MeteoraDlmmYtoX => {
+            Box::new(MeteoraDlmmOracle::new(token_conf, default_max_age, rpc).await?)
+        }
+        OracleType::OrcaWhirlpoolAtoB | OracleType::OrcaWhirlpoolBtoA => {
+            Box::new(WhirlpoolOracle::new(token_conf, default_max_age, rpc).await?)
+        }
+        OracleType::ConstantProductLp => {
+            Box::new(ConstantProductLpOracle::new(token_conf, default_max_age, rpc).await?)
+        }
+        OracleType::SaberStableSwapLp => {
+            Box::new(SaberStableSwapOracle::new(token_conf, default_max_age, rpc).await?)
+        }
+        OracleType::JupiterLp => Box::new(JupiterLpOracle::new(token_conf, default_max_age)),
+        OracleType::SharePrice => {
+            Box::new(SharePriceOracle::new(token_conf, default_max_age, rpc).await?)
+        }
+        OracleType::MostRecentOf => Box::new(MostRecentOfOracle::new(token_conf, default_max_age)?),
+        OracleType::SplStake | OracleType::SplStakeLenient => {
+            Box::new(SplStakeOracle::new(token_conf, default_max_age, rpc).await?)
+        }
         #[cfg(feature = "yvaults")]
         OracleType::KToken => {
             Box::new(ktokens::KTokenOracle::new(token_conf, default_max_age, rpc).await?)
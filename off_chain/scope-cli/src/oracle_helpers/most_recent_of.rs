@@ -0,0 +1,97 @@
+//! Implementation of helper for the most recent price of two sources
+
+use std::fmt::{Debug, Display};
+
+use anchor_client::solana_sdk::clock;
+use anyhow::{anyhow, Result};
+use orbit_link::async_client::AsyncClient;
+use scope::{
+    anchor_lang::prelude::Pubkey,
+    oracles::{most_recent_of, OracleType},
+    DatedPrice,
+};
+
+use super::{OracleHelper, TokenEntry};
+use crate::config::TokenConfig;
+
+#[derive(Debug)]
+pub struct MostRecentOfOracle {
+    label: String,
+    oracle_type: OracleType,
+    /// Placeholder derived from the sources
+    mapping: Pubkey,
+
+    /// Sources in the order of the extra accounts
+    sources: [Pubkey; 2],
+
+    /// Configured max age
+    max_age: clock::Slot,
+}
+
+impl MostRecentOfOracle {
+    pub fn new(conf: &TokenConfig, default_max_age: clock::Slot) -> Result<Self> {
+        let mapping = conf.oracle_mapping;
+        let sources: [Pubkey; 2] = conf
+            .sources
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Expected 2 sources for the most recent price {mapping}"))?;
+        let expected_mapping = most_recent_of::mapping_pubkey(&sources[0], &sources[1]);
+        if mapping != expected_mapping {
+            return Err(anyhow!(
+                "Mapping {mapping} is not derived from the sources, expected {expected_mapping}"
+            ));
+        }
+
+        Ok(Self {
+            label: conf.label.clone(),
+            oracle_type: conf.oracle_type,
+            mapping,
+            sources,
+            max_age: conf.max_age.map(|nz| nz.into()).unwrap_or(default_max_age),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl OracleHelper for MostRecentOfOracle {
+    fn get_type(&self) -> OracleType {
+        self.oracle_type
+    }
+
+    fn get_number_of_extra_accounts(&self) -> usize {
+        2
+    }
+
+    fn get_mapping_account(&self) -> &Pubkey {
+        &self.mapping
+    }
+
+    async fn get_extra_accounts(&self, _rpc: Option<&dyn AsyncClient>) -> Result<Vec<Pubkey>> {
+        Ok(self.sources.to_vec())
+    }
+
+    fn get_max_age(&self) -> clock::Slot {
+        self.max_age
+    }
+
+    fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn need_refresh(
+        &self,
+        _scope_price: &DatedPrice,
+        _rpc: &dyn AsyncClient,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Display for MostRecentOfOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl TokenEntry for MostRecentOfOracle {}
@@ -0,0 +1,103 @@
+//! Implementation of helper for share prices of tokens backed by a vault
+
+use std::fmt::{Debug, Display};
+
+use anchor_client::solana_sdk::clock;
+use anyhow::{anyhow, Context, Result};
+use orbit_link::async_client::AsyncClient;
+use scope::{anchor_lang::prelude::Pubkey, oracles::OracleType, DatedPrice};
+
+use super::{OracleHelper, TokenEntry};
+use crate::config::TokenConfig;
+
+#[derive(Debug)]
+pub struct SharePriceOracle {
+    label: String,
+    oracle_type: OracleType,
+    /// Pubkey of the vault token account
+    mapping: Pubkey,
+
+    /// The share mint, first extra account
+    ///
+    /// It is not referenced by the vault so it must be given in the configuration.
+    share_mint: Pubkey,
+    /// The mint of the vault, second extra account
+    vault_mint: Pubkey,
+
+    /// Configured max age
+    max_age: clock::Slot,
+}
+
+impl SharePriceOracle {
+    pub async fn new(
+        conf: &TokenConfig,
+        default_max_age: clock::Slot,
+        rpc: &dyn AsyncClient,
+    ) -> Result<Self> {
+        let mapping = conf.oracle_mapping;
+        let share_mint = conf
+            .share_mint
+            .ok_or_else(|| anyhow!("No share mint configured for the vault {mapping}"))?;
+        let vault_account_raw = rpc
+            .get_account(&mapping)
+            .await
+            .context("Retrieving vault token account")?;
+        let vault_mint = vault_account_raw
+            .data
+            .get(..32)
+            .map(|mint| Pubkey::new_from_array(mint.try_into().unwrap()))
+            .ok_or_else(|| anyhow!("Invalid vault token account {mapping}"))?;
+
+        Ok(Self {
+            label: conf.label.clone(),
+            oracle_type: conf.oracle_type,
+            mapping,
+            share_mint,
+            vault_mint,
+            max_age: conf.max_age.map(|nz| nz.into()).unwrap_or(default_max_age),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl OracleHelper for SharePriceOracle {
+    fn get_type(&self) -> OracleType {
+        self.oracle_type
+    }
+
+    fn get_number_of_extra_accounts(&self) -> usize {
+        2
+    }
+
+    fn get_mapping_account(&self) -> &Pubkey {
+        &self.mapping
+    }
+
+    async fn get_extra_accounts(&self, _rpc: Option<&dyn AsyncClient>) -> Result<Vec<Pubkey>> {
+        Ok(vec![self.share_mint, self.vault_mint])
+    }
+
+    fn get_max_age(&self) -> clock::Slot {
+        self.max_age
+    }
+
+    fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn need_refresh(
+        &self,
+        _scope_price: &DatedPrice,
+        _rpc: &dyn AsyncClient,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Display for SharePriceOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl TokenEntry for SharePriceOracle {}
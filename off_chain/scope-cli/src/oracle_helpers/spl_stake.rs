@@ -0,0 +1,98 @@
+//! Implementation of helper for SPL stake pool tokens
+
+use std::fmt::{Debug, Display};
+
+use anchor_client::solana_sdk::clock;
+use anyhow::{anyhow, Context, Result};
+use orbit_link::async_client::AsyncClient;
+use scope::{
+    anchor_lang::prelude::Pubkey,
+    oracles::{spl_stake::spl_stake_pool::StakePool, OracleType},
+    DatedPrice,
+};
+
+use super::{OracleHelper, TokenEntry};
+use crate::config::TokenConfig;
+
+#[derive(Debug)]
+pub struct SplStakeOracle {
+    label: String,
+    oracle_type: OracleType,
+    /// Pubkey of the stake pool
+    mapping: Pubkey,
+
+    /// The validator list of the pool, the only extra account
+    ///
+    /// It allows the price to be accepted while the pool update is in progress.
+    validator_list: Pubkey,
+
+    /// Configured max age
+    max_age: clock::Slot,
+}
+
+impl SplStakeOracle {
+    pub async fn new(
+        conf: &TokenConfig,
+        default_max_age: clock::Slot,
+        rpc: &dyn AsyncClient,
+    ) -> Result<Self> {
+        let mapping = conf.oracle_mapping;
+        let pool_account_raw = rpc
+            .get_account(&mapping)
+            .await
+            .context("Retrieving SPL stake pool account")?;
+        let stake_pool = StakePool::from_account_data(&pool_account_raw.data)
+            .map_err(|e| anyhow!("Invalid SPL stake pool account {mapping}: {e:?}"))?;
+
+        Ok(Self {
+            label: conf.label.clone(),
+            oracle_type: conf.oracle_type,
+            mapping,
+            validator_list: stake_pool.validator_list,
+            max_age: conf.max_age.map(|nz| nz.into()).unwrap_or(default_max_age),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl OracleHelper for SplStakeOracle {
+    fn get_type(&self) -> OracleType {
+        self.oracle_type
+    }
+
+    fn get_number_of_extra_accounts(&self) -> usize {
+        1
+    }
+
+    fn get_mapping_account(&self) -> &Pubkey {
+        &self.mapping
+    }
+
+    async fn get_extra_accounts(&self, _rpc: Option<&dyn AsyncClient>) -> Result<Vec<Pubkey>> {
+        Ok(vec![self.validator_list])
+    }
+
+    fn get_max_age(&self) -> clock::Slot {
+        self.max_age
+    }
+
+    fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn need_refresh(
+        &self,
+        _scope_price: &DatedPrice,
+        _rpc: &dyn AsyncClient,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Display for SplStakeOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl TokenEntry for SplStakeOracle {}
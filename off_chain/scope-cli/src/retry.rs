@@ -0,0 +1,272 @@
+//! Retry of transaction sends on transient errors
+//!
+//! A transaction failing on a transient error (expired blockhash, node behind, network issue) is
+//! rebuilt with a fresh blockhash and sent again after an exponential backoff. Permanent errors,
+//! such as a program error, are returned immediately.
+//!
+//! Before any new send, the status of the transactions already sent is fetched again: a
+//! transaction that could not be confirmed in time may still have landed and must not be
+//! executed twice.
+
+use std::{num::NonZeroU32, time::Duration};
+
+use anchor_client::solana_sdk::{
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use tracing::warn;
+
+use crate::errors::{Result, ScopeClientError};
+
+/// Result of a transaction that landed
+pub type TransactionResult = std::result::Result<(), TransactionError>;
+
+/// Default max number of sends of a transaction
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Default delay before the first retry
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Default upper bound of the delay between two sends
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Policy of the retries of a transaction send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Max number of sends of a transaction, including the first one
+    pub max_attempts: NonZeroU32,
+    /// Delay before the first retry, doubled at each following retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two sends
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: NonZeroU32::new(DEFAULT_MAX_ATTEMPTS).unwrap(),
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Transaction that can be sent with [`send_with_retry`]
+#[async_trait::async_trait(?Send)]
+pub trait RetryableTx {
+    /// Build the transaction again, with a fresh blockhash
+    async fn rebuild(&self) -> Result<VersionedTransaction>;
+
+    /// Send the transaction and wait for its confirmation, `None` if it is not confirmed
+    async fn send_and_confirm(&self, tx: VersionedTransaction)
+        -> Result<Option<TransactionResult>>;
+
+    /// Fetch the status of an already sent transaction, `None` if it did not land
+    async fn get_status(&self, signature: &Signature) -> Result<Option<TransactionResult>>;
+}
+
+/// Send a transaction, retrying on transient errors according to the policy
+///
+/// Returns the signature of the transaction that landed successfully.
+pub async fn send_with_retry(
+    sender: &impl RetryableTx,
+    tx: VersionedTransaction,
+    policy: &RetryPolicy,
+) -> Result<Signature> {
+    let mut tx = tx;
+    let mut sent: Vec<Signature> = Vec::new();
+    let mut retry = 0;
+    loop {
+        // The first signature of a transaction is the one of its fee payer
+        let signature = tx.signatures[0];
+        sent.push(signature);
+        let err = match sender.send_and_confirm(tx).await {
+            Ok(res) => match ScopeClientError::from_tx_result(signature, res) {
+                Ok(()) => return Ok(signature),
+                Err(err) => err,
+            },
+            Err(err) => err,
+        };
+
+        retry += 1;
+        if !err.is_transient() || retry >= policy.max_attempts.get() {
+            return Err(err);
+        }
+        let backoff = policy.backoff(retry);
+        warn!(%signature, %err, retry, ?backoff, "Transient error while sending transaction, retrying");
+        tokio::time::sleep(backoff).await;
+
+        // Never send again a transaction that landed after the confirmation timeout
+        for signature in &sent {
+            if let Some(res) = sender.get_status(signature).await? {
+                ScopeClientError::from_tx_result(*signature, Some(res))?;
+                return Ok(*signature);
+            }
+        }
+        tx = sender.rebuild().await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        collections::{HashSet, VecDeque},
+    };
+
+    use super::*;
+
+    /// Outcome of a send of the mock
+    enum Outcome {
+        Confirmed,
+        NotConfirmed,
+        /// Not confirmed in time but landed
+        LandedLate,
+        Failed(TransactionError),
+    }
+
+    /// Mock client failing with the given outcomes then confirming the transactions
+    struct MockClient {
+        outcomes: RefCell<VecDeque<Outcome>>,
+        landed: RefCell<HashSet<Signature>>,
+        sends: Cell<usize>,
+    }
+
+    impl MockClient {
+        fn new(outcomes: Vec<Outcome>) -> Self {
+            Self {
+                outcomes: RefCell::new(outcomes.into()),
+                landed: RefCell::new(HashSet::new()),
+                sends: Cell::new(0),
+            }
+        }
+    }
+
+    fn new_tx() -> VersionedTransaction {
+        VersionedTransaction {
+            signatures: vec![Signature::new_unique()],
+            ..Default::default()
+        }
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl RetryableTx for MockClient {
+        async fn rebuild(&self) -> Result<VersionedTransaction> {
+            Ok(new_tx())
+        }
+
+        async fn send_and_confirm(
+            &self,
+            tx: VersionedTransaction,
+        ) -> Result<Option<TransactionResult>> {
+            self.sends.set(self.sends.get() + 1);
+            let signature = tx.signatures[0];
+            match self
+                .outcomes
+                .borrow_mut()
+                .pop_front()
+                .unwrap_or(Outcome::Confirmed)
+            {
+                Outcome::Confirmed => {
+                    self.landed.borrow_mut().insert(signature);
+                    Ok(Some(Ok(())))
+                }
+                Outcome::NotConfirmed => Ok(None),
+                Outcome::LandedLate => {
+                    self.landed.borrow_mut().insert(signature);
+                    Ok(None)
+                }
+                Outcome::Failed(err) => Ok(Some(Err(err))),
+            }
+        }
+
+        async fn get_status(&self, signature: &Signature) -> Result<Option<TransactionResult>> {
+            Ok(self.landed.borrow().contains(signature).then_some(Ok(())))
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: NonZeroU32::new(max_attempts).unwrap(),
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_until_success() {
+        let client = MockClient::new(vec![
+            Outcome::NotConfirmed,
+            Outcome::Failed(TransactionError::BlockhashNotFound),
+        ]);
+        let first_tx = new_tx();
+        let first_signature = first_tx.signatures[0];
+        let signature = send_with_retry(&client, first_tx, &policy(3))
+            .await
+            .unwrap();
+        assert_eq!(client.sends.get(), 3);
+        // The successful transaction is a rebuilt one
+        assert_ne!(signature, first_signature);
+        assert!(client.landed.borrow().contains(&signature));
+    }
+
+    #[tokio::test]
+    async fn retry_exhausted() {
+        let client = MockClient::new(vec![
+            Outcome::NotConfirmed,
+            Outcome::NotConfirmed,
+            Outcome::NotConfirmed,
+        ]);
+        let res = send_with_retry(&client, new_tx(), &policy(2)).await;
+        assert!(matches!(
+            res,
+            Err(ScopeClientError::TransactionNotConfirmed(_))
+        ));
+        assert_eq!(client.sends.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn permanent_error_not_retried() {
+        let client = MockClient::new(vec![Outcome::Failed(TransactionError::AccountNotFound)]);
+        let res = send_with_retry(&client, new_tx(), &policy(3)).await;
+        assert!(matches!(
+            res,
+            Err(ScopeClientError::TransactionFailed {
+                err: TransactionError::AccountNotFound,
+                ..
+            })
+        ));
+        assert_eq!(client.sends.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn landed_tx_not_sent_again() {
+        let client = MockClient::new(vec![Outcome::LandedLate]);
+        let tx = new_tx();
+        let first_signature = tx.signatures[0];
+        let signature = send_with_retry(&client, tx, &policy(3)).await.unwrap();
+        assert_eq!(signature, first_signature);
+        assert_eq!(client.sends.get(), 1);
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: NonZeroU32::new(10).unwrap(),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        let backoffs: Vec<u64> = (1..=5)
+            .map(|retry| policy.backoff(retry).as_millis() as u64)
+            .collect();
+        assert_eq!(backoffs, vec![100, 200, 400, 500, 500]);
+    }
+}
@@ -1,50 +1,397 @@
 use std::mem::size_of;
-use std::{collections::HashSet, num::NonZeroU64};
+use std::{
+    collections::HashSet,
+    num::{NonZeroU64, NonZeroUsize},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anchor_client::{
-    anchor_lang::ToAccountMetas,
+    anchor_lang::{prelude::AccountLoader, AccountDeserialize, ToAccountMetas},
+    solana_client::rpc_response::RpcSimulateTransactionResult,
     solana_sdk::{
+        account::Account,
+        account_info::{AccountInfo, IntoAccountInfo},
         clock::{self, Clock},
+        entrypoint::MAX_PERMITTED_DATA_INCREASE,
         instruction::AccountMeta,
         pubkey::Pubkey,
         signature::{Keypair, Signature},
         signer::Signer,
         system_program,
         sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
+        transaction::VersionedTransaction,
     },
 };
-use anyhow::{anyhow, bail, Context, Result};
-use futures::future::join_all;
+use anyhow::anyhow;
+use futures::{future::join_all, stream, StreamExt};
 use nohash_hasher::IntMap;
 use orbit_link::{async_client::AsyncClient, OrbitLink};
 use scope::{
-    accounts, instruction, Configuration, OracleMappings, OraclePrices, TokenMetadatas,
-    UpdateTokenMetadataMode,
+    accounts, instruction,
+    oracles::{
+        check_source_owner, get_price, pyth::ConfidencePolicy, validate_oracle_account, OracleType,
+    },
+    utils::math::{apply_hard_cap, deviation_bps},
+    Configuration, DatedPrice, MappingEntry, OracleMappings, OraclePrices, OracleTwaps, Price,
+    TokenMetadatas, UpdateMappingConfigMode, UpdateTokenMetadataMode, MAX_ENTRIES,
 };
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    config::{ScopeConfig, TokenConfig, TokenList},
+    config::{PriceBounds, ScopeConfig, TokenConfig, TokenList},
+    errors::{Result, ScopeClientError},
     oracle_helpers::{entry_from_config, TokenEntry},
-    utils::{get_clock, price_to_f64},
+    retry::{send_with_retry, RetryPolicy, RetryableTx, TransactionResult},
+    utils::{get_clock, normalize_price, price_to_f64},
 };
 
-/// Max number of refresh per tx
-const MAX_REFRESH_CHUNK_SIZE: usize = 24;
+/// Max number of accounts of the tokens refreshed per tx, default refresh chunk size (see
+/// [`ScopeClient::set_refresh_chunk_size`])
+pub const MAX_REFRESH_CHUNK_SIZE: usize = 24;
+/// Max number of token mappings updated per tx
+const MAX_MAPPING_BATCH_SIZE: usize = 20;
+/// Default max number of refresh transactions sent concurrently
+pub const DEFAULT_MAX_IN_FLIGHT_REFRESHES: usize = 4;
+/// Max number of accounts fetched per rpc request
+const MAX_ACCOUNTS_PER_FETCH: usize = 100;
 /// Token gap to max age that still trigger refresh (in slots)
 const REMAINING_AGE_TO_REFRESH: i64 = 10;
+/// Extra compute units needed per token to update its TWAP
+const TWAP_UPDATE_CU_BUDGET: u32 = 10_000;
 
 type TokenEntryList = IntMap<u16, Box<dyn TokenEntry>>;
 
+/// Health of the stored prices of the given tokens, see [`ScopeClient::feed_health`]
+fn tokens_health(
+    oracle_prices: &OraclePrices,
+    tokens: &TokenEntryList,
+    current_slot: u64,
+) -> Vec<TokenHealth> {
+    let mut health: Vec<TokenHealth> = tokens
+        .iter()
+        .map(|(&token, entry)| {
+            let price = &oracle_prices.prices[usize::from(token)];
+            // Prices updated "in the future" have an age of 0
+            let age_slots = current_slot.saturating_sub(price.last_updated_slot);
+            TokenHealth {
+                token,
+                pair: entry.get_label().to_string(),
+                mapped: *entry.get_mapping_account() != Pubkey::default(),
+                age_slots,
+                stale: age_slots > entry.get_max_age(),
+            }
+        })
+        .collect();
+    health.sort_unstable_by_key(|token_health| token_health.token);
+    health
+}
+
+/// Table of the populated prices of the feed at `clock`, see [`ScopeClient::prices_table`]
+///
+/// Pairs are the labels of the local mapping, `-` for tokens not in it. With `stale_after_s`
+/// only the prices older than this number of seconds are listed.
+fn format_prices_table(
+    oracle_prices: &OraclePrices,
+    tokens: &TokenEntryList,
+    clock: &Clock,
+    stale_after_s: Option<u64>,
+) -> String {
+    let now = u64::try_from(clock.unix_timestamp).unwrap_or_default();
+    let mut table = format!(
+        "{:>5} {:<20} {:>28} {:>3} {:>12} {:>12} {:>10}\n",
+        "token", "pair", "value", "exp", "slot", "timestamp", "age_s"
+    );
+    for (token, dated_price) in oracle_prices.prices.iter().enumerate() {
+        if dated_price.price.value == 0 {
+            continue;
+        }
+        // Prices updated "in the future" have an age of 0
+        let age_s = now.saturating_sub(dated_price.unix_timestamp);
+        if matches!(stale_after_s, Some(stale_after_s) if age_s <= stale_after_s) {
+            continue;
+        }
+        let pair = u16::try_from(token)
+            .ok()
+            .and_then(|token| tokens.get(&token))
+            .map_or("-", |entry| entry.get_label());
+        let value = price_to_f64(&dated_price.price);
+        let exp = dated_price.price.exp;
+        table.push_str(&format!(
+            "{token:>5} {pair:<20} {value:>28.prec$} {exp:>3} {:>12} {:>12} {age_s:>10}\n",
+            dated_price.last_updated_slot,
+            dated_price.unix_timestamp,
+            prec = exp as usize,
+        ));
+    }
+    table
+}
+
+/// Compute units consumed by the instructions of `program_id` in a simulated transaction
+///
+/// They are parsed from the `Program <id> consumed <n> of <m> compute units` logs, the compute
+/// units of the other programs of the transaction (e.g. the compute budget) are not counted.
+fn program_compute_units(
+    simulation: &RpcSimulateTransactionResult,
+    program_id: &Pubkey,
+) -> Result<u64> {
+    if let Some(err) = &simulation.err {
+        return Err(ScopeClientError::SimulationFailed(err.clone()));
+    }
+    let prefix = format!("Program {program_id} consumed ");
+    let units: Vec<u64> = simulation
+        .logs
+        .iter()
+        .flatten()
+        .filter_map(|log| log.strip_prefix(&prefix))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse().ok())
+        .collect();
+    if units.is_empty() {
+        return Err(
+            anyhow!("No compute units consumed by {program_id} in the simulation logs").into(),
+        );
+    }
+    Ok(units.iter().sum())
+}
+
+/// Group the given tokens in chunks of max `chunk_size` accounts
+///
+/// The number of accounts varies from one token to another so chunks are
+/// built according to the number of extra accounts of each entry.
+fn split_in_chunks(
+    entries: &TokenEntryList,
+    tokens: &[u16],
+    chunk_size: usize,
+) -> Result<Vec<Vec<u16>>> {
+    let mut acc_account_num = 0_usize;
+    let mut acc_token_id: Vec<u16> = Vec::with_capacity(chunk_size);
+    let mut chunks = Vec::new();
+
+    for id in tokens {
+        let entry = entries.get(id).ok_or(ScopeClientError::UnknownToken(*id))?;
+        // if current entry would overflow the token count > close the chunk and reset
+        if entry.get_number_of_extra_accounts() + 1 + acc_account_num > chunk_size {
+            chunks.push(std::mem::take(&mut acc_token_id));
+            acc_account_num = 0;
+        }
+        // accumulate
+        acc_account_num += entry.get_number_of_extra_accounts() + 1;
+        acc_token_id.push(*id);
+    }
+
+    // last tokens chunk
+    if !acc_token_id.is_empty() {
+        chunks.push(acc_token_id);
+    }
+
+    Ok(chunks)
+}
+
+/// Split entries sorted by token in batches of consecutive tokens of at most `max_size` entries
+fn consecutive_batches<T>(entries: &[(u16, T)], max_size: usize) -> Vec<&[(u16, T)]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    for end in 1..=entries.len() {
+        if end == entries.len()
+            || end - start == max_size
+            || entries[end].0 != entries[end - 1].0 + 1
+        {
+            batches.push(&entries[start..end]);
+            start = end;
+        }
+    }
+    batches
+}
+
+/// On-chain bounds of the accepted prices of a token, zeroed (disabled) for `None`
+fn price_bounds_to_onchain(bounds: Option<PriceBounds>) -> [Price; 2] {
+    bounds.map_or_else(Default::default, |PriceBounds { min, max, exp }| {
+        [Price { value: min, exp }, Price { value: max, exp }]
+    })
+}
+
+/// Bounds of the accepted prices of a token from the on-chain ones, `None` if disabled
+fn price_bounds_from_onchain(bounds: &[Price; 2]) -> Option<PriceBounds> {
+    let [min, max] = bounds;
+    let max_value = if max.value == u64::MAX { 0 } else { max.value };
+    (min.value != 0 || max_value != 0).then_some(PriceBounds {
+        min: min.value,
+        max: max.value,
+        exp: max.exp,
+    })
+}
+
+/// Whether the mapping of the oracle type is an account, and not a source encoded in the pubkey
+fn has_mapping_account(price_type: OracleType) -> bool {
+    !price_type.is_derived()
+        && !matches!(
+            price_type,
+            OracleType::FixedPrice | OracleType::MostRecentOf
+        )
+}
+
+/// Check a mapping account as the `update_mapping` instruction would, see
+/// [`ScopeClient::verify_mapping_accounts`]
+fn verify_mapping_account(
+    price_type: OracleType,
+    key: Pubkey,
+    account: Option<Account>,
+) -> VerifyResult {
+    let mut account = match account {
+        Some(account) => account,
+        None => {
+            warn!(%key, ?price_type, "Mapping account not found");
+            return VerifyResult::Missing;
+        }
+    };
+    let account_info = (&key, &mut account).into_account_info();
+    match check_source_owner(price_type, &account_info)
+        .and_then(|()| validate_oracle_account(price_type, &account_info))
+    {
+        Ok(()) => VerifyResult::Ok,
+        Err(err) => {
+            warn!(%key, ?price_type, "Mapping account is not a valid source: {err}");
+            VerifyResult::WrongType
+        }
+    }
+}
+
+/// Token of the local mapping whose pair label is `pair`, ignoring case, see
+/// [`ScopeClient::get_price_by_pair`]
+fn find_pair(tokens: &TokenEntryList, pair: &str) -> Result<u16> {
+    let mut matches: Vec<u16> = tokens
+        .iter()
+        .filter(|(_, entry)| entry.get_label().eq_ignore_ascii_case(pair))
+        .map(|(&token, _)| token)
+        .collect();
+    matches.sort_unstable();
+    match matches[..] {
+        [] => Err(ScopeClientError::UnknownPair(pair.to_string())),
+        [token] if *tokens[&token].get_mapping_account() == Pubkey::default() => {
+            Err(ScopeClientError::UnmappedPair {
+                pair: pair.to_string(),
+                token,
+            })
+        }
+        [token] => Ok(token),
+        _ => Err(ScopeClientError::AmbiguousPair {
+            pair: pair.to_string(),
+            tokens: matches,
+        }),
+    }
+}
+
+/// Outcome of a refresh transaction
+#[derive(Debug)]
+pub enum RefreshOutcome {
+    /// The transaction was sent and confirmed with the given signature
+    Sent(Signature),
+    /// The transaction was only simulated (see [`ScopeClient::set_dry_run`])
+    Simulated(RpcSimulateTransactionResult),
+}
+
+/// Result of the refresh of one chunk of tokens, see [`ScopeClient::refresh_all_prices`]
+#[derive(Debug)]
+pub struct ChunkRefresh {
+    /// Tokens refreshed by the chunk transaction
+    pub tokens: Vec<u16>,
+    pub result: Result<RefreshOutcome>,
+    /// Time spent sending the transaction until its confirmation, retries included
+    pub elapsed: Duration,
+}
+
+/// Compute units consumed by the refresh of a list of tokens, see
+/// [`ScopeClient::simulate_refresh_compute`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshCompute {
+    /// Tokens refreshed by the simulated transaction
+    pub tokens: Vec<u16>,
+    /// Compute units consumed by the refresh instruction
+    pub compute_units: u64,
+}
+
+/// Token whose local mapping account differs from the on-chain one, see
+/// [`ScopeClient::diff_oracle_mapping`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingDiff {
+    pub token: u16,
+    /// Local mapping account, `None` if the token is not in the local mapping
+    pub local: Option<Pubkey>,
+    /// On-chain mapping account, the default pubkey if the token is not mapped
+    pub remote: Pubkey,
+}
+
+/// On-chain mapping account of a token of the local mapping, see
+/// [`ScopeClient::get_named_mapping`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedMapping {
+    pub token: u16,
+    /// Label of the token in the local mapping
+    pub pair: String,
+    /// On-chain mapping account, `None` if the token is not mapped
+    pub oracle: Option<Pubkey>,
+}
+
+/// Check of the mapping account of a token, see [`ScopeClient::verify_mapping_accounts`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyResult {
+    /// The account is a valid source for the oracle type of the token
+    Ok,
+    /// The account exists but is not a valid source for the oracle type of the token
+    WrongType,
+    /// The account does not exist
+    Missing,
+}
+
+/// Stored price diverging from the price recomputed from its sources, see
+/// [`ScopeClient::check_prices`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceDivergence {
+    pub token: u16,
+    pub stored: Price,
+    pub recomputed: Price,
+    /// Relative difference of the stored price from the recomputed one, `None` if it cannot be
+    /// computed (e.g. the recomputed price is zero)
+    pub deviation_bps: Option<u128>,
+}
+
+/// Health of the price of a token of the local mapping, see [`ScopeClient::feed_health`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenHealth {
+    pub token: u16,
+    /// Label of the token in the local mapping
+    pub pair: String,
+    /// Whether the token has a mapping account in the local mapping
+    pub mapped: bool,
+    /// Number of slots since the last refresh of the stored price
+    pub age_slots: u64,
+    /// Whether the stored price is older than the max age of the token
+    pub stale: bool,
+}
+
 pub struct ScopeClient<T: AsyncClient, S: Signer> {
-    client: OrbitLink<T, S>,
+    /// Connection shared by the clients of the feeds opened from this one
+    client: Arc<OrbitLink<T, S>>,
     program_id: Pubkey,
     feed_name: String,
     configuration_acc: Pubkey,
     oracle_prices_acc: Pubkey,
     oracle_mappings_acc: Pubkey,
     tokens_metadata_acc: Pubkey,
+    oracle_twaps_acc: Option<Pubkey>,
     tokens: TokenEntryList,
+    /// Max age in slots of the source price enforced on-chain, of the tokens configuring one
+    price_max_ages: IntMap<u16, clock::Slot>,
+    /// Range of the accepted prices enforced on-chain, of the tokens configuring one
+    price_bounds: IntMap<u16, PriceBounds>,
+    /// Simulate the refresh transactions instead of sending them
+    dry_run: bool,
+    /// Max number of refresh transactions sent concurrently
+    max_in_flight_refreshes: NonZeroUsize,
+    /// Max number of accounts of the tokens refreshed per transaction
+    refresh_chunk_size: usize,
+    /// Retries of the refresh transactions failing on transient errors
+    retry_policy: RetryPolicy,
 }
 
 impl<T, S> ScopeClient<T, S>
@@ -57,14 +404,32 @@ where
         client: OrbitLink<T, S>,
         program_id: Pubkey,
         price_feed: &str,
+    ) -> Result<Self> {
+        Self::new_shared(Arc::new(client), program_id, price_feed).await
+    }
+
+    async fn new_shared(
+        client: Arc<OrbitLink<T, S>>,
+        program_id: Pubkey,
+        price_feed: &str,
     ) -> Result<Self> {
         // Retrieve accounts in configuration PDA
         let (configuration_acc, _) =
             Pubkey::find_program_address(&[b"conf", price_feed.as_bytes()], &program_id);
 
-        let Configuration { oracle_mappings, oracle_prices, tokens_metadata, .. } = client
-            .get_anchor_account::<Configuration>(&configuration_acc).await
-            .context("Error while retrieving program configuration account, the program might be uninitialized")?;
+        let Configuration {
+            oracle_mappings,
+            oracle_prices,
+            tokens_metadata,
+            oracle_twaps,
+            ..
+        } = client
+            .get_anchor_account::<Configuration>(&configuration_acc)
+            .await
+            .map_err(|err| {
+                debug!(?err, "Error while retrieving program configuration account");
+                ScopeClientError::NotInitialized
+            })?;
 
         let mut client = Self {
             client,
@@ -74,7 +439,14 @@ where
             oracle_prices_acc: oracle_prices,
             oracle_mappings_acc: oracle_mappings,
             tokens_metadata_acc: tokens_metadata,
+            oracle_twaps_acc: (oracle_twaps != Pubkey::default()).then_some(oracle_twaps),
             tokens: IntMap::default(),
+            price_max_ages: IntMap::default(),
+            price_bounds: IntMap::default(),
+            dry_run: false,
+            max_in_flight_refreshes: NonZeroUsize::new(DEFAULT_MAX_IN_FLIGHT_REFRESHES).unwrap(),
+            refresh_chunk_size: MAX_REFRESH_CHUNK_SIZE,
+            retry_policy: RetryPolicy::default(),
         };
 
         // if the token_metadatas is not initialized, initialize it here
@@ -91,6 +463,14 @@ where
         client: OrbitLink<T, S>,
         program_id: &Pubkey,
         price_feed: &str,
+    ) -> Result<Self> {
+        Self::new_init_program_shared(Arc::new(client), program_id, price_feed).await
+    }
+
+    async fn new_init_program_shared(
+        client: Arc<OrbitLink<T, S>>,
+        program_id: &Pubkey,
+        price_feed: &str,
     ) -> Result<Self> {
         // Generate accounts keypairs.
         let oracle_prices_acc = Keypair::new();
@@ -122,10 +502,49 @@ where
             oracle_prices_acc: oracle_prices_acc.pubkey(),
             oracle_mappings_acc: oracle_mappings_acc.pubkey(),
             tokens_metadata_acc: token_metadatas_acc.pubkey(),
+            oracle_twaps_acc: None,
             tokens: IntMap::default(),
+            price_max_ages: IntMap::default(),
+            price_bounds: IntMap::default(),
+            dry_run: false,
+            max_in_flight_refreshes: NonZeroUsize::new(DEFAULT_MAX_IN_FLIGHT_REFRESHES).unwrap(),
+            refresh_chunk_size: MAX_REFRESH_CHUNK_SIZE,
+            retry_policy: RetryPolicy::default(),
         })
     }
 
+    /// Open another feed of the program, sharing the connection and payer of this client
+    ///
+    /// The returned client has its own feed accounts and an empty local mapping, and starts with
+    /// the refresh settings (dry run, in-flight refreshes, chunk size and retries) of this client.
+    /// Each client only ever refreshes and updates the accounts of its own feed.
+    pub async fn open_feed(&self, price_feed: &str) -> Result<Self> {
+        let mut feed = Self::new_shared(self.client.clone(), self.program_id, price_feed).await?;
+        feed.copy_settings(self);
+        Ok(feed)
+    }
+
+    /// Initialize a new feed of the program and open it, see [`ScopeClient::open_feed`]
+    pub async fn init_feed(&self, price_feed: &str) -> Result<Self> {
+        let mut feed =
+            Self::new_init_program_shared(self.client.clone(), &self.program_id, price_feed)
+                .await?;
+        feed.copy_settings(self);
+        Ok(feed)
+    }
+
+    fn copy_settings(&mut self, other: &Self) {
+        self.dry_run = other.dry_run;
+        self.max_in_flight_refreshes = other.max_in_flight_refreshes;
+        self.refresh_chunk_size = other.refresh_chunk_size;
+        self.retry_policy = other.retry_policy;
+    }
+
+    /// Name of the feed of this client
+    pub fn get_feed_name(&self) -> &str {
+        &self.feed_name
+    }
+
     pub async fn init_token_metadatas_if_needed(&mut self, price_feed: &str) -> Result<()> {
         if self.tokens_metadata_acc.eq(&Pubkey::default()) {
             // Generate accounts keypairs.
@@ -146,6 +565,63 @@ where
         Ok(())
     }
 
+    /// Create and initialize the TWAP account of the feed if it does not exist yet.
+    ///
+    /// Once initialized, all price refreshes also update the TWAP of the refreshed tokens.
+    pub async fn init_oracle_twaps_if_needed(&mut self) -> Result<()> {
+        if self.oracle_twaps_acc.is_none() {
+            let oracle_twaps_acc = Keypair::new();
+
+            Self::ix_initialize_oracle_twaps(
+                &self.client,
+                &self.program_id,
+                &self.configuration_acc,
+                &self.oracle_prices_acc,
+                &oracle_twaps_acc,
+                &self.feed_name,
+            )
+            .await?;
+
+            self.oracle_twaps_acc = Some(oracle_twaps_acc.pubkey());
+        }
+
+        Ok(())
+    }
+
+    /// Only simulate the refresh transactions instead of sending them
+    ///
+    /// Simulation logs and consumed compute units are logged, no price is updated.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Set the max number of refresh transactions sent concurrently
+    ///
+    /// Defaults to [`DEFAULT_MAX_IN_FLIGHT_REFRESHES`].
+    pub fn set_max_in_flight_refreshes(&mut self, max_in_flight: NonZeroUsize) {
+        self.max_in_flight_refreshes = max_in_flight;
+    }
+
+    /// Set the max number of accounts of the tokens refreshed per transaction
+    ///
+    /// Heavier tokens (e.g. stake pools) may need smaller chunks to fit in the compute limits,
+    /// see [`ScopeClient::simulate_refresh_compute`]. The size must be between 1 and
+    /// [`MAX_REFRESH_CHUNK_SIZE`], the limit of accounts of the refresh instruction.
+    pub fn set_refresh_chunk_size(&mut self, chunk_size: usize) -> Result<()> {
+        if !(1..=MAX_REFRESH_CHUNK_SIZE).contains(&chunk_size) {
+            return Err(ScopeClientError::InvalidChunkSize(chunk_size));
+        }
+        self.refresh_chunk_size = chunk_size;
+        Ok(())
+    }
+
+    /// Set the retry policy of the refresh transactions failing on transient errors
+    ///
+    /// Defaults to [`RetryPolicy::default`].
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
     /// Set the locally known oracle mapping according to the provided configuration list.
     pub async fn set_local_mapping(&mut self, token_list: &ScopeConfig) -> Result<()> {
         let default_max_age = token_list.default_max_age;
@@ -156,22 +632,35 @@ where
             join_all(token_list.tokens.iter().map(|(id, token_conf)| async {
                 let token_entry: Box<dyn TokenEntry> =
                     entry_from_config(token_conf, default_max_age, rpc).await?;
-                Ok((*id, token_entry))
+                Result::<(u16, Box<dyn TokenEntry>)>::Ok((*id, token_entry))
             }))
             .await
             .into_iter()
             .collect();
         self.tokens = tokens_res?;
+        self.price_max_ages = token_list
+            .tokens
+            .iter()
+            .filter_map(|(id, token_conf)| Some((*id, token_conf.price_max_age?.get())))
+            .collect();
+        self.price_bounds = token_list
+            .tokens
+            .iter()
+            .filter_map(|(id, token_conf)| Some((*id, token_conf.price_bounds?)))
+            .collect();
         Ok(())
     }
 
     /// Update the remote oracle mapping from the local
+    ///
+    /// The mappings of consecutive tokens are updated in batches, see [`MAX_MAPPING_BATCH_SIZE`].
     pub async fn upload_oracle_mapping(&self) -> Result<()> {
         let program_mapping = self.get_program_mapping().await?;
         let onchain_accounts_mapping = program_mapping.price_info_accounts;
         let onchain_price_type_mapping = program_mapping.price_types;
         let token_metadatas = self.get_token_metadatas().await?;
 
+        let mut mappings_to_update: Vec<(u16, (Pubkey, u8))> = Vec::new();
         // For all "token" local and remote
         for (&token_idx, local_entry) in &self.tokens {
             let idx: usize = token_idx.try_into().unwrap();
@@ -180,8 +669,23 @@ where
             // Update remote in case of difference
             let local_mapping_pk = local_entry.get_mapping_account();
             let loc_price_type_u8: u8 = local_entry.get_type().into();
-            if rem_mapping != local_mapping_pk || rem_price_type != loc_price_type_u8 {
-                self.ix_update_mapping(Some(local_mapping_pk), token_idx.into(), loc_price_type_u8)
+            let local_price_max_age = self.get_price_max_age(token_idx);
+            if program_mapping.price_max_ages[idx] != local_price_max_age {
+                // The batch update does not set the max age, update this token alone
+                self.ix_update_mapping(
+                    local_mapping_pk,
+                    token_idx.into(),
+                    loc_price_type_u8,
+                    local_price_max_age,
+                )
+                .await?;
+            } else if rem_mapping != local_mapping_pk || rem_price_type != loc_price_type_u8 {
+                mappings_to_update.push((token_idx, (*local_mapping_pk, loc_price_type_u8)));
+            }
+            let local_price_bounds = self.price_bounds.get(&token_idx).copied();
+            // Compared as bounds, disabled ones are not stored the same way on-chain
+            if price_bounds_from_onchain(&program_mapping.price_bounds[idx]) != local_price_bounds {
+                self.ix_set_price_bounds(token_idx.into(), local_price_bounds)
                     .await?;
             }
             let token_metadata = token_metadatas.metadatas_array[idx];
@@ -204,7 +708,12 @@ where
             }
         }
 
-        // if the token mapping contains entries that are not in the local mapping make their mapping account default
+        mappings_to_update.sort_by_key(|(token, _)| *token);
+        for batch in consecutive_batches(&mappings_to_update, MAX_MAPPING_BATCH_SIZE) {
+            self.ix_update_mapping_batch(batch).await?;
+        }
+
+        // if the token mapping contains entries that are not in the local mapping retire them
         for (idx, rem_mapping) in onchain_accounts_mapping.iter().enumerate() {
             if rem_mapping != &Pubkey::default()
                 && !self
@@ -212,13 +721,107 @@ where
                     .iter()
                     .any(|(local_id, _)| idx == usize::from(*local_id))
             {
-                self.ix_update_mapping(None, idx.try_into().unwrap(), 0)
+                self.ix_reset_oracle_mapping(idx.try_into().unwrap())
                     .await?;
             }
         }
         Ok(())
     }
 
+    /// List the tokens whose local mapping account differs from the on-chain one, by token order
+    ///
+    /// A token missing from the local mapping only differs if it is mapped on-chain.
+    /// Nothing is updated, see [`Self::upload_oracle_mapping`] to apply the local mapping.
+    pub async fn diff_oracle_mapping(&self) -> Result<Vec<MappingDiff>> {
+        let onchain_accounts_mapping = self.get_program_mapping().await?.price_info_accounts;
+
+        Ok(onchain_accounts_mapping
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, &remote)| {
+                let token: u16 = idx.try_into().unwrap();
+                let local = self
+                    .tokens
+                    .get(&token)
+                    .map(|entry| *entry.get_mapping_account());
+                (local.unwrap_or_default() != remote).then_some(MappingDiff {
+                    token,
+                    local,
+                    remote,
+                })
+            })
+            .collect())
+    }
+
+    /// Check that the mapping account of each token of the local mapping is a valid source for
+    /// the oracle type of the token, by token order
+    ///
+    /// The accounts are validated as the `update_mapping` instruction would, use it before
+    /// [`Self::upload_oracle_mapping`] to catch a wrong pubkey or oracle type. The mappings
+    /// encoding their source in the pubkey (e.g. fixed or derived prices) are validated without
+    /// fetching any account. Nothing is updated.
+    pub async fn verify_mapping_accounts(&self) -> Result<Vec<(u16, VerifyResult)>> {
+        let mut entries: Vec<(u16, &dyn TokenEntry)> = self
+            .tokens
+            .iter()
+            .map(|(idx, entry)| (*idx, entry.as_ref()))
+            .collect();
+        entries.sort_by_key(|(idx, _)| *idx);
+
+        let keys: Vec<Pubkey> = entries
+            .iter()
+            .filter(|(_, entry)| has_mapping_account(entry.get_type()))
+            .map(|(_, entry)| *entry.get_mapping_account())
+            .collect();
+        let mut fetched = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(MAX_ACCOUNTS_PER_FETCH) {
+            fetched.extend(self.get_rpc().get_multiple_accounts(chunk).await?);
+        }
+        let mut fetched = fetched.into_iter();
+
+        Ok(entries
+            .into_iter()
+            .map(|(token, entry)| {
+                let price_type = entry.get_type();
+                let key = *entry.get_mapping_account();
+                let account = if has_mapping_account(price_type) {
+                    fetched.next().unwrap()
+                } else {
+                    Some(Account::default())
+                };
+                (token, verify_mapping_account(price_type, key, account))
+            })
+            .collect())
+    }
+
+    /// Replace the whole on-chain oracle mapping with the local one in a single transaction.
+    ///
+    /// Unlike [`Self::upload_oracle_mapping`], the feed never observes a partially updated
+    /// mapping. The number of tokens is limited by the transaction size. Token metadatas are not
+    /// updated.
+    pub async fn replace_all_oracle_mappings(&self) -> Result<()> {
+        let mut entries: Vec<(u16, &dyn TokenEntry)> = self
+            .tokens
+            .iter()
+            .map(|(idx, entry)| (*idx, entry.as_ref()))
+            .collect();
+        entries.sort_by_key(|(idx, _)| *idx);
+
+        let mappings = entries
+            .iter()
+            .map(|(token, entry)| MappingEntry {
+                token: *token,
+                price_type: entry.get_type().into(),
+            })
+            .collect();
+        let price_infos = entries
+            .iter()
+            .map(|(_, entry)| *entry.get_mapping_account())
+            .collect::<Vec<_>>();
+
+        self.ix_replace_all_mappings(mappings, &price_infos).await
+    }
+
     /// Update the local oracle mapping from the on-chain version
     pub async fn download_oracle_mapping(&mut self, default_max_age: clock::Slot) -> Result<()> {
         let onchain_oracle_mapping = self.get_program_mapping().await?;
@@ -237,7 +840,9 @@ where
             .filter(|(((_, &oracle_mapping), _), _)| oracle_mapping != zero_pk)
             .map(
                 |(((idx, &oracle_mapping), oracle_type), token_metadata)| async move {
-                    let id: u16 = idx.try_into()?;
+                    let id: u16 = idx
+                        .try_into()
+                        .map_err(|_| ScopeClientError::TokenOutOfRange(idx as u64))?;
                     let first_0_or_length = token_metadata
                         .name
                         .iter()
@@ -247,12 +852,21 @@ where
                         label: std::str::from_utf8(&token_metadata.name[..first_0_or_length])
                             .unwrap()
                             .to_owned(),
-                        oracle_type: oracle_type.try_into()?,
+                        oracle_type: oracle_type
+                            .try_into()
+                            .map_err(|_| ScopeClientError::InvalidOracleType(oracle_type))?,
                         max_age: match NonZeroU64::try_from(token_metadata.max_age_price_seconds) {
                             Err(_) => None,
                             Ok(nz) => Some(nz),
                         },
+                        // Kept by the client, not needed to build the entry
+                        price_max_age: None,
                         oracle_mapping,
+                        // Not stored on-chain
+                        share_mint: None,
+                        sources: vec![],
+                        // Kept by the client, not needed to build the entry
+                        price_bounds: None,
                     };
                     let entry = entry_from_config(&oracle_conf, default_max_age, rpc).await?;
                     Result::<(u16, Box<dyn TokenEntry>)>::Ok((id, entry))
@@ -263,9 +877,30 @@ where
             .await
             .into_iter()
             .collect::<Result<TokenEntryList>>()?;
+        self.price_max_ages = self
+            .tokens
+            .keys()
+            .map(|&id| (id, onchain_oracle_mapping.price_max_ages[usize::from(id)]))
+            .filter(|(_, max_age)| *max_age != 0)
+            .collect();
+        self.price_bounds = self
+            .tokens
+            .keys()
+            .filter_map(|&id| {
+                let bounds = price_bounds_from_onchain(
+                    &onchain_oracle_mapping.price_bounds[usize::from(id)],
+                )?;
+                Some((id, bounds))
+            })
+            .collect();
         Ok(())
     }
 
+    /// Max age in slots of the source price of a token enforced on-chain, 0 for no limit
+    fn get_price_max_age(&self, token: u16) -> clock::Slot {
+        self.price_max_ages.get(&token).copied().unwrap_or(0)
+    }
+
     /// Extract the local oracle mapping to a token list configuration
     pub fn get_local_mapping(&self) -> Result<ScopeConfig> {
         let tokens: TokenList = self
@@ -279,6 +914,10 @@ where
                         oracle_mapping: *entry.get_mapping_account(),
                         oracle_type: entry.get_type(),
                         max_age: None,
+                        price_max_age: NonZeroU64::new(self.get_price_max_age(*id)),
+                        share_mint: None,
+                        sources: vec![],
+                        price_bounds: self.price_bounds.get(id).copied(),
                     },
                 )
             })
@@ -291,39 +930,54 @@ where
 
     /// Refresh all price referenced in oracle mapping
     ///
-    /// We will use [`ScopeClient::ix_refresh_price_list`] for this method.
+    /// We will use the [`instruction::RefreshPriceList`] ix for this method.
     /// The ix has a hard limit of [`MAX_REFRESH_CHUNK_SIZE`] accounts that needs
     /// to be carefully taken care of since the number of accounts varies from
     /// one token to another.
+    ///
+    /// Chunks are sent concurrently, up to the configured max in flight (see
+    /// [`ScopeClient::set_max_in_flight_refreshes`]). A failed chunk does not prevent
+    /// the others from being sent, the result of each chunk is returned.
     #[tracing::instrument(skip(self))]
-    pub async fn refresh_all_prices(&self) -> Result<()> {
+    pub async fn refresh_all_prices(&self) -> Result<Vec<ChunkRefresh>> {
         info!("Refresh all prices");
-        // Create chunk of tokens of max `MAX_REFRESH_CHUNK_SIZE` accounts
-        let mut acc_account_num = 0_usize;
-        let mut acc_token_id: Vec<u16> = Vec::with_capacity(MAX_REFRESH_CHUNK_SIZE);
-
-        let mut refresh_futures = Vec::new();
-
-        for (id, entry) in &self.tokens {
-            // if current entry would overflow the token count > send and reset
-            if entry.get_number_of_extra_accounts() + 1 + acc_account_num > MAX_REFRESH_CHUNK_SIZE {
-                refresh_futures.push(self.refresh_price_list_print_res(acc_token_id.clone()));
-                acc_account_num = 0;
-                acc_token_id.clear()
-            }
-            // accumulate
-            acc_account_num += entry.get_number_of_extra_accounts() + 1;
-            acc_token_id.push(*id);
-        }
-
-        // last tokens refresh
-        if !acc_token_id.is_empty() {
-            refresh_futures.push(self.refresh_price_list_print_res(acc_token_id));
-        }
+        let tokens: Vec<u16> = self.tokens.keys().copied().collect();
+        self.refresh_prices_in_chunks(&tokens, false).await
+    }
 
-        join_all(refresh_futures).await;
+    /// Refresh all price referenced in oracle mapping, keeping the valid prices of each chunk
+    ///
+    /// Same as [`ScopeClient::refresh_all_prices`] but with the
+    /// [`instruction::RefreshPriceListLenient`] ix: a token whose accounts do not match the
+    /// onchain mapping does not fail its chunk, the prices refreshed before it are kept.
+    #[tracing::instrument(skip(self))]
+    pub async fn refresh_all_prices_lenient(&self) -> Result<Vec<ChunkRefresh>> {
+        info!("Refresh all prices (lenient)");
+        let tokens: Vec<u16> = self.tokens.keys().copied().collect();
+        self.refresh_prices_in_chunks(&tokens, true).await
+    }
 
-        Ok(())
+    /// Refresh the prices of the tokens in the inclusive range `[start, end]`
+    ///
+    /// Only the entries present in the local oracle mapping are refreshed.
+    /// Chunks are built the same way as in [`ScopeClient::refresh_all_prices`].
+    #[tracing::instrument(skip(self))]
+    pub async fn refresh_price_range(&self, start: u16, end: u16) -> Result<Vec<ChunkRefresh>> {
+        if start > end {
+            return Err(ScopeClientError::InvalidRange { start, end });
+        }
+        if usize::from(end) >= MAX_ENTRIES {
+            return Err(ScopeClientError::TokenOutOfRange(end.into()));
+        }
+        info!("Refresh prices from {start} to {end}");
+        let mut tokens: Vec<u16> = self
+            .tokens
+            .keys()
+            .copied()
+            .filter(|id| (start..=end).contains(id))
+            .collect();
+        tokens.sort_unstable();
+        self.refresh_prices_in_chunks(&tokens, false).await
     }
 
     /// Refresh all prices that has reach 0 ttl
@@ -331,7 +985,7 @@ where
     /// As an optimization for number of tx, we complete tx with not 0 ttl
     /// if some room is left.
     #[tracing::instrument(skip(self))]
-    pub async fn refresh_old_prices(&self) -> Result<()> {
+    pub async fn refresh_old_prices(&self) -> Result<Vec<ChunkRefresh>> {
         let mut prices_ttl: Vec<(u16, i64)> = self.get_prices_ttl().await?.collect();
         // TODO: filter prices that cannot be refreshed
         // Sort the prices ttl from the smallest to biggest.
@@ -342,35 +996,62 @@ where
         // Keep only the prices that are below REMAINING_AGE_TO_REFRESH
         prices_ttl.retain(|(_, ttl)| *ttl < REMAINING_AGE_TO_REFRESH);
 
-        // Create chunk of tokens of max `MAX_REFRESH_CHUNK_SIZE` accounts
-        let mut acc_account_num = 0_usize;
-        let mut acc_token_id: Vec<u16> = Vec::with_capacity(MAX_REFRESH_CHUNK_SIZE);
-        let mut refresh_futures = Vec::new();
-
-        for (id, _ttl) in &prices_ttl {
-            let entry = self
-                .tokens
-                .get(id)
-                .ok_or_else(|| anyhow!("Unknown price at index {id}"))?;
-            // if current entry would overflow the token count > send and reset
-            if entry.get_number_of_extra_accounts() + 1 + acc_account_num > MAX_REFRESH_CHUNK_SIZE {
-                refresh_futures.push(self.refresh_price_list_print_res(acc_token_id.clone()));
-                acc_account_num = 0;
-                acc_token_id.clear();
-            }
-            // accumulate
-            acc_account_num += entry.get_number_of_extra_accounts() + 1;
-            acc_token_id.push(*id);
-        }
-
-        // last tokens refresh
-        if !acc_token_id.is_empty() {
-            refresh_futures.push(self.refresh_price_list_print_res(acc_token_id));
-        }
+        let tokens: Vec<u16> = prices_ttl.iter().map(|(id, _ttl)| *id).collect();
+        self.refresh_prices_in_chunks(&tokens, false).await
+    }
 
-        join_all(refresh_futures).await;
+    /// Refresh the given tokens, grouped in chunks of max `refresh_chunk_size` accounts (see
+    /// [`ScopeClient::set_refresh_chunk_size`])
+    ///
+    /// All the chunk transactions are built before sending any of them, the results
+    /// are returned in the order of the chunks. If `lenient`, the chunks use the
+    /// [`instruction::RefreshPriceListLenient`] ix.
+    async fn refresh_prices_in_chunks(
+        &self,
+        tokens: &[u16],
+        lenient: bool,
+    ) -> Result<Vec<ChunkRefresh>> {
+        let chunks = split_in_chunks(&self.tokens, tokens, self.refresh_chunk_size)?;
+        let max_in_flight = self.max_in_flight_refreshes.get();
+
+        let txs: Vec<Result<VersionedTransaction>> = stream::iter(&chunks)
+            .map(|chunk| self.build_refresh_price_list_tx(chunk, lenient))
+            .buffered(max_in_flight)
+            .collect()
+            .await;
+
+        let results = stream::iter(chunks.into_iter().zip(txs))
+            .map(|(tokens, tx)| async move {
+                let start = Instant::now();
+                let result = match tx {
+                    Ok(tx) => {
+                        let ix = RefreshIx::List {
+                            tokens: &tokens,
+                            lenient,
+                        };
+                        self.send_refresh_tx(tx, ix).await
+                    }
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = &result {
+                    warn!(
+                        ?err,
+                        ?tokens,
+                        "Error while sending refresh price list transaction"
+                    );
+                    // Ok case already printed
+                }
+                ChunkRefresh {
+                    tokens,
+                    result,
+                    elapsed: start.elapsed(),
+                }
+            })
+            .buffered(max_in_flight)
+            .collect()
+            .await;
 
-        Ok(())
+        Ok(results)
     }
 
     /// Get an iterator over `(id, price_ttl)`
@@ -421,13 +1102,23 @@ where
 
     /// Log current prices
     /// Note: this uses local mapping
-    pub async fn log_prices(&self, current_slot: u64) -> Result<()> {
+    ///
+    /// If `uniform_exp` is provided all prices are displayed with this exponent, a price that
+    /// cannot be rescaled is displayed with its own exponent.
+    pub async fn log_prices(&self, current_slot: u64, uniform_exp: Option<u8>) -> Result<()> {
         let prices = self.get_prices().await?.prices;
 
         for (&id, entry) in &self.tokens {
             let dated_price = prices[usize::from(id)];
-            let price = price_to_f64(&dated_price.price);
-            let exponent = (dated_price.price.exp + 1) as usize;
+            let displayed_price = match uniform_exp {
+                Some(exp) => normalize_price(&dated_price.price, exp).unwrap_or_else(|e| {
+                    warn!("Cannot display price of {entry} with a uniform exponent: {e}");
+                    dated_price.price
+                }),
+                None => dated_price.price,
+            };
+            let price = price_to_f64(&displayed_price);
+            let exponent = (displayed_price.exp + 1) as usize;
             let price_type = entry.get_type();
             let age_in_slots: i64 = current_slot as i64 - dated_price.last_updated_slot as i64;
             let max_age = entry.get_max_age() as i64;
@@ -442,6 +1133,71 @@ where
         Ok(())
     }
 
+    /// Decoded prices account as a table, one row per populated token
+    ///
+    /// With `stale_after_s` only the prices older than this number of seconds are listed.
+    /// Note: this uses local mapping for the pair names
+    pub async fn prices_table(&self, stale_after_s: Option<u64>) -> Result<String> {
+        let oracle_prices = self.get_prices().await?;
+        let clock = get_clock(self.get_rpc()).await?;
+        Ok(format_prices_table(
+            &oracle_prices,
+            &self.tokens,
+            &clock,
+            stale_after_s,
+        ))
+    }
+
+    /// Report the health of the stored price of each token of the local mapping at
+    /// `current_slot`, sorted by token
+    ///
+    /// Only reads the prices account, no transaction is sent.
+    pub async fn feed_health(&self, current_slot: u64) -> Result<Vec<TokenHealth>> {
+        let oracle_prices = self.get_prices().await?;
+        Ok(tokens_health(&oracle_prices, &self.tokens, current_slot))
+    }
+
+    /// List the pairs of the local mapping with their token, sorted by token
+    pub fn list_pairs(&self) -> Vec<(u16, String)> {
+        let mut pairs: Vec<(u16, String)> = self
+            .tokens
+            .iter()
+            .map(|(&token, entry)| (token, entry.get_label().to_string()))
+            .collect();
+        pairs.sort_unstable_by_key(|(token, _)| *token);
+        pairs
+    }
+
+    /// List the pairs of the local mapping with their on-chain mapping account, sorted by token
+    ///
+    /// Tokens only mapped on-chain are not listed, see [`Self::diff_oracle_mapping`].
+    pub async fn get_named_mapping(&self) -> Result<Vec<NamedMapping>> {
+        let onchain_accounts_mapping = self.get_program_mapping().await?.price_info_accounts;
+
+        Ok(self
+            .list_pairs()
+            .into_iter()
+            .map(|(token, pair)| {
+                let oracle = onchain_accounts_mapping[usize::from(token)];
+                NamedMapping {
+                    token,
+                    pair,
+                    oracle: (oracle != Pubkey::default()).then_some(oracle),
+                }
+            })
+            .collect())
+    }
+
+    /// Get the stored price of a pair of the local mapping, e.g. "SOL/USD"
+    ///
+    /// The pair is matched on the token labels ignoring case. It fails if no token or several
+    /// tokens have this label, or if the token is not mapped to an oracle.
+    pub async fn get_price_by_pair(&self, pair: &str) -> Result<DatedPrice> {
+        let token = find_pair(&self.tokens, pair)?;
+        let prices = self.get_prices().await?;
+        Ok(prices.prices[usize::from(token)])
+    }
+
     /// Return a list (label if available) of expired prices
     pub async fn get_expired_prices(&self) -> Result<Vec<String>> {
         Ok(self
@@ -457,6 +1213,127 @@ where
             .collect())
     }
 
+    /// Recompute the prices of the local mapping from their sources and compare them to the
+    /// stored prices, returning the ones diverging by more than `tolerance_bps`
+    ///
+    /// Prices are recomputed with the same logic as the refresh instructions but at the current
+    /// slot, sources moving since the last refresh must be covered by the tolerance.
+    /// Tokens never refreshed are not checked, tokens whose price cannot be recomputed are logged
+    /// and skipped.
+    pub async fn check_prices(&self, tolerance_bps: u64) -> Result<Vec<PriceDivergence>> {
+        let rpc = self.get_rpc();
+        let clock = get_clock(rpc).await?;
+        let mut prices_account = rpc.get_account(&self.oracle_prices_acc).await?;
+        let stored_prices = OraclePrices::try_deserialize(&mut prices_account.data.as_slice())
+            .map_err(anyhow::Error::from)?;
+        let mappings = self.get_program_mapping().await?;
+        let mut twaps_account = match self.oracle_twaps_acc {
+            Some(twaps_key) => Some((twaps_key, rpc.get_account(&twaps_key).await?)),
+            None => None,
+        };
+
+        let mut tokens: Vec<u16> = self.tokens.keys().copied().collect();
+        tokens.sort_unstable();
+
+        let mut divergences = Vec::new();
+        for token in tokens {
+            let stored = stored_prices.prices[usize::from(token)];
+            if stored == DatedPrice::default() {
+                trace!(token, "Price never refreshed, not checked");
+                continue;
+            }
+            let recomputed = match self
+                .recompute_price(
+                    token,
+                    &clock,
+                    &mut prices_account,
+                    twaps_account.as_mut(),
+                    &mappings,
+                )
+                .await
+            {
+                Ok(recomputed) => recomputed,
+                Err(err) => {
+                    warn!(token, "Price cannot be recomputed: {err}");
+                    continue;
+                }
+            };
+            let deviation = deviation_bps(&stored.price, &recomputed);
+            let diverges = match deviation {
+                Some(bps) => bps > u128::from(tolerance_bps),
+                None => stored.price != recomputed,
+            };
+            if diverges {
+                divergences.push(PriceDivergence {
+                    token,
+                    stored: stored.price,
+                    recomputed,
+                    deviation_bps: deviation,
+                });
+            }
+        }
+        Ok(divergences)
+    }
+
+    /// Compute the price of a token from its current sources, as the refresh would store it
+    async fn recompute_price(
+        &self,
+        token: u16,
+        clock: &Clock,
+        prices_account: &mut Account,
+        twaps_account: Option<&mut (Pubkey, Account)>,
+        mappings: &OracleMappings,
+    ) -> Result<Price> {
+        let rpc = self.get_rpc();
+        let entry = self
+            .tokens
+            .get(&token)
+            .ok_or(ScopeClientError::UnknownToken(token))?;
+
+        let mut keys = vec![*entry.get_mapping_account()];
+        keys.extend(entry.get_extra_accounts(Some(rpc)).await?);
+        // Missing accounts are given to the refresh as empty accounts, as the encoded sources of
+        // derived prices
+        let mut accounts: Vec<Account> = rpc
+            .get_multiple_accounts(&keys)
+            .await?
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+
+        let account_infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(accounts.iter_mut())
+            .map(|account| account.into_account_info())
+            .collect();
+        let prices_info = (&self.oracle_prices_acc, prices_account).into_account_info();
+        let oracle_prices =
+            AccountLoader::<OraclePrices>::try_from_unchecked(&self.program_id, &prices_info)
+                .map_err(anyhow::Error::from)?;
+        let twaps_info =
+            twaps_account.map(|(twaps_key, account)| (&*twaps_key, account).into_account_info());
+        let oracle_twaps = twaps_info
+            .as_ref()
+            .map(|info| AccountLoader::<OracleTwaps>::try_from_unchecked(&self.program_id, info))
+            .transpose()
+            .map_err(anyhow::Error::from)?;
+
+        let (base_account, extra_accounts) = account_infos.split_first().unwrap();
+        let idx = usize::from(token);
+        let price = get_price(
+            entry.get_type(),
+            base_account,
+            &mut extra_accounts.iter().peekable(),
+            clock,
+            &oracle_prices,
+            oracle_twaps.as_ref(),
+            mappings,
+            idx,
+        )
+        .map_err(|err| ScopeClientError::PriceRecomputation { token, err })?;
+        Ok(apply_hard_cap(price.price, &mappings.hard_caps[idx]))
+    }
+
     /// Print a list of all pubkeys that are needed for price refreshed.
     pub async fn print_pubkeys(&self) -> Result<()> {
         // Print only unique pubkeys
@@ -480,8 +1357,13 @@ where
         &self.client.client
     }
 
+    /// Get the account paying for the transactions of the ScopeClient
+    pub fn get_payer(&self) -> Pubkey {
+        self.client.payer()
+    }
+
     /// Get all prices
-    async fn get_prices(&self) -> Result<OraclePrices> {
+    pub async fn get_prices(&self) -> Result<OraclePrices> {
         let prices: OraclePrices = self
             .client
             .get_anchor_account(&self.oracle_prices_acc)
@@ -580,10 +1462,7 @@ where
         let (signature, init_res) = client.send_retry_and_confirm_transaction(init_tx).await?;
 
         info!(%signature, "Init tx");
-        match init_res {
-            Some(r) => r.context(format!("Init transaction: {signature}")),
-            None => bail!("Init transaction failed to confirm: {signature}"),
-        }
+        ScopeClientError::from_tx_result(signature, init_res)
     }
 
     #[tracing::instrument(skip(client))]
@@ -630,24 +1509,279 @@ where
         let (signature, init_res) = client.send_retry_and_confirm_transaction(init_tx).await?;
 
         info!(%signature, "Init metadatas tx");
-        match init_res {
-            Some(r) => r.context(format!("Init tokens_metadata transaction: {signature}")),
-            None => bail!("Init tokens_metadata transaction failed to confirm: {signature}"),
+        ScopeClientError::from_tx_result(signature, init_res)
+    }
+
+    #[tracing::instrument(skip(client))]
+    async fn ix_initialize_oracle_twaps(
+        client: &OrbitLink<T, S>,
+        program_id: &Pubkey,
+        configuration_acc: &Pubkey,
+        oracle_prices_acc: &Pubkey,
+        oracle_twaps_acc: &Keypair,
+        price_feed: &str,
+    ) -> Result<()> {
+        debug!("Entering oracle_twaps initialize ix");
+
+        let init_account = accounts::InitializeOracleTwaps {
+            admin: client.payer(),
+            configuration: *configuration_acc,
+            oracle_prices: *oracle_prices_acc,
+            oracle_twaps: oracle_twaps_acc.pubkey(),
+        };
+
+        let init_tx = client
+            .tx_builder()
+            // Create the twaps account
+            .add_ix_with_budget(
+                client
+                    .create_account_ix(
+                        &oracle_twaps_acc.pubkey(),
+                        size_of::<OracleTwaps>() + 8,
+                        program_id,
+                    )
+                    .await?,
+                50_000,
+            )
+            .add_anchor_ix(
+                program_id,
+                init_account,
+                instruction::InitializeOracleTwaps {
+                    feed_name: price_feed.to_string(),
+                },
+            )
+            .build_with_budget_and_fee(&[oracle_twaps_acc])
+            .await?;
+
+        let (signature, init_res) = client.send_retry_and_confirm_transaction(init_tx).await?;
+
+        info!(%signature, "Init oracle twaps tx");
+        ScopeClientError::from_tx_result(signature, init_res)
+    }
+
+    /// Set the only account allowed to refresh the prices, the default pubkey lets anyone refresh
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_refresh_authority(&self, refresh_authority: Pubkey) -> Result<()> {
+        let set_accounts = accounts::SetRefreshAuthority {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_mappings: self.oracle_mappings_acc,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                set_accounts,
+                instruction::SetRefreshAuthority {
+                    feed_name: self.feed_name.clone(),
+                    refresh_authority,
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Refresh authority set successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Refresh authority update failed"),
+            None => error!(%signature, "Could not confirm refresh authority update transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Propose a new admin of the feed, it becomes the admin with [`Self::ix_accept_admin`]
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_admin(&self, pending_admin: Pubkey) -> Result<()> {
+        let set_accounts = accounts::SetAdmin {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                set_accounts,
+                instruction::SetAdmin {
+                    feed_name: self.feed_name.clone(),
+                    pending_admin,
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Pending admin set successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Pending admin update failed"),
+            None => error!(%signature, "Could not confirm pending admin update transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Become the admin of the feed, the payer must be the pending admin
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_accept_admin(&self) -> Result<()> {
+        let accept_accounts = accounts::AcceptAdmin {
+            pending_admin: self.client.payer(),
+            configuration: self.configuration_acc,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                accept_accounts,
+                instruction::AcceptAdmin {
+                    feed_name: self.feed_name.clone(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Admin accepted successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Admin acceptance failed"),
+            None => error!(%signature, "Could not confirm admin acceptance transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Set the max number of slots without any price update after which the reads of the feed
+    /// fail, 0 disables the check
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_max_feed_staleness(&self, max_feed_staleness_slots: u64) -> Result<()> {
+        let set_accounts = accounts::SetMaxFeedStaleness {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_prices: self.oracle_prices_acc,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                set_accounts,
+                instruction::SetMaxFeedStaleness {
+                    feed_name: self.feed_name.clone(),
+                    max_feed_staleness_slots,
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Max feed staleness set successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Max feed staleness update failed"),
+            None => error!(%signature, "Could not confirm max feed staleness update transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Clear the TWAP samples of a token and set the number of samples used to compute it
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_reset_twap(&self, token: u64, window_size: u64) -> Result<()> {
+        let oracle_twaps = self
+            .oracle_twaps_acc
+            .ok_or(ScopeClientError::NotInitialized)?;
+        let reset_accounts = accounts::ResetTwap {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_twaps,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                reset_accounts,
+                instruction::ResetTwap {
+                    token,
+                    window_size,
+                    feed_name: self.feed_name.clone(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "TWAP reset successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "TWAP reset failed"),
+            None => error!(%signature, "Could not confirm TWAP reset transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Clear the EMA of a token and set its half-life in slots (0 for the default half-life)
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_ema_half_life(&self, token: u64, half_life_slots: u64) -> Result<()> {
+        let oracle_twaps = self
+            .oracle_twaps_acc
+            .ok_or(ScopeClientError::NotInitialized)?;
+        let set_accounts = accounts::SetEmaHalfLife {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_twaps,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                set_accounts,
+                instruction::SetEmaHalfLife {
+                    token,
+                    half_life_slots,
+                    feed_name: self.feed_name.clone(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "EMA half-life set successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "EMA half-life update failed"),
+            None => error!(%signature, "Could not confirm EMA half-life transaction"),
         }
+
+        ScopeClientError::from_tx_result(signature, res)
     }
 
     #[tracing::instrument(skip(self))]
     async fn ix_update_mapping(
         &self,
-        oracle_account: Option<&Pubkey>,
+        oracle_account: &Pubkey,
         token: u64,
         price_type: u8,
+        price_max_age: clock::Slot,
     ) -> Result<()> {
         let update_accounts = accounts::UpdateOracleMapping {
             admin: self.client.payer(),
             configuration: self.configuration_acc,
             oracle_mappings: self.oracle_mappings_acc,
-            price_info: oracle_account.copied(),
+            price_info: Some(*oracle_account),
         };
 
         let request = self.client.tx_builder();
@@ -660,6 +1794,7 @@ where
                     token,
                     price_type,
                     feed_name: self.feed_name.clone(),
+                    price_max_age,
                 },
             )
             .build_with_budget_and_fee(&[])
@@ -667,19 +1802,230 @@ where
 
         let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
 
-        match res {
+        match &res {
             Some(Ok(())) => info!(%signature, "Accounts updated successfully"),
-            Some(Err(err)) => {
-                error!(%signature, err = ?err, "Mapping update failed");
-                bail!(err);
-            }
-            None => {
-                error!(%signature, "Could not confirm mapping update transaction");
-                bail!("Could not confirm mapping update transaction");
-            }
+            Some(Err(err)) => error!(%signature, err = ?err, "Mapping update failed"),
+            None => error!(%signature, "Could not confirm mapping update transaction"),
         }
 
-        Ok(())
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Remove the mapping of `token` and clear its on-chain price
+    #[tracing::instrument(skip(self))]
+    async fn ix_reset_oracle_mapping(&self, token: u64) -> Result<()> {
+        let reset_accounts = accounts::ResetOracleMapping {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_mappings: self.oracle_mappings_acc,
+            oracle_prices: self.oracle_prices_acc,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                reset_accounts,
+                instruction::ResetOracleMapping {
+                    token,
+                    feed_name: self.feed_name.clone(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Mapping reset successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Mapping reset failed"),
+            None => error!(%signature, "Could not confirm mapping reset transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Close the accounts of the feed, including its TWAP account if any, and send their
+    /// lamports to `destination`
+    ///
+    /// The client cannot be used anymore once the feed is closed.
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_close(&self, destination: &Pubkey) -> Result<()> {
+        let close_accounts = accounts::CloseFeed {
+            admin: self.client.payer(),
+            destination: *destination,
+            configuration: self.configuration_acc,
+            oracle_mappings: self.oracle_mappings_acc,
+            oracle_prices: self.oracle_prices_acc,
+            tokens_metadata: self.tokens_metadata_acc,
+            oracle_twaps: self.oracle_twaps_acc,
+        };
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                close_accounts,
+                instruction::CloseFeed {
+                    feed_name: self.feed_name.clone(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Feed closed successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Feed close failed"),
+            None => error!(%signature, "Could not confirm feed close transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Upgrade the accounts of the feed to the current layout, does nothing if they already
+    /// have it
+    ///
+    /// `migrate_feed` grows an account by at most `MAX_PERMITTED_DATA_INCREASE` bytes, the
+    /// transaction repeats it as many times as needed.
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_migrate_feed(&self) -> Result<()> {
+        let rpc = self.get_rpc();
+        let mappings_len = rpc.get_account(&self.oracle_mappings_acc).await?.data.len();
+        let prices_len = rpc.get_account(&self.oracle_prices_acc).await?.data.len();
+        // Each instruction upgrades both accounts
+        let steps = [
+            (size_of::<OracleMappings>() + 8).saturating_sub(mappings_len),
+            (size_of::<OraclePrices>() + 8).saturating_sub(prices_len),
+        ]
+        .into_iter()
+        .map(|missing_len| {
+            (missing_len + MAX_PERMITTED_DATA_INCREASE - 1) / MAX_PERMITTED_DATA_INCREASE
+        })
+        .max()
+        .unwrap_or(0);
+        if steps == 0 {
+            info!("Feed accounts already have the current layout");
+            return Ok(());
+        }
+
+        let mut tx_builder = self.client.tx_builder();
+        for _ in 0..steps {
+            tx_builder = tx_builder.add_anchor_ix(
+                &self.program_id,
+                accounts::MigrateFeed {
+                    admin: self.client.payer(),
+                    configuration: self.configuration_acc,
+                    oracle_mappings: self.oracle_mappings_acc,
+                    oracle_prices: self.oracle_prices_acc,
+                    system_program: system_program::ID,
+                },
+                instruction::MigrateFeed {
+                    feed_name: self.feed_name.clone(),
+                },
+            );
+        }
+        let tx = tx_builder.build_with_budget_and_fee(&[]).await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, steps, "Feed migrated successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Feed migration failed"),
+            None => error!(%signature, "Could not confirm feed migration transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Update the mapping of consecutive tokens, `batch` is sorted by token
+    #[tracing::instrument(skip(self, batch), fields(first_token = batch[0].0, len = batch.len()))]
+    async fn ix_update_mapping_batch(&self, batch: &[(u16, (Pubkey, u8))]) -> Result<()> {
+        let mut update_accounts = accounts::UpdateMappingBatch {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_mappings: self.oracle_mappings_acc,
+        }
+        .to_account_metas(None);
+        update_accounts.extend(
+            batch
+                .iter()
+                .map(|(_, (price_info, _))| AccountMeta::new_readonly(*price_info, false)),
+        );
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                update_accounts,
+                instruction::UpdateMappingBatch {
+                    feed_name: self.feed_name.clone(),
+                    first_token: batch[0].0,
+                    price_types: batch
+                        .iter()
+                        .map(|(_, (_, price_type))| *price_type)
+                        .collect(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Accounts updated successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Mapping batch update failed"),
+            None => error!(%signature, "Could not confirm mapping batch update transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    #[tracing::instrument(skip(self, mappings))]
+    async fn ix_replace_all_mappings(
+        &self,
+        mappings: Vec<MappingEntry>,
+        price_infos: &[Pubkey],
+    ) -> Result<()> {
+        let mut replace_accounts = accounts::ReplaceAllMappings {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_mappings: self.oracle_mappings_acc,
+        }
+        .to_account_metas(None);
+        replace_accounts.extend(
+            price_infos
+                .iter()
+                .map(|price_info| AccountMeta::new_readonly(*price_info, false)),
+        );
+
+        let tx = self
+            .client
+            .tx_builder()
+            .add_anchor_ix(
+                &self.program_id,
+                replace_accounts,
+                instruction::ReplaceAllMappings {
+                    feed_name: self.feed_name.clone(),
+                    mappings,
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Mappings replaced successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Mappings replacement failed"),
+            None => error!(%signature, "Could not confirm mappings replacement transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
     }
 
     #[tracing::instrument(skip(self))]
@@ -713,33 +2059,133 @@ where
 
         let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
 
-        match res {
+        match &res {
             Some(Ok(())) => info!(%signature, "Token metadata updated successfully"),
-            Some(Err(err)) => {
-                error!(%signature, err = ?err, "Token metadata update failed");
-                bail!(err);
-            }
-            None => {
-                error!(%signature, "Could not confirm token metadata update transaction");
-                bail!("Could not confirm mapping update transaction");
-            }
+            Some(Err(err)) => error!(%signature, err = ?err, "Token metadata update failed"),
+            None => error!(%signature, "Could not confirm token metadata update transaction"),
         }
 
-        Ok(())
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_update_mapping_config(
+        &self,
+        token: u64,
+        mode: UpdateMappingConfigMode,
+        value: Vec<u8>,
+    ) -> Result<()> {
+        let update_accounts = accounts::UpdateMappingConfig {
+            admin: self.client.payer(),
+            configuration: self.configuration_acc,
+            oracle_mappings: self.oracle_mappings_acc,
+        };
+
+        let request = self.client.tx_builder();
+
+        let tx = request
+            .add_anchor_ix(
+                &self.program_id,
+                update_accounts,
+                instruction::UpdateMappingConfig {
+                    token,
+                    mode: mode.to_u64(),
+                    value,
+                    feed_name: self.feed_name.clone(),
+                },
+            )
+            .build_with_budget_and_fee(&[])
+            .await?;
+
+        let (signature, res) = self.client.send_retry_and_confirm_transaction(tx).await?;
+
+        match &res {
+            Some(Ok(())) => info!(%signature, "Mapping config updated successfully"),
+            Some(Err(err)) => error!(%signature, err = ?err, "Mapping config update failed"),
+            None => error!(%signature, "Could not confirm mapping config update transaction"),
+        }
+
+        ScopeClientError::from_tx_result(signature, res)
+    }
+
+    /// Reject refreshed prices of `token` deviating by more than `max_deviation_bps` from the
+    /// stored price, 0 disables the check
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_max_deviation(&self, token: u64, max_deviation_bps: u16) -> Result<()> {
+        self.ix_update_mapping_config(
+            token,
+            UpdateMappingConfigMode::MaxDeviation,
+            u64::from(max_deviation_bps).to_le_bytes().to_vec(),
+        )
+        .await
+    }
+
+    /// Set the max confidence/price ratio of the pyth based entry `token` and whether a wider
+    /// confidence interval rejects the price or only flags it, 0 restores the default ratio
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_max_confidence(
+        &self,
+        token: u64,
+        max_confidence_bps: u16,
+        policy: ConfidencePolicy,
+    ) -> Result<()> {
+        let mut value = u64::from(max_confidence_bps).to_le_bytes().to_vec();
+        value.extend_from_slice(&(policy as u64).to_le_bytes());
+        self.ix_update_mapping_config(token, UpdateMappingConfigMode::MaxConfidence, value)
+            .await
+    }
+
+    /// Set the range of the accepted prices of `token`, `None` removes the bounds
+    #[tracing::instrument(skip(self))]
+    pub async fn ix_set_price_bounds(&self, token: u64, bounds: Option<PriceBounds>) -> Result<()> {
+        let [min, max] = price_bounds_to_onchain(bounds);
+        let value = [min.value, max.value, max.exp]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        self.ix_update_mapping_config(token, UpdateMappingConfigMode::PriceBounds, value)
+            .await
+    }
+
+    /// Simulate the refresh of `tokens` in a single list refresh transaction and return the
+    /// compute units it consumes
+    ///
+    /// Nothing is refreshed. Used to find the largest chunk of tokens fitting in the compute
+    /// limits (see [`MAX_REFRESH_CHUNK_SIZE`]), a simulation exceeding them fails.
+    #[tracing::instrument(skip(self))]
+    pub async fn simulate_refresh_compute(&self, tokens: &[u16]) -> Result<RefreshCompute> {
+        let tx = self.build_refresh_price_list_tx(tokens, false).await?;
+        let simulation = self.get_rpc().simulate_transaction(&tx).await?.value;
+        for log in simulation.logs.iter().flatten() {
+            debug!("{log}");
+        }
+        Ok(RefreshCompute {
+            tokens: tokens.to_vec(),
+            compute_units: program_compute_units(&simulation, &self.program_id)?,
+        })
     }
 
     #[tracing::instrument(skip(self))]
-    pub async fn ix_refresh_one_price(&self, token: u16) -> Result<()> {
+    pub async fn ix_refresh_one_price(&self, token: u16) -> Result<RefreshOutcome> {
+        let tx = self.build_refresh_one_price_tx(token).await?;
+        self.send_refresh_tx(tx, RefreshIx::One(token)).await
+    }
+
+    /// Build the transaction refreshing one token with the single refresh ix
+    async fn build_refresh_one_price_tx(&self, token: u16) -> Result<VersionedTransaction> {
         let entry = self
             .tokens
             .get(&token)
-            .ok_or_else(|| anyhow!("Unexpected token id {token}"))?;
+            .ok_or(ScopeClientError::UnknownToken(token))?;
         let mut refresh_accounts = accounts::RefreshOne {
             oracle_prices: self.oracle_prices_acc,
             oracle_mappings: self.oracle_mappings_acc,
             price_info: *entry.get_mapping_account(),
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: self.oracle_twaps_acc,
+            // Harmless when the feed has no refresh authority
+            refresh_authority: Some(self.client.payer()),
         }
         .to_account_metas(None);
 
@@ -765,22 +2211,23 @@ where
             .build_with_budget_and_fee(&[])
             .await?;
 
-        let (signature, res) = self.client.send_and_confirm_transaction(tx).await?;
-
-        info!(%signature, "Price refresh tx sent");
-
-        match res {
-            Some(r) => r.context(format!("Price refresh transaction: {signature}")),
-            None => bail!("Init transaction failed to confirm: {signature}"),
-        }
+        Ok(tx)
     }
 
-    async fn ix_refresh_price_list(&self, tokens: &[u16]) -> Result<Signature> {
+    /// Build the transaction refreshing the given tokens with a single list refresh ix
+    async fn build_refresh_price_list_tx(
+        &self,
+        tokens: &[u16],
+        lenient: bool,
+    ) -> Result<VersionedTransaction> {
         let mut refresh_accounts = accounts::RefreshList {
             oracle_prices: self.oracle_prices_acc,
             oracle_mappings: self.oracle_mappings_acc,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: self.oracle_twaps_acc,
+            // Harmless when the feed has no refresh authority
+            refresh_authority: Some(self.client.payer()),
         }
         .to_account_metas(None);
 
@@ -791,7 +2238,7 @@ where
             let entry = self
                 .tokens
                 .get(token_idx)
-                .ok_or_else(|| anyhow!("Unexpected token {token_idx}"))?;
+                .ok_or(ScopeClientError::UnknownToken(*token_idx))?;
             // Note: no control at this point, all token accounts will be sent in on tx
             refresh_accounts.push(AccountMeta::new_readonly(
                 *entry.get_mapping_account(),
@@ -801,44 +2248,336 @@ where
                 refresh_accounts.push(AccountMeta::new_readonly(extra, false));
             }
             cu_budget += entry.get_update_cu_budget();
+            if self.oracle_twaps_acc.is_some() {
+                cu_budget += TWAP_UPDATE_CU_BUDGET;
+            }
         }
 
         let tokens = tokens.to_vec();
 
-        let tx = self
-            .client
-            .tx_builder()
-            .add_anchor_ix_with_budget(
+        let tx_builder = self.client.tx_builder();
+        let tx_builder = if lenient {
+            tx_builder.add_anchor_ix_with_budget(
+                &self.program_id,
+                refresh_accounts,
+                instruction::RefreshPriceListLenient { tokens },
+                cu_budget,
+            )
+        } else {
+            tx_builder.add_anchor_ix_with_budget(
                 &self.program_id,
                 refresh_accounts,
                 instruction::RefreshPriceList { tokens },
                 cu_budget,
             )
-            .build_with_budget_and_fee(&[])
-            .await?;
+        };
+        let tx = tx_builder.build_with_budget_and_fee(&[]).await?;
 
-        let (signature, tx_res) = self.client.send_and_confirm_transaction(tx).await?;
+        Ok(tx)
+    }
 
-        match tx_res {
-            Some(Ok(())) => {
-                info!(%signature, "Prices list refreshed successfully");
+    /// Send a refresh transaction, or only simulate it in dry-run mode
+    ///
+    /// The transaction is rebuilt from `ix` and sent again on transient errors, according to
+    /// the configured retry policy (see [`ScopeClient::set_retry_policy`]).
+    async fn send_refresh_tx(
+        &self,
+        tx: VersionedTransaction,
+        ix: RefreshIx<'_>,
+    ) -> Result<RefreshOutcome> {
+        if self.dry_run {
+            return self.simulate_refresh(&tx).await;
+        }
+
+        let sender = RefreshTxSender { scope: self, ix };
+        match send_with_retry(&sender, tx, &self.retry_policy).await {
+            Ok(signature) => {
+                info!(%signature, ?ix, "Prices refreshed successfully");
+                Ok(RefreshOutcome::Sent(signature))
             }
-            Some(Err(err)) => {
-                error!(%signature, ?err, "Failed to refresh price list");
+            Err(err) => {
+                error!(?err, ?ix, "Failed to refresh prices");
+                Err(err)
             }
-            None => {
-                info!(%signature, "Could not confirm refresh price list transaction");
+        }
+    }
+
+    /// Simulate a refresh transaction, logging the program logs and consumed compute units
+    async fn simulate_refresh(&self, tx: &VersionedTransaction) -> Result<RefreshOutcome> {
+        let simulation = self.get_rpc().simulate_transaction(tx).await?.value;
+        let units_consumed = simulation.units_consumed;
+        match &simulation.err {
+            None => info!(
+                ?units_consumed,
+                "Refresh transaction simulated successfully"
+            ),
+            Some(err) => error!(
+                ?units_consumed,
+                ?err,
+                "Refresh transaction simulation failed"
+            ),
+        }
+        for log in simulation.logs.iter().flatten() {
+            info!("{log}");
+        }
+        Ok(RefreshOutcome::Simulated(simulation))
+    }
+}
+
+/// Refresh instruction of a transaction, used to rebuild it before a retry
+#[derive(Debug, Clone, Copy)]
+enum RefreshIx<'a> {
+    One(u16),
+    List { tokens: &'a [u16], lenient: bool },
+}
+
+/// Refresh transaction sent with [`send_with_retry`]
+struct RefreshTxSender<'a, T: AsyncClient, S: Signer> {
+    scope: &'a ScopeClient<T, S>,
+    ix: RefreshIx<'a>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl<T, S> RetryableTx for RefreshTxSender<'_, T, S>
+where
+    T: AsyncClient,
+    S: Signer,
+{
+    async fn rebuild(&self) -> Result<VersionedTransaction> {
+        match self.ix {
+            RefreshIx::One(token) => self.scope.build_refresh_one_price_tx(token).await,
+            RefreshIx::List { tokens, lenient } => {
+                self.scope
+                    .build_refresh_price_list_tx(tokens, lenient)
+                    .await
             }
         }
+    }
 
-        Ok(signature)
+    async fn send_and_confirm(
+        &self,
+        tx: VersionedTransaction,
+    ) -> Result<Option<TransactionResult>> {
+        let (_signature, res) = self.scope.client.send_and_confirm_transaction(tx).await?;
+        Ok(res)
     }
 
-    #[tracing::instrument(skip(self))]
-    async fn refresh_price_list_print_res(&self, tokens: Vec<u16>) {
-        if let Err(err) = self.ix_refresh_price_list(&tokens).await {
-            warn!(?err, "Error while sending refresh price list transaction");
-            // Ok case already printed
+    async fn get_status(&self, signature: &Signature) -> Result<Option<TransactionResult>> {
+        let status = self
+            .scope
+            .get_rpc()
+            .get_signature_statuses(&[*signature])
+            .await?
+            .pop()
+            .flatten();
+        Ok(status.map(|status| status.err.map_or(Ok(()), Err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+    use scope::oracles::OracleType;
+
+    use super::*;
+    use crate::oracle_helpers::SingleAccountOracle;
+
+    fn entry(label: &str, mapping: Pubkey, max_age: clock::Slot) -> Box<dyn TokenEntry> {
+        let conf = TokenConfig {
+            label: label.to_string(),
+            oracle_type: OracleType::Pyth,
+            max_age: None,
+            price_max_age: None,
+            oracle_mapping: mapping,
+            share_mint: None,
+            sources: vec![],
+            price_bounds: None,
+        };
+        Box::new(SingleAccountOracle::new(&conf, max_age))
+    }
+
+    #[test]
+    fn tokens_health_staleness() {
+        let mut oracle_prices: OraclePrices = bytemuck::Zeroable::zeroed();
+        oracle_prices.prices[0].last_updated_slot = 990;
+        oracle_prices.prices[1].last_updated_slot = 950;
+        oracle_prices.prices[2].last_updated_slot = 1_005;
+
+        let mut tokens = TokenEntryList::default();
+        tokens.insert(1, entry("ETH/USD", Pubkey::new_unique(), 40));
+        tokens.insert(0, entry("SOL/USD", Pubkey::new_unique(), 10));
+        tokens.insert(2, entry("BTC/USD", Pubkey::default(), 10));
+        // Never refreshed
+        tokens.insert(3, entry("USDC/USD", Pubkey::new_unique(), 10));
+
+        let health = tokens_health(&oracle_prices, &tokens, 1_000);
+        let expected = [
+            (0, "SOL/USD", true, 10, false),
+            (1, "ETH/USD", true, 50, true),
+            (2, "BTC/USD", false, 0, false),
+            (3, "USDC/USD", true, 1_000, true),
+        ]
+        .map(|(token, pair, mapped, age_slots, stale)| TokenHealth {
+            token,
+            pair: pair.to_string(),
+            mapped,
+            age_slots,
+            stale,
+        });
+        assert_eq!(health, expected);
+    }
+
+    #[test]
+    fn prices_table_rows() {
+        let mut oracle_prices: OraclePrices = bytemuck::Zeroable::zeroed();
+        oracle_prices.prices[0] = DatedPrice {
+            price: Price {
+                value: 150_250,
+                exp: 3,
+            },
+            last_updated_slot: 990,
+            unix_timestamp: 1_720_000_000,
+            ..Default::default()
+        };
+        oracle_prices.prices[4] = DatedPrice {
+            price: Price { value: 2, exp: 0 },
+            last_updated_slot: 900,
+            unix_timestamp: 1_719_999_900,
+            ..Default::default()
+        };
+        let mut tokens = TokenEntryList::default();
+        tokens.insert(0, entry("SOL/USD", Pubkey::new_unique(), 10));
+        // Mapped but never refreshed
+        tokens.insert(1, entry("ETH/USD", Pubkey::new_unique(), 10));
+        let clock = Clock {
+            slot: 1_000,
+            unix_timestamp: 1_720_000_004,
+            ..Default::default()
+        };
+
+        let header = "token pair                                        value exp         slot    timestamp      age_s\n";
+        let sol = "    0 SOL/USD                                   150.250   3          990   1720000000          4\n";
+        let unknown = "    4 -                                               2   0          900   1719999900        104\n";
+        assert_eq!(
+            format_prices_table(&oracle_prices, &tokens, &clock, None),
+            format!("{header}{sol}{unknown}")
+        );
+        assert_eq!(
+            format_prices_table(&oracle_prices, &tokens, &clock, Some(60)),
+            format!("{header}{unknown}")
+        );
+    }
+
+    fn simulation(err: Option<TransactionError>, logs: &[String]) -> RpcSimulateTransactionResult {
+        RpcSimulateTransactionResult {
+            err,
+            logs: Some(logs.to_vec()),
+            accounts: None,
+            units_consumed: Some(200_000),
+            return_data: None,
         }
     }
+
+    #[test]
+    fn refresh_compute_units_from_logs() {
+        let program_id = Pubkey::new_unique();
+        let budget_program = Pubkey::new_unique();
+        let logs = [
+            format!("Program {budget_program} invoke [1]"),
+            format!("Program {budget_program} consumed 150 of 1400000 compute units"),
+            format!("Program {budget_program} success"),
+            format!("Program {program_id} invoke [1]"),
+            "Program log: Instruction: RefreshPriceList".to_string(),
+            format!("Program {program_id} consumed 84321 of 1399850 compute units"),
+            format!("Program {program_id} success"),
+        ];
+        assert_eq!(
+            program_compute_units(&simulation(None, &logs), &program_id).unwrap(),
+            84_321
+        );
+
+        // No log of the program
+        assert!(matches!(
+            program_compute_units(&simulation(None, &logs[..3]), &program_id),
+            Err(ScopeClientError::Other(_))
+        ));
+
+        // Exceeded compute budget
+        let err =
+            TransactionError::InstructionError(1, InstructionError::ComputationalBudgetExceeded);
+        assert!(matches!(
+            program_compute_units(&simulation(Some(err), &logs), &program_id),
+            Err(ScopeClientError::SimulationFailed(
+                TransactionError::InstructionError(1, _)
+            ))
+        ));
+    }
+
+    #[test]
+    fn smaller_refresh_chunks() {
+        let entries: TokenEntryList = (0..10_u16)
+            .map(|token| (token, entry("SOL/USD", Pubkey::new_unique(), 30)))
+            .collect();
+        let tokens: Vec<u16> = (0..10).collect();
+
+        let chunks = split_in_chunks(&entries, &tokens, MAX_REFRESH_CHUNK_SIZE).unwrap();
+        assert_eq!(chunks, [tokens.clone()]);
+
+        let chunks = split_in_chunks(&entries, &tokens, 4).unwrap();
+        assert_eq!(chunks, [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+        assert_eq!(chunks.concat(), tokens);
+
+        assert!(matches!(
+            split_in_chunks(&entries, &[10], 4),
+            Err(ScopeClientError::UnknownToken(10))
+        ));
+    }
+
+    #[test]
+    fn consecutive_token_batches() {
+        let entries: Vec<(u16, ())> = [0, 1, 2, 3, 4, 7, 8, 10]
+            .into_iter()
+            .map(|token| (token, ()))
+            .collect();
+        let batches: Vec<Vec<u16>> = consecutive_batches(&entries, 3)
+            .into_iter()
+            .map(|batch| batch.iter().map(|(token, _)| *token).collect())
+            .collect();
+        assert_eq!(batches, [vec![0, 1, 2], vec![3, 4], vec![7, 8], vec![10]]);
+        assert!(consecutive_batches::<()>(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn find_pair_by_label() {
+        let mut tokens = TokenEntryList::default();
+        tokens.insert(0, entry("SOL/USD", Pubkey::new_unique(), 10));
+        tokens.insert(1, entry("ETH/USD", Pubkey::new_unique(), 10));
+        tokens.insert(2, entry("BTC/USD", Pubkey::default(), 10));
+
+        assert_eq!(find_pair(&tokens, "ETH/USD").unwrap(), 1);
+        assert_eq!(find_pair(&tokens, "sol/usd").unwrap(), 0);
+        assert!(matches!(
+            find_pair(&tokens, "SOL/USDC"),
+            Err(ScopeClientError::UnknownPair(pair)) if pair == "SOL/USDC"
+        ));
+        assert!(matches!(
+            find_pair(&tokens, "BTC/USD"),
+            Err(ScopeClientError::UnmappedPair { token: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn find_duplicated_pair() {
+        let mut tokens = TokenEntryList::default();
+        tokens.insert(7, entry("SOL/USD", Pubkey::new_unique(), 10));
+        tokens.insert(1, entry("ETH/USD", Pubkey::new_unique(), 10));
+        tokens.insert(3, entry("sol/USD", Pubkey::new_unique(), 10));
+
+        // Duplicates are reported whatever their case instead of picking one of them
+        assert!(matches!(
+            find_pair(&tokens, "SOL/USD"),
+            Err(ScopeClientError::AmbiguousPair { tokens, .. }) if tokens == vec![3, 7]
+        ));
+        assert_eq!(find_pair(&tokens, "ETH/USD").unwrap(), 1);
+    }
 }
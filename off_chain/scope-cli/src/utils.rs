@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use anchor_client::solana_sdk::{clock::Clock, pubkey::Pubkey, sysvar::SysvarId};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use orbit_link::async_client::AsyncClient;
 use scope::Price;
 
@@ -24,9 +24,195 @@ pub fn price_to_f64(price: &Price) -> f64 {
     (price.value as f64) * 10_f64.powi(-(price.exp as i32))
 }
 
+/// Format a price as a decimal number rounded to `sig_digits` significant digits
+///
+/// The value is formatted from its digits without going through f64, so large values and
+/// small exponents are displayed exactly. Trailing zeros of the fractional part are removed and
+/// at least one significant digit is kept.
+pub fn format_price(price: &Price, sig_digits: usize) -> String {
+    let sig_digits = sig_digits.max(1);
+    let mut value = u128::from(price.value);
+    let mut exp = i64::try_from(price.exp).unwrap_or(i64::MAX);
+
+    // Round half up to the significant digits
+    let digits = value.to_string().len();
+    if digits > sig_digits {
+        let dropped = u32::try_from(digits - sig_digits).unwrap();
+        let divisor = 10_u128.pow(dropped);
+        value = (value + divisor / 2) / divisor;
+        exp -= i64::from(dropped);
+    }
+
+    if value == 0 {
+        return "0".to_string();
+    }
+    if exp <= 0 {
+        let zeros = usize::try_from(-exp).unwrap();
+        return format!("{value}{}", "0".repeat(zeros));
+    }
+    let frac_len = usize::try_from(exp).unwrap();
+    let digits = format!("{value:0>width$}", width = frac_len + 1);
+    let (int_part, frac_part) = digits.split_at(digits.len() - frac_len);
+    let frac_part = frac_part.trim_end_matches('0');
+    if frac_part.is_empty() {
+        int_part.to_string()
+    } else {
+        format!("{int_part}.{frac_part}")
+    }
+}
+
+/// Rescale a price to the given exponent
+///
+/// Scaling down truncates the value, scaling up fails if the value overflows.
+pub fn normalize_price(price: &Price, target_exp: u8) -> Result<Price> {
+    let target_exp = u64::from(target_exp);
+    let value = if target_exp >= price.exp {
+        u32::try_from(target_exp - price.exp)
+            .ok()
+            .and_then(|diff| 10_u64.checked_pow(diff))
+            .and_then(|factor| price.value.checked_mul(factor))
+            .ok_or_else(|| anyhow!("Price {price:?} overflows at exponent {target_exp}"))?
+    } else {
+        // A divisor too large to be represented is always above the value
+        u32::try_from(price.exp - target_exp)
+            .ok()
+            .and_then(|diff| 10_u64.checked_pow(diff))
+            .map_or(0, |divisor| price.value / divisor)
+    };
+    Ok(Price {
+        value,
+        exp: target_exp,
+    })
+}
+
+/// Ratio between two prices with different exponents
+///
+/// Used for display only
+pub fn price_ratio(a: &Price, b: &Price) -> f64 {
+    price_to_f64(a) / price_to_f64(b)
+}
+
 /// Get current clock
 pub async fn get_clock(rpc: &impl AsyncClient) -> Result<Clock> {
     let clock = rpc.get_account(&Clock::id()).await?.deserialize_data()?;
 
     Ok(clock)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_price_up() {
+        let price = Price {
+            value: 12345,
+            exp: 2,
+        };
+        assert_eq!(
+            normalize_price(&price, 8).unwrap(),
+            Price {
+                value: 12_345_000_000,
+                exp: 8
+            }
+        );
+        assert_eq!(normalize_price(&price, 2).unwrap(), price);
+    }
+
+    #[test]
+    fn normalize_price_down() {
+        let price = Price {
+            value: 123_456_789,
+            exp: 8,
+        };
+        assert_eq!(
+            normalize_price(&price, 2).unwrap(),
+            Price { value: 123, exp: 2 }
+        );
+        assert_eq!(
+            normalize_price(&price, 0).unwrap(),
+            Price { value: 1, exp: 0 }
+        );
+
+        // The divisor does not fit in a u64
+        let price = Price {
+            value: u64::MAX,
+            exp: 30,
+        };
+        assert_eq!(
+            normalize_price(&price, 0).unwrap(),
+            Price { value: 0, exp: 0 }
+        );
+    }
+
+    #[test]
+    fn normalize_price_overflow() {
+        let price = Price {
+            value: u64::MAX / 10 + 1,
+            exp: 0,
+        };
+        assert!(normalize_price(&price, 1).is_err());
+        assert!(normalize_price(&Price { value: 1, exp: 0 }, 20).is_err());
+        assert!(normalize_price(&Price { value: 1, exp: 0 }, 19).is_ok());
+    }
+
+    #[test]
+    fn format_price_with_exponent() {
+        let price = Price {
+            value: 2_345_678_900,
+            exp: 8,
+        };
+        assert_eq!(format_price(&price, 10), "23.456789");
+        assert_eq!(format_price(&price, 6), "23.4568");
+        assert_eq!(format_price(&price, 2), "23");
+        // Rounding carries to a new digit
+        let price = Price {
+            value: 99_999_999,
+            exp: 8,
+        };
+        assert_eq!(format_price(&price, 4), "1");
+    }
+
+    #[test]
+    fn format_price_without_exponent() {
+        let price = Price { value: 150, exp: 0 };
+        assert_eq!(format_price(&price, 6), "150");
+        assert_eq!(format_price(&price, 2), "150");
+        assert_eq!(format_price(&price, 1), "200");
+        let price = Price {
+            value: u64::MAX,
+            exp: 0,
+        };
+        assert_eq!(format_price(&price, 20), "18446744073709551615");
+        assert_eq!(format_price(&price, 3), "18400000000000000000");
+        assert_eq!(format_price(&Price { value: 0, exp: 0 }, 6), "0");
+    }
+
+    #[test]
+    fn format_small_price_exactly() {
+        let price = Price {
+            value: 123_456_789_012_345_678,
+            exp: 30,
+        };
+        assert_eq!(format_price(&price, 18), "0.000000000000123456789012345678");
+        // f64 does not have enough precision for these digits
+        assert_ne!(
+            format!("{:.30}", price_to_f64(&price)),
+            "0.000000000000123456789012345678"
+        );
+        assert_eq!(format_price(&price, 3), "0.000000000000123");
+    }
+
+    #[test]
+    fn ratio_of_prices_with_different_exponents() {
+        let sol = Price {
+            value: 20_000_000,
+            exp: 6,
+        };
+        let usdc = Price {
+            value: 99_990_000,
+            exp: 8,
+        };
+        assert!((price_ratio(&sol, &usdc) - 20.0 / 0.9999).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,725 @@
+//! End to end test of the crank loop: `ScopeClient` -> scope program -> `ScopeClient`
+//!
+//! The scope program runs in a `solana-program-test` bank and the client talks to it
+//! through the [`orbit_link`] `BanksClient` implementation.
+
+use std::num::NonZeroUsize;
+
+use anchor_client::solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, pubkey, pubkey::Pubkey,
+    signature::Keypair,
+};
+use nohash_hasher::IntMap;
+use orbit_link::OrbitLink;
+use pyth_sdk_solana::state::{AccountType, PriceAccount, PriceStatus, PriceType, MAGIC, VERSION_2};
+use scope::{
+    anchor_lang::AccountDeserialize,
+    oracles::{fixed_price, pyth, scope_twap, OracleType},
+    Configuration, OracleMappings, OraclePrices, Price, UpdateMappingConfigMode,
+};
+use scope_client::{
+    config::{PriceBounds, TokenConfig},
+    scope_client::{MappingDiff, NamedMapping, PriceDivergence, RefreshOutcome, VerifyResult},
+    ScopeClient, ScopeClientError, ScopeConfig,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use tokio::sync::Mutex;
+
+const FEED_NAME: &str = "e2e_feed";
+const MOCK_PYTH_ACCOUNT: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+const MOCK_PYTH_OWNER: Pubkey = pyth::PYTH_PROGRAM_ID;
+
+fn mock_pyth_account(price: &Price) -> Account {
+    let mut price_account: PriceAccount = bytemuck::Zeroable::zeroed();
+    price_account.magic = MAGIC;
+    price_account.ver = VERSION_2;
+    price_account.atype = AccountType::Price as u32;
+    price_account.ptype = PriceType::Price;
+    price_account.expo = -i32::try_from(price.exp).unwrap();
+    price_account.agg.price = i64::try_from(price.value).unwrap();
+    price_account.agg.status = PriceStatus::Trading;
+
+    Account {
+        lamports: u32::MAX.into(),
+        data: bytemuck::bytes_of(&price_account).to_vec(),
+        owner: MOCK_PYTH_OWNER,
+        ..Account::default()
+    }
+}
+
+fn scope_config() -> ScopeConfig {
+    let mut tokens = IntMap::default();
+    tokens.insert(
+        0,
+        pyth_token_config("SOL/USD".to_string(), MOCK_PYTH_ACCOUNT),
+    );
+    ScopeConfig {
+        default_max_age: 30,
+        tokens,
+    }
+}
+
+fn pyth_token_config(label: String, oracle_mapping: Pubkey) -> TokenConfig {
+    TokenConfig {
+        label,
+        oracle_type: OracleType::Pyth,
+        max_age: None,
+        price_max_age: None,
+        oracle_mapping,
+        share_mint: None,
+        sources: vec![],
+        price_bounds: None,
+    }
+}
+
+/// Start a bank with the scope program and a client with the mock pyth price mapped
+async fn setup_scope_client(mock_price: &Price) -> ScopeClient<Mutex<BanksClient>, Keypair> {
+    setup_scope_client_with(
+        &scope_config(),
+        &[(MOCK_PYTH_ACCOUNT, mock_pyth_account(mock_price))],
+    )
+    .await
+}
+
+/// Start a bank with the scope program and the given accounts, and a client with the
+/// given configuration mapped
+async fn setup_scope_client_with(
+    config: &ScopeConfig,
+    accounts: &[(Pubkey, Account)],
+) -> ScopeClient<Mutex<BanksClient>, Keypair> {
+    let (banks_client, payer, _) = scope_program_test(accounts).start().await;
+    init_scope_client(banks_client, payer, config).await
+}
+
+fn scope_program_test(accounts: &[(Pubkey, Account)]) -> ProgramTest {
+    let mut program_test = ProgramTest::new("scope", scope::ID, processor!(scope::entry));
+    for (address, account) in accounts {
+        program_test.add_account(*address, account.clone());
+    }
+    program_test
+}
+
+/// Initialize the scope feed and upload the given configuration
+async fn init_scope_client(
+    banks_client: BanksClient,
+    payer: Keypair,
+    config: &ScopeConfig,
+) -> ScopeClient<Mutex<BanksClient>, Keypair> {
+    let orbit_link = OrbitLink::new(
+        Mutex::new(banks_client),
+        payer,
+        None,
+        CommitmentConfig::processed(),
+    );
+
+    let mut scope = ScopeClient::new_init_program(orbit_link, &scope::ID, FEED_NAME)
+        .await
+        .unwrap();
+    scope.set_local_mapping(config).await.unwrap();
+    scope.upload_oracle_mapping().await.unwrap();
+    scope
+}
+
+#[tokio::test]
+async fn test_crank_refresh_read_loop() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+
+    let scope = setup_scope_client(&mock_price).await;
+
+    scope.refresh_all_prices().await.unwrap();
+
+    let prices = scope.get_prices().await.unwrap();
+    let dated_price = prices.prices[0];
+    assert_eq!(dated_price.price, mock_price);
+    assert_eq!(dated_price.index, 0);
+}
+
+// In dry-run mode the refresh is simulated, no price is updated
+#[tokio::test]
+async fn test_dry_run_refresh() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+
+    let mut scope = setup_scope_client(&mock_price).await;
+    scope.set_dry_run(true);
+
+    let simulation = match scope.ix_refresh_one_price(0).await.unwrap() {
+        RefreshOutcome::Simulated(simulation) => simulation,
+        outcome => panic!("Refresh was not simulated: {outcome:?}"),
+    };
+    assert_eq!(simulation.err, None);
+    assert!(simulation.units_consumed.unwrap() > 0);
+    assert!(simulation
+        .logs
+        .unwrap()
+        .iter()
+        .any(|log| log.contains("Instruction: RefreshOnePrice")));
+
+    scope.refresh_all_prices().await.unwrap();
+
+    let prices = scope.get_prices().await.unwrap();
+    assert_eq!(prices.prices[0].last_updated_slot, 0);
+    assert_eq!(prices.prices[0].price, Price::default());
+}
+
+// All the chunks of a refresh are sent, even when one of them fails
+#[tokio::test]
+async fn test_refresh_chunks_failure_is_isolated() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    // 50 single account tokens: chunks of 24, 24 and 2 tokens
+    let pyth_accounts: Vec<Pubkey> = (0..50).map(|_| Pubkey::new_unique()).collect();
+    let mut config = ScopeConfig {
+        default_max_age: 30,
+        tokens: IntMap::default(),
+    };
+    for (id, account) in pyth_accounts.iter().enumerate() {
+        config.tokens.insert(
+            id.try_into().unwrap(),
+            pyth_token_config(format!("Token {id}"), *account),
+        );
+    }
+    let accounts: Vec<(Pubkey, Account)> = pyth_accounts
+        .iter()
+        .map(|account| (*account, mock_pyth_account(&mock_price)))
+        .collect();
+
+    let mut scope = setup_scope_client_with(&config, &accounts).await;
+    scope.set_max_in_flight_refreshes(NonZeroUsize::new(2).unwrap());
+
+    // The local mapping of the first token no longer matches the onchain one,
+    // its chunk is rejected by the program
+    config.tokens.insert(
+        0,
+        pyth_token_config("Token 0".to_string(), MOCK_PYTH_ACCOUNT),
+    );
+    scope.set_local_mapping(&config).await.unwrap();
+
+    let chunks = scope.refresh_price_range(0, 49).await.unwrap();
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].tokens, (0..24).collect::<Vec<u16>>());
+    assert!(matches!(
+        chunks[0].result,
+        Err(ScopeClientError::TransactionFailed { .. })
+    ));
+    assert!(chunks[1..]
+        .iter()
+        .all(|chunk| matches!(chunk.result, Ok(RefreshOutcome::Sent(_)))));
+
+    let prices = scope.get_prices().await.unwrap();
+    for id in 0..24 {
+        assert_eq!(prices.prices[id].last_updated_slot, 0);
+    }
+    for id in 24..50 {
+        assert_eq!(prices.prices[id].price, mock_price);
+    }
+}
+
+#[tokio::test]
+async fn test_refresh_all_prices_lenient() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let pyth_accounts: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    let mut config = ScopeConfig {
+        default_max_age: 30,
+        tokens: IntMap::default(),
+    };
+    for (id, account) in pyth_accounts.iter().enumerate() {
+        config.tokens.insert(
+            id.try_into().unwrap(),
+            pyth_token_config(format!("Token {id}"), *account),
+        );
+    }
+    let accounts: Vec<(Pubkey, Account)> = pyth_accounts
+        .iter()
+        .map(|account| (*account, mock_pyth_account(&mock_price)))
+        .collect();
+
+    let mut scope = setup_scope_client_with(&config, &accounts).await;
+
+    // The local mapping of the first token no longer matches the onchain one
+    config.tokens.insert(
+        0,
+        pyth_token_config("Token 0".to_string(), MOCK_PYTH_ACCOUNT),
+    );
+    scope.set_local_mapping(&config).await.unwrap();
+
+    // Both tokens are in the same chunk, it is rejected by the strict refresh
+    let chunks = scope.refresh_all_prices().await.unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert!(matches!(
+        chunks[0].result,
+        Err(ScopeClientError::TransactionFailed { .. })
+    ));
+
+    // The lenient refresh is sent, the mismatched token is not refreshed
+    let chunks = scope.refresh_all_prices_lenient().await.unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert!(matches!(chunks[0].result, Ok(RefreshOutcome::Sent(_))));
+    let prices = scope.get_prices().await.unwrap();
+    assert_eq!(prices.prices[0].last_updated_slot, 0);
+}
+
+// The diff lists the tokens mapped to different accounts locally and on-chain
+#[tokio::test]
+async fn test_diff_oracle_mapping() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let mut scope = setup_scope_client(&mock_price).await;
+
+    assert_eq!(scope.diff_oracle_mapping().await.unwrap(), vec![]);
+
+    // Token 0 is remapped and token 2 is only known locally
+    let remapped_account = Pubkey::new_unique();
+    let new_account = Pubkey::new_unique();
+    let mut config = scope_config();
+    config.tokens.insert(
+        0,
+        pyth_token_config("SOL/USD".to_string(), remapped_account),
+    );
+    config
+        .tokens
+        .insert(2, pyth_token_config("New".to_string(), new_account));
+    scope.set_local_mapping(&config).await.unwrap();
+
+    assert_eq!(
+        scope.diff_oracle_mapping().await.unwrap(),
+        vec![
+            MappingDiff {
+                token: 0,
+                local: Some(remapped_account),
+                remote: MOCK_PYTH_ACCOUNT,
+            },
+            MappingDiff {
+                token: 2,
+                local: Some(new_account),
+                remote: Pubkey::default(),
+            },
+        ]
+    );
+
+    // Token 0 is only known on-chain
+    scope
+        .set_local_mapping(&ScopeConfig {
+            default_max_age: 30,
+            tokens: IntMap::default(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        scope.diff_oracle_mapping().await.unwrap(),
+        vec![MappingDiff {
+            token: 0,
+            local: None,
+            remote: MOCK_PYTH_ACCOUNT,
+        }]
+    );
+
+    // Nothing was updated on-chain
+    scope.set_local_mapping(&scope_config()).await.unwrap();
+    assert_eq!(scope.diff_oracle_mapping().await.unwrap(), vec![]);
+}
+
+// The local labels are listed with the on-chain accounts, unmapped tokens have no account
+#[tokio::test]
+async fn test_get_named_mapping() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let mut scope = setup_scope_client(&mock_price).await;
+
+    // Token 2 is only known locally, not uploaded
+    let mut config = scope_config();
+    config.tokens.insert(
+        2,
+        pyth_token_config("ETH/USD".to_string(), Pubkey::new_unique()),
+    );
+    scope.set_local_mapping(&config).await.unwrap();
+
+    assert_eq!(
+        scope.get_named_mapping().await.unwrap(),
+        vec![
+            NamedMapping {
+                token: 0,
+                pair: "SOL/USD".to_string(),
+                oracle: Some(MOCK_PYTH_ACCOUNT),
+            },
+            NamedMapping {
+                token: 2,
+                pair: "ETH/USD".to_string(),
+                oracle: None,
+            },
+        ]
+    );
+}
+
+// Feeds opened from the same client are refreshed independently
+#[tokio::test]
+async fn test_multiple_feeds() {
+    let sol_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let eth_price = Price {
+        value: 345_678_900_000,
+        exp: 8,
+    };
+    let eth_account = Pubkey::new_unique();
+    let mut scope = setup_scope_client_with(
+        &scope_config(),
+        &[
+            (MOCK_PYTH_ACCOUNT, mock_pyth_account(&sol_price)),
+            (eth_account, mock_pyth_account(&eth_price)),
+        ],
+    )
+    .await;
+    scope.set_dry_run(true);
+
+    let mut other = scope.init_feed("e2e_feed_2").await.unwrap();
+    assert_eq!(other.get_feed_name(), "e2e_feed_2");
+    let mut config = scope_config();
+    config
+        .tokens
+        .insert(0, pyth_token_config("ETH/USD".to_string(), eth_account));
+    other.set_local_mapping(&config).await.unwrap();
+    other.upload_oracle_mapping().await.unwrap();
+
+    // Settings are inherited but each client keeps its own
+    scope.set_dry_run(false);
+    scope.refresh_all_prices().await.unwrap();
+    other.refresh_all_prices().await.unwrap();
+    assert_eq!(scope.get_prices().await.unwrap().prices[0].price, sol_price);
+    assert_eq!(
+        other.get_prices().await.unwrap().prices[0].price,
+        Price::default()
+    );
+
+    other.set_dry_run(false);
+    other.refresh_all_prices().await.unwrap();
+    assert_eq!(other.get_prices().await.unwrap().prices[0].price, eth_price);
+    assert_eq!(scope.get_prices().await.unwrap().prices[0].price, sol_price);
+
+    // The feed can be reopened by name
+    let reopened = scope.open_feed("e2e_feed_2").await.unwrap();
+    assert_eq!(
+        reopened.get_prices().await.unwrap().prices[0].price,
+        eth_price
+    );
+}
+
+// The price bounds of the configuration are uploaded and enforced at refresh
+#[tokio::test]
+async fn test_price_bounds_upload() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let mut config = scope_config();
+    let bounds = PriceBounds {
+        min: 100,
+        max: 200,
+        exp: 0,
+    };
+    config.tokens.get_mut(&0).unwrap().price_bounds = Some(bounds);
+    let mut scope = setup_scope_client_with(
+        &config,
+        &[(MOCK_PYTH_ACCOUNT, mock_pyth_account(&mock_price))],
+    )
+    .await;
+
+    let mut downloaded = scope.open_feed(FEED_NAME).await.unwrap();
+    downloaded.download_oracle_mapping(30).await.unwrap();
+    assert_eq!(
+        downloaded.get_local_mapping().unwrap().tokens[&0].price_bounds,
+        Some(bounds)
+    );
+
+    // 23.45 is below the lower bound, the token is skipped
+    scope.refresh_all_prices().await.unwrap();
+    assert_eq!(
+        scope.get_prices().await.unwrap().prices[0].price,
+        Price::default()
+    );
+
+    config.tokens.get_mut(&0).unwrap().price_bounds = None;
+    scope.set_local_mapping(&config).await.unwrap();
+    scope.upload_oracle_mapping().await.unwrap();
+    scope.ix_refresh_one_price(0).await.unwrap();
+    assert_eq!(
+        scope.get_prices().await.unwrap().prices[0].price,
+        mock_price
+    );
+}
+
+// Bounds disabled on-chain with a `u64::MAX` upper bound match a configuration without bounds
+#[tokio::test]
+async fn test_disabled_price_bounds_are_not_uploaded() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let mut ctx = scope_program_test(&[(MOCK_PYTH_ACCOUNT, mock_pyth_account(&mock_price))])
+        .start_with_context()
+        .await;
+    let payer = Keypair::from_bytes(&ctx.payer.to_bytes()).unwrap();
+    let scope = init_scope_client(ctx.banks_client.clone(), payer, &scope_config()).await;
+
+    let disabled_bounds = [0, u64::MAX, 0]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    scope
+        .ix_update_mapping_config(0, UpdateMappingConfigMode::PriceBounds, disabled_bounds)
+        .await
+        .unwrap();
+    scope.upload_oracle_mapping().await.unwrap();
+
+    let (configuration, _) =
+        Pubkey::find_program_address(&[b"conf", FEED_NAME.as_bytes()], &scope::ID);
+    let configuration = ctx
+        .banks_client
+        .get_account(configuration)
+        .await
+        .unwrap()
+        .unwrap();
+    let configuration = Configuration::try_deserialize(&mut configuration.data.as_slice()).unwrap();
+    let mappings_account = ctx
+        .banks_client
+        .get_account(configuration.oracle_mappings)
+        .await
+        .unwrap()
+        .unwrap();
+    let mappings = OracleMappings::try_deserialize(&mut mappings_account.data.as_slice()).unwrap();
+    // Not overwritten by the upload
+    assert_eq!(
+        mappings.price_bounds[0],
+        [
+            Price::default(),
+            Price {
+                value: u64::MAX,
+                exp: 0
+            }
+        ]
+    );
+}
+
+// Each mapping account of the local mapping is checked against its oracle type
+#[tokio::test]
+async fn test_verify_mapping_accounts() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let garbage_account = Pubkey::new_unique();
+    let mut scope = setup_scope_client_with(
+        &scope_config(),
+        &[
+            (MOCK_PYTH_ACCOUNT, mock_pyth_account(&mock_price)),
+            (
+                garbage_account,
+                Account {
+                    lamports: u32::MAX.into(),
+                    data: vec![1, 2, 3, 4],
+                    owner: MOCK_PYTH_OWNER,
+                    ..Account::default()
+                },
+            ),
+        ],
+    )
+    .await;
+
+    let mut config = scope_config();
+    // Existing account of another oracle type
+    let mut switchboard_config = pyth_token_config("Wrong type".to_string(), MOCK_PYTH_ACCOUNT);
+    switchboard_config.oracle_type = OracleType::SwitchboardV2;
+    config.tokens.insert(1, switchboard_config);
+    config
+        .tokens
+        .insert(2, pyth_token_config("Garbage".to_string(), garbage_account));
+    config.tokens.insert(
+        3,
+        pyth_token_config("Missing".to_string(), Pubkey::new_unique()),
+    );
+    // No account to fetch, the price is encoded in the pubkey
+    let mut fixed_config = pyth_token_config(
+        "Fixed".to_string(),
+        fixed_price::price_to_mapping_pubkey(&mock_price),
+    );
+    fixed_config.oracle_type = OracleType::FixedPrice;
+    config.tokens.insert(4, fixed_config);
+    scope.set_local_mapping(&config).await.unwrap();
+
+    assert_eq!(
+        scope.verify_mapping_accounts().await.unwrap(),
+        vec![
+            (0, VerifyResult::Ok),
+            (1, VerifyResult::WrongType),
+            (2, VerifyResult::WrongType),
+            (3, VerifyResult::Missing),
+            (4, VerifyResult::Ok),
+        ]
+    );
+}
+
+// The watchdog alerts on a stored price that does not match its source
+#[tokio::test]
+async fn test_check_prices_detects_wrong_stored_price() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let mut ctx = scope_program_test(&[(MOCK_PYTH_ACCOUNT, mock_pyth_account(&mock_price))])
+        .start_with_context()
+        .await;
+    let payer = Keypair::from_bytes(&ctx.payer.to_bytes()).unwrap();
+    let scope = init_scope_client(ctx.banks_client.clone(), payer, &scope_config()).await;
+
+    // Never refreshed prices are not checked
+    assert_eq!(scope.check_prices(100).await.unwrap(), vec![]);
+
+    scope.refresh_all_prices().await.unwrap();
+    assert_eq!(scope.check_prices(100).await.unwrap(), vec![]);
+
+    // Overwrite the stored price with a value 2% above the source
+    let (configuration, _) =
+        Pubkey::find_program_address(&[b"conf", FEED_NAME.as_bytes()], &scope::ID);
+    let configuration = ctx
+        .banks_client
+        .get_account(configuration)
+        .await
+        .unwrap()
+        .unwrap();
+    let configuration = Configuration::try_deserialize(&mut configuration.data.as_slice()).unwrap();
+    let mut prices_account = ctx
+        .banks_client
+        .get_account(configuration.oracle_prices)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut prices = OraclePrices::try_deserialize(&mut prices_account.data.as_slice()).unwrap();
+    let wrong_price = Price {
+        value: 2_392_592_478,
+        exp: 8,
+    };
+    prices.prices[0].price = wrong_price;
+    prices_account.data[8..].copy_from_slice(bytemuck::bytes_of(&prices));
+    ctx.set_account(&configuration.oracle_prices, &prices_account.into());
+
+    assert_eq!(
+        scope.check_prices(100).await.unwrap(),
+        vec![PriceDivergence {
+            token: 0,
+            stored: wrong_price,
+            recomputed: mock_price,
+            deviation_bps: Some(200),
+        }]
+    );
+    // Within a wider tolerance
+    assert_eq!(scope.check_prices(200).await.unwrap(), vec![]);
+}
+
+// The watchdog recomputes the EMA prices from the TWAP account of the feed
+#[tokio::test]
+async fn test_check_prices_scope_twap() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let mut config = scope_config();
+    config.tokens.insert(
+        1,
+        TokenConfig {
+            oracle_type: OracleType::ScopeTwap,
+            oracle_mapping: scope_twap::source_to_mapping_pubkey(0),
+            ..pyth_token_config("SOL/USD EMA".to_string(), Pubkey::default())
+        },
+    );
+    let mut ctx = scope_program_test(&[(MOCK_PYTH_ACCOUNT, mock_pyth_account(&mock_price))])
+        .start_with_context()
+        .await;
+    let orbit_link = OrbitLink::new(
+        Mutex::new(ctx.banks_client.clone()),
+        Keypair::from_bytes(&ctx.payer.to_bytes()).unwrap(),
+        None,
+        CommitmentConfig::processed(),
+    );
+    let mut scope = ScopeClient::new_init_program(orbit_link, &scope::ID, FEED_NAME)
+        .await
+        .unwrap();
+    scope.init_oracle_twaps_if_needed().await.unwrap();
+    scope.set_local_mapping(&config).await.unwrap();
+    scope.upload_oracle_mapping().await.unwrap();
+
+    // The source is refreshed before its EMA
+    scope.refresh_price_range(0, 1).await.unwrap();
+    let prices = scope.get_prices().await.unwrap();
+    assert_eq!(prices.prices[1].price, mock_price);
+    assert_eq!(scope.check_prices(100).await.unwrap(), vec![]);
+
+    // Overwrite the stored EMA price with a value 2% above its source
+    let (configuration, _) =
+        Pubkey::find_program_address(&[b"conf", FEED_NAME.as_bytes()], &scope::ID);
+    let configuration = ctx
+        .banks_client
+        .get_account(configuration)
+        .await
+        .unwrap()
+        .unwrap();
+    let configuration = Configuration::try_deserialize(&mut configuration.data.as_slice()).unwrap();
+    let mut prices_account = ctx
+        .banks_client
+        .get_account(configuration.oracle_prices)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut prices = OraclePrices::try_deserialize(&mut prices_account.data.as_slice()).unwrap();
+    let wrong_price = Price {
+        value: 2_392_592_478,
+        exp: 8,
+    };
+    prices.prices[1].price = wrong_price;
+    prices_account.data[8..].copy_from_slice(bytemuck::bytes_of(&prices));
+    ctx.set_account(&configuration.oracle_prices, &prices_account.into());
+
+    assert_eq!(
+        scope.check_prices(100).await.unwrap(),
+        vec![PriceDivergence {
+            token: 1,
+            stored: wrong_price,
+            recomputed: mock_price,
+            deviation_bps: Some(200),
+        }]
+    );
+}
+
+// Prices can be read by their pair label once refreshed
+#[tokio::test]
+async fn test_get_price_by_pair() {
+    let mock_price = Price {
+        value: 2_345_678_900,
+        exp: 8,
+    };
+    let scope = setup_scope_client(&mock_price).await;
+    scope.refresh_all_prices().await.unwrap();
+
+    assert_eq!(scope.list_pairs(), vec![(0, "SOL/USD".to_string())]);
+    let dated_price = scope.get_price_by_pair("sol/usd").await.unwrap();
+    assert_eq!(dated_price.price, mock_price);
+    assert!(matches!(
+        scope.get_price_by_pair("ETH/USD").await,
+        Err(ScopeClientError::UnknownPair(_))
+    ));
+}
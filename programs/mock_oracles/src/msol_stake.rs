@@ -5,24 +5,17 @@ use anchor_lang::{
 
 use self::msol_stake_pool::{StakeSystem, State};
 
+/// Discriminator of the Marinade `State` account
+const STATE_DISCRIMINATOR: [u8; 8] = [216, 146, 107, 94, 104, 75, 182, 177];
+/// `msol_price` is a fixed point number with 32 fractional bits
+const MSOL_PRICE_DENOMINATOR: u128 = 1 << 32;
+
 pub fn initialize(
     stake_pool_account: &AccountInfo,
     mint_total_supply: u64,
     total_liquidity: u64,
 ) -> Result<()> {
-    let pool = State {
-        available_reserve_balance: total_liquidity,
-        msol_supply: mint_total_supply,
-        stake_system: StakeSystem {
-            last_stake_delta_epoch: Clock::get()?.epoch,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-    let mut acc = stake_pool_account.data.borrow_mut();
-    let mut data = &mut acc.as_mut()[8..];
-    pool.serialize(&mut data)?;
-    Ok(())
+    write_state(stake_pool_account, mint_total_supply, total_liquidity)
 }
 
 pub fn update(
@@ -30,9 +23,23 @@ pub fn update(
     mint_total_supply: u64,
     total_liquidity: u64,
 ) -> Result<()> {
+    write_state(stake_pool_account, mint_total_supply, total_liquidity)
+}
+
+fn write_state(
+    stake_pool_account: &AccountInfo,
+    mint_total_supply: u64,
+    total_liquidity: u64,
+) -> Result<()> {
+    let msol_price = if mint_total_supply == 0 {
+        MSOL_PRICE_DENOMINATOR
+    } else {
+        u128::from(total_liquidity) * MSOL_PRICE_DENOMINATOR / u128::from(mint_total_supply)
+    };
     let pool = State {
         available_reserve_balance: total_liquidity,
         msol_supply: mint_total_supply,
+        msol_price: msol_price.try_into().unwrap(),
         stake_system: StakeSystem {
             last_stake_delta_epoch: Clock::get()?.epoch,
             ..Default::default()
@@ -40,6 +47,7 @@ pub fn update(
         ..Default::default()
     };
     let mut acc = stake_pool_account.data.borrow_mut();
+    acc[..8].copy_from_slice(&STATE_DISCRIMINATOR);
     let mut data = &mut acc.as_mut()[8..];
     pool.serialize(&mut data)?;
     Ok(())
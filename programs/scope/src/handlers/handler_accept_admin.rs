@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::ScopeError;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+    #[account(mut, seeds = [b"conf", feed_name.as_bytes()], bump)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+}
+
+/// Make the pending admin proposed with [`crate::handlers::handler_set_admin::SetAdmin`] the
+/// admin of the feed.
+pub fn process(ctx: Context<AcceptAdmin>, _: String) -> Result<()> {
+    let pending_admin = ctx.accounts.pending_admin.key();
+    let mut configuration = ctx.accounts.configuration.load_mut()?;
+    if configuration.pending_admin == Pubkey::default()
+        || configuration.pending_admin != pending_admin
+    {
+        msg!(
+            "Signer {} is not the pending admin {}",
+            pending_admin,
+            configuration.pending_admin
+        );
+        return err!(ScopeError::NotPendingAdmin);
+    }
+    msg!(
+        "Set admin from {} to {}",
+        configuration.admin,
+        pending_admin
+    );
+    configuration.admin = pending_admin;
+    configuration.pending_admin = Pubkey::default();
+    Ok(())
+}
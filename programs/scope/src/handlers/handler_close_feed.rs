@@ -0,0 +1,58 @@
+use anchor_lang::{prelude::*, AccountsClose};
+
+use crate::{oracles::check_context, ScopeError};
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct CloseFeed<'info> {
+    pub admin: Signer<'info>,
+
+    /// CHECK: Receives the lamports of the closed accounts, can be any account
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    // Accounts not referenced by the configuration of the feed are rejected, so the accounts of
+    // another feed cannot be closed
+    #[account(mut, seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings, has_one = oracle_prices, has_one = tokens_metadata, close = destination)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    #[account(mut, close = destination)]
+    pub oracle_mappings: AccountLoader<'info, crate::OracleMappings>,
+
+    #[account(mut, close = destination)]
+    pub oracle_prices: AccountLoader<'info, crate::OraclePrices>,
+
+    #[account(mut, close = destination)]
+    pub tokens_metadata: AccountLoader<'info, crate::TokenMetadatas>,
+
+    /// Required when the feed has a TWAP account, closed with the feed
+    #[account(mut)]
+    pub oracle_twaps: Option<AccountLoader<'info, crate::OracleTwaps>>,
+}
+
+/// Close the accounts of a feed, their lamports are sent to `destination`
+///
+/// The accounts are closed when the instruction exits (see the `close` constraints), except the
+/// optional TWAP account closed here.
+pub fn process(ctx: Context<CloseFeed>, feed_name: String) -> Result<()> {
+    check_context(&ctx)?;
+
+    let feed_twaps = ctx.accounts.configuration.load()?.oracle_twaps;
+    match &ctx.accounts.oracle_twaps {
+        Some(oracle_twaps) if oracle_twaps.key() == feed_twaps => {
+            oracle_twaps.close(ctx.accounts.destination.to_account_info())?;
+        }
+        None if feed_twaps == Pubkey::default() => {}
+        _ => {
+            msg!(
+                "The TWAP account of the feed must be provided: {}",
+                feed_twaps
+            );
+            return err!(ScopeError::UnexpectedAccount);
+        }
+    }
+
+    msg!("Close feed {}", feed_name);
+
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use std::cmp::Ordering;
+
+use anchor_lang::{prelude::*, solana_program::program::MAX_RETURN_DATA};
+
+use crate::{
+    oracles::{pyth::SourceStatus, OracleType},
+    utils::{
+        math::{cmp_prices, rescale},
+        twap::window_capacity,
+    },
+    CallerClock, DatedPrice, OracleMappings, OraclePrices, OracleTwaps, Price, PriceSide,
+    ScopeError,
+};
+
+/// Max drift allowed between a caller provided clock and the `Clock` sysvar, in slots
+pub const MAX_CALLER_CLOCK_DRIFT_SLOTS: u64 = 150;
+/// Max drift allowed between a caller provided clock and the `Clock` sysvar, in seconds
+pub const MAX_CALLER_CLOCK_DRIFT_SECONDS: i64 = 60;
+/// Max number of tokens read at once by [`get_prices`]
+///
+/// The returned prices (and the length of the list) must fit in the return data.
+pub const MAX_GET_PRICES_TOKENS: usize = 15;
+
+const _: () =
+    assert!(4 + MAX_GET_PRICES_TOKENS * std::mem::size_of::<DatedPrice>() <= MAX_RETURN_DATA);
+
+#[derive(Accounts)]
+pub struct GetPrice<'info> {
+    pub oracle_prices: AccountLoader<'info, OraclePrices>,
+}
+
+#[derive(Accounts)]
+pub struct GetConservativePrice<'info> {
+    #[account(has_one = oracle_mappings)]
+    pub oracle_prices: AccountLoader<'info, OraclePrices>,
+    pub oracle_mappings: AccountLoader<'info, OracleMappings>,
+    #[account(has_one = oracle_prices)]
+    pub oracle_twaps: AccountLoader<'info, OracleTwaps>,
+}
+
+/// Read the price of a token, staleness is evaluated against the `Clock` sysvar
+pub fn get_price(ctx: Context<GetPrice>, token: usize, max_age_seconds: u64) -> Result<DatedPrice> {
+    let reference = sysvar_reference_clock()?;
+    check_feed_liveness(&ctx, &reference)?;
+    read_price(&ctx, token, max_age_seconds, &reference)
+}
+
+/// Read the price of a token expressed with the `target_exp` exponent, staleness is evaluated
+/// against the `Clock` sysvar
+///
+/// Fails if the price overflows or is truncated to zero once rescaled. The confidence interval is
+/// rescaled with the price, it can be truncated to zero.
+pub fn get_price_rescaled(
+    ctx: Context<GetPrice>,
+    token: usize,
+    max_age_seconds: u64,
+    target_exp: u8,
+) -> Result<DatedPrice> {
+    let reference = sysvar_reference_clock()?;
+    check_feed_liveness(&ctx, &reference)?;
+    let price = read_price(&ctx, token, max_age_seconds, &reference)?;
+    let conf = Price {
+        value: price.conf,
+        exp: price.price.exp,
+    };
+    let conf = match rescale(conf, target_exp) {
+        Ok(conf) => conf.value,
+        Err(ScopeError::PrecisionLoss) => 0,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(DatedPrice {
+        price: rescale(price.price, target_exp)?,
+        conf,
+        ..price
+    })
+}
+
+/// Read the prices of a list of tokens, in order, staleness is evaluated against the `Clock`
+/// sysvar
+///
+/// The whole read fails if any of the prices is invalid or stale.
+pub fn get_prices(
+    ctx: Context<GetPrice>,
+    tokens: &[u16],
+    max_age_seconds: u64,
+) -> Result<Vec<DatedPrice>> {
+    if tokens.len() > MAX_GET_PRICES_TOKENS {
+        msg!(
+            "Cannot read more than {} prices at once, {} requested",
+            MAX_GET_PRICES_TOKENS,
+            tokens.len()
+        );
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let reference = sysvar_reference_clock()?;
+    check_feed_liveness(&ctx, &reference)?;
+    tokens
+        .iter()
+        .map(|&token| read_price(&ctx, token.into(), max_age_seconds, &reference))
+        .collect()
+}
+
+/// Tell if the price of a token can be used right now, staleness is evaluated against the
+/// `Clock` sysvar
+///
+/// Same checks as [`get_price`] without failing: an out of range token, a price never
+/// refreshed or a stale price is not usable. A price whose source is known to have stopped
+/// trading (see [`SourceStatus`]) is not usable either, it is the last price kept before.
+pub fn is_price_usable(ctx: Context<GetPrice>, token: u64, max_age_seconds: u64) -> Result<bool> {
+    let token = match usize::try_from(token) {
+        Ok(token) => token,
+        Err(_) => return Ok(false),
+    };
+    let reference = sysvar_reference_clock()?;
+    match check_feed_liveness(&ctx, &reference)
+        .and_then(|_| read_price(&ctx, token, max_age_seconds, &reference))
+    {
+        Ok(price) => Ok(!is_source_not_trading(&price)),
+        Err(err)
+            if [
+                ScopeError::BadTokenNb,
+                ScopeError::PriceNotValid,
+                ScopeError::StalePrice,
+                ScopeError::StaleFeed,
+            ]
+            .into_iter()
+            .any(|unusable| err == unusable.into()) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether the last status seen on the source of the price is halted or in auction
+fn is_source_not_trading(price: &DatedPrice) -> bool {
+    matches!(
+        SourceStatus::try_from(price.source_status),
+        Ok(SourceStatus::Halted | SourceStatus::Auction)
+    )
+}
+
+/// Read the price of a token, staleness is evaluated against the caller provided clock
+///
+/// The provided clock can be late compared to the `Clock` sysvar but not ahead of it by more
+/// than [`MAX_CALLER_CLOCK_DRIFT_SLOTS`] / [`MAX_CALLER_CLOCK_DRIFT_SECONDS`].
+pub fn get_price_with_clock(
+    ctx: Context<GetPrice>,
+    token: usize,
+    max_age_seconds: u64,
+    caller_clock: CallerClock,
+) -> Result<DatedPrice> {
+    let clock = Clock::get()?;
+    if caller_clock.slot > clock.slot.saturating_add(MAX_CALLER_CLOCK_DRIFT_SLOTS)
+        || caller_clock.unix_timestamp
+            > clock
+                .unix_timestamp
+                .saturating_add(MAX_CALLER_CLOCK_DRIFT_SECONDS)
+        || caller_clock.unix_timestamp < 0
+    {
+        msg!(
+            "Caller clock (slot {}, ts {}) is not consistent with the current clock (slot {}, ts {})",
+            caller_clock.slot,
+            caller_clock.unix_timestamp,
+            clock.slot,
+            clock.unix_timestamp
+        );
+        return err!(ScopeError::InvalidCallerClock);
+    }
+    check_feed_liveness(&ctx, &caller_clock)?;
+    read_price(&ctx, token, max_age_seconds, &caller_clock)
+}
+
+/// Read the lowest (collateral) or highest (debt) of the spot price, the TWAP and the EMA of a
+/// token, staleness is evaluated against the `Clock` sysvar
+///
+/// The spot price and the TWAP are required, the TWAP must be maintained over `window` samples
+/// (0 means [`crate::MAX_TWAP_SAMPLES`]). The EMA is used when the token is a Pyth price and
+/// the same Pyth account is mapped as a [`OracleType::PythEMA`] entry of the feed.
+pub fn get_conservative_price(
+    ctx: Context<GetConservativePrice>,
+    token: usize,
+    side: PriceSide,
+    window: u64,
+    max_age_seconds: u64,
+) -> Result<DatedPrice> {
+    let reference = sysvar_reference_clock()?;
+    let oracle_prices = ctx.accounts.oracle_prices.load()?;
+    let spot = check_price(&oracle_prices, token, max_age_seconds, &reference)?;
+
+    let oracle_twaps = ctx.accounts.oracle_twaps.load()?;
+    let twap_buffer = &oracle_twaps.twaps[token];
+    if twap_buffer.capacity() != window_capacity(window) {
+        msg!(
+            "TWAP of token {} is maintained over {} samples, {} requested",
+            token,
+            twap_buffer.capacity(),
+            window_capacity(window)
+        );
+        return err!(ScopeError::TwapWindowMismatch);
+    }
+    let twap = oracle_twaps.get_twap(token)?;
+    check_age(&twap, token, max_age_seconds, &reference)?;
+
+    let oracle_mappings = ctx.accounts.oracle_mappings.load()?;
+    let ema = find_ema_token(&oracle_mappings, token)
+        .map(|ema_token| check_price(&oracle_prices, ema_token, max_age_seconds, &reference))
+        .transpose()?;
+
+    Ok(conservative_price(side, spot, twap, ema))
+}
+
+/// Token of the EMA entry sharing the Pyth account of a Pyth spot entry, if any
+fn find_ema_token(oracle_mappings: &OracleMappings, token: usize) -> Option<usize> {
+    if oracle_mappings.price_types[token] != u8::from(OracleType::Pyth) {
+        return None;
+    }
+    let pyth_account = oracle_mappings.price_info_accounts[token];
+    oracle_mappings
+        .price_types
+        .iter()
+        .zip(oracle_mappings.price_info_accounts.iter())
+        .position(|(&price_type, account)| {
+            price_type == u8::from(OracleType::PythEMA) && *account == pyth_account
+        })
+}
+
+/// Lowest price for a collateral, highest for a debt, the first one wins ties
+fn conservative_price(
+    side: PriceSide,
+    spot: DatedPrice,
+    twap: DatedPrice,
+    ema: Option<DatedPrice>,
+) -> DatedPrice {
+    let less_conservative = match side {
+        PriceSide::Collateral => Ordering::Greater,
+        PriceSide::Debt => Ordering::Less,
+    };
+    [twap]
+        .into_iter()
+        .chain(ema)
+        .fold(spot, |selected, candidate| {
+            if cmp_prices(&selected.price, &candidate.price) == less_conservative {
+                candidate
+            } else {
+                selected
+            }
+        })
+}
+
+fn sysvar_reference_clock() -> Result<CallerClock> {
+    let clock = Clock::get()?;
+    Ok(CallerClock {
+        slot: clock.slot,
+        unix_timestamp: clock.unix_timestamp,
+    })
+}
+
+/// Fail if no price of the feed has been updated for more than its `max_feed_staleness_slots`,
+/// the crank of the feed is then likely down
+///
+/// Skipped when the max feed staleness is 0.
+fn check_feed_liveness(ctx: &Context<GetPrice>, reference: &CallerClock) -> Result<()> {
+    let oracle_prices = ctx.accounts.oracle_prices.load()?;
+    let max_feed_staleness_slots = oracle_prices.max_feed_staleness_slots;
+    if max_feed_staleness_slots == 0 {
+        return Ok(());
+    }
+    let last_updated_slot = oracle_prices
+        .prices
+        .iter()
+        .map(|price| price.last_updated_slot)
+        .max()
+        .unwrap_or_default();
+    if reference.slot.saturating_sub(last_updated_slot) > max_feed_staleness_slots {
+        msg!(
+            "Feed last updated at slot {}, more than {} slots before slot {}",
+            last_updated_slot,
+            max_feed_staleness_slots,
+            reference.slot
+        );
+        return err!(ScopeError::StaleFeed);
+    }
+    Ok(())
+}
+
+fn read_price(
+    ctx: &Context<GetPrice>,
+    token: usize,
+    max_age_seconds: u64,
+    reference: &CallerClock,
+) -> Result<DatedPrice> {
+    let oracle_prices = ctx.accounts.oracle_prices.load()?;
+    check_price(&oracle_prices, token, max_age_seconds, reference)
+}
+
+fn check_price(
+    oracle_prices: &OraclePrices,
+    token: usize,
+    max_age_seconds: u64,
+    reference: &CallerClock,
+) -> Result<DatedPrice> {
+    let price = *oracle_prices
+        .prices
+        .get(token)
+        .ok_or(ScopeError::BadTokenNb)?;
+
+    if price.price.value == 0 {
+        msg!("Price of token {} has never been refreshed", token);
+        return err!(ScopeError::PriceNotValid);
+    }
+    check_age(&price, token, max_age_seconds, reference)?;
+
+    Ok(price)
+}
+
+fn check_age(
+    price: &DatedPrice,
+    token: usize,
+    max_age_seconds: u64,
+    reference: &CallerClock,
+) -> Result<()> {
+    // A price more recent than the reference clock is considered fresh
+    let reference_timestamp = u64::try_from(reference.unix_timestamp).unwrap_or(0);
+    let age = reference_timestamp.saturating_sub(price.unix_timestamp);
+    if age > max_age_seconds {
+        msg!(
+            "Price of token {} is stale: updated at ts {}, reference ts {}, max age {}",
+            token,
+            price.unix_timestamp,
+            reference.unix_timestamp,
+            max_age_seconds
+        );
+        return err!(ScopeError::StalePrice);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Price;
+
+    fn dated_price(value: u64, exp: u64, unix_timestamp: u64) -> DatedPrice {
+        DatedPrice {
+            price: Price { value, exp },
+            unix_timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conservative_price_spot_below_twap() {
+        let spot = dated_price(95_000, 3, 100);
+        let twap = dated_price(10_000, 2, 90);
+        assert_eq!(
+            conservative_price(PriceSide::Collateral, spot, twap, None),
+            spot
+        );
+        assert_eq!(conservative_price(PriceSide::Debt, spot, twap, None), twap);
+    }
+
+    #[test]
+    fn test_conservative_price_with_ema() {
+        let spot = dated_price(10_500, 2, 100);
+        let twap = dated_price(10_000, 2, 90);
+        let ema = dated_price(9_800, 2, 100);
+        assert_eq!(
+            conservative_price(PriceSide::Collateral, spot, twap, Some(ema)),
+            ema
+        );
+        assert_eq!(
+            conservative_price(PriceSide::Debt, spot, twap, Some(ema)),
+            spot
+        );
+    }
+
+    #[test]
+    fn test_conservative_price_ties_keep_spot() {
+        let spot = dated_price(100, 0, 100);
+        let twap = dated_price(10_000, 2, 90);
+        for side in [PriceSide::Collateral, PriceSide::Debt] {
+            assert_eq!(conservative_price(side, spot, twap, None), spot);
+        }
+    }
+}
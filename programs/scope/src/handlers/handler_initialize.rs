@@ -28,12 +28,14 @@ pub struct Initialize<'info> {
 
 pub fn process(ctx: Context<Initialize>, _: String) -> Result<()> {
     // Initialize oracle mapping account
-    let _mappings = ctx.accounts.oracle_mappings.load_init()?;
+    let mut mappings = ctx.accounts.oracle_mappings.load_init()?;
+    mappings.version = crate::OracleMappings::VERSION;
 
     // Initialize oracle price account
     let oracle_pbk = ctx.accounts.oracle_mappings.key();
     let mut oracle_prices = ctx.accounts.oracle_prices.load_init()?;
     oracle_prices.oracle_mappings = oracle_pbk;
+    oracle_prices.version = crate::OraclePrices::VERSION;
 
     // Initialize configuration account
     let prices_pbk = ctx.accounts.oracle_prices.key();
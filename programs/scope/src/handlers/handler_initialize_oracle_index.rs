@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct InitializeOracleIndex<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_prices)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    pub oracle_prices: AccountLoader<'info, crate::OraclePrices>,
+
+    // Account is pre-reserved/paid outside the program
+    #[account(zero)]
+    pub oracle_index: AccountLoader<'info, crate::OracleIndex>,
+}
+
+pub fn process(ctx: Context<InitializeOracleIndex>, _: String) -> Result<()> {
+    let mut oracle_index = ctx.accounts.oracle_index.load_init()?;
+    oracle_index.oracle_prices = ctx.accounts.oracle_prices.key();
+
+    Ok(())
+}
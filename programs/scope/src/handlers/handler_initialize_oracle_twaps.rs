@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct InitializeOracleTwaps<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_prices)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    #[account(mut)]
+    pub oracle_prices: AccountLoader<'info, crate::OraclePrices>,
+
+    // Account is pre-reserved/paid outside the program
+    #[account(zero)]
+    pub oracle_twaps: AccountLoader<'info, crate::OracleTwaps>,
+}
+
+pub fn process(ctx: Context<InitializeOracleTwaps>, _: String) -> Result<()> {
+    let mut oracle_twaps = ctx.accounts.oracle_twaps.load_init()?;
+    oracle_twaps.oracle_prices = ctx.accounts.oracle_prices.key();
+
+    let mut oracle_prices = ctx.accounts.oracle_prices.load_mut()?;
+    oracle_prices.oracle_twaps = ctx.accounts.oracle_twaps.key();
+
+    let mut configuration = ctx.accounts.configuration.load_mut()?;
+    configuration.oracle_twaps = ctx.accounts.oracle_twaps.key();
+
+    Ok(())
+}
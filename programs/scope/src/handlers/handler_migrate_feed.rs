@@ -0,0 +1,180 @@
+use anchor_lang::{prelude::*, system_program, Discriminator};
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+
+use crate::{DatedPrice, OracleMappings, OraclePrices, ScopeError, MAX_ENTRIES};
+
+/// Size of a version 0 `OracleMappings` account, without its discriminator: the price info
+/// accounts and price types followed by a reserve of one `u64` per token
+const ORACLE_MAPPINGS_V0_SIZE: usize = MAX_ENTRIES * (32 + 1 + 8);
+/// Offset of the reserve of a version 0 `OracleMappings` account, without its discriminator
+const ORACLE_MAPPINGS_V0_RESERVE_OFFSET: usize = MAX_ENTRIES * (32 + 1);
+/// Size of a `DatedPrice` of a version 0 `OraclePrices` account, without `conf`
+const DATED_PRICE_V0_SIZE: usize = 56;
+/// Size of a version 0 `OraclePrices` account, without its discriminator
+const ORACLE_PRICES_V0_SIZE: usize = 32 + MAX_ENTRIES * DATED_PRICE_V0_SIZE;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct MigrateFeed<'info> {
+    /// Pays the rent of the grown accounts
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings, has_one = oracle_prices)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    /// CHECK: Account of the feed (see `has_one`), not deserialized as it can have the layout of
+    /// a previous version
+    #[account(mut, owner = crate::ID)]
+    pub oracle_mappings: AccountInfo<'info>,
+
+    /// CHECK: Account of the feed (see `has_one`), not deserialized as it can have the layout of
+    /// a previous version
+    #[account(mut, owner = crate::ID)]
+    pub oracle_prices: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Upgrade the accounts of a feed to the current layout
+///
+/// An account grows by at most `MAX_PERMITTED_DATA_INCREASE` bytes per instruction, so the
+/// instruction is repeated (possibly in the same transaction) until the accounts have their
+/// current size. It does nothing on accounts already upgraded.
+pub fn process(ctx: Context<MigrateFeed>, _: String) -> Result<()> {
+    let accounts = &ctx.accounts;
+    migrate_oracle_mappings(
+        &accounts.oracle_mappings,
+        &accounts.admin,
+        &accounts.system_program,
+    )?;
+    migrate_oracle_prices(
+        &accounts.oracle_prices,
+        &accounts.admin,
+        &accounts.system_program,
+    )
+}
+
+/// Grow an `OracleMappings` account of version 0 towards the current layout
+///
+/// The per-token configuration of the current layout starts where the reserve of version 0 was,
+/// all its fields are disabled when zeroed so the upgraded account keeps the mapped tokens
+/// without any additional limit. The version is set once the account has its final size.
+fn migrate_oracle_mappings<'info>(
+    oracle_mappings: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system: &Program<'info, System>,
+) -> Result<()> {
+    check_discriminator::<OracleMappings>(oracle_mappings)?;
+    let target_len = 8 + std::mem::size_of::<OracleMappings>();
+    let current_len = oracle_mappings.data_len();
+    if current_len == target_len {
+        msg!(
+            "OracleMappings already at version {}",
+            OracleMappings::VERSION
+        );
+        return Ok(());
+    }
+    if current_len < 8 + ORACLE_MAPPINGS_V0_SIZE || current_len > target_len {
+        msg!("Unexpected OracleMappings size {}", current_len);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    let new_len = target_len.min(current_len + MAX_PERMITTED_DATA_INCREASE);
+    grow(oracle_mappings, new_len, payer, system)?;
+    oracle_mappings.try_borrow_mut_data()?
+        [8 + ORACLE_MAPPINGS_V0_RESERVE_OFFSET..8 + ORACLE_MAPPINGS_V0_SIZE]
+        .fill(0);
+
+    if new_len == target_len {
+        let loader = AccountLoader::<OracleMappings>::try_from(oracle_mappings)?;
+        loader.load_mut()?.version = OracleMappings::VERSION;
+        msg!(
+            "OracleMappings upgraded to version {}",
+            OracleMappings::VERSION
+        );
+    }
+
+    Ok(())
+}
+
+/// Upgrade an `OraclePrices` account of version 0 to the current layout
+///
+/// The prices are moved to their new, larger, slot with a zero `conf`, the first 56 bytes of a
+/// `DatedPrice` are unchanged. The growth fits in a single instruction.
+fn migrate_oracle_prices<'info>(
+    oracle_prices: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system: &Program<'info, System>,
+) -> Result<()> {
+    check_discriminator::<OraclePrices>(oracle_prices)?;
+    let target_len = 8 + std::mem::size_of::<OraclePrices>();
+    let current_len = oracle_prices.data_len();
+    if current_len == target_len {
+        msg!("OraclePrices already at version {}", OraclePrices::VERSION);
+        return Ok(());
+    }
+    if current_len != 8 + ORACLE_PRICES_V0_SIZE {
+        msg!("Unexpected OraclePrices size {}", current_len);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    grow(oracle_prices, target_len, payer, system)?;
+    {
+        let mut data = oracle_prices.try_borrow_mut_data()?;
+        let prices_offset = 8 + 32;
+        // From the last price so that a price is not overwritten before being moved
+        for token in (0..MAX_ENTRIES).rev() {
+            let from = prices_offset + token * DATED_PRICE_V0_SIZE;
+            let to = prices_offset + token * std::mem::size_of::<DatedPrice>();
+            data.copy_within(from..from + DATED_PRICE_V0_SIZE, to);
+            data[to + DATED_PRICE_V0_SIZE..to + std::mem::size_of::<DatedPrice>()].fill(0);
+        }
+    }
+
+    let loader = AccountLoader::<OraclePrices>::try_from(oracle_prices)?;
+    loader.load_mut()?.version = OraclePrices::VERSION;
+    msg!("OraclePrices upgraded to version {}", OraclePrices::VERSION);
+
+    Ok(())
+}
+
+fn check_discriminator<T: Discriminator>(account: &AccountInfo) -> Result<()> {
+    if account.try_borrow_data()?.get(..8) != Some(&T::discriminator()[..]) {
+        msg!("Account {} is not of the expected type", account.key);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    Ok(())
+}
+
+/// Grow `account` to `new_len` bytes, the new bytes are zeroed and `payer` pays the missing rent
+fn grow<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &Signer<'info>,
+    system: &Program<'info, System>,
+) -> Result<()> {
+    let missing_rent = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if missing_rent > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            missing_rent,
+        )?;
+    }
+    msg!(
+        "Grow account {} from {} to {} bytes",
+        account.key,
+        account.data_len(),
+        new_len
+    );
+    account.realloc(new_len, true)?;
+    Ok(())
+}
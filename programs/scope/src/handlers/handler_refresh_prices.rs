@@ -1,7 +1,8 @@
-use std::convert::TryInto;
+use std::{cmp::Ordering, convert::TryInto};
 
 use anchor_lang::prelude::*;
 use solana_program::{
+    ed25519_program,
     instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
     pubkey,
     sysvar::instructions::{
@@ -10,8 +11,12 @@ use solana_program::{
 };
 
 use crate::{
-    oracles::{get_price, OracleType},
-    ScopeError,
+    oracles::{
+        check_source_identity, check_source_owner, derived_price, get_price, get_source_status,
+        OracleType,
+    },
+    utils::math::{apply_hard_cap, cmp_prices, deviation_bps},
+    DatedPrice, Price, PriceRefreshSkipped, PriceRefreshed, ScopeError, MAX_SOURCE_SLOT_DRIFT,
 };
 
 const COMPUTE_BUDGET_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
@@ -28,6 +33,11 @@ pub struct RefreshOne<'info> {
     /// CHECK: Sysvar fixed address
     #[account(address = SYSVAR_INSTRUCTIONS_ID)]
     pub instruction_sysvar_account_info: AccountInfo<'info>,
+    /// TWAP of the prices, updated with the refreshed price if provided
+    #[account(mut, has_one = oracle_prices)]
+    pub oracle_twaps: Option<AccountLoader<'info, crate::OracleTwaps>>,
+    /// Required when the feed has a refresh authority, see [`check_refresh_authority`]
+    pub refresh_authority: Option<Signer<'info>>,
 }
 
 #[derive(Accounts)]
@@ -41,17 +51,37 @@ pub struct RefreshList<'info> {
     /// CHECK: Sysvar fixed address
     #[account(address = SYSVAR_INSTRUCTIONS_ID)]
     pub instruction_sysvar_account_info: AccountInfo<'info>,
+    /// TWAP of the prices, updated with the refreshed prices if provided
+    #[account(mut, has_one = oracle_prices)]
+    pub oracle_twaps: Option<AccountLoader<'info, crate::OracleTwaps>>,
+    /// Required when the feed has a refresh authority, see [`check_refresh_authority`]
+    pub refresh_authority: Option<Signer<'info>>,
     // Note: use remaining accounts as price accounts
 }
 
 pub fn refresh_one_price(ctx: Context<RefreshOne>, token: usize) -> Result<()> {
     check_execution_ctx(&ctx.accounts.instruction_sysvar_account_info)?;
+    // Prices and mappings are borrowed at the same time, they must never alias
+    require_keys_neq!(
+        ctx.accounts.oracle_prices.key(),
+        ctx.accounts.oracle_mappings.key(),
+        ScopeError::UnexpectedAccount
+    );
 
     let oracle_mappings = ctx.accounts.oracle_mappings.load()?;
+    check_refresh_authority(&oracle_mappings, ctx.accounts.refresh_authority.as_ref())?;
+    check_oracle_twaps(
+        &ctx.accounts.oracle_prices,
+        ctx.accounts.oracle_twaps.as_ref(),
+    )?;
     let price_info = &ctx.accounts.price_info;
 
     // Check that the provided account is the one referenced in oracleMapping
-    if oracle_mappings.price_info_accounts[token] != price_info.key() {
+    let mapped_account = oracle_mappings
+        .price_info_accounts
+        .get(token)
+        .ok_or(ScopeError::BadTokenNb)?;
+    if *mapped_account != price_info.key() {
         return err!(ScopeError::UnexpectedAccount);
     }
 
@@ -59,13 +89,42 @@ pub fn refresh_one_price(ctx: Context<RefreshOne>, token: usize) -> Result<()> {
         .try_into()
         .map_err(|_| ScopeError::BadTokenType)?;
 
-    let mut remaining_iter = ctx.remaining_accounts.iter();
+    let mut remaining_iter = ctx.remaining_accounts.iter().peekable();
     let clock = Clock::get()?;
-    let mut price = get_price(price_type, price_info, &mut remaining_iter, &clock)?;
+    let mut price = get_price(
+        price_type,
+        price_info,
+        &mut remaining_iter,
+        &clock,
+        &ctx.accounts.oracle_prices,
+        ctx.accounts.oracle_twaps.as_ref(),
+        &oracle_mappings,
+        token,
+    )?;
+    check_source_owner(price_type, price_info)?;
+    check_source_identity(
+        price_type,
+        price_info,
+        &oracle_mappings.source_identities[token],
+    )?;
+    check_source_age(&price, oracle_mappings.max_source_ages[token], &clock)?;
+    check_price_slot_drift(&price, &clock)?;
+    check_price_max_age(&price, oracle_mappings.price_max_ages[token], &clock)?;
+    check_price_bounds(&price, &oracle_mappings.price_bounds[token])?;
+    price.price = apply_hard_cap(price.price, &oracle_mappings.hard_caps[token]);
     price.index = token.try_into().unwrap();
+    if let Some(status) = get_source_status(price_type, price_info) {
+        price.source_status = status.into();
+    }
 
     // Only load when needed, allows prices computation to use scope chain
     let mut oracle = ctx.accounts.oracle_prices.load_mut()?;
+    check_price_deviation(
+        &price,
+        &oracle.prices[token],
+        oracle_mappings.max_deviations_bps[token],
+        &clock,
+    )?;
 
     msg!(
         "tk {}, {:?}: {:?} to {:?} | prev_slot: {:?}, new_slot: {:?}, crt_slot: {:?}",
@@ -79,14 +138,47 @@ pub fn refresh_one_price(ctx: Context<RefreshOne>, token: usize) -> Result<()> {
     );
 
     oracle.prices[token] = price;
+    emit_price_refreshed(&price);
+
+    if let Some(oracle_twaps) = &ctx.accounts.oracle_twaps {
+        oracle_twaps.load_mut()?.twaps[token].push_sample(&price)?;
+    }
 
     Ok(())
 }
 
+/// Refresh the prices of a list of tokens
+///
+/// A token whose price cannot be computed or validated is skipped, the transaction fails if the
+/// provided accounts do not match the tokens.
 pub fn refresh_price_list(ctx: Context<RefreshList>, tokens: &[u16]) -> Result<()> {
+    refresh_list(ctx, tokens, false)
+}
+
+/// Refresh the prices of a list of tokens, committing the successful refreshes in all cases
+///
+/// Same as [`refresh_price_list`] but an account not matching its token does not fail the
+/// transaction: the failure is logged and the prices refreshed before it are kept. The
+/// accounts of the following tokens cannot be matched anymore, they are skipped.
+pub fn refresh_price_list_lenient(ctx: Context<RefreshList>, tokens: &[u16]) -> Result<()> {
+    refresh_list(ctx, tokens, true)
+}
+
+fn refresh_list(ctx: Context<RefreshList>, tokens: &[u16], lenient: bool) -> Result<()> {
     check_execution_ctx(&ctx.accounts.instruction_sysvar_account_info)?;
+    // Prices and mappings are borrowed at the same time, they must never alias
+    require_keys_neq!(
+        ctx.accounts.oracle_prices.key(),
+        ctx.accounts.oracle_mappings.key(),
+        ScopeError::UnexpectedAccount
+    );
 
     let oracle_mappings = &ctx.accounts.oracle_mappings.load()?;
+    check_refresh_authority(oracle_mappings, ctx.accounts.refresh_authority.as_ref())?;
+    check_oracle_twaps(
+        &ctx.accounts.oracle_prices,
+        ctx.accounts.oracle_twaps.as_ref(),
+    )?;
 
     // Check that the received token list is not too long
     if tokens.len() > crate::MAX_ENTRIES {
@@ -97,37 +189,61 @@ pub fn refresh_price_list(ctx: Context<RefreshList>, tokens: &[u16]) -> Result<(
         return err!(ScopeError::AccountsAndTokenMismatch);
     }
 
-    let zero_pk: Pubkey = Pubkey::default();
+    // Derived prices must be computed from the refreshed value of their sources
+    derived_price::check_refresh_order(oracle_mappings, tokens)?;
 
-    let mut accounts_iter = ctx.remaining_accounts.iter();
+    let mut accounts_iter = ctx.remaining_accounts.iter().peekable();
 
     for &token_nb in tokens.iter() {
         let token_idx: usize = token_nb.into();
-        let oracle_mapping = oracle_mappings
-            .price_info_accounts
-            .get(token_idx)
-            .ok_or(ScopeError::BadTokenNb)?;
-        let price_type: OracleType = oracle_mappings.price_types[token_idx]
-            .try_into()
-            .map_err(|_| ScopeError::BadTokenType)?;
-        let received_account = accounts_iter
-            .next()
-            .ok_or(ScopeError::AccountsAndTokenMismatch)?;
-        // Ignore unset mapping accounts
-        if zero_pk == *oracle_mapping {
-            continue;
-        }
-        // Check that the provided oracle accounts are the one referenced in oracleMapping
-        if oracle_mappings.price_info_accounts[token_idx] != received_account.key() {
-            msg!(
-                "Invalid price account: {}, expected: {}",
-                received_account.key(),
-                oracle_mappings.price_info_accounts[token_idx]
-            );
-            return err!(ScopeError::UnexpectedAccount);
-        }
+        let (price_type, received_account) =
+            match get_list_entry(oracle_mappings, token_idx, &mut accounts_iter) {
+                Ok(Some(entry)) => entry,
+                // Ignore unset mapping accounts
+                Ok(None) => continue,
+                Err(err) if lenient => {
+                    msg!(
+                        "Token {} and following skipped as its accounts are invalid: {}",
+                        token_idx,
+                        err
+                    );
+                    emit_price_refresh_skipped(token_nb, &err);
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
         let clock = Clock::get()?;
-        match get_price(price_type, received_account, &mut accounts_iter, &clock) {
+        let price = get_price(
+            price_type,
+            received_account,
+            &mut accounts_iter,
+            &clock,
+            &ctx.accounts.oracle_prices,
+            ctx.accounts.oracle_twaps.as_ref(),
+            oracle_mappings,
+            token_idx,
+        )
+        .and_then(|mut price| {
+            check_source_owner(price_type, received_account)?;
+            check_source_identity(
+                price_type,
+                received_account,
+                &oracle_mappings.source_identities[token_idx],
+            )?;
+            check_source_age(&price, oracle_mappings.max_source_ages[token_idx], &clock)?;
+            check_price_slot_drift(&price, &clock)?;
+            check_price_max_age(&price, oracle_mappings.price_max_ages[token_idx], &clock)?;
+            check_price_bounds(&price, &oracle_mappings.price_bounds[token_idx])?;
+            price.price = apply_hard_cap(price.price, &oracle_mappings.hard_caps[token_idx]);
+            check_price_deviation(
+                &price,
+                &ctx.accounts.oracle_prices.load()?.prices[token_idx],
+                oracle_mappings.max_deviations_bps[token_idx],
+                &clock,
+            )?;
+            Ok(price)
+        });
+        match price {
             Ok(price) => {
                 // Only temporary load as mut to allow prices to be computed based on a scope chain
                 // from the price feed that is currently updated
@@ -150,14 +266,28 @@ pub fn refresh_price_list(ctx: Context<RefreshList>, tokens: &[u16]) -> Result<(
 
                 *to_update = price;
                 to_update.index = token_nb;
+                if let Some(status) = get_source_status(price_type, received_account) {
+                    to_update.source_status = status.into();
+                }
+                emit_price_refreshed(to_update);
+
+                if let Some(oracle_twaps) = &ctx.accounts.oracle_twaps {
+                    oracle_twaps.load_mut()?.twaps[token_idx].push_sample(to_update)?;
+                }
             }
-            Err(_) => {
+            Err(err) => {
                 // Skip the error, details is already logged in get_price and formatting here cost a lot of CU
                 msg!(
                     "Price skipped as validation failed (token {}, type {:?})",
                     token_idx,
                     price_type
                 );
+                emit_price_refresh_skipped(token_nb, &err);
+                // Keep the last valid price but record why the source did not provide a new one
+                if let Some(status) = get_source_status(price_type, received_account) {
+                    ctx.accounts.oracle_prices.load_mut()?.prices[token_idx].source_status =
+                        status.into();
+                }
             }
         };
     }
@@ -165,6 +295,210 @@ pub fn refresh_price_list(ctx: Context<RefreshList>, tokens: &[u16]) -> Result<(
     Ok(())
 }
 
+/// Get the type and the price account of a token of a refresh list, `None` if the token
+/// is not mapped
+///
+/// The price account is consumed from the remaining accounts and checked against the mapping.
+fn get_list_entry<'a, 'b>(
+    oracle_mappings: &crate::OracleMappings,
+    token_idx: usize,
+    accounts_iter: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+) -> Result<Option<(OracleType, &'b AccountInfo<'a>)>>
+where
+    'a: 'b,
+{
+    let oracle_mapping = oracle_mappings
+        .price_info_accounts
+        .get(token_idx)
+        .ok_or(ScopeError::BadTokenNb)?;
+    let price_type: OracleType = oracle_mappings.price_types[token_idx]
+        .try_into()
+        .map_err(|_| ScopeError::BadTokenType)?;
+    let received_account = accounts_iter
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    if *oracle_mapping == Pubkey::default() {
+        return Ok(None);
+    }
+    // Check that the provided oracle accounts are the one referenced in oracleMapping
+    if *oracle_mapping != received_account.key() {
+        msg!(
+            "Invalid price account: {}, expected: {}",
+            received_account.key(),
+            oracle_mapping
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    Ok(Some((price_type, received_account)))
+}
+
+fn emit_price_refreshed(price: &DatedPrice) {
+    if cfg!(feature = "skip_refresh_events") {
+        return;
+    }
+    emit!(PriceRefreshed {
+        token: price.index,
+        value: price.price.value,
+        exp: price.price.exp,
+        slot: price.last_updated_slot,
+        timestamp: price.unix_timestamp,
+    });
+}
+
+fn emit_price_refresh_skipped(token: u16, err: &anchor_lang::error::Error) {
+    if cfg!(feature = "skip_refresh_events") {
+        return;
+    }
+    let reason = match err {
+        anchor_lang::error::Error::AnchorError(err) => err.error_code_number.into(),
+        anchor_lang::error::Error::ProgramError(err) => err.program_error.clone().into(),
+    };
+    emit!(PriceRefreshSkipped { token, reason });
+}
+
+/// Check that the refresh authority of the feed, if any, signed the refresh
+///
+/// The lenient refresh also fails in that case, no price can be written by another account.
+fn check_refresh_authority(
+    oracle_mappings: &crate::OracleMappings,
+    refresh_authority: Option<&Signer>,
+) -> Result<()> {
+    if oracle_mappings.refresh_authority == Pubkey::default() {
+        return Ok(());
+    }
+    match refresh_authority {
+        Some(signer) if signer.key() == oracle_mappings.refresh_authority => Ok(()),
+        _ => {
+            msg!(
+                "Refresh must be signed by the refresh authority {}",
+                oracle_mappings.refresh_authority
+            );
+            err!(ScopeError::UnauthorizedRefresh)
+        }
+    }
+}
+
+/// Check that the TWAP account of the feed, if any, is provided so that every refreshed price is
+/// sampled
+fn check_oracle_twaps(
+    oracle_prices: &AccountLoader<crate::OraclePrices>,
+    oracle_twaps: Option<&AccountLoader<crate::OracleTwaps>>,
+) -> Result<()> {
+    let feed_twaps = oracle_prices.load()?.oracle_twaps;
+    if feed_twaps == Pubkey::default() {
+        return Ok(());
+    }
+    match oracle_twaps {
+        Some(oracle_twaps) if oracle_twaps.key() == feed_twaps => Ok(()),
+        _ => {
+            msg!(
+                "Refresh must provide the TWAP account of the feed {}",
+                feed_twaps
+            );
+            err!(ScopeError::UnexpectedAccount)
+        }
+    }
+}
+
+/// Reject a price whose source timestamp is older than `max_age` seconds (0 disables the check)
+fn check_source_age(price: &DatedPrice, max_age: u64, clock: &Clock) -> Result<()> {
+    if max_age == 0 {
+        return Ok(());
+    }
+    let now = u64::try_from(clock.unix_timestamp).unwrap_or(0);
+    let age = now.saturating_sub(price.unix_timestamp);
+    if age > max_age {
+        msg!(
+            "Source price is too old: {} seconds, max age {} seconds",
+            age,
+            max_age
+        );
+        return err!(ScopeError::StalePrice);
+    }
+    Ok(())
+}
+
+/// Reject a price published more than [`MAX_SOURCE_SLOT_DRIFT`] slots after the current slot
+///
+/// The clock of forked or test validators can lag behind the slot reported by the source, the
+/// age of such a price would be meaningless.
+fn check_price_slot_drift(price: &DatedPrice, clock: &Clock) -> Result<()> {
+    if price.last_updated_slot > clock.slot.saturating_add(MAX_SOURCE_SLOT_DRIFT) {
+        msg!(
+            "Source price is in the future: slot {}, current slot {}",
+            price.last_updated_slot,
+            clock.slot
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(())
+}
+
+/// Reject a price published more than `max_age` slots ago (0 disables the check)
+fn check_price_max_age(price: &DatedPrice, max_age: u64, clock: &Clock) -> Result<()> {
+    if max_age != 0 && price.is_stale(clock.slot, max_age) {
+        msg!(
+            "Source price is too old: {} slots, max age {} slots",
+            price.age_slots(clock.slot),
+            max_age
+        );
+        return err!(ScopeError::StalePrice);
+    }
+    Ok(())
+}
+
+/// Reject a price outside of the `[min, max]` bounds of its token (see
+/// [`crate::UpdateMappingConfigMode::PriceBounds`])
+fn check_price_bounds(price: &DatedPrice, bounds: &[Price; 2]) -> Result<()> {
+    let [min, max] = bounds;
+    let below = min.value != 0 && cmp_prices(&price.price, min) == Ordering::Less;
+    let above = max.value != 0
+        && max.value != u64::MAX
+        && cmp_prices(&price.price, max) == Ordering::Greater;
+    if below || above {
+        msg!(
+            "Price {} (exp {}) is out of the bounds [{}, {}] (exp {})",
+            price.price.value,
+            price.price.exp,
+            min.value,
+            max.value,
+            max.exp
+        );
+        return err!(ScopeError::PriceOutOfBounds);
+    }
+    Ok(())
+}
+
+/// Reject a price deviating from the stored one by more than `max_deviation_bps` (0 disables the
+/// check)
+///
+/// The check is skipped if the stored price is unset or older than
+/// [`crate::PRICE_DEVIATION_WINDOW_SLOTS`].
+fn check_price_deviation(
+    price: &DatedPrice,
+    stored: &DatedPrice,
+    max_deviation_bps: u16,
+    clock: &Clock,
+) -> Result<()> {
+    if max_deviation_bps == 0
+        || stored.price.value == 0
+        || clock.slot.saturating_sub(stored.last_updated_slot) > crate::PRICE_DEVIATION_WINDOW_SLOTS
+    {
+        return Ok(());
+    }
+    match deviation_bps(&price.price, &stored.price) {
+        Some(deviation) if deviation <= u128::from(max_deviation_bps) => Ok(()),
+        deviation => {
+            msg!(
+                "Price deviates too much from the stored one: {:?} bps, max {} bps",
+                deviation,
+                max_deviation_bps
+            );
+            err!(ScopeError::PriceNotValid)
+        }
+    }
+}
+
 /// Ensure that the refresh instruction is executed directly to avoid any manipulation:
 ///
 /// - Check that the current instruction is executed by our program id (not in CPI).
@@ -185,10 +519,11 @@ fn check_execution_ctx(instruction_sysvar_account_info: &AccountInfo) -> Result<
         return err!(ScopeError::RefreshInCPI);
     }
 
-    // 2- Check that instructions preceding the refresh are compute budget instructions.
+    // 2- Check that instructions preceding the refresh are compute budget instructions, or
+    // ed25519 signature verifications (see `oracles::pyth_lazer`).
     for ixn in 0..current_index {
         let ix = load_instruction_at_checked(ixn, instruction_sysvar_account_info)?;
-        if ix.program_id != COMPUTE_BUDGET_ID {
+        if ix.program_id != COMPUTE_BUDGET_ID && ix.program_id != ed25519_program::ID {
             return err!(ScopeError::RefreshWithUnexpectedIxs);
         }
     }
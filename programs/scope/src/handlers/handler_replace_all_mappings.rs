@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::{alias_price, derived_price, validate_oracle_account, OracleType},
+    MappingEntry, OracleMappings, ScopeError,
+};
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct ReplaceAllMappings<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+    #[account(mut)]
+    pub oracle_mappings: AccountLoader<'info, OracleMappings>,
+    // Note: the price info accounts of the new mappings are passed as remaining accounts,
+    // in the same order as the mapping entries.
+}
+
+/// Replace the whole oracle mapping with the provided entries.
+///
+/// All the entries are validated before anything is written. Tokens that are not part of the
+/// new set are unmapped.
+pub fn process(
+    ctx: Context<ReplaceAllMappings>,
+    mappings: &[MappingEntry],
+    _: String,
+) -> Result<()> {
+    if ctx.remaining_accounts.len() != mappings.len() {
+        msg!(
+            "Expected {} price info accounts, got {}",
+            mappings.len(),
+            ctx.remaining_accounts.len()
+        );
+        return err!(ScopeError::AccountsAndTokenMismatch);
+    }
+
+    let mut seen_tokens = [false; crate::MAX_ENTRIES];
+    for (entry, price_info) in mappings.iter().zip(ctx.remaining_accounts.iter()) {
+        let token = usize::from(entry.token);
+        let seen = seen_tokens.get_mut(token).ok_or(ScopeError::BadTokenNb)?;
+        if *seen {
+            msg!("Token {} is mapped more than once", token);
+            return err!(ScopeError::DuplicatedMappingToken);
+        }
+        *seen = true;
+
+        let price_type: OracleType = entry
+            .price_type
+            .try_into()
+            .map_err(|_| ScopeError::BadTokenType)?;
+        validate_oracle_account(price_type, price_info)?;
+    }
+
+    // Every check succeeded, replace the current mapping with the new one
+    let mut oracle_mappings = ctx.accounts.oracle_mappings.load_mut()?;
+    oracle_mappings.price_info_accounts = [Pubkey::default(); crate::MAX_ENTRIES];
+    oracle_mappings.price_types = [0; crate::MAX_ENTRIES];
+    for (entry, price_info) in mappings.iter().zip(ctx.remaining_accounts.iter()) {
+        let token = usize::from(entry.token);
+        oracle_mappings.price_info_accounts[token] = price_info.key();
+        oracle_mappings.price_types[token] = entry.price_type;
+    }
+    for entry in mappings {
+        let price_type = OracleType::try_from(entry.price_type).unwrap(); // Checked above
+        if price_type.is_derived() {
+            derived_price::check_no_cycle(&oracle_mappings, usize::from(entry.token))?;
+            alias_price::check_no_alias_chain(&oracle_mappings, usize::from(entry.token))?;
+        }
+    }
+
+    msg!("Replaced oracle mappings with {} entries", mappings.len());
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::{oracles::check_context, DatedPrice, OracleMappings, OraclePrices, Price, ScopeError};
+
+#[derive(Accounts)]
+#[instruction(token: u64, feed_name: String)]
+pub struct ResetOracleMapping<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings, has_one = oracle_prices)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+    #[account(mut)]
+    pub oracle_mappings: AccountLoader<'info, OracleMappings>,
+    #[account(mut)]
+    pub oracle_prices: AccountLoader<'info, OraclePrices>,
+}
+
+pub fn process(ctx: Context<ResetOracleMapping>, token: usize, _: String) -> Result<()> {
+    check_context(&ctx)?;
+
+    let mut oracle_mappings = ctx.accounts.oracle_mappings.load_mut()?;
+    let price_info_account = oracle_mappings
+        .price_info_accounts
+        .get_mut(token)
+        .ok_or(ScopeError::BadTokenNb)?;
+
+    msg!(
+        "Reset mapping of token {} (was {})",
+        token,
+        price_info_account
+    );
+    *price_info_account = Pubkey::default();
+    reset_token_config(&mut oracle_mappings, token);
+
+    // The last price must not be read anymore once the token is retired
+    ctx.accounts.oracle_prices.load_mut()?.prices[token] = DatedPrice::default();
+
+    Ok(())
+}
+
+/// Reset the per-token configuration of the entry, a token mapped again later at the same index
+/// starts without any of the previous limits
+fn reset_token_config(oracle_mappings: &mut OracleMappings, token: usize) {
+    oracle_mappings.price_types[token] = 0;
+    oracle_mappings.hard_caps[token] = Price::default();
+    oracle_mappings.max_source_ages[token] = 0;
+    oracle_mappings.source_identities[token] = Pubkey::default();
+    oracle_mappings.underlying_prices[token] = [0; 2];
+    oracle_mappings.max_aum_ages[token] = 0;
+    oracle_mappings.max_std_deviations_bps[token] = 0;
+    oracle_mappings.max_deviations_bps[token] = 0;
+    oracle_mappings.max_confidences_bps[token] = 0;
+    oracle_mappings.confidence_policies[token] = 0;
+    oracle_mappings.price_max_ages[token] = 0;
+    oracle_mappings.price_bounds[token] = [Price::default(); 2];
+    oracle_mappings.pyth_lazer_feed_ids[token] = 0;
+}
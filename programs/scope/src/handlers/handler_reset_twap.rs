@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::ScopeError;
+
+#[derive(Accounts)]
+#[instruction(token: u64, window_size: u64, feed_name: String)]
+pub struct ResetTwap<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_twaps)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    #[account(mut)]
+    pub oracle_twaps: AccountLoader<'info, crate::OracleTwaps>,
+}
+
+pub fn process(ctx: Context<ResetTwap>, token: usize, window_size: u64, _: String) -> Result<()> {
+    if window_size > crate::MAX_TWAP_SAMPLES as u64 {
+        return err!(ScopeError::BadTwapWindowSize);
+    }
+
+    let mut oracle_twaps = ctx.accounts.oracle_twaps.load_mut()?;
+    let twap = oracle_twaps
+        .twaps
+        .get_mut(token)
+        .ok_or(ScopeError::BadTokenNb)?;
+
+    msg!(
+        "Reset TWAP of token {} with window size {}",
+        token,
+        window_size
+    );
+    twap.reset(window_size);
+
+    Ok(())
+}
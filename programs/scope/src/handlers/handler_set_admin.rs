@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct SetAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+}
+
+/// Propose a new admin of the feed, it only becomes the admin once it has accepted with
+/// [`crate::handlers::handler_accept_admin::AcceptAdmin`].
+///
+/// A new proposal replaces the pending one, the default pubkey cancels it.
+pub fn process(ctx: Context<SetAdmin>, pending_admin: Pubkey, _: String) -> Result<()> {
+    let mut configuration = ctx.accounts.configuration.load_mut()?;
+    msg!(
+        "Set pending admin from {} to {}",
+        configuration.pending_admin,
+        pending_admin
+    );
+    configuration.pending_admin = pending_admin;
+    Ok(())
+}
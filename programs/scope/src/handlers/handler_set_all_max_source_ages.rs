@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::{MaxSourceAgeOverride, OracleMappings, ScopeError};
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct SetAllMaxSourceAges<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+    #[account(mut)]
+    pub oracle_mappings: AccountLoader<'info, OracleMappings>,
+}
+
+/// Set the max source age of every token at once.
+///
+/// All tokens get `default_max_age` except the ones listed in `overrides`.
+/// A max age of 0 disables the check for the token.
+pub fn process(
+    ctx: Context<SetAllMaxSourceAges>,
+    default_max_age: u64,
+    overrides: &[MaxSourceAgeOverride],
+    _: String,
+) -> Result<()> {
+    if overrides.len() > crate::MAX_ENTRIES {
+        msg!(
+            "Too many max source age overrides: {} (max {})",
+            overrides.len(),
+            crate::MAX_ENTRIES
+        );
+        return err!(ScopeError::BadTokenNb);
+    }
+
+    let mut seen_tokens = [false; crate::MAX_ENTRIES];
+    for entry in overrides {
+        let token = usize::from(entry.token);
+        let seen = seen_tokens.get_mut(token).ok_or(ScopeError::BadTokenNb)?;
+        if *seen {
+            msg!("Token {} has more than one max source age override", token);
+            return err!(ScopeError::DuplicatedMappingToken);
+        }
+        *seen = true;
+    }
+
+    // Every check succeeded, write the values in place
+    let mut oracle_mappings = ctx.accounts.oracle_mappings.load_mut()?;
+    oracle_mappings.max_source_ages.fill(default_max_age);
+    for entry in overrides {
+        oracle_mappings.max_source_ages[usize::from(entry.token)] = entry.max_age;
+    }
+
+    msg!(
+        "Set max source age of all tokens to {} seconds with {} overrides",
+        default_max_age,
+        overrides.len()
+    );
+
+    Ok(())
+}
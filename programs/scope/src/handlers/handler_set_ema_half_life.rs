@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::ScopeError;
+
+#[derive(Accounts)]
+#[instruction(token: u64, half_life_slots: u64, feed_name: String)]
+pub struct SetEmaHalfLife<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_twaps)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    #[account(mut)]
+    pub oracle_twaps: AccountLoader<'info, crate::OracleTwaps>,
+}
+
+pub fn process(
+    ctx: Context<SetEmaHalfLife>,
+    token: usize,
+    half_life_slots: u64,
+    _: String,
+) -> Result<()> {
+    let mut oracle_twaps = ctx.accounts.oracle_twaps.load_mut()?;
+    let twap = oracle_twaps
+        .twaps
+        .get_mut(token)
+        .ok_or(ScopeError::BadTokenNb)?;
+
+    msg!(
+        "Reset EMA of token {} with half-life {} slots",
+        token,
+        half_life_slots
+    );
+    twap.reset_ema(half_life_slots);
+
+    Ok(())
+}
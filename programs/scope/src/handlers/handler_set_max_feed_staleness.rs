@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct SetMaxFeedStaleness<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_prices)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+    #[account(mut)]
+    pub oracle_prices: AccountLoader<'info, crate::OraclePrices>,
+}
+
+/// Set the max number of slots without any price update after which the reads of the feed
+/// fail, see [`crate::handlers::handler_get_price::GetPrice`].
+///
+/// 0 disables the check.
+pub fn process(
+    ctx: Context<SetMaxFeedStaleness>,
+    max_feed_staleness_slots: u64,
+    _: String,
+) -> Result<()> {
+    let mut oracle_prices = ctx.accounts.oracle_prices.load_mut()?;
+    msg!(
+        "Set max feed staleness from {} to {} slots",
+        oracle_prices.max_feed_staleness_slots,
+        max_feed_staleness_slots
+    );
+    oracle_prices.max_feed_staleness_slots = max_feed_staleness_slots;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::OracleMappings;
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct SetRefreshAuthority<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+    #[account(mut)]
+    pub oracle_mappings: AccountLoader<'info, OracleMappings>,
+}
+
+/// Set the only account allowed to refresh the prices of the feed.
+///
+/// The default pubkey lets anyone refresh the prices.
+pub fn process(
+    ctx: Context<SetRefreshAuthority>,
+    refresh_authority: Pubkey,
+    _: String,
+) -> Result<()> {
+    let mut oracle_mappings = ctx.accounts.oracle_mappings.load_mut()?;
+    msg!(
+        "Set refresh authority from {} to {}",
+        oracle_mappings.refresh_authority,
+        refresh_authority
+    );
+    oracle_mappings.refresh_authority = refresh_authority;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::{IndexConstituent, ScopeError, MAX_ENTRIES, MAX_INDEX_CONSTITUENTS};
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct UpdateIndexConstituents<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    #[account(mut)]
+    pub oracle_index: AccountLoader<'info, crate::OracleIndex>,
+}
+
+/// Replace all the constituents of the index, used to rebalance it
+///
+/// Constituents must reference distinct tokens of the feed with a non-zero quantity.
+pub fn process(
+    ctx: Context<UpdateIndexConstituents>,
+    constituents: &[IndexConstituent],
+    max_constituent_age_slots: u64,
+    _: String,
+) -> Result<()> {
+    let mut oracle_index = ctx.accounts.oracle_index.load_mut()?;
+    if oracle_index.oracle_prices != ctx.accounts.configuration.load()?.oracle_prices {
+        msg!("Index account does not belong to the feed");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    if constituents.is_empty() || constituents.len() > MAX_INDEX_CONSTITUENTS {
+        msg!(
+            "An index must have between 1 and {} constituents, received {}",
+            MAX_INDEX_CONSTITUENTS,
+            constituents.len()
+        );
+        return err!(ScopeError::InvalidUpdateValue);
+    }
+    let mut seen = [false; MAX_ENTRIES];
+    for constituent in constituents {
+        let token = usize::try_from(constituent.token)
+            .ok()
+            .filter(|&token| token < MAX_ENTRIES)
+            .ok_or(ScopeError::BadTokenNb)?;
+        if std::mem::replace(&mut seen[token], true) {
+            msg!("Token {} is listed twice in the index", token);
+            return err!(ScopeError::InvalidUpdateValue);
+        }
+        if constituent.quantity.value == 0 {
+            msg!("Token {} has a zero quantity", token);
+            return err!(ScopeError::InvalidUpdateValue);
+        }
+    }
+
+    msg!(
+        "Set {} index constituents, max age {} slots",
+        constituents.len(),
+        max_constituent_age_slots
+    );
+    oracle_index.constituents = Default::default();
+    oracle_index.constituents[..constituents.len()].copy_from_slice(constituents);
+    oracle_index.num_constituents = constituents.len().try_into().unwrap();
+    oracle_index.max_constituent_age_slots = max_constituent_age_slots;
+
+    Ok(())
+}
@@ -1,7 +1,10 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    oracles::{check_context, validate_oracle_account, OracleType},
+    oracles::{
+        alias_price, check_context, check_source_owner, derived_price, validate_oracle_account,
+        OracleType,
+    },
     OracleMappings, ScopeError,
 };
 
@@ -22,6 +25,7 @@ pub fn process(
     token: usize,
     price_type: u8,
     _: String,
+    price_max_age: u64,
 ) -> Result<()> {
     check_context(&ctx)?;
 
@@ -36,6 +40,7 @@ pub fn process(
 
     match &ctx.accounts.price_info {
         Some(price_info_acc) => {
+            check_source_owner(price_type, price_info_acc)?;
             validate_oracle_account(price_type, price_info_acc)?;
             // Every check succeeded, replace current with new
             let new_price_pubkey = price_info_acc.key();
@@ -48,6 +53,12 @@ pub fn process(
     }
 
     oracle_mappings.price_types[token] = price_type.into();
+    oracle_mappings.price_max_ages[token] = price_max_age;
+
+    if price_type.is_derived() {
+        derived_price::check_no_cycle(&oracle_mappings, token)?;
+        alias_price::check_no_alias_chain(&oracle_mappings, token)?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::{alias_price, derived_price, validate_oracle_account, OracleType},
+    OracleMappings, ScopeError,
+};
+
+#[derive(Accounts)]
+#[instruction(feed_name: String)]
+pub struct UpdateMappingBatch<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+    #[account(mut)]
+    pub oracle_mappings: AccountLoader<'info, OracleMappings>,
+    // Note: the price info accounts of the consecutive tokens are passed as remaining accounts,
+    // in the same order as the price types.
+}
+
+/// Update the mapping of consecutive tokens starting at `first_token`.
+///
+/// Each entry is validated as in a single `update_mapping`, nothing is written if one of them
+/// is invalid. The mapping of the other tokens is left untouched.
+pub fn process(
+    ctx: Context<UpdateMappingBatch>,
+    first_token: u16,
+    price_types: &[u8],
+    _: String,
+) -> Result<()> {
+    if ctx.remaining_accounts.len() != price_types.len() {
+        msg!(
+            "Expected {} price info accounts, got {}",
+            price_types.len(),
+            ctx.remaining_accounts.len()
+        );
+        return err!(ScopeError::AccountsAndTokenMismatch);
+    }
+    let first_token = usize::from(first_token);
+    let tokens = first_token..first_token + price_types.len();
+    if tokens.end > crate::MAX_ENTRIES {
+        msg!("Tokens {:?} are out of range", tokens);
+        return err!(ScopeError::BadTokenNb);
+    }
+
+    for (&price_type, price_info) in price_types.iter().zip(ctx.remaining_accounts.iter()) {
+        let price_type: OracleType = price_type
+            .try_into()
+            .map_err(|_| ScopeError::BadTokenType)?;
+        validate_oracle_account(price_type, price_info)?;
+    }
+
+    // Every check succeeded, write the new entries
+    let mut oracle_mappings = ctx.accounts.oracle_mappings.load_mut()?;
+    for ((token, &price_type), price_info) in tokens
+        .clone()
+        .zip(price_types.iter())
+        .zip(ctx.remaining_accounts.iter())
+    {
+        oracle_mappings.price_info_accounts[token] = price_info.key();
+        oracle_mappings.price_types[token] = price_type;
+    }
+    for (token, &price_type) in tokens.zip(price_types.iter()) {
+        let price_type = OracleType::try_from(price_type).unwrap(); // Checked above
+        if price_type.is_derived() {
+            derived_price::check_no_cycle(&oracle_mappings, token)?;
+            alias_price::check_no_alias_chain(&oracle_mappings, token)?;
+        }
+    }
+
+    msg!(
+        "Updated the mapping of {} tokens from token {}",
+        price_types.len(),
+        first_token
+    );
+
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use crate::{
+    oracles::{fixed_price, pyth::ConfidencePolicy, OracleType},
+    Price, ScopeError, UpdateMappingConfigMode,
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(token: u64, mode: u64, feed_name: String, value: Vec<u8>)]
+pub struct UpdateMappingConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"conf", feed_name.as_bytes()], bump, has_one = admin, has_one = oracle_mappings)]
+    pub configuration: AccountLoader<'info, crate::Configuration>,
+
+    #[account(mut)]
+    pub oracle_mappings: AccountLoader<'info, crate::OracleMappings>,
+}
+
+pub fn process(
+    ctx: Context<UpdateMappingConfig>,
+    token: usize,
+    mode: u64,
+    value: Vec<u8>,
+    _: String,
+) -> Result<()> {
+    let mut oracle_mappings = ctx.accounts.oracle_mappings.load_mut()?;
+
+    if token >= crate::MAX_ENTRIES {
+        return err!(ScopeError::BadTokenNb);
+    }
+
+    let mode: UpdateMappingConfigMode = mode
+        .try_into()
+        .map_err(|_| ScopeError::InvalidMappingConfigUpdateMode)?;
+    match mode {
+        UpdateMappingConfigMode::HardCap => {
+            let hard_cap = Price {
+                value: read_u64(&value, 0)?,
+                exp: read_u64(&value, 8)?,
+            };
+            msg!(
+                "Setting hard cap for token {} to {} (exp {})",
+                token,
+                hard_cap.value,
+                hard_cap.exp
+            );
+            oracle_mappings.hard_caps[token] = hard_cap;
+        }
+        UpdateMappingConfigMode::FixedPrice => {
+            let price = Price {
+                value: read_u64(&value, 0)?,
+                exp: read_u64(&value, 8)?,
+            };
+            if price.value == 0 {
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            msg!(
+                "Setting fixed price for token {} to {} (exp {})",
+                token,
+                price.value,
+                price.exp
+            );
+            oracle_mappings.price_info_accounts[token] =
+                fixed_price::price_to_mapping_pubkey(&price);
+            oracle_mappings.price_types[token] = OracleType::FixedPrice.into();
+        }
+        UpdateMappingConfigMode::MaxSourceAge => {
+            let max_age = read_u64(&value, 0)?;
+            msg!(
+                "Setting max source age for token {} to {} seconds",
+                token,
+                max_age
+            );
+            oracle_mappings.max_source_ages[token] = max_age;
+        }
+        UpdateMappingConfigMode::SourceIdentity => {
+            let identity = read_pubkey(&value, 0)?;
+            let price_type: OracleType = oracle_mappings.price_types[token]
+                .try_into()
+                .map_err(|_| ScopeError::BadTokenType)?;
+            if identity != Pubkey::default() && !price_type.has_source_identity() {
+                msg!("Oracle type {:?} has no source identity to pin", price_type);
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            msg!(
+                "Setting source identity for token {} to {}",
+                token,
+                identity
+            );
+            oracle_mappings.source_identities[token] = identity;
+        }
+        UpdateMappingConfigMode::UnderlyingPrices => {
+            let underlying = [read_u16(&value, 0)?, read_u16(&value, 2)?];
+            if oracle_mappings.price_types[token] != u8::from(OracleType::ConstantProductLp) {
+                msg!("Token {} is not an LP token", token);
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            if underlying[0] == underlying[1] || underlying.iter().any(|&t| usize::from(t) == token)
+            {
+                msg!(
+                    "Underlying prices {:?} of token {} are invalid",
+                    underlying,
+                    token
+                );
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            if underlying
+                .iter()
+                .any(|&t| usize::from(t) >= crate::MAX_ENTRIES)
+            {
+                return err!(ScopeError::BadTokenNb);
+            }
+            msg!(
+                "Setting underlying prices for token {} to {:?}",
+                token,
+                underlying
+            );
+            oracle_mappings.underlying_prices[token] = underlying;
+        }
+        UpdateMappingConfigMode::MaxAumAge => {
+            if oracle_mappings.price_types[token] != u8::from(OracleType::JupiterLp) {
+                msg!("Token {} is not a Jupiter LP token", token);
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            let max_age: u32 = read_u64(&value, 0)?
+                .try_into()
+                .map_err(|_| ScopeError::InvalidUpdateValue)?;
+            msg!(
+                "Setting max AUM age for token {} to {} slots",
+                token,
+                max_age
+            );
+            oracle_mappings.max_aum_ages[token] = max_age;
+        }
+        UpdateMappingConfigMode::MaxStdDeviation => {
+            if oracle_mappings.price_types[token] != u8::from(OracleType::SwitchboardV2) {
+                msg!("Token {} is not a Switchboard V2 price", token);
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            let max_std_deviation_bps: u16 = read_u64(&value, 0)?
+                .try_into()
+                .map_err(|_| ScopeError::InvalidUpdateValue)?;
+            msg!(
+                "Setting max standard deviation for token {} to {} bps",
+                token,
+                max_std_deviation_bps
+            );
+            oracle_mappings.max_std_deviations_bps[token] = max_std_deviation_bps;
+        }
+        UpdateMappingConfigMode::MaxDeviation => {
+            let max_deviation_bps: u16 = read_u64(&value, 0)?
+                .try_into()
+                .map_err(|_| ScopeError::InvalidUpdateValue)?;
+            msg!(
+                "Setting max price deviation for token {} to {} bps",
+                token,
+                max_deviation_bps
+            );
+            oracle_mappings.max_deviations_bps[token] = max_deviation_bps;
+        }
+        UpdateMappingConfigMode::MaxConfidence => {
+            let price_type: OracleType = oracle_mappings.price_types[token]
+                .try_into()
+                .map_err(|_| ScopeError::BadTokenType)?;
+            if !price_type.has_confidence_interval() {
+                msg!("Oracle type {:?} has no confidence interval", price_type);
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            let max_confidence_bps: u16 = read_u64(&value, 0)?
+                .try_into()
+                .map_err(|_| ScopeError::InvalidUpdateValue)?;
+            let policy = u8::try_from(read_u64(&value, 8)?)
+                .ok()
+                .and_then(|policy| ConfidencePolicy::try_from(policy).ok())
+                .ok_or(ScopeError::InvalidUpdateValue)?;
+            msg!(
+                "Setting max confidence for token {} to {} bps ({:?})",
+                token,
+                max_confidence_bps,
+                policy
+            );
+            oracle_mappings.max_confidences_bps[token] = max_confidence_bps;
+            oracle_mappings.confidence_policies[token] = policy as u8;
+        }
+        UpdateMappingConfigMode::PriceBounds => {
+            let exp = read_u64(&value, 16)?;
+            let min = Price {
+                value: read_u64(&value, 0)?,
+                exp,
+            };
+            let max = Price {
+                value: read_u64(&value, 8)?,
+                exp,
+            };
+            if max.value != 0 && min.value > max.value {
+                msg!(
+                    "Lower bound {} is above the upper bound {}",
+                    min.value,
+                    max.value
+                );
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            msg!(
+                "Setting price bounds for token {} to [{}, {}] (exp {})",
+                token,
+                min.value,
+                max.value,
+                exp
+            );
+            oracle_mappings.price_bounds[token] = [min, max];
+        }
+        UpdateMappingConfigMode::PythLazerFeedId => {
+            if oracle_mappings.price_types[token] != u8::from(OracleType::PythLazer) {
+                msg!("Token {} is not a Pyth Lazer price", token);
+                return err!(ScopeError::InvalidUpdateValue);
+            }
+            let feed_id: u32 = read_u64(&value, 0)?
+                .try_into()
+                .map_err(|_| ScopeError::InvalidUpdateValue)?;
+            msg!(
+                "Setting Pyth Lazer feed id for token {} to {}",
+                token,
+                feed_id
+            );
+            oracle_mappings.pyth_lazer_feed_ids[token] = feed_id;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_u16(value: &[u8], offset: usize) -> Result<u16> {
+    let bytes = value
+        .get(offset..offset + 2)
+        .ok_or(ScopeError::InvalidUpdateValue)?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(value: &[u8], offset: usize) -> Result<u64> {
+    let bytes = value
+        .get(offset..offset + 8)
+        .ok_or(ScopeError::InvalidUpdateValue)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_pubkey(value: &[u8], offset: usize) -> Result<Pubkey> {
+    let bytes = value
+        .get(offset..offset + 32)
+        .ok_or(ScopeError::InvalidUpdateValue)?;
+    Ok(Pubkey::new_from_array(bytes.try_into().unwrap()))
+}
@@ -1,11 +1,45 @@
+pub mod handler_accept_admin;
+pub mod handler_close_feed;
+pub mod handler_get_price;
 pub mod handler_initialize;
+pub mod handler_initialize_oracle_index;
+pub mod handler_initialize_oracle_twaps;
 pub mod handler_initialize_tokens_metadata;
+pub mod handler_migrate_feed;
 pub mod handler_refresh_prices;
+pub mod handler_replace_all_mappings;
+pub mod handler_reset_oracle_mapping;
+pub mod handler_reset_twap;
+pub mod handler_set_admin;
+pub mod handler_set_all_max_source_ages;
+pub mod handler_set_ema_half_life;
+pub mod handler_set_max_feed_staleness;
+pub mod handler_set_refresh_authority;
+pub mod handler_update_index_constituents;
 pub mod handler_update_mapping;
+pub mod handler_update_mapping_batch;
+pub mod handler_update_mapping_config;
 pub mod handler_update_token_metadata;
 
+pub use handler_accept_admin::*;
+pub use handler_close_feed::*;
+pub use handler_get_price::*;
 pub use handler_initialize::*;
+pub use handler_initialize_oracle_index::*;
+pub use handler_initialize_oracle_twaps::*;
 pub use handler_initialize_tokens_metadata::*;
+pub use handler_migrate_feed::*;
 pub use handler_refresh_prices::*;
+pub use handler_replace_all_mappings::*;
+pub use handler_reset_oracle_mapping::*;
+pub use handler_reset_twap::*;
+pub use handler_set_admin::*;
+pub use handler_set_all_max_source_ages::*;
+pub use handler_set_ema_half_life::*;
+pub use handler_set_max_feed_staleness::*;
+pub use handler_set_refresh_authority::*;
+pub use handler_update_index_constituents::*;
 pub use handler_update_mapping::*;
+pub use handler_update_mapping_batch::*;
+pub use handler_update_mapping_config::*;
 pub use handler_update_token_metadata::*;
@@ -28,6 +28,16 @@ pub const MAX_ENTRIES_U16: u16 = 512;
 // Note: Need to be directly integer value to not confuse the IDL generator
 pub const MAX_ENTRIES: usize = 512;
 pub const VALUE_BYTE_ARRAY_LEN: usize = 32;
+// Note: Need to be directly integer value to not confuse the IDL generator
+pub const MAX_TWAP_SAMPLES: usize = 32;
+// Note: Need to be directly integer value to not confuse the IDL generator
+pub const MAX_INDEX_CONSTITUENTS: usize = 16;
+/// Max age in slots of the stored price for a refreshed price to be checked against it
+/// (see [`UpdateMappingConfigMode::MaxDeviation`])
+pub const PRICE_DEVIATION_WINDOW_SLOTS: u64 = 150;
+/// Max number of slots a source price can be published ahead of the `Clock` sysvar, a price
+/// further in the future is rejected at refresh
+pub const MAX_SOURCE_SLOT_DRIFT: u64 = 10;
 
 #[program]
 pub mod scope {
@@ -40,6 +50,17 @@ pub mod scope {
         handler_initialize::process(ctx, feed_name)
     }
 
+    /// Close the accounts of a feed and send their lamports to `destination`
+    pub fn close_feed(ctx: Context<CloseFeed>, feed_name: String) -> Result<()> {
+        handler_close_feed::process(ctx, feed_name)
+    }
+
+    /// Upgrade the accounts of a feed created with a previous layout, to repeat until they have
+    /// their current size
+    pub fn migrate_feed(ctx: Context<MigrateFeed>, feed_name: String) -> Result<()> {
+        handler_migrate_feed::process(ctx, feed_name)
+    }
+
     pub fn initialize_tokens_metadata(
         ctx: Context<InitializeTokensMetadata>,
         feed_name: String,
@@ -59,16 +80,143 @@ pub mod scope {
         handler_refresh_prices::refresh_price_list(ctx, &tokens)
     }
 
+    pub fn refresh_price_list_lenient(ctx: Context<RefreshList>, tokens: Vec<u16>) -> Result<()> {
+        handler_refresh_prices::refresh_price_list_lenient(ctx, &tokens)
+    }
+
     pub fn update_mapping(
         ctx: Context<UpdateOracleMapping>,
         token: u64,
         price_type: u8,
         feed_name: String,
+        price_max_age: u64,
+    ) -> Result<()> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_update_mapping::process(ctx, token, price_type, feed_name, price_max_age)
+    }
+
+    /// Remove the mapping of a token and clear its stored price
+    pub fn reset_oracle_mapping(
+        ctx: Context<ResetOracleMapping>,
+        token: u64,
+        feed_name: String,
+    ) -> Result<()> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_reset_oracle_mapping::process(ctx, token, feed_name)
+    }
+
+    pub fn replace_all_mappings(
+        ctx: Context<ReplaceAllMappings>,
+        feed_name: String,
+        mappings: Vec<MappingEntry>,
+    ) -> Result<()> {
+        handler_replace_all_mappings::process(ctx, &mappings, feed_name)
+    }
+
+    pub fn update_mapping_batch(
+        ctx: Context<UpdateMappingBatch>,
+        feed_name: String,
+        first_token: u16,
+        price_types: Vec<u8>,
+    ) -> Result<()> {
+        handler_update_mapping_batch::process(ctx, first_token, &price_types, feed_name)
+    }
+
+    pub fn set_all_max_source_ages(
+        ctx: Context<SetAllMaxSourceAges>,
+        feed_name: String,
+        default_max_age: u64,
+        overrides: Vec<MaxSourceAgeOverride>,
+    ) -> Result<()> {
+        handler_set_all_max_source_ages::process(ctx, default_max_age, &overrides, feed_name)
+    }
+
+    pub fn set_refresh_authority(
+        ctx: Context<SetRefreshAuthority>,
+        feed_name: String,
+        refresh_authority: Pubkey,
+    ) -> Result<()> {
+        handler_set_refresh_authority::process(ctx, refresh_authority, feed_name)
+    }
+
+    /// Propose a new admin of the feed, see [`accept_admin`]
+    pub fn set_admin(
+        ctx: Context<SetAdmin>,
+        feed_name: String,
+        pending_admin: Pubkey,
+    ) -> Result<()> {
+        handler_set_admin::process(ctx, pending_admin, feed_name)
+    }
+
+    /// Become the admin of the feed, signed by the pending admin set with [`set_admin`]
+    pub fn accept_admin(ctx: Context<AcceptAdmin>, feed_name: String) -> Result<()> {
+        handler_accept_admin::process(ctx, feed_name)
+    }
+
+    pub fn set_max_feed_staleness(
+        ctx: Context<SetMaxFeedStaleness>,
+        feed_name: String,
+        max_feed_staleness_slots: u64,
+    ) -> Result<()> {
+        handler_set_max_feed_staleness::process(ctx, max_feed_staleness_slots, feed_name)
+    }
+
+    pub fn initialize_oracle_index(
+        ctx: Context<InitializeOracleIndex>,
+        feed_name: String,
+    ) -> Result<()> {
+        handler_initialize_oracle_index::process(ctx, feed_name)
+    }
+
+    /// Replace the constituents of an index, see [`oracles::index_weighted`]
+    pub fn update_index_constituents(
+        ctx: Context<UpdateIndexConstituents>,
+        feed_name: String,
+        constituents: Vec<IndexConstituent>,
+        max_constituent_age_slots: u64,
+    ) -> Result<()> {
+        handler_update_index_constituents::process(
+            ctx,
+            &constituents,
+            max_constituent_age_slots,
+            feed_name,
+        )
+    }
+
+    pub fn initialize_oracle_twaps(
+        ctx: Context<InitializeOracleTwaps>,
+        feed_name: String,
+    ) -> Result<()> {
+        handler_initialize_oracle_twaps::process(ctx, feed_name)
+    }
+
+    pub fn reset_twap(
+        ctx: Context<ResetTwap>,
+        token: u64,
+        window_size: u64,
+        feed_name: String,
     ) -> Result<()> {
         let token: usize = token
             .try_into()
             .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
-        handler_update_mapping::process(ctx, token, price_type, feed_name)
+        handler_reset_twap::process(ctx, token, window_size, feed_name)
+    }
+
+    /// Clear the EMA of a token and set its half-life, 0 means the default half-life
+    pub fn set_ema_half_life(
+        ctx: Context<SetEmaHalfLife>,
+        token: u64,
+        half_life_slots: u64,
+        feed_name: String,
+    ) -> Result<()> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_set_ema_half_life::process(ctx, token, half_life_slots, feed_name)
     }
 
     pub fn update_token_metadata(
@@ -89,10 +237,96 @@ pub mod scope {
             .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
         handler_update_token_metadata::process(ctx, index, mode, value, feed_name)
     }
+
+    pub fn update_mapping_config(
+        ctx: Context<UpdateMappingConfig>,
+        token: u64,
+        mode: u64,
+        feed_name: String,
+        value: Vec<u8>,
+    ) -> Result<()> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_update_mapping_config::process(ctx, token, mode, value, feed_name)
+    }
+
+    /// Read the price of a token, failing if it is older than `max_age_seconds`
+    pub fn get_price(
+        ctx: Context<GetPrice>,
+        token: u64,
+        max_age_seconds: u64,
+    ) -> Result<DatedPrice> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_get_price::get_price(ctx, token, max_age_seconds)
+    }
+
+    /// Read the prices of a list of tokens (at most [`MAX_GET_PRICES_TOKENS`]), in order,
+    /// failing if any of them is older than `max_age_seconds`
+    pub fn get_prices(
+        ctx: Context<GetPrice>,
+        tokens: Vec<u16>,
+        max_age_seconds: u64,
+    ) -> Result<Vec<DatedPrice>> {
+        handler_get_price::get_prices(ctx, &tokens, max_age_seconds)
+    }
+
+    /// Tell if the price of a token exists and is not older than `max_age_seconds`, returns
+    /// `false` instead of failing like [`get_price`]
+    pub fn is_price_usable(
+        ctx: Context<GetPrice>,
+        token: u64,
+        max_age_seconds: u64,
+    ) -> Result<bool> {
+        handler_get_price::is_price_usable(ctx, token, max_age_seconds)
+    }
+
+    /// Same as [`get_price`] with the staleness evaluated against the caller's clock
+    pub fn get_price_with_clock(
+        ctx: Context<GetPrice>,
+        token: u64,
+        max_age_seconds: u64,
+        caller_clock: CallerClock,
+    ) -> Result<DatedPrice> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_get_price::get_price_with_clock(ctx, token, max_age_seconds, caller_clock)
+    }
+
+    /// Same as [`get_price`] with the price expressed with the `target_exp` exponent
+    pub fn get_price_rescaled(
+        ctx: Context<GetPrice>,
+        token: u64,
+        max_age_seconds: u64,
+        target_exp: u8,
+    ) -> Result<DatedPrice> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_get_price::get_price_rescaled(ctx, token, max_age_seconds, target_exp)
+    }
+
+    /// Read the most conservative of the spot price, the TWAP over `window` samples and the EMA
+    /// of a token for the given side, failing if any of them is older than `max_age_seconds`
+    pub fn get_conservative_price(
+        ctx: Context<GetConservativePrice>,
+        token: u64,
+        side: PriceSide,
+        window: u64,
+        max_age_seconds: u64,
+    ) -> Result<DatedPrice> {
+        let token: usize = token
+            .try_into()
+            .map_err(|_| ScopeError::OutOfRangeIntegralConversion)?;
+        handler_get_price::get_conservative_price(ctx, token, side, window, max_age_seconds)
+    }
 }
 
 #[zero_copy]
-#[derive(Debug, Eq, PartialEq, Default)]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Eq, PartialEq, Default)]
 pub struct Price {
     // Pyth price, integer + exponent representation
     // decimal price would be
@@ -109,15 +343,25 @@ pub struct Price {
 }
 
 #[zero_copy]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Eq, PartialEq)]
 pub struct DatedPrice {
     pub price: Price,
     pub last_updated_slot: u64,
     pub unix_timestamp: u64,
-    pub _reserved: [u64; 2],
-    pub _reserved2: [u16; 3],
+    /// Data specific to the oracle type of the price (e.g. the winning source of
+    /// [`oracles::OracleType::MostRecentOf`] prices)
+    pub generic_data: [u8; 16],
+    /// Properties of the price set at refresh (e.g. [`DatedPrice::FLAG_LOW_CONFIDENCE`])
+    pub flags: u16,
+    /// Last status seen on the source account of the price, stored even when the refresh is
+    /// skipped (see [`oracles::pyth::SourceStatus`]). 0 for the oracle types without one.
+    pub source_status: u16,
+    pub _reserved2: [u16; 1],
     // Current index of the dated price.
     pub index: u16,
+    /// Confidence interval (Pyth) or standard deviation (Switchboard) of the price, with the
+    /// exponent of `price`. 0 for the oracle types without one.
+    pub conf: u64,
 }
 
 impl Default for DatedPrice {
@@ -126,18 +370,60 @@ impl Default for DatedPrice {
             price: Default::default(),
             last_updated_slot: Default::default(),
             unix_timestamp: Default::default(),
-            _reserved: Default::default(),
+            generic_data: Default::default(),
+            flags: 0,
+            source_status: 0,
             _reserved2: Default::default(),
             index: MAX_ENTRIES_U16,
+            conf: 0,
         }
     }
 }
 
+impl DatedPrice {
+    /// The confidence interval of the price was wider than the max ratio of its entry, which
+    /// is configured to only flag such prices (see [`UpdateMappingConfigMode::MaxConfidence`])
+    pub const FLAG_LOW_CONFIDENCE: u16 = 1;
+
+    /// Whether the price was stored with [`DatedPrice::FLAG_LOW_CONFIDENCE`]
+    pub fn is_low_confidence(&self) -> bool {
+        self.flags & Self::FLAG_LOW_CONFIDENCE != 0
+    }
+
+    /// Number of slots elapsed since the price was updated
+    ///
+    /// A price updated in the future of `current_slot` has an age of 0.
+    pub fn age_slots(&self, current_slot: u64) -> u64 {
+        current_slot.saturating_sub(self.last_updated_slot)
+    }
+
+    /// Whether the price was updated more than `max_age_slots` before `current_slot`
+    pub fn is_stale(&self, current_slot: u64, max_age_slots: u64) -> bool {
+        self.age_slots(current_slot) > max_age_slots
+    }
+}
+
 // Account to store dated prices
 #[account(zero_copy)]
 pub struct OraclePrices {
     pub oracle_mappings: Pubkey,
     pub prices: [DatedPrice; MAX_ENTRIES],
+    /// Layout version of the account, see [`OraclePrices::VERSION`]
+    pub version: u64,
+    /// TWAP account of the feed, the refreshes must provide it when set
+    pub oracle_twaps: Pubkey,
+    /// Max number of slots since the last update of any price of the feed for the reads to
+    /// succeed, 0 disables the check
+    pub max_feed_staleness_slots: u64,
+    pub _reserved: [u64; 59],
+}
+
+impl OraclePrices {
+    /// Current layout version, the accounts of a previous version are upgraded by `migrate_feed`
+    ///
+    /// - 0: prices of 56 bytes, without `DatedPrice::conf`
+    /// - 1: prices of 64 bytes, followed by the version and a reserve
+    pub const VERSION: u64 = 1;
 }
 
 // Accounts holding source of prices
@@ -145,7 +431,165 @@ pub struct OraclePrices {
 pub struct OracleMappings {
     pub price_info_accounts: [Pubkey; MAX_ENTRIES],
     pub price_types: [u8; MAX_ENTRIES],
-    pub _reserved2: [u64; MAX_ENTRIES],
+    /// Maximum price stored for each token at refresh (see [`UpdateMappingConfigMode::HardCap`])
+    pub hard_caps: [Price; MAX_ENTRIES],
+    /// Max age of the source price at refresh (see [`UpdateMappingConfigMode::MaxSourceAge`])
+    pub max_source_ages: [u64; MAX_ENTRIES],
+    /// Identity the source account must embed at refresh, default pubkey when not pinned
+    /// (see [`UpdateMappingConfigMode::SourceIdentity`])
+    pub source_identities: [Pubkey; MAX_ENTRIES],
+    /// Tokens of the feed pricing the underlying tokens of an LP token
+    /// (see [`UpdateMappingConfigMode::UnderlyingPrices`])
+    pub underlying_prices: [[u16; 2]; MAX_ENTRIES],
+    /// Max age in slots of the pool AUM of a Jupiter LP token
+    /// (see [`UpdateMappingConfigMode::MaxAumAge`])
+    pub max_aum_ages: [u32; MAX_ENTRIES],
+    /// Only account allowed to refresh the prices, anyone can refresh them when it is the
+    /// default pubkey
+    pub refresh_authority: Pubkey,
+    /// Max standard deviation of the oracle results in bps of a Switchboard V2 price
+    /// (see [`UpdateMappingConfigMode::MaxStdDeviation`])
+    pub max_std_deviations_bps: [u16; MAX_ENTRIES],
+    /// Max change in bps of a refreshed price relative to the stored one
+    /// (see [`UpdateMappingConfigMode::MaxDeviation`])
+    pub max_deviations_bps: [u16; MAX_ENTRIES],
+    /// Max confidence/price ratio in bps of a price having a confidence interval
+    /// (see [`UpdateMappingConfigMode::MaxConfidence`])
+    pub max_confidences_bps: [u16; MAX_ENTRIES],
+    /// Handling of a price exceeding its max confidence/price ratio, a
+    /// [`oracles::pyth::ConfidencePolicy`] (see [`UpdateMappingConfigMode::MaxConfidence`])
+    pub confidence_policies: [u8; MAX_ENTRIES],
+    /// Max age in slots of the source price at refresh, 0 for no limit (set by
+    /// `update_mapping`)
+    pub price_max_ages: [u64; MAX_ENTRIES],
+    /// Lowest and highest price accepted at refresh
+    /// (see [`UpdateMappingConfigMode::PriceBounds`])
+    pub price_bounds: [[Price; 2]; MAX_ENTRIES],
+    /// Layout version of the account, see [`OracleMappings::VERSION`]
+    pub version: u64,
+    /// Feed id a Pyth Lazer update must have at refresh
+    /// (see [`UpdateMappingConfigMode::PythLazerFeedId`])
+    pub pyth_lazer_feed_ids: [u32; MAX_ENTRIES],
+    pub _reserved2: [u32; MAX_ENTRIES],
+}
+
+impl OracleMappings {
+    /// Current layout version, the accounts of a previous version are upgraded by `migrate_feed`
+    ///
+    /// - 0: price info accounts and price types followed by a reserve of one `u64` per token
+    /// - 1: per-token refresh configuration stored after the price types, the reserve is moved
+    ///   to the end of the account
+    pub const VERSION: u64 = 1;
+}
+
+/// Sample of a price used to compute a TWAP
+#[zero_copy]
+#[derive(Debug, Eq, PartialEq, Default)]
+pub struct TwapSample {
+    pub value: u64,
+    pub unix_timestamp: u64,
+}
+
+/// Ring buffer of the last refreshed prices of a token and the TWAP computed over them, with
+/// the EMA of the refreshed prices
+#[zero_copy]
+#[derive(Debug, Eq, PartialEq, Default)]
+pub struct TwapBuffer {
+    pub samples: [TwapSample; MAX_TWAP_SAMPLES],
+    // Exponent of all the samples
+    pub exp: u64,
+    // Index in `samples` where the next sample is written
+    pub next_index: u64,
+    pub num_samples: u64,
+    // Number of samples used for the TWAP, 0 means `MAX_TWAP_SAMPLES`
+    pub window_size: u64,
+    pub twap: DatedPrice,
+    // Half-life of the EMA in slots, 0 means `utils::ema::DEFAULT_EMA_HALF_LIFE_SLOTS`
+    pub ema_half_life_slots: u64,
+    pub ema: DatedPrice,
+}
+
+// Account to store the TWAP and the EMA of the prices of a feed
+#[account(zero_copy)]
+pub struct OracleTwaps {
+    pub oracle_prices: Pubkey,
+    pub twaps: [TwapBuffer; MAX_ENTRIES],
+}
+
+/// Constituent of an [`oracles::OracleType::IndexWeighted`] price
+#[zero_copy]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Eq, PartialEq, Default)]
+pub struct IndexConstituent {
+    /// Token of the feed giving the price of the constituent
+    pub token: u64,
+    /// Quantity of the constituent in one unit of the index
+    pub quantity: Price,
+}
+
+// Account to store the constituents of an index price of a feed
+#[account(zero_copy)]
+pub struct OracleIndex {
+    pub oracle_prices: Pubkey,
+    /// Max age in slots of the constituent prices at refresh, 0 disables the check
+    pub max_constituent_age_slots: u64,
+    /// Number of used entries at the start of `constituents`
+    pub num_constituents: u64,
+    pub constituents: [IndexConstituent; MAX_INDEX_CONSTITUENTS],
+}
+
+/// Reference clock provided by a caller of [`scope::get_price_with_clock`]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallerClock {
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+/// Side of a position priced by [`scope::get_conservative_price`]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSide {
+    /// The lowest of the available prices is used
+    Collateral,
+    /// The highest of the available prices is used
+    Debt,
+}
+
+/// New oracle mapping of a token, used to replace all the mappings at once
+///
+/// The price info account is provided as a remaining account of the instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappingEntry {
+    pub token: u16,
+    pub price_type: u8,
+}
+
+/// Max source age of a token, in seconds (see [`UpdateMappingConfigMode::MaxSourceAge`])
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxSourceAgeOverride {
+    pub token: u16,
+    pub max_age: u64,
+}
+
+/// Emitted for each token whose price is stored by a refresh instruction
+///
+/// Refresh events are not emitted when built with the `skip_refresh_events` feature.
+#[event]
+#[derive(Debug, PartialEq, Eq)]
+pub struct PriceRefreshed {
+    pub token: u16,
+    pub value: u64,
+    pub exp: u64,
+    pub slot: u64,
+    pub timestamp: u64,
+}
+
+/// Emitted for each token of a list refresh whose price is not stored because it failed
+/// validation or its accounts are invalid
+#[event]
+#[derive(Debug, PartialEq, Eq)]
+pub struct PriceRefreshSkipped {
+    pub token: u16,
+    /// Code of the error rejecting the price (e.g. 6000 + [`ScopeError::StalePrice`])
+    pub reason: u64,
 }
 
 #[account(zero_copy)]
@@ -168,7 +612,11 @@ pub struct Configuration {
     pub oracle_mappings: Pubkey,
     pub oracle_prices: Pubkey,
     pub tokens_metadata: Pubkey,
-    _padding: [u64; 1263],
+    pub oracle_twaps: Pubkey,
+    /// Admin proposed by the current admin, it has to accept to become the admin, the default
+    /// pubkey when there is no proposal
+    pub pending_admin: Pubkey,
+    _padding: [u64; 1255],
 }
 
 #[derive(TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug)]
@@ -187,6 +635,110 @@ impl UpdateTokenMetadataMode {
     }
 }
 
+/// Per token configuration stored in [`OracleMappings`] alongside the price source.
+#[derive(TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug)]
+#[repr(u64)]
+pub enum UpdateMappingConfigMode {
+    /// Clamp the refreshed price to a maximum value instead of rejecting it.
+    ///
+    /// Value is the cap `value` followed by its `exp`, both as little endian u64.
+    /// A cap `value` of 0 disables the cap.
+    ///
+    /// Use it for assets that can never be worth more than a known amount (e.g. a stable
+    /// redeemable for at most 1 USD): a source reporting a higher price is still usable and
+    /// the capped value is the correct one for consumers. Do not use it to filter out faulty
+    /// sources, a clamped price stays "fresh" and hides the issue; rejecting the price (and
+    /// letting it become stale) is the right behavior in that case.
+    HardCap = 0,
+    /// Map the token to a constant price (see [`oracles::fixed_price`]).
+    ///
+    /// Value is the price `value` followed by its `exp`, both as little endian u64.
+    FixedPrice = 1,
+    /// Reject refreshed prices older than a number of seconds.
+    ///
+    /// Value is the max age in seconds as little endian u64, 0 disables the check.
+    ///
+    /// The age is the difference between the current clock and the timestamp reported by the
+    /// source (e.g. the observations timestamp of a Chainlink round).
+    MaxSourceAge = 2,
+    /// Pin the feed identity embedded in the source account (see
+    /// [`oracles::get_source_identity`]), only available for oracle types having one.
+    ///
+    /// Value is the identity pubkey (32 bytes), the default pubkey removes the pin.
+    ///
+    /// The pin is kept when the source account of the token is changed: a source account
+    /// swapped for another feed is rejected at refresh.
+    SourceIdentity = 3,
+    /// Set the tokens of the feed giving the prices of the two underlying tokens of an LP
+    /// token, only available for [`oracles::OracleType::ConstantProductLp`] entries.
+    ///
+    /// Value is the token of the pool token A followed by the one of the pool token B, both
+    /// as little endian u16. They must be different and different from the LP token itself.
+    UnderlyingPrices = 4,
+    /// Reject the price of a Jupiter LP token if the pool AUM was updated more than a number
+    /// of slots ago, only available for [`oracles::OracleType::JupiterLp`] entries.
+    ///
+    /// Value is the max age in slots as little endian u64, it must fit in a u32. 0 restores the
+    /// default max age (see [`oracles::jupiter_lp::DEFAULT_MAX_AUM_AGE_SLOTS`]).
+    MaxAumAge = 5,
+    /// Reject a Switchboard V2 price if the standard deviation of the oracle results of the
+    /// round reaches a ratio of the result, only available for
+    /// [`oracles::OracleType::SwitchboardV2`] entries.
+    ///
+    /// Value is the max ratio in bps as little endian u64, it must fit in a u16. 0 restores the
+    /// default ratio (see [`oracles::switchboard_v2::DEFAULT_MAX_STD_DEVIATION_BPS`]).
+    MaxStdDeviation = 6,
+    /// Reject a refreshed price deviating from the stored one by more than a ratio of the
+    /// stored price, protecting against a single corrupted update of the source.
+    ///
+    /// Value is the max deviation in bps as little endian u64, it must fit in a u16. 0 disables
+    /// the check.
+    ///
+    /// The check is skipped when the stored price is older than
+    /// [`PRICE_DEVIATION_WINDOW_SLOTS`], a legitimate move of the price would otherwise be
+    /// rejected forever.
+    MaxDeviation = 7,
+    /// Set the max confidence/price ratio of a price and how a wider confidence interval is
+    /// handled, only available for entries having a confidence interval (Pyth based types).
+    ///
+    /// Value is the max ratio in bps as little endian u64, it must fit in a u16, followed by the
+    /// [`oracles::pyth::ConfidencePolicy`] as little endian u64. A ratio of 0 restores the
+    /// default ratio (see [`oracles::pyth::ORACLE_CONFIDENCE_MAX_BPS`]). With the `FlagOnly`
+    /// policy a price with a wider interval is stored with [`DatedPrice::FLAG_LOW_CONFIDENCE`].
+    MaxConfidence = 8,
+    /// Reject a refreshed price outside of a range of values, a circuit breaker against a
+    /// source reporting an impossible price.
+    ///
+    /// Value is the lowest accepted `value`, the highest accepted `value` and their `exp`, all
+    /// as little endian u64. A lowest value of 0 disables the lower bound, a highest value of 0
+    /// or `u64::MAX` disables the upper bound.
+    PriceBounds = 9,
+    /// Set the feed id of the Pyth Lazer updates of a token, only available for
+    /// [`oracles::OracleType::PythLazer`] entries.
+    ///
+    /// Value is the feed id as little endian u64, it must fit in a u32. An update of another
+    /// feed is rejected at refresh, as are all updates of a token without feed id (0).
+    PythLazerFeedId = 10,
+}
+
+impl UpdateMappingConfigMode {
+    pub fn to_u64(self) -> u64 {
+        match self {
+            UpdateMappingConfigMode::HardCap => 0,
+            UpdateMappingConfigMode::FixedPrice => 1,
+            UpdateMappingConfigMode::MaxSourceAge => 2,
+            UpdateMappingConfigMode::SourceIdentity => 3,
+            UpdateMappingConfigMode::UnderlyingPrices => 4,
+            UpdateMappingConfigMode::MaxAumAge => 5,
+            UpdateMappingConfigMode::MaxStdDeviation => 6,
+            UpdateMappingConfigMode::MaxDeviation => 7,
+            UpdateMappingConfigMode::MaxConfidence => 8,
+            UpdateMappingConfigMode::PriceBounds => 9,
+            UpdateMappingConfigMode::PythLazerFeedId => 10,
+        }
+    }
+}
+
 #[error_code]
 #[derive(PartialEq, Eq, FromPrimitive)]
 pub enum ScopeError {
@@ -237,6 +789,69 @@ pub enum ScopeError {
 
     #[msg("Invalid token metadata update mode")]
     InvalidTokenUpdateMode,
+
+    #[msg("Invalid oracle mapping config update mode")]
+    InvalidMappingConfigUpdateMode,
+
+    #[msg("Invalid value for the requested update")]
+    InvalidUpdateValue,
+
+    #[msg("TWAP window size is bigger than the number of samples that can be stored")]
+    BadTwapWindowSize,
+
+    #[msg("The same token is mapped more than once")]
+    DuplicatedMappingToken,
+
+    #[msg("Price is older than the requested max age")]
+    StalePrice,
+
+    #[msg("Caller provided clock is inconsistent with the current clock")]
+    InvalidCallerClock,
+
+    #[msg("Derived price depends on itself")]
+    CyclicDerivedPrice,
+
+    #[msg("Derived price is refreshed before its sources")]
+    DerivedPriceBeforeSources,
+
+    #[msg("Source account identity does not match the pinned identity")]
+    SourceIdentityMismatch,
+
+    #[msg("Stable swap invariant computation did not converge")]
+    InvariantNotConverged,
+
+    #[msg("Prices can only be refreshed by the refresh authority of the feed")]
+    UnauthorizedRefresh,
+
+    #[msg("The oracles of the source disagree too much on the price")]
+    OracleDisagreement,
+
+    #[msg("Price cannot be expressed with the requested exponent without losing its value")]
+    PrecisionLoss,
+
+    #[msg("TWAP of the token is not maintained with the requested window size")]
+    TwapWindowMismatch,
+
+    #[msg("The cap of the price band is below its floor")]
+    InvalidPriceBand,
+
+    #[msg("No price of the feed has been refreshed recently")]
+    StaleFeed,
+
+    #[msg("Signed payload is not verified for a trusted signer")]
+    InvalidPayloadSignature,
+
+    #[msg("Alias price references another alias or is referenced by one")]
+    ChainedAlias,
+
+    #[msg("Signer is not the pending admin of the feed")]
+    NotPendingAdmin,
+
+    #[msg("Price is out of the bounds of the token")]
+    PriceOutOfBounds,
+
+    #[msg("Confidence interval of the price is too wide")]
+    PriceConfidenceTooWide,
 }
 
 impl<T> From<TryFromPrimitiveError<T>> for ScopeError
@@ -263,3 +878,32 @@ impl From<DecimalError> for ScopeError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price_at(last_updated_slot: u64) -> DatedPrice {
+        DatedPrice {
+            last_updated_slot,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dated_price_age() {
+        assert_eq!(price_at(100).age_slots(100), 0);
+        assert_eq!(price_at(100).age_slots(150), 50);
+        // Updated in the future of the given slot
+        assert_eq!(price_at(100).age_slots(90), 0);
+    }
+
+    #[test]
+    fn test_dated_price_is_stale() {
+        // Exactly at max age is not stale
+        assert!(!price_at(100).is_stale(110, 10));
+        assert!(price_at(100).is_stale(111, 10));
+        assert!(!price_at(100).is_stale(90, 0));
+        assert!(!price_at(0).is_stale(u64::MAX, u64::MAX));
+    }
+}
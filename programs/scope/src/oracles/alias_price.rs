@@ -0,0 +1,169 @@
+//! Copy of another price of the same scope feed
+//!
+//! Used for assets wrapped 1:1 (e.g. wSOL or bridged tokens) that should mirror the entry of
+//! their underlying. The stored price is the source `DatedPrice` as is: value, exponent, dates,
+//! generic data and flags, only its index is the one of the alias.
+//!
+//! As for [`super::derived_price`], the source token is encoded in the pubkey referenced in the
+//! oracle mapping (see [`source_to_mapping_pubkey`]) and the source must be refreshed before the
+//! alias. Aliases are resolved one level only: an alias of an alias is rejected when the mapping
+//! is updated (see [`check_no_alias_chain`]).
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::{derived_price::get_source_price, OracleType},
+    DatedPrice, OracleMappings, OraclePrices, Result, ScopeError,
+};
+
+/// Marker byte of the encoded source
+const SOURCE_MARKER: u8 = 6;
+
+/// Encode the source token of an alias in a pubkey to store it in the oracle mapping
+pub fn source_to_mapping_pubkey(source: u16) -> Pubkey {
+    let mut bytes = [0_u8; 32];
+    bytes[..2].copy_from_slice(&source.to_le_bytes());
+    bytes[2] = SOURCE_MARKER;
+    Pubkey::new_from_array(bytes)
+}
+
+/// Decode the source token of an alias stored in the oracle mapping
+pub fn source_from_mapping_pubkey(pubkey: &Pubkey) -> Result<u16> {
+    let bytes = pubkey.to_bytes();
+    if bytes[2] != SOURCE_MARKER || bytes[3..].iter().any(|&b| b != 0) {
+        msg!("Alias price mapping {} is not an encoded source", pubkey);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let source = u16::from_le_bytes(bytes[..2].try_into().unwrap());
+    if usize::from(source) >= crate::MAX_ENTRIES {
+        msg!("Alias price source out of range: {}", source);
+        return err!(ScopeError::BadTokenNb);
+    }
+    Ok(source)
+}
+
+pub fn get_price(base_account: &AccountInfo, oracle_prices: &OraclePrices) -> Result<DatedPrice> {
+    let source = source_from_mapping_pubkey(base_account.key)?;
+    get_source_price(oracle_prices, source)
+}
+
+/// Check that an alias price mapping is an encoded source
+pub fn validate_mapping_account(mapping_account: &AccountInfo) -> Result<()> {
+    source_from_mapping_pubkey(mapping_account.key).map(|_| ())
+}
+
+/// Check that the alias `token` neither references an alias nor is referenced by one
+///
+/// To be called once the new mapping of `token` is written.
+pub fn check_no_alias_chain(oracle_mappings: &OracleMappings, token: usize) -> Result<()> {
+    let is_alias = |token: usize| oracle_mappings.price_types[token] == u8::from(OracleType::Alias);
+    if !is_alias(token) {
+        return Ok(());
+    }
+    let source = source_from_mapping_pubkey(&oracle_mappings.price_info_accounts[token])?;
+    if is_alias(source.into()) {
+        msg!("Alias {} references the alias {}", token, source);
+        return err!(ScopeError::ChainedAlias);
+    }
+    let referencing_alias = (0..crate::MAX_ENTRIES).find(|&other| {
+        other != token
+            && is_alias(other)
+            && source_from_mapping_pubkey(&oracle_mappings.price_info_accounts[other])
+                .map_or(false, |other_source| usize::from(other_source) == token)
+    });
+    if let Some(other) = referencing_alias {
+        msg!("Alias {} is referenced by the alias {}", token, other);
+        return err!(ScopeError::ChainedAlias);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::__private::bytemuck::Zeroable;
+
+    use super::*;
+    use crate::{oracles::scope_twap, Price};
+
+    fn map_alias(mappings: &mut OracleMappings, token: usize, source: u16) {
+        mappings.price_info_accounts[token] = source_to_mapping_pubkey(source);
+        mappings.price_types[token] = OracleType::Alias.into();
+    }
+
+    #[test]
+    fn test_encode_decode_source() {
+        for source in [0, 3, 511] {
+            assert_eq!(
+                source_from_mapping_pubkey(&source_to_mapping_pubkey(source)).unwrap(),
+                source
+            );
+        }
+        assert_eq!(
+            source_from_mapping_pubkey(&source_to_mapping_pubkey(512)).unwrap_err(),
+            ScopeError::BadTokenNb.into()
+        );
+        // Source of an EMA price
+        assert_eq!(
+            source_from_mapping_pubkey(&scope_twap::source_to_mapping_pubkey(1)).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    fn test_alias_is_an_exact_copy() {
+        let mut oracle_prices: Box<OraclePrices> = Box::new(Zeroable::zeroed());
+        let mut source = DatedPrice {
+            price: Price {
+                value: 123_456,
+                exp: 4,
+            },
+            last_updated_slot: 1_000,
+            unix_timestamp: 1_720_000_000,
+            flags: DatedPrice::FLAG_LOW_CONFIDENCE,
+            index: 3,
+            ..Default::default()
+        };
+        source.generic_data[0] = 7;
+        oracle_prices.prices[3] = source;
+
+        let key = source_to_mapping_pubkey(3);
+        let owner = Pubkey::default();
+        let mut lamports = 0;
+        let mut data = [];
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        assert_eq!(get_price(&account, &oracle_prices).unwrap(), source);
+    }
+
+    #[test]
+    fn test_alias_of_alias_is_rejected() {
+        let mut mappings: Box<OracleMappings> = Box::new(Zeroable::zeroed());
+        map_alias(&mut mappings, 1, 0);
+        map_alias(&mut mappings, 2, 0);
+        assert!(check_no_alias_chain(&mappings, 1).is_ok());
+        assert!(check_no_alias_chain(&mappings, 2).is_ok());
+
+        // Alias of an alias
+        map_alias(&mut mappings, 3, 1);
+        assert_eq!(
+            check_no_alias_chain(&mappings, 3).unwrap_err(),
+            ScopeError::ChainedAlias.into()
+        );
+
+        // Token becoming an alias while an alias references it
+        map_alias(&mut mappings, 5, 4);
+        map_alias(&mut mappings, 4, 0);
+        assert_eq!(
+            check_no_alias_chain(&mappings, 4).unwrap_err(),
+            ScopeError::ChainedAlias.into()
+        );
+    }
+}
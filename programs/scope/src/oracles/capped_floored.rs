@@ -0,0 +1,314 @@
+//! Price of another token of the same scope feed, clamped between a floor and a cap
+//!
+//! Used for pegged assets (e.g. stake pool tokens) whose source price can spike outside of its
+//! theoretical band. Each bound is optional and is either another price of the feed or a
+//! constant price. The stored price is `min(max(source, floor), cap)`, expressed with the
+//! exponent of the bound when clamped. Whether the source price was clamped is recorded in
+//! [`DatedPrice::generic_data`] (see [`clamping`]).
+//!
+//! As for [`super::derived_price`], the configuration is encoded in the pubkey referenced in the
+//! oracle mapping (see [`PriceBand::to_mapping_pubkey`]) and the source and bound prices must be
+//! refreshed before the clamped price. The clamped price is only as fresh as the oldest of them.
+//! A band whose constant cap is below its constant floor is rejected when the mapping is
+//! updated, a band with a price bound is checked at refresh.
+
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::derived_price::get_source_price, utils::math::cmp_prices, DatedPrice, OraclePrices,
+    Price, Result, ScopeError,
+};
+
+/// [`clamping`] of a price within its band
+pub const NOT_CLAMPED: u8 = 0;
+/// [`clamping`] of a price raised to the floor of its band
+pub const CLAMPED_TO_FLOOR: u8 = 1;
+/// [`clamping`] of a price lowered to the cap of its band
+pub const CLAMPED_TO_CAP: u8 = 2;
+
+/// Marker byte of the encoded band
+const BAND_MARKER: u8 = 3;
+/// Offset of the cap in the encoded band, the floor follows it
+const CAP_OFFSET: usize = 2;
+/// Size of an encoded bound: kind then token or constant price
+const BOUND_SIZE: usize = 10;
+const MARKER_OFFSET: usize = CAP_OFFSET + 2 * BOUND_SIZE;
+
+const BOUND_NONE: u8 = 0;
+const BOUND_TOKEN: u8 = 1;
+const BOUND_CONSTANT: u8 = 2;
+
+/// Cap or floor of a [`PriceBand`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Price of another token of the feed
+    Token(u16),
+    /// Constant price, its exponent must fit in a `u8`
+    Constant(Price),
+}
+
+/// Configuration of a clamped price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceBand {
+    /// Token whose price is clamped
+    pub source: u16,
+    pub cap: Option<Bound>,
+    pub floor: Option<Bound>,
+}
+
+impl PriceBand {
+    /// Encode the band in a pubkey to store it in the oracle mapping
+    ///
+    /// Panics if the exponent of a constant bound does not fit in a `u8`.
+    pub fn to_mapping_pubkey(&self) -> Pubkey {
+        let mut bytes = [0_u8; 32];
+        bytes[..2].copy_from_slice(&self.source.to_le_bytes());
+        encode_bound(&self.cap, &mut bytes[CAP_OFFSET..CAP_OFFSET + BOUND_SIZE]);
+        encode_bound(
+            &self.floor,
+            &mut bytes[CAP_OFFSET + BOUND_SIZE..MARKER_OFFSET],
+        );
+        bytes[MARKER_OFFSET] = BAND_MARKER;
+        Pubkey::new_from_array(bytes)
+    }
+
+    /// Decode the band stored in the oracle mapping
+    pub fn from_mapping_pubkey(pubkey: &Pubkey) -> Result<Self> {
+        let bytes = pubkey.to_bytes();
+        let invalid = || {
+            msg!("Clamped price mapping {} is not an encoded band", pubkey);
+            error!(ScopeError::UnexpectedAccount)
+        };
+        if bytes[MARKER_OFFSET] != BAND_MARKER || bytes[MARKER_OFFSET + 1..].iter().any(|&b| b != 0)
+        {
+            return Err(invalid());
+        }
+        let band = PriceBand {
+            source: u16::from_le_bytes(bytes[..2].try_into().unwrap()),
+            cap: decode_bound(&bytes[CAP_OFFSET..CAP_OFFSET + BOUND_SIZE]).ok_or_else(invalid)?,
+            floor: decode_bound(&bytes[CAP_OFFSET + BOUND_SIZE..MARKER_OFFSET])
+                .ok_or_else(invalid)?,
+        };
+        if band
+            .sources()
+            .iter()
+            .any(|&token| usize::from(token) >= crate::MAX_ENTRIES)
+        {
+            msg!("Clamped price source out of range: {:?}", band);
+            return err!(ScopeError::BadTokenNb);
+        }
+        Ok(band)
+    }
+
+    /// Tokens of the feed the clamped price is computed from
+    pub fn sources(&self) -> Vec<u16> {
+        let mut sources = vec![self.source];
+        for bound in [self.cap, self.floor].into_iter().flatten() {
+            if let Bound::Token(token) = bound {
+                sources.push(token);
+            }
+        }
+        sources
+    }
+}
+
+fn encode_bound(bound: &Option<Bound>, bytes: &mut [u8]) {
+    match bound {
+        None => bytes[0] = BOUND_NONE,
+        Some(Bound::Token(token)) => {
+            bytes[0] = BOUND_TOKEN;
+            bytes[1..3].copy_from_slice(&token.to_le_bytes());
+        }
+        Some(Bound::Constant(price)) => {
+            bytes[0] = BOUND_CONSTANT;
+            bytes[1..9].copy_from_slice(&price.value.to_le_bytes());
+            bytes[9] = u8::try_from(price.exp).expect("Constant bound exponent must fit in a u8");
+        }
+    }
+}
+
+/// Decode a bound, `None` if the encoding is invalid
+fn decode_bound(bytes: &[u8]) -> Option<Option<Bound>> {
+    match bytes[0] {
+        BOUND_NONE if bytes[1..].iter().all(|&b| b == 0) => Some(None),
+        BOUND_TOKEN if bytes[3..].iter().all(|&b| b == 0) => Some(Some(Bound::Token(
+            u16::from_le_bytes(bytes[1..3].try_into().unwrap()),
+        ))),
+        BOUND_CONSTANT => Some(Some(Bound::Constant(Price {
+            value: u64::from_le_bytes(bytes[1..9].try_into().unwrap()),
+            exp: bytes[9].into(),
+        }))),
+        _ => None,
+    }
+}
+
+/// How a price of this oracle type was clamped, one of [`NOT_CLAMPED`], [`CLAMPED_TO_FLOOR`]
+/// and [`CLAMPED_TO_CAP`]
+pub fn clamping(dated_price: &DatedPrice) -> u8 {
+    dated_price.generic_data[0]
+}
+
+pub fn get_price(base_account: &AccountInfo, oracle_prices: &OraclePrices) -> Result<DatedPrice> {
+    let band = PriceBand::from_mapping_pubkey(base_account.key)?;
+    let source = get_source_price(oracle_prices, band.source)?;
+    let mut last_updated_slot = source.last_updated_slot;
+    let mut unix_timestamp = source.unix_timestamp;
+    let mut resolve = |bound: Option<Bound>| -> Result<Option<Price>> {
+        Ok(match bound {
+            None => None,
+            Some(Bound::Constant(price)) => Some(price),
+            Some(Bound::Token(token)) => {
+                let bound_price = get_source_price(oracle_prices, token)?;
+                last_updated_slot = last_updated_slot.min(bound_price.last_updated_slot);
+                unix_timestamp = unix_timestamp.min(bound_price.unix_timestamp);
+                Some(bound_price.price)
+            }
+        })
+    };
+    let cap = resolve(band.cap)?;
+    let floor = resolve(band.floor)?;
+    check_band(cap.as_ref(), floor.as_ref())?;
+
+    let (price, clamped) = clamp(source.price, cap.as_ref(), floor.as_ref());
+    let mut dated_price = DatedPrice {
+        price,
+        last_updated_slot,
+        unix_timestamp,
+        ..Default::default()
+    };
+    dated_price.generic_data[0] = clamped;
+    Ok(dated_price)
+}
+
+/// Check the encoding of a clamped price mapping and that its constant bounds are consistent
+pub fn validate_mapping_account(mapping_account: &AccountInfo) -> Result<()> {
+    let band = PriceBand::from_mapping_pubkey(mapping_account.key)?;
+    let constant = |bound: Option<Bound>| match bound {
+        Some(Bound::Constant(price)) => Some(price),
+        _ => None,
+    };
+    check_band(constant(band.cap).as_ref(), constant(band.floor).as_ref())
+}
+
+fn check_band(cap: Option<&Price>, floor: Option<&Price>) -> Result<()> {
+    if let (Some(cap), Some(floor)) = (cap, floor) {
+        if cmp_prices(cap, floor) == Ordering::Less {
+            msg!("Price band cap {:?} is below its floor {:?}", cap, floor);
+            return err!(ScopeError::InvalidPriceBand);
+        }
+    }
+    Ok(())
+}
+
+/// Clamp a price to its band, with how it was clamped
+fn clamp(price: Price, cap: Option<&Price>, floor: Option<&Price>) -> (Price, u8) {
+    match (cap, floor) {
+        (Some(cap), _) if cmp_prices(&price, cap) == Ordering::Greater => (*cap, CLAMPED_TO_CAP),
+        (_, Some(floor)) if cmp_prices(&price, floor) == Ordering::Less => {
+            (*floor, CLAMPED_TO_FLOOR)
+        }
+        _ => (price, NOT_CLAMPED),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PEG: Price = Price {
+        value: 1_000_000,
+        exp: 6,
+    };
+
+    #[test]
+    fn test_encode_decode_band() {
+        for band in [
+            PriceBand {
+                source: 511,
+                cap: Some(Bound::Constant(Price {
+                    value: u64::MAX,
+                    exp: 255,
+                })),
+                floor: Some(Bound::Token(3)),
+            },
+            PriceBand {
+                source: 0,
+                cap: None,
+                floor: Some(Bound::Constant(PEG)),
+            },
+            PriceBand {
+                source: 1,
+                cap: Some(Bound::Token(0)),
+                floor: None,
+            },
+        ] {
+            assert_eq!(
+                PriceBand::from_mapping_pubkey(&band.to_mapping_pubkey()).unwrap(),
+                band
+            );
+        }
+        assert_eq!(
+            PriceBand::from_mapping_pubkey(&Pubkey::default()).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        let out_of_range = PriceBand {
+            source: 0,
+            cap: Some(Bound::Token(512)),
+            floor: None,
+        };
+        assert_eq!(
+            PriceBand::from_mapping_pubkey(&out_of_range.to_mapping_pubkey()).unwrap_err(),
+            ScopeError::BadTokenNb.into()
+        );
+    }
+
+    #[test]
+    fn test_clamp_with_different_exponents() {
+        let cap = Price {
+            value: 1_050,
+            exp: 3,
+        };
+        let floor = Price { value: 95, exp: 2 };
+        let within = Price {
+            value: 1_010_000_000,
+            exp: 9,
+        };
+        assert_eq!(
+            clamp(within, Some(&cap), Some(&floor)),
+            (within, NOT_CLAMPED)
+        );
+        let spike = Price {
+            value: 1_200_000,
+            exp: 6,
+        };
+        assert_eq!(
+            clamp(spike, Some(&cap), Some(&floor)),
+            (cap, CLAMPED_TO_CAP)
+        );
+        assert_eq!(clamp(spike, None, Some(&floor)), (spike, NOT_CLAMPED));
+        let drop = Price {
+            value: 900_000,
+            exp: 6,
+        };
+        assert_eq!(
+            clamp(drop, Some(&cap), Some(&floor)),
+            (floor, CLAMPED_TO_FLOOR)
+        );
+        // Prices at the bounds are not clamped
+        assert_eq!(clamp(PEG, Some(&PEG), Some(&PEG)), (PEG, NOT_CLAMPED));
+    }
+
+    #[test]
+    fn test_cap_below_floor() {
+        let above_peg = Price { value: 101, exp: 2 };
+        assert!(check_band(Some(&above_peg), Some(&PEG)).is_ok());
+        assert!(check_band(Some(&PEG), Some(&PEG)).is_ok());
+        assert!(check_band(None, Some(&PEG)).is_ok());
+        assert_eq!(
+            check_band(Some(&PEG), Some(&above_peg)).unwrap_err(),
+            ScopeError::InvalidPriceBand.into()
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Chainlink OCR2 feeds stored by the Chainlink store program
+//!
+//! A feed is a `Transmissions` account: an anchor (borsh) header padded to [`HEADER_SIZE`],
+//! followed by a ring buffer of [`Transmission`]. The latest transmission is the one right
+//! before the `live_cursor`.
+//!
+//! The answer is an `i128` with `decimals` decimals, the observations timestamp of the
+//! transmission is used as the price timestamp so staleness can be enforced on refresh.
+
+use anchor_lang::prelude::*;
+
+use crate::{utils::math::decimal_to_price, DatedPrice, Result, ScopeError};
+
+/// Chainlink store program id
+pub const CHAINLINK_STORE_PROGRAM_ID: Pubkey =
+    pubkey!("HEvSKofvBgfaexv23kMabbYqxasxU3mQ4ibBMEmJWHny");
+
+/// Anchor discriminator of the `Transmissions` account
+const TRANSMISSIONS_DISCRIMINATOR: [u8; 8] = [96, 179, 69, 66, 128, 129, 73, 117];
+/// Size reserved for the header after the discriminator
+pub const HEADER_SIZE: usize = 192;
+/// Size of one transmission in the ring buffer
+pub const TRANSMISSION_SIZE: usize = 48;
+
+/// Header of the `Transmissions` account (borsh serialized)
+#[derive(AnchorDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct TransmissionsHeader {
+    pub version: u8,
+    pub state: u8,
+    pub owner: Pubkey,
+    pub proposed_owner: Pubkey,
+    pub writer: Pubkey,
+    pub description: [u8; 32],
+    pub decimals: u8,
+    pub flagging_threshold: u32,
+    pub latest_round_id: u32,
+    pub granularity: u8,
+    pub live_length: u32,
+    pub live_cursor: u32,
+    pub historical_cursor: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Transmission {
+    pub slot: u64,
+    /// Timestamp of the observations of the round
+    pub timestamp: u32,
+    pub answer: i128,
+}
+
+impl Transmission {
+    fn from_bytes(data: &[u8]) -> Transmission {
+        Transmission {
+            slot: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+            timestamp: u32::from_le_bytes(data[8..12].try_into().unwrap()),
+            // 4 bytes of padding
+            answer: i128::from_le_bytes(data[16..32].try_into().unwrap()),
+            // 16 bytes of padding
+        }
+    }
+}
+
+pub fn get_price(transmissions_info: &AccountInfo) -> Result<DatedPrice> {
+    let data = transmissions_info.try_borrow_data()?;
+    let (header, transmission) = parse_latest_transmission(&data)?;
+
+    let price = decimal_to_price(transmission.answer, header.decimals.into())?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: transmission.slot,
+        unix_timestamp: transmission.timestamp.into(),
+        ..Default::default()
+    })
+}
+
+pub fn validate_transmissions_account(transmissions_info: &AccountInfo) -> Result<()> {
+    if transmissions_info.owner != &CHAINLINK_STORE_PROGRAM_ID {
+        msg!(
+            "Chainlink feed {} is not owned by the store program",
+            transmissions_info.key()
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let data = transmissions_info.try_borrow_data()?;
+    parse_header(&data).map(|_| ())
+}
+
+fn parse_header(data: &[u8]) -> Result<TransmissionsHeader> {
+    if data.len() < 8 + HEADER_SIZE {
+        msg!("Chainlink transmissions account is too small");
+        return err!(ScopeError::UnableToDeserializeAccount);
+    }
+    if data[..8] != TRANSMISSIONS_DISCRIMINATOR {
+        msg!("Chainlink transmissions account has an invalid discriminator");
+        return err!(ScopeError::InvalidAccountDiscriminator);
+    }
+    let mut header_data = &data[8..8 + HEADER_SIZE];
+    TransmissionsHeader::deserialize(&mut header_data)
+        .map_err(|_| error!(ScopeError::UnableToDeserializeAccount))
+}
+
+fn parse_latest_transmission(data: &[u8]) -> Result<(TransmissionsHeader, Transmission)> {
+    let header = parse_header(data)?;
+
+    if header.latest_round_id == 0 || header.live_length == 0 {
+        msg!("Chainlink feed has no transmission");
+        return err!(ScopeError::PriceNotValid);
+    }
+
+    let live_length = usize::try_from(header.live_length).unwrap();
+    let live_cursor = usize::try_from(header.live_cursor).unwrap();
+    let latest_index = (live_cursor + live_length - 1) % live_length;
+    let offset = 8 + HEADER_SIZE + latest_index * TRANSMISSION_SIZE;
+    let transmission_data = data
+        .get(offset..offset + TRANSMISSION_SIZE)
+        .ok_or(ScopeError::UnableToDeserializeAccount)?;
+
+    Ok((header, Transmission::from_bytes(transmission_data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a transmissions account with the layout of the mainnet store program
+    fn transmissions_data(
+        decimals: u8,
+        transmissions: &[Transmission],
+        live_cursor: u32,
+    ) -> Vec<u8> {
+        let mut data = TRANSMISSIONS_DISCRIMINATOR.to_vec();
+
+        let mut header = vec![1_u8, 1]; // version, state
+        header.extend_from_slice(&[0; 32 * 3]); // owner, proposed_owner, writer
+        let mut description = [0_u8; 32];
+        description[..7].copy_from_slice(b"SOL/USD");
+        header.extend_from_slice(&description);
+        header.push(decimals);
+        header.extend_from_slice(&0_u32.to_le_bytes()); // flagging_threshold
+        header.extend_from_slice(&42_u32.to_le_bytes()); // latest_round_id
+        header.push(30); // granularity
+        header.extend_from_slice(&u32::try_from(transmissions.len()).unwrap().to_le_bytes());
+        header.extend_from_slice(&live_cursor.to_le_bytes());
+        header.extend_from_slice(&0_u32.to_le_bytes()); // historical_cursor
+        header.resize(HEADER_SIZE, 0);
+        data.extend_from_slice(&header);
+
+        for transmission in transmissions {
+            data.extend_from_slice(&transmission.slot.to_le_bytes());
+            data.extend_from_slice(&transmission.timestamp.to_le_bytes());
+            data.extend_from_slice(&[0; 4]);
+            data.extend_from_slice(&transmission.answer.to_le_bytes());
+            data.extend_from_slice(&[0; 16]);
+        }
+        data
+    }
+
+    fn transmission(slot: u64, timestamp: u32, answer: i128) -> Transmission {
+        Transmission {
+            slot,
+            timestamp,
+            answer,
+        }
+    }
+
+    #[test]
+    fn test_header_parsing() {
+        let data = transmissions_data(8, &[transmission(1, 2, 3)], 1);
+        let header = parse_header(&data).unwrap();
+        assert_eq!(header.decimals, 8);
+        assert_eq!(header.latest_round_id, 42);
+        assert_eq!(header.live_length, 1);
+        assert_eq!(&header.description[..7], b"SOL/USD");
+    }
+
+    #[test]
+    fn test_latest_transmission_8_decimals() {
+        // 20.81 USD with 8 decimals, cursor wrapped around: latest is the last one
+        let data = transmissions_data(
+            8,
+            &[
+                transmission(100, 1_700_000_000, 2_075_000_000),
+                transmission(101, 1_700_000_030, 2_078_000_000),
+                transmission(102, 1_700_000_060, 2_081_000_000),
+            ],
+            0,
+        );
+        let (header, latest) = parse_latest_transmission(&data).unwrap();
+        assert_eq!(latest, transmission(102, 1_700_000_060, 2_081_000_000));
+
+        let price = decimal_to_price(latest.answer, header.decimals.into()).unwrap();
+        assert_eq!(price.value, 2_081_000_000);
+        assert_eq!(price.exp, 8);
+    }
+
+    #[test]
+    fn test_latest_transmission_18_decimals() {
+        // 1.0002 with 18 decimals, latest is right before the cursor
+        let data = transmissions_data(
+            18,
+            &[
+                transmission(200, 1_700_000_100, 1_000_200_000_000_000_000),
+                transmission(150, 1_700_000_000, 999_900_000_000_000_000),
+            ],
+            1,
+        );
+        let (header, latest) = parse_latest_transmission(&data).unwrap();
+        assert_eq!(latest.slot, 200);
+        assert_eq!(latest.timestamp, 1_700_000_100);
+
+        // Exponent is capped to 10 decimals
+        let price = decimal_to_price(latest.answer, header.decimals.into()).unwrap();
+        assert_eq!(price.value, 10_002_000_000);
+        assert_eq!(price.exp, 10);
+    }
+
+    #[test]
+    fn test_no_transmission() {
+        let mut data = transmissions_data(8, &[transmission(1, 2, 3)], 1);
+        // latest_round_id = 0
+        let round_id_offset = 8 + 2 + 32 * 4 + 1 + 4;
+        data[round_id_offset..round_id_offset + 4].copy_from_slice(&0_u32.to_le_bytes());
+        assert_eq!(
+            parse_latest_transmission(&data).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn test_invalid_discriminator() {
+        let mut data = transmissions_data(8, &[transmission(1, 2, 3)], 1);
+        data[0] = !data[0];
+        assert_eq!(
+            parse_header(&data).unwrap_err(),
+            ScopeError::InvalidAccountDiscriminator.into()
+        );
+    }
+}
@@ -0,0 +1,493 @@
+//! Fair price of the LP token of a constant product pool
+//!
+//! Summing the value of the pool vaults at market prices gives an LP price that can be moved
+//! within a transaction: a swap skewing the vault balances changes their total value. The fair
+//! price only depends on the pool invariant `k = reserve_a * reserve_b` and on the market prices
+//! `price_a` and `price_b` of the underlying tokens:
+//!
+//! `lp_price = 2 * sqrt(k * price_a * price_b) / lp_supply`
+//!
+//! A swap keeps `k` constant (fees aside, which only increase it) so it does not move the price.
+//!
+//! Pools use the SPL token-swap layout (e.g. Orca legacy pools), only the constant product curve
+//! is accepted. The pool vaults, LP mint and underlying mints are provided as extra accounts.
+//! The market prices of the underlying tokens are read from other entries of the feed, set with
+//! [`UpdateMappingConfigMode::UnderlyingPrices`]. As for [`super::derived_price`], the LP price is
+//! dated as its oldest underlying price, they must be refreshed first.
+//!
+//! [`UpdateMappingConfigMode::UnderlyingPrices`]: crate::UpdateMappingConfigMode::UnderlyingPrices
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::derived_price::get_source_price,
+    utils::math::{div_prices, isqrt},
+    DatedPrice, OraclePrices, Price, Result, ScopeError,
+};
+
+/// Programs owning pools with the SPL token-swap layout
+pub const TOKEN_SWAP_PROGRAM_IDS: [Pubkey; 3] = [
+    // SPL token-swap
+    pubkey!("SwapsVeCiPHMUAtzQWZw7RjsKjgCjhwU55QGu4U1Szw"),
+    // Orca token-swap v1
+    pubkey!("DjVE6JNiYqPL2QXyCUUh8rNjHrbz9hXHNYt99MQ59qw1"),
+    // Orca token-swap v2
+    pubkey!("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP"),
+];
+
+// Offsets of the used fields in the token-swap account (version byte included)
+const SWAP_V1_VERSION: u8 = 1;
+const IS_INITIALIZED_OFFSET: usize = 1;
+const TOKEN_A_OFFSET: usize = 35;
+const TOKEN_B_OFFSET: usize = 67;
+const POOL_MINT_OFFSET: usize = 99;
+const TOKEN_A_MINT_OFFSET: usize = 131;
+const TOKEN_B_MINT_OFFSET: usize = 163;
+const CURVE_TYPE_OFFSET: usize = 291;
+const SWAP_SIZE: usize = 324;
+const CONSTANT_PRODUCT_CURVE: u8 = 0;
+
+// Offsets in SPL token accounts and mints
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+const MINT_SUPPLY_OFFSET: usize = 36;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_SIZE: usize = 82;
+
+/// Fields of the token-swap account needed to compute the LP price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenSwap {
+    /// Vault of the token A
+    pub token_a: Pubkey,
+    /// Vault of the token B
+    pub token_b: Pubkey,
+    /// LP token mint
+    pub pool_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+}
+
+impl TokenSwap {
+    pub fn from_account_data(data: &[u8]) -> Result<TokenSwap> {
+        if data.len() < SWAP_SIZE || data[0] != SWAP_V1_VERSION || data[IS_INITIALIZED_OFFSET] != 1
+        {
+            msg!("Account is not an initialized token-swap pool");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        if data[CURVE_TYPE_OFFSET] != CONSTANT_PRODUCT_CURVE {
+            msg!("Token-swap pool is not a constant product pool");
+            return err!(ScopeError::UnexpectedAccount);
+        }
+        let read_pubkey =
+            |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+        Ok(TokenSwap {
+            token_a: read_pubkey(TOKEN_A_OFFSET),
+            token_b: read_pubkey(TOKEN_B_OFFSET),
+            pool_mint: read_pubkey(POOL_MINT_OFFSET),
+            token_a_mint: read_pubkey(TOKEN_A_MINT_OFFSET),
+            token_b_mint: read_pubkey(TOKEN_B_MINT_OFFSET),
+        })
+    }
+
+    /// Extra accounts expected at refresh, in order
+    pub fn extra_accounts(&self) -> [Pubkey; 5] {
+        [
+            self.token_a,
+            self.token_b,
+            self.pool_mint,
+            self.token_a_mint,
+            self.token_b_mint,
+        ]
+    }
+}
+
+/// One side of the pool
+#[derive(Debug, Clone, Copy)]
+struct PoolSide {
+    /// Amount in the vault, in lamports
+    reserve: u64,
+    decimals: u8,
+    /// Market price of one token
+    price: Price,
+}
+
+/// Get the fair price of the LP token, see the module documentation
+///
+/// The extra accounts are consumed in the order of [`TokenSwap::extra_accounts`]: vault A,
+/// vault B, LP mint, mint A and mint B.
+pub fn get_price<'a, 'b>(
+    pool_info: &AccountInfo,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+    oracle_prices: &OraclePrices,
+    underlying_prices: &[u16; 2],
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let pool = TokenSwap::from_account_data(&pool_info.try_borrow_data()?)?;
+
+    let mut accounts = [None; 5];
+    for account in accounts.iter_mut() {
+        *account = Some(
+            extra_accounts
+                .next()
+                .ok_or(ScopeError::AccountsAndTokenMismatch)?,
+        );
+    }
+    let accounts = accounts.map(Option::unwrap);
+    for (account_info, expected) in accounts.iter().zip(pool.extra_accounts()) {
+        if account_info.key() != expected {
+            msg!(
+                "Token-swap account received {} is not the expected {}",
+                account_info.key(),
+                expected
+            );
+            return err!(ScopeError::UnexpectedAccount);
+        }
+    }
+    let [vault_a, vault_b, pool_mint, mint_a, mint_b] = accounts;
+
+    if *underlying_prices == [0, 0] {
+        msg!("Underlying prices of the LP token are not configured");
+        return err!(ScopeError::PriceNotValid);
+    }
+    let price_a = get_source_price(oracle_prices, underlying_prices[0])?;
+    let price_b = get_source_price(oracle_prices, underlying_prices[1])?;
+
+    let (lp_supply, lp_decimals) = read_mint(pool_mint)?;
+    let side_a = PoolSide {
+        reserve: read_token_amount(vault_a)?,
+        decimals: read_mint(mint_a)?.1,
+        price: price_a.price,
+    };
+    let side_b = PoolSide {
+        reserve: read_token_amount(vault_b)?,
+        decimals: read_mint(mint_b)?.1,
+        price: price_b.price,
+    };
+    let price = fair_lp_price(&side_a, &side_b, lp_supply, lp_decimals)?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: price_a.last_updated_slot.min(price_b.last_updated_slot),
+        unix_timestamp: price_a.unix_timestamp.min(price_b.unix_timestamp),
+        ..Default::default()
+    })
+}
+
+/// Check that the given account is a constant product token-swap pool
+pub fn validate_pool_account(pool_info: &AccountInfo) -> Result<()> {
+    if !TOKEN_SWAP_PROGRAM_IDS.contains(pool_info.owner) {
+        msg!(
+            "Pool {} is not owned by a token-swap program",
+            pool_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    TokenSwap::from_account_data(&pool_info.try_borrow_data()?).map(|_| ())
+}
+
+pub(crate) fn read_token_amount(token_account_info: &AccountInfo) -> Result<u64> {
+    let data = token_account_info.try_borrow_data()?;
+    if data.len() < TOKEN_ACCOUNT_SIZE {
+        msg!("Account {} is not a token account", token_account_info.key);
+        return err!(ScopeError::UnableToDeserializeAccount);
+    }
+    Ok(u64::from_le_bytes(
+        data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+            .try_into()
+            .unwrap(),
+    ))
+}
+
+/// Supply and decimals of a mint
+pub(crate) fn read_mint(mint_info: &AccountInfo) -> Result<(u64, u8)> {
+    let data = mint_info.try_borrow_data()?;
+    if data.len() < MINT_SIZE {
+        msg!("Account {} is not a token mint", mint_info.key);
+        return err!(ScopeError::UnableToDeserializeAccount);
+    }
+    let supply = u64::from_le_bytes(
+        data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8]
+            .try_into()
+            .unwrap(),
+    );
+    Ok((supply, data[MINT_DECIMALS_OFFSET]))
+}
+
+/// `2 * sqrt(value_a * value_b) / lp_supply`, with `value_x` the value of the reserve `x`
+///
+/// This is the same as the formula of the module documentation, the value of a reserve
+/// being `reserve * price`.
+fn fair_lp_price(
+    side_a: &PoolSide,
+    side_b: &PoolSide,
+    lp_supply: u64,
+    lp_decimals: u8,
+) -> Result<Price> {
+    let (mut value_a, mut exp_a) = reserve_value(side_a)?;
+    let (mut value_b, mut exp_b) = reserve_value(side_b)?;
+
+    // The square root halves the exponent, the sum of the exponents must be even
+    if (exp_a + exp_b) % 2 != 0 {
+        let (small, small_exp, big, big_exp) = if value_a <= value_b {
+            (&mut value_a, &mut exp_a, &mut value_b, &mut exp_b)
+        } else {
+            (&mut value_b, &mut exp_b, &mut value_a, &mut exp_a)
+        };
+        // Keep as many digits as possible
+        if *small * 10 <= u128::from(u64::MAX) {
+            *small *= 10;
+            *small_exp += 1;
+        } else {
+            *big /= 10;
+            *big_exp -= 1;
+        }
+    }
+
+    // Both values fit in a u64, their product cannot overflow
+    let root = isqrt(value_a * value_b);
+    let lp_value = to_price(2 * root, (exp_a + exp_b) / 2)?;
+    let lp_supply = Price {
+        value: lp_supply,
+        exp: lp_decimals.into(),
+    };
+    Ok(div_prices(&lp_value, &lp_supply)?)
+}
+
+/// Value of a reserve as `value * 10^-exp`, with `value` fitting in a `u64`
+fn reserve_value(side: &PoolSide) -> Result<(u128, i64)> {
+    let mut value = u128::from(side.reserve) * u128::from(side.price.value);
+    let price_exp = i64::try_from(side.price.exp).map_err(|_| ScopeError::MathOverflow)?;
+    let mut exp = price_exp + i64::from(side.decimals);
+    while value > u128::from(u64::MAX) {
+        // Loss of precision here is expected.
+        value /= 10;
+        exp -= 1;
+    }
+    Ok((value, exp))
+}
+
+/// Convert `value * 10^-exp` to a [`Price`], the exponent of a price cannot be negative
+fn to_price(mut value: u128, mut exp: i64) -> Result<Price> {
+    while value > u128::from(u64::MAX) {
+        // Loss of precision here is expected.
+        value /= 10;
+        exp -= 1;
+    }
+    if exp < 0 {
+        let factor = u32::try_from(-exp)
+            .ok()
+            .and_then(|e| 10_u128.checked_pow(e))
+            .ok_or(ScopeError::MathOverflow)?;
+        value = value.checked_mul(factor).ok_or(ScopeError::MathOverflow)?;
+        exp = 0;
+    }
+    Ok(Price {
+        value: value.try_into().map_err(|_| ScopeError::MathOverflow)?,
+        exp: exp.try_into().unwrap(), // Cannot fail, positive
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use super::*;
+    use crate::utils::math::cmp_prices;
+
+    const SOL_USD: Price = Price {
+        value: 2_000_000_000,
+        exp: 8,
+    };
+    const USDC_USD: Price = Price {
+        value: 1_000_000,
+        exp: 6,
+    };
+
+    fn sol(reserve: u64) -> PoolSide {
+        PoolSide {
+            reserve: reserve * 1_000_000_000,
+            decimals: 9,
+            price: SOL_USD,
+        }
+    }
+
+    fn usdc(reserve: u64) -> PoolSide {
+        PoolSide {
+            reserve: reserve * 1_000_000,
+            decimals: 6,
+            price: USDC_USD,
+        }
+    }
+
+    // 1000 LP tokens with 6 decimals
+    const LP_SUPPLY: u64 = 1_000_000_000;
+    const LP_DECIMALS: u8 = 6;
+
+    fn to_f64(price: &Price) -> f64 {
+        price.value as f64 / 10_f64.powi(price.exp.try_into().unwrap())
+    }
+
+    #[test]
+    fn balanced_pool_is_worth_its_reserves() {
+        // 1000 SOL at 20 USD and 20_000 USDC, 40 USD per LP token
+        let price = fair_lp_price(&sol(1000), &usdc(20_000), LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(
+            cmp_prices(&price, &Price { value: 40, exp: 0 }),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn fair_price_is_invariant_to_swaps() {
+        let before = fair_lp_price(&sol(1000), &usdc(20_000), LP_SUPPLY, LP_DECIMALS).unwrap();
+
+        // Swap 5000 USDC for 200 SOL, k is unchanged
+        // Naively summing the vaults would give 41 USD per LP token
+        let after = fair_lp_price(&sol(800), &usdc(25_000), LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(cmp_prices(&after, &before), Ordering::Equal);
+
+        // Swap so the pool holds 2000 SOL and 10_000 USDC (naive sum is 50 USD per LP token)
+        let after = fair_lp_price(&sol(2000), &usdc(10_000), LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(cmp_prices(&after, &before), Ordering::Equal);
+
+        // Swap to reserves that are not round numbers, k is only preserved up to the rounding
+        // of the reserves to lamports
+        let skewed_sol = PoolSide {
+            reserve: 1_234_567_890_123,
+            ..sol(0)
+        };
+        // 20_000 * 1000 / 1234.567890123 USDC
+        let skewed_usdc = PoolSide {
+            reserve: 16_200_000_146,
+            ..usdc(0)
+        };
+        let after = fair_lp_price(&skewed_sol, &skewed_usdc, LP_SUPPLY, LP_DECIMALS).unwrap();
+        let rel_diff = (to_f64(&after) - 40.0).abs() / 40.0;
+        assert!(rel_diff < 1e-10, "{after:?}");
+    }
+
+    #[test]
+    fn naive_price_is_manipulable_under_imbalance() {
+        let naive_lp_price = |side_a: &PoolSide, side_b: &PoolSide| {
+            let value = |side: &PoolSide| {
+                side.reserve as f64 / 10_f64.powi(side.decimals.into()) * to_f64(&side.price)
+            };
+            (value(side_a) + value(side_b)) / 1000.0
+        };
+
+        // Balanced pool, both methods agree
+        let (balanced_sol, balanced_usdc) = (sol(1000), usdc(20_000));
+        let fair = fair_lp_price(&balanced_sol, &balanced_usdc, LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(to_f64(&fair), 40.0);
+        assert_eq!(naive_lp_price(&balanced_sol, &balanced_usdc), 40.0);
+
+        // A flash loan swaps 180_000 USDC for 900 SOL, k is unchanged but the pool is now
+        // valued far from the market prices
+        let (skewed_sol, skewed_usdc) = (sol(100), usdc(200_000));
+        let fair = fair_lp_price(&skewed_sol, &skewed_usdc, LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(to_f64(&fair), 40.0);
+        assert_eq!(naive_lp_price(&skewed_sol, &skewed_usdc), 202.0);
+    }
+
+    #[test]
+    fn fair_price_follows_market_prices() {
+        // SOL price is multiplied by 4, the LP token is worth twice as much
+        let sol_side = PoolSide {
+            price: Price {
+                value: 8_000_000_000,
+                exp: 8,
+            },
+            ..sol(1000)
+        };
+        let price = fair_lp_price(&sol_side, &usdc(20_000), LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(
+            cmp_prices(&price, &Price { value: 80, exp: 0 }),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn odd_exponents_and_large_values() {
+        // Sum of the reserves exponents is odd: 9 + 8 + 6 + 7
+        let usdc_side = PoolSide {
+            price: Price {
+                value: 10_000_000,
+                exp: 7,
+            },
+            ..usdc(20_000)
+        };
+        let price = fair_lp_price(&sol(1000), &usdc_side, LP_SUPPLY, LP_DECIMALS).unwrap();
+        assert_eq!(
+            cmp_prices(&price, &Price { value: 40, exp: 0 }),
+            Ordering::Equal
+        );
+
+        // Max reserves worth 1M USD per token do not overflow
+        let max_side = PoolSide {
+            reserve: u64::MAX,
+            decimals: 9,
+            price: Price {
+                value: 100_000_000_000_000,
+                exp: 8,
+            },
+        };
+        let price = fair_lp_price(&max_side, &max_side, u64::MAX, 9).unwrap();
+        // As many LP tokens as tokens in each reserve, each LP token is worth 2M USD
+        let rel_diff = (to_f64(&price) - 2e6).abs() / 2e6;
+        assert!(rel_diff < 1e-15, "{price:?}");
+    }
+
+    #[test]
+    fn empty_lp_supply_is_not_valid() {
+        assert_eq!(
+            fair_lp_price(&sol(1000), &usdc(20_000), 0, LP_DECIMALS).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    fn swap_data(curve_type: u8) -> Vec<u8> {
+        let mut data = vec![0_u8; SWAP_SIZE];
+        data[0] = SWAP_V1_VERSION;
+        data[IS_INITIALIZED_OFFSET] = 1;
+        for (i, offset) in [
+            TOKEN_A_OFFSET,
+            TOKEN_B_OFFSET,
+            POOL_MINT_OFFSET,
+            TOKEN_A_MINT_OFFSET,
+            TOKEN_B_MINT_OFFSET,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            data[offset..offset + 32].fill(i as u8 + 1);
+        }
+        data[CURVE_TYPE_OFFSET] = curve_type;
+        data
+    }
+
+    #[test]
+    fn token_swap_parsing() {
+        let pool = TokenSwap::from_account_data(&swap_data(CONSTANT_PRODUCT_CURVE)).unwrap();
+        let expected: Vec<Pubkey> = (1..=5).map(|i| Pubkey::new_from_array([i; 32])).collect();
+        assert_eq!(pool.extra_accounts().to_vec(), expected);
+
+        // Stable curve
+        assert_eq!(
+            TokenSwap::from_account_data(&swap_data(2)).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        assert_eq!(
+            TokenSwap::from_account_data(&swap_data(CONSTANT_PRODUCT_CURVE)[..SWAP_SIZE - 1])
+                .unwrap_err(),
+            ScopeError::UnableToDeserializeAccount.into()
+        );
+        let mut uninitialized = swap_data(CONSTANT_PRODUCT_CURVE);
+        uninitialized[IS_INITIALIZED_OFFSET] = 0;
+        assert_eq!(
+            TokenSwap::from_account_data(&uninitialized).unwrap_err(),
+            ScopeError::UnableToDeserializeAccount.into()
+        );
+    }
+}
@@ -0,0 +1,441 @@
+//! Prices derived from other prices of the same scope feed
+//!
+//! No external account is read on refresh, the source prices are read from the `OraclePrices`
+//! account being refreshed. For instance a stSOL/USD price can be derived as the product of
+//! the stSOL/SOL and SOL/USD entries, or a USDC/SOL price as the quotient of the USDC/USD and
+//! SOL/USD entries.
+//!
+//! As for [`crate::oracles::fixed_price`], the indexes of the source tokens are encoded in the
+//! bytes of the pubkey referenced in the oracle mapping (see [`sources_to_mapping_pubkey`]).
+//! The refresh instructions still check the received account against the mapping, so the crank
+//! must pass an account with this address. This account does not need to exist.
+//!
+//! The derived price is only as fresh as its oldest source: `last_updated_slot` and
+//! `unix_timestamp` are the minimum of the sources ones. Sources must be refreshed before the
+//! derived entry for the derived price to be up to date, when refreshed in the same
+//! `refresh_price_list` the sources must be listed first (see [`check_refresh_order`]).
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::{
+        alias_price, capped_floored, discounted_price, median_price, scope_twap, OracleType,
+    },
+    utils::math::{div_prices, inv_price, mul_prices},
+    DatedPrice, OracleMappings, OraclePrices, Result, ScopeError,
+};
+
+/// Marker byte of the encoded sources, distinguishes token 0 sources from an unset mapping
+const SOURCES_MARKER: u8 = 1;
+
+/// Encode the source tokens of a derived price in a pubkey to store it in the oracle mapping
+///
+/// The inverse price only uses `token_a`, `token_b` is expected to be the same token.
+pub fn sources_to_mapping_pubkey(token_a: u16, token_b: u16) -> Pubkey {
+    let mut bytes = [0_u8; 32];
+    bytes[..2].copy_from_slice(&token_a.to_le_bytes());
+    bytes[2..4].copy_from_slice(&token_b.to_le_bytes());
+    bytes[4] = SOURCES_MARKER;
+    Pubkey::new_from_array(bytes)
+}
+
+/// Decode the source tokens of a derived price stored in the oracle mapping
+pub fn sources_from_mapping_pubkey(pubkey: &Pubkey) -> Result<(u16, u16)> {
+    let bytes = pubkey.to_bytes();
+    if bytes[4] != SOURCES_MARKER || bytes[5..].iter().any(|&b| b != 0) {
+        msg!("Derived price mapping {} is not encoded sources", pubkey);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let token_a = u16::from_le_bytes(bytes[..2].try_into().unwrap());
+    let token_b = u16::from_le_bytes(bytes[2..4].try_into().unwrap());
+    if usize::from(token_a) >= crate::MAX_ENTRIES || usize::from(token_b) >= crate::MAX_ENTRIES {
+        msg!(
+            "Derived price source out of range: {}, {}",
+            token_a,
+            token_b
+        );
+        return err!(ScopeError::BadTokenNb);
+    }
+    Ok((token_a, token_b))
+}
+
+pub fn get_price(
+    price_type: OracleType,
+    base_account: &AccountInfo,
+    oracle_prices: &OraclePrices,
+) -> Result<DatedPrice> {
+    let (token_a, token_b) = sources_from_mapping_pubkey(base_account.key)?;
+    let price_a = get_source_price(oracle_prices, token_a)?;
+    let price_b = get_source_price(oracle_prices, token_b)?;
+
+    let price = match price_type {
+        OracleType::ScopeChainProduct => mul_prices(&price_a.price, &price_b.price)?,
+        OracleType::ScopeChainQuotient => div_prices(&price_a.price, &price_b.price)?,
+        OracleType::ScopeChainInverse => inv_price(&price_a.price)?,
+        _ => unreachable!("{:?} is not a derived price type", price_type),
+    };
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: price_a.last_updated_slot.min(price_b.last_updated_slot),
+        unix_timestamp: price_a.unix_timestamp.min(price_b.unix_timestamp),
+        ..Default::default()
+    })
+}
+
+/// Get a source price from the feed, failing if it has never been refreshed
+pub(crate) fn get_source_price(oracle_prices: &OraclePrices, token: u16) -> Result<DatedPrice> {
+    let price = oracle_prices.prices[usize::from(token)];
+    if price.price.value == 0 {
+        msg!("Source price of token {} has never been refreshed", token);
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(price)
+}
+
+pub fn validate_mapping_account(
+    price_type: OracleType,
+    mapping_account: &AccountInfo,
+) -> Result<()> {
+    let (token_a, token_b) = sources_from_mapping_pubkey(mapping_account.key)?;
+    if price_type == OracleType::ScopeChainInverse && token_a != token_b {
+        msg!("Inverse price mapping must reference a single token");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    Ok(())
+}
+
+/// Check that the derived price of `token` does not depend on itself, directly or through
+/// other derived prices.
+///
+/// To be called once the new mapping of `token` is written.
+pub fn check_no_cycle(oracle_mappings: &OracleMappings, token: usize) -> Result<()> {
+    let mut visited = [false; crate::MAX_ENTRIES];
+    let mut to_visit = vec![token];
+    while let Some(current) = to_visit.pop() {
+        let price_type = match derived_type(oracle_mappings.price_types[current]) {
+            Some(price_type) => price_type,
+            None => continue,
+        };
+        let sources = derived_sources(price_type, &oracle_mappings.price_info_accounts[current])?;
+        for source in sources.into_iter().map(usize::from) {
+            if source == token {
+                msg!("Derived price of token {} depends on itself", token);
+                return err!(ScopeError::CyclicDerivedPrice);
+            }
+            if !visited[source] {
+                visited[source] = true;
+                to_visit.push(source);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that the sources of the derived prices of a refresh list are not refreshed after them
+///
+/// A derived price computed before one of its sources would be based on the previous value of
+/// the source, while the caller expects all the listed prices to be consistent.
+pub fn check_refresh_order(oracle_mappings: &OracleMappings, tokens: &[u16]) -> Result<()> {
+    let mut refreshed_after = [false; crate::MAX_ENTRIES];
+    for &token in tokens.iter().rev() {
+        let token = usize::from(token);
+        if token >= crate::MAX_ENTRIES {
+            return err!(ScopeError::BadTokenNb);
+        }
+        if let Some(price_type) = derived_type(oracle_mappings.price_types[token]) {
+            // Invalid mappings are reported when the price is computed
+            if let Ok(sources) =
+                derived_sources(price_type, &oracle_mappings.price_info_accounts[token])
+            {
+                if sources
+                    .iter()
+                    .any(|&source| refreshed_after[usize::from(source)])
+                {
+                    msg!(
+                        "Derived price of token {} is refreshed before its sources",
+                        token
+                    );
+                    return err!(ScopeError::DerivedPriceBeforeSources);
+                }
+            }
+        }
+        refreshed_after[token] = true;
+    }
+    Ok(())
+}
+
+/// Oracle type of a mapping entry if it is a derived price
+fn derived_type(price_type: u8) -> Option<OracleType> {
+    OracleType::try_from(price_type)
+        .ok()
+        .filter(|price_type| price_type.is_derived())
+}
+
+/// Source tokens of a derived price, whatever its encoding
+fn derived_sources(price_type: OracleType, mapping: &Pubkey) -> Result<Vec<u16>> {
+    match price_type {
+        OracleType::ScopeMedian => median_price::sources_from_mapping_pubkey(mapping),
+        OracleType::CappedFloored => {
+            capped_floored::PriceBand::from_mapping_pubkey(mapping).map(|band| band.sources())
+        }
+        OracleType::ScopeTwap => {
+            scope_twap::source_from_mapping_pubkey(mapping).map(|source| vec![source])
+        }
+        OracleType::Discounted => discounted_price::Discount::from_mapping_pubkey(mapping)
+            .map(|discount| vec![discount.source]),
+        OracleType::Alias => {
+            alias_price::source_from_mapping_pubkey(mapping).map(|source| vec![source])
+        }
+        _ => sources_from_mapping_pubkey(mapping).map(|(token_a, token_b)| vec![token_a, token_b]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::__private::bytemuck::Zeroable;
+
+    use super::*;
+    use crate::Price;
+
+    fn with_mapping_account<R>(key: Pubkey, f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let owner = Pubkey::default();
+        let mut lamports = 0;
+        let mut data = [];
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        f(&account)
+    }
+
+    fn prices_with(entries: &[(usize, Price, u64)]) -> Box<OraclePrices> {
+        let mut prices: Box<OraclePrices> = Box::new(Zeroable::zeroed());
+        for &(token, price, slot) in entries {
+            prices.prices[token] = DatedPrice {
+                price,
+                last_updated_slot: slot,
+                unix_timestamp: slot * 2,
+                ..Default::default()
+            };
+        }
+        prices
+    }
+
+    fn mappings_with(entries: &[(usize, u16, u16)]) -> Box<OracleMappings> {
+        let mut mappings: Box<OracleMappings> = Box::new(Zeroable::zeroed());
+        for &(token, token_a, token_b) in entries {
+            mappings.price_info_accounts[token] = sources_to_mapping_pubkey(token_a, token_b);
+            mappings.price_types[token] = OracleType::ScopeChainProduct.into();
+        }
+        mappings
+    }
+
+    fn derived_price(
+        price_type: OracleType,
+        sources: (u16, u16),
+        prices: &OraclePrices,
+    ) -> Result<DatedPrice> {
+        with_mapping_account(sources_to_mapping_pubkey(sources.0, sources.1), |acc| {
+            get_price(price_type, acc, prices)
+        })
+    }
+
+    #[test]
+    fn encode_decode_sources() {
+        let pk = sources_to_mapping_pubkey(3, 511);
+        assert_eq!(sources_from_mapping_pubkey(&pk).unwrap(), (3, 511));
+        let pk = sources_to_mapping_pubkey(0, 0);
+        assert_ne!(pk, Pubkey::default());
+        assert_eq!(sources_from_mapping_pubkey(&pk).unwrap(), (0, 0));
+        assert_eq!(
+            sources_from_mapping_pubkey(&Pubkey::default()).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        assert_eq!(
+            sources_from_mapping_pubkey(&crate::ID).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        assert_eq!(
+            sources_from_mapping_pubkey(&sources_to_mapping_pubkey(0, 512)).unwrap_err(),
+            ScopeError::BadTokenNb.into()
+        );
+    }
+
+    #[test]
+    fn product_with_different_exponents() {
+        // stSOL/SOL with 15 decimals, SOL/USD with 8 decimals
+        let prices = prices_with(&[
+            (
+                0,
+                Price {
+                    value: 1_100_000_000_000_000,
+                    exp: 15,
+                },
+                100,
+            ),
+            (
+                1,
+                Price {
+                    value: 2_081_000_000,
+                    exp: 8,
+                },
+                90,
+            ),
+        ]);
+        let price = with_mapping_account(sources_to_mapping_pubkey(0, 1), |acc| {
+            get_price(OracleType::ScopeChainProduct, acc, &prices)
+        })
+        .unwrap();
+        assert_eq!(
+            price.price,
+            Price {
+                value: 2_289_100_000_000_000_000,
+                exp: 17
+            }
+        );
+        // Oldest source
+        assert_eq!(price.last_updated_slot, 90);
+        assert_eq!(price.unix_timestamp, 180);
+    }
+
+    #[test]
+    fn unset_source_is_not_valid() {
+        let prices = prices_with(&[(0, Price { value: 1, exp: 0 }, 100)]);
+        let res = with_mapping_account(sources_to_mapping_pubkey(0, 1), |acc| {
+            get_price(OracleType::ScopeChainProduct, acc, &prices)
+        });
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    fn cycles_are_detected() {
+        // Direct self reference
+        let mappings = mappings_with(&[(2, 0, 2)]);
+        assert_eq!(
+            check_no_cycle(&mappings, 2).unwrap_err(),
+            ScopeError::CyclicDerivedPrice.into()
+        );
+
+        // Indirect: 2 = 0 * 3, 3 = 1 * 2
+        let mappings = mappings_with(&[(2, 0, 3), (3, 1, 2)]);
+        assert_eq!(
+            check_no_cycle(&mappings, 2).unwrap_err(),
+            ScopeError::CyclicDerivedPrice.into()
+        );
+
+        // Derived from another derived price without cycle: 2 = 0 * 1, 3 = 2 * 2
+        let mappings = mappings_with(&[(2, 0, 1), (3, 2, 2)]);
+        assert!(check_no_cycle(&mappings, 2).is_ok());
+        assert!(check_no_cycle(&mappings, 3).is_ok());
+    }
+
+    #[test]
+    fn quotient_and_inverse() {
+        // USDC/USD with 6 decimals, SOL/USD with 8 decimals
+        let prices = prices_with(&[
+            (
+                0,
+                Price {
+                    value: 1_000_000,
+                    exp: 6,
+                },
+                100,
+            ),
+            (
+                1,
+                Price {
+                    value: 2_081_000_000,
+                    exp: 8,
+                },
+                90,
+            ),
+        ]);
+        let usdc_sol = derived_price(OracleType::ScopeChainQuotient, (0, 1), &prices).unwrap();
+        assert_eq!(
+            usdc_sol.price,
+            Price {
+                value: 48_053_820_278_712_157,
+                exp: 18
+            }
+        );
+        assert_eq!(usdc_sol.last_updated_slot, 90);
+
+        let usd_sol = derived_price(OracleType::ScopeChainInverse, (1, 1), &prices).unwrap();
+        assert_eq!(usd_sol.price, usdc_sol.price);
+        assert_eq!(usd_sol.last_updated_slot, 90);
+    }
+
+    #[test]
+    fn inverse_round_trip() {
+        for price in [
+            Price {
+                value: 2_081_000_000,
+                exp: 8,
+            },
+            Price { value: 3, exp: 0 },
+            Price {
+                value: 123_456_789,
+                exp: 2,
+            },
+            Price { value: 7, exp: 12 },
+        ] {
+            let prices = prices_with(&[(0, price, 100)]);
+            let inverse = derived_price(OracleType::ScopeChainInverse, (0, 0), &prices).unwrap();
+            assert_eq!(inverse.last_updated_slot, 100);
+            let prices = prices_with(&[(1, inverse.price, inverse.last_updated_slot)]);
+            let round_trip = derived_price(OracleType::ScopeChainInverse, (1, 1), &prices).unwrap();
+            assert_eq!(round_trip.last_updated_slot, 100);
+
+            let expected = price.value as f64 / 10_f64.powi(price.exp as i32);
+            let actual = round_trip.price.value as f64 / 10_f64.powi(round_trip.price.exp as i32);
+            assert!(
+                ((actual - expected) / expected).abs() < 1e-9,
+                "{price:?} round trips to {:?}",
+                round_trip.price
+            );
+        }
+    }
+
+    #[test]
+    fn inverse_of_zero_price() {
+        let prices = prices_with(&[(0, Price { value: 0, exp: 6 }, 100)]);
+        assert_eq!(
+            derived_price(OracleType::ScopeChainInverse, (0, 0), &prices).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn quotient_by_unset_price() {
+        let prices = prices_with(&[(0, Price { value: 1, exp: 0 }, 100)]);
+        assert_eq!(
+            derived_price(OracleType::ScopeChainQuotient, (0, 1), &prices).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn refresh_order() {
+        // 2 = 0 * 1, 3 = 2 * 1
+        let mappings = mappings_with(&[(2, 0, 1), (3, 2, 1)]);
+        assert!(check_refresh_order(&mappings, &[0, 1, 2, 3]).is_ok());
+        assert!(check_refresh_order(&mappings, &[1, 0, 2]).is_ok());
+        // Sources not refreshed in the same list
+        assert!(check_refresh_order(&mappings, &[3]).is_ok());
+        assert!(check_refresh_order(&mappings, &[2, 3]).is_ok());
+
+        for tokens in [&[2, 0, 1][..], &[0, 2, 1], &[0, 1, 3, 2]] {
+            assert_eq!(
+                check_refresh_order(&mappings, tokens).unwrap_err(),
+                ScopeError::DerivedPriceBeforeSources.into()
+            );
+        }
+    }
+}
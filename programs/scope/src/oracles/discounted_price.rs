@@ -0,0 +1,174 @@
+//! Price of another token of the same scope feed multiplied by a constant factor
+//!
+//! Used for haircuts (e.g. a factor of 9_500 bps for a 5% collateral haircut) or fee adjusted
+//! prices (e.g. 10_005 bps). The stored price is `source * factor_bps / 10_000`, with the
+//! exponent of the source.
+//!
+//! As for [`super::derived_price`], the source token and the factor are encoded in the pubkey
+//! referenced in the oracle mapping (see [`Discount::to_mapping_pubkey`]) and the source must be
+//! refreshed before the discounted price. The discounted price has the dates of its source so it
+//! is never fresher than it. A zero factor is rejected when the mapping is updated.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::derived_price::get_source_price, DatedPrice, OraclePrices, Price, Result, ScopeError,
+};
+
+const FULL_BPS: u128 = 10_000;
+
+/// Marker byte of the encoded discount
+const DISCOUNT_MARKER: u8 = 5;
+/// Offset of the factor in the encoded discount, after the source token
+const FACTOR_OFFSET: usize = 2;
+const MARKER_OFFSET: usize = FACTOR_OFFSET + 4;
+
+/// Configuration of a discounted price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Discount {
+    /// Token whose price is discounted
+    pub source: u16,
+    /// Factor applied to the source price, in bps
+    pub factor_bps: u32,
+}
+
+impl Discount {
+    /// Encode the discount in a pubkey to store it in the oracle mapping
+    pub fn to_mapping_pubkey(&self) -> Pubkey {
+        let mut bytes = [0_u8; 32];
+        bytes[..FACTOR_OFFSET].copy_from_slice(&self.source.to_le_bytes());
+        bytes[FACTOR_OFFSET..MARKER_OFFSET].copy_from_slice(&self.factor_bps.to_le_bytes());
+        bytes[MARKER_OFFSET] = DISCOUNT_MARKER;
+        Pubkey::new_from_array(bytes)
+    }
+
+    /// Decode the discount stored in the oracle mapping
+    pub fn from_mapping_pubkey(pubkey: &Pubkey) -> Result<Self> {
+        let bytes = pubkey.to_bytes();
+        if bytes[MARKER_OFFSET] != DISCOUNT_MARKER
+            || bytes[MARKER_OFFSET + 1..].iter().any(|&b| b != 0)
+        {
+            msg!(
+                "Discounted price mapping {} is not an encoded discount",
+                pubkey
+            );
+            return err!(ScopeError::UnexpectedAccount);
+        }
+        let discount = Discount {
+            source: u16::from_le_bytes(bytes[..FACTOR_OFFSET].try_into().unwrap()),
+            factor_bps: u32::from_le_bytes(bytes[FACTOR_OFFSET..MARKER_OFFSET].try_into().unwrap()),
+        };
+        if usize::from(discount.source) >= crate::MAX_ENTRIES {
+            msg!("Discounted price source out of range: {}", discount.source);
+            return err!(ScopeError::BadTokenNb);
+        }
+        Ok(discount)
+    }
+}
+
+pub fn get_price(base_account: &AccountInfo, oracle_prices: &OraclePrices) -> Result<DatedPrice> {
+    let discount = Discount::from_mapping_pubkey(base_account.key)?;
+    let source = get_source_price(oracle_prices, discount.source)?;
+    Ok(DatedPrice {
+        price: apply_factor(&source.price, discount.factor_bps)?,
+        last_updated_slot: source.last_updated_slot,
+        unix_timestamp: source.unix_timestamp,
+        ..Default::default()
+    })
+}
+
+/// Check the encoding of a discounted price mapping and that its factor is not zero
+pub fn validate_mapping_account(mapping_account: &AccountInfo) -> Result<()> {
+    let discount = Discount::from_mapping_pubkey(mapping_account.key)?;
+    if discount.factor_bps == 0 {
+        msg!("Discounted price factor cannot be zero");
+        return err!(ScopeError::InvalidUpdateValue);
+    }
+    Ok(())
+}
+
+fn apply_factor(price: &Price, factor_bps: u32) -> Result<Price> {
+    let value = u128::from(price.value) * u128::from(factor_bps) / FULL_BPS;
+    Ok(Price {
+        value: u64::try_from(value).map_err(|_| ScopeError::MathOverflow)?,
+        exp: price.exp,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::oracles::capped_floored::PriceBand;
+
+    #[test]
+    fn test_encode_decode_discount() {
+        for discount in [
+            Discount {
+                source: 0,
+                factor_bps: 9_500,
+            },
+            Discount {
+                source: 511,
+                factor_bps: u32::MAX,
+            },
+        ] {
+            assert_eq!(
+                Discount::from_mapping_pubkey(&discount.to_mapping_pubkey()).unwrap(),
+                discount
+            );
+        }
+        let out_of_range = Discount {
+            source: 512,
+            factor_bps: 10_000,
+        };
+        assert_eq!(
+            Discount::from_mapping_pubkey(&out_of_range.to_mapping_pubkey()).unwrap_err(),
+            ScopeError::BadTokenNb.into()
+        );
+        assert_eq!(
+            Discount::from_mapping_pubkey(&Pubkey::default()).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        // Band of a clamped price
+        let band = PriceBand {
+            source: 0,
+            cap: None,
+            floor: None,
+        };
+        assert_eq!(
+            Discount::from_mapping_pubkey(&band.to_mapping_pubkey()).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    fn test_apply_factor() {
+        let price = Price {
+            value: 1_500_000,
+            exp: 6,
+        };
+        assert_eq!(
+            apply_factor(&price, 9_500).unwrap(),
+            Price {
+                value: 1_425_000,
+                exp: 6
+            }
+        );
+        assert_eq!(
+            apply_factor(&price, 10_005).unwrap(),
+            Price {
+                value: 1_500_750,
+                exp: 6
+            }
+        );
+        assert_eq!(apply_factor(&price, 10_000).unwrap(), price);
+        let max = Price {
+            value: u64::MAX,
+            exp: 0,
+        };
+        assert_eq!(
+            apply_factor(&max, 10_001).unwrap_err(),
+            ScopeError::MathOverflow.into()
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Constant price stored directly in the oracle mapping
+//!
+//! Useful for hard pegged tokens and tests: no external account is read on refresh.
+//!
+//! The price is encoded in the bytes of the pubkey referenced in the oracle mapping
+//! (see [`price_to_mapping_pubkey`]). The refresh instructions still check the received
+//! account against the mapping, so the crank must pass an account with this address.
+//! This account does not need to exist, it is only used as a placeholder.
+
+use anchor_lang::prelude::*;
+
+use crate::{DatedPrice, Price, Result, ScopeError};
+
+/// Encode a fixed price in a pubkey to store it in the oracle mapping
+pub fn price_to_mapping_pubkey(price: &Price) -> Pubkey {
+    let mut bytes = [0_u8; 32];
+    bytes[..8].copy_from_slice(&price.value.to_le_bytes());
+    bytes[8..16].copy_from_slice(&price.exp.to_le_bytes());
+    Pubkey::new_from_array(bytes)
+}
+
+/// Decode the fixed price stored in the oracle mapping
+pub fn price_from_mapping_pubkey(pubkey: &Pubkey) -> Result<Price> {
+    let bytes = pubkey.to_bytes();
+    if bytes[16..].iter().any(|&b| b != 0) {
+        msg!("Fixed price mapping {} is not an encoded price", pubkey);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let price = Price {
+        value: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        exp: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+    };
+    if price.value == 0 {
+        msg!("Fixed price is not set");
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(price)
+}
+
+pub fn get_price(base_account: &AccountInfo, clock: &Clock) -> Result<DatedPrice> {
+    let price = price_from_mapping_pubkey(base_account.key)?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: clock.slot,
+        unix_timestamp: u64::try_from(clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    })
+}
+
+pub fn validate_mapping_account(mapping_account: &AccountInfo) -> Result<()> {
+    price_from_mapping_pubkey(mapping_account.key).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_decode_price() {
+        let price = Price {
+            value: 1_000_000,
+            exp: 6,
+        };
+        let pk = price_to_mapping_pubkey(&price);
+        assert_eq!(price_from_mapping_pubkey(&pk).unwrap(), price);
+    }
+
+    #[test]
+    fn unset_price_is_not_valid() {
+        assert_eq!(
+            price_from_mapping_pubkey(&Pubkey::default()).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn regular_pubkey_is_rejected() {
+        assert_eq!(
+            price_from_mapping_pubkey(&crate::ID).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    fn dated_price_uses_clock() {
+        let price = Price { value: 42, exp: 0 };
+        let key = price_to_mapping_pubkey(&price);
+        let owner = Pubkey::default();
+        let mut lamports = 0;
+        let mut data = [];
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        let clock = Clock {
+            slot: 10,
+            unix_timestamp: 100,
+            ..Default::default()
+        };
+        let dated_price = get_price(&account, &clock).unwrap();
+        assert_eq!(dated_price.price, price);
+        assert_eq!(dated_price.last_updated_slot, 10);
+        assert_eq!(dated_price.unix_timestamp, 100);
+    }
+}
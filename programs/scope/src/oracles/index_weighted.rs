@@ -0,0 +1,241 @@
+//! Index price computed from weighted constituents of the same scope feed
+//!
+//! The index level is `sum(quantity_i * price_i)` where the quantities are absolute amounts of
+//! each constituent in one unit of the index (like the divisor of an index), not percentages.
+//! Rebalancing the index is done by the admin with the `update_index_constituents` instruction.
+//!
+//! The constituents are stored in an [`OracleIndex`] account owned by scope, referenced in the
+//! oracle mapping. As for [`crate::oracles::derived_price`] the constituent prices are read from
+//! the `OraclePrices` account being refreshed: they must be refreshed before the index. The index
+//! is only as fresh as its oldest constituent, and a constituent older than the
+//! `max_constituent_age_slots` of the index is rejected.
+
+use anchor_lang::{__private::bytemuck, prelude::*, Discriminator};
+
+use crate::{
+    oracles::derived_price::get_source_price,
+    utils::math::{add_prices, mul_prices},
+    DatedPrice, OracleIndex, OraclePrices, Price, Result, ScopeError,
+};
+
+pub fn get_price(
+    index_info: &AccountInfo,
+    oracle_prices: &OraclePrices,
+    oracle_prices_key: &Pubkey,
+    token: usize,
+    clock: &Clock,
+) -> Result<DatedPrice> {
+    let data = index_info.try_borrow_data()?;
+    let index = load_index(&data)?;
+    if index.oracle_prices != *oracle_prices_key {
+        msg!(
+            "Index {} does not belong to the refreshed feed",
+            index_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    compute_index(index, oracle_prices, token, clock)
+}
+
+/// Check that the given account is an index account of scope
+pub fn validate_index_account(index_info: &AccountInfo) -> Result<()> {
+    if index_info.owner != &crate::ID {
+        msg!("Index {} is not owned by scope", index_info.key);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    load_index(&index_info.try_borrow_data()?).map(|_| ())
+}
+
+fn load_index(data: &[u8]) -> Result<&OracleIndex> {
+    let size = std::mem::size_of::<OracleIndex>();
+    if data.len() < 8 + size || data[..8] != OracleIndex::discriminator() {
+        msg!("Provided account is not a scope index");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    Ok(bytemuck::from_bytes(&data[8..8 + size]))
+}
+
+fn compute_index(
+    index: &OracleIndex,
+    oracle_prices: &OraclePrices,
+    token: usize,
+    clock: &Clock,
+) -> Result<DatedPrice> {
+    let num_constituents = usize::try_from(index.num_constituents)
+        .ok()
+        .filter(|&num| num > 0 && num <= index.constituents.len())
+        .ok_or_else(|| {
+            msg!("Index has no valid constituents");
+            error!(ScopeError::PriceNotValid)
+        })?;
+
+    let mut level = Price { value: 0, exp: 0 };
+    let mut last_updated_slot = u64::MAX;
+    let mut unix_timestamp = u64::MAX;
+    for constituent in &index.constituents[..num_constituents] {
+        let constituent_token =
+            u16::try_from(constituent.token).map_err(|_| ScopeError::BadTokenNb)?;
+        if usize::from(constituent_token) == token {
+            msg!("Index of token {} cannot be one of its constituents", token);
+            return err!(ScopeError::CyclicDerivedPrice);
+        }
+        let price = get_source_price(oracle_prices, constituent_token)?;
+        let age = clock.slot.saturating_sub(price.last_updated_slot);
+        if index.max_constituent_age_slots != 0 && age > index.max_constituent_age_slots {
+            msg!(
+                "Constituent {} of the index is stale: {} slots old, max {}",
+                constituent_token,
+                age,
+                index.max_constituent_age_slots
+            );
+            return err!(ScopeError::PriceNotValid);
+        }
+        let value = mul_prices(&constituent.quantity, &price.price)?;
+        level = add_prices(&level, &value)?;
+        last_updated_slot = last_updated_slot.min(price.last_updated_slot);
+        unix_timestamp = unix_timestamp.min(price.unix_timestamp);
+    }
+
+    Ok(DatedPrice {
+        price: level,
+        last_updated_slot,
+        unix_timestamp,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IndexConstituent;
+
+    fn prices(entries: &[(usize, Price, u64)]) -> OraclePrices {
+        let mut oracle_prices: OraclePrices = bytemuck::Zeroable::zeroed();
+        for &(token, price, slot) in entries {
+            oracle_prices.prices[token] = DatedPrice {
+                price,
+                last_updated_slot: slot,
+                unix_timestamp: slot / 2,
+                ..Default::default()
+            };
+        }
+        oracle_prices
+    }
+
+    fn index(constituents: &[(u64, Price)], max_constituent_age_slots: u64) -> OracleIndex {
+        let mut index: OracleIndex = bytemuck::Zeroable::zeroed();
+        for (i, &(token, quantity)) in constituents.iter().enumerate() {
+            index.constituents[i] = IndexConstituent { token, quantity };
+        }
+        index.num_constituents = constituents.len() as u64;
+        index.max_constituent_age_slots = max_constituent_age_slots;
+        index
+    }
+
+    fn clock_at_slot(slot: u64) -> Clock {
+        Clock {
+            slot,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn index_level_is_sum_of_constituent_values() {
+        // 0.5 BTC at 60000.00 + 10 ETH at 3000.000
+        let oracle_prices = prices(&[
+            (
+                1,
+                Price {
+                    value: 6_000_000,
+                    exp: 2,
+                },
+                100,
+            ),
+            (
+                2,
+                Price {
+                    value: 3_000_000,
+                    exp: 3,
+                },
+                90,
+            ),
+        ]);
+        let index = index(
+            &[
+                (1, Price { value: 5, exp: 1 }),
+                (2, Price { value: 10, exp: 0 }),
+            ],
+            0,
+        );
+        let price = compute_index(&index, &oracle_prices, 0, &clock_at_slot(110)).unwrap();
+        assert_eq!(
+            price.price,
+            Price {
+                value: 60_000_000,
+                exp: 3
+            }
+        );
+        assert_eq!(price.last_updated_slot, 90);
+        assert_eq!(price.unix_timestamp, 45);
+    }
+
+    #[test]
+    fn stale_constituent() {
+        let oracle_prices = prices(&[
+            (1, Price { value: 100, exp: 0 }, 100),
+            (2, Price { value: 100, exp: 0 }, 50),
+        ]);
+        let index = index(
+            &[
+                (1, Price { value: 1, exp: 0 }),
+                (2, Price { value: 1, exp: 0 }),
+            ],
+            50,
+        );
+        assert!(compute_index(&index, &oracle_prices, 0, &clock_at_slot(100)).is_ok());
+        assert_eq!(
+            compute_index(&index, &oracle_prices, 0, &clock_at_slot(101)).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn invalid_constituents() {
+        let oracle_prices = prices(&[(1, Price { value: 100, exp: 0 }, 100)]);
+        // Never refreshed constituent
+        let unset = index(&[(3, Price { value: 1, exp: 0 })], 0);
+        assert_eq!(
+            compute_index(&unset, &oracle_prices, 0, &clock_at_slot(100)).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+        // Index referencing itself
+        let cyclic = index(&[(1, Price { value: 1, exp: 0 })], 0);
+        assert_eq!(
+            compute_index(&cyclic, &oracle_prices, 1, &clock_at_slot(100)).unwrap_err(),
+            ScopeError::CyclicDerivedPrice.into()
+        );
+        // No constituent
+        let empty = index(&[], 0);
+        assert_eq!(
+            compute_index(&empty, &oracle_prices, 0, &clock_at_slot(100)).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn overflowing_index() {
+        let oracle_prices = prices(&[(
+            1,
+            Price {
+                value: u64::MAX,
+                exp: 0,
+            },
+            100,
+        )]);
+        let index = index(&[(1, Price { value: 2, exp: 0 })], 0);
+        assert_eq!(
+            compute_index(&index, &oracle_prices, 0, &clock_at_slot(100)).unwrap_err(),
+            ScopeError::MathOverflow.into()
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Price of the LP token (JLP) of a Jupiter perpetuals pool
+//!
+//! The pool tracks the value of its assets in USD in `aum_usd` (with [`USD_DECIMALS`]
+//! decimals), the LP price is `aum_usd / lp_supply`. The LP mint is provided as extra account,
+//! it must be the LP mint of the pool.
+//!
+//! The AUM is only updated by the Jupiter keepers, the price is rejected if the last update is
+//! older than the max AUM age of the entry (in slots, see
+//! [`UpdateMappingConfigMode::MaxAumAge`]), or [`DEFAULT_MAX_AUM_AGE_SLOTS`] if not configured.
+//!
+//! [`UpdateMappingConfigMode::MaxAumAge`]: crate::UpdateMappingConfigMode::MaxAumAge
+
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    oracles::constant_product_lp::read_mint, utils::math::div_prices, DatedPrice, Price, Result,
+    ScopeError,
+};
+
+use self::perpetuals::Pool;
+
+/// Jupiter perpetuals program id
+pub const JUPITER_PERPETUALS_PROGRAM_ID: Pubkey =
+    pubkey!("PERPHjGBqRHArX4DySjwM6UJHiR3sWAatqfdBS2qQJu");
+
+/// Number of decimals of the USD values of the pool
+pub const USD_DECIMALS: u64 = 6;
+
+/// Max age of the AUM (in slots) when the entry does not configure one
+pub const DEFAULT_MAX_AUM_AGE_SLOTS: u64 = 150;
+
+/// Seed of the LP mint PDA of a pool
+const LP_MINT_SEED: &[u8] = b"lp_token_mint";
+
+/// Get the price of the LP token, see the module documentation
+///
+/// The only extra account consumed is the LP mint of the pool.
+pub fn get_price<'a, 'b>(
+    pool_info: &AccountInfo,
+    clock: &Clock,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+    max_aum_age: u32,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let pool = get_pool(pool_info)?;
+
+    let lp_mint_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    let expected_mint = lp_mint_address(pool_info.key);
+    if lp_mint_info.key() != expected_mint {
+        msg!(
+            "LP mint received {} is not the expected {}",
+            lp_mint_info.key(),
+            expected_mint
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    check_aum_age(&pool, clock, max_aum_age)?;
+
+    let (lp_supply, lp_decimals) = read_mint(lp_mint_info)?;
+    let price = lp_price(pool.aum_usd, lp_supply, lp_decimals)?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: clock.slot,
+        unix_timestamp: u64::try_from(clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    })
+}
+
+/// Check that the given account is a Jupiter perpetuals pool
+pub fn validate_pool_account(pool_info: &AccountInfo) -> Result<()> {
+    if pool_info.owner != &JUPITER_PERPETUALS_PROGRAM_ID {
+        msg!(
+            "Pool {} is not owned by the Jupiter perpetuals program",
+            pool_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    get_pool(pool_info).map(|_| ())
+}
+
+/// Address of the LP mint of the given pool
+pub fn lp_mint_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[LP_MINT_SEED, pool.as_ref()],
+        &JUPITER_PERPETUALS_PROGRAM_ID,
+    )
+    .0
+}
+
+fn get_pool(pool_info: &AccountInfo) -> Result<Pool> {
+    let data = pool_info.try_borrow_data()?;
+    if data.len() < 8 || data[..8] != Pool::DISCRIMINATOR {
+        msg!("Provided pubkey is not a Jupiter perpetuals pool");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    // Only the leading fields are declared, the rest of the account is ignored
+    Pool::deserialize(&mut &data[8..]).map_err(|_| {
+        msg!("Jupiter perpetuals pool cannot be deserialized");
+        error!(ScopeError::UnableToDeserializeAccount)
+    })
+}
+
+fn check_aum_age(pool: &Pool, clock: &Clock, max_aum_age: u32) -> Result<()> {
+    let max_age = match max_aum_age {
+        0 => DEFAULT_MAX_AUM_AGE_SLOTS,
+        max_age => u64::from(max_age),
+    };
+    let age = clock.slot.saturating_sub(pool.aum_usd_updated_slot);
+    if age > max_age {
+        msg!(
+            "Jupiter pool AUM is stale: updated at slot {}, current slot {}, max age {}",
+            pool.aum_usd_updated_slot,
+            clock.slot,
+            max_age
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(())
+}
+
+/// `aum_usd / lp_supply`, in USD for one LP token
+fn lp_price(aum_usd: u128, lp_supply: u64, lp_decimals: u8) -> Result<Price> {
+    if lp_supply == 0 {
+        msg!("Jupiter pool LP token has no supply");
+        return err!(ScopeError::PriceNotValid);
+    }
+    let aum = Price {
+        value: u64::try_from(aum_usd).map_err(|_| ScopeError::MathOverflow)?,
+        exp: USD_DECIMALS,
+    };
+    let supply = Price {
+        value: lp_supply,
+        exp: lp_decimals.into(),
+    };
+    Ok(div_prices(&aum, &supply)?)
+}
+
+pub mod perpetuals {
+    use super::*;
+
+    /// Leading fields of the `Pool` account of the Jupiter perpetuals program
+    #[derive(Default, Clone, Debug, AnchorDeserialize, AnchorSerialize)]
+    pub struct Pool {
+        pub name: String,
+        pub custodies: Vec<Pubkey>,
+        /// Value of the assets of the pool, in USD with [`USD_DECIMALS`] decimals
+        pub aum_usd: u128,
+        /// Slot of the last update of `aum_usd`
+        pub aum_usd_updated_slot: u64,
+    }
+
+    impl Discriminator for Pool {
+        // sha256("account:Pool")[..8]
+        const DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+
+        fn discriminator() -> [u8; 8] {
+            Self::DISCRIMINATOR
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pool_data(pool: &Pool) -> Vec<u8> {
+        let mut data = Pool::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&pool.try_to_vec().unwrap());
+        // Fields of the pool not read by scope
+        data.extend_from_slice(&[0; 64]);
+        data
+    }
+
+    fn with_pool_account<R>(data: &mut [u8], f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            data,
+            &JUPITER_PERPETUALS_PROGRAM_ID,
+            false,
+            0,
+        );
+        f(&account)
+    }
+
+    fn clock_at_slot(slot: u64) -> Clock {
+        Clock {
+            slot,
+            unix_timestamp: 1_700_000_000,
+            ..Default::default()
+        }
+    }
+
+    /// Pool with values in the range of the mainnet JLP pool
+    fn jlp_pool() -> Pool {
+        Pool {
+            name: "Pool".to_string(),
+            custodies: vec![Pubkey::new_unique(); 5],
+            // 1.5B USD
+            aum_usd: 1_500_000_000_000_000,
+            aum_usd_updated_slot: 1_000,
+        }
+    }
+
+    #[test]
+    fn price_is_aum_per_lp_token() {
+        // 500M JLP with 6 decimals
+        let price = lp_price(1_500_000_000_000_000, 500_000_000_000_000, 6).unwrap();
+        assert_eq!(
+            price,
+            Price {
+                value: 3_000_000_000_000_000_000,
+                exp: 18
+            }
+        );
+
+        assert_eq!(
+            lp_price(1_500_000_000_000_000, 0, 6).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+        assert_eq!(
+            lp_price(u128::from(u64::MAX) + 1, 1, 6).unwrap_err(),
+            ScopeError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn pool_parsing() {
+        let mut data = pool_data(&jlp_pool());
+        let pool = with_pool_account(&mut data, get_pool).unwrap();
+        assert_eq!(pool.aum_usd, 1_500_000_000_000_000);
+        assert_eq!(pool.aum_usd_updated_slot, 1_000);
+        assert!(with_pool_account(&mut data, validate_pool_account).is_ok());
+
+        data[0] = !data[0];
+        assert_eq!(
+            with_pool_account(&mut data, get_pool).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    fn stale_aum() {
+        let pool = jlp_pool();
+        assert!(check_aum_age(&pool, &clock_at_slot(1_000 + DEFAULT_MAX_AUM_AGE_SLOTS), 0).is_ok());
+        assert_eq!(
+            check_aum_age(&pool, &clock_at_slot(1_001 + DEFAULT_MAX_AUM_AGE_SLOTS), 0).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+
+        // Configured max age
+        assert!(check_aum_age(&pool, &clock_at_slot(1_010), 10).is_ok());
+        assert_eq!(
+            check_aum_age(&pool, &clock_at_slot(1_011), 10).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+}
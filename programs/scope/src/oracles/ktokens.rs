@@ -39,6 +39,13 @@ const USD_DECIMALS_PRECISION: u8 = 6;
 /// When calculating invested amounts, a sqrt price derived from scope price_a and price_b is used to determine the 'correct' ratio of underlying assets, the sqrt price of the pool cannot be considered reliable
 ///
 /// The kToken price timestamp is taken from the least-recently updated price in the scope price chains of token_a and token_b
+///
+/// `k_account` is the strategy account, the following extra accounts are consumed from `extra_accounts`, in order:
+/// 1. the strategy global config
+/// 2. the collateral infos referenced by the global config
+/// 3. the pool of the strategy (Orca whirlpool or Raydium pool)
+/// 4. the position of the strategy
+/// 5. the scope prices account used by the strategy
 pub fn get_price<'a, 'b>(
     k_account: &AccountInfo,
     clock: &Clock,
@@ -146,7 +153,7 @@ where
         &collateral_infos_ref,
         &strategy_account_ref,
     )?;
-    let value: u64 = token_price.as_u64();
+    let value = share_price_value(token_price)?;
     let exp = USD_DECIMALS_PRECISION.into();
 
     Ok(DatedPrice {
@@ -157,6 +164,16 @@ where
     })
 }
 
+/// Value of the price of a share, with [`USD_DECIMALS_PRECISION`] decimals
+///
+/// Fails if the price does not fit in a `u64`.
+fn share_price_value<T: TryInto<u64>>(token_price: T) -> Result<u64> {
+    token_price.try_into().map_err(|_| {
+        msg!("kToken price does not fit in a u64");
+        error!(ScopeError::IntegerOverflow)
+    })
+}
+
 fn get_clmm<'a, 'info>(
     pool: &'a AccountInfo<'info>,
     position: &'a AccountInfo<'info>,
@@ -362,6 +379,11 @@ mod price_utils {
             .checked_div(px_b)
             .unwrap();
 
+        if price_a_to_b > U128::from(u64::MAX) {
+            msg!("Price of token a in token b does not fit in a u64");
+            return err!(ScopeError::IntegerOverflow);
+        }
+
         Ok(yvaults::utils::price::Price {
             value: price_a_to_b.as_u64(),
             exp,
@@ -466,6 +488,26 @@ mod tests_price_utils {
         // assert_eq!(sqrt_price_from_scope_price(scope_price), sqrt_price);
     }
 
+    #[test]
+    fn test_a_to_b_overflow() {
+        let a = Price {
+            value: 100_000_000,
+            exp: 12,
+        };
+        let b = Price { value: 1, exp: 12 };
+        // 10^8 token b per token a, with 12 decimals
+        assert_eq!(
+            a_to_b(&a, &b).unwrap_err(),
+            ScopeError::IntegerOverflow.into()
+        );
+
+        let a = Price {
+            value: 10_000_000,
+            exp: 12,
+        };
+        assert_eq!(a_to_b(&a, &b).unwrap().value, 10_000_000 * 10_u64.pow(12));
+    }
+
     #[test]
     fn test_sqrt_price_from_float() {
         let price = 1.0;
@@ -613,6 +655,19 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    pub fn test_share_price_value_overflow() {
+        use decimal_wad::rate::U128;
+
+        // More than 2^64 scaled lamports per share
+        let token_price = U128::from(u64::MAX) + U128::from(1_u64);
+        assert_eq!(
+            share_price_value(token_price).unwrap_err(),
+            ScopeError::IntegerOverflow.into()
+        );
+        assert_eq!(share_price_value(U128::from(u64::MAX)).unwrap(), u64::MAX);
+    }
+
     #[test]
     pub fn test_get_component_px_last_update_single_link_chains() {
         let (scope_prices, collateral_infos, strategy) =
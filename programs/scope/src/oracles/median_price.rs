@@ -0,0 +1,318 @@
+//! Median of up to three other prices of the same scope feed
+//!
+//! Used for high value feeds aggregating independent sources (e.g. a Pyth, a Switchboard and a
+//! DEX TWAP entry). The median is computed over the valid sources only and at least
+//! [`MIN_VALID_SOURCES`] of them are required. With two valid sources the median is their mean.
+//! A source is valid if it has been refreshed and is not flagged as low confidence (see
+//! [`DatedPrice::is_low_confidence`]). Sources with different exponents are compared on their
+//! actual value.
+//!
+//! As for [`super::derived_price`], the source tokens are encoded in the pubkey referenced in the
+//! oracle mapping (see [`sources_to_mapping_pubkey`]) and the sources must be refreshed before the
+//! median. The median is only as fresh as the oldest valid source.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::derived_price::get_source_price,
+    utils::math::{add_prices, cmp_prices},
+    DatedPrice, OraclePrices, Price, Result, ScopeError,
+};
+
+/// Max number of sources of a median price
+pub const MAX_SOURCES: usize = 3;
+/// Min number of valid sources to compute a median price
+pub const MIN_VALID_SOURCES: usize = 2;
+
+/// Marker byte of the encoded sources, distinguishes them from the other derived prices ones
+const SOURCES_MARKER: u8 = 2;
+/// Offset of the number of sources in the encoded pubkey
+const NUM_SOURCES_OFFSET: usize = 2 * MAX_SOURCES;
+
+/// Encode the source tokens of a median price in a pubkey to store it in the oracle mapping
+///
+/// Panics if there are not between [`MIN_VALID_SOURCES`] and [`MAX_SOURCES`] sources.
+pub fn sources_to_mapping_pubkey(sources: &[u16]) -> Pubkey {
+    assert!(
+        (MIN_VALID_SOURCES..=MAX_SOURCES).contains(&sources.len()),
+        "A median price has {MIN_VALID_SOURCES} to {MAX_SOURCES} sources"
+    );
+    let mut bytes = [0_u8; 32];
+    for (i, source) in sources.iter().enumerate() {
+        bytes[2 * i..2 * i + 2].copy_from_slice(&source.to_le_bytes());
+    }
+    bytes[NUM_SOURCES_OFFSET] = sources.len() as u8;
+    bytes[NUM_SOURCES_OFFSET + 1] = SOURCES_MARKER;
+    Pubkey::new_from_array(bytes)
+}
+
+/// Decode the source tokens of a median price stored in the oracle mapping
+pub fn sources_from_mapping_pubkey(pubkey: &Pubkey) -> Result<Vec<u16>> {
+    let bytes = pubkey.to_bytes();
+    let num_sources = usize::from(bytes[NUM_SOURCES_OFFSET]);
+    if bytes[NUM_SOURCES_OFFSET + 1] != SOURCES_MARKER
+        || !(MIN_VALID_SOURCES..=MAX_SOURCES).contains(&num_sources)
+        || bytes[2 * num_sources..NUM_SOURCES_OFFSET]
+            .iter()
+            .chain(&bytes[NUM_SOURCES_OFFSET + 2..])
+            .any(|&b| b != 0)
+    {
+        msg!("Median price mapping {} is not encoded sources", pubkey);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let sources: Vec<u16> = bytes[..2 * num_sources]
+        .chunks_exact(2)
+        .map(|source| u16::from_le_bytes(source.try_into().unwrap()))
+        .collect();
+    if sources
+        .iter()
+        .any(|&source| usize::from(source) >= crate::MAX_ENTRIES)
+    {
+        msg!("Median price source out of range: {:?}", sources);
+        return err!(ScopeError::BadTokenNb);
+    }
+    Ok(sources)
+}
+
+pub fn get_price(base_account: &AccountInfo, oracle_prices: &OraclePrices) -> Result<DatedPrice> {
+    let sources = sources_from_mapping_pubkey(base_account.key)?;
+    let valid_prices: Vec<DatedPrice> = sources
+        .iter()
+        .filter_map(|&source| match get_source_price(oracle_prices, source) {
+            Ok(price) if !price.is_low_confidence() => Some(price),
+            Ok(_) => {
+                msg!("Source price of token {} has a low confidence", source);
+                None
+            }
+            Err(_) => None,
+        })
+        .collect();
+    if valid_prices.len() < MIN_VALID_SOURCES {
+        msg!(
+            "Only {} valid sources out of {}, at least {} are required",
+            valid_prices.len(),
+            sources.len(),
+            MIN_VALID_SOURCES
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+
+    let mut prices: Vec<Price> = valid_prices.iter().map(|price| price.price).collect();
+    let price = median(&mut prices)?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: valid_prices
+            .iter()
+            .map(|price| price.last_updated_slot)
+            .min()
+            .unwrap(),
+        unix_timestamp: valid_prices
+            .iter()
+            .map(|price| price.unix_timestamp)
+            .min()
+            .unwrap(),
+        ..Default::default()
+    })
+}
+
+/// Check that a median price mapping references distinct sources
+pub fn validate_mapping_account(mapping_account: &AccountInfo) -> Result<()> {
+    let sources = sources_from_mapping_pubkey(mapping_account.key)?;
+    for (i, source) in sources.iter().enumerate() {
+        if sources[i + 1..].contains(source) {
+            msg!("Median price source {} is referenced twice", source);
+            return err!(ScopeError::UnexpectedAccount);
+        }
+    }
+    Ok(())
+}
+
+/// Median of the given prices, the mean of the two middle ones for an even number of prices
+fn median(prices: &mut [Price]) -> Result<Price> {
+    prices.sort_by(cmp_prices);
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        return Ok(prices[middle]);
+    }
+    let sum = add_prices(&prices[middle - 1], &prices[middle])?;
+    // Divide by 2 without losing the last digit when possible: x / 2 = 5 * x / 10
+    Ok(match sum.value.checked_mul(5) {
+        Some(value) => Price {
+            value,
+            exp: sum.exp + 1,
+        },
+        None => Price {
+            value: sum.value / 2,
+            exp: sum.exp,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::__private::bytemuck::Zeroable;
+
+    use super::*;
+    use crate::{
+        oracles::{derived_price, OracleType},
+        OracleMappings,
+    };
+
+    fn with_mapping_account<R>(key: Pubkey, f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let owner = Pubkey::default();
+        let mut lamports = 0;
+        let mut data = [];
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        f(&account)
+    }
+
+    fn median_price(sources: &[u16], prices: &OraclePrices) -> Result<DatedPrice> {
+        with_mapping_account(sources_to_mapping_pubkey(sources), |acc| {
+            get_price(acc, prices)
+        })
+    }
+
+    fn prices_with(entries: &[(usize, Price, u64)]) -> Box<OraclePrices> {
+        let mut prices: Box<OraclePrices> = Box::new(Zeroable::zeroed());
+        for &(token, price, slot) in entries {
+            prices.prices[token] = DatedPrice {
+                price,
+                last_updated_slot: slot,
+                unix_timestamp: slot * 2,
+                ..Default::default()
+            };
+        }
+        prices
+    }
+
+    #[test]
+    fn test_encode_decode_sources() {
+        let pk = sources_to_mapping_pubkey(&[3, 511, 0]);
+        assert_eq!(sources_from_mapping_pubkey(&pk).unwrap(), vec![3, 511, 0]);
+        let pk = sources_to_mapping_pubkey(&[0, 0]);
+        assert_eq!(sources_from_mapping_pubkey(&pk).unwrap(), vec![0, 0]);
+        // Pair of sources of the other derived prices
+        assert_eq!(
+            sources_from_mapping_pubkey(&derived_price::sources_to_mapping_pubkey(0, 1))
+                .unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        assert_eq!(
+            sources_from_mapping_pubkey(&sources_to_mapping_pubkey(&[0, 512])).unwrap_err(),
+            ScopeError::BadTokenNb.into()
+        );
+    }
+
+    #[test]
+    fn test_median_with_different_exponents() {
+        // 100.10, 100.0 and 99.5 with different exponents
+        let prices = prices_with(&[
+            (
+                0,
+                Price {
+                    value: 10_010,
+                    exp: 2,
+                },
+                100,
+            ),
+            (
+                1,
+                Price {
+                    value: 100_000_000,
+                    exp: 6,
+                },
+                90,
+            ),
+            (2, Price { value: 995, exp: 1 }, 95),
+        ]);
+        let price = median_price(&[0, 1, 2], &prices).unwrap();
+        assert_eq!(
+            price.price,
+            Price {
+                value: 100_000_000,
+                exp: 6
+            }
+        );
+        // Oldest source
+        assert_eq!(price.last_updated_slot, 90);
+        assert_eq!(price.unix_timestamp, 180);
+    }
+
+    #[test]
+    fn test_median_of_two_valid_sources() {
+        // Source 2 has never been refreshed, source 3 has a low confidence
+        let mut prices = prices_with(&[
+            (
+                0,
+                Price {
+                    value: 10_010,
+                    exp: 2,
+                },
+                100,
+            ),
+            (
+                1,
+                Price {
+                    value: 100_000_000,
+                    exp: 6,
+                },
+                90,
+            ),
+            (3, Price { value: 1, exp: 0 }, 80),
+        ]);
+        prices.prices[3].flags = DatedPrice::FLAG_LOW_CONFIDENCE;
+        for sources in [[0, 1, 2], [0, 1, 3]] {
+            let price = median_price(&sources, &prices).unwrap();
+            // Mean of 100.10 and 100.0
+            assert_eq!(
+                price.price,
+                Price {
+                    value: 1_000_500_000,
+                    exp: 7
+                }
+            );
+            assert_eq!(price.last_updated_slot, 90);
+        }
+
+        assert_eq!(
+            median_price(&[0, 2, 3], &prices).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn test_median_sources_are_checked() {
+        let valid = sources_to_mapping_pubkey(&[0, 1, 2]);
+        assert!(with_mapping_account(valid, validate_mapping_account).is_ok());
+        let duplicated = sources_to_mapping_pubkey(&[0, 1, 0]);
+        assert_eq!(
+            with_mapping_account(duplicated, validate_mapping_account).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+
+        // 3 = median(0, 1, 4) and 4 = 3 * 2
+        let mut mappings: Box<OracleMappings> = Box::new(Zeroable::zeroed());
+        mappings.price_info_accounts[3] = sources_to_mapping_pubkey(&[0, 1, 4]);
+        mappings.price_types[3] = OracleType::ScopeMedian.into();
+        mappings.price_info_accounts[4] = derived_price::sources_to_mapping_pubkey(3, 2);
+        mappings.price_types[4] = OracleType::ScopeChainProduct.into();
+        assert_eq!(
+            derived_price::check_no_cycle(&mappings, 3).unwrap_err(),
+            ScopeError::CyclicDerivedPrice.into()
+        );
+        assert_eq!(
+            derived_price::check_refresh_order(&mappings, &[0, 3, 1]).unwrap_err(),
+            ScopeError::DerivedPriceBeforeSources.into()
+        );
+    }
+}
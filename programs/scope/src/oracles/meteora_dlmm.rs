@@ -0,0 +1,328 @@
+//! Spot price of Meteora DLMM (Dynamic Liquidity Market Maker) pools
+//!
+//! The price of a pool is the price of its active bin: `(1 + bin_step / 10_000) ^ active_id`,
+//! expressed in lamports of token Y for one lamport of token X. It is adjusted with the decimals
+//! of both mints, provided as extra accounts, to give the price of 1 token X in token Y.
+//!
+//! The ordering of the tokens in a pool is arbitrary, [`OracleType::MeteoraDlmmYtoX`] gives the
+//! price of 1 token Y in token X instead.
+//!
+//! The price is read from the pool state only, it is dated with the current clock.
+//!
+//! [`OracleType::MeteoraDlmmYtoX`]: super::OracleType::MeteoraDlmmYtoX
+
+use anchor_lang::prelude::*;
+use rust_decimal::Decimal;
+
+use crate::{DatedPrice, Price, Result, ScopeError};
+
+/// Meteora DLMM program id
+pub const METEORA_DLMM_PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+
+/// Anchor discriminator of the `LbPair` account
+const LB_PAIR_DISCRIMINATOR: [u8; 8] = [33, 11, 49, 98, 181, 101, 177, 13];
+// Offsets of the used fields in the `LbPair` account (discriminator included)
+const ACTIVE_ID_OFFSET: usize = 76;
+const BIN_STEP_OFFSET: usize = 80;
+const TOKEN_X_MINT_OFFSET: usize = 88;
+const TOKEN_Y_MINT_OFFSET: usize = 120;
+const LB_PAIR_MIN_SIZE: usize = 152;
+
+/// Offset of the decimals in a SPL token mint account (same for token-2022 mints)
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_SIZE: usize = 82;
+
+/// Fields of the `LbPair` account needed to compute the pool price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LbPair {
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+}
+
+impl LbPair {
+    pub fn from_account_data(data: &[u8]) -> Result<LbPair> {
+        if data.len() < LB_PAIR_MIN_SIZE {
+            msg!("Meteora DLMM pool account is too small");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        if data[..8] != LB_PAIR_DISCRIMINATOR {
+            msg!("Account is not a Meteora DLMM pool");
+            return err!(ScopeError::InvalidAccountDiscriminator);
+        }
+        let read_pubkey =
+            |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+        Ok(LbPair {
+            active_id: i32::from_le_bytes(
+                data[ACTIVE_ID_OFFSET..ACTIVE_ID_OFFSET + 4]
+                    .try_into()
+                    .unwrap(),
+            ),
+            bin_step: u16::from_le_bytes(
+                data[BIN_STEP_OFFSET..BIN_STEP_OFFSET + 2]
+                    .try_into()
+                    .unwrap(),
+            ),
+            token_x_mint: read_pubkey(TOKEN_X_MINT_OFFSET),
+            token_y_mint: read_pubkey(TOKEN_Y_MINT_OFFSET),
+        })
+    }
+}
+
+/// Get the price of the pool, see the module documentation
+///
+/// The mint X and mint Y accounts of the pool are consumed from `extra_accounts`, in this order.
+pub fn get_price<'a, 'b>(
+    y_to_x: bool,
+    lb_pair_info: &AccountInfo,
+    clock: &Clock,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let lb_pair = LbPair::from_account_data(&lb_pair_info.try_borrow_data()?)?;
+
+    let mint_x_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    let mint_y_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    if mint_x_info.key() != lb_pair.token_x_mint || mint_y_info.key() != lb_pair.token_y_mint {
+        msg!(
+            "Meteora DLMM mints received ({}, {}) are not the ones of the pool ({}, {})",
+            mint_x_info.key(),
+            mint_y_info.key(),
+            lb_pair.token_x_mint,
+            lb_pair.token_y_mint
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    let price = pool_price(
+        &lb_pair,
+        mint_decimals(mint_x_info)?,
+        mint_decimals(mint_y_info)?,
+        y_to_x,
+    )?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: clock.slot,
+        unix_timestamp: u64::try_from(clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    })
+}
+
+/// Check that the given account is a Meteora DLMM pool
+pub fn validate_lb_pair_account(lb_pair_info: &AccountInfo) -> Result<()> {
+    if lb_pair_info.owner != &METEORA_DLMM_PROGRAM_ID {
+        msg!(
+            "Meteora DLMM pool {} is not owned by the DLMM program",
+            lb_pair_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    LbPair::from_account_data(&lb_pair_info.try_borrow_data()?).map(|_| ())
+}
+
+fn mint_decimals(mint_info: &AccountInfo) -> Result<u8> {
+    let data = mint_info.try_borrow_data()?;
+    if data.len() < MINT_SIZE {
+        msg!("Account {} is not a token mint", mint_info.key);
+        return err!(ScopeError::UnableToDeserializeAccount);
+    }
+    Ok(data[MINT_DECIMALS_OFFSET])
+}
+
+/// Price of 1 token X in token Y, or of 1 token Y in token X if `y_to_x` is set
+fn pool_price(lb_pair: &LbPair, decimals_x: u8, decimals_y: u8, y_to_x: bool) -> Result<Price> {
+    let bin_price = bin_price(lb_pair.active_id, lb_pair.bin_step)?;
+    // Lamports of Y per lamport of X to tokens of Y per token of X
+    let decimals_factor = checked_powi(Decimal::TEN, i32::from(decimals_x) - i32::from(decimals_y))
+        .ok_or(ScopeError::MathOverflow)?;
+    let x_to_y = bin_price
+        .checked_mul(decimals_factor)
+        .ok_or(ScopeError::MathOverflow)?;
+    let price = if y_to_x {
+        Decimal::ONE
+            .checked_div(x_to_y)
+            .ok_or(ScopeError::MathOverflow)?
+    } else {
+        x_to_y
+    };
+    decimal_to_price(price)
+}
+
+/// Price of the bin `active_id`, in lamports of token Y per lamport of token X
+fn bin_price(active_id: i32, bin_step: u16) -> Result<Decimal> {
+    // The bin step is expressed in basis points
+    let base = Decimal::ONE + Decimal::new(bin_step.into(), 4);
+    checked_powi(base, active_id).ok_or_else(|| {
+        msg!(
+            "Meteora DLMM bin price overflow (active id {}, bin step {})",
+            active_id,
+            bin_step
+        );
+        error!(ScopeError::MathOverflow)
+    })
+}
+
+/// `base ^ exp` by exponentiation by squaring, `None` on overflow
+fn checked_powi(base: Decimal, exp: i32) -> Option<Decimal> {
+    let mut result = Decimal::ONE;
+    let mut base = base;
+    let mut n = exp.unsigned_abs();
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        n >>= 1;
+        // Don't square past the last bit to avoid a spurious overflow
+        if n > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    if exp < 0 {
+        Decimal::ONE.checked_div(result)
+    } else {
+        Some(result)
+    }
+}
+
+/// Convert a positive decimal to a [`Price`], keeping as many decimals as fit in a `u64`
+fn decimal_to_price(price: Decimal) -> Result<Price> {
+    let price = price.normalize();
+    let mut value = price.mantissa();
+    let mut exp = price.scale();
+    while value > i128::from(u64::MAX) {
+        if exp == 0 {
+            msg!("Meteora DLMM price does not fit in a u64");
+            return err!(ScopeError::MathOverflow);
+        }
+        // Loss of precision here is expected.
+        value /= 10;
+        exp -= 1;
+    }
+    if value <= 0 {
+        msg!("Meteora DLMM price is too small to be represented");
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(Price {
+        value: value.try_into().unwrap(), // Cannot fail, checked above
+        exp: exp.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lb_pair(active_id: i32, bin_step: u16) -> LbPair {
+        LbPair {
+            active_id,
+            bin_step,
+            token_x_mint: Pubkey::new_unique(),
+            token_y_mint: Pubkey::new_unique(),
+        }
+    }
+
+    fn to_f64(price: Price) -> f64 {
+        price.value as f64 / 10_f64.powi(price.exp.try_into().unwrap())
+    }
+
+    fn assert_price_eq(price: Price, expected: f64) {
+        let rel_diff = (to_f64(price) - expected).abs() / expected;
+        assert!(
+            rel_diff < 1e-12,
+            "price {price:?} ({}) != {expected}",
+            to_f64(price)
+        );
+    }
+
+    #[test]
+    fn test_bin_zero_is_one_lamport() {
+        // Same decimals, 1 lamport of X for 1 lamport of Y
+        assert_eq!(
+            pool_price(&lb_pair(0, 10), 6, 6, false).unwrap(),
+            Price { value: 1, exp: 0 }
+        );
+        // 9 decimals X in 6 decimals Y
+        assert_eq!(
+            pool_price(&lb_pair(0, 10), 9, 6, false).unwrap(),
+            Price {
+                value: 1000,
+                exp: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_sol_usdc_pool() {
+        // SOL (9 decimals) / USDC (6 decimals) with a 10 bps bin step
+        let pool = lb_pair(-1897, 10);
+        assert_price_eq(
+            pool_price(&pool, 9, 6, false).unwrap(),
+            150.160263507669503154,
+        );
+        assert_price_eq(
+            pool_price(&pool, 9, 6, true).unwrap(),
+            0.006659551446171540243,
+        );
+    }
+
+    #[test]
+    fn test_bin_steps_and_decimals() {
+        // 1 bps bin step, same decimals
+        assert_price_eq(
+            pool_price(&lb_pair(100, 1), 6, 6, false).unwrap(),
+            1.010049662092876568855,
+        );
+        // 25 bps bin step
+        assert_price_eq(
+            pool_price(&lb_pair(3000, 25), 6, 6, false).unwrap(),
+            1791.199158990692745072,
+        );
+        // 100 bps bin step, 5 decimals X in 9 decimals Y
+        assert_price_eq(
+            pool_price(&lb_pair(-1100, 100), 5, 9, false).unwrap(),
+            1.763960431984680049e-9,
+        );
+    }
+
+    #[test]
+    fn test_price_overflow() {
+        assert_eq!(
+            pool_price(&lb_pair(443_636, 100), 6, 6, false).unwrap_err(),
+            error!(ScopeError::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn test_parse_lb_pair() {
+        let expected = lb_pair(-1897, 10);
+        let mut data = vec![0_u8; 904];
+        data[..8].copy_from_slice(&LB_PAIR_DISCRIMINATOR);
+        data[ACTIVE_ID_OFFSET..ACTIVE_ID_OFFSET + 4]
+            .copy_from_slice(&expected.active_id.to_le_bytes());
+        data[BIN_STEP_OFFSET..BIN_STEP_OFFSET + 2]
+            .copy_from_slice(&expected.bin_step.to_le_bytes());
+        data[TOKEN_X_MINT_OFFSET..TOKEN_X_MINT_OFFSET + 32]
+            .copy_from_slice(expected.token_x_mint.as_ref());
+        data[TOKEN_Y_MINT_OFFSET..TOKEN_Y_MINT_OFFSET + 32]
+            .copy_from_slice(expected.token_y_mint.as_ref());
+        assert_eq!(LbPair::from_account_data(&data).unwrap(), expected);
+
+        data[0] = 0;
+        assert_eq!(
+            LbPair::from_account_data(&data).unwrap_err(),
+            error!(ScopeError::InvalidAccountDiscriminator)
+        );
+        assert_eq!(
+            LbPair::from_account_data(&data[..100]).unwrap_err(),
+            error!(ScopeError::UnableToDeserializeAccount)
+        );
+    }
+}
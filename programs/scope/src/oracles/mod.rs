@@ -1,18 +1,43 @@
+pub mod alias_price;
+pub mod capped_floored;
+pub mod chainlink;
+pub mod constant_product_lp;
 pub mod ctokens;
+pub mod derived_price;
+pub mod discounted_price;
+pub mod fixed_price;
+pub mod index_weighted;
+pub mod jupiter_lp;
 #[cfg(feature = "yvaults")]
 pub mod ktokens;
+pub mod median_price;
+pub mod meteora_dlmm;
+pub mod most_recent_of;
 pub mod msol_stake;
 pub mod pyth;
+pub mod pyth_conservative;
 pub mod pyth_ema;
+pub mod pyth_lazer;
+pub mod redstone;
+pub mod saber_stable_swap;
+pub mod scope_twap;
+pub mod share_price;
 pub mod spl_stake;
+pub mod stsol_stake;
+pub mod switchboard_on_demand;
 pub mod switchboard_v1;
 pub mod switchboard_v2;
+pub mod whirlpool;
 
-use anchor_lang::prelude::{err, AccountInfo, Clock, Context, Result};
+use std::iter::Peekable;
+
+use anchor_lang::prelude::{
+    err, msg, AccountInfo, AccountLoader, Clock, Context, Key, Pubkey, Result,
+};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
 
-use crate::{DatedPrice, ScopeError};
+use crate::{DatedPrice, OracleMappings, OraclePrices, OracleTwaps, ScopeError};
 
 pub fn check_context<T>(ctx: &Context<T>) -> Result<()> {
     //make sure there are no extra accounts
@@ -36,7 +61,7 @@ pub enum OracleType {
     DeprecatedPlaceholder = 3,
     /// Solend tokens
     CToken = 4,
-    /// SPL Stake Pool token (like scnSol)
+    /// SPL Stake Pool token (like scnSol), also used for Sanctum single and multi-validator pools
     SplStake = 5,
     /// KTokens from Kamino
     KToken = 6,
@@ -44,6 +69,61 @@ pub enum OracleType {
     PythEMA = 7,
     /// MSOL Stake Pool token
     MsolStake = 8,
+    /// Lido stSOL token
+    StsolStake = 9,
+    /// Constant price stored in the oracle mapping
+    FixedPrice = 10,
+    /// Switchboard On-Demand pull feed
+    SwitchboardOnDemand = 11,
+    /// Chainlink OCR2 feed
+    Chainlink = 12,
+    /// Product of two other prices of the feed
+    ScopeChainProduct = 13,
+    /// Quotient of two other prices of the feed
+    ScopeChainQuotient = 14,
+    /// Inverse of another price of the feed
+    ScopeChainInverse = 15,
+    /// Meteora DLMM pool price of token X in token Y
+    MeteoraDlmmXtoY = 16,
+    /// Meteora DLMM pool price of token Y in token X
+    MeteoraDlmmYtoX = 17,
+    /// Fair price of the LP token of a constant product pool
+    ConstantProductLp = 18,
+    /// Virtual price of the LP token of a Saber stable swap pool
+    SaberStableSwapLp = 19,
+    /// Price of the LP token of a Jupiter perpetuals pool (JLP)
+    JupiterLp = 20,
+    /// Pyth price minus its confidence interval
+    PythConservativeLower = 21,
+    /// Pyth price plus its confidence interval
+    PythConservativeUpper = 22,
+    /// Index level computed from quantities of other prices of the feed
+    IndexWeighted = 23,
+    /// RedStone price adapter push feed
+    RedStone = 24,
+    /// Price of a share in the tokens held by its vault
+    SharePrice = 25,
+    /// Most recent valid price of a Pyth and a Switchboard source
+    MostRecentOf = 26,
+    /// Median of two or three other prices of the feed
+    ScopeMedian = 27,
+    /// Price of another token of the feed clamped between a floor and a cap
+    CappedFloored = 28,
+    /// EMA of another price of the feed maintained in the TWAP account
+    ScopeTwap = 29,
+    /// SPL Stake Pool token priced even when the pool update is late, dated from the epoch of
+    /// its last update
+    SplStakeLenient = 30,
+    /// Pyth Lazer update signed by a trusted signer
+    PythLazer = 31,
+    /// Price of another token of the feed multiplied by a constant factor
+    Discounted = 32,
+    /// Exact copy of another price of the feed
+    Alias = 33,
+    /// Orca Whirlpool pool price of token A in token B
+    OrcaWhirlpoolAtoB = 34,
+    /// Orca Whirlpool pool price of token B in token A
+    OrcaWhirlpoolBtoA = 35,
 }
 
 impl OracleType {
@@ -54,51 +134,353 @@ impl OracleType {
             OracleType::SwitchboardV1 => 15000,
             OracleType::SwitchboardV2 => 30000,
             OracleType::CToken => 130000,
-            OracleType::SplStake => 20000,
+            OracleType::SplStake | OracleType::SplStakeLenient => 40000,
             OracleType::KToken => 120000,
             OracleType::PythEMA => 15000,
             OracleType::MsolStake => 20000,
+            OracleType::StsolStake => 20000,
+            OracleType::FixedPrice => 5000,
+            OracleType::SwitchboardOnDemand => 30000,
+            OracleType::Chainlink => 20000,
+            OracleType::ScopeChainProduct => 10000,
+            OracleType::ScopeChainQuotient => 10000,
+            OracleType::ScopeChainInverse => 10000,
+            OracleType::MeteoraDlmmXtoY => 30000,
+            OracleType::MeteoraDlmmYtoX => 30000,
+            OracleType::ConstantProductLp => 40000,
+            OracleType::SaberStableSwapLp => 60000,
+            OracleType::JupiterLp => 40000,
+            OracleType::PythConservativeLower => 15000,
+            OracleType::PythConservativeUpper => 15000,
+            OracleType::IndexWeighted => 40000,
+            OracleType::RedStone => 20000,
+            OracleType::SharePrice => 30000,
+            OracleType::MostRecentOf => 50000,
+            OracleType::ScopeMedian => 20000,
+            OracleType::CappedFloored => 15000,
+            OracleType::ScopeTwap => 10000,
+            OracleType::PythLazer => 30000,
+            OracleType::Discounted => 10000,
+            OracleType::Alias => 5000,
+            OracleType::OrcaWhirlpoolAtoB => 30000,
+            OracleType::OrcaWhirlpoolBtoA => 30000,
             OracleType::DeprecatedPlaceholder => {
                 panic!("DeprecatedPlaceholder is not a valid oracle type")
             }
         }
     }
+
+    /// Whether the source account embeds a feed identity (see [`get_source_identity`])
+    pub fn has_source_identity(&self) -> bool {
+        matches!(
+            self,
+            OracleType::Pyth
+                | OracleType::PythEMA
+                | OracleType::PythConservativeLower
+                | OracleType::PythConservativeUpper
+                | OracleType::PythLazer
+        )
+    }
+
+    /// Whether the price is validated against its confidence interval (see [`pyth::ConfidenceCheck`])
+    pub fn has_confidence_interval(&self) -> bool {
+        matches!(
+            self,
+            OracleType::Pyth
+                | OracleType::PythEMA
+                | OracleType::PythConservativeLower
+                | OracleType::PythConservativeUpper
+        )
+    }
+
+    /// Whether the price is computed from other prices of the feed (see [`derived_price`])
+    pub fn is_derived(&self) -> bool {
+        matches!(
+            self,
+            OracleType::ScopeChainProduct
+                | OracleType::ScopeChainQuotient
+                | OracleType::ScopeChainInverse
+                | OracleType::ScopeMedian
+                | OracleType::CappedFloored
+                | OracleType::ScopeTwap
+                | OracleType::Discounted
+                | OracleType::Alias
+        )
+    }
+
+    /// Programs allowed to own the source account, `None` when the ownership is not checked
+    ///
+    /// Pyth and Switchboard v1/v2 accounts are created by the mock oracles program on localnet,
+    /// their ownership is only checked on mainnet. Pyth Lazer updates can be held by any
+    /// account, they are authenticated by their signature and feed id.
+    pub fn expected_owners(&self) -> Option<&'static [Pubkey]> {
+        match self {
+            OracleType::Pyth
+            | OracleType::PythEMA
+            | OracleType::PythConservativeLower
+            | OracleType::PythConservativeUpper
+                if !cfg!(any(feature = "localnet", feature = "skip_price_validation")) =>
+            {
+                Some(&[pyth::PYTH_PROGRAM_ID])
+            }
+            OracleType::SwitchboardV1
+                if !cfg!(any(feature = "localnet", feature = "skip_price_validation")) =>
+            {
+                Some(&[switchboard_v1::SWITCHBOARD_V1_PROGRAM_ID])
+            }
+            OracleType::SwitchboardV2
+                if !cfg!(any(feature = "localnet", feature = "skip_price_validation")) =>
+            {
+                Some(&[switchboard_v2::SWITCHBOARD_V2_PROGRAM_ID])
+            }
+            OracleType::MsolStake if !cfg!(feature = "skip_price_validation") => {
+                Some(&[msol_stake::MARINADE_PROGRAM_ID])
+            }
+            OracleType::StsolStake if !cfg!(feature = "skip_price_validation") => {
+                Some(&[stsol_stake::LIDO_PROGRAM_ID])
+            }
+            OracleType::SwitchboardOnDemand => {
+                Some(&[switchboard_on_demand::SWITCHBOARD_ON_DEMAND_PROGRAM_ID])
+            }
+            OracleType::Chainlink => Some(&[chainlink::CHAINLINK_STORE_PROGRAM_ID]),
+            OracleType::MeteoraDlmmXtoY | OracleType::MeteoraDlmmYtoX => {
+                Some(&[meteora_dlmm::METEORA_DLMM_PROGRAM_ID])
+            }
+            OracleType::OrcaWhirlpoolAtoB | OracleType::OrcaWhirlpoolBtoA => {
+                Some(&[whirlpool::WHIRLPOOL_PROGRAM_ID])
+            }
+            OracleType::ConstantProductLp => Some(&constant_product_lp::TOKEN_SWAP_PROGRAM_IDS),
+            OracleType::SaberStableSwapLp => {
+                Some(&[saber_stable_swap::SABER_STABLE_SWAP_PROGRAM_ID])
+            }
+            OracleType::JupiterLp => Some(&[jupiter_lp::JUPITER_PERPETUALS_PROGRAM_ID]),
+            OracleType::IndexWeighted => Some(&[crate::ID]),
+            OracleType::RedStone => Some(&[redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID]),
+            OracleType::SharePrice => Some(&share_price::TOKEN_PROGRAM_IDS),
+            OracleType::SplStake | OracleType::SplStakeLenient
+                if !cfg!(feature = "skip_price_validation") =>
+            {
+                Some(&spl_stake::STAKE_POOL_PROGRAM_IDS)
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Get the price for a given oracle type
 ///
 /// The `base_account` should have been checked against the oracle mapping
 /// If needed the `extra_accounts` will be extracted from the provided iterator and checked
-/// with the data contained in the `base_account`
-pub fn get_price<'a, 'b>(
+/// with the data contained in the `base_account`, optional ones are only extracted if they are
+/// the next account
+/// The `oracle_prices` are only loaded for prices derived from other prices of the feed, the
+/// `oracle_twaps` for the EMA prices
+/// The configuration of the `token` entry in `oracle_mappings` is used by the oracle types
+/// having one (e.g. the underlying prices of LP tokens)
+pub fn get_price<'a, 'b, I>(
     price_type: OracleType,
     base_account: &AccountInfo,
-    _extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+    extra_accounts: &mut Peekable<I>,
     clock: &Clock,
+    oracle_prices: &AccountLoader<OraclePrices>,
+    oracle_twaps: Option<&AccountLoader<OracleTwaps>>,
+    oracle_mappings: &OracleMappings,
+    token: usize,
 ) -> crate::Result<DatedPrice>
 where
+    I: Iterator<Item = &'b AccountInfo<'a>>,
     'a: 'b,
 {
     match price_type {
-        OracleType::Pyth => pyth::get_price(base_account),
+        OracleType::Pyth => pyth::get_price(
+            base_account,
+            &pyth::ConfidenceCheck::from_mappings(oracle_mappings, token),
+        ),
         OracleType::SwitchboardV1 => switchboard_v1::get_price(base_account),
-        OracleType::SwitchboardV2 => switchboard_v2::get_price(base_account),
+        OracleType::SwitchboardV2 => switchboard_v2::get_price(
+            base_account,
+            clock,
+            oracle_mappings.max_std_deviations_bps[token],
+        ),
         OracleType::CToken => ctokens::get_price(base_account, clock),
-        OracleType::SplStake => spl_stake::get_price(base_account, clock),
+        OracleType::SplStake => spl_stake::get_price(base_account, clock, extra_accounts),
+        OracleType::SplStakeLenient => {
+            spl_stake::get_price_lenient(base_account, clock, extra_accounts)
+        }
         #[cfg(not(feature = "yvaults"))]
         OracleType::KToken => {
             panic!("yvaults feature is not enabled, KToken oracle type is not available")
         }
         #[cfg(feature = "yvaults")]
-        OracleType::KToken => ktokens::get_price(base_account, clock, _extra_accounts),
-        OracleType::PythEMA => pyth_ema::get_price(base_account),
+        OracleType::KToken => ktokens::get_price(base_account, clock, extra_accounts),
+        OracleType::PythEMA => pyth_ema::get_price(
+            base_account,
+            &pyth::ConfidenceCheck::from_mappings(oracle_mappings, token),
+        ),
         OracleType::MsolStake => msol_stake::get_price(base_account, clock),
+        OracleType::StsolStake => stsol_stake::get_price(base_account, clock),
+        OracleType::FixedPrice => fixed_price::get_price(base_account, clock),
+        OracleType::SwitchboardOnDemand => switchboard_on_demand::get_price(base_account, clock),
+        OracleType::Chainlink => chainlink::get_price(base_account),
+        OracleType::MeteoraDlmmXtoY => {
+            meteora_dlmm::get_price(false, base_account, clock, extra_accounts)
+        }
+        OracleType::MeteoraDlmmYtoX => {
+            meteora_dlmm::get_price(true, base_account, clock, extra_accounts)
+        }
+        OracleType::ScopeChainProduct
+        | OracleType::ScopeChainQuotient
+        | OracleType::ScopeChainInverse => {
+            derived_price::get_price(price_type, base_account, &*oracle_prices.load()?)
+        }
+        OracleType::ConstantProductLp => constant_product_lp::get_price(
+            base_account,
+            extra_accounts,
+            &*oracle_prices.load()?,
+            &oracle_mappings.underlying_prices[token],
+        ),
+        OracleType::SaberStableSwapLp => {
+            saber_stable_swap::get_price(base_account, clock, extra_accounts)
+        }
+        OracleType::JupiterLp => jupiter_lp::get_price(
+            base_account,
+            clock,
+            extra_accounts,
+            oracle_mappings.max_aum_ages[token],
+        ),
+        OracleType::PythConservativeLower => pyth_conservative::get_price(
+            base_account,
+            pyth_conservative::ConfidenceBound::Lower,
+            &pyth::ConfidenceCheck::from_mappings(oracle_mappings, token),
+        ),
+        OracleType::PythConservativeUpper => pyth_conservative::get_price(
+            base_account,
+            pyth_conservative::ConfidenceBound::Upper,
+            &pyth::ConfidenceCheck::from_mappings(oracle_mappings, token),
+        ),
+        OracleType::IndexWeighted => index_weighted::get_price(
+            base_account,
+            &*oracle_prices.load()?,
+            &oracle_prices.key(),
+            token,
+            clock,
+        ),
+        OracleType::RedStone => redstone::get_price(base_account),
+        OracleType::SharePrice => share_price::get_price(base_account, clock, extra_accounts),
+        OracleType::MostRecentOf => most_recent_of::get_price(
+            base_account,
+            clock,
+            extra_accounts,
+            oracle_mappings,
+            token,
+        ),
+        OracleType::ScopeMedian => median_price::get_price(base_account, &*oracle_prices.load()?),
+        OracleType::CappedFloored => {
+            capped_floored::get_price(base_account, &*oracle_prices.load()?)
+        }
+        OracleType::ScopeTwap => scope_twap::get_price(base_account, oracle_twaps),
+        OracleType::PythLazer => {
+            pyth_lazer::get_price(base_account, extra_accounts, clock, oracle_mappings, token)
+        }
+        OracleType::Discounted => {
+            discounted_price::get_price(base_account, &*oracle_prices.load()?)
+        }
+        OracleType::Alias => alias_price::get_price(base_account, &*oracle_prices.load()?),
+        OracleType::OrcaWhirlpoolAtoB => {
+            whirlpool::get_price(false, base_account, clock, extra_accounts)
+        }
+        OracleType::OrcaWhirlpoolBtoA => {
+            whirlpool::get_price(true, base_account, clock, extra_accounts)
+        }
         OracleType::DeprecatedPlaceholder => {
             panic!("DeprecatedPlaceholder is not a valid oracle type")
         }
     }
 }
 
+/// Get the status of a source account, if the oracle type has one (see [`pyth::SourceStatus`])
+///
+/// An account whose status cannot be read has no status.
+pub fn get_source_status(
+    price_type: OracleType,
+    price_account: &AccountInfo,
+) -> Option<pyth::SourceStatus> {
+    match price_type {
+        OracleType::Pyth
+        | OracleType::PythEMA
+        | OracleType::PythConservativeLower
+        | OracleType::PythConservativeUpper => pyth::get_source_status(price_account).ok(),
+        _ => None,
+    }
+}
+
+/// Get the identity of the feed embedded in a source account, if the oracle type has one
+///
+/// For Pyth prices it is the product account the price account belongs to, for Pyth Lazer
+/// updates the signer of the update.
+pub fn get_source_identity(
+    price_type: OracleType,
+    price_account: &AccountInfo,
+) -> crate::Result<Option<Pubkey>> {
+    match price_type {
+        OracleType::Pyth
+        | OracleType::PythEMA
+        | OracleType::PythConservativeLower
+        | OracleType::PythConservativeUpper => pyth::get_product_account(price_account).map(Some),
+        OracleType::PythLazer => {
+            let data = price_account.try_borrow_data()?;
+            pyth_lazer::SignedUpdate::from_account_data(&data).map(|update| Some(update.signer))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Check that the source account embeds the `pinned` identity, the default pubkey disables
+/// the check
+///
+/// A pinned identity cannot be verified on an oracle type without identity, it is rejected.
+pub fn check_source_identity(
+    price_type: OracleType,
+    price_account: &AccountInfo,
+    pinned: &Pubkey,
+) -> crate::Result<()> {
+    if *pinned == Pubkey::default() {
+        return Ok(());
+    }
+    match get_source_identity(price_type, price_account)? {
+        Some(identity) if identity == *pinned => Ok(()),
+        identity => {
+            msg!(
+                "Source identity {:?} does not match the pinned identity {}",
+                identity,
+                pinned
+            );
+            err!(ScopeError::SourceIdentityMismatch)
+        }
+    }
+}
+
+/// Check that the source account is still owned by the program expected for its oracle type
+///
+/// The owner is also checked when the mapping is updated, an account closed and reallocated by
+/// another program since then must not be read as a valid source.
+pub fn check_source_owner(
+    price_type: OracleType,
+    price_account: &AccountInfo,
+) -> crate::Result<()> {
+    match price_type.expected_owners() {
+        Some(owners) if !owners.contains(price_account.owner) => {
+            msg!(
+                "Source account {} is owned by {}, not by the program of its oracle type",
+                price_account.key,
+                price_account.owner
+            );
+            err!(ScopeError::UnexpectedAccount)
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Validate the given account as being an appropriate price account for the
 /// given oracle type.
 ///
@@ -109,13 +491,48 @@ pub fn validate_oracle_account(
 ) -> crate::Result<()> {
     match price_type {
         OracleType::Pyth => pyth::validate_pyth_price_info(price_account),
-        OracleType::SwitchboardV1 => Ok(()), // TODO at least check account ownership?
-        OracleType::SwitchboardV2 => Ok(()), // TODO at least check account ownership?
-        OracleType::CToken => Ok(()),        // TODO how shall we validate ctoken account?
-        OracleType::SplStake => Ok(()),
+        OracleType::SwitchboardV1 => switchboard_v1::validate_aggregator_account(price_account),
+        OracleType::SwitchboardV2 => switchboard_v2::validate_aggregator_account(price_account),
+        OracleType::CToken => Ok(()), // TODO how shall we validate ctoken account?
+        OracleType::SplStake | OracleType::SplStakeLenient => {
+            spl_stake::validate_stake_pool_account(price_account)
+        }
         OracleType::KToken => Ok(()),
-        OracleType::PythEMA => pyth::validate_pyth_price_info(price_account),
-        OracleType::MsolStake => Ok(()),
+        OracleType::PythEMA
+        | OracleType::PythConservativeLower
+        | OracleType::PythConservativeUpper => pyth::validate_pyth_price_info(price_account),
+        OracleType::MsolStake => msol_stake::validate_marinade_account(price_account),
+        OracleType::StsolStake => stsol_stake::validate_lido_account(price_account),
+        OracleType::FixedPrice => fixed_price::validate_mapping_account(price_account),
+        OracleType::SwitchboardOnDemand => {
+            switchboard_on_demand::validate_feed_account(price_account)
+        }
+        OracleType::Chainlink => chainlink::validate_transmissions_account(price_account),
+        OracleType::MeteoraDlmmXtoY | OracleType::MeteoraDlmmYtoX => {
+            meteora_dlmm::validate_lb_pair_account(price_account)
+        }
+        OracleType::ScopeChainProduct
+        | OracleType::ScopeChainQuotient
+        | OracleType::ScopeChainInverse => {
+            derived_price::validate_mapping_account(price_type, price_account)
+        }
+        OracleType::ConstantProductLp => constant_product_lp::validate_pool_account(price_account),
+        OracleType::SaberStableSwapLp => saber_stable_swap::validate_swap_account(price_account),
+        OracleType::JupiterLp => jupiter_lp::validate_pool_account(price_account),
+        OracleType::IndexWeighted => index_weighted::validate_index_account(price_account),
+        OracleType::RedStone => redstone::validate_price_data_account(price_account),
+        OracleType::SharePrice => share_price::validate_vault_account(price_account),
+        // The mapping is a placeholder, the sources are validated at refresh
+        OracleType::MostRecentOf => Ok(()),
+        OracleType::ScopeMedian => median_price::validate_mapping_account(price_account),
+        OracleType::CappedFloored => capped_floored::validate_mapping_account(price_account),
+        OracleType::ScopeTwap => scope_twap::validate_mapping_account(price_account),
+        OracleType::PythLazer => pyth_lazer::validate_update_account(price_account),
+        OracleType::Discounted => discounted_price::validate_mapping_account(price_account),
+        OracleType::Alias => alias_price::validate_mapping_account(price_account),
+        OracleType::OrcaWhirlpoolAtoB | OracleType::OrcaWhirlpoolBtoA => {
+            whirlpool::validate_whirlpool_account(price_account)
+        }
         OracleType::DeprecatedPlaceholder => {
             panic!("DeprecatedPlaceholder is not a valid oracle type")
         }
@@ -0,0 +1,178 @@
+//! Most recent price of two sources, for redundancy between providers
+//!
+//! An entry references a Pyth price account and a Switchboard feed (V2 or on-demand), in any
+//! order. Both are evaluated at refresh and the valid price with the newest timestamp is stored,
+//! the first source winning ties. The refresh only fails if both sources are invalid, or if any
+//! of them is not owned by the program of its oracle type.
+//!
+//! The mapping account is a placeholder derived from both sources (see [`mapping_pubkey`]),
+//! the sources are provided as extra accounts in the same order. The stored price records the
+//! source it comes from in [`DatedPrice::generic_data`] (see [`winning_source`]).
+
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+
+use crate::{
+    oracles::{
+        check_source_owner, pyth, switchboard_on_demand, switchboard_v2,
+        switchboard_v2::switchboard::AggregatorAccountData, OracleType,
+    },
+    DatedPrice, OracleMappings, Result, ScopeError,
+};
+
+/// Index of the first source in [`DatedPrice::generic_data`]
+pub const FIRST_SOURCE: u8 = 0;
+/// Index of the second source in [`DatedPrice::generic_data`]
+pub const SECOND_SOURCE: u8 = 1;
+
+/// Placeholder referenced in the oracle mapping, binding the entry to its two sources
+///
+/// Like [`crate::oracles::fixed_price`] mappings the account does not need to exist.
+pub fn mapping_pubkey(first_source: &Pubkey, second_source: &Pubkey) -> Pubkey {
+    Pubkey::new_from_array(
+        hashv(&[
+            b"most_recent_of",
+            first_source.as_ref(),
+            second_source.as_ref(),
+        ])
+        .to_bytes(),
+    )
+}
+
+/// Index of the source a price of this oracle type comes from
+pub fn winning_source(dated_price: &DatedPrice) -> u8 {
+    dated_price.generic_data[0]
+}
+
+/// Get the most recent valid price of the two sources, see the module documentation
+///
+/// The extra accounts are consumed in order: first source, second source.
+pub fn get_price<'a, 'b>(
+    base_account: &AccountInfo,
+    clock: &Clock,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+    oracle_mappings: &OracleMappings,
+    token: usize,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let first_source = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    let second_source = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    let expected_mapping = mapping_pubkey(first_source.key, second_source.key);
+    if base_account.key() != expected_mapping {
+        msg!(
+            "Sources {} and {} do not match the mapping {}",
+            first_source.key,
+            second_source.key,
+            base_account.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    // A source owned by another program fails the refresh, it is not skipped like an invalid price
+    let first_type = source_type(first_source)?;
+    check_source_owner(first_type, first_source)?;
+    let second_type = source_type(second_source)?;
+    check_source_owner(second_type, second_source)?;
+
+    let first_price = get_source_price(first_type, first_source, clock, oracle_mappings, token);
+    let second_price = get_source_price(second_type, second_source, clock, oracle_mappings, token);
+    let (source, mut dated_price) = select_most_recent(first_price, second_price)?;
+    dated_price.generic_data[0] = source;
+    Ok(dated_price)
+}
+
+/// Oracle type of a source, Switchboard feeds are recognized by their owner or discriminator
+fn source_type(source_info: &AccountInfo) -> Result<OracleType> {
+    if source_info.owner == &switchboard_on_demand::SWITCHBOARD_ON_DEMAND_PROGRAM_ID {
+        return Ok(OracleType::SwitchboardOnDemand);
+    }
+    let data = source_info.try_borrow_data()?;
+    if data.get(..8) == Some(&AggregatorAccountData::discriminator()[..]) {
+        Ok(OracleType::SwitchboardV2)
+    } else {
+        Ok(OracleType::Pyth)
+    }
+}
+
+/// Price of a source with the configuration of the entry
+fn get_source_price(
+    source_type: OracleType,
+    source_info: &AccountInfo,
+    clock: &Clock,
+    oracle_mappings: &OracleMappings,
+    token: usize,
+) -> Result<DatedPrice> {
+    match source_type {
+        OracleType::SwitchboardOnDemand => switchboard_on_demand::get_price(source_info, clock),
+        OracleType::SwitchboardV2 => switchboard_v2::get_price(
+            source_info,
+            clock,
+            oracle_mappings.max_std_deviations_bps[token],
+        ),
+        _ => pyth::get_price(
+            source_info,
+            &pyth::ConfidenceCheck::from_mappings(oracle_mappings, token),
+        ),
+    }
+    .map_err(|e| {
+        msg!("Source {} of the entry is invalid", source_info.key);
+        e
+    })
+}
+
+/// Select the valid price with the newest timestamp, with the index of its source
+fn select_most_recent(
+    first: Result<DatedPrice>,
+    second: Result<DatedPrice>,
+) -> Result<(u8, DatedPrice)> {
+    match (first, second) {
+        (Ok(first), Ok(second)) if second.unix_timestamp > first.unix_timestamp => {
+            Ok((SECOND_SOURCE, second))
+        }
+        (Ok(first), _) => Ok((FIRST_SOURCE, first)),
+        (Err(_), Ok(second)) => Ok((SECOND_SOURCE, second)),
+        (Err(_), Err(_)) => {
+            msg!("No valid price in both sources");
+            err!(ScopeError::PriceNotValid)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Price;
+
+    fn price_at(value: u64, unix_timestamp: u64) -> Result<DatedPrice> {
+        Ok(DatedPrice {
+            price: Price { value, exp: 2 },
+            unix_timestamp,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_select_most_recent() {
+        let (source, price) = select_most_recent(price_at(100, 10), price_at(200, 11)).unwrap();
+        assert_eq!((source, price.price.value), (SECOND_SOURCE, 200));
+        let (source, price) = select_most_recent(price_at(100, 11), price_at(200, 10)).unwrap();
+        assert_eq!((source, price.price.value), (FIRST_SOURCE, 100));
+        // The first source wins ties
+        let (source, _) = select_most_recent(price_at(100, 10), price_at(200, 10)).unwrap();
+        assert_eq!(source, FIRST_SOURCE);
+    }
+
+    #[test]
+    fn test_mapping_pubkey_depends_on_order() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_ne!(
+            mapping_pubkey(&first, &second),
+            mapping_pubkey(&second, &first)
+        );
+    }
+}
@@ -1,10 +1,23 @@
-use anchor_lang::prelude::*;
+//! Marinade mSOL priced from the Marinade `State` account
+//!
+//! Marinade publishes the value of 1 mSOL in SOL in the `msol_price` field of its state, as a
+//! fraction of [`MSOL_PRICE_DENOMINATOR`]. It is updated by the Marinade cranks every epoch, the
+//! price is rejected if the last stake delta (run at the end of each epoch) is older than the
+//! previous epoch.
+
+use anchor_lang::{prelude::*, Discriminator};
 use solana_program::borsh0_10::try_from_slice_unchecked;
 
 use crate::{DatedPrice, Price, Result, ScopeError};
 
 use self::msol_stake_pool::State;
 
+/// Marinade liquid staking program id
+pub const MARINADE_PROGRAM_ID: Pubkey = pubkey!("MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD");
+
+/// `msol_price` is a fixed point number with 32 fractional bits
+pub const MSOL_PRICE_DENOMINATOR: u64 = 1 << 32;
+
 const DECIMALS: u32 = 15u32;
 
 // Gives the price of 1 staked SOL in SOL
@@ -12,13 +25,23 @@ pub fn get_price(
     msol_pool_account_info: &AccountInfo,
     current_clock: &Clock,
 ) -> Result<DatedPrice> {
-    let stake_pool = try_from_slice_unchecked::<State>(&msol_pool_account_info.data.borrow()[8..])
-        .map_err(|_| {
-            msg!("Provided pubkey is not a MSOL Stake account");
-            ScopeError::UnexpectedAccount
-        })?;
+    let stake_pool = get_marinade_state(msol_pool_account_info)?;
+
+    #[cfg(not(feature = "skip_price_validation"))]
+    {
+        let last_update_epoch = stake_pool.stake_system.last_stake_delta_epoch;
+        if last_update_epoch.saturating_add(1) < current_clock.epoch {
+            msg!(
+                "Marinade state has not been updated since epoch {} (current epoch {})",
+                last_update_epoch,
+                current_clock.epoch
+            );
+            #[cfg(not(feature = "localnet"))]
+            return Err(ScopeError::PriceNotValid.into());
+        }
+    }
 
-    let value = scaled_rate(&stake_pool)?;
+    let value = scaled_rate(stake_pool.msol_price)?;
 
     let price = Price {
         value,
@@ -34,30 +57,39 @@ pub fn get_price(
     Ok(dated_price)
 }
 
-fn scaled_rate(stake_pool: &State) -> Result<u64> {
-    const FACTOR: u64 = 10u64.pow(DECIMALS);
-    stake_pool.calc_lamports_from_msol_amount(FACTOR)
+/// Check that the given account is the Marinade state account
+pub fn validate_marinade_account(msol_pool_account_info: &AccountInfo) -> Result<()> {
+    if cfg!(feature = "skip_price_validation") {
+        return Ok(());
+    }
+    if msol_pool_account_info.owner != &MARINADE_PROGRAM_ID {
+        msg!("Provided Marinade state is not owned by the Marinade program");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    get_marinade_state(msol_pool_account_info).map(|_| ())
 }
 
-mod msol_stake_pool {
-    use anchor_lang::prelude::borsh::BorshSchema;
-
-    /// calculate amount*numerator/denominator
-    /// as value  = shares * share_price where share_price=total_value/total_shares
-    /// or shares = amount_value / share_price where share_price=total_value/total_shares
-    ///     => shares = amount_value * 1/share_price where 1/share_price=total_shares/total_value
-    pub fn proportional(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
-        if denominator == 0 {
-            return Ok(amount);
-        }
-        u64::try_from((amount as u128) * (numerator as u128) / (denominator as u128))
-            .map_err(|_| ScopeError::MathOverflow.into())
+fn get_marinade_state(msol_pool_account_info: &AccountInfo) -> Result<State> {
+    let data = msol_pool_account_info.data.borrow();
+    if data.len() < 8 || data[..8] != State::DISCRIMINATOR {
+        msg!("Provided pubkey is not a Marinade state account");
+        return err!(ScopeError::UnexpectedAccount);
     }
+    try_from_slice_unchecked::<State>(&data[8..]).map_err(|_| {
+        msg!("Provided pubkey is not a MSOL Stake account");
+        error!(ScopeError::UnexpectedAccount)
+    })
+}
 
-    #[inline] //alias for proportional
-    pub fn value_from_shares(shares: u64, total_value: u64, total_shares: u64) -> Result<u64> {
-        proportional(shares, total_value, total_shares)
-    }
+/// Convert the Marinade `msol_price` to a price with `DECIMALS` decimals
+fn scaled_rate(msol_price: u64) -> Result<u64> {
+    const FACTOR: u128 = 10u128.pow(DECIMALS);
+    u64::try_from(u128::from(msol_price) * FACTOR / u128::from(MSOL_PRICE_DENOMINATOR))
+        .map_err(|_| ScopeError::MathOverflow.into())
+}
+
+mod msol_stake_pool {
+    use anchor_lang::prelude::borsh::BorshSchema;
 
     use super::*;
     #[derive(
@@ -189,37 +221,12 @@ mod msol_stake_pool {
         pub emergency_cooling_down: u64,
     }
 
-    impl State {
-        pub fn total_cooling_down(&self) -> Result<u64> {
-            self.stake_system
-                .delayed_unstake_cooling_down
-                .checked_add(self.emergency_cooling_down)
-                .ok_or_else(|| ScopeError::MathOverflow.into())
-        }
+    impl Discriminator for State {
+        // sha256("account:State")[..8]
+        const DISCRIMINATOR: [u8; 8] = [216, 146, 107, 94, 104, 75, 182, 177];
 
-        /// total_active_balance + total_cooling_down + available_reserve_balance
-        pub fn total_lamports_under_control(&self) -> Result<u64> {
-            self.validator_system
-                .total_active_balance
-                .checked_add(self.total_cooling_down()?)
-                .ok_or(ScopeError::MathOverflow)?
-                .checked_add(self.available_reserve_balance) // reserve_pda.lamports() - self.rent_exempt_for_token_acc
-                .ok_or_else(|| ScopeError::MathOverflow.into())
-        }
-
-        pub fn total_virtual_staked_lamports(&self) -> Result<u64> {
-            // if we get slashed it may be negative but we must use 0 instead
-            Ok(self
-                .total_lamports_under_control()?
-                .saturating_sub(self.circulating_ticket_balance)) //tickets created -> cooling down lamports or lamports already in reserve and not claimed yet
-        }
-
-        pub fn calc_lamports_from_msol_amount(&self, msol_amount: u64) -> Result<u64> {
-            value_from_shares(
-                msol_amount,
-                self.total_virtual_staked_lamports()?,
-                self.msol_supply,
-            )
+        fn discriminator() -> [u8; 8] {
+            Self::DISCRIMINATOR
         }
     }
 }
@@ -230,45 +237,91 @@ mod test {
 
     use super::*;
 
-    #[test]
-    pub fn minted_token_is_equal_to_token_in_vault() {
-        let available_reserve_balance = 10u64.pow(5);
-        let msol_supply = 10u64.pow(5);
-        let stake_pool = State {
-            available_reserve_balance,
-            msol_supply,
+    fn state_data(state: &State) -> Vec<u8> {
+        let mut data = State::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&state.try_to_vec().unwrap());
+        data
+    }
+
+    fn with_state_account<R>(data: &mut [u8], f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            data,
+            &MARINADE_PROGRAM_ID,
+            false,
+            0,
+        );
+        f(&account)
+    }
+
+    /// State with values in the range of the mainnet account
+    fn mainnet_state() -> State {
+        let mut state = State {
+            msol_mint: pubkey!("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So"),
+            available_reserve_balance: 29_406_466_584_185,
+            msol_supply: 5_426_813_207_345_432,
+            // 1.17 SOL
+            msol_price: 5_025_111_736,
             ..Default::default()
         };
-        assert_eq!(scaled_rate(&stake_pool).unwrap(), 10u64.pow(DECIMALS));
+        state.stake_system.last_stake_delta_epoch = 560;
+        state
     }
 
-    #[test]
-    pub fn minted_token_is_2x_token_in_vault() {
-        // Note: this should never happen
-        let available_reserve_balance = 10u64.pow(5);
-        let msol_supply = 2 * 10u64.pow(5);
-        let stake_pool = State {
-            available_reserve_balance,
-            msol_supply,
+    fn clock_at_epoch(epoch: u64) -> Clock {
+        Clock {
+            slot: 241_920_000,
+            epoch,
+            unix_timestamp: 1_700_000_000,
             ..Default::default()
-        };
-        // Expect staked token price to be 0.5 token
+        }
+    }
+
+    #[test]
+    pub fn msol_price_is_a_32_bits_fraction() {
+        assert_eq!(
+            scaled_rate(MSOL_PRICE_DENOMINATOR).unwrap(),
+            10u64.pow(DECIMALS)
+        );
         assert_eq!(
-            scaled_rate(&stake_pool).unwrap(),
+            scaled_rate(MSOL_PRICE_DENOMINATOR / 2).unwrap(),
             5 * 10u64.pow(DECIMALS - 1)
         );
+        assert_eq!(
+            scaled_rate(2 * MSOL_PRICE_DENOMINATOR).unwrap(),
+            2 * 10u64.pow(DECIMALS)
+        );
     }
 
     #[test]
-    pub fn token_in_vault_is_2x_token_minted() {
-        let available_reserve_balance = 2 * 10u64.pow(5);
-        let msol_supply = 10u64.pow(5);
-        let stake_pool = State {
-            available_reserve_balance,
-            msol_supply,
-            ..Default::default()
-        };
-        // Expect staked token price to be 2 tokens
-        assert_eq!(scaled_rate(&stake_pool).unwrap(), 2 * 10u64.pow(DECIMALS));
+    pub fn price_from_state_snapshot() {
+        let mut data = state_data(&mainnet_state());
+        let price =
+            with_state_account(&mut data, |acc| get_price(acc, &clock_at_epoch(561))).unwrap();
+        assert_eq!(price.price.value, 1_169_999_999_925_494);
+        assert_eq!(price.price.exp, u64::from(DECIMALS));
+        assert_eq!(price.last_updated_slot, 241_920_000);
+        assert_eq!(price.unix_timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    pub fn invalid_discriminator() {
+        let mut data = state_data(&mainnet_state());
+        data[0] = !data[0];
+        let res = with_state_account(&mut data, |acc| get_price(acc, &clock_at_epoch(560)));
+        assert_eq!(res.unwrap_err(), ScopeError::UnexpectedAccount.into());
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "skip_price_validation", feature = "localnet")))]
+    pub fn outdated_state() {
+        let mut data = state_data(&mainnet_state());
+        let res = with_state_account(&mut data, |acc| get_price(acc, &clock_at_epoch(562)));
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
     }
 }
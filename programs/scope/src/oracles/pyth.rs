@@ -7,20 +7,120 @@
 //!    expected to be checked by the admin to ensure the product has the expected quality prior the mapping
 //!    update.
 //! 2. Upon usage the current price state is checked in [`validate_valid_price`]
-//! 3. The confidence interval is also checked in this same function with [`ORACLE_CONFIDENCE_FACTOR`]
+//! 3. The confidence interval is also checked in this same function with [`validate_confidence`], the
+//!    confidence/price ratio must not exceed the max ratio of the entry ([`ORACLE_CONFIDENCE_MAX_BPS`]
+//!    by default). Depending on the [`ConfidencePolicy`] of the entry a wider interval either rejects
+//!    the price or flags it as low confidence.
 
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 
 use anchor_lang::prelude::*;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
 use pyth_client::PriceType;
 use pyth_sdk_solana::state as pyth_client;
 
-use crate::{DatedPrice, Price, Result, ScopeError};
+use crate::{DatedPrice, OracleMappings, Price, Result, ScopeError};
 
-/// validate price confidence - confidence/price ratio should be less than 2%
-const ORACLE_CONFIDENCE_FACTOR: u64 = 50; // 100% / 2%
+/// Program owning the Pyth price accounts
+pub const PYTH_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
 
-pub fn get_price(price_info: &AccountInfo) -> Result<DatedPrice> {
+/// Max confidence/price ratio accepted by default, in basis points (2%)
+pub const ORACLE_CONFIDENCE_MAX_BPS: u64 = 200;
+
+const FULL_BPS: u128 = 10_000;
+
+/// Max number of decimals of a pyth price, `10^19` is the largest power of 10 in a `u64`
+pub const MAX_PYTH_DECIMALS: u64 = 19;
+
+/// Handling of a price whose confidence interval is wider than the max ratio of its entry
+/// (see [`crate::UpdateMappingConfigMode::MaxConfidence`])
+#[derive(TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[repr(u8)]
+pub enum ConfidencePolicy {
+    /// The price is rejected
+    #[default]
+    Reject = 0,
+    /// The price is stored with [`DatedPrice::FLAG_LOW_CONFIDENCE`] set
+    FlagOnly = 1,
+}
+
+/// Status of the aggregate price of a pyth account, stored in [`DatedPrice::source_status`]
+///
+/// Only a `Trading` price is accepted at refresh, the status is stored anyway so consumers can
+/// tell a price kept because its source is halted from one kept because it was not refreshed.
+#[derive(IntoPrimitive, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[repr(u16)]
+pub enum SourceStatus {
+    #[default]
+    Unknown = 0,
+    Trading = 1,
+    Halted = 2,
+    Auction = 3,
+}
+
+impl From<pyth_client::PriceStatus> for SourceStatus {
+    fn from(status: pyth_client::PriceStatus) -> Self {
+        match status {
+            pyth_client::PriceStatus::Unknown => SourceStatus::Unknown,
+            pyth_client::PriceStatus::Trading => SourceStatus::Trading,
+            pyth_client::PriceStatus::Halted => SourceStatus::Halted,
+            pyth_client::PriceStatus::Auction => SourceStatus::Auction,
+        }
+    }
+}
+
+/// Confidence interval requirement of a pyth entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfidenceCheck {
+    pub max_confidence_bps: u64,
+    pub policy: ConfidencePolicy,
+}
+
+impl Default for ConfidenceCheck {
+    fn default() -> Self {
+        Self {
+            max_confidence_bps: ORACLE_CONFIDENCE_MAX_BPS,
+            policy: ConfidencePolicy::Reject,
+        }
+    }
+}
+
+impl ConfidenceCheck {
+    /// Requirement configured for the `token` entry, a max ratio of 0 is the default one
+    pub fn from_mappings(oracle_mappings: &OracleMappings, token: usize) -> Self {
+        let max_confidence_bps = match oracle_mappings.max_confidences_bps[token] {
+            0 => ORACLE_CONFIDENCE_MAX_BPS,
+            bps => bps.into(),
+        };
+        // Policies are validated when configured
+        let policy = ConfidencePolicy::try_from(oracle_mappings.confidence_policies[token])
+            .unwrap_or_default();
+        Self {
+            max_confidence_bps,
+            policy,
+        }
+    }
+
+    /// Check the confidence interval of a price, returning the flags of the price to store
+    ///
+    /// A zero price is rejected whatever the policy.
+    pub fn check(&self, price: u64, conf: u64) -> Result<u16> {
+        match validate_confidence(price, conf, self.max_confidence_bps) {
+            Ok(()) => Ok(0),
+            Err(_) if price != 0 && self.policy == ConfidencePolicy::FlagOnly => {
+                msg!("Pyth price accepted with a low confidence flag");
+                Ok(DatedPrice::FLAG_LOW_CONFIDENCE)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Get the validated aggregate price, with its confidence interval
+pub fn get_price(
+    price_info: &AccountInfo,
+    confidence_check: &ConfidenceCheck,
+) -> Result<DatedPrice> {
     let data = price_info.try_borrow_data()?;
     let price_account = pyth_client::load_price_account(data.as_ref())
         .map_err(|_| error!(ScopeError::PriceNotValid))?;
@@ -38,7 +138,7 @@ pub fn get_price(price_info: &AccountInfo) -> Result<DatedPrice> {
         return err!(ScopeError::PriceNotValid);
     };
 
-    let price = validate_valid_price(&pyth_price, ORACLE_CONFIDENCE_FACTOR).map_err(|e| {
+    let (price, flags) = validate_valid_price(&pyth_price, confidence_check).map_err(|e| {
         msg!(
             "Confidence interval check failed on pyth account {}",
             price_info.key
@@ -46,35 +146,109 @@ pub fn get_price(price_info: &AccountInfo) -> Result<DatedPrice> {
         e
     })?;
 
-    Ok(DatedPrice {
+    let dated_price = DatedPrice {
         price: Price {
             value: price,
-            exp: pyth_price.expo.abs().try_into().unwrap(),
+            exp: scope_exp_from_pyth_expo(pyth_price.expo)?,
         },
         last_updated_slot: price_account.valid_slot,
-        unix_timestamp: u64::try_from(price_account.timestamp).unwrap(),
+        unix_timestamp: u64::try_from(price_account.timestamp).map_err(|_| {
+            msg!(
+                "Pyth price timestamp is negative: {}",
+                price_account.timestamp
+            );
+            error!(ScopeError::PriceNotValid)
+        })?,
+        flags,
+        conf: pyth_price.conf,
         ..Default::default()
-    })
+    };
+    Ok(dated_price)
+}
+
+/// Current status of the aggregate price of a pyth price account
+pub fn get_source_status(price_info: &AccountInfo) -> Result<SourceStatus> {
+    let data = price_info.try_borrow_data()?;
+    let price_account = pyth_client::load_price_account(data.as_ref())
+        .map_err(|_| error!(ScopeError::PriceNotValid))?;
+    Ok(price_account.agg.status.into())
+}
+
+/// Product account referenced by a pyth price account, identifying the priced asset
+pub fn get_product_account(price_info: &AccountInfo) -> Result<Pubkey> {
+    let data = price_info.try_borrow_data()?;
+    let price_account = pyth_client::load_price_account(data.as_ref())
+        .map_err(|_| error!(ScopeError::PriceNotValid))?;
+    Ok(price_account.prod)
 }
 
+/// Validate a pyth price, returning it with the flags of the price to store
 pub fn validate_valid_price(
     pyth_price: &pyth_client::Price,
-    oracle_confidence_factor: u64,
-) -> Result<u64> {
+    confidence_check: &ConfidenceCheck,
+) -> Result<(u64, u16)> {
     if cfg!(feature = "skip_price_validation") {
-        return Ok(u64::try_from(pyth_price.price).unwrap());
+        return Ok((u64::try_from(pyth_price.price).unwrap(), 0));
     }
 
-    let price = u64::try_from(pyth_price.price).unwrap();
+    let price = u64::try_from(pyth_price.price).map_err(|_| {
+        msg!("Pyth price is negative: {}", pyth_price.price);
+        error!(ScopeError::PriceNotValid)
+    })?;
+    let flags = confidence_check.check(price, pyth_price.conf)?;
+    Ok((price, flags))
+}
+
+/// Check that the confidence interval is at most `max_confidence_bps` of the price
+///
+/// A wider interval is rejected with `PriceConfidenceTooWide`, so that monitoring can tell it
+/// apart from an invalid price. A zero price is always rejected as no ratio can be computed.
+pub fn validate_confidence(price: u64, conf: u64, max_confidence_bps: u64) -> Result<()> {
     if price == 0 {
+        msg!("Pyth price is zero");
         return err!(ScopeError::PriceNotValid);
     }
-    let conf: u64 = pyth_price.conf;
-    let conf_50x: u64 = conf.checked_mul(oracle_confidence_factor).unwrap();
-    if conf_50x > price {
+    // conf / price > max_bps / 10_000, computed in u128 to avoid overflows
+    if u128::from(conf) * FULL_BPS > u128::from(price) * u128::from(max_confidence_bps) {
+        msg!(
+            "Pyth confidence interval too wide: conf {}, price {}, max {} bps",
+            conf,
+            price,
+            max_confidence_bps
+        );
+        return err!(ScopeError::PriceConfidenceTooWide);
+    }
+    Ok(())
+}
+
+/// Convert a pyth exponent to the scope `exp` convention (number of decimals)
+///
+/// Pyth exponents are expected to be negative or zero, a positive exponent cannot be
+/// represented in scope and is rejected. So is an exponent of more than
+/// [`MAX_PYTH_DECIMALS`] decimals, `10^exp` would not fit in the `u64` math of the prices.
+pub fn scope_exp_from_pyth_expo(expo: i32) -> Result<u64> {
+    if expo > 0 {
+        msg!("Pyth price has an unsupported positive exponent {}", expo);
         return err!(ScopeError::PriceNotValid);
-    };
-    Ok(price)
+    }
+    let exp = u64::from(expo.unsigned_abs());
+    if exp > MAX_PYTH_DECIMALS {
+        msg!("Pyth price has an unsupported exponent {}", expo);
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(exp)
+}
+
+/// Check that the aggregated price of the pyth account is currently trading
+pub fn validate_trading_status(price_account: &pyth_client::PriceAccount) -> Result<()> {
+    if cfg!(feature = "skip_price_validation") {
+        return Ok(());
+    }
+    if !matches!(price_account.agg.status, pyth_client::PriceStatus::Trading) {
+        msg!("Pyth price account is not trading");
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(())
 }
 
 fn validate_pyth_price(pyth_price: &pyth_client::PriceAccount) -> Result<()> {
@@ -102,7 +276,8 @@ pub fn validate_pyth_price_info(pyth_price_info: &AccountInfo) -> Result<()> {
         return Ok(());
     }
     let pyth_price_data = pyth_price_info.try_borrow_data()?;
-    let pyth_price = pyth_client::load_price_account(&pyth_price_data).unwrap();
+    let pyth_price = pyth_client::load_price_account(&pyth_price_data)
+        .map_err(|_| error!(ScopeError::PriceNotValid))?;
 
     validate_pyth_price(pyth_price)
 }
@@ -128,6 +303,99 @@ mod tests {
         };
     }
 
+    #[test]
+    pub fn test_scope_exp_from_pyth_expo() {
+        assert_eq!(scope_exp_from_pyth_expo(-8).unwrap(), 8);
+        assert_eq!(scope_exp_from_pyth_expo(0).unwrap(), 0);
+        assert_eq!(scope_exp_from_pyth_expo(-19).unwrap(), 19);
+        assert_err(scope_exp_from_pyth_expo(-20), ScopeError::PriceNotValid);
+        assert_err(
+            scope_exp_from_pyth_expo(i32::MIN),
+            ScopeError::PriceNotValid,
+        );
+        assert_err(scope_exp_from_pyth_expo(2), ScopeError::PriceNotValid);
+    }
+
+    #[test]
+    pub fn test_validate_confidence_tight() {
+        // 1% confidence on a 100.00 price
+        assert!(validate_confidence(10_000, 100, ORACLE_CONFIDENCE_MAX_BPS).is_ok());
+        // Exactly at the threshold
+        assert!(validate_confidence(10_000, 200, ORACLE_CONFIDENCE_MAX_BPS).is_ok());
+        assert!(validate_confidence(10_000, 0, ORACLE_CONFIDENCE_MAX_BPS).is_ok());
+    }
+
+    #[test]
+    pub fn test_validate_confidence_wide() {
+        assert_err(
+            validate_confidence(10_000, 201, ORACLE_CONFIDENCE_MAX_BPS),
+            ScopeError::PriceConfidenceTooWide,
+        );
+        assert_err(
+            validate_confidence(10_000, 10_000, ORACLE_CONFIDENCE_MAX_BPS),
+            ScopeError::PriceConfidenceTooWide,
+        );
+        // Large values don't overflow
+        assert!(validate_confidence(u64::MAX, u64::MAX / 100, ORACLE_CONFIDENCE_MAX_BPS).is_ok());
+        assert_err(
+            validate_confidence(u64::MAX / 100, u64::MAX, ORACLE_CONFIDENCE_MAX_BPS),
+            ScopeError::PriceConfidenceTooWide,
+        );
+    }
+
+    #[test]
+    pub fn test_validate_confidence_zero_price() {
+        assert_err(
+            validate_confidence(0, 0, ORACLE_CONFIDENCE_MAX_BPS),
+            ScopeError::PriceNotValid,
+        );
+        assert_err(
+            validate_confidence(0, 1, ORACLE_CONFIDENCE_MAX_BPS),
+            ScopeError::PriceNotValid,
+        );
+    }
+
+    #[test]
+    pub fn test_confidence_check_policies() {
+        let reject = ConfidenceCheck {
+            max_confidence_bps: 500,
+            policy: ConfidencePolicy::Reject,
+        };
+        let flag_only = ConfidenceCheck {
+            policy: ConfidencePolicy::FlagOnly,
+            ..reject
+        };
+        // 3% confidence is within the configured 5%
+        assert_eq!(reject.check(10_000, 300).unwrap(), 0);
+        assert_eq!(flag_only.check(10_000, 300).unwrap(), 0);
+        // 6% confidence is too wide
+        assert_err(
+            reject.check(10_000, 600),
+            ScopeError::PriceConfidenceTooWide,
+        );
+        assert_eq!(
+            flag_only.check(10_000, 600).unwrap(),
+            DatedPrice::FLAG_LOW_CONFIDENCE
+        );
+        // A zero price is never accepted
+        assert_err(flag_only.check(0, 0), ScopeError::PriceNotValid);
+    }
+
+    #[test]
+    pub fn test_validate_trading_status() {
+        let buff = valid_price_bytes();
+        let price = pyth_client::load_price_account(&buff).unwrap();
+        assert!(super::validate_trading_status(price).is_ok());
+
+        let mut buff = valid_price_bytes();
+        write_bytes(&mut buff, &0_u32.to_le_bytes(), PRICE_STATUS_OFFSET);
+        let price = pyth_client::load_price_account(&buff).unwrap();
+        assert_err(
+            super::validate_trading_status(price),
+            ScopeError::PriceNotValid,
+        );
+    }
+
     #[test]
     pub fn test_validate_price() {
         let buff = valid_price_bytes();
@@ -170,6 +438,22 @@ mod tests {
         assert_err(super::validate_pyth_price(price), ScopeError::PriceNotValid);
     }
 
+    #[test]
+    pub fn test_source_status() {
+        for (status, expected) in [
+            (0_u32, SourceStatus::Unknown),
+            (1, SourceStatus::Trading),
+            (2, SourceStatus::Halted),
+            (3, SourceStatus::Auction),
+        ] {
+            let mut buff = valid_price_bytes();
+            write_bytes(&mut buff, &status.to_le_bytes(), PRICE_STATUS_OFFSET);
+            let price = pyth_client::load_price_account(&buff).unwrap();
+            assert_eq!(SourceStatus::from(price.agg.status), expected);
+            assert_eq!(u16::from(expected), u16::try_from(status).unwrap());
+        }
+    }
+
     fn valid_price_bytes() -> [u8; PRICE_ACCT_SIZE] {
         let mut buff = [0_u8; PRICE_ACCT_SIZE];
         write_bytes(
@@ -0,0 +1,102 @@
+//! Conservative bounds of a pyth price
+//!
+//! The aggregate price is read and validated as for [`crate::oracles::OracleType::Pyth`], the
+//! stored price is then adjusted by the confidence interval:
+//!
+//! - [`ConfidenceBound::Lower`] stores `price - conf`, e.g. to value collateral in liquidations
+//! - [`ConfidenceBound::Upper`] stores `price + conf`, e.g. to value borrows
+//!
+//! The bound is selected per entry in the oracle mapping, the same pyth account can therefore back
+//! a spot entry and conservative entries at different indexes.
+
+use anchor_lang::prelude::*;
+
+use crate::{oracles::pyth::ConfidenceCheck, DatedPrice, Result, ScopeError};
+
+/// Side of the confidence interval used as price
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfidenceBound {
+    /// `price - conf`
+    Lower,
+    /// `price + conf`
+    Upper,
+}
+
+pub fn get_price(
+    price_info: &AccountInfo,
+    bound: ConfidenceBound,
+    confidence_check: &ConfidenceCheck,
+) -> Result<DatedPrice> {
+    let mut dated_price = crate::oracles::pyth::get_price(price_info, confidence_check)?;
+    dated_price.price.value = apply_confidence(dated_price.price.value, dated_price.conf, bound)
+        .map_err(|e| {
+            msg!(
+                "Invalid {:?} confidence bound on pyth account {}",
+                bound,
+                price_info.key
+            );
+            e
+        })?;
+    Ok(dated_price)
+}
+
+/// Move the price to the given bound of its confidence interval
+///
+/// A lower bound that is not strictly positive (`conf >= price`) is not a valid price.
+fn apply_confidence(price: u64, conf: u64, bound: ConfidenceBound) -> Result<u64> {
+    match bound {
+        ConfidenceBound::Lower => match price.checked_sub(conf) {
+            Some(lower) if lower > 0 => Ok(lower),
+            _ => {
+                msg!(
+                    "Pyth confidence interval {} is not below the price {}",
+                    conf,
+                    price
+                );
+                err!(ScopeError::PriceNotValid)
+            }
+        },
+        ConfidenceBound::Upper => price
+            .checked_add(conf)
+            .ok_or_else(|| error!(ScopeError::MathOverflow)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_bound() {
+        assert_eq!(
+            apply_confidence(10_000, 150, ConfidenceBound::Lower).unwrap(),
+            9_850
+        );
+        assert_eq!(
+            apply_confidence(10_000, 0, ConfidenceBound::Lower).unwrap(),
+            10_000
+        );
+    }
+
+    #[test]
+    fn lower_bound_underflow() {
+        for conf in [10_000, 10_001, u64::MAX] {
+            assert_eq!(
+                apply_confidence(10_000, conf, ConfidenceBound::Lower).unwrap_err(),
+                ScopeError::PriceNotValid.into()
+            );
+        }
+    }
+
+    #[test]
+    fn upper_bound() {
+        assert_eq!(
+            apply_confidence(10_000, 150, ConfidenceBound::Upper).unwrap(),
+            10_150
+        );
+        assert_eq!(
+            apply_confidence(u64::MAX, 1, ConfidenceBound::Upper).unwrap_err(),
+            ScopeError::MathOverflow.into()
+        );
+    }
+}
@@ -7,25 +7,36 @@
 //!    expected to be checked by the admin to ensure the product has the expected quality prior the mapping
 //!    update.
 //! 2. Upon usage the current ema price state is checked in [`validate_valid_price`]
-//! 3. The confidence interval is also checked in this same function with [`ORACLE_CONFIDENCE_FACTOR`]
+//! 3. The confidence interval is also checked in this same function with the
+//!    [`ConfidenceCheck`] of the entry
+//! 4. The EMA is only used while the aggregate price status is `Trading`
+//!
+//! The EMA type is selected per entry in the oracle mapping, the same pyth account can therefore back
+//! both a spot entry ([`crate::oracles::OracleType::Pyth`]) and an EMA entry at two different indexes.
 
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 
 use anchor_lang::prelude::*;
 use pyth_sdk_solana::state as pyth_client;
 
-use crate::{DatedPrice, Price, Result, ScopeError};
-
-/// validate price confidence - confidence/price ratio should be less than 2%
-const ORACLE_CONFIDENCE_FACTOR: u64 = 50; // 100% / 2%
+use crate::{oracles::pyth::ConfidenceCheck, DatedPrice, Price, Result, ScopeError};
 
-pub fn get_price(price_info: &AccountInfo) -> Result<DatedPrice> {
+pub fn get_price(
+    price_info: &AccountInfo,
+    confidence_check: &ConfidenceCheck,
+) -> Result<DatedPrice> {
     let data = price_info.try_borrow_data()?;
     let price_account = pyth_client::load_price_account(data.as_ref()).map_err(|e| {
         msg!("Invalid pyth price account: {}", e);
         error!(ScopeError::PriceNotValid)
     })?;
 
+    // The EMA is only meaningful if the underlying aggregate is currently trading
+    crate::oracles::pyth::validate_trading_status(price_account).map_err(|e| {
+        msg!("EMA price not available on pyth account {}", price_info.key);
+        e
+    })?;
+
     let pyth_raw = price_account.to_price_feed(price_info.key);
 
     let pyth_ema_price = if cfg!(feature = "skip_price_validation") {
@@ -39,20 +50,29 @@ pub fn get_price(price_info: &AccountInfo) -> Result<DatedPrice> {
         return err!(ScopeError::PriceNotValid);
     };
 
-    let ema_price =
-        crate::oracles::pyth::validate_valid_price(&pyth_ema_price, ORACLE_CONFIDENCE_FACTOR)
-            .map_err(|e| {
+    let (ema_price, flags) =
+        crate::oracles::pyth::validate_valid_price(&pyth_ema_price, confidence_check).map_err(
+            |e| {
                 msg!("Invalid EMA price on pyth account {}", price_info.key);
                 e
-            })?;
+            },
+        )?;
 
     Ok(DatedPrice {
         price: Price {
             value: ema_price,
-            exp: pyth_ema_price.expo.abs().try_into().unwrap(),
+            exp: crate::oracles::pyth::scope_exp_from_pyth_expo(pyth_ema_price.expo)?,
         },
         last_updated_slot: price_account.valid_slot,
-        unix_timestamp: u64::try_from(price_account.timestamp).unwrap(),
+        unix_timestamp: u64::try_from(price_account.timestamp).map_err(|_| {
+            msg!(
+                "Pyth price timestamp is negative: {}",
+                price_account.timestamp
+            );
+            error!(ScopeError::PriceNotValid)
+        })?,
+        flags,
+        conf: pyth_ema_price.conf,
         ..Default::default()
     })
 }
@@ -0,0 +1,486 @@
+//! Pyth Lazer signed price updates
+//!
+//! The mapped account holds a Lazer update in the Solana format: the ed25519 signature of the
+//! payload, the public key of the signer and the payload itself (see [`SignedUpdate`]). It must
+//! be dedicated to a single feed, the payload is expected to contain exactly one feed update.
+//!
+//! The signature is verified by the ed25519 program: the refresh transaction must contain an
+//! ed25519 instruction verifying the same signature, signer and payload. The instructions sysvar
+//! is the extra account of the price, it is read to find this instruction.
+//!
+//! The signer is the source identity of the account (see [`super::get_source_identity`]): the
+//! trusted signer must be pinned with [`crate::UpdateMappingConfigMode::SourceIdentity`], an
+//! update of a token without trusted signer is rejected. Rotating the signer is an admin update
+//! of the mapping config.
+//!
+//! The account can be owned by any program, the update is authenticated by its signature. The
+//! feed of the update must be the one of the token, set with
+//! [`crate::UpdateMappingConfigMode::PythLazerFeedId`]: an update of another feed, or any update
+//! of a token without feed id, is rejected.
+//!
+//! The payload timestamp is the price timestamp. Payloads older than the max source age of the
+//! token, or [`DEFAULT_MAX_PAYLOAD_AGE_SECONDS`] if not set, are rejected.
+
+use anchor_lang::prelude::*;
+use solana_program::{
+    clock::DEFAULT_MS_PER_SLOT,
+    ed25519_program,
+    sysvar::instructions::{load_instruction_at_checked, ID as SYSVAR_INSTRUCTIONS_ID},
+};
+
+use crate::{DatedPrice, OracleMappings, Price, Result, ScopeError};
+
+/// Magic number of a Lazer update in the Solana format
+pub const SOLANA_FORMAT_MAGIC: u32 = 0x821a_01b9;
+/// Magic number of a Lazer payload
+pub const PAYLOAD_FORMAT_MAGIC: u32 = 0x93c7_d375;
+
+/// Max age of a payload when the token has no max source age
+pub const DEFAULT_MAX_PAYLOAD_AGE_SECONDS: u64 = 10;
+
+const SIGNATURE_OFFSET: usize = 4;
+const SIGNATURE_LEN: usize = 64;
+const SIGNER_OFFSET: usize = SIGNATURE_OFFSET + SIGNATURE_LEN;
+const MESSAGE_SIZE_OFFSET: usize = SIGNER_OFFSET + 32;
+const MESSAGE_OFFSET: usize = MESSAGE_SIZE_OFFSET + 2;
+
+/// Property ids of a feed update
+mod property {
+    pub const PRICE: u8 = 0;
+    pub const BEST_BID_PRICE: u8 = 1;
+    pub const BEST_ASK_PRICE: u8 = 2;
+    pub const PUBLISHER_COUNT: u8 = 3;
+    pub const EXPONENT: u8 = 4;
+}
+
+/// Size of an ed25519 instruction header: number of signatures and padding
+const ED25519_HEADER_LEN: usize = 2;
+/// Size of the offsets of a signature in an ed25519 instruction (7 u16)
+const ED25519_OFFSETS_LEN: usize = 14;
+
+/// Signed update read from the payload account
+pub struct SignedUpdate<'a> {
+    pub signature: &'a [u8],
+    pub signer: Pubkey,
+    pub message: &'a [u8],
+}
+
+impl<'a> SignedUpdate<'a> {
+    pub fn from_account_data(data: &'a [u8]) -> Result<Self> {
+        if data.len() < MESSAGE_OFFSET || read_u32(data, 0) != SOLANA_FORMAT_MAGIC {
+            msg!("Account is not a Pyth Lazer update");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        let message_size = usize::from(read_u16(data, MESSAGE_SIZE_OFFSET));
+        let message = data
+            .get(MESSAGE_OFFSET..MESSAGE_OFFSET + message_size)
+            .ok_or_else(|| {
+                msg!("Pyth Lazer update is truncated");
+                ScopeError::UnableToDeserializeAccount
+            })?;
+        Ok(Self {
+            signature: &data[SIGNATURE_OFFSET..SIGNER_OFFSET],
+            signer: Pubkey::new_from_array(
+                data[SIGNER_OFFSET..MESSAGE_SIZE_OFFSET].try_into().unwrap(),
+            ),
+            message,
+        })
+    }
+}
+
+/// Price of the single feed of a Lazer payload
+#[derive(Debug, PartialEq, Eq)]
+pub struct FeedUpdate {
+    pub timestamp_us: u64,
+    pub feed_id: u32,
+    pub price: i64,
+    pub exponent: i16,
+}
+
+impl FeedUpdate {
+    pub fn from_payload(payload: &[u8]) -> Result<Self> {
+        let mut reader = Reader(payload);
+        if reader.read_u32()? != PAYLOAD_FORMAT_MAGIC {
+            msg!("Invalid Pyth Lazer payload magic");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        let timestamp_us = reader.read_u64()?;
+        let _channel = reader.read_u8()?;
+        let nb_feeds = reader.read_u8()?;
+        if nb_feeds != 1 {
+            msg!("Pyth Lazer payload has {} feeds, expected 1", nb_feeds);
+            return err!(ScopeError::UnexpectedAccount);
+        }
+        let feed_id = reader.read_u32()?;
+        let mut price = None;
+        let mut exponent = None;
+        for _ in 0..reader.read_u8()? {
+            match reader.read_u8()? {
+                property::PRICE => price = Some(reader.read_u64()? as i64),
+                property::BEST_BID_PRICE | property::BEST_ASK_PRICE => {
+                    reader.read_u64()?;
+                }
+                property::PUBLISHER_COUNT => {
+                    reader.read_u16()?;
+                }
+                property::EXPONENT => exponent = Some(reader.read_u16()? as i16),
+                id => {
+                    msg!("Unknown Pyth Lazer property {}", id);
+                    return err!(ScopeError::UnableToDeserializeAccount);
+                }
+            }
+        }
+        match (price, exponent) {
+            (Some(price), Some(exponent)) => Ok(Self {
+                timestamp_us,
+                feed_id,
+                price,
+                exponent,
+            }),
+            _ => {
+                msg!("Pyth Lazer feed {} has no price or exponent", feed_id);
+                err!(ScopeError::PriceNotValid)
+            }
+        }
+    }
+}
+
+pub fn get_price<'a, 'b>(
+    payload_account: &AccountInfo,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+    clock: &Clock,
+    oracle_mappings: &OracleMappings,
+    token: usize,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let instructions_sysvar = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    if instructions_sysvar.key() != SYSVAR_INSTRUCTIONS_ID {
+        msg!(
+            "Expected the instructions sysvar, got {}",
+            instructions_sysvar.key()
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    let data = payload_account.try_borrow_data()?;
+    let update = SignedUpdate::from_account_data(&data)?;
+    let trusted_signer = oracle_mappings.source_identities[token];
+    if trusted_signer == Pubkey::default() || update.signer != trusted_signer {
+        msg!(
+            "Pyth Lazer update signer {} is not the trusted signer {}",
+            update.signer,
+            trusted_signer
+        );
+        return err!(ScopeError::InvalidPayloadSignature);
+    }
+    check_ed25519_verification(instructions_sysvar, &update)?;
+
+    let feed = FeedUpdate::from_payload(update.message)?;
+    let expected_feed_id = oracle_mappings.pyth_lazer_feed_ids[token];
+    if feed.feed_id != expected_feed_id {
+        msg!(
+            "Pyth Lazer update is for feed {}, expected feed {}",
+            feed.feed_id,
+            expected_feed_id
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+    let dated_price = to_dated_price(&feed, clock)?;
+    let max_age = match oracle_mappings.max_source_ages[token] {
+        0 => DEFAULT_MAX_PAYLOAD_AGE_SECONDS,
+        max_age => max_age,
+    };
+    let age = u64::try_from(clock.unix_timestamp)
+        .map_err(|_| error!(ScopeError::PriceNotValid))?
+        .saturating_sub(dated_price.unix_timestamp);
+    if age > max_age {
+        msg!(
+            "Pyth Lazer payload is too old: {} seconds, max age {} seconds",
+            age,
+            max_age
+        );
+        return err!(ScopeError::StalePrice);
+    }
+    Ok(dated_price)
+}
+
+/// Check that the mapped account holds a Lazer update
+pub fn validate_update_account(update_account: &AccountInfo) -> Result<()> {
+    let data = update_account.try_borrow_data()?;
+    SignedUpdate::from_account_data(&data).map(|_| ())
+}
+
+fn to_dated_price(feed: &FeedUpdate, clock: &Clock) -> Result<DatedPrice> {
+    if feed.price <= 0 || feed.exponent > 0 {
+        msg!(
+            "Invalid Pyth Lazer price {} with exponent {}",
+            feed.price,
+            feed.exponent
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+    let unix_timestamp = feed.timestamp_us / 1_000_000;
+    // Estimate the slot of the payload from its age
+    let age_ms = u64::try_from(clock.unix_timestamp)
+        .map_err(|_| error!(ScopeError::PriceNotValid))?
+        .saturating_sub(unix_timestamp)
+        .saturating_mul(1000);
+    Ok(DatedPrice {
+        price: Price {
+            value: feed.price as u64,
+            exp: u64::from(feed.exponent.unsigned_abs()),
+        },
+        last_updated_slot: clock.slot.saturating_sub(age_ms / DEFAULT_MS_PER_SLOT),
+        unix_timestamp,
+        ..Default::default()
+    })
+}
+
+/// Check that an ed25519 instruction of the transaction verifies the signature of the update
+fn check_ed25519_verification(
+    instructions_sysvar: &AccountInfo,
+    update: &SignedUpdate,
+) -> Result<()> {
+    let mut index = 0;
+    while let Ok(ix) = load_instruction_at_checked(index, instructions_sysvar) {
+        if ix.program_id == ed25519_program::ID && verifies_update(&ix.data, update) {
+            return Ok(());
+        }
+        index += 1;
+    }
+    msg!("No ed25519 instruction verifies the Pyth Lazer update");
+    err!(ScopeError::InvalidPayloadSignature)
+}
+
+/// Tell if the data of an ed25519 instruction verifies the signature of the update
+///
+/// Only signatures whose data is in the ed25519 instruction itself are considered.
+fn verifies_update(ed25519_data: &[u8], update: &SignedUpdate) -> bool {
+    let nb_signatures = match ed25519_data.first() {
+        Some(&nb_signatures) => usize::from(nb_signatures),
+        None => return false,
+    };
+    (0..nb_signatures).any(|i| {
+        let offsets_start = ED25519_HEADER_LEN + i * ED25519_OFFSETS_LEN;
+        let offsets = match ed25519_data.get(offsets_start..offsets_start + ED25519_OFFSETS_LEN) {
+            Some(offsets) => offsets,
+            None => return false,
+        };
+        let field = |n: usize| usize::from(read_u16(offsets, 2 * n));
+        let (signature_offset, signer_offset, message_offset, message_size) =
+            (field(0), field(2), field(4), field(5));
+        // Instruction indexes of the signature, signer and message
+        let this_ix = usize::from(u16::MAX);
+        if [field(1), field(3), field(6)] != [this_ix; 3] {
+            return false;
+        }
+        ed25519_data.get(signature_offset..signature_offset + SIGNATURE_LEN)
+            == Some(update.signature)
+            && ed25519_data.get(signer_offset..signer_offset + 32) == Some(update.signer.as_ref())
+            && ed25519_data.get(message_offset..message_offset + message_size)
+                == Some(update.message)
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Little endian reader of a payload
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn read<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.0.len() < N {
+            msg!("Pyth Lazer payload is truncated");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        self.read::<1>().map(|bytes| bytes[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        self.read().map(u16::from_le_bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read().map(u32::from_le_bytes)
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        self.read().map(u64::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SIGNATURE: [u8; 64] = [7; 64];
+    const TIMESTAMP_US: u64 = 1_720_000_000_250_000;
+
+    fn signer() -> Pubkey {
+        Pubkey::new_from_array([3; 32])
+    }
+
+    fn payload(timestamp_us: u64, feed_id: u32, price: i64, exponent: i16) -> Vec<u8> {
+        let mut payload = PAYLOAD_FORMAT_MAGIC.to_le_bytes().to_vec();
+        payload.extend_from_slice(&timestamp_us.to_le_bytes());
+        // Channel and number of feeds
+        payload.extend_from_slice(&[1, 1]);
+        payload.extend_from_slice(&feed_id.to_le_bytes());
+        payload.extend_from_slice(&[2, property::PRICE]);
+        payload.extend_from_slice(&price.to_le_bytes());
+        payload.push(property::EXPONENT);
+        payload.extend_from_slice(&exponent.to_le_bytes());
+        payload
+    }
+
+    fn signed_update(signature: &[u8; 64], signer: &Pubkey, payload: &[u8]) -> Vec<u8> {
+        let mut data = SOLANA_FORMAT_MAGIC.to_le_bytes().to_vec();
+        data.extend_from_slice(signature);
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&u16::try_from(payload.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// Data of an ed25519 instruction verifying a single signature
+    fn ed25519_instruction_data(signature: &[u8; 64], signer: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let signer_offset = ED25519_HEADER_LEN + ED25519_OFFSETS_LEN;
+        let signature_offset = signer_offset + 32;
+        let message_offset = signature_offset + SIGNATURE_LEN;
+        let this_ix = usize::from(u16::MAX);
+        let offsets = [
+            signature_offset,
+            this_ix,
+            signer_offset,
+            this_ix,
+            message_offset,
+            message.len(),
+            this_ix,
+        ];
+        let mut data = vec![1, 0];
+        for offset in offsets {
+            data.extend_from_slice(&u16::try_from(offset).unwrap().to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(signature);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn parse_signed_update() {
+        let payload = payload(TIMESTAMP_US, 6, 15_512_345_678, -8);
+        let data = signed_update(&SIGNATURE, &signer(), &payload);
+
+        let update = SignedUpdate::from_account_data(&data).unwrap();
+        assert_eq!(update.signature, &SIGNATURE[..]);
+        assert_eq!(update.signer, signer());
+        assert_eq!(update.message, &payload[..]);
+        assert_eq!(
+            FeedUpdate::from_payload(update.message).unwrap(),
+            FeedUpdate {
+                timestamp_us: TIMESTAMP_US,
+                feed_id: 6,
+                price: 15_512_345_678,
+                exponent: -8,
+            }
+        );
+
+        // Truncated account
+        assert_eq!(
+            SignedUpdate::from_account_data(&data[..data.len() - 1]).err(),
+            Some(ScopeError::UnableToDeserializeAccount.into())
+        );
+    }
+
+    #[test]
+    fn payload_with_several_feeds_is_rejected() {
+        let mut payload = payload(TIMESTAMP_US, 6, 15_512_345_678, -8);
+        payload[13] = 2;
+        assert_eq!(
+            FeedUpdate::from_payload(&payload).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    fn dated_price_from_feed() {
+        let clock = Clock {
+            slot: 1_000,
+            unix_timestamp: 1_720_000_002,
+            ..Default::default()
+        };
+        let feed = FeedUpdate::from_payload(&payload(TIMESTAMP_US, 6, 15_512_345_678, -8)).unwrap();
+        let dated_price = to_dated_price(&feed, &clock).unwrap();
+        assert_eq!(
+            dated_price.price,
+            Price {
+                value: 15_512_345_678,
+                exp: 8
+            }
+        );
+        assert_eq!(dated_price.unix_timestamp, 1_720_000_000);
+        // 2 seconds ago
+        assert_eq!(dated_price.last_updated_slot, 995);
+
+        let feed = FeedUpdate::from_payload(&payload(TIMESTAMP_US, 6, 0, -8)).unwrap();
+        assert_eq!(
+            to_dated_price(&feed, &clock).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+
+        let feed = FeedUpdate::from_payload(&payload(TIMESTAMP_US, 6, 15_512_345_678, -8)).unwrap();
+        let clock = Clock {
+            unix_timestamp: -1,
+            ..clock
+        };
+        assert_eq!(
+            to_dated_price(&feed, &clock).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn ed25519_instruction_must_match_the_update() {
+        let payload = payload(TIMESTAMP_US, 6, 15_512_345_678, -8);
+        let data = signed_update(&SIGNATURE, &signer(), &payload);
+        let update = SignedUpdate::from_account_data(&data).unwrap();
+
+        let ix_data = ed25519_instruction_data(&SIGNATURE, &signer(), &payload);
+        assert!(verifies_update(&ix_data, &update));
+
+        let other_payload = payload(TIMESTAMP_US, 6, 15_512_345_679, -8);
+        let ix_data = ed25519_instruction_data(&SIGNATURE, &signer(), &other_payload);
+        assert!(!verifies_update(&ix_data, &update));
+
+        let ix_data = ed25519_instruction_data(&[8; 64], &signer(), &payload);
+        assert!(!verifies_update(&ix_data, &update));
+
+        let ix_data = ed25519_instruction_data(&SIGNATURE, &Pubkey::new_unique(), &payload);
+        assert!(!verifies_update(&ix_data, &update));
+
+        // Signature data in another instruction
+        let mut ix_data = ed25519_instruction_data(&SIGNATURE, &signer(), &payload);
+        ix_data[4..6].copy_from_slice(&0_u16.to_le_bytes());
+        assert!(!verifies_update(&ix_data, &update));
+
+        assert!(!verifies_update(&[], &update));
+        assert!(!verifies_update(&[1, 0, 0], &update));
+    }
+}
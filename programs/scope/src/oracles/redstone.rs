@@ -0,0 +1,199 @@
+//! RedStone push feeds written by the RedStone price adapter program
+//!
+//! A feed is a `PriceData` anchor account updated with the values signed by the RedStone
+//! nodes. The value is a big-endian `u256` with `decimals` decimals.
+//!
+//! The timestamp of the signed data package (in milliseconds) is used as the price timestamp,
+//! staleness is enforced on refresh against it with the max source age of the token, the slot
+//! of the refresh transaction is not relevant.
+
+use anchor_lang::prelude::*;
+
+use crate::{utils::math::decimal_to_price, DatedPrice, Result, ScopeError};
+
+/// RedStone price adapter program id
+pub const REDSTONE_PRICE_ADAPTER_PROGRAM_ID: Pubkey =
+    pubkey!("REDSTBDUecGjwXd6YGPzHSvEUBHQqVRfCcjUVgPiHsr");
+
+/// Anchor discriminator of the `PriceData` account
+pub const PRICE_DATA_DISCRIMINATOR: [u8; 8] = [232, 113, 193, 231, 133, 209, 206, 154];
+
+/// Content of the `PriceData` account (borsh serialized)
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct PriceData {
+    /// Identifier of the feed, e.g. `ETH` padded with zeros
+    pub feed_id: [u8; 32],
+    /// Big-endian `u256` value
+    pub value: [u8; 32],
+    /// Timestamp of the signed data package in milliseconds
+    pub timestamp: u64,
+    /// Timestamp of the write transaction in milliseconds
+    pub write_timestamp: Option<u64>,
+    pub write_slot: u64,
+    pub decimals: u8,
+    /// Number of distinct signers of the data package
+    pub signer_count: u8,
+    /// Min number of signers required by the adapter configuration
+    pub signer_count_threshold: u8,
+    pub _reserved: [u8; 5],
+}
+
+pub fn get_price(price_data_info: &AccountInfo) -> Result<DatedPrice> {
+    let data = price_data_info.try_borrow_data()?;
+    let price_data = parse_price_data(&data)?;
+    validate_signers(&price_data)?;
+
+    // Values that do not fit in an i128 cannot fit in a price either
+    let (high, low) = price_data.value.split_at(16);
+    let value = i128::from_be_bytes(low.try_into().unwrap());
+    if high.iter().any(|&b| b != 0) || value < 0 {
+        msg!("RedStone value is too large");
+        return err!(ScopeError::IntegerOverflow);
+    }
+    let price = decimal_to_price(value, price_data.decimals.into())?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: price_data.write_slot,
+        unix_timestamp: price_data.timestamp / 1000,
+        ..Default::default()
+    })
+}
+
+pub fn validate_price_data_account(price_data_info: &AccountInfo) -> Result<()> {
+    if price_data_info.owner != &REDSTONE_PRICE_ADAPTER_PROGRAM_ID {
+        msg!(
+            "RedStone feed {} is not owned by the price adapter program",
+            price_data_info.key()
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let data = price_data_info.try_borrow_data()?;
+    validate_signers(&parse_price_data(&data)?)
+}
+
+fn parse_price_data(data: &[u8]) -> Result<PriceData> {
+    if data.len() < 8 || data[..8] != PRICE_DATA_DISCRIMINATOR {
+        msg!("RedStone price data account has an invalid discriminator");
+        return err!(ScopeError::InvalidAccountDiscriminator);
+    }
+    let mut price_data = &data[8..];
+    PriceData::deserialize(&mut price_data)
+        .map_err(|_| error!(ScopeError::UnableToDeserializeAccount))
+}
+
+/// The data package must have been signed by at least the configured threshold of signers
+fn validate_signers(price_data: &PriceData) -> Result<()> {
+    if price_data.signer_count_threshold == 0
+        || price_data.signer_count < price_data.signer_count_threshold
+    {
+        msg!(
+            "RedStone value signed by {} signers, threshold {}",
+            price_data.signer_count,
+            price_data.signer_count_threshold
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value_bytes(value: u128) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[16..].copy_from_slice(&value.to_be_bytes());
+        bytes
+    }
+
+    fn price_data_bytes(price_data: &PriceData) -> Vec<u8> {
+        let mut data = PRICE_DATA_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&price_data.try_to_vec().unwrap());
+        data
+    }
+
+    fn get_price_from_data(price_data: &PriceData) -> Result<DatedPrice> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = price_data_bytes(price_data);
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &REDSTONE_PRICE_ADAPTER_PROGRAM_ID,
+            false,
+            0,
+        );
+        get_price(&info)
+    }
+
+    fn eth_price_data() -> PriceData {
+        PriceData {
+            value: value_bytes(3_456_789_012_345),
+            timestamp: 1_700_000_000_123,
+            write_timestamp: Some(1_700_000_001_000),
+            write_slot: 42,
+            decimals: 8,
+            signer_count: 3,
+            signer_count_threshold: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_price_from_feed() {
+        let price = get_price_from_data(&eth_price_data()).unwrap();
+        assert_eq!(
+            price.price,
+            crate::Price {
+                value: 3_456_789_012_345,
+                exp: 8
+            }
+        );
+        // Feed timestamp, not the write one
+        assert_eq!(price.unix_timestamp, 1_700_000_000);
+        assert_eq!(price.last_updated_slot, 42);
+    }
+
+    #[test]
+    fn test_not_enough_signers() {
+        for (signer_count, signer_count_threshold) in [(2, 3), (0, 0)] {
+            assert_eq!(
+                get_price_from_data(&PriceData {
+                    signer_count,
+                    signer_count_threshold,
+                    ..eth_price_data()
+                })
+                .unwrap_err(),
+                ScopeError::PriceNotValid.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_too_large() {
+        let mut value = value_bytes(1);
+        value[15] = 1;
+        assert_eq!(
+            get_price_from_data(&PriceData {
+                value,
+                ..eth_price_data()
+            })
+            .unwrap_err(),
+            ScopeError::IntegerOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_invalid_discriminator() {
+        let mut data = price_data_bytes(&eth_price_data());
+        data[0] ^= 1;
+        assert_eq!(
+            parse_price_data(&data).unwrap_err(),
+            ScopeError::InvalidAccountDiscriminator.into()
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Virtual price of the LP token of a Saber stable swap pool
+//!
+//! The virtual price is the value of one LP token in underlying tokens: `D / lp_supply`, with
+//! `D` the StableSwap invariant of the pool. `D` is the total amount of tokens the pool would
+//! hold if it was balanced, it is computed from the vault balances and the amplification
+//! coefficient with Newton's method, as done by the pool program.
+//!
+//! The tokens of a stable swap pool are expected to be pegged to each other, the price is
+//! expressed in one of them (e.g. in USD for a USDC/USDT pool). Both tokens must have the same
+//! decimals.
+//!
+//! The pool vaults, LP mint and token mints are provided as extra accounts. The price is read from
+//! the pool state only, it is dated with the current clock.
+
+use anchor_lang::prelude::*;
+use uint_internal::U192;
+
+use crate::{
+    oracles::constant_product_lp::{read_mint, read_token_amount},
+    DatedPrice, Price, Result, ScopeError,
+};
+
+// U192 with 192 bits consisting of 3 x 64-bit words
+#[allow(clippy::all)]
+mod uint_internal {
+    use uint::construct_uint;
+    construct_uint! {
+        pub struct U192(3);
+    }
+}
+
+/// Saber stable swap program id
+pub const SABER_STABLE_SWAP_PROGRAM_ID: Pubkey =
+    pubkey!("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ");
+
+/// Exponent of the computed virtual prices
+pub const VIRTUAL_PRICE_EXP: u32 = 12;
+
+/// Max number of Newton iterations to compute the invariant
+const MAX_D_ITERATIONS: usize = 32;
+const N_COINS: u64 = 2;
+
+// Offsets of the used fields in the `SwapInfo` account
+const IS_INITIALIZED_OFFSET: usize = 0;
+const INITIAL_AMP_FACTOR_OFFSET: usize = 3;
+const TARGET_AMP_FACTOR_OFFSET: usize = 11;
+const START_RAMP_TS_OFFSET: usize = 19;
+const STOP_RAMP_TS_OFFSET: usize = 27;
+const TOKEN_A_OFFSET: usize = 107;
+const TOKEN_B_OFFSET: usize = 139;
+const POOL_MINT_OFFSET: usize = 171;
+const TOKEN_A_MINT_OFFSET: usize = 203;
+const TOKEN_B_MINT_OFFSET: usize = 235;
+const SWAP_INFO_SIZE: usize = 395;
+
+/// Fields of the `SwapInfo` account needed to compute the virtual price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapInfo {
+    pub initial_amp_factor: u64,
+    pub target_amp_factor: u64,
+    pub start_ramp_ts: i64,
+    pub stop_ramp_ts: i64,
+    /// Vault of the token A
+    pub token_a: Pubkey,
+    /// Vault of the token B
+    pub token_b: Pubkey,
+    /// LP token mint
+    pub pool_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+}
+
+impl SwapInfo {
+    pub fn from_account_data(data: &[u8]) -> Result<SwapInfo> {
+        if data.len() < SWAP_INFO_SIZE || data[IS_INITIALIZED_OFFSET] != 1 {
+            msg!("Account is not an initialized Saber stable swap pool");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        let read_u64 =
+            |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let read_pubkey =
+            |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+        Ok(SwapInfo {
+            initial_amp_factor: read_u64(INITIAL_AMP_FACTOR_OFFSET),
+            target_amp_factor: read_u64(TARGET_AMP_FACTOR_OFFSET),
+            start_ramp_ts: read_u64(START_RAMP_TS_OFFSET) as i64,
+            stop_ramp_ts: read_u64(STOP_RAMP_TS_OFFSET) as i64,
+            token_a: read_pubkey(TOKEN_A_OFFSET),
+            token_b: read_pubkey(TOKEN_B_OFFSET),
+            pool_mint: read_pubkey(POOL_MINT_OFFSET),
+            token_a_mint: read_pubkey(TOKEN_A_MINT_OFFSET),
+            token_b_mint: read_pubkey(TOKEN_B_MINT_OFFSET),
+        })
+    }
+
+    /// Extra accounts expected at refresh, in order
+    pub fn extra_accounts(&self) -> [Pubkey; 5] {
+        [
+            self.token_a,
+            self.token_b,
+            self.pool_mint,
+            self.token_a_mint,
+            self.token_b_mint,
+        ]
+    }
+
+    /// Amplification coefficient at the given time, ramped linearly between the initial and
+    /// target values
+    fn amp_factor(&self, now: i64) -> Result<u64> {
+        if now >= self.stop_ramp_ts || self.stop_ramp_ts <= self.start_ramp_ts {
+            return Ok(self.target_amp_factor);
+        }
+        let time_range = u128::try_from(self.stop_ramp_ts - self.start_ramp_ts)
+            .map_err(|_| ScopeError::MathOverflow)?;
+        let time_delta = u128::try_from(now.saturating_sub(self.start_ramp_ts).max(0))
+            .map_err(|_| ScopeError::MathOverflow)?;
+        let (initial, target) = (
+            u128::from(self.initial_amp_factor),
+            u128::from(self.target_amp_factor),
+        );
+        let amp = if target >= initial {
+            initial + (target - initial) * time_delta / time_range
+        } else {
+            initial - (initial - target) * time_delta / time_range
+        };
+        u64::try_from(amp).map_err(|_| error!(ScopeError::MathOverflow))
+    }
+}
+
+/// Get the virtual price of the LP token, see the module documentation
+///
+/// The extra accounts are consumed in the order of [`SwapInfo::extra_accounts`]: vault A,
+/// vault B, LP mint, mint A and mint B.
+pub fn get_price<'a, 'b>(
+    swap_info: &AccountInfo,
+    clock: &Clock,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let swap = SwapInfo::from_account_data(&swap_info.try_borrow_data()?)?;
+
+    let mut accounts = [None; 5];
+    for account in accounts.iter_mut() {
+        *account = Some(
+            extra_accounts
+                .next()
+                .ok_or(ScopeError::AccountsAndTokenMismatch)?,
+        );
+    }
+    let accounts = accounts.map(Option::unwrap);
+    for (account_info, expected) in accounts.iter().zip(swap.extra_accounts()) {
+        if account_info.key() != expected {
+            msg!(
+                "Stable swap account received {} is not the expected {}",
+                account_info.key(),
+                expected
+            );
+            return err!(ScopeError::UnexpectedAccount);
+        }
+    }
+    let [vault_a, vault_b, pool_mint, mint_a, mint_b] = accounts;
+
+    let decimals_a = read_mint(mint_a)?.1;
+    let decimals_b = read_mint(mint_b)?.1;
+    if decimals_a != decimals_b {
+        msg!(
+            "Stable swap tokens have different decimals ({}, {})",
+            decimals_a,
+            decimals_b
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+    let (lp_supply, lp_decimals) = read_mint(pool_mint)?;
+
+    let amp_factor = swap.amp_factor(clock.unix_timestamp)?;
+    let d = compute_d(
+        amp_factor,
+        read_token_amount(vault_a)?,
+        read_token_amount(vault_b)?,
+    )?;
+    let price = virtual_price(d, decimals_a, lp_supply, lp_decimals)?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: clock.slot,
+        unix_timestamp: u64::try_from(clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    })
+}
+
+/// Check that the given account is a Saber stable swap pool
+pub fn validate_swap_account(swap_info: &AccountInfo) -> Result<()> {
+    if swap_info.owner != &SABER_STABLE_SWAP_PROGRAM_ID {
+        msg!(
+            "Pool {} is not owned by the Saber stable swap program",
+            swap_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    SwapInfo::from_account_data(&swap_info.try_borrow_data()?).map(|_| ())
+}
+
+/// StableSwap invariant `D` of a 2 tokens pool, in lamports
+///
+/// Same computation as the Saber program: Newton's method starting from the sum of the
+/// reserves, stopping when two consecutive values differ by at most 1.
+fn compute_d(amp_factor: u64, amount_a: u64, amount_b: u64) -> Result<U192> {
+    let sum_x = U192::from(amount_a) + U192::from(amount_b);
+    if sum_x.is_zero() {
+        return Ok(U192::zero());
+    }
+    if amount_a == 0 || amount_b == 0 || amp_factor == 0 {
+        msg!("Stable swap pool is empty on one side");
+        return err!(ScopeError::PriceNotValid);
+    }
+    let n_coins = U192::from(N_COINS);
+    let ann = U192::from(amp_factor) * n_coins;
+    let amount_a_times_coins = U192::from(amount_a) * n_coins;
+    let amount_b_times_coins = U192::from(amount_b) * n_coins;
+    let overflow = || error!(ScopeError::MathOverflow);
+
+    let mut d = sum_x;
+    for _ in 0..MAX_D_ITERATIONS {
+        let d_product = d
+            .checked_mul(d)
+            .ok_or_else(overflow)?
+            .checked_div(amount_a_times_coins)
+            .ok_or_else(overflow)?
+            .checked_mul(d)
+            .ok_or_else(overflow)?
+            .checked_div(amount_b_times_coins)
+            .ok_or_else(overflow)?;
+        let d_prev = d;
+        // d = (ann * sum_x + d_product * n) * d / ((ann - 1) * d + (n + 1) * d_product)
+        let numerator = d
+            .checked_mul(
+                d_product
+                    .checked_mul(n_coins)
+                    .and_then(|v| v.checked_add(sum_x.checked_mul(ann)?))
+                    .ok_or_else(overflow)?,
+            )
+            .ok_or_else(overflow)?;
+        let denominator = d
+            .checked_mul(ann - 1)
+            .and_then(|v| v.checked_add(d_product.checked_mul(n_coins + 1)?))
+            .ok_or_else(overflow)?;
+        d = numerator / denominator;
+        let diff = if d > d_prev { d - d_prev } else { d_prev - d };
+        if diff <= U192::one() {
+            return Ok(d);
+        }
+    }
+    msg!(
+        "Stable swap invariant did not converge in {} iterations",
+        MAX_D_ITERATIONS
+    );
+    err!(ScopeError::InvariantNotConverged)
+}
+
+/// `d / lp_supply` in tokens for one LP token, with the exponent [`VIRTUAL_PRICE_EXP`]
+fn virtual_price(d: U192, decimals: u8, lp_supply: u64, lp_decimals: u8) -> Result<Price> {
+    if lp_supply == 0 {
+        msg!("Stable swap LP token has no supply");
+        return err!(ScopeError::PriceNotValid);
+    }
+    let pow10 = |exp: u32| {
+        10_u128
+            .checked_pow(exp)
+            .map(U192::from)
+            .ok_or_else(|| error!(ScopeError::MathOverflow))
+    };
+    let numerator = d
+        .checked_mul(pow10(VIRTUAL_PRICE_EXP + u32::from(lp_decimals))?)
+        .ok_or(ScopeError::MathOverflow)?;
+    let denominator = U192::from(lp_supply) * pow10(decimals.into())?;
+    let value = numerator / denominator;
+    if value > U192::from(u64::MAX) {
+        return err!(ScopeError::MathOverflow);
+    }
+    Ok(Price {
+        value: value.as_u64(),
+        exp: VIRTUAL_PRICE_EXP.into(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SUPPLY: u64 = 2_000_000_000_000;
+
+    #[test]
+    fn balanced_pool_is_at_par() {
+        let d = compute_d(100, 1_000_000_000_000, 1_000_000_000_000).unwrap();
+        assert_eq!(d, U192::from(2_000_000_000_000_u64));
+        let price = virtual_price(d, 6, SUPPLY, 6).unwrap();
+        assert_eq!(
+            price,
+            Price {
+                value: 1_000_000_000_000,
+                exp: 12
+            }
+        );
+    }
+
+    #[test]
+    fn imbalanced_pool_is_below_par() {
+        // 95% of the pool in token A
+        let d = compute_d(100, 1_900_000_000_000, 100_000_000_000).unwrap();
+        assert_eq!(d, U192::from(1_960_246_144_398_u64));
+        let price = virtual_price(d, 6, SUPPLY, 6).unwrap();
+        assert_eq!(price.value, 980_123_072_199);
+
+        // A lower amplification gives a lower virtual price for the same reserves
+        let d_low_amp = compute_d(10, 1_900_000_000_000, 100_000_000_000).unwrap();
+        assert!(d_low_amp < d);
+    }
+
+    #[test]
+    fn extremely_imbalanced_pool_does_not_converge() {
+        assert_eq!(
+            compute_d(1, 1_000_000_000_000, 1).unwrap_err(),
+            ScopeError::InvariantNotConverged.into()
+        );
+    }
+
+    #[test]
+    fn empty_pools() {
+        assert_eq!(compute_d(100, 0, 0).unwrap(), U192::zero());
+        assert_eq!(
+            compute_d(100, 1_000_000, 0).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+        assert_eq!(
+            virtual_price(U192::from(1_000_000), 6, 0, 6).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn lp_decimals_different_from_tokens() {
+        // 1000 tokens with 6 decimals for 100 LP tokens with 9 decimals
+        let price = virtual_price(U192::from(1_000_000_000_u64), 6, 100_000_000_000, 9).unwrap();
+        assert_eq!(price.value, 10_000_000_000_000);
+    }
+
+    #[test]
+    fn amp_factor_ramp() {
+        let mut swap = SwapInfo {
+            initial_amp_factor: 100,
+            target_amp_factor: 200,
+            start_ramp_ts: 1_000,
+            stop_ramp_ts: 2_000,
+            token_a: Pubkey::new_unique(),
+            token_b: Pubkey::new_unique(),
+            pool_mint: Pubkey::new_unique(),
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+        };
+        assert_eq!(swap.amp_factor(500).unwrap(), 100);
+        assert_eq!(swap.amp_factor(1_500).unwrap(), 150);
+        assert_eq!(swap.amp_factor(2_500).unwrap(), 200);
+
+        // Ramp down
+        swap.initial_amp_factor = 200;
+        swap.target_amp_factor = 100;
+        assert_eq!(swap.amp_factor(1_250).unwrap(), 175);
+    }
+
+    #[test]
+    fn swap_info_parsing() {
+        let mut data = vec![0_u8; SWAP_INFO_SIZE];
+        assert_eq!(
+            SwapInfo::from_account_data(&data).unwrap_err(),
+            ScopeError::UnableToDeserializeAccount.into()
+        );
+
+        data[IS_INITIALIZED_OFFSET] = 1;
+        data[TARGET_AMP_FACTOR_OFFSET..TARGET_AMP_FACTOR_OFFSET + 8]
+            .copy_from_slice(&100_u64.to_le_bytes());
+        let pool_mint = Pubkey::new_unique();
+        data[POOL_MINT_OFFSET..POOL_MINT_OFFSET + 32].copy_from_slice(pool_mint.as_ref());
+        let swap = SwapInfo::from_account_data(&data).unwrap();
+        assert_eq!(swap.target_amp_factor, 100);
+        assert_eq!(swap.pool_mint, pool_mint);
+        assert_eq!(swap.amp_factor(0).unwrap(), 100);
+
+        assert!(SwapInfo::from_account_data(&data[..SWAP_INFO_SIZE - 1]).is_err());
+    }
+}
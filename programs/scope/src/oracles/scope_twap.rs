@@ -0,0 +1,95 @@
+//! EMA of another price of the same scope feed
+//!
+//! Exposes as a regular price the EMA maintained in the [`OracleTwaps`] account for a source
+//! token of the feed (see [`crate::utils::ema`]). The TWAP account must be provided to the
+//! refresh instruction.
+//!
+//! As for [`super::derived_price`], the source token is encoded in the pubkey referenced in the
+//! oracle mapping (see [`source_to_mapping_pubkey`]). The EMA is updated when its source is
+//! refreshed, the source must be refreshed first for the EMA price to include its last value.
+
+use anchor_lang::prelude::*;
+
+use crate::{DatedPrice, OracleTwaps, Result, ScopeError};
+
+/// Marker byte of the encoded source, distinguishes token 0 from an unset mapping
+const SOURCE_MARKER: u8 = 4;
+
+/// Encode the source token of an EMA price in a pubkey to store it in the oracle mapping
+pub fn source_to_mapping_pubkey(source: u16) -> Pubkey {
+    let mut bytes = [0_u8; 32];
+    bytes[..2].copy_from_slice(&source.to_le_bytes());
+    bytes[2] = SOURCE_MARKER;
+    Pubkey::new_from_array(bytes)
+}
+
+/// Decode the source token of an EMA price stored in the oracle mapping
+pub fn source_from_mapping_pubkey(pubkey: &Pubkey) -> Result<u16> {
+    let bytes = pubkey.to_bytes();
+    if bytes[2] != SOURCE_MARKER || bytes[3..].iter().any(|&b| b != 0) {
+        msg!("EMA price mapping {} is not an encoded source", pubkey);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let source = u16::from_le_bytes(bytes[..2].try_into().unwrap());
+    if usize::from(source) >= crate::MAX_ENTRIES {
+        msg!("EMA price source out of range: {}", source);
+        return err!(ScopeError::BadTokenNb);
+    }
+    Ok(source)
+}
+
+pub fn get_price(
+    base_account: &AccountInfo,
+    oracle_twaps: Option<&AccountLoader<OracleTwaps>>,
+) -> Result<DatedPrice> {
+    let source = source_from_mapping_pubkey(base_account.key)?;
+    let oracle_twaps = match oracle_twaps {
+        Some(oracle_twaps) => oracle_twaps,
+        None => {
+            msg!("The TWAP account is required to refresh an EMA price");
+            return err!(ScopeError::UnexpectedAccount);
+        }
+    };
+    let ema = oracle_twaps.load()?.get_ema(source.into())?;
+    Ok(DatedPrice {
+        price: ema.price,
+        last_updated_slot: ema.last_updated_slot,
+        unix_timestamp: ema.unix_timestamp,
+        ..Default::default()
+    })
+}
+
+/// Check that an EMA price mapping is an encoded source
+pub fn validate_mapping_account(mapping_account: &AccountInfo) -> Result<()> {
+    source_from_mapping_pubkey(mapping_account.key).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::oracles::derived_price;
+
+    #[test]
+    fn test_encode_decode_source() {
+        for source in [0, 3, 511] {
+            assert_eq!(
+                source_from_mapping_pubkey(&source_to_mapping_pubkey(source)).unwrap(),
+                source
+            );
+        }
+        assert_eq!(
+            source_from_mapping_pubkey(&source_to_mapping_pubkey(512)).unwrap_err(),
+            ScopeError::BadTokenNb.into()
+        );
+        assert_eq!(
+            source_from_mapping_pubkey(&Pubkey::default()).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        // Sources of the other derived prices
+        assert_eq!(
+            source_from_mapping_pubkey(&derived_price::sources_to_mapping_pubkey(0, 1))
+                .unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Share price of a token backed by the balance of a vault
+//!
+//! Simple wrapped tokens hold the underlying tokens in an escrow token account (the vault) and
+//! mint shares against it. The price of one share in underlying tokens is
+//! `vault_amount / share_supply`, normalized with the decimals of both mints.
+//!
+//! The vault is the mapped account, the share mint and the mint of the vault are provided as
+//! extra accounts. The share mint is bound to the vault by its mint authority, which must be the
+//! owner of the vault: whoever mints the shares controls the underlying tokens.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    oracles::constant_product_lp::{read_mint, read_token_amount},
+    utils::math::div_prices,
+    DatedPrice, Price, Result, ScopeError,
+};
+
+/// Programs owning the vault and the mints (SPL token and token-2022)
+pub const TOKEN_PROGRAM_IDS: [Pubkey; 2] = [
+    pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+    pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+];
+
+// Offsets in SPL token accounts and mints
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const MINT_AUTHORITY_OFFSET: usize = 0;
+
+/// Get the price of one share, see the module documentation
+///
+/// The extra accounts are consumed in order: share mint, mint of the vault.
+pub fn get_price<'a, 'b>(
+    vault_info: &AccountInfo,
+    clock: &Clock,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let share_mint_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    let vault_mint_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    for mint_info in [share_mint_info, vault_mint_info] {
+        if !TOKEN_PROGRAM_IDS.contains(mint_info.owner) {
+            msg!("Mint {} is not owned by a token program", mint_info.key);
+            return err!(ScopeError::UnexpectedAccount);
+        }
+    }
+
+    let vault_amount = read_token_amount(vault_info)?;
+    let (vault_mint, vault_owner) = read_vault_mint_and_owner(vault_info)?;
+    if vault_mint_info.key() != vault_mint {
+        msg!(
+            "Vault mint received {} is not the expected {}",
+            vault_mint_info.key(),
+            vault_mint
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let mint_authority = read_mint_authority(share_mint_info)?;
+    if mint_authority != Some(vault_owner) {
+        msg!(
+            "Share mint {} is minted by {:?}, not by the vault owner {}",
+            share_mint_info.key(),
+            mint_authority,
+            vault_owner
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    let (share_supply, share_decimals) = read_mint(share_mint_info)?;
+    let (_, vault_decimals) = read_mint(vault_mint_info)?;
+    let price = share_price(vault_amount, vault_decimals, share_supply, share_decimals)?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: clock.slot,
+        unix_timestamp: u64::try_from(clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    })
+}
+
+/// Check that the given account is a token account
+pub fn validate_vault_account(vault_info: &AccountInfo) -> Result<()> {
+    if !TOKEN_PROGRAM_IDS.contains(vault_info.owner) {
+        msg!("Vault {} is not owned by a token program", vault_info.key);
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    read_token_amount(vault_info).map(|_| ())
+}
+
+/// Mint and owner of a token account, its size must have been checked
+fn read_vault_mint_and_owner(vault_info: &AccountInfo) -> Result<(Pubkey, Pubkey)> {
+    let data = vault_info.try_borrow_data()?;
+    let read_pubkey =
+        |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+    Ok((
+        read_pubkey(TOKEN_ACCOUNT_MINT_OFFSET),
+        read_pubkey(TOKEN_ACCOUNT_OWNER_OFFSET),
+    ))
+}
+
+/// Mint authority of a mint (`COption<Pubkey>`), `None` if the supply is fixed
+fn read_mint_authority(mint_info: &AccountInfo) -> Result<Option<Pubkey>> {
+    // Checks the size of the mint
+    read_mint(mint_info)?;
+    let data = mint_info.try_borrow_data()?;
+    let tag = u32::from_le_bytes(
+        data[MINT_AUTHORITY_OFFSET..MINT_AUTHORITY_OFFSET + 4]
+            .try_into()
+            .unwrap(),
+    );
+    Ok((tag == 1).then(|| {
+        Pubkey::new_from_array(
+            data[MINT_AUTHORITY_OFFSET + 4..MINT_AUTHORITY_OFFSET + 36]
+                .try_into()
+                .unwrap(),
+        )
+    }))
+}
+
+/// `vault_amount / share_supply` with both amounts converted to tokens
+fn share_price(
+    vault_amount: u64,
+    vault_decimals: u8,
+    share_supply: u64,
+    share_decimals: u8,
+) -> Result<Price> {
+    if share_supply == 0 {
+        msg!("Share mint has no supply");
+        return err!(ScopeError::PriceNotValid);
+    }
+    let vault_value = Price {
+        value: vault_amount,
+        exp: vault_decimals.into(),
+    };
+    let share_supply = Price {
+        value: share_supply,
+        exp: share_decimals.into(),
+    };
+    Ok(div_prices(&vault_value, &share_supply)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_share_price_same_decimals() {
+        // 1050 tokens for 1000 shares
+        assert_eq!(
+            share_price(1_050_000_000, 6, 1_000_000_000, 6).unwrap(),
+            Price {
+                value: 1_050_000_000_000_000_000,
+                exp: 18
+            }
+        );
+    }
+
+    #[test]
+    fn test_share_price_decimals_normalization() {
+        // 21 tokens with 9 decimals for 10 shares with 6 decimals
+        let price = share_price(21_000_000_000, 9, 10_000_000, 6).unwrap();
+        assert_eq!(
+            price,
+            Price {
+                value: 2_100_000_000_000_000_000,
+                exp: 18
+            }
+        );
+    }
+
+    #[test]
+    fn test_share_price_zero_supply() {
+        assert_eq!(
+            share_price(1_000, 6, 0, 6).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn test_empty_vault() {
+        assert_eq!(
+            share_price(0, 6, 1_000, 6).unwrap(),
+            Price { value: 0, exp: 18 }
+        );
+    }
+}
@@ -1,36 +1,93 @@
+use std::iter::Peekable;
+
 use anchor_lang::prelude::*;
-use solana_program::borsh0_10::try_from_slice_unchecked;
+use solana_program::{
+    borsh0_10::try_from_slice_unchecked, clock::DEFAULT_MS_PER_SLOT, epoch_schedule::EpochSchedule,
+};
 
-use crate::{utils::hours_since_timestamp, DatedPrice, Price, Result, ScopeError};
+use crate::{
+    utils::{clock::ClockLike, hours_since_timestamp},
+    DatedPrice, Price, Result, ScopeError,
+};
 
-use self::spl_stake_pool::StakePool;
+use self::spl_stake_pool::{AccountType, StakePool};
 
 const DECIMALS: u32 = 15u32;
 
+/// SPL stake pool program id
+pub const SPL_STAKE_POOL_PROGRAM_ID: Pubkey =
+    pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+/// Sanctum single-validator stake pool program id
+pub const SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID: Pubkey =
+    pubkey!("SP12tWFxD9oJsVWNavTTBZvMbA6gkAmxtVgxdqvyvhY");
+
+/// Sanctum multi-validator stake pool program id
+pub const SANCTUM_MULTI_VALIDATOR_PROGRAM_ID: Pubkey =
+    pubkey!("SPMBzsVUuoHA4Jm6KunbsotaahvVikZs1JyTW6iJvbn");
+
+/// Programs owning the stake pools, all of them are deployments of the SPL stake pool
+/// program and share the same `StakePool` account layout
+pub const STAKE_POOL_PROGRAM_IDS: [Pubkey; 3] = [
+    SPL_STAKE_POOL_PROGRAM_ID,
+    SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID,
+    SANCTUM_MULTI_VALIDATOR_PROGRAM_ID,
+];
+
 // Gives the price of 1 staked SOL in SOL
-pub fn get_price(
+//
+// The validator list of the pool can be provided as extra account, it is only consumed if it is
+// the next one. It allows to accept a pool whose update is in progress (see `check_pool_epoch`).
+pub fn get_price<'a, 'b, I>(
     stake_pool_account_info: &AccountInfo,
-    current_clock: &Clock,
-) -> Result<DatedPrice> {
-    let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_account_info.data.borrow())
-        .map_err(|_| {
-            msg!("Provided pubkey is not a SPL Stake account");
-            ScopeError::UnexpectedAccount
-        })?;
+    current_clock: &impl ClockLike,
+    extra_accounts: &mut Peekable<I>,
+) -> Result<DatedPrice>
+where
+    I: Iterator<Item = &'b AccountInfo<'a>>,
+    'a: 'b,
+{
+    let stake_pool = get_stake_pool(stake_pool_account_info)?;
+    #[cfg_attr(feature = "skip_price_validation", allow(unused_variables))]
+    let validator_list =
+        extra_accounts.next_if(|account| account.key == &stake_pool.validator_list);
 
     #[cfg(not(feature = "skip_price_validation"))]
-    {
-        let hours_since_epoch_started = hours_since_timestamp(
-            current_clock.unix_timestamp as u64,
-            current_clock.epoch_start_timestamp as u64,
-        );
-        if stake_pool.last_update_epoch != current_clock.epoch && hours_since_epoch_started >= 1 {
-            // The price has not been refreshed this epoch and it's been 1 hour
-            msg!("SPL Stake account has not been refreshed in current epoch");
-            #[cfg(not(feature = "localnet"))]
-            return Err(ScopeError::PriceNotValid.into());
-        }
-    }
+    check_pool_epoch(&stake_pool, validator_list, current_clock)?;
+
+    let value = scaled_rate(&stake_pool)?;
+
+    let price = Price {
+        value,
+        exp: DECIMALS.into(),
+    };
+    let dated_price = DatedPrice {
+        price,
+        last_updated_slot: current_clock.slot(),
+        unix_timestamp: u64::try_from(current_clock.unix_timestamp()).unwrap(),
+        ..Default::default()
+    };
+
+    Ok(dated_price)
+}
+
+// Gives the price of 1 staked SOL in SOL, also for pools updated late
+//
+// Same as `get_price` but a pool rejected by the epoch check is still priced. The price is then
+// dated from the start of the epoch of the last pool update (see `last_update_epoch_start`) so
+// consumers can apply their own max age.
+pub fn get_price_lenient<'a, 'b, I>(
+    stake_pool_account_info: &AccountInfo,
+    current_clock: &impl ClockLike,
+    extra_accounts: &mut Peekable<I>,
+) -> Result<DatedPrice>
+where
+    I: Iterator<Item = &'b AccountInfo<'a>>,
+    'a: 'b,
+{
+    let stake_pool = get_stake_pool(stake_pool_account_info)?;
+    let validator_list =
+        extra_accounts.next_if(|account| account.key == &stake_pool.validator_list);
 
     let value = scaled_rate(&stake_pool)?;
 
@@ -38,16 +95,145 @@ pub fn get_price(
         value,
         exp: DECIMALS.into(),
     };
+    let (last_updated_slot, unix_timestamp) =
+        if is_pool_epoch_valid(&stake_pool, validator_list, current_clock)? {
+            (
+                current_clock.slot(),
+                u64::try_from(current_clock.unix_timestamp()).unwrap(),
+            )
+        } else {
+            let epoch = stake_pool.last_update_epoch;
+            msg!("SPL Stake account not refreshed since epoch {}", epoch);
+            last_update_epoch_start(epoch, &EpochSchedule::get()?, current_clock)
+        };
     let dated_price = DatedPrice {
         price,
-        last_updated_slot: current_clock.slot,
-        unix_timestamp: u64::try_from(current_clock.unix_timestamp).unwrap(),
+        last_updated_slot,
+        unix_timestamp,
         ..Default::default()
     };
 
     Ok(dated_price)
 }
 
+/// Slot and estimated timestamp of the start of the epoch of the last pool update
+///
+/// The timestamp is estimated from the current one with the default slot duration. Neither of
+/// them are after the current ones.
+fn last_update_epoch_start(
+    last_update_epoch: u64,
+    epoch_schedule: &EpochSchedule,
+    current_clock: &impl ClockLike,
+) -> (u64, u64) {
+    let slot = epoch_schedule
+        .get_first_slot_in_epoch(last_update_epoch)
+        .min(current_clock.slot());
+    let elapsed_seconds = (current_clock.slot() - slot).saturating_mul(DEFAULT_MS_PER_SLOT) / 1000;
+    let unix_timestamp = u64::try_from(current_clock.unix_timestamp())
+        .unwrap()
+        .saturating_sub(elapsed_seconds);
+    (slot, unix_timestamp)
+}
+
+/// Check that the given account is a stake pool owned by one of the [`STAKE_POOL_PROGRAM_IDS`]
+pub fn validate_stake_pool_account(stake_pool_account_info: &AccountInfo) -> Result<()> {
+    if cfg!(feature = "skip_price_validation") {
+        return Ok(());
+    }
+    if !STAKE_POOL_PROGRAM_IDS.contains(stake_pool_account_info.owner) {
+        msg!(
+            "Stake pool {} is not owned by a stake pool program",
+            stake_pool_account_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    get_stake_pool(stake_pool_account_info).map(|_| ())
+}
+
+fn get_stake_pool(stake_pool_account_info: &AccountInfo) -> Result<StakePool> {
+    StakePool::from_account_data(&stake_pool_account_info.data.borrow())
+}
+
+/// Reject a pool not updated in the current epoch once 1 hour of the epoch has passed
+///
+/// The update crank first updates the validators of the list and then the pool. If the pool has
+/// been updated at the previous epoch and some validators have already been updated in the
+/// current one, the update is in progress and the pool is accepted.
+#[cfg(not(feature = "skip_price_validation"))]
+fn check_pool_epoch(
+    stake_pool: &StakePool,
+    validator_list: Option<&AccountInfo>,
+    current_clock: &impl ClockLike,
+) -> Result<()> {
+    if is_pool_epoch_valid(stake_pool, validator_list, current_clock)? {
+        return Ok(());
+    }
+    // The price has not been refreshed this epoch and it's been 1 hour
+    msg!("SPL Stake account has not been refreshed in current epoch");
+    if cfg!(feature = "localnet") {
+        Ok(())
+    } else {
+        err!(ScopeError::PriceNotValid)
+    }
+}
+
+/// Tell if the pool has been updated in the current epoch, is being updated or if less than
+/// 1 hour of the epoch has passed
+fn is_pool_epoch_valid(
+    stake_pool: &StakePool,
+    validator_list: Option<&AccountInfo>,
+    current_clock: &impl ClockLike,
+) -> Result<bool> {
+    if stake_pool.last_update_epoch == current_clock.epoch() {
+        return Ok(true);
+    }
+    let hours_since_epoch_started = hours_since_timestamp(
+        current_clock.unix_timestamp() as u64,
+        current_clock.epoch_start_timestamp() as u64,
+    );
+    if hours_since_epoch_started < 1 {
+        return Ok(true);
+    }
+    if stake_pool.last_update_epoch.saturating_add(1) == current_clock.epoch() {
+        if let Some(validator_list) = validator_list {
+            if is_update_in_progress(validator_list, current_clock.epoch())? {
+                msg!("SPL Stake account update is in progress");
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Tell if a validator of the list has been updated in the given epoch
+///
+/// Only the epochs of the validators are read from the raw data, the list can be large.
+fn is_update_in_progress(validator_list: &AccountInfo, epoch: u64) -> Result<bool> {
+    use self::spl_stake_pool::validator_list::*;
+
+    let data = validator_list.try_borrow_data()?;
+    if data.len() < HEADER_LEN || data[0] != AccountType::ValidatorList as u8 {
+        msg!("Provided SPL Stake validator list is not of type ValidatorList");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let nb_validators =
+        u32::from_le_bytes(data[VALIDATORS_LEN_OFFSET..HEADER_LEN].try_into().unwrap()) as usize;
+    let validators = nb_validators
+        .checked_mul(VALIDATOR_STAKE_INFO_LEN)
+        .and_then(|len| data.get(HEADER_LEN..HEADER_LEN + len))
+        .ok_or_else(|| {
+            msg!("SPL Stake validator list is too short");
+            ScopeError::UnableToDeserializeAccount
+        })?;
+    Ok(validators
+        .chunks_exact(VALIDATOR_STAKE_INFO_LEN)
+        .any(|validator| {
+            let last_update_epoch = &validator
+                [VALIDATOR_LAST_UPDATE_EPOCH_OFFSET..VALIDATOR_LAST_UPDATE_EPOCH_OFFSET + 8];
+            u64::from_le_bytes(last_update_epoch.try_into().unwrap()) == epoch
+        }))
+}
+
 fn scaled_rate(stake_pool: &StakePool) -> Result<u64> {
     const FACTOR: u64 = 10u64.pow(DECIMALS);
     stake_pool
@@ -55,7 +241,7 @@ fn scaled_rate(stake_pool: &StakePool) -> Result<u64> {
         .ok_or_else(|| ScopeError::MathOverflow.into())
 }
 
-mod spl_stake_pool {
+pub mod spl_stake_pool {
     use anchor_lang::prelude::borsh::BorshSchema;
     use solana_program::stake::state::Lockup;
 
@@ -65,7 +251,7 @@ mod spl_stake_pool {
     /// native `Option`
     #[repr(C)]
     #[derive(Clone, Copy, Debug, PartialEq, AnchorSerialize, AnchorDeserialize, BorshSchema)]
-    pub(crate) enum FutureEpoch<T> {
+    pub enum FutureEpoch<T> {
         /// Nothing is set
         None,
         /// Value is ready after the next epoch boundary
@@ -82,7 +268,7 @@ mod spl_stake_pool {
 
     /// Enum representing the account type managed by the program
     #[derive(Clone, Debug, Default, PartialEq, AnchorDeserialize, AnchorSerialize, BorshSchema)]
-    pub(crate) enum AccountType {
+    pub enum AccountType {
         /// If the account has not been initialized, the enum will be 0
         #[default]
         Uninitialized,
@@ -96,7 +282,7 @@ mod spl_stake_pool {
     #[derive(
         Clone, Copy, Debug, Default, PartialEq, AnchorSerialize, AnchorDeserialize, BorshSchema,
     )]
-    pub(crate) struct Fee {
+    pub struct Fee {
         /// denominator of the fee ratio
         pub denominator: u64,
         /// numerator of the fee ratio
@@ -105,7 +291,7 @@ mod spl_stake_pool {
 
     /// The type of fees that can be set on the stake pool
     #[derive(Clone, Debug, PartialEq, AnchorDeserialize, AnchorSerialize, BorshSchema)]
-    pub(crate) enum FeeType {
+    pub enum FeeType {
         /// Referral fees for SOL deposits
         SolReferral(u8),
         /// Referral fees for stake deposits
@@ -125,7 +311,7 @@ mod spl_stake_pool {
     /// Initialized program details.
     #[repr(C)]
     #[derive(Clone, Debug, Default, PartialEq, AnchorDeserialize, AnchorSerialize, BorshSchema)]
-    pub(crate) struct StakePool {
+    pub struct StakePool {
         /// Account type, must be StakePool currently
         pub account_type: AccountType,
 
@@ -237,7 +423,35 @@ mod spl_stake_pool {
         pub last_epoch_total_lamports: u64,
     }
 
+    /// Layout of the `ValidatorList` account
+    pub mod validator_list {
+        /// Offset of the number of validators, after the account type and `max_validators`
+        pub const VALIDATORS_LEN_OFFSET: usize = 1 + 4;
+        /// Size of the header, the validators follow
+        pub const HEADER_LEN: usize = VALIDATORS_LEN_OFFSET + 4;
+        /// Size of a `ValidatorStakeInfo`
+        pub const VALIDATOR_STAKE_INFO_LEN: usize = 73;
+        /// Offset of `last_update_epoch` in a `ValidatorStakeInfo`, after the active and
+        /// transient stake lamports
+        pub const VALIDATOR_LAST_UPDATE_EPOCH_OFFSET: usize = 16;
+    }
+
     impl StakePool {
+        pub fn from_account_data(data: &[u8]) -> Result<StakePool> {
+            let stake_pool = try_from_slice_unchecked::<StakePool>(data).map_err(|_| {
+                msg!("Provided pubkey is not a SPL Stake account");
+                ScopeError::UnexpectedAccount
+            })?;
+
+            // A validator list account can partially deserialize as a stake pool, reject it
+            // explicitly
+            if stake_pool.account_type != AccountType::StakePool {
+                msg!("Provided SPL Stake account is not of type StakePool");
+                return Err(ScopeError::UnexpectedAccount.into());
+            }
+            Ok(stake_pool)
+        }
+
         /// calculate lamports amount on withdrawal
         #[inline]
         pub fn calc_lamports_withdraw_amount(&self, pool_tokens: u64) -> Option<u64> {
@@ -257,10 +471,306 @@ mod spl_stake_pool {
 
 #[cfg(test)]
 mod test {
-    use crate::oracles::spl_stake::spl_stake_pool::StakePool;
+    use std::iter;
+
+    use crate::{oracles::spl_stake::spl_stake_pool::StakePool, utils::clock::mock::MockClock};
 
     use super::*;
 
+    /// Size of a stake pool account as allocated by the stake pool programs
+    const STAKE_POOL_ACCOUNT_SIZE: usize = 611;
+
+    /// Single-validator pool as found on Sanctum LSTs: a single preferred validator,
+    /// a fresh pool has slightly more lamports than pool tokens
+    fn sanctum_single_validator_pool() -> StakePool {
+        let vote_account = Pubkey::new_unique();
+        StakePool {
+            account_type: AccountType::StakePool,
+            preferred_deposit_validator_vote_address: Some(vote_account),
+            preferred_withdraw_validator_vote_address: Some(vote_account),
+            total_lamports: 1_052_391_287_615_473,
+            pool_token_supply: 1_000_000_000_000_000,
+            last_update_epoch: 650,
+            ..Default::default()
+        }
+    }
+
+    fn stake_pool_data(stake_pool: &StakePool) -> Vec<u8> {
+        let mut data = stake_pool.try_to_vec().unwrap();
+        data.resize(STAKE_POOL_ACCOUNT_SIZE, 0);
+        data
+    }
+
+    fn with_stake_pool_account<R>(
+        data: &mut [u8],
+        owner: &Pubkey,
+        f: impl FnOnce(&AccountInfo) -> R,
+    ) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, owner, false, 0);
+        f(&account)
+    }
+
+    #[test]
+    pub fn sanctum_single_validator_pool_price() {
+        let mut data = stake_pool_data(&sanctum_single_validator_pool());
+        let clock = Clock {
+            slot: 280_000_000,
+            epoch: 650,
+            unix_timestamp: 1_720_000_000,
+            ..Default::default()
+        };
+        let price = with_stake_pool_account(&mut data, &SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID, |a| {
+            get_price(a, &clock, &mut iter::empty().peekable())
+        })
+        .unwrap();
+        assert_eq!(
+            price.price,
+            Price {
+                value: 1_052_391_287_615_473,
+                exp: DECIMALS.into()
+            }
+        );
+        assert_eq!(price.last_updated_slot, clock.slot);
+    }
+
+    #[cfg(not(any(feature = "skip_price_validation", feature = "localnet")))]
+    #[test]
+    pub fn sanctum_single_validator_pool_not_updated_this_epoch() {
+        let mut data = stake_pool_data(&sanctum_single_validator_pool());
+        let clock = Clock {
+            epoch: 651,
+            epoch_start_timestamp: 1_720_000_000,
+            unix_timestamp: 1_720_000_000 + 3_600,
+            ..Default::default()
+        };
+        let res = with_stake_pool_account(&mut data, &SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID, |a| {
+            get_price(a, &clock, &mut iter::empty().peekable())
+        });
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    /// Raw `ValidatorList` account with validators updated at the given epochs
+    fn validator_list_data(last_update_epochs: &[u64]) -> Vec<u8> {
+        use self::spl_stake_pool::validator_list::*;
+
+        let mut data = vec![AccountType::ValidatorList as u8];
+        data.extend_from_slice(&10u32.to_le_bytes());
+        data.extend_from_slice(&(last_update_epochs.len() as u32).to_le_bytes());
+        for epoch in last_update_epochs {
+            let mut validator = [0; VALIDATOR_STAKE_INFO_LEN];
+            validator[VALIDATOR_LAST_UPDATE_EPOCH_OFFSET..VALIDATOR_LAST_UPDATE_EPOCH_OFFSET + 8]
+                .copy_from_slice(&epoch.to_le_bytes());
+            data.extend_from_slice(&validator);
+        }
+        data
+    }
+
+    /// Clock 2 hours in the given epoch
+    fn clock_in_epoch(epoch: u64) -> Clock {
+        Clock {
+            epoch,
+            epoch_start_timestamp: 1_720_000_000,
+            unix_timestamp: 1_720_000_000 + 7_200,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(not(any(feature = "skip_price_validation", feature = "localnet")))]
+    #[test]
+    pub fn update_in_progress_is_accepted() {
+        let stake_pool = sanctum_single_validator_pool();
+        let owner = Pubkey::new_unique();
+        let clock = clock_in_epoch(651);
+
+        // Crank started: the first validators have been updated
+        let mut data = validator_list_data(&[651, 651, 650]);
+        let res = with_stake_pool_account(&mut data, &owner, |list| {
+            check_pool_epoch(&stake_pool, Some(list), &clock)
+        });
+        assert!(res.is_ok());
+
+        // Crank not started yet
+        let mut data = validator_list_data(&[650, 650, 650]);
+        let res = with_stake_pool_account(&mut data, &owner, |list| {
+            check_pool_epoch(&stake_pool, Some(list), &clock)
+        });
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+
+        // Without the validator list the update cannot be detected
+        assert_eq!(
+            check_pool_epoch(&stake_pool, None, &clock).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[cfg(not(any(feature = "skip_price_validation", feature = "localnet")))]
+    #[test]
+    pub fn pool_late_by_several_epochs_is_rejected() {
+        let stake_pool = sanctum_single_validator_pool();
+        let mut data = validator_list_data(&[652, 652]);
+        let res = with_stake_pool_account(&mut data, &Pubkey::new_unique(), |list| {
+            check_pool_epoch(&stake_pool, Some(list), &clock_in_epoch(652))
+        });
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    pub fn pool_epoch_with_mock_clock() {
+        let stake_pool = sanctum_single_validator_pool();
+        let epoch_651 = MockClock::new().epoch(651, 1_720_000_000);
+
+        // Updated in the current epoch
+        let clock = MockClock::new()
+            .epoch(650, 1_720_000_000)
+            .seconds_in_epoch(7_200);
+        assert!(is_pool_epoch_valid(&stake_pool, None, &clock).unwrap());
+
+        // Not updated yet, but the epoch started less than 1 hour ago
+        let clock = epoch_651.seconds_in_epoch(3_599);
+        assert!(is_pool_epoch_valid(&stake_pool, None, &clock).unwrap());
+
+        // Not refreshed this epoch after 1 hour
+        let clock = epoch_651.seconds_in_epoch(3_600);
+        assert!(!is_pool_epoch_valid(&stake_pool, None, &clock).unwrap());
+    }
+
+    #[cfg(not(any(feature = "skip_price_validation", feature = "localnet")))]
+    #[test]
+    pub fn price_with_mock_clock() {
+        let mut data = stake_pool_data(&sanctum_single_validator_pool());
+        let epoch_651 = MockClock::new().slot(281_000_000).epoch(651, 1_720_000_000);
+
+        let clock = epoch_651.seconds_in_epoch(1_800);
+        let price = with_stake_pool_account(&mut data, &SPL_STAKE_POOL_PROGRAM_ID, |a| {
+            get_price(a, &clock, &mut iter::empty().peekable())
+        })
+        .unwrap();
+        assert_eq!(price.last_updated_slot, 281_000_000);
+        assert_eq!(price.unix_timestamp, 1_720_001_800);
+
+        let clock = epoch_651.seconds_in_epoch(3_600);
+        let res = with_stake_pool_account(&mut data, &SPL_STAKE_POOL_PROGRAM_ID, |a| {
+            get_price(a, &clock, &mut iter::empty().peekable())
+        });
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    pub fn invalid_validator_list_is_rejected() {
+        let owner = Pubkey::new_unique();
+
+        let mut data = validator_list_data(&[651, 651]);
+        data[0] = AccountType::StakePool as u8;
+        let res =
+            with_stake_pool_account(&mut data, &owner, |list| is_update_in_progress(list, 651));
+        assert_eq!(res.unwrap_err(), ScopeError::UnexpectedAccount.into());
+
+        let mut data = validator_list_data(&[651, 651]);
+        data.truncate(data.len() - 1);
+        let res =
+            with_stake_pool_account(&mut data, &owner, |list| is_update_in_progress(list, 651));
+        assert_eq!(
+            res.unwrap_err(),
+            ScopeError::UnableToDeserializeAccount.into()
+        );
+    }
+
+    #[test]
+    pub fn validator_list_is_consumed_only_if_next() {
+        let validator_list_key = Pubkey::new_unique();
+        let stake_pool = StakePool {
+            validator_list: validator_list_key,
+            ..sanctum_single_validator_pool()
+        };
+        let mut pool_data = stake_pool_data(&stake_pool);
+        let clock = clock_in_epoch(650);
+        let owner = Pubkey::new_unique();
+        let other_key = Pubkey::new_unique();
+        let mut list_data = validator_list_data(&[650]);
+        let (mut list_lamports, mut other_lamports) = (0, 0);
+        let mut other_data = vec![];
+        let list = AccountInfo::new(
+            &validator_list_key,
+            false,
+            false,
+            &mut list_lamports,
+            &mut list_data,
+            &owner,
+            false,
+            0,
+        );
+        let other = AccountInfo::new(
+            &other_key,
+            false,
+            false,
+            &mut other_lamports,
+            &mut other_data,
+            &owner,
+            false,
+            0,
+        );
+
+        with_stake_pool_account(&mut pool_data, &SPL_STAKE_POOL_PROGRAM_ID, |pool| {
+            let accounts = [list.clone(), other.clone()];
+            let mut extra_accounts = accounts.iter().peekable();
+            get_price(pool, &clock, &mut extra_accounts).unwrap();
+            assert_eq!(extra_accounts.next().unwrap().key, &other_key);
+
+            let accounts = [other.clone()];
+            let mut extra_accounts = accounts.iter().peekable();
+            get_price(pool, &clock, &mut extra_accounts).unwrap();
+            assert_eq!(extra_accounts.next().unwrap().key, &other_key);
+        });
+    }
+
+    #[cfg(not(feature = "skip_price_validation"))]
+    #[test]
+    pub fn stake_pool_owners_are_validated() {
+        let mut data = stake_pool_data(&sanctum_single_validator_pool());
+        for owner in STAKE_POOL_PROGRAM_IDS {
+            assert!(
+                with_stake_pool_account(&mut data, &owner, validate_stake_pool_account).is_ok()
+            );
+        }
+        assert_eq!(
+            with_stake_pool_account(
+                &mut data,
+                &Pubkey::new_unique(),
+                validate_stake_pool_account
+            )
+            .unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    pub fn validator_list_account_is_rejected() {
+        let stake_pool = StakePool {
+            account_type: AccountType::ValidatorList,
+            total_lamports: 10u64.pow(5),
+            pool_token_supply: 10u64.pow(5),
+            ..Default::default()
+        };
+        let mut data = stake_pool.try_to_vec().unwrap();
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        let res = get_price(&account, &Clock::default(), &mut iter::empty().peekable());
+        assert_eq!(res.unwrap_err(), ScopeError::UnexpectedAccount.into());
+    }
+
     #[test]
     pub fn minted_token_is_equal_to_token_in_vault() {
         let total_lamports = 10u64.pow(5);
@@ -302,4 +812,53 @@ mod test {
         // Expect staked token price to be 2 tokens
         assert_eq!(scaled_rate(&stake_pool).unwrap(), 2 * 10u64.pow(DECIMALS));
     }
+
+    #[test]
+    pub fn late_pool_is_dated_from_its_last_update_epoch() {
+        let epoch_schedule = EpochSchedule::custom(432_000, 432_000, false);
+        // 2 hours in epoch 651
+        let clock = Clock {
+            slot: 651 * 432_000 + 18_000,
+            epoch: 651,
+            epoch_start_timestamp: 1_720_000_000,
+            unix_timestamp: 1_720_000_000 + 7_200,
+            ..Default::default()
+        };
+
+        // Updated at the previous epoch: dated from its start, 432_000 slots of 400ms earlier
+        assert_eq!(
+            last_update_epoch_start(650, &epoch_schedule, &clock),
+            (650 * 432_000, 1_720_000_000 - 172_800)
+        );
+        // Late by several epochs
+        assert_eq!(
+            last_update_epoch_start(648, &epoch_schedule, &clock),
+            (648 * 432_000, 1_720_000_000 - 3 * 172_800)
+        );
+        // Never after the current slot and time
+        assert_eq!(
+            last_update_epoch_start(652, &epoch_schedule, &clock),
+            (clock.slot, 1_720_000_000 + 7_200)
+        );
+    }
+
+    #[test]
+    pub fn lenient_price_of_up_to_date_pool_is_not_backdated() {
+        let mut data = stake_pool_data(&sanctum_single_validator_pool());
+        let clock = Clock {
+            slot: 280_000_000,
+            epoch: 651,
+            epoch_start_timestamp: 1_720_000_000,
+            unix_timestamp: 1_720_000_000 + 600,
+            ..Default::default()
+        };
+        // Less than 1 hour in the epoch, accepted as for the strict price
+        let price = with_stake_pool_account(&mut data, &SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID, |a| {
+            get_price_lenient(a, &clock, &mut iter::empty().peekable())
+        })
+        .unwrap();
+        assert_eq!(price.price.value, 1_052_391_287_615_473);
+        assert_eq!(price.last_updated_slot, clock.slot);
+        assert_eq!(price.unix_timestamp, 1_720_000_600);
+    }
 }
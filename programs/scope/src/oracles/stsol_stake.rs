@@ -0,0 +1,255 @@
+use anchor_lang::prelude::*;
+use solana_program::borsh0_10::try_from_slice_unchecked;
+
+use crate::{utils::hours_since_timestamp, DatedPrice, Price, Result, ScopeError};
+
+use self::lido::{AccountType, ExchangeRate, Lido};
+
+/// Lido for Solana program id
+pub const LIDO_PROGRAM_ID: Pubkey = pubkey!("CrX7kMhLC3cSsXJdT7JDgqrRVWGnUpX3gfEfxxU2NVLi");
+
+const DECIMALS: u32 = 15u32;
+
+// Gives the price of 1 stSOL in SOL
+pub fn get_price(lido_account_info: &AccountInfo, current_clock: &Clock) -> Result<DatedPrice> {
+    let lido = get_lido_state(lido_account_info)?;
+
+    check_exchange_rate_epoch(&lido.exchange_rate, current_clock)?;
+
+    let value = scaled_rate(&lido)?;
+
+    let price = Price {
+        value,
+        exp: DECIMALS.into(),
+    };
+    let dated_price = DatedPrice {
+        price,
+        last_updated_slot: current_clock.slot,
+        unix_timestamp: u64::try_from(current_clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    };
+
+    Ok(dated_price)
+}
+
+/// Check that the exchange rate was computed in the current epoch
+///
+/// The Lido exchange rate is updated by a permissionless crank at the start of each epoch, the rate
+/// of the previous epoch is tolerated during the first hour of a new epoch.
+fn check_exchange_rate_epoch(exchange_rate: &ExchangeRate, current_clock: &Clock) -> Result<()> {
+    if cfg!(feature = "skip_price_validation") {
+        return Ok(());
+    }
+    let hours_since_epoch_started = hours_since_timestamp(
+        current_clock.unix_timestamp as u64,
+        current_clock.epoch_start_timestamp as u64,
+    );
+    if exchange_rate.computed_in_epoch != current_clock.epoch && hours_since_epoch_started >= 1 {
+        // The exchange rate has not been updated this epoch and it's been 1 hour
+        msg!("Lido exchange rate has not been updated in current epoch");
+        #[cfg(not(feature = "localnet"))]
+        return Err(ScopeError::PriceNotValid.into());
+    }
+    Ok(())
+}
+
+/// Check that the given account is a Lido state account
+pub fn validate_lido_account(lido_account_info: &AccountInfo) -> Result<()> {
+    get_lido_state(lido_account_info).map(|_| ())
+}
+
+/// Deserialize the Lido state, the account must be owned by the Lido program
+fn get_lido_state(lido_account_info: &AccountInfo) -> Result<Lido> {
+    if !cfg!(feature = "skip_price_validation") && lido_account_info.owner != &LIDO_PROGRAM_ID {
+        msg!("Provided Lido state is not owned by the Lido program");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    let lido =
+        try_from_slice_unchecked::<Lido>(&lido_account_info.data.borrow()).map_err(|_| {
+            msg!("Provided pubkey is not a Lido state account");
+            ScopeError::UnexpectedAccount
+        })?;
+
+    if lido.account_type != AccountType::Lido {
+        msg!("Provided Lido account is not of type Lido");
+        return Err(ScopeError::UnexpectedAccount.into());
+    }
+
+    Ok(lido)
+}
+
+fn scaled_rate(lido: &Lido) -> Result<u64> {
+    const FACTOR: u64 = 10u64.pow(DECIMALS);
+    lido.exchange_rate
+        .calc_sol_amount(FACTOR)
+        .ok_or_else(|| ScopeError::MathOverflow.into())
+}
+
+mod lido {
+    use super::*;
+
+    /// Type of the accounts managed by the Lido program
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+    pub(crate) enum AccountType {
+        #[default]
+        Uninitialized,
+        Lido,
+        Validator,
+        Maintainer,
+    }
+
+    /// Exchange rate between SOL and stSOL, updated once per epoch
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+    pub(crate) struct ExchangeRate {
+        /// Epoch in which the exchange rate was last computed
+        pub computed_in_epoch: u64,
+        /// Amount of stSOL that existed at that time
+        pub st_sol_supply: u64,
+        /// Amount of SOL that was managed by Lido at that time
+        pub sol_balance: u64,
+    }
+
+    /// Lido state account
+    ///
+    /// Only the beginning of the account is mapped, the remaining data is ignored
+    /// by the deserialization.
+    #[derive(Clone, Debug, Default, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+    pub(crate) struct Lido {
+        /// Account type, must be Lido
+        pub account_type: AccountType,
+        /// Version of the Lido program
+        pub lido_version: u8,
+        /// Manager authority
+        pub manager: Pubkey,
+        /// The SPL Token mint address for stSOL
+        pub st_sol_mint: Pubkey,
+        /// Exchange rate to use when depositing
+        pub exchange_rate: ExchangeRate,
+    }
+
+    impl ExchangeRate {
+        /// Convert an amount of stSOL to SOL at this exchange rate
+        pub fn calc_sol_amount(&self, st_sol_amount: u64) -> Option<u64> {
+            // An empty pool is valued 1:1
+            if self.st_sol_supply == 0 {
+                return Some(st_sol_amount);
+            }
+            let numerator = (st_sol_amount as u128).checked_mul(self.sol_balance as u128)?;
+            u64::try_from(numerator.checked_div(self.st_sol_supply as u128)?).ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lido_with_rate(st_sol_supply: u64, sol_balance: u64) -> Lido {
+        Lido {
+            account_type: AccountType::Lido,
+            exchange_rate: ExchangeRate {
+                computed_in_epoch: 0,
+                st_sol_supply,
+                sol_balance,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn with_lido_account<R>(lido: &Lido, owner: &Pubkey, f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = lido.try_to_vec().unwrap();
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            owner,
+            false,
+            0,
+        );
+        f(&account)
+    }
+
+    #[test]
+    pub fn lido_state_is_read() {
+        let lido = lido_with_rate(10u64.pow(5), 2 * 10u64.pow(5));
+        let state = with_lido_account(&lido, &LIDO_PROGRAM_ID, get_lido_state).unwrap();
+        assert_eq!(state, lido);
+        with_lido_account(&lido, &LIDO_PROGRAM_ID, validate_lido_account).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "skip_price_validation"))]
+    pub fn lido_state_of_another_program_is_rejected() {
+        let lido = lido_with_rate(10u64.pow(5), 2 * 10u64.pow(5));
+        let owner = Pubkey::new_unique();
+        assert_eq!(
+            with_lido_account(&lido, &owner, validate_lido_account).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+        assert_eq!(
+            with_lido_account(&lido, &owner, |account| {
+                get_price(account, &Clock::default()).map(|_| ())
+            })
+            .unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+
+    #[test]
+    pub fn st_sol_supply_is_equal_to_sol_balance() {
+        let lido = lido_with_rate(10u64.pow(5), 10u64.pow(5));
+        assert_eq!(scaled_rate(&lido).unwrap(), 10u64.pow(DECIMALS));
+    }
+
+    #[test]
+    pub fn sol_balance_is_2x_st_sol_supply() {
+        let lido = lido_with_rate(10u64.pow(5), 2 * 10u64.pow(5));
+        // Expect stSOL price to be 2 SOL
+        assert_eq!(scaled_rate(&lido).unwrap(), 2 * 10u64.pow(DECIMALS));
+    }
+
+    #[test]
+    pub fn st_sol_supply_is_2x_sol_balance() {
+        // Note: this should never happen
+        let lido = lido_with_rate(2 * 10u64.pow(5), 10u64.pow(5));
+        // Expect stSOL price to be 0.5 SOL
+        assert_eq!(scaled_rate(&lido).unwrap(), 5 * 10u64.pow(DECIMALS - 1));
+    }
+
+    fn clock_in_epoch(epoch: u64, seconds_since_epoch_start: i64) -> Clock {
+        let epoch_start_timestamp = 1_700_000_000;
+        Clock {
+            epoch,
+            epoch_start_timestamp,
+            unix_timestamp: epoch_start_timestamp + seconds_since_epoch_start,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn exchange_rate_of_current_epoch_is_valid() {
+        let mut lido = lido_with_rate(10u64.pow(5), 10u64.pow(5));
+        lido.exchange_rate.computed_in_epoch = 500;
+        let clock = clock_in_epoch(500, 10 * 3600);
+        assert!(check_exchange_rate_epoch(&lido.exchange_rate, &clock).is_ok());
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "skip_price_validation", feature = "localnet")))]
+    pub fn exchange_rate_of_previous_epoch_is_tolerated_for_one_hour() {
+        let mut lido = lido_with_rate(10u64.pow(5), 10u64.pow(5));
+        lido.exchange_rate.computed_in_epoch = 499;
+        let clock = clock_in_epoch(500, 3599);
+        assert!(check_exchange_rate_epoch(&lido.exchange_rate, &clock).is_ok());
+
+        let clock = clock_in_epoch(500, 3600);
+        assert_eq!(
+            check_exchange_rate_epoch(&lido.exchange_rate, &clock).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+}
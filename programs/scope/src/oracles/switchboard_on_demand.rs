@@ -0,0 +1,295 @@
+//! Switchboard On-Demand pull feeds
+//!
+//! Unlike Switchboard V2 aggregators, on-demand feeds are updated by the users that need the
+//! price, so the stored result can be arbitrarily old. The slot of the latest result is checked
+//! against the current slot with the feed `max_staleness` (in slots), or
+//! [`DEFAULT_MAX_STALENESS_SLOTS`] if the feed does not define one.
+//!
+//! All the values of a pull feed are stored as `i128` with a fixed scale of [`PRECISION`].
+
+use std::cell::Ref;
+
+use anchor_lang::{
+    __private::bytemuck::{self, Pod, Zeroable},
+    prelude::*,
+};
+
+use crate::{utils::math::decimal_to_price, DatedPrice, Result, ScopeError};
+
+/// Switchboard On-Demand program id (same on mainnet and devnet)
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+/// Number of decimals of all the values stored in a pull feed
+pub const PRECISION: u32 = 18;
+
+/// Max age of the latest result (in slots) when the feed does not define `max_staleness`
+pub const DEFAULT_MAX_STALENESS_SLOTS: u64 = 250;
+
+pub fn get_price(feed_info: &AccountInfo, clock: &Clock) -> Result<DatedPrice> {
+    let feed = PullFeedAccountData::new(feed_info)?;
+
+    // Copy to avoid references to a packed struct
+    let result = feed.result;
+    let num_samples = result.num_samples;
+    let result_slot = result.slot;
+    let result_value = result.value;
+    let feed_max_staleness = feed.max_staleness;
+    let last_update_timestamp = feed.last_update_timestamp;
+
+    if num_samples == 0 {
+        msg!(
+            "Switchboard on-demand feed {} has no valid sample",
+            feed_info.key()
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+
+    let max_staleness = match feed_max_staleness {
+        0 => DEFAULT_MAX_STALENESS_SLOTS,
+        max_staleness => u64::from(max_staleness),
+    };
+
+    let dated_price = DatedPrice {
+        price: decimal_to_price(result_value, PRECISION)?,
+        last_updated_slot: result_slot,
+        unix_timestamp: u64::try_from(last_update_timestamp).unwrap_or(0),
+        ..Default::default()
+    };
+
+    if dated_price.is_stale(clock.slot, max_staleness) {
+        msg!(
+            "Switchboard on-demand feed {} is stale: last result at slot {}, current slot {}, max staleness {}",
+            feed_info.key(),
+            result_slot,
+            clock.slot,
+            max_staleness
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+
+    Ok(dated_price)
+}
+
+pub fn validate_feed_account(feed_info: &AccountInfo) -> Result<()> {
+    if feed_info.owner != &SWITCHBOARD_ON_DEMAND_PROGRAM_ID {
+        msg!(
+            "Switchboard on-demand feed {} is not owned by the on-demand program",
+            feed_info.key()
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    PullFeedAccountData::new(feed_info).map(|_| ())
+}
+
+#[zero_copy(unsafe)]
+#[repr(packed)]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct OracleSubmission {
+    pub oracle: Pubkey,
+    pub slot: u64,
+    pub landed_at: u64,
+    pub value: i128,
+}
+
+#[zero_copy(unsafe)]
+#[repr(packed)]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct CurrentResult {
+    /// Median of the samples of the last update
+    pub value: i128,
+    pub std_dev: i128,
+    pub mean: i128,
+    pub range: i128,
+    pub min_value: i128,
+    pub max_value: i128,
+    /// Number of samples used to compute the result
+    pub num_samples: u8,
+    pub submission_idx: u8,
+    pub padding1: [u8; 6],
+    /// Slot of the most recent sample used to compute the result
+    pub slot: u64,
+    pub min_slot: u64,
+    pub max_slot: u64,
+}
+
+#[zero_copy(unsafe)]
+#[repr(packed)]
+#[derive(Default, Debug, PartialEq)]
+pub struct CompactResult {
+    pub std_dev: f32,
+    pub mean: f32,
+    pub slot: u64,
+}
+
+#[zero_copy(unsafe)]
+#[repr(packed)]
+#[derive(Debug)]
+pub struct PullFeedAccountData {
+    pub submissions: [OracleSubmission; 32],
+    pub authority: Pubkey,
+    pub queue: Pubkey,
+    pub feed_hash: [u8; 32],
+    pub initialized_at: i64,
+    pub permissions: u64,
+    pub max_variance: u64,
+    pub min_responses: u32,
+    pub name: [u8; 32],
+    pub padding1: [u8; 2],
+    pub historical_result_idx: u8,
+    pub min_sample_size: u8,
+    pub last_update_timestamp: i64,
+    pub lut_slot: u64,
+    pub _reserved1: [u8; 32],
+    pub result: CurrentResult,
+    /// Max age of the result in slots
+    pub max_staleness: u32,
+    pub padding2: [u8; 12],
+    pub historical_results: [CompactResult; 32],
+    pub _ebuf4: [u8; 8],
+    pub _ebuf3: [u8; 24],
+    pub _ebuf2: [u8; 256],
+}
+
+impl PullFeedAccountData {
+    pub fn new<'info>(feed_info: &'info AccountInfo) -> Result<Ref<'info, PullFeedAccountData>> {
+        let data = feed_info.try_borrow_data()?;
+
+        if data.len() < 8 + std::mem::size_of::<PullFeedAccountData>() {
+            msg!("Switchboard on-demand feed account is too small");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        if data[..8] != PullFeedAccountData::discriminator() {
+            msg!(
+                "Switchboard on-demand feed account has an invalid discriminator: {:?}",
+                &data[..8]
+            );
+            return err!(ScopeError::InvalidAccountDiscriminator);
+        }
+
+        Ok(Ref::map(data, |data| {
+            bytemuck::from_bytes(&data[8..8 + std::mem::size_of::<PullFeedAccountData>()])
+        }))
+    }
+
+    pub fn discriminator() -> [u8; 8] {
+        [196, 27, 108, 196, 10, 215, 219, 40]
+    }
+}
+
+unsafe impl Pod for PullFeedAccountData {}
+unsafe impl Zeroable for PullFeedAccountData {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_data(value: i128, num_samples: u8, slot: u64, max_staleness: u32) -> Vec<u8> {
+        let mut feed: PullFeedAccountData = Zeroable::zeroed();
+        feed.result.value = value;
+        feed.result.num_samples = num_samples;
+        feed.result.slot = slot;
+        feed.max_staleness = max_staleness;
+        feed.last_update_timestamp = 1_700_000_000;
+
+        let mut data = PullFeedAccountData::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&feed));
+        data
+    }
+
+    fn with_feed_account<R>(data: &mut [u8], f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            data,
+            &SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+            false,
+            0,
+        );
+        f(&account)
+    }
+
+    fn clock_at(slot: u64) -> Clock {
+        Clock {
+            slot,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_account_size() {
+        assert_eq!(std::mem::size_of::<PullFeedAccountData>(), 3200);
+    }
+
+    #[test]
+    fn test_valid_price() {
+        // 123.45 with 18 decimals
+        let mut data = feed_data(123_450_000_000_000_000_000, 3, 100, 0);
+        let price = with_feed_account(&mut data, |acc| get_price(acc, &clock_at(110))).unwrap();
+        // Exponent is capped to 10 decimals
+        assert_eq!(price.price.value, 1_234_500_000_000);
+        assert_eq!(price.price.exp, 10);
+        assert_eq!(price.last_updated_slot, 100);
+        assert_eq!(price.unix_timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_no_sample() {
+        let mut data = feed_data(0, 0, 0, 0);
+        let res = with_feed_account(&mut data, |acc| get_price(acc, &clock_at(10)));
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    fn test_stale_price_default_staleness() {
+        let mut data = feed_data(1_000_000_000_000_000_000, 1, 100, 0);
+        let res = with_feed_account(&mut data, |acc| {
+            get_price(acc, &clock_at(100 + DEFAULT_MAX_STALENESS_SLOTS + 1))
+        });
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    fn test_feed_max_staleness() {
+        let mut data = feed_data(1_000_000_000_000_000_000, 1, 100, 10);
+        let res = with_feed_account(&mut data, |acc| get_price(acc, &clock_at(110)));
+        assert!(res.is_ok());
+        let res = with_feed_account(&mut data, |acc| get_price(acc, &clock_at(111)));
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    fn test_negative_price() {
+        let mut data = feed_data(-1, 1, 100, 0);
+        let res = with_feed_account(&mut data, |acc| get_price(acc, &clock_at(100)));
+        assert_eq!(res.unwrap_err(), ScopeError::PriceNotValid.into());
+    }
+
+    #[test]
+    fn test_validate_owner() {
+        let mut data = feed_data(1, 1, 0, 0);
+        assert!(with_feed_account(&mut data, validate_feed_account).is_ok());
+
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        assert_eq!(
+            validate_feed_account(&account).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
+}
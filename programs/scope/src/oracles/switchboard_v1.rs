@@ -7,18 +7,17 @@ use switchboard_program::{
 
 use crate::{DatedPrice, Price, Result, ScopeError};
 
+/// Switchboard v1 program id
+pub const SWITCHBOARD_V1_PROGRAM_ID: Pubkey =
+    pubkey!("DtmE9D2CSB4L5D6A15mraeEjrGMm6auWVzgaD8hK2tZM");
+
 const SWITCHBOARD_V1_PRICE_DECIMALS: u32 = 8u32;
 const PRICE_MULTIPLIER: f64 = 10u64.pow(SWITCHBOARD_V1_PRICE_DECIMALS) as f64;
 const MAX_PRICE_FLOAT: f64 = 10_000_000_000f64; //we choose an arbitrarily high number to do a sanity check and avoid overflow in the multiplication below
 const MIN_NUM_SUCCESS: i32 = 3i32;
 
 pub fn get_price(switchboard_feed_info: &AccountInfo) -> Result<DatedPrice> {
-    let account_buf = switchboard_feed_info.try_borrow_data()?;
-    // first byte type discriminator
-    if account_buf[0] != SwitchboardAccountType::TYPE_AGGREGATOR as u8 {
-        msg!("switchboard address not of type aggregator");
-        return err!(ScopeError::UnexpectedAccount);
-    }
+    validate_aggregator_account(switchboard_feed_info)?;
 
     let aggregator: AggregatorState = get_aggregator(switchboard_feed_info)?;
     let round_result: RoundResult = get_aggregator_result(&aggregator)?;
@@ -28,18 +27,44 @@ pub fn get_price(switchboard_feed_info: &AccountInfo) -> Result<DatedPrice> {
         ScopeError::PriceNotValid
     })?;
 
+    let price = f64_to_price_value(price_float)?;
+    let slot: u64 = round_result.round_open_slot.ok_or_else(|| {
+        msg!("Price not valid: round_open_slot not set");
+        ScopeError::PriceNotValid
+    })?;
+    let timestamp = round_result
+        .round_open_timestamp
+        .and_then(|timestamp| timestamp.try_into().ok())
+        .ok_or_else(|| {
+            msg!("Price not valid: round_open_timestamp not set or negative");
+            ScopeError::PriceNotValid
+        })?;
+    validate_valid_price(price, slot, timestamp, aggregator, round_result)
+}
+
+/// Check that the given account is a Switchboard v1 aggregator
+pub fn validate_aggregator_account(switchboard_feed_info: &AccountInfo) -> Result<()> {
+    let account_buf = switchboard_feed_info.try_borrow_data()?;
+    // first byte type discriminator
+    if account_buf.first() != Some(&(SwitchboardAccountType::TYPE_AGGREGATOR as u8)) {
+        msg!("switchboard address not of type aggregator");
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    Ok(())
+}
+
+/// Convert the f64 result of a round to a value with [`SWITCHBOARD_V1_PRICE_DECIMALS`] decimals,
+/// rounded to the nearest
+fn f64_to_price_value(price_float: f64) -> Result<u64> {
+    if price_float.is_nan() || price_float < 0.0 {
+        msg!("Price not valid: {}", price_float);
+        return err!(ScopeError::PriceNotValid);
+    }
     if price_float >= MAX_PRICE_FLOAT {
         msg!("Price is above 'MAX_PRICE_FLOAT'");
         return err!(ScopeError::MathOverflow);
     }
-    let price: u64 = (price_float * PRICE_MULTIPLIER) as u64;
-    let slot: u64 = round_result.round_open_slot.unwrap();
-    let timestamp = round_result
-        .round_open_timestamp
-        .unwrap()
-        .try_into()
-        .unwrap();
-    validate_valid_price(price, slot, timestamp, aggregator, round_result)
+    Ok((price_float * PRICE_MULTIPLIER).round() as u64)
 }
 
 pub fn validate_valid_price(
@@ -89,9 +114,98 @@ pub fn validate_valid_price(
 
 #[cfg(test)]
 mod tests {
-    use switchboard_program::{mod_AggregatorState, AggregatorState, RoundResult};
+    use anchor_lang::prelude::{AccountInfo, Pubkey};
+    use quick_protobuf::serialize_into_slice;
+    use switchboard_program::{
+        mod_AggregatorState, AggregatorState, RoundResult, SwitchboardAccountType,
+    };
+
+    use crate::{oracles::switchboard_v1, Price, ScopeError};
+
+    const SLOT: u64 = 10;
+    const TIMESTAMP: i64 = 1_700_000_000;
+
+    /// Account data as written by the `initialize_switchboard_v1` instruction of the mock oracles
+    fn aggregator_data(result: f64, num_success: i32) -> Vec<u8> {
+        let aggregator = AggregatorState {
+            last_round_result: Some(RoundResult {
+                num_success: Some(num_success),
+                result: Some(result),
+                round_open_slot: Some(SLOT),
+                round_open_timestamp: Some(TIMESTAMP),
+                ..RoundResult::default()
+            }),
+            configs: Some(mod_AggregatorState::Configs {
+                min_confirmations: Some(3),
+                ..mod_AggregatorState::Configs::default()
+            }),
+            ..AggregatorState::default()
+        };
+        let mut data = vec![0; 1_000];
+        data[0] = SwitchboardAccountType::TYPE_AGGREGATOR as u8;
+        serialize_into_slice(&aggregator, &mut data[1..]).unwrap();
+        data
+    }
 
-    use crate::oracles::switchboard_v1;
+    fn get_price_from_data(data: &mut [u8]) -> crate::Result<crate::DatedPrice> {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        switchboard_v1::get_price(&account)
+    }
+
+    #[test]
+    fn test_parse_mock_aggregator() {
+        // Mantissa 123456789 with a scale of 10, as sent to the mock oracles
+        let mut data = aggregator_data(123_456_789_f64 / 10_000_000_000_f64, 3);
+        let price = get_price_from_data(&mut data).unwrap();
+        assert_eq!(
+            price.price,
+            Price {
+                value: 1_234_568,
+                exp: 8
+            }
+        );
+        assert_eq!(price.last_updated_slot, SLOT);
+        assert_eq!(price.unix_timestamp, TIMESTAMP as u64);
+    }
+
+    #[test]
+    fn test_result_is_rounded() {
+        // 0.29 * 10^8 is 28999999.999999996 in f64
+        let mut data = aggregator_data(0.29, 3);
+        assert_eq!(
+            get_price_from_data(&mut data).unwrap().price,
+            Price {
+                value: 29_000_000,
+                exp: 8
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_aggregator() {
+        let mut data = aggregator_data(1.0, 2);
+        assert_eq!(
+            get_price_from_data(&mut data).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+
+        let mut data = aggregator_data(-1.0, 3);
+        assert_eq!(
+            get_price_from_data(&mut data).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+
+        let mut data = aggregator_data(1.0, 3);
+        // Uninitialized account
+        data[0] = 0;
+        assert_eq!(
+            get_price_from_data(&mut data).unwrap_err(),
+            ScopeError::UnexpectedAccount.into()
+        );
+    }
 
     fn get_structs_from_min_confirmations_and_num_success(
         min_confirmations: i32,
@@ -3,14 +3,32 @@ use std::convert::TryInto;
 use anchor_lang::prelude::*;
 
 use self::switchboard::*;
-use crate::{DatedPrice, Price, Result, ScopeError};
-
-const MAX_EXPONENT: u32 = 10;
-
-const MIN_CONFIDENCE_PERCENTAGE: u64 = 2u64;
-const CONFIDENCE_FACTOR: u64 = 100 / MIN_CONFIDENCE_PERCENTAGE;
-
-pub fn get_price(switchboard_feed_info: &AccountInfo) -> Result<DatedPrice> {
+use crate::{utils::math::decimal_to_price, DatedPrice, Price, Result, ScopeError};
+
+/// Program owning the Switchboard v2 aggregator accounts
+pub const SWITCHBOARD_V2_PROGRAM_ID: Pubkey =
+    pubkey!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+
+/// Max standard deviation of the results of a round relative to its result, in bps, when the
+/// entry does not configure one (see [`UpdateMappingConfigMode::MaxStdDeviation`])
+///
+/// [`UpdateMappingConfigMode::MaxStdDeviation`]: crate::UpdateMappingConfigMode::MaxStdDeviation
+pub const DEFAULT_MAX_STD_DEVIATION_BPS: u16 = 200;
+const BPS_FACTOR: i128 = 10_000;
+
+/// Max number of slots since the opening of the latest confirmed round (~10 minutes)
+pub const MAX_STALENESS_SLOTS: u64 = 1500;
+
+/// Get the result of the latest confirmed round
+///
+/// The price is rejected with [`ScopeError::OracleDisagreement`] when the standard deviation of
+/// the oracle results reaches `max_std_deviation_bps` of the result (0 for the default
+/// [`DEFAULT_MAX_STD_DEVIATION_BPS`]).
+pub fn get_price(
+    switchboard_feed_info: &AccountInfo,
+    clock: &Clock,
+    max_std_deviation_bps: u16,
+) -> Result<DatedPrice> {
     let feed = AggregatorAccountData::new(switchboard_feed_info)
         .map_err(|_| ScopeError::SwitchboardV2Error)?;
 
@@ -27,23 +45,27 @@ pub fn get_price(switchboard_feed_info: &AccountInfo) -> Result<DatedPrice> {
     if !cfg!(feature = "skip_price_validation") {
         let stdev_mantissa = feed.latest_confirmed_round.std_deviation.mantissa;
         let stdev_scale = feed.latest_confirmed_round.std_deviation.scale;
-        if validate_confidence(
+        let max_std_deviation_bps = match max_std_deviation_bps {
+            0 => DEFAULT_MAX_STD_DEVIATION_BPS,
+            bps => bps,
+        };
+        if let Err(e) = validate_confidence(
             price_switchboard_desc.mantissa,
             price_switchboard_desc.scale,
             stdev_mantissa,
             stdev_scale,
-        )
-        .is_err()
-        {
+            max_std_deviation_bps,
+        ) {
             // Using sol log because with exactly 5 parameters, msg! expect u64s.
             msg!(
-                    "Validation of confidence interval for switchboard v2 feed {} failed. Price: {:?}, stdev_mantissa: {:?}, stdev_scale: {:?}",
+                    "Validation of confidence interval for switchboard v2 feed {} failed. Price: {:?}, stdev_mantissa: {:?}, stdev_scale: {:?}, max stdev: {} bps",
                     switchboard_feed_info.key(),
                     price,
                     stdev_mantissa,
-                    stdev_scale
+                    stdev_scale,
+                    max_std_deviation_bps
                 );
-            return err!(ScopeError::SwitchboardV2Error);
+            return Err(e.into());
         }
     };
 
@@ -54,19 +76,62 @@ pub fn get_price(switchboard_feed_info: &AccountInfo) -> Result<DatedPrice> {
         .try_into()
         .unwrap();
 
-    Ok(DatedPrice {
+    let dated_price = DatedPrice {
         price,
         last_updated_slot,
         unix_timestamp,
+        conf: std_deviation_to_conf(&feed.latest_confirmed_round.std_deviation, price.exp),
         ..Default::default()
-    })
+    };
+
+    if !cfg!(feature = "skip_price_validation")
+        && dated_price.is_stale(clock.slot, MAX_STALENESS_SLOTS)
+    {
+        msg!(
+            "Switchboard v2 feed {} is stale: latest round opened at slot {}, current slot {}",
+            switchboard_feed_info.key(),
+            last_updated_slot,
+            clock.slot
+        );
+        return err!(ScopeError::PriceNotValid);
+    }
+
+    Ok(dated_price)
 }
 
+/// Check that the given account is a switchboard v2 aggregator
+pub fn validate_aggregator_account(switchboard_feed_info: &AccountInfo) -> Result<()> {
+    AggregatorAccountData::new(switchboard_feed_info).map(|_| ())
+}
+
+/// Express the standard deviation of a round with the exponent of its price
+///
+/// Extra decimals are truncated and a standard deviation too large for a `u64` saturates.
+fn std_deviation_to_conf(std_deviation: &SwitchboardDecimal, exp: u64) -> u64 {
+    let mantissa = std_deviation.mantissa.unsigned_abs();
+    let scale = u64::from(std_deviation.scale);
+    let conf = if scale >= exp {
+        u32::try_from(scale - exp)
+            .ok()
+            .and_then(|decimals| 10_u128.checked_pow(decimals))
+            .map_or(0, |divisor| mantissa / divisor)
+    } else {
+        u32::try_from(exp - scale)
+            .ok()
+            .and_then(|decimals| 10_u128.checked_pow(decimals))
+            .and_then(|factor| mantissa.checked_mul(factor))
+            .unwrap_or(u128::MAX)
+    };
+    u64::try_from(conf).unwrap_or(u64::MAX)
+}
+
+/// Reject the price when the standard deviation reaches `max_std_deviation_bps` of the price
 fn validate_confidence(
     price_mantissa: i128,
     price_scale: u32,
     stdev_mantissa: i128,
     stdev_scale: u32,
+    max_std_deviation_bps: u16,
 ) -> std::result::Result<(), ScopeError> {
     // Step 1: compute scaling factor to bring the stdev to the same scale as the price.
     let (scale_op, scale_diff): (&dyn Fn(i128, i128) -> Option<i128>, _) =
@@ -86,50 +151,46 @@ fn validate_confidence(
         .checked_pow(scale_diff)
         .ok_or(ScopeError::MathOverflow)?;
 
-    // Step 2: multiply the stdev by the CONFIDENCE_FACTOR and apply scaling factor.
+    // Step 2: reduce the `max_std_deviation_bps / BPS_FACTOR` ratio to keep the products small
+    // (e.g. 2% is 1/50).
+    let max_std_deviation_bps = i128::from(max_std_deviation_bps);
+    let gcd = gcd(max_std_deviation_bps, BPS_FACTOR);
+    let (ratio_num, ratio_den) = (max_std_deviation_bps / gcd, BPS_FACTOR / gcd);
 
-    let stdev_x_confidence_factor_scaled = stdev_mantissa
-        .checked_mul(CONFIDENCE_FACTOR.into())
+    // Step 3: multiply the stdev by the ratio denominator and apply scaling factor.
+
+    let stdev_x_ratio_den_scaled = stdev_mantissa
+        .checked_mul(ratio_den)
         .and_then(|a| scale_op(a, scaling_factor))
         .ok_or(ScopeError::MathOverflow)?;
 
-    if stdev_x_confidence_factor_scaled >= price_mantissa {
-        Err(ScopeError::PriceNotValid)
+    let price_x_ratio_num = price_mantissa
+        .checked_mul(ratio_num)
+        .ok_or(ScopeError::MathOverflow)?;
+
+    if stdev_x_ratio_den_scaled >= price_x_ratio_num {
+        Err(ScopeError::OracleDisagreement)
     } else {
         Ok(())
     }
 }
 
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
 impl TryFrom<SwitchboardDecimal> for Price {
     type Error = ScopeError;
 
     fn try_from(sb_decimal: SwitchboardDecimal) -> std::result::Result<Self, Self::Error> {
-        if sb_decimal.mantissa < 0 {
-            msg!("Switchboard v2 oracle price feed is negative");
-            return Err(ScopeError::PriceNotValid);
-        }
-        let (exp, value) = if sb_decimal.scale > MAX_EXPONENT {
-            // exp is capped. Remove the extra digits from the mantissa.
-            let exp_diff = sb_decimal
-                .scale
-                .checked_sub(MAX_EXPONENT)
-                .ok_or(ScopeError::MathOverflow)?;
-            let factor = 10_i128
-                .checked_pow(exp_diff)
-                .ok_or(ScopeError::MathOverflow)?;
-            // Loss of precision here is expected.
-            let value = sb_decimal.mantissa / factor;
-            (MAX_EXPONENT, value)
-        } else {
-            (sb_decimal.scale, sb_decimal.mantissa)
-        };
-        let exp: u64 = exp.into();
-        let value: u64 = value.try_into().map_err(|_| ScopeError::IntegerOverflow)?;
-        Ok(Price { value, exp })
+        decimal_to_price(sb_decimal.mantissa, sb_decimal.scale)
     }
 }
 
-mod switchboard {
+pub mod switchboard {
 
     use std::cell::Ref;
 
@@ -202,6 +263,11 @@ mod switchboard {
         ) -> Result<Ref<'info, AggregatorAccountData>> {
             let data = switchboard_feed.try_borrow_data()?;
 
+            if data.len() != 8 + std::mem::size_of::<AggregatorAccountData>() {
+                msg!("Switchboard aggregator account has an invalid size");
+                return err!(ScopeError::SwitchboardV2Error);
+            }
+
             let mut disc_bytes = [0u8; 8];
             disc_bytes.copy_from_slice(&data[..8]);
             if disc_bytes != AggregatorAccountData::discriminator() {
@@ -227,7 +293,7 @@ mod switchboard {
             }
         }
 
-        fn discriminator() -> [u8; 8] {
+        pub fn discriminator() -> [u8; 8] {
             [217, 230, 65, 101, 201, 162, 27, 125]
         }
     }
@@ -281,10 +347,191 @@ mod switchboard {
 
 #[cfg(test)]
 mod tests {
+    use anchor_lang::__private::bytemuck;
     use proptest::prelude::*;
 
     use super::*;
 
+    /// Same layout as written by the `initialize_switchboard_v2` instruction of the mock oracles
+    fn aggregator_data(
+        mantissa: i128,
+        scale: u32,
+        num_success: u32,
+        round_open_slot: u64,
+    ) -> Vec<u8> {
+        aggregator_data_with_std_deviation(
+            SwitchboardDecimal::new(mantissa, scale),
+            SwitchboardDecimal::new(0, 1),
+            num_success,
+            round_open_slot,
+        )
+    }
+
+    fn aggregator_data_with_std_deviation(
+        result: SwitchboardDecimal,
+        std_deviation: SwitchboardDecimal,
+        num_success: u32,
+        round_open_slot: u64,
+    ) -> Vec<u8> {
+        let mut aggregator: AggregatorAccountData = bytemuck::Zeroable::zeroed();
+        aggregator.latest_confirmed_round.result = result;
+        aggregator.latest_confirmed_round.std_deviation = std_deviation;
+        aggregator.latest_confirmed_round.round_open_slot = round_open_slot;
+        aggregator.latest_confirmed_round.round_open_timestamp = 1_700_000_000;
+        aggregator.latest_confirmed_round.num_success = num_success;
+        aggregator.min_oracle_results = 3;
+
+        let mut data = AggregatorAccountData::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&aggregator));
+        data
+    }
+
+    fn get_price_from_data(data: &mut [u8], current_slot: u64) -> Result<DatedPrice> {
+        get_price_from_data_with_max_std_deviation(data, current_slot, 0)
+    }
+
+    fn get_price_from_data_with_max_std_deviation(
+        data: &mut [u8],
+        current_slot: u64,
+        max_std_deviation_bps: u16,
+    ) -> Result<DatedPrice> {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        let clock = Clock {
+            slot: current_slot,
+            ..Default::default()
+        };
+        get_price(&account, &clock, max_std_deviation_bps)
+    }
+
+    #[test]
+    fn test_get_price_from_aggregator() {
+        let mut data = aggregator_data(123_456, 3, 3, 100);
+        let dated_price = get_price_from_data(&mut data, 110).unwrap();
+        assert_eq!(
+            dated_price.price,
+            Price {
+                value: 123_456,
+                exp: 3
+            }
+        );
+        assert_eq!(dated_price.last_updated_slot, 100);
+        assert_eq!(dated_price.unix_timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_get_price_not_enough_oracle_results() {
+        let mut data = aggregator_data(123_456, 3, 2, 100);
+        assert_eq!(
+            get_price_from_data(&mut data, 110).unwrap_err(),
+            ScopeError::SwitchboardV2Error.into()
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "skip_price_validation"))]
+    fn test_get_price_stale_round() {
+        let mut data = aggregator_data(123_456, 3, 3, 100);
+        assert!(get_price_from_data(&mut data, 100 + MAX_STALENESS_SLOTS).is_ok());
+        assert_eq!(
+            get_price_from_data(&mut data, 100 + MAX_STALENESS_SLOTS + 1).unwrap_err(),
+            ScopeError::PriceNotValid.into()
+        );
+    }
+
+    #[test]
+    fn test_get_price_invalid_discriminator() {
+        let mut data = aggregator_data(123_456, 3, 3, 100);
+        data[0] = !data[0];
+        assert_eq!(
+            get_price_from_data(&mut data, 110).unwrap_err(),
+            ScopeError::SwitchboardV2Error.into()
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "skip_price_validation"))]
+    fn test_get_price_max_std_deviation() {
+        // Result of 100.000 with a standard deviation of 1.000 (1%)
+        let mut data = aggregator_data_with_std_deviation(
+            SwitchboardDecimal::new(100_000, 3),
+            SwitchboardDecimal::new(1_000, 3),
+            3,
+            100,
+        );
+        // Below the default 2%
+        assert!(get_price_from_data(&mut data, 110).is_ok());
+        assert!(get_price_from_data_with_max_std_deviation(&mut data, 110, 101).is_ok());
+        // At or above the configured bound
+        for max_std_deviation_bps in [100, 50] {
+            assert_eq!(
+                get_price_from_data_with_max_std_deviation(&mut data, 110, max_std_deviation_bps)
+                    .unwrap_err(),
+                ScopeError::OracleDisagreement.into()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "skip_price_validation"))]
+    fn test_get_price_max_std_deviation_above_default() {
+        // Result of 100.000 with a standard deviation of 4.000 (4%)
+        let mut data = aggregator_data_with_std_deviation(
+            SwitchboardDecimal::new(100_000, 3),
+            SwitchboardDecimal::new(4_000, 3),
+            3,
+            100,
+        );
+        assert_eq!(
+            get_price_from_data(&mut data, 110).unwrap_err(),
+            ScopeError::OracleDisagreement.into()
+        );
+        assert!(get_price_from_data_with_max_std_deviation(&mut data, 110, 500).is_ok());
+    }
+
+    #[test]
+    fn test_get_price_stores_std_deviation() {
+        let mut data = aggregator_data_with_std_deviation(
+            SwitchboardDecimal::new(100_000, 3),
+            SwitchboardDecimal::new(15, 1),
+            3,
+            100,
+        );
+        assert_eq!(get_price_from_data(&mut data, 110).unwrap().conf, 1_500);
+    }
+
+    #[test]
+    fn test_std_deviation_to_conf() {
+        let conf = |mantissa, scale, exp| {
+            std_deviation_to_conf(&SwitchboardDecimal::new(mantissa, scale), exp)
+        };
+        assert_eq!(conf(1_234, 3, 3), 1_234);
+        assert_eq!(conf(1_234, 3, 5), 123_400);
+        // Extra decimals are truncated
+        assert_eq!(conf(1_234, 5, 3), 12);
+        assert_eq!(conf(1_234, 30, 0), 0);
+        assert_eq!(conf(-1_234, 3, 3), 1_234);
+        assert_eq!(conf(1, 0, 30), u64::MAX);
+    }
+
+    #[test]
+    fn test_confidence_configured_bound() {
+        // 0.5% bound, stdev scale larger than the price scale
+        assert!(validate_confidence(100_000, 0, 4_999, 1, 50).is_ok());
+        assert_eq!(
+            validate_confidence(100_000, 0, 5_000, 1, 50).unwrap_err(),
+            ScopeError::OracleDisagreement
+        );
+        // 3.33% bound, price scale larger than the stdev scale
+        assert!(validate_confidence(1_000_000, 3, 33, 0, 333).is_ok());
+        assert_eq!(
+            validate_confidence(1_000_000, 3, 34, 0, 333).unwrap_err(),
+            ScopeError::OracleDisagreement
+        );
+    }
+
     const U64_MAX: i128 = std::u64::MAX as i128;
 
     proptest! {
@@ -326,7 +573,7 @@ mod tests {
 
     #[test]
     fn test_valid_switchboard_v2_price() {
-        assert!(validate_confidence(1, 1, 0, 1).is_ok());
+        assert!(validate_confidence(1, 1, 0, 1, DEFAULT_MAX_STD_DEVIATION_BPS).is_ok());
     }
 
     // V2 Standard Deviation Confidence Tests
@@ -334,32 +581,39 @@ mod tests {
     // Success cases
     #[test]
     fn test_valid_switchboard_v2_price_stdev_1_point_99_percent() {
-        assert!(validate_confidence(100_000, 3, 1999, 3).is_ok());
+        assert!(validate_confidence(100_000, 3, 1999, 3, DEFAULT_MAX_STD_DEVIATION_BPS).is_ok());
     }
 
     #[test]
     fn test_valid_switchboard_v2_price_stdev_zero() {
-        assert!(validate_confidence(100, 3, 0, 15).is_ok());
+        assert!(validate_confidence(100, 3, 0, 15, DEFAULT_MAX_STD_DEVIATION_BPS).is_ok());
     }
 
     #[test]
     fn test_valid_switchboard_v2_price_stdev_1p() {
-        assert!(validate_confidence(474003240021234567, 15, 4, 0).is_ok());
+        assert!(
+            validate_confidence(474003240021234567, 15, 4, 0, DEFAULT_MAX_STD_DEVIATION_BPS)
+                .is_ok()
+        );
     }
 
     #[test]
     fn test_valid_switchboard_v2_price_stdev_1p9percent_std_exp_larger_than_price_exp() {
-        assert!(validate_confidence(100_000, 0, 19, 1).is_ok());
+        assert!(validate_confidence(100_000, 0, 19, 1, DEFAULT_MAX_STD_DEVIATION_BPS).is_ok());
     }
 
     #[test]
     fn test_valid_switchboard_v2_price_stdev_1p9_std_exp_larger_than_price_exp_8_decimals_diff() {
-        assert!(validate_confidence(100_000_000_000, 0, 19, 8).is_ok());
+        assert!(
+            validate_confidence(100_000_000_000, 0, 19, 8, DEFAULT_MAX_STD_DEVIATION_BPS).is_ok()
+        );
     }
 
     #[test]
     fn test_valid_switchboard_v2_price_stdev_1p9_std_exp_larger_than_price_exp_9_decimals_diff() {
-        assert!(validate_confidence(100_000_000_000, 0, 1, 9).is_ok());
+        assert!(
+            validate_confidence(100_000_000_000, 0, 1, 9, DEFAULT_MAX_STD_DEVIATION_BPS).is_ok()
+        );
     }
 
     #[test]
@@ -370,7 +624,14 @@ mod tests {
             (61950, 5, 5000000000000000000000000, 28),
         ];
         for (value, exp, stdev_val, stdev_exp) in valid_onchain_exp {
-            validate_confidence(value, exp, stdev_val, stdev_exp).unwrap();
+            validate_confidence(
+                value,
+                exp,
+                stdev_val,
+                stdev_exp,
+                DEFAULT_MAX_STD_DEVIATION_BPS,
+            )
+            .unwrap();
         }
     }
 
@@ -383,7 +644,7 @@ mod tests {
         ) {
             let stdev_scale = scale + stdev_scale_diff;
             let stdev_mantissa = (mantissa * 2 * 10_i128.pow(stdev_scale_diff) / 100) - 1;
-            validate_confidence(mantissa, scale, stdev_mantissa, stdev_scale).unwrap();
+            validate_confidence(mantissa, scale, stdev_mantissa, stdev_scale, DEFAULT_MAX_STD_DEVIATION_BPS).unwrap();
         }
     }
 
@@ -396,40 +657,41 @@ mod tests {
         // stdev at 2% of price
         let stdev = price * 10_i128.pow(stdev_scale) * 2 / 100;
         assert_eq!(
-            validate_confidence(price, 0, stdev, stdev_scale).unwrap_err(),
-            ScopeError::PriceNotValid
+            validate_confidence(price, 0, stdev, stdev_scale, DEFAULT_MAX_STD_DEVIATION_BPS)
+                .unwrap_err(),
+            ScopeError::OracleDisagreement
         );
     }
 
     #[test]
     fn test_invalid_switchboard_v2_price_stdev_2percent_std_exp_larger_than_price_exp_2() {
         assert_eq!(
-            validate_confidence(100, 2, 20, 3).unwrap_err(),
-            ScopeError::PriceNotValid
+            validate_confidence(100, 2, 20, 3, DEFAULT_MAX_STD_DEVIATION_BPS).unwrap_err(),
+            ScopeError::OracleDisagreement
         );
     }
 
     #[test]
     fn test_invalid_switchboard_v2_price_stdev_above_2percent() {
         assert_eq!(
-            validate_confidence(100, 0, 2001, 3).unwrap_err(),
-            ScopeError::PriceNotValid
+            validate_confidence(100, 0, 2001, 3, DEFAULT_MAX_STD_DEVIATION_BPS).unwrap_err(),
+            ScopeError::OracleDisagreement
         );
     }
 
     #[test]
     fn test_invalid_switchboard_v2_price_stdev_above_2percent_2() {
         assert_eq!(
-            validate_confidence(100, 1, 201, 3).unwrap_err(),
-            ScopeError::PriceNotValid
+            validate_confidence(100, 1, 201, 3, DEFAULT_MAX_STD_DEVIATION_BPS).unwrap_err(),
+            ScopeError::OracleDisagreement
         );
     }
 
     #[test]
     fn test_invalid_switchboard_v2_price_stdev_higher_than_price() {
         assert_eq!(
-            validate_confidence(100, 0, 100001, 3).unwrap_err(),
-            ScopeError::PriceNotValid
+            validate_confidence(100, 0, 100001, 3, DEFAULT_MAX_STD_DEVIATION_BPS).unwrap_err(),
+            ScopeError::OracleDisagreement
         );
     }
 
@@ -443,7 +705,7 @@ mod tests {
             let stdev_scale = scale + stdev_scale_diff;
             // 2% + 1 unit to be just above the 2% threshold
             let stdev_mantissa = mantissa * 2 * 10_i128.pow(stdev_scale_diff) / 100 + 1;
-            prop_assert!(matches!(validate_confidence(mantissa, scale, stdev_mantissa, stdev_scale), Err(ScopeError::PriceNotValid)));
+            prop_assert!(matches!(validate_confidence(mantissa, scale, stdev_mantissa, stdev_scale, DEFAULT_MAX_STD_DEVIATION_BPS), Err(ScopeError::OracleDisagreement)));
         }
     }
 }
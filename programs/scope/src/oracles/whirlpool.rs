@@ -0,0 +1,375 @@
+//! Spot price of Orca Whirlpool (concentrated liquidity) pools
+//!
+//! The price of a pool is given by its `sqrt_price`, a Q64.64 fixed point number: the price is
+//! `(sqrt_price / 2^64)^2`, expressed in lamports of token B for one lamport of token A. It is
+//! adjusted with the decimals of both mints, provided as extra accounts, to give the price of
+//! 1 token A in token B.
+//!
+//! The ordering of the tokens in a pool is arbitrary, [`OracleType::OrcaWhirlpoolBtoA`] gives the
+//! price of 1 token B in token A instead.
+//!
+//! The price is read from the pool state only, it is dated with the current clock. Whirlpools
+//! do not keep a history of their prices: a spot price can be moved within a single
+//! transaction, consumers should rather use a [`super::scope_twap`] entry of this price.
+//!
+//! [`OracleType::OrcaWhirlpoolBtoA`]: super::OracleType::OrcaWhirlpoolBtoA
+
+use anchor_lang::prelude::*;
+
+use self::uint_internal::U256;
+use crate::{DatedPrice, Price, Result, ScopeError};
+
+// U256 with 256 bits consisting of 4 x 64-bit words
+#[allow(clippy::all)]
+mod uint_internal {
+    use uint::construct_uint;
+    construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+/// Orca Whirlpool program id
+pub const WHIRLPOOL_PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+
+/// Anchor discriminator of the `Whirlpool` account
+const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+// Offsets of the used fields in the `Whirlpool` account (discriminator included)
+const SQRT_PRICE_OFFSET: usize = 65;
+const TOKEN_MINT_A_OFFSET: usize = 101;
+const TOKEN_MINT_B_OFFSET: usize = 181;
+const WHIRLPOOL_MIN_SIZE: usize = 213;
+
+/// Bounds of the `sqrt_price` of a Whirlpool (ticks -443636 and 443636)
+const MIN_SQRT_PRICE: u128 = 4_295_048_016;
+const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_055;
+
+/// Offset of the decimals in a SPL token mint account (same for token-2022 mints)
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_SIZE: usize = 82;
+
+/// Max number of decimals of the price, as the [`super::meteora_dlmm`] prices
+const MAX_PRICE_EXP: i32 = 28;
+
+/// Fields of the `Whirlpool` account needed to compute the pool price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Whirlpool {
+    pub sqrt_price: u128,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+}
+
+impl Whirlpool {
+    pub fn from_account_data(data: &[u8]) -> Result<Whirlpool> {
+        if data.len() < WHIRLPOOL_MIN_SIZE {
+            msg!("Orca Whirlpool account is too small");
+            return err!(ScopeError::UnableToDeserializeAccount);
+        }
+        if data[..8] != WHIRLPOOL_DISCRIMINATOR {
+            msg!("Account is not an Orca Whirlpool");
+            return err!(ScopeError::InvalidAccountDiscriminator);
+        }
+        let read_pubkey =
+            |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+        Ok(Whirlpool {
+            sqrt_price: u128::from_le_bytes(
+                data[SQRT_PRICE_OFFSET..SQRT_PRICE_OFFSET + 16]
+                    .try_into()
+                    .unwrap(),
+            ),
+            token_mint_a: read_pubkey(TOKEN_MINT_A_OFFSET),
+            token_mint_b: read_pubkey(TOKEN_MINT_B_OFFSET),
+        })
+    }
+}
+
+/// Get the price of the pool, see the module documentation
+///
+/// The mint A and mint B accounts of the pool are consumed from `extra_accounts`, in this order.
+pub fn get_price<'a, 'b>(
+    b_to_a: bool,
+    whirlpool_info: &AccountInfo,
+    clock: &Clock,
+    extra_accounts: &mut impl Iterator<Item = &'b AccountInfo<'a>>,
+) -> Result<DatedPrice>
+where
+    'a: 'b,
+{
+    let whirlpool = Whirlpool::from_account_data(&whirlpool_info.try_borrow_data()?)?;
+
+    let mint_a_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    let mint_b_info = extra_accounts
+        .next()
+        .ok_or(ScopeError::AccountsAndTokenMismatch)?;
+    if mint_a_info.key() != whirlpool.token_mint_a || mint_b_info.key() != whirlpool.token_mint_b {
+        msg!(
+            "Orca Whirlpool mints received ({}, {}) are not the ones of the pool ({}, {})",
+            mint_a_info.key(),
+            mint_b_info.key(),
+            whirlpool.token_mint_a,
+            whirlpool.token_mint_b
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+
+    let price = pool_price(
+        whirlpool.sqrt_price,
+        mint_decimals(mint_a_info)?,
+        mint_decimals(mint_b_info)?,
+        b_to_a,
+    )?;
+
+    Ok(DatedPrice {
+        price,
+        last_updated_slot: clock.slot,
+        unix_timestamp: u64::try_from(clock.unix_timestamp).unwrap(),
+        ..Default::default()
+    })
+}
+
+/// Check that the given account is an Orca Whirlpool
+pub fn validate_whirlpool_account(whirlpool_info: &AccountInfo) -> Result<()> {
+    if whirlpool_info.owner != &WHIRLPOOL_PROGRAM_ID {
+        msg!(
+            "Orca Whirlpool {} is not owned by the Whirlpool program",
+            whirlpool_info.key
+        );
+        return err!(ScopeError::UnexpectedAccount);
+    }
+    Whirlpool::from_account_data(&whirlpool_info.try_borrow_data()?).map(|_| ())
+}
+
+fn mint_decimals(mint_info: &AccountInfo) -> Result<u8> {
+    let data = mint_info.try_borrow_data()?;
+    if data.len() < MINT_SIZE {
+        msg!("Account {} is not a token mint", mint_info.key);
+        return err!(ScopeError::UnableToDeserializeAccount);
+    }
+    Ok(data[MINT_DECIMALS_OFFSET])
+}
+
+/// Price of 1 token A in token B, or of 1 token B in token A if `b_to_a` is set
+fn pool_price(sqrt_price: u128, decimals_a: u8, decimals_b: u8, b_to_a: bool) -> Result<Price> {
+    if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+        msg!("Orca Whirlpool sqrt price out of bounds: {}", sqrt_price);
+        return err!(ScopeError::PriceNotValid);
+    }
+    // Lamports of B per lamport of A is sqrt_price^2 / 2^128, cannot overflow within the bounds
+    let sqrt_price = U256::from(sqrt_price);
+    let squared = sqrt_price * sqrt_price;
+    let q128 = U256::one() << 128;
+    let (decimals_a, decimals_b) = (i32::from(decimals_a), i32::from(decimals_b));
+    if b_to_a {
+        ratio_to_price(q128, squared, decimals_b - decimals_a)
+    } else {
+        ratio_to_price(squared, q128, decimals_a - decimals_b)
+    }
+}
+
+/// Convert `num / den * 10^decimals_shift` to a [`Price`], keeping as many decimals as fit in a
+/// `u64` (at most [`MAX_PRICE_EXP`])
+fn ratio_to_price(num: U256, den: U256, decimals_shift: i32) -> Result<Price> {
+    // num / den is in ]2^(l - 1), 2^(l + 1)[, scaling it by 10^s with 10^s <= 2^(63 - l) fits
+    // in a u64, log10(2) is under-approximated by 0.30102
+    let l = num.bits() as i32 - den.bits() as i32;
+    let scale = ((63 - l) * 30_102).div_euclid(100_000);
+    let mut exp = (scale - decimals_shift).clamp(0, MAX_PRICE_EXP);
+
+    let scale = decimals_shift + exp;
+    let overflow = || {
+        msg!("Orca Whirlpool price overflow");
+        error!(ScopeError::MathOverflow)
+    };
+    let factor = exp10(scale.unsigned_abs()).ok_or_else(overflow)?;
+    let (scaled_num, den) = if scale >= 0 {
+        (num.checked_mul(factor).ok_or_else(overflow)?, den)
+    } else {
+        (num, den.checked_mul(factor).ok_or_else(overflow)?)
+    };
+    let mut value = scaled_num / den;
+    let mut rem = scaled_num % den;
+    let max_value = U256::from(u64::MAX);
+    if value > max_value {
+        msg!("Orca Whirlpool price does not fit in a u64");
+        return err!(ScopeError::MathOverflow);
+    }
+
+    // The estimate can be off by a digit, add the ones still fitting
+    while exp < MAX_PRICE_EXP && !rem.is_zero() {
+        let rem10 = match rem.checked_mul(U256::from(10)) {
+            Some(rem10) => rem10,
+            None => break,
+        };
+        let next = value * 10 + rem10 / den;
+        if next > max_value {
+            break;
+        }
+        value = next;
+        rem = rem10 % den;
+        exp += 1;
+    }
+    // Drop the trailing zeros, they carry no precision
+    while exp > 0 && !value.is_zero() && (value % 10).is_zero() {
+        value /= 10;
+        exp -= 1;
+    }
+
+    if value.is_zero() {
+        msg!("Orca Whirlpool price is too small to be represented");
+        return err!(ScopeError::PriceNotValid);
+    }
+    Ok(Price {
+        value: value.as_u64(), // Cannot fail, checked above
+        exp: exp.try_into().unwrap(),
+    })
+}
+
+fn exp10(exp: u32) -> Option<U256> {
+    U256::from(10).checked_pow(U256::from(exp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sqrt prices of 1 lamport of A for 1 lamport of B, 0.15 and 0.0002
+    const SQRT_PRICE_ONE: u128 = 1 << 64;
+    const SQRT_PRICE_SOL_USDC: u128 = 7_144_393_258_922_745_604;
+    const SQRT_PRICE_BONK_USDC: u128 = 260_876_356_506_655_644;
+
+    #[test]
+    fn test_sqrt_price_one() {
+        // Same decimals, 1 lamport of A for 1 lamport of B
+        assert_eq!(
+            pool_price(SQRT_PRICE_ONE, 6, 6, false).unwrap(),
+            Price { value: 1, exp: 0 }
+        );
+        assert_eq!(
+            pool_price(SQRT_PRICE_ONE, 6, 6, true).unwrap(),
+            Price { value: 1, exp: 0 }
+        );
+        // 9 decimals A in 6 decimals B
+        assert_eq!(
+            pool_price(SQRT_PRICE_ONE, 9, 6, false).unwrap(),
+            Price {
+                value: 1000,
+                exp: 0
+            }
+        );
+        assert_eq!(
+            pool_price(SQRT_PRICE_ONE, 9, 6, true).unwrap(),
+            Price { value: 1, exp: 3 }
+        );
+    }
+
+    #[test]
+    fn test_sol_usdc_pool() {
+        // SOL (9 decimals) / USDC (6 decimals) at 150 USDC
+        assert_eq!(
+            pool_price(SQRT_PRICE_SOL_USDC, 9, 6, false).unwrap(),
+            Price {
+                value: 14_999_999_999_999_999_996,
+                exp: 17
+            }
+        );
+        assert_eq!(
+            pool_price(SQRT_PRICE_SOL_USDC, 9, 6, true).unwrap(),
+            Price {
+                value: 6_666_666_666_666_666_668,
+                exp: 21
+            }
+        );
+    }
+
+    #[test]
+    fn test_bonk_usdc_pool() {
+        // BONK (5 decimals) / USDC (6 decimals) at 0.00002 USDC
+        assert_eq!(
+            pool_price(SQRT_PRICE_BONK_USDC, 5, 6, false).unwrap(),
+            Price {
+                value: 1_999_999_999_999_999_996,
+                exp: 23
+            }
+        );
+        assert_eq!(
+            pool_price(SQRT_PRICE_BONK_USDC, 5, 6, true).unwrap(),
+            Price {
+                value: 5_000_000_000_000_000_009,
+                exp: 14
+            }
+        );
+    }
+
+    #[test]
+    fn test_sqrt_price_bounds() {
+        // Highest price representable with the same decimals
+        assert_eq!(
+            pool_price(MAX_SQRT_PRICE, 0, 0, false).unwrap(),
+            Price {
+                value: 18_446_050_711_097_703_529,
+                exp: 0
+            }
+        );
+        assert_eq!(
+            pool_price(MIN_SQRT_PRICE, 0, 0, true).unwrap(),
+            Price {
+                value: 18_446_050_713_735_950_759,
+                exp: 0
+            }
+        );
+        // The lowest one only keeps the decimals up to `MAX_PRICE_EXP`
+        assert_eq!(
+            pool_price(MIN_SQRT_PRICE, 0, 0, false).unwrap(),
+            Price {
+                value: 542_121_463,
+                exp: 28
+            }
+        );
+
+        assert_eq!(
+            pool_price(MAX_SQRT_PRICE, 18, 0, false).unwrap_err(),
+            error!(ScopeError::MathOverflow)
+        );
+        assert_eq!(
+            pool_price(MIN_SQRT_PRICE, 0, 18, false).unwrap_err(),
+            error!(ScopeError::PriceNotValid)
+        );
+        assert_eq!(
+            pool_price(MIN_SQRT_PRICE - 1, 6, 6, false).unwrap_err(),
+            error!(ScopeError::PriceNotValid)
+        );
+        assert_eq!(
+            pool_price(MAX_SQRT_PRICE + 1, 6, 6, false).unwrap_err(),
+            error!(ScopeError::PriceNotValid)
+        );
+    }
+
+    #[test]
+    fn test_parse_whirlpool() {
+        let expected = Whirlpool {
+            sqrt_price: SQRT_PRICE_SOL_USDC,
+            token_mint_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+        };
+        let mut data = vec![0_u8; 653];
+        data[..8].copy_from_slice(&WHIRLPOOL_DISCRIMINATOR);
+        data[SQRT_PRICE_OFFSET..SQRT_PRICE_OFFSET + 16]
+            .copy_from_slice(&expected.sqrt_price.to_le_bytes());
+        data[TOKEN_MINT_A_OFFSET..TOKEN_MINT_A_OFFSET + 32]
+            .copy_from_slice(expected.token_mint_a.as_ref());
+        data[TOKEN_MINT_B_OFFSET..TOKEN_MINT_B_OFFSET + 32]
+            .copy_from_slice(expected.token_mint_b.as_ref());
+        assert_eq!(Whirlpool::from_account_data(&data).unwrap(), expected);
+
+        data[0] = 0;
+        assert_eq!(
+            Whirlpool::from_account_data(&data).unwrap_err(),
+            error!(ScopeError::InvalidAccountDiscriminator)
+        );
+        assert_eq!(
+            Whirlpool::from_account_data(&data[..100]).unwrap_err(),
+            error!(ScopeError::UnableToDeserializeAccount)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Abstraction of the clock read by the oracles
+//!
+//! Oracles whose validity depends on the epoch (see [`crate::oracles::spl_stake`]) read the time
+//! through [`ClockLike`] so their logic can be tested with [`mock::MockClock`].
+
+use anchor_lang::prelude::Clock;
+
+/// Time of the cluster as seen by an oracle
+pub trait ClockLike {
+    fn slot(&self) -> u64;
+    fn unix_timestamp(&self) -> i64;
+    fn epoch(&self) -> u64;
+    fn epoch_start_timestamp(&self) -> i64;
+}
+
+impl ClockLike for Clock {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    fn unix_timestamp(&self) -> i64 {
+        self.unix_timestamp
+    }
+
+    fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    fn epoch_start_timestamp(&self) -> i64 {
+        self.epoch_start_timestamp
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::ClockLike;
+
+    /// Clock with a fixed time, built with chained setters
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct MockClock {
+        slot: u64,
+        unix_timestamp: i64,
+        epoch: u64,
+        epoch_start_timestamp: i64,
+    }
+
+    impl MockClock {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn slot(mut self, slot: u64) -> Self {
+            self.slot = slot;
+            self
+        }
+
+        pub fn unix_timestamp(mut self, unix_timestamp: i64) -> Self {
+            self.unix_timestamp = unix_timestamp;
+            self
+        }
+
+        /// Start `epoch` at `epoch_start_timestamp`, the current time is left unchanged
+        pub fn epoch(mut self, epoch: u64, epoch_start_timestamp: i64) -> Self {
+            self.epoch = epoch;
+            self.epoch_start_timestamp = epoch_start_timestamp;
+            self
+        }
+
+        /// Move the current time to `seconds` after the start of the epoch
+        pub fn seconds_in_epoch(mut self, seconds: i64) -> Self {
+            self.unix_timestamp = self.epoch_start_timestamp + seconds;
+            self
+        }
+    }
+
+    impl ClockLike for MockClock {
+        fn slot(&self) -> u64 {
+            self.slot
+        }
+
+        fn unix_timestamp(&self) -> i64 {
+            self.unix_timestamp
+        }
+
+        fn epoch(&self) -> u64 {
+            self.epoch
+        }
+
+        fn epoch_start_timestamp(&self) -> i64 {
+            self.epoch_start_timestamp
+        }
+    }
+}
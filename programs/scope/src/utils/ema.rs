@@ -0,0 +1,243 @@
+//! Exponential moving average of the refreshed prices
+//!
+//! Each refreshed price updates the EMA held in the [`TwapBuffer`] of its token. The weight of
+//! the previous EMA halves every `ema_half_life_slots`:
+//!
+//! `ema = price + (ema - price) * 2^(-elapsed_slots / half_life_slots)`
+//!
+//! so refreshes at irregular slot intervals are properly accounted for. The decay is computed
+//! in 64 bits fixed point. The EMA restarts from the refreshed price when its exponent changes.
+
+use crate::{DatedPrice, OracleTwaps, Price, ScopeError, ScopeResult, TwapBuffer};
+
+/// Half-life of the EMA when not configured, about one minute
+pub const DEFAULT_EMA_HALF_LIFE_SLOTS: u64 = 150;
+
+/// 1 in 64 bits fixed point
+const ONE: u128 = 1 << 64;
+
+/// `2^(-1/2^k)` for `k` in `1..=16`, in 64 bits fixed point
+const FRACTIONAL_DECAYS: [u128; 16] = [
+    13_043_817_825_332_782_212,
+    15_511_800_964_685_064_948,
+    16_915_738_899_553_466_670,
+    17_664_662_643_191_237_676,
+    18_051_468_387_014_017_850,
+    18_248_035_989_933_441_396,
+    18_347_121_020_861_646_923,
+    18_396_865_112_328_554_661,
+    18_421_787_711_448_657_617,
+    18_434_261_669_329_232_139,
+    18_440_501_815_349_552_981,
+    18_443_622_680_442_407_997,
+    18_445_183_311_048_607_332,
+    18_445_963_675_871_538_003,
+    18_446_353_870_663_572_145,
+    18_446_548_971_154_807_802,
+];
+
+/// `2^(-elapsed / half_life)` in 64 bits fixed point
+///
+/// The fractional part of `elapsed / half_life` is truncated to 16 bits.
+fn decay(elapsed: u64, half_life: u64) -> u128 {
+    let half_lives = elapsed / half_life;
+    if half_lives >= 64 {
+        return 0;
+    }
+    let mut decay = ONE >> half_lives;
+    // Binary expansion of the fractional part of `elapsed / half_life`
+    let mut remainder = u128::from(elapsed % half_life);
+    for fractional_decay in FRACTIONAL_DECAYS {
+        remainder *= 2;
+        if remainder >= u128::from(half_life) {
+            remainder -= u128::from(half_life);
+            decay = (decay * fractional_decay) >> 64;
+        }
+    }
+    decay
+}
+
+/// `price + (ema - price) * decay`, with `decay` in 64 bits fixed point
+fn blend(ema: u64, price: u64, decay: u128) -> u64 {
+    // The products cannot overflow: both factors are at most 2^64, one of them strictly less
+    if ema >= price {
+        price + ((u128::from(ema - price) * decay) >> 64) as u64
+    } else {
+        ema + ((u128::from(price - ema) * (ONE - decay)) >> 64) as u64
+    }
+}
+
+impl TwapBuffer {
+    /// Half-life of the EMA in slots
+    pub fn ema_half_life(&self) -> u64 {
+        match self.ema_half_life_slots {
+            0 => DEFAULT_EMA_HALF_LIFE_SLOTS,
+            half_life => half_life,
+        }
+    }
+
+    /// Clear the EMA and set a new half-life (0 means [`DEFAULT_EMA_HALF_LIFE_SLOTS`])
+    pub fn reset_ema(&mut self, half_life_slots: u64) {
+        self.ema_half_life_slots = half_life_slots;
+        self.ema = DatedPrice::default();
+    }
+
+    /// Update the EMA with a new refreshed price
+    ///
+    /// Prices older than the last one used are ignored.
+    pub(crate) fn update_ema(&mut self, price: &DatedPrice) {
+        let ema = &self.ema;
+        let value = if ema.price.value == 0 || ema.price.exp != price.price.exp {
+            price.price.value
+        } else if price.last_updated_slot < ema.last_updated_slot {
+            return;
+        } else {
+            let elapsed = price.last_updated_slot - ema.last_updated_slot;
+            blend(
+                ema.price.value,
+                price.price.value,
+                decay(elapsed, self.ema_half_life()),
+            )
+        };
+        self.ema = DatedPrice {
+            price: Price {
+                value,
+                exp: price.price.exp,
+            },
+            last_updated_slot: price.last_updated_slot,
+            unix_timestamp: price.unix_timestamp,
+            index: price.index,
+            ..Default::default()
+        };
+    }
+}
+
+impl OracleTwaps {
+    /// Get the last computed EMA of a token
+    pub fn get_ema(&self, token: usize) -> ScopeResult<DatedPrice> {
+        let buffer = self.twaps.get(token).ok_or(ScopeError::BadTokenNb)?;
+        if buffer.ema.price.value == 0 {
+            return Err(ScopeError::PriceNotValid);
+        }
+        Ok(buffer.ema)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dated_price(value: u64, last_updated_slot: u64) -> DatedPrice {
+        DatedPrice {
+            price: Price { value, exp: 6 },
+            last_updated_slot,
+            ..Default::default()
+        }
+    }
+
+    fn buffer_with(half_life_slots: u64, prices: &[(u64, u64)]) -> TwapBuffer {
+        let mut buffer = TwapBuffer::default();
+        buffer.reset_ema(half_life_slots);
+        for &(value, slot) in prices {
+            buffer.update_ema(&dated_price(value, slot));
+        }
+        buffer
+    }
+
+    #[test]
+    fn decay_halves_every_half_life() {
+        assert_eq!(decay(0, 100), ONE);
+        assert_eq!(decay(100, 100), ONE / 2);
+        assert_eq!(decay(300, 100), ONE / 8);
+        assert_eq!(decay(64 * 100, 100), 0);
+        assert_eq!(decay(u64::MAX, 1), 0);
+        // 2^(-1/2) and 2^(-3/4)
+        let sqrt_half = decay(50, 100) as f64 / ONE as f64;
+        assert!((sqrt_half - 0.5_f64.sqrt()).abs() < 1e-12);
+        let three_quarters = decay(75, 100) as f64 / ONE as f64;
+        assert!((three_quarters - 0.5_f64.powf(0.75)).abs() < 1e-12);
+        // Fraction with no exact binary expansion, truncated to 16 bits
+        let third = decay(1, 3) as f64 / ONE as f64;
+        assert!((third - 0.5_f64.powf(1.0 / 3.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn first_price_starts_the_ema() {
+        let buffer = buffer_with(100, &[(1_000, 10)]);
+        assert_eq!(
+            buffer.ema.price,
+            Price {
+                value: 1_000,
+                exp: 6
+            }
+        );
+        assert_eq!(buffer.ema.last_updated_slot, 10);
+    }
+
+    #[test]
+    fn ema_converges_at_irregular_intervals() {
+        // Step from 1000 to 2000, the distance to the new price halves every 100 slots
+        // whatever the number of refreshes in between
+        let buffer = buffer_with(100, &[(1_000, 0), (2_000, 100)]);
+        assert_eq!(buffer.ema.price.value, 1_500);
+        let buffer = buffer_with(100, &[(1_000, 0), (2_000, 30), (2_000, 100)]);
+        assert!(buffer.ema.price.value.abs_diff(1_500) <= 1);
+        // 2000 - 1000 * 2^(-400 / 100) = 1937.5
+        let buffer = buffer_with(
+            100,
+            &[
+                (1_000, 0),
+                (2_000, 7),
+                (2_000, 61),
+                (2_000, 250),
+                (2_000, 400),
+            ],
+        );
+        assert!(buffer.ema.price.value.abs_diff(1_937) <= 1);
+
+        // Converged after enough half-lives
+        let buffer = buffer_with(100, &[(1_000, 0), (2_000, 10), (2_000, 10_000)]);
+        assert_eq!(buffer.ema.price.value, 2_000);
+    }
+
+    #[test]
+    fn ema_decreasing_price() {
+        let buffer = buffer_with(10, &[(u64::MAX, 0), (0, 10)]);
+        assert_eq!(buffer.ema.price.value, u64::MAX / 2);
+        let buffer = buffer_with(10, &[(0, 0), (u64::MAX, 10)]);
+        // A zero price does not start the EMA
+        assert_eq!(buffer.ema.price.value, u64::MAX);
+    }
+
+    #[test]
+    fn same_slot_does_not_move_the_ema() {
+        let buffer = buffer_with(100, &[(1_000, 10), (2_000, 10)]);
+        assert_eq!(buffer.ema.price.value, 1_000);
+    }
+
+    #[test]
+    fn older_price_is_ignored() {
+        let buffer = buffer_with(100, &[(1_000, 10), (2_000, 110), (4_000, 50)]);
+        assert_eq!(buffer.ema.price.value, 1_500);
+        assert_eq!(buffer.ema.last_updated_slot, 110);
+    }
+
+    #[test]
+    fn exponent_change_restarts_the_ema() {
+        let mut buffer = buffer_with(100, &[(1_000, 10)]);
+        let new_price = DatedPrice {
+            price: Price { value: 2, exp: 2 },
+            last_updated_slot: 20,
+            ..Default::default()
+        };
+        buffer.update_ema(&new_price);
+        assert_eq!(buffer.ema.price, new_price.price);
+    }
+
+    #[test]
+    fn default_half_life() {
+        let buffer = buffer_with(0, &[(1_000, 0), (2_000, DEFAULT_EMA_HALF_LIFE_SLOTS)]);
+        assert_eq!(buffer.ema_half_life(), DEFAULT_EMA_HALF_LIFE_SLOTS);
+        assert_eq!(buffer.ema.price.value, 1_500);
+    }
+}
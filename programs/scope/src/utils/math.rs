@@ -0,0 +1,567 @@
+//! Price arithmetic helpers shared by the refresh handlers
+
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::msg;
+
+use crate::{Price, ScopeError, ScopeResult};
+
+/// Max number of decimals kept when converting a decimal number to a [`Price`]
+const MAX_EXPONENT: u32 = 10;
+
+/// Compare two prices that may be expressed with different exponents.
+///
+/// The price with the smallest exponent is scaled up in `u128` before comparison.
+/// If the scaling overflows, the scaled price is necessarily the biggest one
+/// (unless it is zero).
+pub fn cmp_prices(a: &Price, b: &Price) -> Ordering {
+    match a.exp.cmp(&b.exp) {
+        Ordering::Equal => a.value.cmp(&b.value),
+        Ordering::Less => match scale(a.value, b.exp - a.exp) {
+            Some(a_scaled) => a_scaled.cmp(&u128::from(b.value)),
+            None if a.value == 0 => 0.cmp(&b.value),
+            None => Ordering::Greater,
+        },
+        Ordering::Greater => match scale(b.value, a.exp - b.exp) {
+            Some(b_scaled) => u128::from(a.value).cmp(&b_scaled),
+            None if b.value == 0 => a.value.cmp(&0),
+            None => Ordering::Less,
+        },
+    }
+}
+
+/// Absolute relative change from `reference` to `price`, in bps rounded down.
+///
+/// `None` if the reference is zero or if scaling the prices to the same exponent overflows.
+pub fn deviation_bps(price: &Price, reference: &Price) -> Option<u128> {
+    let exp = price.exp.max(reference.exp);
+    let price_value = scale(price.value, exp - price.exp)?;
+    let reference_value = scale(reference.value, exp - reference.exp)?;
+    if reference_value == 0 {
+        return None;
+    }
+    price_value
+        .abs_diff(reference_value)
+        .checked_mul(10_000)
+        .map(|diff| diff / reference_value)
+}
+
+/// `value * 10^decimals` in `u128`, `None` on overflow
+fn scale(value: u64, decimals: u64) -> Option<u128> {
+    let factor = 10u128.checked_pow(u32::try_from(decimals).ok()?)?;
+    u128::from(value).checked_mul(factor)
+}
+
+/// Express a price with the `target_exp` exponent
+///
+/// Increasing the exponent fails if the value does not fit in a `u64` anymore. Decreasing it
+/// truncates the extra decimals, it fails if a non-zero price is truncated to zero.
+pub fn rescale(price: Price, target_exp: u8) -> ScopeResult<Price> {
+    let target_exp = u64::from(target_exp);
+    let value = match target_exp.cmp(&price.exp) {
+        Ordering::Equal => price.value,
+        Ordering::Greater => scale(price.value, target_exp - price.exp)
+            .and_then(|value| u64::try_from(value).ok())
+            .ok_or_else(|| {
+                msg!("Price {:?} cannot be rescaled to exp {}", price, target_exp);
+                ScopeError::MathOverflow
+            })?,
+        Ordering::Less => {
+            // A divisor that does not fit in a `u64` truncates any value to zero
+            let divisor = u32::try_from(price.exp - target_exp)
+                .ok()
+                .and_then(|decimals| 10u64.checked_pow(decimals));
+            divisor.map_or(0, |divisor| price.value / divisor)
+        }
+    };
+    if value == 0 && price.value != 0 {
+        msg!(
+            "Price {:?} is truncated to zero with exp {}",
+            price,
+            target_exp
+        );
+        return Err(ScopeError::PrecisionLoss);
+    }
+    Ok(Price {
+        value,
+        exp: target_exp,
+    })
+}
+
+/// Clamp a price to a hard cap.
+///
+/// A cap with a `value` of 0 means no cap is configured and the price is returned as is.
+/// When the price is above the cap, the cap itself (value and exponent) is returned.
+pub fn apply_hard_cap(price: Price, hard_cap: &Price) -> Price {
+    if hard_cap.value != 0 && cmp_prices(&price, hard_cap) == Ordering::Greater {
+        *hard_cap
+    } else {
+        price
+    }
+}
+
+/// Convert a signed decimal number (`mantissa * 10^-scale`) to a [`Price`]
+///
+/// The exponent is capped to `MAX_EXPONENT`, extra digits are removed from the mantissa.
+/// Negative numbers are not valid prices.
+pub fn decimal_to_price(mantissa: i128, scale: u32) -> ScopeResult<Price> {
+    if mantissa < 0 {
+        msg!("Oracle price feed is negative");
+        return Err(ScopeError::PriceNotValid);
+    }
+    let (exp, value) = if scale > MAX_EXPONENT {
+        // exp is capped. Remove the extra digits from the mantissa.
+        let exp_diff = scale
+            .checked_sub(MAX_EXPONENT)
+            .ok_or(ScopeError::MathOverflow)?;
+        let factor = 10_i128
+            .checked_pow(exp_diff)
+            .ok_or(ScopeError::MathOverflow)?;
+        // Loss of precision here is expected.
+        let value = mantissa / factor;
+        (MAX_EXPONENT, value)
+    } else {
+        (scale, mantissa)
+    };
+    let exp: u64 = exp.into();
+    let value: u64 = value.try_into().map_err(|_| ScopeError::IntegerOverflow)?;
+    Ok(Price { value, exp })
+}
+
+/// Add two prices
+///
+/// The sum is computed in `u128` with the largest exponent of both prices. Decimals are then
+/// removed until the value fits in a `u64`.
+pub fn add_prices(a: &Price, b: &Price) -> ScopeResult<Price> {
+    let mut exp = a.exp.max(b.exp);
+    let mut value = scale(a.value, exp - a.exp)
+        .zip(scale(b.value, exp - b.exp))
+        .and_then(|(a_scaled, b_scaled)| a_scaled.checked_add(b_scaled))
+        .ok_or(ScopeError::MathOverflow)?;
+    while value > u128::from(u64::MAX) {
+        if exp == 0 {
+            msg!("Price sum does not fit in a u64");
+            return Err(ScopeError::MathOverflow);
+        }
+        // Loss of precision here is expected.
+        value /= 10;
+        exp -= 1;
+    }
+    Ok(Price {
+        value: value.try_into().unwrap(), // Cannot fail, checked above
+        exp,
+    })
+}
+
+/// Multiply two prices
+///
+/// The product is computed exactly in `u128` with the sum of both exponents. Decimals are then
+/// removed until the value fits in a `u64`.
+pub fn mul_prices(a: &Price, b: &Price) -> ScopeResult<Price> {
+    let mut value = u128::from(a.value) * u128::from(b.value);
+    let mut exp = a.exp.checked_add(b.exp).ok_or(ScopeError::MathOverflow)?;
+    while value > u128::from(u64::MAX) {
+        if exp == 0 {
+            msg!("Price product does not fit in a u64");
+            return Err(ScopeError::MathOverflow);
+        }
+        // Loss of precision here is expected.
+        value /= 10;
+        exp -= 1;
+    }
+    Ok(Price {
+        value: value.try_into().unwrap(), // Cannot fail, checked above
+        exp,
+    })
+}
+
+/// Number of decimals targeted for the result of a division
+const QUOTIENT_EXPONENT: u64 = 18;
+
+/// Divide two prices
+///
+/// The numerator is scaled up in `u128` before the division so small ratios don't truncate
+/// to zero. The result has up to `QUOTIENT_EXPONENT` decimals, less if needed to fit in a `u64`.
+pub fn div_prices(a: &Price, b: &Price) -> ScopeResult<Price> {
+    if b.value == 0 {
+        msg!("Price division by zero");
+        return Err(ScopeError::PriceNotValid);
+    }
+    // Scale needed for the result to have `QUOTIENT_EXPONENT` decimals, and the minimum
+    // scale for the result exponent to be positive
+    let target_scale = QUOTIENT_EXPONENT
+        .checked_add(b.exp)
+        .ok_or(ScopeError::MathOverflow)?
+        .saturating_sub(a.exp);
+    let min_scale = b.exp.saturating_sub(a.exp);
+
+    let mut numerator = u128::from(a.value);
+    let mut scale = 0;
+    while scale < target_scale {
+        match numerator.checked_mul(10) {
+            Some(scaled) => numerator = scaled,
+            None => break,
+        }
+        scale += 1;
+    }
+    if scale < min_scale {
+        msg!("Price division cannot be represented");
+        return Err(ScopeError::MathOverflow);
+    }
+
+    let mut value = numerator / u128::from(b.value);
+    // Cannot underflow, `scale >= b.exp - a.exp`
+    let mut exp = a.exp + scale - b.exp;
+    while value > u128::from(u64::MAX) {
+        if exp == 0 {
+            msg!("Price quotient does not fit in a u64");
+            return Err(ScopeError::MathOverflow);
+        }
+        // Loss of precision here is expected.
+        value /= 10;
+        exp -= 1;
+    }
+    Ok(Price {
+        value: value.try_into().unwrap(), // Cannot fail, checked above
+        exp,
+    })
+}
+
+/// Inverse of a price (`1 / price`), see [`div_prices`]
+pub fn inv_price(price: &Price) -> ScopeResult<Price> {
+    div_prices(&Price { value: 1, exp: 0 }, price)
+}
+
+/// Integer square root, rounded down
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method, starting from a power of 2 above the root so it decreases to the result
+    let bits = 128 - n.leading_zeros();
+    let mut x = 1_u128 << ((bits + 1) / 2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn price(value: u64, exp: u64) -> Price {
+        Price { value, exp }
+    }
+
+    #[test]
+    fn cmp_same_exp() {
+        assert_eq!(cmp_prices(&price(1, 6), &price(2, 6)), Ordering::Less);
+        assert_eq!(cmp_prices(&price(2, 6), &price(2, 6)), Ordering::Equal);
+        assert_eq!(cmp_prices(&price(3, 6), &price(2, 6)), Ordering::Greater);
+    }
+
+    #[test]
+    fn cmp_different_exp() {
+        // 1.0 vs 1.000000
+        assert_eq!(
+            cmp_prices(&price(1, 0), &price(1_000_000, 6)),
+            Ordering::Equal
+        );
+        // 1.01 vs 1.000000
+        assert_eq!(
+            cmp_prices(&price(101, 2), &price(1_000_000, 6)),
+            Ordering::Greater
+        );
+        // 0.999999 vs 1.00
+        assert_eq!(
+            cmp_prices(&price(999_999, 6), &price(100, 2)),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn cmp_scaling_overflow() {
+        // u64::MAX with 0 decimals scaled by 10^40 overflows u128
+        assert_eq!(
+            cmp_prices(&price(u64::MAX, 0), &price(1, 40)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            cmp_prices(&price(1, 40), &price(u64::MAX, 0)),
+            Ordering::Less
+        );
+        assert_eq!(cmp_prices(&price(0, 0), &price(1, 40)), Ordering::Less);
+        assert_eq!(cmp_prices(&price(1, 40), &price(0, 0)), Ordering::Greater);
+    }
+
+    #[test]
+    fn deviation_same_exp() {
+        assert_eq!(deviation_bps(&price(105, 2), &price(100, 2)), Some(500));
+        assert_eq!(deviation_bps(&price(95, 2), &price(100, 2)), Some(500));
+        assert_eq!(deviation_bps(&price(100, 2), &price(100, 2)), Some(0));
+        // 0.99% is rounded down
+        assert_eq!(
+            deviation_bps(&price(10_099, 4), &price(10_000, 4)),
+            Some(99)
+        );
+    }
+
+    #[test]
+    fn deviation_different_exp() {
+        // 1.02 vs 1.000000
+        assert_eq!(
+            deviation_bps(&price(102, 2), &price(1_000_000, 6)),
+            Some(200)
+        );
+        // 0.5 vs 1.00
+        assert_eq!(deviation_bps(&price(5, 1), &price(100, 2)), Some(5_000));
+        // Doubling and more
+        assert_eq!(deviation_bps(&price(3, 0), &price(1, 0)), Some(20_000));
+    }
+
+    #[test]
+    fn deviation_undefined() {
+        assert_eq!(deviation_bps(&price(1, 0), &price(0, 0)), None);
+        assert_eq!(deviation_bps(&price(u64::MAX, 0), &price(1, 40)), None);
+    }
+
+    #[test]
+    fn price_above_cap_is_clamped() {
+        let cap = price(100, 2); // 1.00
+        assert_eq!(apply_hard_cap(price(1_020_000, 6), &cap), cap);
+        assert_eq!(apply_hard_cap(price(2, 0), &cap), cap);
+    }
+
+    #[test]
+    fn price_below_or_at_cap_is_unchanged() {
+        let cap = price(100, 2); // 1.00
+        let below = price(999_000, 6);
+        assert_eq!(apply_hard_cap(below, &cap), below);
+        let at_cap = price(1_000_000, 6);
+        assert_eq!(apply_hard_cap(at_cap, &cap), at_cap);
+    }
+
+    #[test]
+    fn zero_cap_is_disabled() {
+        let p = price(u64::MAX, 0);
+        assert_eq!(apply_hard_cap(p, &Price::default()), p);
+    }
+
+    #[test]
+    fn rescale_increase_exp() {
+        // 64622.369 BTC/USD from 3 to 8 decimals
+        assert_eq!(
+            rescale(price(64_622_369, 3), 8).unwrap(),
+            price(6_462_236_900_000, 8)
+        );
+        assert_eq!(rescale(price(0, 0), 18).unwrap(), price(0, 18));
+    }
+
+    #[test]
+    fn rescale_decrease_exp() {
+        // 64622.36900000 BTC/USD from 8 to 2 decimals, extra decimals are truncated
+        assert_eq!(
+            rescale(price(6_462_236_912_345, 8), 2).unwrap(),
+            price(6_462_236, 2)
+        );
+        assert_eq!(rescale(price(123, 2), 2).unwrap(), price(123, 2));
+    }
+
+    #[test]
+    fn rescale_precision_loss() {
+        // 0.00001 BONK/USD with 2 decimals
+        assert_eq!(rescale(price(1_000, 8), 2), Err(ScopeError::PrecisionLoss));
+        assert_eq!(
+            rescale(price(u64::MAX, 40), 0),
+            Err(ScopeError::PrecisionLoss)
+        );
+        assert_eq!(rescale(price(0, 40), 0).unwrap(), price(0, 0));
+    }
+
+    #[test]
+    fn rescale_overflow_boundary() {
+        // u64::MAX = 18_446_744_073_709_551_615
+        assert_eq!(
+            rescale(price(1_844_674_407_370_955_161, 0), 1).unwrap(),
+            price(18_446_744_073_709_551_610, 1)
+        );
+        assert_eq!(
+            rescale(price(1_844_674_407_370_955_162, 0), 1),
+            Err(ScopeError::MathOverflow)
+        );
+        assert_eq!(rescale(price(u64::MAX, 0), 0).unwrap(), price(u64::MAX, 0));
+        assert_eq!(
+            rescale(price(u64::MAX, 0), 1),
+            Err(ScopeError::MathOverflow)
+        );
+        assert_eq!(rescale(price(1, 0), 19).unwrap(), price(10_u64.pow(19), 19));
+        assert_eq!(rescale(price(1, 0), 20), Err(ScopeError::MathOverflow));
+    }
+
+    #[test]
+    fn add_different_exp() {
+        // 1.5 + 0.25
+        assert_eq!(
+            add_prices(&price(15, 1), &price(25, 2)).unwrap(),
+            price(175, 2)
+        );
+        assert_eq!(
+            add_prices(&price(0, 0), &price(25, 2)).unwrap(),
+            price(25, 2)
+        );
+    }
+
+    #[test]
+    fn add_large_values() {
+        // Decimals are dropped to fit in a u64
+        assert_eq!(
+            add_prices(&price(u64::MAX, 2), &price(u64::MAX, 2)).unwrap(),
+            price(u64::MAX / 5, 1)
+        );
+        assert_eq!(
+            add_prices(&price(u64::MAX, 0), &price(1, 0)).unwrap_err(),
+            ScopeError::MathOverflow
+        );
+        assert_eq!(
+            add_prices(&price(u64::MAX, 0), &price(1, 40)).unwrap_err(),
+            ScopeError::MathOverflow
+        );
+    }
+
+    #[test]
+    fn mul_same_exp() {
+        // 2.00 * 1.50
+        assert_eq!(
+            mul_prices(&price(200, 2), &price(150, 2)).unwrap(),
+            price(30_000, 4)
+        );
+        assert_eq!(
+            mul_prices(&price(0, 6), &price(150, 2)).unwrap(),
+            price(0, 8)
+        );
+    }
+
+    #[test]
+    fn mul_different_exp() {
+        // 1.1 stSOL/SOL (15 decimals) * 20.81 SOL/USD (8 decimals) = 22.891 stSOL/USD
+        let st_sol_sol = price(1_100_000_000_000_000, 15);
+        let sol_usd = price(2_081_000_000, 8);
+        let st_sol_usd = mul_prices(&st_sol_sol, &sol_usd).unwrap();
+        assert_eq!(st_sol_usd, price(2_289_100_000_000_000_000, 17));
+        assert_eq!(cmp_prices(&st_sol_usd, &price(22_891, 3)), Ordering::Equal);
+        // Product is commutative
+        assert_eq!(mul_prices(&sol_usd, &st_sol_sol).unwrap(), st_sol_usd);
+
+        // 20.81 (8 decimals) * 2 (0 decimals)
+        assert_eq!(
+            mul_prices(&sol_usd, &price(2, 0)).unwrap(),
+            price(4_162_000_000, 8)
+        );
+    }
+
+    #[test]
+    fn mul_overflow() {
+        assert_eq!(
+            mul_prices(&price(u64::MAX, 0), &price(u64::MAX, 0)),
+            Err(ScopeError::MathOverflow)
+        );
+        assert_eq!(
+            mul_prices(&price(1, u64::MAX), &price(1, 1)),
+            Err(ScopeError::MathOverflow)
+        );
+        // Enough decimals to scale down
+        assert_eq!(
+            mul_prices(&price(u64::MAX, 20), &price(u64::MAX, 20))
+                .unwrap()
+                .exp,
+            20
+        );
+    }
+
+    #[test]
+    fn div_different_exp() {
+        // 22.891 stSOL/USD (17 decimals) / 20.81 SOL/USD (8 decimals) = 1.1 stSOL/SOL
+        let st_sol_usd = price(2_289_100_000_000_000_000, 17);
+        let sol_usd = price(2_081_000_000, 8);
+        let st_sol_sol = div_prices(&st_sol_usd, &sol_usd).unwrap();
+        assert_eq!(st_sol_sol, price(1_100_000_000_000_000_000, 18));
+
+        // 1 USDC/USD (6 decimals) / 20.81 SOL/USD (8 decimals) = 0.048053820...
+        let usdc_sol = div_prices(&price(1_000_000, 6), &sol_usd).unwrap();
+        assert_eq!(usdc_sol, price(48_053_820_278_712_157, 18));
+    }
+
+    #[test]
+    fn div_small_ratio_does_not_truncate() {
+        // 0.00001 BONK/USD / 60_000 BTC/USD
+        let bonk_btc = div_prices(&price(1_000, 8), &price(60_000, 0)).unwrap();
+        assert_eq!(bonk_btc, price(166_666_666, 18));
+        assert_ne!(bonk_btc.value, 0);
+    }
+
+    #[test]
+    fn div_large_ratio_reduces_exp() {
+        // 60_000 BTC/USD / 0.00001 BONK/USD = 6_000_000_000 BONK/BTC
+        let btc_bonk = div_prices(&price(60_000, 0), &price(1_000, 8)).unwrap();
+        assert_eq!(
+            cmp_prices(&btc_bonk, &price(6_000_000_000, 0)),
+            Ordering::Equal
+        );
+        assert!(btc_bonk.exp < 18);
+    }
+
+    #[test]
+    fn div_by_zero() {
+        assert_eq!(
+            div_prices(&price(1, 0), &price(0, 6)),
+            Err(ScopeError::PriceNotValid)
+        );
+        assert_eq!(inv_price(&price(0, 0)), Err(ScopeError::PriceNotValid));
+    }
+
+    #[test]
+    fn div_overflow() {
+        assert_eq!(
+            div_prices(&price(u64::MAX, 0), &price(1, 40)),
+            Err(ScopeError::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn integer_sqrt() {
+        for (n, root) in [
+            (0, 0),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (4, 2),
+            (15, 3),
+            (16, 4),
+            (17, 4),
+        ] {
+            assert_eq!(isqrt(n), root, "sqrt({n})");
+        }
+        let max = u128::from(u64::MAX);
+        assert_eq!(isqrt(max * max), max);
+        assert_eq!(isqrt(max * max - 1), max - 1);
+        assert_eq!(isqrt(u128::MAX), max);
+    }
+
+    #[test]
+    fn inverse() {
+        // 1 / 20.81 = 0.048053820...
+        assert_eq!(
+            inv_price(&price(2_081_000_000, 8)).unwrap(),
+            price(48_053_820_278_712_157, 18)
+        );
+        // 1 / 0.5 = 2
+        assert_eq!(
+            cmp_prices(&inv_price(&price(5, 1)).unwrap(), &price(2, 0)),
+            Ordering::Equal
+        );
+    }
+}
@@ -1,4 +1,8 @@
+pub mod clock;
+pub mod ema;
+pub mod math;
 pub mod scope_chain;
+pub mod twap;
 
 use std::cell::Ref;
 
@@ -477,6 +477,7 @@ mod test {
         let mut account = OraclePrices {
             oracle_mappings: Default::default(),
             prices: [zero_price; crate::MAX_ENTRIES],
+            ..bytemuck::Zeroable::zeroed()
         };
 
         for token in ScopeId::iter() {
@@ -0,0 +1,241 @@
+//! Time weighted average price computed from the refreshed prices
+//!
+//! Each refreshed price is pushed as a sample in the [`TwapBuffer`] of its token. The TWAP is
+//! recomputed over the last `window_size` samples: each sample price is weighted by the time
+//! elapsed until the next sample, so unevenly spaced refreshes are properly accounted for.
+//!
+//! Samples are expected to share the same exponent, if the exponent of the refreshed price
+//! changes the buffer is reset.
+
+use crate::{DatedPrice, OracleTwaps, Price, ScopeError, ScopeResult, TwapBuffer, TwapSample};
+
+/// Number of samples held by a buffer with the given window size
+pub fn window_capacity(window_size: u64) -> usize {
+    match usize::try_from(window_size) {
+        Ok(0) | Err(_) => crate::MAX_TWAP_SAMPLES,
+        Ok(window_size) => window_size.min(crate::MAX_TWAP_SAMPLES),
+    }
+}
+
+impl TwapBuffer {
+    /// Number of samples the buffer currently holds at most
+    pub fn capacity(&self) -> usize {
+        window_capacity(self.window_size)
+    }
+
+    /// Clear all samples and set a new window size (0 means [`crate::MAX_TWAP_SAMPLES`])
+    ///
+    /// The EMA is kept, see [`TwapBuffer::reset_ema`].
+    pub fn reset(&mut self, window_size: u64) {
+        *self = TwapBuffer {
+            window_size,
+            ema_half_life_slots: self.ema_half_life_slots,
+            ema: self.ema,
+            ..Default::default()
+        };
+    }
+
+    /// Get the `i`-th sample in chronological order
+    fn sample(&self, i: usize) -> &TwapSample {
+        let capacity = self.capacity();
+        let num_samples = self.num_samples as usize;
+        let first = (self.next_index as usize + capacity - num_samples) % capacity;
+        &self.samples[(first + i) % capacity]
+    }
+
+    fn last_sample_mut(&mut self) -> &mut TwapSample {
+        let capacity = self.capacity();
+        let last = (self.next_index as usize + capacity - 1) % capacity;
+        &mut self.samples[last]
+    }
+
+    /// Record a new refreshed price and update the TWAP and the EMA
+    ///
+    /// Samples older than the last recorded one are ignored. A sample with the same
+    /// timestamp as the last recorded one replaces it.
+    pub fn push_sample(&mut self, price: &DatedPrice) -> ScopeResult<()> {
+        self.update_ema(price);
+
+        if self.num_samples > 0 && price.price.exp != self.exp {
+            self.reset(self.window_size);
+        }
+
+        let sample = TwapSample {
+            value: price.price.value,
+            unix_timestamp: price.unix_timestamp,
+        };
+
+        if self.num_samples > 0 {
+            let last_sample = self.last_sample_mut();
+            if sample.unix_timestamp < last_sample.unix_timestamp {
+                return Ok(());
+            }
+            if sample.unix_timestamp == last_sample.unix_timestamp {
+                *last_sample = sample;
+                return self.update_twap(price);
+            }
+        }
+
+        let capacity = self.capacity();
+        self.samples[self.next_index as usize] = sample;
+        self.next_index = ((self.next_index as usize + 1) % capacity) as u64;
+        self.num_samples = (self.num_samples + 1).min(capacity as u64);
+        self.exp = price.price.exp;
+
+        self.update_twap(price)
+    }
+
+    fn update_twap(&mut self, price: &DatedPrice) -> ScopeResult<()> {
+        let value = self.compute_twap()?;
+        self.twap = DatedPrice {
+            price: Price {
+                value,
+                exp: self.exp,
+            },
+            last_updated_slot: price.last_updated_slot,
+            unix_timestamp: price.unix_timestamp,
+            index: price.index,
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Compute the TWAP over the recorded samples
+    ///
+    /// Each sample price is valid until the next sample. With a single sample (or all
+    /// samples at the same time) the TWAP is the last price.
+    pub fn compute_twap(&self) -> ScopeResult<u64> {
+        let num_samples = self.num_samples as usize;
+        if num_samples == 0 {
+            return Err(ScopeError::PriceNotValid);
+        }
+        let first = self.sample(0);
+        let last = self.sample(num_samples - 1);
+        let duration = last.unix_timestamp - first.unix_timestamp;
+        if duration == 0 {
+            return Ok(last.value);
+        }
+
+        let weighted_sum = (0..num_samples - 1)
+            .map(|i| (self.sample(i), self.sample(i + 1)))
+            .try_fold(0_u128, |acc, (sample, next)| {
+                let elapsed = u128::from(next.unix_timestamp - sample.unix_timestamp);
+                acc.checked_add(u128::from(sample.value) * elapsed)
+            })
+            .ok_or(ScopeError::MathOverflow)?;
+
+        u64::try_from(weighted_sum / u128::from(duration)).map_err(|_| ScopeError::IntegerOverflow)
+    }
+}
+
+impl OracleTwaps {
+    /// Get the last computed TWAP of a token
+    pub fn get_twap(&self, token: usize) -> ScopeResult<DatedPrice> {
+        let buffer = self.twaps.get(token).ok_or(ScopeError::BadTokenNb)?;
+        if buffer.num_samples == 0 {
+            return Err(ScopeError::PriceNotValid);
+        }
+        Ok(buffer.twap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dated_price(value: u64, unix_timestamp: u64) -> DatedPrice {
+        DatedPrice {
+            price: Price { value, exp: 6 },
+            unix_timestamp,
+            ..Default::default()
+        }
+    }
+
+    fn buffer_with(window_size: u64, samples: &[(u64, u64)]) -> TwapBuffer {
+        let mut buffer = TwapBuffer {
+            window_size,
+            ..Default::default()
+        };
+        for &(value, ts) in samples {
+            buffer.push_sample(&dated_price(value, ts)).unwrap();
+        }
+        buffer
+    }
+
+    #[test]
+    fn empty_buffer_has_no_twap() {
+        let buffer = TwapBuffer::default();
+        assert_eq!(buffer.compute_twap(), Err(ScopeError::PriceNotValid));
+    }
+
+    #[test]
+    fn single_sample_twap_is_the_price() {
+        let buffer = buffer_with(0, &[(100, 10)]);
+        assert_eq!(buffer.twap.price, Price { value: 100, exp: 6 });
+        assert_eq!(buffer.twap.unix_timestamp, 10);
+    }
+
+    #[test]
+    fn evenly_spaced_samples() {
+        // 100 during 10s, 200 during 10s, 300 is the last price
+        let buffer = buffer_with(0, &[(100, 0), (200, 10), (300, 20)]);
+        assert_eq!(buffer.twap.price.value, 150);
+    }
+
+    #[test]
+    fn unevenly_spaced_samples() {
+        // 100 during 30s, 400 during 10s, 1000 during 20s
+        // (100 * 30 + 400 * 10 + 1000 * 20) / 60 = 450
+        let buffer = buffer_with(0, &[(100, 0), (400, 30), (1000, 40), (0, 60)]);
+        assert_eq!(buffer.twap.price.value, 450);
+    }
+
+    #[test]
+    fn window_only_keeps_last_samples() {
+        // Window of 3 samples: (400, 30), (1000, 40), (0, 60)
+        // (400 * 10 + 1000 * 20) / 30 = 800
+        let buffer = buffer_with(3, &[(100, 0), (400, 30), (1000, 40), (0, 60)]);
+        assert_eq!(buffer.num_samples, 3);
+        assert_eq!(buffer.twap.price.value, 800);
+    }
+
+    #[test]
+    fn same_timestamp_replaces_last_sample() {
+        let buffer = buffer_with(0, &[(100, 0), (200, 10), (400, 10)]);
+        assert_eq!(buffer.num_samples, 2);
+        assert_eq!(buffer.sample(1).value, 400);
+        assert_eq!(buffer.twap.price.value, 100);
+    }
+
+    #[test]
+    fn older_sample_is_ignored() {
+        let buffer = buffer_with(0, &[(100, 0), (200, 10), (1000, 5)]);
+        assert_eq!(buffer.num_samples, 2);
+        assert_eq!(buffer.sample(1).value, 200);
+    }
+
+    #[test]
+    fn exponent_change_resets_buffer() {
+        let mut buffer = buffer_with(4, &[(100, 0), (200, 10)]);
+        let new_price = DatedPrice {
+            price: Price { value: 2, exp: 2 },
+            unix_timestamp: 20,
+            ..Default::default()
+        };
+        buffer.push_sample(&new_price).unwrap();
+        assert_eq!(buffer.num_samples, 1);
+        assert_eq!(buffer.window_size, 4);
+        assert_eq!(buffer.twap.price, new_price.price);
+    }
+
+    #[test]
+    fn reset_clears_samples() {
+        let mut buffer = buffer_with(0, &[(100, 0), (200, 10)]);
+        buffer.reset(8);
+        assert_eq!(buffer.num_samples, 0);
+        assert_eq!(buffer.capacity(), 8);
+        assert_eq!(buffer.twap, DatedPrice::default());
+        // The EMA is kept
+        assert_eq!(buffer.ema.price, Price { value: 200, exp: 6 });
+    }
+}
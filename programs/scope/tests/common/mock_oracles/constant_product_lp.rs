@@ -0,0 +1,52 @@
+use anchor_lang::prelude::Pubkey;
+use scope::oracles::constant_product_lp::TokenSwap;
+
+pub use super::meteora_dlmm::token_program_id;
+
+const SWAP_SIZE: usize = 324;
+const IS_INITIALIZED_OFFSET: usize = 1;
+const TOKEN_A_OFFSET: usize = 35;
+const CURVE_TYPE_OFFSET: usize = 291;
+
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+const MINT_SIZE: usize = 82;
+const MINT_SUPPLY_OFFSET: usize = 36;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_IS_INITIALIZED_OFFSET: usize = 45;
+
+/// Data of a SPL token-swap account, only the fields read by scope are set
+///
+/// `curve_type` is 0 for a constant product pool.
+pub fn get_token_swap_data(pool: &TokenSwap, curve_type: u8) -> Vec<u8> {
+    let mut data = vec![0_u8; SWAP_SIZE];
+    data[0] = 1; // Version
+    data[IS_INITIALIZED_OFFSET] = 1;
+    // The vaults and mints are stored contiguously in the order of the extra accounts
+    for (i, key) in pool.extra_accounts().iter().enumerate() {
+        let offset = TOKEN_A_OFFSET + 32 * i;
+        data[offset..offset + 32].copy_from_slice(key.as_ref());
+    }
+    data[CURVE_TYPE_OFFSET] = curve_type;
+    data
+}
+
+/// Data of a SPL token account holding `amount` of `mint`
+pub fn get_token_account_data(mint: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0_u8; TOKEN_ACCOUNT_SIZE];
+    data[TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + 32].copy_from_slice(mint.as_ref());
+    data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+        .copy_from_slice(&amount.to_le_bytes());
+    data
+}
+
+/// Data of a SPL token mint account with the given supply and decimals
+pub fn get_mint_data(supply: u64, decimals: u8) -> Vec<u8> {
+    let mut data = vec![0_u8; MINT_SIZE];
+    data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8].copy_from_slice(&supply.to_le_bytes());
+    data[MINT_DECIMALS_OFFSET] = decimals;
+    data[MINT_IS_INITIALIZED_OFFSET] = 1;
+    data
+}
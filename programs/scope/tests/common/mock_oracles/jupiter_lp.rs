@@ -0,0 +1,11 @@
+use anchor_lang::{AnchorSerialize, Discriminator};
+use scope::oracles::jupiter_lp::perpetuals::Pool;
+pub use scope::oracles::jupiter_lp::{lp_mint_address, JUPITER_PERPETUALS_PROGRAM_ID};
+
+/// Data of a Jupiter perpetuals `Pool` account, the fields not read by scope are left empty
+pub fn get_pool_data(pool: &Pool) -> Vec<u8> {
+    let mut data = Pool::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&pool.try_to_vec().unwrap());
+    data.extend_from_slice(&[0; 256]);
+    data
+}
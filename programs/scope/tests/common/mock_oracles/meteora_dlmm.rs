@@ -0,0 +1,43 @@
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::pubkey;
+
+pub use scope::oracles::meteora_dlmm::METEORA_DLMM_PROGRAM_ID;
+
+pub const fn token_program_id() -> Pubkey {
+    pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")
+}
+
+const LB_PAIR_DISCRIMINATOR: [u8; 8] = [33, 11, 49, 98, 181, 101, 177, 13];
+const LB_PAIR_SIZE: usize = 904;
+const ACTIVE_ID_OFFSET: usize = 76;
+const BIN_STEP_OFFSET: usize = 80;
+const TOKEN_X_MINT_OFFSET: usize = 88;
+const TOKEN_Y_MINT_OFFSET: usize = 120;
+
+const MINT_SIZE: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_IS_INITIALIZED_OFFSET: usize = 45;
+
+/// Data of a `LbPair` account, only the fields read by scope are set
+pub fn get_lb_pair_data(
+    active_id: i32,
+    bin_step: u16,
+    token_x_mint: &Pubkey,
+    token_y_mint: &Pubkey,
+) -> Vec<u8> {
+    let mut data = vec![0_u8; LB_PAIR_SIZE];
+    data[..8].copy_from_slice(&LB_PAIR_DISCRIMINATOR);
+    data[ACTIVE_ID_OFFSET..ACTIVE_ID_OFFSET + 4].copy_from_slice(&active_id.to_le_bytes());
+    data[BIN_STEP_OFFSET..BIN_STEP_OFFSET + 2].copy_from_slice(&bin_step.to_le_bytes());
+    data[TOKEN_X_MINT_OFFSET..TOKEN_X_MINT_OFFSET + 32].copy_from_slice(token_x_mint.as_ref());
+    data[TOKEN_Y_MINT_OFFSET..TOKEN_Y_MINT_OFFSET + 32].copy_from_slice(token_y_mint.as_ref());
+    data
+}
+
+/// Data of a SPL token mint account with the given decimals
+pub fn get_mint_data(decimals: u8) -> Vec<u8> {
+    let mut data = vec![0_u8; MINT_SIZE];
+    data[MINT_DECIMALS_OFFSET] = decimals;
+    data[MINT_IS_INITIALIZED_OFFSET] = 1;
+    data
+}
@@ -5,10 +5,19 @@ use solana_program::pubkey::Pubkey;
 use super::types::{OracleConf, TestContext};
 use crate::common::types::{ScopeFeedDefinition, TestOracleType};
 
+pub mod constant_product_lp;
+pub mod jupiter_lp;
 #[cfg(feature = "yvaults")]
 mod ktoken;
+pub mod meteora_dlmm;
 pub mod pyth;
+pub mod pyth_lazer;
+pub mod redstone;
+pub mod saber_stable_swap;
+pub mod share_price;
+pub mod spl_stake;
 pub mod switchboard_v2;
+pub mod whirlpool;
 
 #[async_recursion] // kTokens recursively create underlying token mappings
 pub async fn set_price(
@@ -20,7 +29,9 @@ pub async fn set_price(
     let clock = ctx.get_clock().await;
     let (oracle_data, owner, additional_accs): (Vec<u8>, Pubkey, Vec<(Pubkey, Pubkey, Vec<u8>)>) =
         match conf.price_type {
-            TestOracleType::Pyth => (
+            TestOracleType::Pyth
+            | TestOracleType::PythConservativeLower
+            | TestOracleType::PythConservativeUpper => (
                 pyth::get_account_data_for_price(price, &clock),
                 pyth::id(),
                 vec![],
@@ -30,11 +41,41 @@ pub async fn set_price(
                 switchboard_v2::id(),
                 vec![],
             ),
+            TestOracleType::RedStone => (
+                redstone::get_account_data_for_price(price, &clock),
+                redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID,
+                vec![],
+            ),
             #[cfg(feature = "yvaults")]
             TestOracleType::KToken(dex) => {
                 use crate::common::mock_oracles::ktoken;
                 ktoken::get_ktoken_price_accounts(ctx, _feed, dex, price, &clock).await
             }
+            // The mapping is a placeholder, the sources are set as pyth and switchboard v2
+            TestOracleType::MostRecentOf(first, second) => {
+                ctx.set_account(
+                    &first,
+                    pyth::get_account_data_for_price(price, &clock),
+                    &pyth::id(),
+                );
+                ctx.set_account(
+                    &second,
+                    switchboard_v2::get_account_data_for_price(price, &clock),
+                    &switchboard_v2::id(),
+                );
+                return;
+            }
+            // The price is stored in the mapping, no account to create
+            TestOracleType::FixedPrice
+            | TestOracleType::ScopeChainProduct
+            | TestOracleType::ScopeChainQuotient
+            | TestOracleType::ScopeChainInverse
+            | TestOracleType::ScopeMedian
+            | TestOracleType::CappedFloored
+            | TestOracleType::ScopeTwap
+            | TestOracleType::Discounted
+            | TestOracleType::Alias
+            | TestOracleType::IndexWeighted => return,
             _ => todo!("Implement other oracle types"),
         };
     additional_accs
@@ -1,13 +1,64 @@
 use anchor_lang::prelude::{Clock, Pubkey};
-use scope::Price;
-use solana_sdk::pubkey;
+use scope::{oracles::pyth::SourceStatus, Price};
 
 pub const fn id() -> Pubkey {
-    // It does not matter what the pubkey is
-    pubkey!("Pyth111111111111111111111111111111111111111")
+    scope::oracles::pyth::PYTH_PROGRAM_ID
 }
 
 pub fn get_account_data_for_price(price: &Price, clock: &Clock) -> Vec<u8> {
+    get_account_data_for_price_and_product(price, clock, &Pubkey::default())
+}
+
+/// Price account data referencing the given `product` account
+pub fn get_account_data_for_price_and_product(
+    price: &Price,
+    clock: &Clock,
+    product: &Pubkey,
+) -> Vec<u8> {
+    get_account_data(price, 0, 0, SourceStatus::Trading, clock, product)
+}
+
+/// Price account data with a confidence interval of `conf` (same exponent as the price)
+pub fn get_account_data_for_price_and_conf(price: &Price, conf: u64, clock: &Clock) -> Vec<u8> {
+    get_account_data(
+        price,
+        conf,
+        0,
+        SourceStatus::Trading,
+        clock,
+        &Pubkey::default(),
+    )
+}
+
+/// Price account data with an aggregate price published at `timestamp`
+pub fn get_account_data_for_price_at(price: &Price, timestamp: i64, clock: &Clock) -> Vec<u8> {
+    get_account_data(
+        price,
+        0,
+        timestamp,
+        SourceStatus::Trading,
+        clock,
+        &Pubkey::default(),
+    )
+}
+
+/// Price account data with the aggregate price in the given `status`
+pub fn get_account_data_for_price_with_status(
+    price: &Price,
+    status: SourceStatus,
+    clock: &Clock,
+) -> Vec<u8> {
+    get_account_data(price, 0, 0, status, clock, &Pubkey::default())
+}
+
+fn get_account_data(
+    price: &Price,
+    conf: u64,
+    timestamp: i64,
+    status: SourceStatus,
+    clock: &Clock,
+    product: &Pubkey,
+) -> Vec<u8> {
     let int_price = price.value as i64;
     let expo = -(price.exp as i32);
     pyth_tools::Price {
@@ -18,14 +69,18 @@ pub fn get_account_data_for_price(price: &Price, clock: &Clock) -> Vec<u8> {
         expo,
         valid_slot: clock.slot,
         last_slot: clock.slot,
+        timestamp,
         num_qt: 3,
         agg: pyth_tools::PriceInfo {
             price: int_price,
-            conf: 0,
-            status: pyth_tools::PriceStatus::Trading,
+            conf,
+            status: status.into(),
             corp_act: pyth_tools::CorpAction::NoCorpAct,
             pub_slot: clock.slot,
         },
+        prod: pyth_tools::AccKey {
+            val: product.to_bytes(),
+        },
         ..Default::default()
     }
     .as_bytes()
@@ -93,6 +148,18 @@ mod pyth_tools {
         Volatility,
     }
 
+    impl From<scope::oracles::pyth::SourceStatus> for PriceStatus {
+        fn from(status: scope::oracles::pyth::SourceStatus) -> Self {
+            use scope::oracles::pyth::SourceStatus;
+            match status {
+                SourceStatus::Unknown => PriceStatus::Unknown,
+                SourceStatus::Trading => PriceStatus::Trading,
+                SourceStatus::Halted => PriceStatus::Halted,
+                SourceStatus::Auction => PriceStatus::Auction,
+            }
+        }
+    }
+
     impl From<PriceStatus> for u8 {
         fn from(price: PriceStatus) -> Self {
             match price {
@@ -132,7 +199,7 @@ mod pyth_tools {
         pub valid_slot: u64,       // Valid slot-time of agg. price
         pub twap: Ema,             // Time-weighted average price
         pub twac: Ema,             // Time-weighted average confidence interval
-        pub drv1: i64,             // Space for future derived values
+        pub timestamp: i64,        // Time of the aggregate price
         pub drv2: i64,             // Space for future derived values
         pub prod: AccKey,          // Product account key
         pub next: AccKey,          // Next Price account in linked list
@@ -0,0 +1,64 @@
+use scope::{
+    oracles::pyth_lazer::{PAYLOAD_FORMAT_MAGIC, SOLANA_FORMAT_MAGIC},
+    Price,
+};
+use solana_program::{ed25519_program, instruction::Instruction};
+use solana_sdk::signature::{Keypair, Signer};
+
+const PROPERTY_PRICE: u8 = 0;
+const PROPERTY_EXPONENT: u8 = 4;
+
+/// Lazer payload with a single feed update of `price` at `timestamp` (in seconds)
+pub fn payload(feed_id: u32, price: &Price, timestamp: u64) -> Vec<u8> {
+    let mut payload = PAYLOAD_FORMAT_MAGIC.to_le_bytes().to_vec();
+    payload.extend_from_slice(&(timestamp * 1_000_000).to_le_bytes());
+    // Channel and number of feeds
+    payload.extend_from_slice(&[1, 1]);
+    payload.extend_from_slice(&feed_id.to_le_bytes());
+    payload.extend_from_slice(&[2, PROPERTY_PRICE]);
+    payload.extend_from_slice(&price.value.to_le_bytes());
+    payload.push(PROPERTY_EXPONENT);
+    payload.extend_from_slice(&(-i16::try_from(price.exp).unwrap()).to_le_bytes());
+    payload
+}
+
+/// Data of the account holding `payload` signed by `signer`
+pub fn get_account_data_for_payload(signer: &Keypair, payload: &[u8]) -> Vec<u8> {
+    let signature = signer.sign_message(payload);
+    let mut data = SOLANA_FORMAT_MAGIC.to_le_bytes().to_vec();
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(&u16::try_from(payload.len()).unwrap().to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Ed25519 program instruction verifying the signature of `payload` by `signer`
+pub fn ed25519_instruction(signer: &Keypair, payload: &[u8]) -> Instruction {
+    let signature = signer.sign_message(payload);
+    // Header and offsets of a single signature
+    let signer_offset: u16 = 2 + 14;
+    let signature_offset = signer_offset + 32;
+    let message_offset = signature_offset + 64;
+    let offsets = [
+        signature_offset,
+        u16::MAX,
+        signer_offset,
+        u16::MAX,
+        message_offset,
+        u16::try_from(payload.len()).unwrap(),
+        u16::MAX,
+    ];
+    let mut data = vec![1, 0];
+    for offset in offsets {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(payload);
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
@@ -0,0 +1,33 @@
+use anchor_lang::AnchorSerialize;
+pub use scope::oracles::redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID;
+use scope::{
+    oracles::redstone::{PriceData, PRICE_DATA_DISCRIMINATOR},
+    Price,
+};
+use solana_program::clock::Clock;
+
+/// Data of a RedStone `PriceData` account signed by 3 signers out of a threshold of 3
+///
+/// `timestamp` is the timestamp of the signed data package in seconds, the value is written at
+/// the current slot.
+pub fn get_account_data_for_price_at(price: &Price, timestamp: u64, clock: &Clock) -> Vec<u8> {
+    let mut value = [0_u8; 32];
+    value[24..].copy_from_slice(&price.value.to_be_bytes());
+    let price_data = PriceData {
+        value,
+        timestamp: timestamp * 1000,
+        write_timestamp: Some(u64::try_from(clock.unix_timestamp).unwrap() * 1000),
+        write_slot: clock.slot,
+        decimals: price.exp.try_into().unwrap(),
+        signer_count: 3,
+        signer_count_threshold: 3,
+        ..Default::default()
+    };
+    let mut data = PRICE_DATA_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&price_data.try_to_vec().unwrap());
+    data
+}
+
+pub fn get_account_data_for_price(price: &Price, clock: &Clock) -> Vec<u8> {
+    get_account_data_for_price_at(price, clock.unix_timestamp.try_into().unwrap(), clock)
+}
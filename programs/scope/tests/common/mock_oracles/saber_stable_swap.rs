@@ -0,0 +1,32 @@
+use scope::oracles::saber_stable_swap::SwapInfo;
+pub use scope::oracles::saber_stable_swap::SABER_STABLE_SWAP_PROGRAM_ID;
+
+const SWAP_INFO_SIZE: usize = 395;
+const IS_INITIALIZED_OFFSET: usize = 0;
+const INITIAL_AMP_FACTOR_OFFSET: usize = 3;
+const TOKEN_A_OFFSET: usize = 107;
+
+/// Data of a Saber `SwapInfo` account, only the fields read by scope are set
+pub fn get_swap_info_data(swap: &SwapInfo) -> Vec<u8> {
+    let mut data = vec![0_u8; SWAP_INFO_SIZE];
+    data[IS_INITIALIZED_OFFSET] = 1;
+    // Amp factors and ramp timestamps are stored contiguously
+    for (i, field) in [
+        swap.initial_amp_factor,
+        swap.target_amp_factor,
+        swap.start_ramp_ts as u64,
+        swap.stop_ramp_ts as u64,
+    ]
+    .iter()
+    .enumerate()
+    {
+        let offset = INITIAL_AMP_FACTOR_OFFSET + 8 * i;
+        data[offset..offset + 8].copy_from_slice(&field.to_le_bytes());
+    }
+    // The vaults and mints are stored contiguously in the order of the extra accounts
+    for (i, key) in swap.extra_accounts().iter().enumerate() {
+        let offset = TOKEN_A_OFFSET + 32 * i;
+        data[offset..offset + 32].copy_from_slice(key.as_ref());
+    }
+    data
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::Pubkey;
+
+use super::constant_product_lp::{get_mint_data, get_token_account_data};
+pub use super::meteora_dlmm::token_program_id;
+
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const MINT_AUTHORITY_OFFSET: usize = 0;
+
+/// Share mint of a vault in tests
+///
+/// The vault does not reference its share mint, this derivation is only a test convention used
+/// to find the extra accounts of a refresh.
+pub fn share_mint_address(vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"share_mint", vault.as_ref()], &token_program_id()).0
+}
+
+/// Data of a vault token account owned by `owner` holding `amount` of `mint`
+pub fn get_vault_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = get_token_account_data(mint, amount);
+    data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32]
+        .copy_from_slice(owner.as_ref());
+    data
+}
+
+/// Data of a share mint minted by `mint_authority`
+pub fn get_share_mint_data(mint_authority: &Pubkey, supply: u64, decimals: u8) -> Vec<u8> {
+    let mut data = get_mint_data(supply, decimals);
+    data[MINT_AUTHORITY_OFFSET..MINT_AUTHORITY_OFFSET + 4].copy_from_slice(&1_u32.to_le_bytes());
+    data[MINT_AUTHORITY_OFFSET + 4..MINT_AUTHORITY_OFFSET + 36]
+        .copy_from_slice(mint_authority.as_ref());
+    data
+}
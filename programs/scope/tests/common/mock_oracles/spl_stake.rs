@@ -0,0 +1,34 @@
+use anchor_lang::AnchorSerialize;
+use scope::oracles::spl_stake::spl_stake_pool::{
+    validator_list::{HEADER_LEN, VALIDATOR_LAST_UPDATE_EPOCH_OFFSET, VALIDATOR_STAKE_INFO_LEN},
+    AccountType, StakePool,
+};
+pub use scope::oracles::spl_stake::{
+    SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID, SPL_STAKE_POOL_PROGRAM_ID,
+};
+
+/// Size of a stake pool account as allocated by the stake pool programs
+const STAKE_POOL_ACCOUNT_SIZE: usize = 611;
+
+/// Data of a `StakePool` account, padded to the size allocated on chain
+pub fn get_stake_pool_data(stake_pool: &StakePool) -> Vec<u8> {
+    let mut data = stake_pool.try_to_vec().unwrap();
+    data.resize(STAKE_POOL_ACCOUNT_SIZE, 0);
+    data
+}
+
+/// Data of a `ValidatorList` account whose validators were updated at the given epochs, the
+/// other fields of the validators are left empty
+pub fn get_validator_list_data(last_update_epochs: &[u64]) -> Vec<u8> {
+    let mut data = vec![0; HEADER_LEN];
+    data[0] = AccountType::ValidatorList as u8;
+    data[1..5].copy_from_slice(&(last_update_epochs.len() as u32).to_le_bytes());
+    data[5..HEADER_LEN].copy_from_slice(&(last_update_epochs.len() as u32).to_le_bytes());
+    for epoch in last_update_epochs {
+        let mut validator = [0; VALIDATOR_STAKE_INFO_LEN];
+        validator[VALIDATOR_LAST_UPDATE_EPOCH_OFFSET..VALIDATOR_LAST_UPDATE_EPOCH_OFFSET + 8]
+            .copy_from_slice(&epoch.to_le_bytes());
+        data.extend_from_slice(&validator);
+    }
+    data
+}
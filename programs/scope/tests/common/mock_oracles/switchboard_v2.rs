@@ -1,12 +1,51 @@
 use anchor_lang::prelude::{Clock, Pubkey};
-use scope::Price;
-use solana_sdk::pubkey;
+use scope::{
+    oracles::switchboard_v2::switchboard::{AggregatorAccountData, SwitchboardDecimal},
+    Price,
+};
 
 pub const fn id() -> Pubkey {
-    // It does not matter what the pubkey is
-    pubkey!("Switchv211111111111111111111111111111111111")
+    scope::oracles::switchboard_v2::SWITCHBOARD_V2_PROGRAM_ID
 }
 
-pub fn get_account_data_for_price(_price: &Price, _clock: &Clock) -> Vec<u8> {
-    todo!("Implement switchboard prices");
+/// Same layout as written by the `initialize_switchboard_v2` instruction of the mock oracles
+pub fn get_account_data_for_price(price: &Price, clock: &Clock) -> Vec<u8> {
+    get_account_data_for_price_with_std_deviation(price, &Price { value: 0, exp: 1 }, clock)
+}
+
+/// Round with the given standard deviation of the oracle results
+pub fn get_account_data_for_price_with_std_deviation(
+    price: &Price,
+    std_deviation: &Price,
+    clock: &Clock,
+) -> Vec<u8> {
+    get_account_data(price, std_deviation, clock.unix_timestamp, clock)
+}
+
+/// Round opened at `timestamp`
+pub fn get_account_data_for_price_at(price: &Price, timestamp: i64, clock: &Clock) -> Vec<u8> {
+    get_account_data(price, &Price { value: 0, exp: 1 }, timestamp, clock)
+}
+
+fn get_account_data(
+    price: &Price,
+    std_deviation: &Price,
+    timestamp: i64,
+    clock: &Clock,
+) -> Vec<u8> {
+    let mut aggregator: AggregatorAccountData = bytemuck::Zeroable::zeroed();
+    aggregator.latest_confirmed_round.result = to_decimal(price);
+    aggregator.latest_confirmed_round.std_deviation = to_decimal(std_deviation);
+    aggregator.latest_confirmed_round.round_open_slot = clock.slot;
+    aggregator.latest_confirmed_round.round_open_timestamp = timestamp;
+    aggregator.latest_confirmed_round.num_success = 3;
+    aggregator.min_oracle_results = 3;
+
+    let mut data = AggregatorAccountData::discriminator().to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&aggregator));
+    data
+}
+
+fn to_decimal(price: &Price) -> SwitchboardDecimal {
+    SwitchboardDecimal::new(price.value.into(), price.exp.try_into().unwrap())
 }
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::Pubkey;
+
+pub use scope::oracles::whirlpool::WHIRLPOOL_PROGRAM_ID;
+
+const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+const WHIRLPOOL_SIZE: usize = 653;
+const SQRT_PRICE_OFFSET: usize = 65;
+const TOKEN_MINT_A_OFFSET: usize = 101;
+const TOKEN_MINT_B_OFFSET: usize = 181;
+
+/// Data of a `Whirlpool` account, only the fields read by scope are set
+pub fn get_whirlpool_data(
+    sqrt_price: u128,
+    token_mint_a: &Pubkey,
+    token_mint_b: &Pubkey,
+) -> Vec<u8> {
+    let mut data = vec![0_u8; WHIRLPOOL_SIZE];
+    data[..8].copy_from_slice(&WHIRLPOOL_DISCRIMINATOR);
+    data[SQRT_PRICE_OFFSET..SQRT_PRICE_OFFSET + 16].copy_from_slice(&sqrt_price.to_le_bytes());
+    data[TOKEN_MINT_A_OFFSET..TOKEN_MINT_A_OFFSET + 32].copy_from_slice(token_mint_a.as_ref());
+    data[TOKEN_MINT_B_OFFSET..TOKEN_MINT_B_OFFSET + 32].copy_from_slice(token_mint_b.as_ref());
+    data
+}
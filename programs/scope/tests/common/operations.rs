@@ -1,10 +1,17 @@
 use anchor_lang::{InstructionData, ToAccountMetas};
+use scope::{
+    IndexConstituent, MappingEntry, MaxSourceAgeOverride, OracleIndex, UpdateMappingConfigMode,
+};
 use solana_program::{
     clock::Clock,
-    instruction::Instruction,
+    instruction::{AccountMeta, Instruction},
     sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
 };
-use solana_sdk::signature::Signer;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
 
 use crate::common::{
     types,
@@ -17,6 +24,24 @@ pub async fn update_oracle_mapping(
     feed: &types::ScopeFeedDefinition,
     conf: &OracleConf,
 ) {
+    try_update_oracle_mapping(ctx, feed, conf).await.unwrap();
+}
+
+pub async fn try_update_oracle_mapping(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    conf: &OracleConf,
+) -> Result<(), BanksClientError> {
+    try_update_oracle_mapping_with_max_age(ctx, feed, conf, 0).await
+}
+
+/// Update the mapping of a token with a max age in slots of its source price
+pub async fn try_update_oracle_mapping_with_max_age(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    conf: &OracleConf,
+    price_max_age: u64,
+) -> Result<(), BanksClientError> {
     let accounts = scope::accounts::UpdateOracleMapping {
         admin: ctx.admin.pubkey(),
         configuration: feed.conf,
@@ -27,6 +52,197 @@ pub async fn update_oracle_mapping(
         feed_name: feed.feed_name.clone(),
         token: conf.token.try_into().unwrap(),
         price_type: conf.price_type.to_u8(),
+        price_max_age,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+pub async fn reset_oracle_mapping(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    token: u64,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::ResetOracleMapping {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+        oracle_prices: feed.prices,
+    };
+    let args = scope::instruction::ResetOracleMapping {
+        feed_name: feed.feed_name.clone(),
+        token,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+/// Close the accounts of a feed without TWAP account, sending their lamports to `destination`
+pub async fn close_feed(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    destination: &Pubkey,
+) -> Result<(), BanksClientError> {
+    let configuration: scope::Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+    let accounts = scope::accounts::CloseFeed {
+        admin: ctx.admin.pubkey(),
+        destination: *destination,
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+        oracle_prices: feed.prices,
+        tokens_metadata: configuration.tokens_metadata,
+        oracle_twaps: None,
+    };
+    let args = scope::instruction::CloseFeed {
+        feed_name: feed.feed_name.clone(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+/// Send `steps` times the migration of the accounts of the feed in the same transaction
+pub async fn migrate_feed(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    steps: usize,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::MigrateFeed {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+        oracle_prices: feed.prices,
+        system_program: solana_program::system_program::id(),
+    };
+    let args = scope::instruction::MigrateFeed {
+        feed_name: feed.feed_name.clone(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&vec![ix; steps]).await
+}
+
+pub async fn replace_all_mappings(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    confs: &[OracleConf],
+) -> Result<(), BanksClientError> {
+    let mut accounts = scope::accounts::ReplaceAllMappings {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        confs
+            .iter()
+            .map(|conf| AccountMeta::new_readonly(conf.pubkey, false)),
+    );
+    let args = scope::instruction::ReplaceAllMappings {
+        feed_name: feed.feed_name.clone(),
+        mappings: confs
+            .iter()
+            .map(|conf| MappingEntry {
+                token: conf.token.try_into().unwrap(),
+                price_type: conf.price_type.to_u8(),
+            })
+            .collect(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+/// Update the mapping of consecutive tokens, `confs` must be sorted by token
+pub async fn update_mapping_batch(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    confs: &[OracleConf],
+) -> Result<(), BanksClientError> {
+    let mut accounts = scope::accounts::UpdateMappingBatch {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        confs
+            .iter()
+            .map(|conf| AccountMeta::new_readonly(conf.pubkey, false)),
+    );
+    let args = scope::instruction::UpdateMappingBatch {
+        feed_name: feed.feed_name.clone(),
+        first_token: confs[0].token.try_into().unwrap(),
+        price_types: confs.iter().map(|conf| conf.price_type.to_u8()).collect(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+pub async fn set_all_max_source_ages(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    default_max_age: u64,
+    overrides: Vec<MaxSourceAgeOverride>,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::SetAllMaxSourceAges {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+    };
+    let args = scope::instruction::SetAllMaxSourceAges {
+        feed_name: feed.feed_name.clone(),
+        default_max_age,
+        overrides,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+/// Create an index account of the feed at `oracle_index`
+pub async fn initialize_oracle_index(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    oracle_index: &Pubkey,
+) {
+    ctx.set_account(
+        oracle_index,
+        vec![0; std::mem::size_of::<OracleIndex>() + 8],
+        &scope::ID,
+    );
+    let accounts = scope::accounts::InitializeOracleIndex {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_prices: feed.prices,
+        oracle_index: *oracle_index,
+    };
+    let args = scope::instruction::InitializeOracleIndex {
+        feed_name: feed.feed_name.clone(),
     };
     let ix = Instruction {
         program_id: scope::id(),
@@ -36,17 +252,190 @@ pub async fn update_oracle_mapping(
     ctx.send_transaction(&[ix]).await.unwrap();
 }
 
+pub async fn update_index_constituents(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    oracle_index: &Pubkey,
+    constituents: Vec<IndexConstituent>,
+    max_constituent_age_slots: u64,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::UpdateIndexConstituents {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_index: *oracle_index,
+    };
+    let args = scope::instruction::UpdateIndexConstituents {
+        feed_name: feed.feed_name.clone(),
+        constituents,
+        max_constituent_age_slots,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+/// Set the refresh authority of the feed, signed by `signer` as admin
+pub async fn set_refresh_authority(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    signer: &Keypair,
+    refresh_authority: Pubkey,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::SetRefreshAuthority {
+        admin: signer.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+    };
+    let args = scope::instruction::SetRefreshAuthority {
+        feed_name: feed.feed_name.clone(),
+        refresh_authority,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction_with_payer(&[ix], signer).await
+}
+
+/// Propose `pending_admin` as admin of the feed, signed by `signer` as admin
+pub async fn set_admin(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    signer: &Keypair,
+    pending_admin: Pubkey,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::SetAdmin {
+        admin: signer.pubkey(),
+        configuration: feed.conf,
+    };
+    let args = scope::instruction::SetAdmin {
+        feed_name: feed.feed_name.clone(),
+        pending_admin,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction_with_payer(&[ix], signer).await
+}
+
+/// Accept the admin role of the feed, signed by `signer` as pending admin
+pub async fn accept_admin(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    signer: &Keypair,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::AcceptAdmin {
+        pending_admin: signer.pubkey(),
+        configuration: feed.conf,
+    };
+    let args = scope::instruction::AcceptAdmin {
+        feed_name: feed.feed_name.clone(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction_with_payer(&[ix], signer).await
+}
+
+/// Set the max feed staleness of the feed, signed by the admin
+pub async fn set_max_feed_staleness(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    max_feed_staleness_slots: u64,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::SetMaxFeedStaleness {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_prices: feed.prices,
+    };
+    let args = scope::instruction::SetMaxFeedStaleness {
+        feed_name: feed.feed_name.clone(),
+        max_feed_staleness_slots,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+pub async fn update_mapping_config(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    token: usize,
+    mode: UpdateMappingConfigMode,
+    value: Vec<u8>,
+) {
+    try_update_mapping_config(ctx, feed, token, mode, value)
+        .await
+        .unwrap();
+}
+
+pub async fn try_update_mapping_config(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    token: usize,
+    mode: UpdateMappingConfigMode,
+    value: Vec<u8>,
+) -> Result<(), BanksClientError> {
+    let accounts = scope::accounts::UpdateMappingConfig {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+    };
+    let args = scope::instruction::UpdateMappingConfig {
+        feed_name: feed.feed_name.clone(),
+        token: token.try_into().unwrap(),
+        mode: mode.to_u64(),
+        value,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
 pub async fn refresh_price(
     ctx: &mut TestContext,
     feed: &types::ScopeFeedDefinition,
     conf: &OracleConf,
 ) {
+    try_refresh_price(ctx, feed, conf).await.unwrap();
+}
+
+pub async fn try_refresh_price(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    conf: &OracleConf,
+) -> Result<(), BanksClientError> {
+    let ix = refresh_price_ix(ctx, feed, conf).await;
+    ctx.send_transaction(&[ix]).await
+}
+
+pub async fn refresh_price_ix(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    conf: &OracleConf,
+) -> Instruction {
     let mut accounts = scope::accounts::RefreshOne {
         oracle_prices: feed.prices,
         oracle_mappings: feed.mapping,
         price_info: conf.pubkey,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     let mut refresh_accounts = utils::get_remaining_accounts(ctx, conf).await;
@@ -55,10 +444,100 @@ pub async fn refresh_price(
     let args = scope::instruction::RefreshOnePrice {
         token: conf.token.try_into().unwrap(),
     };
-    let ix = Instruction {
+    Instruction {
         program_id: scope::id(),
         accounts: accounts.to_account_metas(None),
         data: args.data(),
+    }
+}
+
+pub async fn refresh_price_list(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    confs: &[OracleConf],
+) -> Result<(), BanksClientError> {
+    let accounts = get_refresh_list_ix_accounts(ctx, feed, confs).await;
+    let args = scope::instruction::RefreshPriceList {
+        tokens: confs
+            .iter()
+            .map(|conf| conf.token.try_into().unwrap())
+            .collect(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
     };
-    ctx.send_transaction(&[ix]).await.unwrap();
+    ctx.send_transaction(&[ix]).await
+}
+
+pub async fn refresh_price_list_lenient(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    confs: &[OracleConf],
+) -> Result<(), BanksClientError> {
+    let accounts = get_refresh_list_ix_accounts(ctx, feed, confs).await;
+    let args = scope::instruction::RefreshPriceListLenient {
+        tokens: confs
+            .iter()
+            .map(|conf| conf.token.try_into().unwrap())
+            .collect(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+/// Refresh a list of prices, updating the TWAP and EMA of the given TWAP account
+pub async fn refresh_price_list_with_twaps(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    oracle_twaps: &Pubkey,
+    confs: &[OracleConf],
+) -> Result<(), BanksClientError> {
+    let accounts = refresh_list_ix_accounts(ctx, feed, Some(*oracle_twaps), confs).await;
+    let args = scope::instruction::RefreshPriceList {
+        tokens: confs
+            .iter()
+            .map(|conf| conf.token.try_into().unwrap())
+            .collect(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await
+}
+
+pub async fn get_refresh_list_ix_accounts(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    confs: &[OracleConf],
+) -> Vec<AccountMeta> {
+    refresh_list_ix_accounts(ctx, feed, None, confs).await
+}
+
+async fn refresh_list_ix_accounts(
+    ctx: &mut TestContext,
+    feed: &types::ScopeFeedDefinition,
+    oracle_twaps: Option<Pubkey>,
+    confs: &[OracleConf],
+) -> Vec<AccountMeta> {
+    let mut accounts = scope::accounts::RefreshList {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    for conf in confs {
+        accounts.append(&mut utils::get_refresh_list_accounts(ctx, conf).await);
+    }
+    accounts
 }
@@ -1,6 +1,6 @@
 use anchor_lang::prelude::AccountMeta;
 use anchor_lang::{
-    prelude::{Clock, Pubkey},
+    prelude::{Clock, EpochSchedule, Pubkey},
     Owner,
 };
 use solana_program::pubkey;
@@ -159,6 +159,49 @@ impl TestContext {
             .unwrap()
     }
 
+    /// Move the clock forward by the given number of seconds (and matching number of slots).
+    pub async fn fast_forward_seconds(&mut self, seconds: u64) {
+        let mut clock = self.get_clock().await;
+        // 400ms slots, at least one slot to always get a new bank
+        let target_slot = clock.slot + (seconds * 1000 / 400).max(1);
+        self.context.warp_to_slot(target_slot).unwrap();
+        clock.slot = target_slot;
+        clock.unix_timestamp += seconds as i64;
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn get_epoch_schedule(&mut self) -> EpochSchedule {
+        self.context
+            .banks_client
+            .get_sysvar::<EpochSchedule>()
+            .await
+            .unwrap()
+    }
+
+    /// Move the clock and slot to the given epoch of the epoch schedule, `seconds_in_epoch`
+    /// seconds after its first slot.
+    pub async fn warp_to_epoch(&mut self, epoch: u64, seconds_in_epoch: u64) {
+        let epoch_schedule = self.get_epoch_schedule().await;
+        let mut clock = self.get_clock().await;
+        // 400ms slots
+        let target_slot =
+            epoch_schedule.get_first_slot_in_epoch(epoch) + seconds_in_epoch * 1000 / 400;
+        self.context.warp_to_slot(target_slot).unwrap();
+        clock.unix_timestamp += ((target_slot - clock.slot) * 400 / 1000) as i64;
+        clock.slot = target_slot;
+        clock.epoch = epoch;
+        clock.epoch_start_timestamp = clock.unix_timestamp - seconds_in_epoch as i64;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Move the clock to the given epoch, started `seconds_in_epoch` seconds ago.
+    pub async fn set_epoch(&mut self, epoch: u64, seconds_in_epoch: u64) {
+        let mut clock = self.get_clock().await;
+        clock.epoch = epoch;
+        clock.epoch_start_timestamp = clock.unix_timestamp - seconds_in_epoch as i64;
+        self.context.set_sysvar(&clock);
+    }
+
     pub async fn get_now_timestamp(&mut self) -> u64 {
         let clock: Clock = self
             .context
@@ -179,6 +222,51 @@ impl TestContext {
         self.context.banks_client.process_transaction(tx).await
     }
 
+    /// Send the instructions and return the logs of the transaction
+    pub async fn send_transaction_with_logs(
+        &mut self,
+        ixs: &[Instruction],
+    ) -> Result<Vec<String>, BanksClientError> {
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.admin.pubkey()),
+            &[&self.admin],
+            self.context.banks_client.get_latest_blockhash().await?,
+        );
+        let res = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await?;
+        res.result?;
+        Ok(res
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default())
+    }
+
+    /// Simulate the instructions and return the data set with `set_return_data`
+    pub async fn simulate_transaction_return_data(
+        &mut self,
+        ixs: &[Instruction],
+    ) -> Result<Vec<u8>, BanksClientError> {
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.admin.pubkey()),
+            &[&self.admin],
+            self.context.banks_client.get_latest_blockhash().await?,
+        );
+        let res = self.context.banks_client.simulate_transaction(tx).await?;
+        if let Some(Err(err)) = res.result {
+            return Err(BanksClientError::TransactionError(err));
+        }
+        Ok(res
+            .simulation_details
+            .and_then(|details| details.return_data)
+            .map(|return_data| return_data.data)
+            .unwrap_or_default())
+    }
+
     pub async fn send_transaction_through_cpi(
         &mut self,
         ixs: &[Instruction],
@@ -42,6 +42,58 @@ pub enum TestOracleType {
     KToken(DEX),
     /// Pyth Exponentially-Weighted Moving Average
     PythEMA,
+    /// Constant price stored in the oracle mapping
+    FixedPrice,
+    /// Switchboard On-Demand pull feed
+    SwitchboardOnDemand,
+    /// Chainlink OCR2 feed
+    Chainlink,
+    /// Product of two other prices of the feed
+    ScopeChainProduct,
+    /// Quotient of two other prices of the feed
+    ScopeChainQuotient,
+    /// Inverse of another price of the feed
+    ScopeChainInverse,
+    /// Meteora DLMM pool price of token X in token Y
+    MeteoraDlmmXtoY,
+    /// Meteora DLMM pool price of token Y in token X
+    MeteoraDlmmYtoX,
+    /// Fair price of the LP token of a constant product pool
+    ConstantProductLp,
+    /// Virtual price of the LP token of a Saber stable swap pool
+    SaberStableSwapLp,
+    /// Price of the LP token of a Jupiter perpetuals pool (JLP)
+    JupiterLp,
+    /// Pyth price minus its confidence interval
+    PythConservativeLower,
+    /// Pyth price plus its confidence interval
+    PythConservativeUpper,
+    /// Index level computed from quantities of other prices of the feed
+    IndexWeighted,
+    /// RedStone price adapter push feed
+    RedStone,
+    /// Price of a share in the tokens held by its vault
+    SharePrice,
+    /// Most recent valid price of two sources (first, second)
+    MostRecentOf(Pubkey, Pubkey),
+    /// Median of two or three other prices of the feed
+    ScopeMedian,
+    /// Price of another token of the feed clamped between a floor and a cap
+    CappedFloored,
+    /// EMA of another price of the feed maintained in the TWAP account
+    ScopeTwap,
+    /// SPL Stake Pool token priced even when the pool update is late
+    SplStakeLenient,
+    /// Pyth Lazer update signed by a trusted signer
+    PythLazer,
+    /// Price of another token of the feed multiplied by a constant factor
+    Discounted,
+    /// Exact copy of another price of the feed
+    Alias,
+    /// Orca Whirlpool pool price of token A in token B
+    OrcaWhirlpoolAtoB,
+    /// Orca Whirlpool pool price of token B in token A
+    OrcaWhirlpoolBtoA,
 }
 
 impl TestOracleType {
@@ -61,6 +113,32 @@ impl Into<OracleType> for TestOracleType {
             TestOracleType::SplStake => OracleType::SplStake,
             TestOracleType::KToken(_) => OracleType::KToken,
             TestOracleType::PythEMA => OracleType::PythEMA,
+            TestOracleType::FixedPrice => OracleType::FixedPrice,
+            TestOracleType::SwitchboardOnDemand => OracleType::SwitchboardOnDemand,
+            TestOracleType::Chainlink => OracleType::Chainlink,
+            TestOracleType::ScopeChainProduct => OracleType::ScopeChainProduct,
+            TestOracleType::ScopeChainQuotient => OracleType::ScopeChainQuotient,
+            TestOracleType::ScopeChainInverse => OracleType::ScopeChainInverse,
+            TestOracleType::MeteoraDlmmXtoY => OracleType::MeteoraDlmmXtoY,
+            TestOracleType::MeteoraDlmmYtoX => OracleType::MeteoraDlmmYtoX,
+            TestOracleType::ConstantProductLp => OracleType::ConstantProductLp,
+            TestOracleType::SaberStableSwapLp => OracleType::SaberStableSwapLp,
+            TestOracleType::JupiterLp => OracleType::JupiterLp,
+            TestOracleType::PythConservativeLower => OracleType::PythConservativeLower,
+            TestOracleType::PythConservativeUpper => OracleType::PythConservativeUpper,
+            TestOracleType::IndexWeighted => OracleType::IndexWeighted,
+            TestOracleType::RedStone => OracleType::RedStone,
+            TestOracleType::SharePrice => OracleType::SharePrice,
+            TestOracleType::MostRecentOf(..) => OracleType::MostRecentOf,
+            TestOracleType::ScopeMedian => OracleType::ScopeMedian,
+            TestOracleType::CappedFloored => OracleType::CappedFloored,
+            TestOracleType::ScopeTwap => OracleType::ScopeTwap,
+            TestOracleType::SplStakeLenient => OracleType::SplStakeLenient,
+            TestOracleType::PythLazer => OracleType::PythLazer,
+            TestOracleType::Discounted => OracleType::Discounted,
+            TestOracleType::Alias => OracleType::Alias,
+            TestOracleType::OrcaWhirlpoolAtoB => OracleType::OrcaWhirlpoolAtoB,
+            TestOracleType::OrcaWhirlpoolBtoA => OracleType::OrcaWhirlpoolBtoA,
             TestOracleType::DeprecatedPlaceholder => {
                 panic!("DeprecatedPlaceholder is not a valid oracle type")
             }
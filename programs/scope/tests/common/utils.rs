@@ -1,13 +1,25 @@
 use std::fmt::Debug;
 
+use anchor_lang::Event;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use num_enum::TryFromPrimitive;
 use num_traits::cast::FromPrimitive;
-use scope::oracles::OracleType;
-use solana_program::instruction::{AccountMeta, InstructionError};
+use scope::oracles::{
+    constant_product_lp::TokenSwap, jupiter_lp, meteora_dlmm::LbPair, saber_stable_swap::SwapInfo,
+    spl_stake::spl_stake_pool::StakePool, whirlpool::Whirlpool, OracleType,
+};
+use solana_program::{
+    instruction::{AccountMeta, InstructionError},
+    pubkey::Pubkey,
+    sysvar::instructions::ID as SYSVAR_INSTRUCTIONS_ID,
+};
 use solana_program_test::BanksClientError;
 use solana_sdk::transaction::TransactionError;
 
-use crate::common::types::{OracleConf, TestContext};
+use crate::common::{
+    mock_oracles::share_price,
+    types::{OracleConf, TestContext, TestOracleType},
+};
 
 pub async fn get_refresh_list_accounts(
     ctx: &mut TestContext,
@@ -20,13 +32,72 @@ pub async fn get_refresh_list_accounts(
     accounts
 }
 
-pub async fn get_remaining_accounts(_ctx: &mut TestContext, conf: &OracleConf) -> Vec<AccountMeta> {
-    #[allow(unused_mut)]
+pub async fn get_remaining_accounts(ctx: &mut TestContext, conf: &OracleConf) -> Vec<AccountMeta> {
     let mut accounts: Vec<AccountMeta> = vec![];
     match conf.price_type.into() {
         #[cfg(feature = "yvaults")]
         OracleType::KToken => {
-            accounts.append(&mut ktokens::get_ktoken_remaining_accounts(_ctx, conf).await);
+            accounts.append(&mut ktokens::get_ktoken_remaining_accounts(ctx, conf).await);
+        }
+        OracleType::MeteoraDlmmXtoY | OracleType::MeteoraDlmmYtoX => {
+            let data = ctx.get_account_data(&conf.pubkey).await.unwrap();
+            let lb_pair = LbPair::from_account_data(&data).unwrap();
+            accounts.push(AccountMeta::new_readonly(lb_pair.token_x_mint, false));
+            accounts.push(AccountMeta::new_readonly(lb_pair.token_y_mint, false));
+        }
+        OracleType::OrcaWhirlpoolAtoB | OracleType::OrcaWhirlpoolBtoA => {
+            let data = ctx.get_account_data(&conf.pubkey).await.unwrap();
+            let whirlpool = Whirlpool::from_account_data(&data).unwrap();
+            accounts.push(AccountMeta::new_readonly(whirlpool.token_mint_a, false));
+            accounts.push(AccountMeta::new_readonly(whirlpool.token_mint_b, false));
+        }
+        OracleType::ConstantProductLp => {
+            let data = ctx.get_account_data(&conf.pubkey).await.unwrap();
+            let pool = TokenSwap::from_account_data(&data).unwrap();
+            accounts.extend(
+                pool.extra_accounts()
+                    .iter()
+                    .map(|key| AccountMeta::new_readonly(*key, false)),
+            );
+        }
+        OracleType::SaberStableSwapLp => {
+            let data = ctx.get_account_data(&conf.pubkey).await.unwrap();
+            let swap = SwapInfo::from_account_data(&data).unwrap();
+            accounts.extend(
+                swap.extra_accounts()
+                    .iter()
+                    .map(|key| AccountMeta::new_readonly(*key, false)),
+            );
+        }
+        OracleType::SplStake | OracleType::SplStakeLenient => {
+            let data = ctx.get_account_data(&conf.pubkey).await.unwrap();
+            let stake_pool = StakePool::from_account_data(&data).unwrap();
+            accounts.push(AccountMeta::new_readonly(stake_pool.validator_list, false));
+        }
+        OracleType::JupiterLp => {
+            accounts.push(AccountMeta::new_readonly(
+                jupiter_lp::lp_mint_address(&conf.pubkey),
+                false,
+            ));
+        }
+        OracleType::SharePrice => {
+            // The mint of the vault is the first field of the token account
+            let data = ctx.get_account_data(&conf.pubkey).await.unwrap();
+            let vault_mint = Pubkey::new_from_array(data[..32].try_into().unwrap());
+            accounts.push(AccountMeta::new_readonly(
+                share_price::share_mint_address(&conf.pubkey),
+                false,
+            ));
+            accounts.push(AccountMeta::new_readonly(vault_mint, false));
+        }
+        OracleType::PythLazer => {
+            accounts.push(AccountMeta::new_readonly(SYSVAR_INSTRUCTIONS_ID, false));
+        }
+        OracleType::MostRecentOf => {
+            if let TestOracleType::MostRecentOf(first, second) = conf.price_type {
+                accounts.push(AccountMeta::new_readonly(first, false));
+                accounts.push(AccountMeta::new_readonly(second, false));
+            }
         }
         #[cfg(not(feature = "yvaults"))]
         OracleType::KToken => {
@@ -64,6 +135,16 @@ mod ktokens {
     }
 }
 
+/// Decode the anchor events of type `T` emitted in the given transaction logs
+pub fn parse_events<T: Event>(logs: &[String]) -> Vec<T> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter(|data| data.len() >= 8 && data[..8] == T::discriminator())
+        .map(|data| T::try_from_slice(&data[8..]).unwrap())
+        .collect()
+}
+
 pub fn map_scope_error<T: Debug>(res: Result<T, BanksClientError>) -> scope::ScopeError {
     if let Err(BanksClientError::TransactionError(TransactionError::InstructionError(
         _y,
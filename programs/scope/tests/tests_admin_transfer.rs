@@ -0,0 +1,101 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use scope::{Configuration, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::signature::{Keypair, Signer};
+use types::*;
+
+use crate::utils::{map_anchor_error, map_scope_error, AnchorErrorCode};
+
+/// New funded keypair able to pay its own transactions
+async fn new_signer(ctx: &mut TestContext) -> Keypair {
+    let signer = Keypair::new();
+    ctx.clone_account(&ctx.admin.pubkey(), &signer.pubkey())
+        .await;
+    signer
+}
+
+// The admin role is transferred once the proposed admin accepts it
+#[tokio::test]
+async fn test_admin_transfer() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, Vec::new()).await;
+    let admin = ctx.admin.insecure_clone();
+    let new_admin = new_signer(&mut ctx).await;
+
+    operations::set_admin(&mut ctx, &feed, &admin, new_admin.pubkey())
+        .await
+        .unwrap();
+    let configuration: Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+    assert_eq!(configuration.admin, admin.pubkey());
+    assert_eq!(configuration.pending_admin, new_admin.pubkey());
+
+    // Still pending, the proposed admin has no rights yet
+    assert_eq!(
+        map_anchor_error(
+            operations::set_refresh_authority(&mut ctx, &feed, &new_admin, Pubkey::new_unique())
+                .await
+        ),
+        AnchorErrorCode::ConstraintHasOne
+    );
+
+    operations::accept_admin(&mut ctx, &feed, &new_admin)
+        .await
+        .unwrap();
+    let configuration: Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+    assert_eq!(configuration.admin, new_admin.pubkey());
+    assert_eq!(configuration.pending_admin, Pubkey::default());
+
+    operations::set_refresh_authority(&mut ctx, &feed, &new_admin, Pubkey::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        map_anchor_error(
+            operations::set_refresh_authority(&mut ctx, &feed, &admin, Pubkey::default()).await
+        ),
+        AnchorErrorCode::ConstraintHasOne
+    );
+}
+
+// Only the proposed admin can accept, and only while the proposal stands
+#[tokio::test]
+async fn test_admin_transfer_not_pending() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, Vec::new()).await;
+    let admin = ctx.admin.insecure_clone();
+    let new_admin = new_signer(&mut ctx).await;
+    let other = new_signer(&mut ctx).await;
+
+    // Nothing proposed
+    assert_eq!(
+        map_scope_error(operations::accept_admin(&mut ctx, &feed, &new_admin).await),
+        ScopeError::NotPendingAdmin
+    );
+
+    operations::set_admin(&mut ctx, &feed, &admin, new_admin.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(
+        map_scope_error(operations::accept_admin(&mut ctx, &feed, &other).await),
+        ScopeError::NotPendingAdmin
+    );
+    // Only the admin can propose
+    assert_eq!(
+        map_anchor_error(
+            operations::set_admin(&mut ctx, &feed, &new_admin, new_admin.pubkey()).await
+        ),
+        AnchorErrorCode::ConstraintHasOne
+    );
+
+    // A cancelled proposal cannot be accepted
+    ctx.fast_forward_seconds(10).await;
+    operations::set_admin(&mut ctx, &feed, &admin, Pubkey::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        map_scope_error(operations::accept_admin(&mut ctx, &feed, &new_admin).await),
+        ScopeError::NotPendingAdmin
+    );
+    let configuration: Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+    assert_eq!(configuration.admin, admin.pubkey());
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::*;
+use scope::{
+    oracles::{alias_price, fixed_price},
+    DatedPrice, OraclePrices, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const SOURCE_PRICE: Price = Price {
+    value: 1_234_567,
+    exp: 5,
+};
+
+fn source_conf() -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&SOURCE_PRICE),
+        token: 0,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn alias_conf(token: usize, source: u16) -> OracleConf {
+    OracleConf {
+        pubkey: alias_price::source_to_mapping_pubkey(source),
+        token,
+        price_type: TestOracleType::Alias,
+    }
+}
+
+// The alias is the source price as is, even when refreshed later
+#[tokio::test]
+async fn test_alias_copies_source_price() {
+    let alias = alias_conf(1, 0);
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source_conf(), alias]).await;
+
+    operations::refresh_price(&mut ctx, &feed, &source_conf()).await;
+    ctx.fast_forward_seconds(30).await;
+    operations::refresh_price(&mut ctx, &feed, &alias).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let source = data.prices[0];
+    assert_eq!(source.price, SOURCE_PRICE);
+    assert_eq!(
+        data.prices[alias.token],
+        DatedPrice {
+            index: alias.token.try_into().unwrap(),
+            ..source
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_alias_of_alias_is_rejected() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source_conf(), alias_conf(1, 0)]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &alias_conf(2, 1)).await
+        ),
+        ScopeError::ChainedAlias
+    );
+    // Several aliases of the same source are allowed
+    operations::update_oracle_mapping(&mut ctx, &feed, &alias_conf(2, 0)).await;
+}
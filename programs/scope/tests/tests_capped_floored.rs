@@ -0,0 +1,183 @@
+mod common;
+
+use common::*;
+use scope::{
+    oracles::{
+        capped_floored::{self, Bound, PriceBand},
+        fixed_price,
+    },
+    OraclePrices, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+// Band of a token pegged to 1 with a 5% tolerance
+const CAP: Price = Price { value: 105, exp: 2 };
+const FLOOR: Price = Price {
+    value: 950_000,
+    exp: 6,
+};
+
+fn fixed_price_conf(token: usize, price: &Price) -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(price),
+        token,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn band_conf(token: usize, band: &PriceBand) -> OracleConf {
+    OracleConf {
+        pubkey: band.to_mapping_pubkey(),
+        token,
+        price_type: TestOracleType::CappedFloored,
+    }
+}
+
+fn constant_band(source: u16) -> PriceBand {
+    PriceBand {
+        source,
+        cap: Some(Bound::Constant(CAP)),
+        floor: Some(Bound::Constant(FLOOR)),
+    }
+}
+
+async fn clamped_price(source_price: Price) -> (Price, u8) {
+    let source = fixed_price_conf(0, &source_price);
+    let clamped = band_conf(1, &constant_band(0));
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source, clamped]).await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[source, clamped])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let price = data.prices[clamped.token];
+    (price.price, capped_floored::clamping(&price))
+}
+
+#[tokio::test]
+async fn test_price_within_band() {
+    let source_price = Price {
+        value: 1_010,
+        exp: 3,
+    };
+    assert_eq!(
+        clamped_price(source_price).await,
+        (source_price, capped_floored::NOT_CLAMPED)
+    );
+}
+
+#[tokio::test]
+async fn test_price_clamped_to_cap() {
+    let source_price = Price {
+        value: 1_200_000_000,
+        exp: 9,
+    };
+    assert_eq!(
+        clamped_price(source_price).await,
+        (CAP, capped_floored::CLAMPED_TO_CAP)
+    );
+}
+
+#[tokio::test]
+async fn test_price_clamped_to_floor() {
+    let source_price = Price { value: 9, exp: 1 };
+    assert_eq!(
+        clamped_price(source_price).await,
+        (FLOOR, capped_floored::CLAMPED_TO_FLOOR)
+    );
+}
+
+#[tokio::test]
+async fn test_price_floored_by_another_token() {
+    let source = fixed_price_conf(0, &Price { value: 9, exp: 1 });
+    let floor = fixed_price_conf(1, &FLOOR);
+    let clamped = band_conf(
+        2,
+        &PriceBand {
+            source: 0,
+            cap: None,
+            floor: Some(Bound::Token(1)),
+        },
+    );
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source, floor, clamped]).await;
+
+    operations::refresh_price(&mut ctx, &feed, &floor).await;
+    ctx.fast_forward_seconds(10).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[source, clamped])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let clamped_price = data.prices[clamped.token];
+    assert_eq!(clamped_price.price, FLOOR);
+    assert_eq!(
+        capped_floored::clamping(&clamped_price),
+        capped_floored::CLAMPED_TO_FLOOR
+    );
+    // The clamped price is as old as the floor it was read with
+    let floor_price = data.prices[floor.token];
+    assert!(floor_price.last_updated_slot < data.prices[source.token].last_updated_slot);
+    assert_eq!(
+        clamped_price.last_updated_slot,
+        floor_price.last_updated_slot
+    );
+    assert_eq!(clamped_price.unix_timestamp, floor_price.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_cap_below_floor_is_rejected() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    let inverted = PriceBand {
+        source: 0,
+        cap: Some(Bound::Constant(FLOOR)),
+        floor: Some(Bound::Constant(CAP)),
+    };
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &band_conf(1, &inverted)).await
+        ),
+        ScopeError::InvalidPriceBand
+    );
+    // Self reference
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(
+                &mut ctx,
+                &feed,
+                &band_conf(1, &constant_band(1))
+            )
+            .await
+        ),
+        ScopeError::CyclicDerivedPrice
+    );
+}
+
+#[tokio::test]
+async fn test_token_cap_below_floor_fails_refresh() {
+    let source = fixed_price_conf(0, &Price { value: 1, exp: 0 });
+    let cap = fixed_price_conf(1, &Price { value: 9, exp: 1 });
+    let clamped = band_conf(
+        2,
+        &PriceBand {
+            source: 0,
+            cap: Some(Bound::Token(1)),
+            floor: Some(Bound::Constant(FLOOR)),
+        },
+    );
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source, cap, clamped]).await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[source, cap])
+        .await
+        .unwrap();
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &clamped).await),
+        ScopeError::InvalidPriceBand
+    );
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use anchor_lang::{prelude::Pubkey, InstructionData, ToAccountMetas};
+use common::*;
+use scope::{oracles::fixed_price, Configuration, Price, ScopeError};
+use solana_program::instruction::Instruction;
+use solana_program_test::tokio;
+use solana_sdk::signer::Signer;
+use types::*;
+
+use crate::{
+    common::utils::AnchorErrorCode,
+    utils::{map_anchor_error, map_scope_error},
+};
+
+fn fixed_price_conf() -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&Price { value: 100, exp: 2 }),
+        token: 0,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+// The lamports of all the accounts of the feed are sent to the destination
+#[tokio::test]
+async fn test_close_feed() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![fixed_price_conf()]).await;
+    operations::refresh_price(&mut ctx, &feed, &fixed_price_conf()).await;
+    let configuration: Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+    let feed_accounts = [
+        feed.conf,
+        feed.mapping,
+        feed.prices,
+        configuration.tokens_metadata,
+    ];
+    let mut feed_lamports = 0;
+    for account in &feed_accounts {
+        feed_lamports += ctx.get_account(account).await.unwrap().lamports;
+    }
+
+    let destination = Pubkey::new_unique();
+    operations::close_feed(&mut ctx, &feed, &destination)
+        .await
+        .unwrap();
+
+    for account in &feed_accounts {
+        assert!(ctx.get_account(account).await.is_err());
+    }
+    assert_eq!(
+        ctx.get_account(&destination).await.unwrap().lamports,
+        feed_lamports
+    );
+}
+
+// A price account not referenced by the configuration cannot be closed with the feed
+#[tokio::test]
+async fn test_close_feed_with_other_prices_account() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    let other_prices = Pubkey::new_unique();
+    ctx.clone_account(&feed.prices, &other_prices).await;
+    let configuration: Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+
+    let accounts = scope::accounts::CloseFeed {
+        admin: ctx.admin.pubkey(),
+        destination: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+        oracle_prices: other_prices,
+        tokens_metadata: configuration.tokens_metadata,
+        oracle_twaps: None,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::CloseFeed {
+            feed_name: feed.feed_name.clone(),
+        }
+        .data(),
+    };
+    assert_eq!(
+        map_anchor_error(ctx.send_transaction(&[ix]).await),
+        AnchorErrorCode::ConstraintHasOne
+    );
+    assert!(ctx.get_account(&feed.prices).await.is_ok());
+}
+
+// The TWAP account of a feed must be closed with it
+#[tokio::test]
+async fn test_close_feed_without_twaps_account() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    let mut configuration: Configuration = ctx.get_zero_copy_account(&feed.conf).await.unwrap();
+    configuration.oracle_twaps = Pubkey::new_unique();
+    ctx.set_zero_copy_account(&feed.conf, &configuration);
+
+    assert_eq!(
+        map_scope_error(operations::close_feed(&mut ctx, &feed, &Pubkey::new_unique()).await),
+        ScopeError::UnexpectedAccount
+    );
+}
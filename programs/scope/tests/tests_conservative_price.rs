@@ -0,0 +1,150 @@
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize, InstructionData, ToAccountMetas};
+use common::*;
+use scope::{DatedPrice, OracleTwaps, Price, PriceSide, ScopeError};
+use solana_program::instruction::Instruction;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeSo1PythPriceAccount11111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const MAX_AGE_SECONDS: u64 = 60;
+const TWAP_WINDOW: u64 = 4;
+
+async fn refresh_spot_price(ctx: &mut TestContext, feed: &ScopeFeedDefinition) {
+    let spot_price = Price {
+        value: 10_000,
+        exp: 2,
+    };
+    mock_oracles::set_price(ctx, feed, &PYTH_CONF, &spot_price).await;
+    operations::refresh_price(ctx, feed, &PYTH_CONF).await;
+}
+
+/// Feed with a spot price of 100.00 and a TWAP of 90.00 over `TWAP_WINDOW` samples
+async fn setup_diverging_feed() -> (TestContext, ScopeFeedDefinition, Pubkey) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    refresh_spot_price(&mut ctx, &feed).await;
+
+    // The TWAP account is too big for the stack, build it in an aligned buffer
+    let now = ctx.get_now_timestamp().await;
+    let size = std::mem::size_of::<OracleTwaps>();
+    let mut words = vec![0_u64; size / 8 + 1];
+    let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+    let oracle_twaps: &mut OracleTwaps = bytemuck::from_bytes_mut(&mut bytes[..size]);
+    oracle_twaps.oracle_prices = feed.prices;
+    let twap_buffer = &mut oracle_twaps.twaps[PYTH_CONF.token];
+    twap_buffer.reset(TWAP_WINDOW);
+    for (value, age) in [(8_800, 30), (9_200, 20), (9_100, 10)] {
+        twap_buffer
+            .push_sample(&DatedPrice {
+                price: Price { value, exp: 2 },
+                unix_timestamp: now - age,
+                ..Default::default()
+            })
+            .unwrap();
+    }
+    let oracle_twaps_pk = Pubkey::new_unique();
+    ctx.set_zero_copy_account(&oracle_twaps_pk, oracle_twaps);
+
+    (ctx, feed, oracle_twaps_pk)
+}
+
+async fn get_conservative_price(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    oracle_twaps: &Pubkey,
+    side: PriceSide,
+    window: u64,
+) -> Result<DatedPrice, BanksClientError> {
+    let accounts = scope::accounts::GetConservativePrice {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        oracle_twaps: *oracle_twaps,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::GetConservativePrice {
+            token: PYTH_CONF.token.try_into().unwrap(),
+            side,
+            window,
+            max_age_seconds: MAX_AGE_SECONDS,
+        }
+        .data(),
+    };
+    let return_data = ctx.simulate_transaction_return_data(&[ix]).await?;
+    Ok(DatedPrice::try_from_slice(&return_data).unwrap())
+}
+
+// A collateral is valued at the TWAP below the spot price
+#[tokio::test]
+async fn test_conservative_price_collateral() {
+    let (mut ctx, feed, oracle_twaps) = setup_diverging_feed().await;
+
+    let dated_price = get_conservative_price(
+        &mut ctx,
+        &feed,
+        &oracle_twaps,
+        PriceSide::Collateral,
+        TWAP_WINDOW,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        dated_price.price,
+        Price {
+            value: 9_000,
+            exp: 2
+        }
+    );
+}
+
+// A debt is valued at the spot price above the TWAP
+#[tokio::test]
+async fn test_conservative_price_debt() {
+    let (mut ctx, feed, oracle_twaps) = setup_diverging_feed().await;
+
+    let dated_price =
+        get_conservative_price(&mut ctx, &feed, &oracle_twaps, PriceSide::Debt, TWAP_WINDOW)
+            .await
+            .unwrap();
+    assert_eq!(
+        dated_price.price,
+        Price {
+            value: 10_000,
+            exp: 2
+        }
+    );
+}
+
+// The TWAP must be maintained over the requested window and be fresh
+#[tokio::test]
+async fn test_conservative_price_twap_not_maintained() {
+    let (mut ctx, feed, oracle_twaps) = setup_diverging_feed().await;
+
+    assert_eq!(
+        map_scope_error(
+            get_conservative_price(&mut ctx, &feed, &oracle_twaps, PriceSide::Debt, 8).await
+        ),
+        ScopeError::TwapWindowMismatch
+    );
+
+    // The spot price is refreshed but the TWAP is not updated anymore
+    ctx.fast_forward_seconds(MAX_AGE_SECONDS - 5).await;
+    refresh_spot_price(&mut ctx, &feed).await;
+    assert_eq!(
+        map_scope_error(
+            get_conservative_price(&mut ctx, &feed, &oracle_twaps, PriceSide::Debt, TWAP_WINDOW)
+                .await
+        ),
+        ScopeError::StalePrice
+    );
+}
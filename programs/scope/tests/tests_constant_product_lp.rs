@@ -0,0 +1,247 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, Clock, Pubkey},
+    InstructionData, ToAccountMetas,
+};
+use common::*;
+use mock_oracles::constant_product_lp;
+use scope::{
+    oracles::constant_product_lp::{TokenSwap, TOKEN_SWAP_PROGRAM_IDS},
+    OracleMappings, OraclePrices, Price, ScopeError, UpdateMappingConfigMode,
+};
+use solana_program::{
+    instruction::Instruction,
+    sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const SOL_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeSo1PythPriceAccount11111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const USDC_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeUsdcPythPriceAccount1111111111111111111"),
+    token: 1,
+    price_type: TestOracleType::Pyth,
+};
+
+const LP_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SwapPoo111111111111111111111111111111111111"),
+    token: 2,
+    price_type: TestOracleType::ConstantProductLp,
+};
+
+const POOL: TokenSwap = TokenSwap {
+    token_a: pubkey!("VauitA1111111111111111111111111111111111111"),
+    token_b: pubkey!("VauitB1111111111111111111111111111111111111"),
+    pool_mint: pubkey!("LpMint1111111111111111111111111111111111111"),
+    // SOL like token, 9 decimals
+    token_a_mint: pubkey!("MintA11111111111111111111111111111111111111"),
+    // USDC like token, 6 decimals
+    token_b_mint: pubkey!("MintB11111111111111111111111111111111111111"),
+};
+
+// 1000 LP tokens with 6 decimals
+const LP_SUPPLY: u64 = 1_000_000_000;
+
+fn set_pool(ctx: &mut TestContext, reserve_a: u64, reserve_b: u64) {
+    ctx.set_account(
+        &LP_CONF.pubkey,
+        constant_product_lp::get_token_swap_data(&POOL, 0),
+        &TOKEN_SWAP_PROGRAM_IDS[0],
+    );
+    let token_program = constant_product_lp::token_program_id();
+    ctx.set_account(
+        &POOL.token_a,
+        constant_product_lp::get_token_account_data(&POOL.token_a_mint, reserve_a),
+        &token_program,
+    );
+    ctx.set_account(
+        &POOL.token_b,
+        constant_product_lp::get_token_account_data(&POOL.token_b_mint, reserve_b),
+        &token_program,
+    );
+    ctx.set_account(
+        &POOL.pool_mint,
+        constant_product_lp::get_mint_data(LP_SUPPLY, 6),
+        &token_program,
+    );
+    ctx.set_account(
+        &POOL.token_a_mint,
+        constant_product_lp::get_mint_data(0, 9),
+        &token_program,
+    );
+    ctx.set_account(
+        &POOL.token_b_mint,
+        constant_product_lp::get_mint_data(0, 6),
+        &token_program,
+    );
+}
+
+fn underlying_prices_value(token_a: u16, token_b: u16) -> Vec<u8> {
+    [token_a.to_le_bytes(), token_b.to_le_bytes()].concat()
+}
+
+/// Feed with SOL at 20 USD, USDC at 1 USD and a pool of 1000 SOL and 20_000 USDC
+async fn setup_lp_feed() -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![SOL_CONF, USDC_CONF]).await;
+    mock_oracles::set_price(&mut ctx, &feed, &SOL_CONF, &Price { value: 20, exp: 0 }).await;
+    mock_oracles::set_price(&mut ctx, &feed, &USDC_CONF, &Price { value: 1, exp: 0 }).await;
+    set_pool(&mut ctx, 1_000_000_000_000, 20_000_000_000);
+    operations::update_oracle_mapping(&mut ctx, &feed, &LP_CONF).await;
+    (ctx, feed)
+}
+
+fn to_f64(price: &Price) -> f64 {
+    price.value as f64 / 10_f64.powi(price.exp.try_into().unwrap())
+}
+
+fn assert_price_eq(price: &Price, expected: f64) {
+    let rel_diff = (to_f64(price) - expected).abs() / expected;
+    assert!(rel_diff < 1e-10, "price {price:?} != {expected}");
+}
+
+// The LP price is the fair value of the pool and is not moved by a swap
+#[tokio::test]
+async fn test_constant_product_lp_refresh() {
+    let (mut ctx, feed) = setup_lp_feed().await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        LP_CONF.token,
+        UpdateMappingConfigMode::UnderlyingPrices,
+        underlying_prices_value(0, 1),
+    )
+    .await;
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.underlying_prices[LP_CONF.token], [0, 1]);
+
+    operations::refresh_price_list(&mut ctx, &feed, &[SOL_CONF, USDC_CONF, LP_CONF])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let lp = data.prices[LP_CONF.token];
+    assert_price_eq(&lp.price, 40.0);
+    assert_eq!(
+        lp.last_updated_slot,
+        data.prices[SOL_CONF.token].last_updated_slot
+    );
+
+    // A swap skews the vaults, k is kept up to the rounding of the reserves
+    ctx.fast_forward_seconds(10).await;
+    set_pool(&mut ctx, 1_234_567_890_123, 16_200_000_146);
+    operations::refresh_price(&mut ctx, &feed, &LP_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_price_eq(&data.prices[LP_CONF.token].price, 40.0);
+}
+
+// The LP price cannot be refreshed before its underlying prices are configured
+#[tokio::test]
+async fn test_constant_product_lp_not_configured() {
+    let (mut ctx, feed) = setup_lp_feed().await;
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &LP_CONF).await),
+        ScopeError::PriceNotValid
+    );
+}
+
+// The underlying prices must be two distinct other tokens of an LP entry
+#[tokio::test]
+async fn test_constant_product_lp_invalid_config() {
+    let (mut ctx, feed) = setup_lp_feed().await;
+
+    for (token, value) in [
+        (SOL_CONF.token, underlying_prices_value(1, 2)),
+        (LP_CONF.token, underlying_prices_value(0, 0)),
+        (LP_CONF.token, underlying_prices_value(0, 2)),
+        (LP_CONF.token, 0_u16.to_le_bytes().to_vec()),
+    ] {
+        assert_eq!(
+            map_scope_error(
+                operations::try_update_mapping_config(
+                    &mut ctx,
+                    &feed,
+                    token,
+                    UpdateMappingConfigMode::UnderlyingPrices,
+                    value,
+                )
+                .await
+            ),
+            ScopeError::InvalidUpdateValue
+        );
+    }
+}
+
+// The vaults and mints provided at refresh must be the ones of the pool, in order
+#[tokio::test]
+async fn test_constant_product_lp_wrong_pool_accounts() {
+    let (mut ctx, feed) = setup_lp_feed().await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        LP_CONF.token,
+        UpdateMappingConfigMode::UnderlyingPrices,
+        underlying_prices_value(0, 1),
+    )
+    .await;
+
+    let mut accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        price_info: LP_CONF.pubkey,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    // Vaults swapped
+    for key in [
+        POOL.token_b,
+        POOL.token_a,
+        POOL.pool_mint,
+        POOL.token_a_mint,
+        POOL.token_b_mint,
+    ] {
+        accounts.push(AccountMeta::new_readonly(key, false));
+    }
+
+    let args = scope::instruction::RefreshOnePrice {
+        token: LP_CONF.token.try_into().unwrap(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+
+    assert_eq!(
+        map_scope_error(ctx.send_transaction_with_bot(&[ix]).await),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+// Only accounts owned by a token-swap program can be mapped
+#[tokio::test]
+async fn test_constant_product_lp_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    ctx.set_account(
+        &LP_CONF.pubkey,
+        constant_product_lp::get_token_swap_data(&POOL, 0),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &LP_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
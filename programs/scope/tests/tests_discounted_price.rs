@@ -0,0 +1,97 @@
+mod common;
+
+use common::*;
+use scope::{
+    oracles::{discounted_price::Discount, fixed_price},
+    OraclePrices, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const SOURCE_PRICE: Price = Price {
+    value: 1_500_000,
+    exp: 6,
+};
+
+fn source_conf() -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&SOURCE_PRICE),
+        token: 0,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn discounted_conf(token: usize, factor_bps: u32) -> OracleConf {
+    OracleConf {
+        pubkey: Discount {
+            source: 0,
+            factor_bps,
+        }
+        .to_mapping_pubkey(),
+        token,
+        price_type: TestOracleType::Discounted,
+    }
+}
+
+#[tokio::test]
+async fn test_discounted_prices() {
+    let haircut = discounted_conf(1, 9_500);
+    let fee_adjusted = discounted_conf(2, 10_005);
+    let (mut ctx, feed) = fixtures::setup_scope(
+        DEFAULT_FEED_NAME,
+        vec![source_conf(), haircut, fee_adjusted],
+    )
+    .await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[source_conf(), haircut, fee_adjusted])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[haircut.token].price,
+        Price {
+            value: 1_425_000,
+            exp: 6
+        }
+    );
+    assert_eq!(
+        data.prices[fee_adjusted.token].price,
+        Price {
+            value: 1_500_750,
+            exp: 6
+        }
+    );
+}
+
+// A discounted price refreshed later keeps the dates of its source
+#[tokio::test]
+async fn test_discounted_price_is_not_fresher_than_source() {
+    let haircut = discounted_conf(1, 9_500);
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source_conf(), haircut]).await;
+
+    operations::refresh_price(&mut ctx, &feed, &source_conf()).await;
+    ctx.fast_forward_seconds(30).await;
+    operations::refresh_price(&mut ctx, &feed, &haircut).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let source = data.prices[0];
+    let discounted = data.prices[haircut.token];
+    assert_eq!(discounted.last_updated_slot, source.last_updated_slot);
+    assert_eq!(discounted.unix_timestamp, source.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_zero_factor_is_rejected() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source_conf()]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &discounted_conf(1, 0)).await
+        ),
+        ScopeError::InvalidUpdateValue
+    );
+}
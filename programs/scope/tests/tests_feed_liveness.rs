@@ -0,0 +1,122 @@
+mod common;
+
+use anchor_lang::{AnchorDeserialize, InstructionData, ToAccountMetas};
+use common::*;
+use scope::{oracles::fixed_price, DatedPrice, Price, ScopeError};
+use solana_program::instruction::Instruction;
+use solana_program_test::{tokio, BanksClientError};
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const FIXED_PRICE: Price = Price {
+    value: 1_000_000,
+    exp: 6,
+};
+
+// Prices are not stale on their own during the tests
+const MAX_AGE_SECONDS: u64 = 3600;
+// One minute
+const MAX_FEED_STALENESS_SLOTS: u64 = 150;
+
+fn fixed_price_conf() -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&FIXED_PRICE),
+        token: 0,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn get_price_ix(feed: &ScopeFeedDefinition) -> Instruction {
+    let accounts = scope::accounts::GetPrice {
+        oracle_prices: feed.prices,
+    };
+    Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::GetPrice {
+            token: 0,
+            max_age_seconds: MAX_AGE_SECONDS,
+        }
+        .data(),
+    }
+}
+
+async fn get_price(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+) -> Result<DatedPrice, BanksClientError> {
+    let return_data = ctx
+        .simulate_transaction_return_data(&[get_price_ix(feed)])
+        .await?;
+    Ok(DatedPrice::try_from_slice(&return_data).unwrap())
+}
+
+async fn is_price_usable(ctx: &mut TestContext, feed: &ScopeFeedDefinition) -> bool {
+    let accounts = scope::accounts::GetPrice {
+        oracle_prices: feed.prices,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::IsPriceUsable {
+            token: 0,
+            max_age_seconds: MAX_AGE_SECONDS,
+        }
+        .data(),
+    };
+    let return_data = ctx.simulate_transaction_return_data(&[ix]).await.unwrap();
+    bool::try_from_slice(&return_data).unwrap()
+}
+
+#[tokio::test]
+async fn test_reads_fail_when_feed_is_not_cranked() {
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    operations::set_max_feed_staleness(&mut ctx, &feed, MAX_FEED_STALENESS_SLOTS)
+        .await
+        .unwrap();
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+
+    let dated_price = get_price(&mut ctx, &feed).await.unwrap();
+    assert_eq!(dated_price.price, FIXED_PRICE);
+    assert!(is_price_usable(&mut ctx, &feed).await);
+
+    // 2 minutes without any refresh
+    ctx.fast_forward_seconds(120).await;
+    assert_eq!(
+        map_scope_error(get_price(&mut ctx, &feed).await),
+        ScopeError::StaleFeed
+    );
+    assert!(!is_price_usable(&mut ctx, &feed).await);
+
+    // Reads resume once the crank is back
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let dated_price = get_price(&mut ctx, &feed).await.unwrap();
+    assert_eq!(dated_price.price, FIXED_PRICE);
+    assert!(is_price_usable(&mut ctx, &feed).await);
+}
+
+#[tokio::test]
+async fn test_feed_liveness_check_disabled() {
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+
+    // Disabled by default
+    ctx.fast_forward_seconds(120).await;
+    get_price(&mut ctx, &feed).await.unwrap();
+
+    // Disabled again after being enabled
+    operations::set_max_feed_staleness(&mut ctx, &feed, MAX_FEED_STALENESS_SLOTS)
+        .await
+        .unwrap();
+    assert_eq!(
+        map_scope_error(get_price(&mut ctx, &feed).await),
+        ScopeError::StaleFeed
+    );
+    operations::set_max_feed_staleness(&mut ctx, &feed, 0)
+        .await
+        .unwrap();
+    get_price(&mut ctx, &feed).await.unwrap();
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use anchor_lang::{prelude::Pubkey, InstructionData, ToAccountMetas};
+use common::*;
+use scope::{oracles::fixed_price, OraclePrices, Price, ScopeError, UpdateMappingConfigMode};
+use solana_program::instruction::Instruction;
+use solana_program_test::tokio;
+use solana_sdk::signer::Signer;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const FIXED_PRICE: Price = Price {
+    value: 1_000_000,
+    exp: 6,
+};
+
+fn fixed_price_conf(token: usize) -> OracleConf {
+    fixed_price_conf_with(token, &FIXED_PRICE)
+}
+
+fn fixed_price_conf_with(token: usize, price: &Price) -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(price),
+        token,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn price_to_le_bytes(price: &Price) -> Vec<u8> {
+    let mut value = price.value.to_le_bytes().to_vec();
+    value.extend_from_slice(&price.exp.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn test_fixed_price_refresh_stamps_time() {
+    let conf = fixed_price_conf(0);
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        conf.token,
+        UpdateMappingConfigMode::FixedPrice,
+        price_to_le_bytes(&FIXED_PRICE),
+    )
+    .await;
+
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let first = data.prices[conf.token];
+    assert_eq!(first.price, FIXED_PRICE);
+
+    ctx.fast_forward_seconds(60).await;
+
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let second = data.prices[conf.token];
+    assert_eq!(second.price, FIXED_PRICE);
+    assert!(second.last_updated_slot > first.last_updated_slot);
+    assert!(second.unix_timestamp > first.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_fixed_price_through_update_mapping() {
+    let conf = fixed_price_conf(1);
+    // Mapping the encoded pubkey is equivalent to the dedicated config update
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[conf.token].price, FIXED_PRICE);
+}
+
+#[tokio::test]
+async fn test_change_fixed_price_through_update_mapping() {
+    let conf = fixed_price_conf(2);
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+
+    let new_price = Price {
+        value: 999_500,
+        exp: 6,
+    };
+    let new_conf = fixed_price_conf_with(conf.token, &new_price);
+    operations::update_oracle_mapping(&mut ctx, &feed, &new_conf).await;
+
+    // The previous constant is not accepted anymore
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &conf).await),
+        ScopeError::UnexpectedAccount
+    );
+
+    operations::refresh_price(&mut ctx, &feed, &new_conf).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[conf.token].price, new_price);
+}
+
+#[tokio::test]
+async fn test_unset_fixed_price_is_not_valid() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    // Set the token type without any price
+    let accounts = scope::accounts::UpdateOracleMapping {
+        admin: ctx.admin.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+        price_info: None,
+    };
+    let args = scope::instruction::UpdateMapping {
+        feed_name: feed.feed_name.clone(),
+        token: 3,
+        price_type: TestOracleType::FixedPrice.to_u8(),
+        price_max_age: 0,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    ctx.send_transaction(&[ix]).await.unwrap();
+
+    let unset_conf = OracleConf {
+        pubkey: Pubkey::default(),
+        token: 3,
+        price_type: TestOracleType::FixedPrice,
+    };
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &unset_conf).await),
+        ScopeError::PriceNotValid
+    );
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[unset_conf.token].price, Price::default());
+}
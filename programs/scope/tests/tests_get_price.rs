@@ -0,0 +1,415 @@
+mod common;
+
+use anchor_lang::{prelude::Clock, AnchorDeserialize, InstructionData, ToAccountMetas};
+use common::*;
+use mock_oracles::pyth;
+use scope::{
+    oracles::{fixed_price, pyth::SourceStatus},
+    CallerClock, DatedPrice, Price, ScopeError,
+};
+use solana_program::instruction::{Instruction, InstructionError};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::{pubkey, transaction::TransactionError};
+use types::*;
+
+use crate::utils::{map_scope_error, map_tx_error};
+
+const FIXED_PRICE: Price = Price {
+    value: 1_000_000,
+    exp: 6,
+};
+
+const MAX_AGE_SECONDS: u64 = 60;
+
+fn fixed_price_conf() -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&FIXED_PRICE),
+        token: 0,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+async fn get_price(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    caller_clock: Option<CallerClock>,
+) -> Result<DatedPrice, BanksClientError> {
+    let accounts = scope::accounts::GetPrice {
+        oracle_prices: feed.prices,
+    };
+    let data = match caller_clock {
+        None => scope::instruction::GetPrice {
+            token: 0,
+            max_age_seconds: MAX_AGE_SECONDS,
+        }
+        .data(),
+        Some(caller_clock) => scope::instruction::GetPriceWithClock {
+            token: 0,
+            max_age_seconds: MAX_AGE_SECONDS,
+            caller_clock,
+        }
+        .data(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data,
+    };
+    let return_data = ctx.simulate_transaction_return_data(&[ix]).await?;
+    Ok(DatedPrice::try_from_slice(&return_data).unwrap())
+}
+
+async fn get_prices(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    tokens: Vec<u16>,
+) -> Result<Vec<DatedPrice>, BanksClientError> {
+    let accounts = scope::accounts::GetPrice {
+        oracle_prices: feed.prices,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::GetPrices {
+            tokens,
+            max_age_seconds: MAX_AGE_SECONDS,
+        }
+        .data(),
+    };
+    let return_data = ctx.simulate_transaction_return_data(&[ix]).await?;
+    Ok(Vec::<DatedPrice>::try_from_slice(&return_data).unwrap())
+}
+
+async fn is_price_usable(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    token: u64,
+) -> Result<bool, BanksClientError> {
+    let accounts = scope::accounts::GetPrice {
+        oracle_prices: feed.prices,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::IsPriceUsable {
+            token,
+            max_age_seconds: MAX_AGE_SECONDS,
+        }
+        .data(),
+    };
+    let return_data = ctx.simulate_transaction_return_data(&[ix]).await?;
+    Ok(bool::try_from_slice(&return_data).unwrap())
+}
+
+async fn get_price_rescaled(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    target_exp: u8,
+) -> Result<DatedPrice, BanksClientError> {
+    let accounts = scope::accounts::GetPrice {
+        oracle_prices: feed.prices,
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::GetPriceRescaled {
+            token: 0,
+            max_age_seconds: MAX_AGE_SECONDS,
+            target_exp,
+        }
+        .data(),
+    };
+    let return_data = ctx.simulate_transaction_return_data(&[ix]).await?;
+    Ok(DatedPrice::try_from_slice(&return_data).unwrap())
+}
+
+fn caller_clock(clock: &Clock, seconds_offset: i64) -> CallerClock {
+    CallerClock {
+        slot: clock.slot,
+        unix_timestamp: clock.unix_timestamp + seconds_offset,
+    }
+}
+
+#[tokio::test]
+async fn test_get_price_sysvar_clock() {
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+
+    let dated_price = get_price(&mut ctx, &feed, None).await.unwrap();
+    assert_eq!(dated_price.price, FIXED_PRICE);
+
+    ctx.fast_forward_seconds(MAX_AGE_SECONDS + 1).await;
+
+    assert_eq!(
+        map_scope_error(get_price(&mut ctx, &feed, None).await),
+        ScopeError::StalePrice
+    );
+}
+
+#[tokio::test]
+async fn test_get_price_injected_clock() {
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let refresh_clock = ctx.get_clock().await;
+
+    ctx.fast_forward_seconds(MAX_AGE_SECONDS + 1).await;
+    let clock = ctx.get_clock().await;
+
+    // The price is stale according to the sysvar clock...
+    assert_eq!(
+        map_scope_error(get_price(&mut ctx, &feed, None).await),
+        ScopeError::StalePrice
+    );
+    // ...but not according to a caller lagging behind
+    let dated_price = get_price(&mut ctx, &feed, Some(caller_clock(&refresh_clock, 10)))
+        .await
+        .unwrap();
+    assert_eq!(dated_price.price, FIXED_PRICE);
+
+    // Same result with both clocks when they agree
+    assert_eq!(
+        map_scope_error(get_price(&mut ctx, &feed, Some(caller_clock(&clock, 0))).await),
+        ScopeError::StalePrice
+    );
+}
+
+#[tokio::test]
+async fn test_get_price_injected_clock_in_the_future() {
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let clock = ctx.get_clock().await;
+
+    assert_eq!(
+        map_scope_error(get_price(&mut ctx, &feed, Some(caller_clock(&clock, 3600))).await),
+        ScopeError::InvalidCallerClock
+    );
+}
+
+#[tokio::test]
+async fn test_get_prices_basket() {
+    let other_price = Price {
+        value: 42_000,
+        exp: 3,
+    };
+    let other_conf = OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&other_price),
+        token: 1,
+        price_type: TestOracleType::FixedPrice,
+    };
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf, other_conf]).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[conf, other_conf])
+        .await
+        .unwrap();
+
+    // Prices are returned in the requested order
+    let prices = get_prices(&mut ctx, &feed, vec![1, 0, 1]).await.unwrap();
+    let prices: Vec<Price> = prices.iter().map(|dated_price| dated_price.price).collect();
+    assert_eq!(prices, vec![other_price, FIXED_PRICE, other_price]);
+
+    // A single invalid token fails the whole read
+    assert_eq!(
+        map_scope_error(get_prices(&mut ctx, &feed, vec![0, 600]).await),
+        ScopeError::BadTokenNb
+    );
+    assert_eq!(
+        map_scope_error(get_prices(&mut ctx, &feed, vec![0, 2]).await),
+        ScopeError::PriceNotValid
+    );
+
+    // The returned list must fit in the return data
+    assert_eq!(
+        map_tx_error(get_prices(&mut ctx, &feed, vec![0; 16]).await),
+        TransactionError::InstructionError(0, InstructionError::InvalidArgument)
+    );
+    assert_eq!(
+        get_prices(&mut ctx, &feed, vec![0; 15])
+            .await
+            .unwrap()
+            .len(),
+        15
+    );
+
+    ctx.fast_forward_seconds(MAX_AGE_SECONDS + 1).await;
+    assert_eq!(
+        map_scope_error(get_prices(&mut ctx, &feed, vec![0, 1]).await),
+        ScopeError::StalePrice
+    );
+}
+
+#[tokio::test]
+async fn test_is_price_usable() {
+    let conf = fixed_price_conf();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+
+    // Never refreshed
+    assert!(!is_price_usable(&mut ctx, &feed, 0).await.unwrap());
+
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    assert!(is_price_usable(&mut ctx, &feed, 0).await.unwrap());
+
+    // Out of range tokens
+    assert!(!is_price_usable(&mut ctx, &feed, 600).await.unwrap());
+    assert!(!is_price_usable(&mut ctx, &feed, u64::MAX).await.unwrap());
+
+    // Stale
+    ctx.fast_forward_seconds(MAX_AGE_SECONDS + 1).await;
+    assert!(!is_price_usable(&mut ctx, &feed, 0).await.unwrap());
+}
+
+// A price kept while its source is halted or in auction is not usable
+#[tokio::test]
+async fn test_is_price_usable_source_not_trading() {
+    let pyth_conf = OracleConf {
+        pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+        token: 0,
+        price_type: TestOracleType::Pyth,
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![pyth_conf]).await;
+
+    for (status, usable) in [
+        (SourceStatus::Trading, true),
+        (SourceStatus::Halted, false),
+        (SourceStatus::Auction, false),
+        (SourceStatus::Trading, true),
+    ] {
+        ctx.fast_forward_seconds(10).await;
+        let clock = ctx.get_clock().await;
+        ctx.set_account(
+            &pyth_conf.pubkey,
+            pyth::get_account_data_for_price_with_status(&FIXED_PRICE, status, &clock),
+            &pyth::id(),
+        );
+        operations::refresh_price_list(&mut ctx, &feed, &[pyth_conf])
+            .await
+            .unwrap();
+        assert_eq!(
+            is_price_usable(&mut ctx, &feed, 0).await.unwrap(),
+            usable,
+            "{status:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_get_price_rescaled() {
+    let conf = OracleConf {
+        pubkey: pubkey!("SomeBtcPythPriceAccount11111111111111111111"),
+        token: 0,
+        price_type: TestOracleType::Pyth,
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    // 64622.36900000 BTC/USD
+    mock_oracles::set_price(
+        &mut ctx,
+        &feed,
+        &conf,
+        &Price {
+            value: 6_462_236_900_000,
+            exp: 8,
+        },
+    )
+    .await;
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+
+    for (target_exp, value) in [
+        (8, 6_462_236_900_000),
+        (2, 6_462_236),
+        (12, 64_622_369_000_000_000),
+    ] {
+        let dated_price = get_price_rescaled(&mut ctx, &feed, target_exp)
+            .await
+            .unwrap();
+        assert_eq!(
+            dated_price.price,
+            Price {
+                value,
+                exp: target_exp.into()
+            }
+        );
+    }
+
+    // 64622 with 16 decimals does not fit in a u64
+    assert_eq!(
+        map_scope_error(get_price_rescaled(&mut ctx, &feed, 16).await),
+        ScopeError::MathOverflow
+    );
+    ctx.fast_forward_seconds(MAX_AGE_SECONDS + 1).await;
+    assert_eq!(
+        map_scope_error(get_price_rescaled(&mut ctx, &feed, 0).await),
+        ScopeError::StalePrice
+    );
+}
+
+// Reducing the exponent of a small price until it would be truncated to zero fails instead of
+// returning a zero price
+#[tokio::test]
+async fn test_get_price_rescaled_precision_loss() {
+    // 0.00002 BONK/USD
+    let bonk_price = Price {
+        value: 2_000,
+        exp: 8,
+    };
+    let conf = OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&bonk_price),
+        token: 0,
+        price_type: TestOracleType::FixedPrice,
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+    let stored = get_price(&mut ctx, &feed, None).await.unwrap();
+
+    for (target_exp, value) in [(5, 2), (8, 2_000), (18, 20_000_000_000_000)] {
+        let dated_price = get_price_rescaled(&mut ctx, &feed, target_exp)
+            .await
+            .unwrap();
+        assert_eq!(
+            dated_price.price,
+            Price {
+                value,
+                exp: target_exp.into()
+            }
+        );
+        // Only the representation of the price changes
+        assert_eq!(dated_price.last_updated_slot, stored.last_updated_slot);
+        assert_eq!(dated_price.unix_timestamp, stored.unix_timestamp);
+    }
+
+    assert_eq!(
+        map_scope_error(get_price_rescaled(&mut ctx, &feed, 4).await),
+        ScopeError::PrecisionLoss
+    );
+}
+
+// The last status seen on the source is returned with the kept price
+#[tokio::test]
+async fn test_get_prices_returns_source_status() {
+    let pyth_conf = OracleConf {
+        pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+        token: 1,
+        price_type: TestOracleType::Pyth,
+    };
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![fixed_price_conf(), pyth_conf]).await;
+
+    for status in [SourceStatus::Trading, SourceStatus::Halted] {
+        let clock = ctx.get_clock().await;
+        ctx.set_account(
+            &pyth_conf.pubkey,
+            pyth::get_account_data_for_price_with_status(&FIXED_PRICE, status, &clock),
+            &pyth::id(),
+        );
+        operations::refresh_price_list(&mut ctx, &feed, &[fixed_price_conf(), pyth_conf])
+            .await
+            .unwrap();
+    }
+
+    let prices = get_prices(&mut ctx, &feed, vec![0, 1]).await.unwrap();
+    // Fixed prices have no source status
+    assert_eq!(prices[0].source_status, 0);
+    assert_eq!(prices[1].price, FIXED_PRICE);
+    assert_eq!(prices[1].source_status, u16::from(SourceStatus::Halted));
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use common::*;
+use scope::{IndexConstituent, OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const BTC_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeBtcPythPriceAccount11111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const ETH_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeEthPythPriceAccount11111111111111111111"),
+    token: 1,
+    price_type: TestOracleType::Pyth,
+};
+
+const INDEX_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeWeightedScopeBasket11111111111111111111"),
+    token: 2,
+    price_type: TestOracleType::IndexWeighted,
+};
+
+fn constituent(conf: &OracleConf, value: u64, exp: u64) -> IndexConstituent {
+    IndexConstituent {
+        token: conf.token.try_into().unwrap(),
+        quantity: Price { value, exp },
+    }
+}
+
+/// Feed with BTC at 60000.00 and ETH at 3000.000, and an index of 0.5 BTC + 10 ETH
+async fn setup_index_feed(max_constituent_age_slots: u64) -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![BTC_CONF, ETH_CONF]).await;
+    let btc_price = Price {
+        value: 6_000_000,
+        exp: 2,
+    };
+    let eth_price = Price {
+        value: 3_000_000,
+        exp: 3,
+    };
+    mock_oracles::set_price(&mut ctx, &feed, &BTC_CONF, &btc_price).await;
+    mock_oracles::set_price(&mut ctx, &feed, &ETH_CONF, &eth_price).await;
+
+    operations::initialize_oracle_index(&mut ctx, &feed, &INDEX_CONF.pubkey).await;
+    operations::update_index_constituents(
+        &mut ctx,
+        &feed,
+        &INDEX_CONF.pubkey,
+        vec![constituent(&BTC_CONF, 5, 1), constituent(&ETH_CONF, 10, 0)],
+        max_constituent_age_slots,
+    )
+    .await
+    .unwrap();
+    operations::update_oracle_mapping(&mut ctx, &feed, &INDEX_CONF).await;
+    (ctx, feed)
+}
+
+async fn get_index_price(ctx: &mut TestContext, feed: &ScopeFeedDefinition) -> Price {
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    data.prices[INDEX_CONF.token].price
+}
+
+// The index level is the sum of the values of the constituent quantities
+#[tokio::test]
+async fn test_index_weighted_refresh() {
+    let (mut ctx, feed) = setup_index_feed(0).await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[BTC_CONF, ETH_CONF, INDEX_CONF])
+        .await
+        .unwrap();
+    // 0.5 * 60000 + 10 * 3000
+    assert_eq!(
+        get_index_price(&mut ctx, &feed).await,
+        Price {
+            value: 60_000_000,
+            exp: 3
+        }
+    );
+}
+
+// Rebalanced quantities are used from the next refresh
+#[tokio::test]
+async fn test_index_weighted_rebalance() {
+    let (mut ctx, feed) = setup_index_feed(0).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[BTC_CONF, ETH_CONF, INDEX_CONF])
+        .await
+        .unwrap();
+
+    operations::update_index_constituents(
+        &mut ctx,
+        &feed,
+        &INDEX_CONF.pubkey,
+        vec![constituent(&BTC_CONF, 25, 2), constituent(&ETH_CONF, 15, 0)],
+        0,
+    )
+    .await
+    .unwrap();
+    ctx.fast_forward_seconds(10).await;
+    operations::refresh_price(&mut ctx, &feed, &INDEX_CONF).await;
+
+    // 0.25 * 60000 + 15 * 3000
+    assert_eq!(
+        get_index_price(&mut ctx, &feed).await,
+        Price {
+            value: 600_000_000,
+            exp: 4
+        }
+    );
+
+    // Removing a constituent
+    operations::update_index_constituents(
+        &mut ctx,
+        &feed,
+        &INDEX_CONF.pubkey,
+        vec![constituent(&ETH_CONF, 15, 0)],
+        0,
+    )
+    .await
+    .unwrap();
+    ctx.fast_forward_seconds(10).await;
+    operations::refresh_price(&mut ctx, &feed, &INDEX_CONF).await;
+    assert_eq!(
+        get_index_price(&mut ctx, &feed).await,
+        Price {
+            value: 45_000_000,
+            exp: 3
+        }
+    );
+}
+
+// The index is not refreshed from constituents older than the max age of the index
+#[tokio::test]
+async fn test_index_weighted_stale_constituent() {
+    let (mut ctx, feed) = setup_index_feed(100).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[BTC_CONF, ETH_CONF])
+        .await
+        .unwrap();
+
+    // 400ms slots
+    ctx.fast_forward_seconds(101 * 400 / 1000 + 1).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &INDEX_CONF).await),
+        ScopeError::PriceNotValid
+    );
+}
+
+// Constituents must be distinct tokens with a quantity
+#[tokio::test]
+async fn test_index_weighted_invalid_constituents() {
+    let (mut ctx, feed) = setup_index_feed(0).await;
+
+    for (constituents, error) in [
+        (vec![], ScopeError::InvalidUpdateValue),
+        (
+            vec![constituent(&BTC_CONF, 1, 0), constituent(&BTC_CONF, 2, 0)],
+            ScopeError::InvalidUpdateValue,
+        ),
+        (
+            vec![constituent(&BTC_CONF, 0, 0)],
+            ScopeError::InvalidUpdateValue,
+        ),
+        (
+            vec![IndexConstituent {
+                token: 512,
+                quantity: Price { value: 1, exp: 0 },
+            }],
+            ScopeError::BadTokenNb,
+        ),
+    ] {
+        assert_eq!(
+            map_scope_error(
+                operations::update_index_constituents(
+                    &mut ctx,
+                    &feed,
+                    &INDEX_CONF.pubkey,
+                    constituents,
+                    0,
+                )
+                .await
+            ),
+            error
+        );
+    }
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::{constant_product_lp, jupiter_lp};
+use scope::{
+    oracles::jupiter_lp::{perpetuals::Pool, DEFAULT_MAX_AUM_AGE_SLOTS},
+    OracleMappings, OraclePrices, Price, ScopeError, UpdateMappingConfigMode,
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const SOL_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeSo1PythPriceAccount11111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const JLP_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("JLPPoo1111111111111111111111111111111111111"),
+    token: 1,
+    price_type: TestOracleType::JupiterLp,
+};
+
+// 500M JLP with 6 decimals
+const LP_SUPPLY: u64 = 500_000_000_000_000;
+
+/// Set the pool with an AUM of `aum_usd` USD updated at `slot`
+fn set_pool(ctx: &mut TestContext, aum_usd: u128, slot: u64) {
+    let pool = Pool {
+        name: "Pool".to_string(),
+        custodies: vec![Pubkey::new_unique(); 5],
+        aum_usd: aum_usd * 1_000_000,
+        aum_usd_updated_slot: slot,
+    };
+    ctx.set_account(
+        &JLP_CONF.pubkey,
+        jupiter_lp::get_pool_data(&pool),
+        &jupiter_lp::JUPITER_PERPETUALS_PROGRAM_ID,
+    );
+    ctx.set_account(
+        &jupiter_lp::lp_mint_address(&JLP_CONF.pubkey),
+        constant_product_lp::get_mint_data(LP_SUPPLY, 6),
+        &constant_product_lp::token_program_id(),
+    );
+}
+
+async fn setup_jlp_feed() -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![SOL_CONF]).await;
+    let slot = ctx.get_clock().await.slot;
+    set_pool(&mut ctx, 1_500_000_000, slot);
+    operations::update_oracle_mapping(&mut ctx, &feed, &JLP_CONF).await;
+    (ctx, feed)
+}
+
+fn max_aum_age_value(max_age: u64) -> Vec<u8> {
+    max_age.to_le_bytes().to_vec()
+}
+
+// The JLP price is the AUM of the pool divided by the LP supply
+#[tokio::test]
+async fn test_jupiter_lp_refresh() {
+    let (mut ctx, feed) = setup_jlp_feed().await;
+    mock_oracles::set_price(&mut ctx, &feed, &SOL_CONF, &Price { value: 20, exp: 0 }).await;
+
+    // The LP mint is consumed as extra account in a refresh list
+    operations::refresh_price_list(&mut ctx, &feed, &[JLP_CONF, SOL_CONF])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[JLP_CONF.token].price,
+        Price {
+            value: 3_000_000_000_000_000_000,
+            exp: 18
+        }
+    );
+    assert_eq!(
+        data.prices[SOL_CONF.token].price,
+        Price { value: 20, exp: 0 }
+    );
+}
+
+// The price is rejected when the AUM has not been updated for too long
+#[tokio::test]
+async fn test_jupiter_lp_stale_aum() {
+    let (mut ctx, feed) = setup_jlp_feed().await;
+
+    // 400ms slots
+    ctx.fast_forward_seconds(DEFAULT_MAX_AUM_AGE_SLOTS * 400 / 1000 + 1)
+        .await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &JLP_CONF).await),
+        ScopeError::PriceNotValid
+    );
+
+    // A larger configured max age accepts the same AUM
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        JLP_CONF.token,
+        UpdateMappingConfigMode::MaxAumAge,
+        max_aum_age_value(2 * DEFAULT_MAX_AUM_AGE_SLOTS),
+    )
+    .await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        u64::from(mappings.max_aum_ages[JLP_CONF.token]),
+        2 * DEFAULT_MAX_AUM_AGE_SLOTS
+    );
+    operations::refresh_price(&mut ctx, &feed, &JLP_CONF).await;
+
+    // A fresh AUM is accepted again with the default max age
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        JLP_CONF.token,
+        UpdateMappingConfigMode::MaxAumAge,
+        max_aum_age_value(0),
+    )
+    .await;
+    ctx.fast_forward_seconds(10).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &JLP_CONF).await),
+        ScopeError::PriceNotValid
+    );
+    let slot = ctx.get_clock().await.slot;
+    set_pool(&mut ctx, 1_500_000_000, slot);
+    operations::refresh_price(&mut ctx, &feed, &JLP_CONF).await;
+}
+
+// The max AUM age can only be set on Jupiter LP entries and must fit in a u32
+#[tokio::test]
+async fn test_jupiter_lp_invalid_config() {
+    let (mut ctx, feed) = setup_jlp_feed().await;
+
+    for (token, value) in [
+        (SOL_CONF.token, max_aum_age_value(10)),
+        (JLP_CONF.token, max_aum_age_value(u64::from(u32::MAX) + 1)),
+    ] {
+        assert_eq!(
+            map_scope_error(
+                operations::try_update_mapping_config(
+                    &mut ctx,
+                    &feed,
+                    token,
+                    UpdateMappingConfigMode::MaxAumAge,
+                    value,
+                )
+                .await
+            ),
+            ScopeError::InvalidUpdateValue
+        );
+    }
+}
+
+// Only accounts owned by the Jupiter perpetuals program can be mapped
+#[tokio::test]
+async fn test_jupiter_lp_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, 1_500_000_000, 0);
+    let data = ctx.get_account_data(&JLP_CONF.pubkey).await.unwrap();
+    ctx.set_account(&JLP_CONF.pubkey, data, &Pubkey::new_unique());
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &JLP_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
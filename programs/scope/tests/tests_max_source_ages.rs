@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+use scope::{MaxSourceAgeOverride, OracleMappings, ScopeError, MAX_ENTRIES};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+// Set the max source age of all tokens in one instruction
+#[tokio::test]
+async fn test_set_all_max_source_ages() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    let overrides = vec![
+        MaxSourceAgeOverride {
+            token: 2,
+            max_age: 0,
+        },
+        MaxSourceAgeOverride {
+            token: 5,
+            max_age: 600,
+        },
+        MaxSourceAgeOverride {
+            token: (MAX_ENTRIES - 1).try_into().unwrap(),
+            max_age: 3600,
+        },
+    ];
+    operations::set_all_max_source_ages(&mut ctx, &feed, 60, overrides)
+        .await
+        .unwrap();
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    for (token, max_age) in mappings.max_source_ages.iter().enumerate() {
+        let expected = match token {
+            2 => 0,
+            5 => 600,
+            token if token == MAX_ENTRIES - 1 => 3600,
+            _ => 60,
+        };
+        assert_eq!(*max_age, expected, "token {token}");
+    }
+
+    // A new call overwrites all the previous values
+    operations::set_all_max_source_ages(&mut ctx, &feed, 120, vec![])
+        .await
+        .unwrap();
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert!(mappings.max_source_ages.iter().all(|age| *age == 120));
+}
+
+// Invalid overrides leave the current values untouched
+#[tokio::test]
+async fn test_set_all_max_source_ages_invalid_overrides() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    let duplicated = vec![
+        MaxSourceAgeOverride {
+            token: 1,
+            max_age: 10,
+        },
+        MaxSourceAgeOverride {
+            token: 1,
+            max_age: 20,
+        },
+    ];
+    assert_eq!(
+        map_scope_error(operations::set_all_max_source_ages(&mut ctx, &feed, 60, duplicated).await),
+        ScopeError::DuplicatedMappingToken
+    );
+
+    let out_of_range = vec![MaxSourceAgeOverride {
+        token: MAX_ENTRIES.try_into().unwrap(),
+        max_age: 10,
+    }];
+    assert_eq!(
+        map_scope_error(
+            operations::set_all_max_source_ages(&mut ctx, &feed, 60, out_of_range).await
+        ),
+        ScopeError::BadTokenNb
+    );
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert!(mappings.max_source_ages.iter().all(|age| *age == 0));
+}
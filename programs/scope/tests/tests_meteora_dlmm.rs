@@ -0,0 +1,148 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, Clock, Pubkey},
+    InstructionData, ToAccountMetas,
+};
+use common::*;
+use mock_oracles::meteora_dlmm;
+use scope::{OraclePrices, Price, ScopeError};
+use solana_program::{
+    instruction::Instruction,
+    sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const POOL: Pubkey = pubkey!("DLMMPoo111111111111111111111111111111111111");
+// SOL like token, 9 decimals
+const MINT_X: Pubkey = pubkey!("MintX11111111111111111111111111111111111111");
+// USDC like token, 6 decimals
+const MINT_Y: Pubkey = pubkey!("MintY11111111111111111111111111111111111111");
+
+const X_TO_Y_CONF: OracleConf = OracleConf {
+    pubkey: POOL,
+    token: 0,
+    price_type: TestOracleType::MeteoraDlmmXtoY,
+};
+
+const Y_TO_X_CONF: OracleConf = OracleConf {
+    pubkey: POOL,
+    token: 1,
+    price_type: TestOracleType::MeteoraDlmmYtoX,
+};
+
+fn set_pool(ctx: &mut TestContext, active_id: i32, bin_step: u16) {
+    ctx.set_account(
+        &POOL,
+        meteora_dlmm::get_lb_pair_data(active_id, bin_step, &MINT_X, &MINT_Y),
+        &meteora_dlmm::METEORA_DLMM_PROGRAM_ID,
+    );
+    ctx.set_account(
+        &MINT_X,
+        meteora_dlmm::get_mint_data(9),
+        &meteora_dlmm::token_program_id(),
+    );
+    ctx.set_account(
+        &MINT_Y,
+        meteora_dlmm::get_mint_data(6),
+        &meteora_dlmm::token_program_id(),
+    );
+}
+
+fn to_f64(price: &Price) -> f64 {
+    price.value as f64 / 10_f64.powi(price.exp.try_into().unwrap())
+}
+
+fn assert_price_eq(price: &Price, expected: f64) {
+    let rel_diff = (to_f64(price) - expected).abs() / expected;
+    assert!(rel_diff < 1e-12, "price {price:?} != {expected}");
+}
+
+// Both directions of the pool price are refreshed from the same pool account
+#[tokio::test]
+async fn test_meteora_dlmm_refresh() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    // ~150.16 Y per X with a 10 bps bin step
+    set_pool(&mut ctx, -1897, 10);
+    operations::update_oracle_mapping(&mut ctx, &feed, &X_TO_Y_CONF).await;
+    operations::update_oracle_mapping(&mut ctx, &feed, &Y_TO_X_CONF).await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[X_TO_Y_CONF, Y_TO_X_CONF])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let x_to_y = data.prices[X_TO_Y_CONF.token];
+    assert_price_eq(&x_to_y.price, 150.160263507669503154);
+    assert!(x_to_y.last_updated_slot > 0);
+    assert_price_eq(
+        &data.prices[Y_TO_X_CONF.token].price,
+        0.006659551446171540243,
+    );
+
+    // The pool moves up by one bin
+    ctx.fast_forward_seconds(10).await;
+    set_pool(&mut ctx, -1896, 10);
+    operations::refresh_price(&mut ctx, &feed, &X_TO_Y_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_price_eq(
+        &data.prices[X_TO_Y_CONF.token].price,
+        150.160263507669503154 * 1.001,
+    );
+}
+
+// The mints provided at refresh must be the ones of the pool, in order
+#[tokio::test]
+async fn test_meteora_dlmm_wrong_mints() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, -1897, 10);
+    operations::update_oracle_mapping(&mut ctx, &feed, &X_TO_Y_CONF).await;
+
+    let mut accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        price_info: POOL,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new_readonly(MINT_Y, false));
+    accounts.push(AccountMeta::new_readonly(MINT_X, false));
+
+    let args = scope::instruction::RefreshOnePrice {
+        token: X_TO_Y_CONF.token.try_into().unwrap(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+
+    assert_eq!(
+        map_scope_error(ctx.send_transaction_with_bot(&[ix]).await),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+// Only accounts owned by the DLMM program can be mapped
+#[tokio::test]
+async fn test_meteora_dlmm_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, -1897, 10);
+    ctx.set_account(
+        &POOL,
+        meteora_dlmm::get_lb_pair_data(-1897, 10, &MINT_X, &MINT_Y),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &X_TO_Y_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use anchor_lang::Discriminator;
+use common::*;
+use scope::{DatedPrice, OracleMappings, OraclePrices, Price, MAX_ENTRIES};
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 3,
+    price_type: TestOracleType::Pyth,
+};
+
+// Price info accounts, price types and a reserve of one u64 per token
+const ORACLE_MAPPINGS_V0_SIZE: usize = MAX_ENTRIES * (32 + 1 + 8);
+// Prices without `conf`
+const DATED_PRICE_V0_SIZE: usize = 56;
+
+/// Replace the mappings of the feed by a version 0 account mapping `PYTH_CONF`
+fn set_v0_mappings(ctx: &mut TestContext, feed: &ScopeFeedDefinition) {
+    let mut data = OracleMappings::discriminator().to_vec();
+    data.resize(8 + ORACLE_MAPPINGS_V0_SIZE, 0);
+    let account_offset = 8 + 32 * PYTH_CONF.token;
+    data[account_offset..account_offset + 32].copy_from_slice(PYTH_CONF.pubkey.as_ref());
+    data[8 + 32 * MAX_ENTRIES + PYTH_CONF.token] = PYTH_CONF.price_type.to_u8();
+    ctx.set_account(&feed.mapping, data, &scope::ID);
+}
+
+/// Replace the prices of the feed by a version 0 account storing `price` for `PYTH_CONF`
+fn set_v0_prices(ctx: &mut TestContext, feed: &ScopeFeedDefinition, price: &DatedPrice) {
+    let mut data = OraclePrices::discriminator().to_vec();
+    data.extend_from_slice(feed.mapping.as_ref());
+    data.resize(8 + 32 + MAX_ENTRIES * DATED_PRICE_V0_SIZE, 0);
+    let offset = 8 + 32 + PYTH_CONF.token * DATED_PRICE_V0_SIZE;
+    data[offset..offset + DATED_PRICE_V0_SIZE]
+        .copy_from_slice(&bytemuck::bytes_of(price)[..DATED_PRICE_V0_SIZE]);
+    ctx.set_account(&feed.prices, data, &scope::ID);
+}
+
+async fn mappings_len(ctx: &mut TestContext, feed: &ScopeFeedDefinition) -> usize {
+    ctx.get_account(&feed.mapping).await.unwrap().data.len()
+}
+
+// A version 0 mapping grows step by step and keeps its entries without any per-token limit
+#[tokio::test]
+async fn test_migrate_oracle_mappings_v0() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    set_v0_mappings(&mut ctx, &feed);
+
+    operations::migrate_feed(&mut ctx, &feed, 1).await.unwrap();
+    assert_eq!(
+        mappings_len(&mut ctx, &feed).await,
+        8 + ORACLE_MAPPINGS_V0_SIZE + MAX_PERMITTED_DATA_INCREASE
+    );
+
+    let growth = std::mem::size_of::<OracleMappings>() - ORACLE_MAPPINGS_V0_SIZE;
+    let steps = (growth + MAX_PERMITTED_DATA_INCREASE - 1) / MAX_PERMITTED_DATA_INCREASE - 1;
+    operations::migrate_feed(&mut ctx, &feed, steps)
+        .await
+        .unwrap();
+    assert_eq!(
+        mappings_len(&mut ctx, &feed).await,
+        8 + std::mem::size_of::<OracleMappings>()
+    );
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.version, OracleMappings::VERSION);
+    assert_eq!(
+        mappings.price_info_accounts[PYTH_CONF.token],
+        PYTH_CONF.pubkey
+    );
+    assert_eq!(
+        mappings.price_types[PYTH_CONF.token],
+        PYTH_CONF.price_type.to_u8()
+    );
+    assert_eq!(mappings.hard_caps[PYTH_CONF.token], Price::default());
+    assert_eq!(mappings.price_max_ages[PYTH_CONF.token], 0);
+
+    // The upgraded mapping is usable
+    let price = Price {
+        value: 12_345,
+        exp: 2,
+    };
+    mock_oracles::set_price(&mut ctx, &feed, &PYTH_CONF, &price).await;
+    operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[PYTH_CONF.token].price, price);
+}
+
+// The prices of a version 0 account are moved to their new slot with a zero confidence interval
+#[tokio::test]
+async fn test_migrate_oracle_prices_v0() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    let stored = DatedPrice {
+        price: Price {
+            value: 42_000,
+            exp: 3,
+        },
+        last_updated_slot: 7,
+        unix_timestamp: 11,
+        index: PYTH_CONF.token.try_into().unwrap(),
+        ..Default::default()
+    };
+    set_v0_prices(&mut ctx, &feed, &stored);
+
+    operations::migrate_feed(&mut ctx, &feed, 1).await.unwrap();
+    assert_eq!(
+        ctx.get_account(&feed.prices).await.unwrap().data.len(),
+        8 + std::mem::size_of::<OraclePrices>()
+    );
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.version, OraclePrices::VERSION);
+    assert_eq!(data.oracle_mappings, feed.mapping);
+    assert_eq!(data.prices[PYTH_CONF.token], stored);
+    assert_eq!(data.prices[PYTH_CONF.token - 1].price, Price::default());
+    assert_eq!(data.prices[PYTH_CONF.token + 1].price, Price::default());
+}
+
+// The accounts of a feed created with the current layout are left untouched
+#[tokio::test]
+async fn test_migrate_current_feed() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.version, OracleMappings::VERSION);
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.version, OraclePrices::VERSION);
+
+    operations::migrate_feed(&mut ctx, &feed, 1).await.unwrap();
+    assert_eq!(
+        mappings_len(&mut ctx, &feed).await,
+        8 + std::mem::size_of::<OracleMappings>()
+    );
+    let migrated: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        migrated.price_info_accounts[PYTH_CONF.token],
+        PYTH_CONF.pubkey
+    );
+}
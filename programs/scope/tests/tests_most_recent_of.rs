@@ -0,0 +1,176 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::{pyth, switchboard_v2};
+use scope::{
+    oracles::most_recent_of::{self, FIRST_SOURCE, SECOND_SOURCE},
+    OraclePrices, Price, ScopeError, UpdateMappingConfigMode,
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_SOURCE: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+const SWITCHBOARD_SOURCE: Pubkey = pubkey!("SomeSwitchboardAggregator111111111111111111");
+
+fn most_recent_conf() -> OracleConf {
+    OracleConf {
+        pubkey: most_recent_of::mapping_pubkey(&PYTH_SOURCE, &SWITCHBOARD_SOURCE),
+        token: 0,
+        price_type: TestOracleType::MostRecentOf(PYTH_SOURCE, SWITCHBOARD_SOURCE),
+    }
+}
+
+const PYTH_PRICE: Price = Price {
+    value: 10_000,
+    exp: 2,
+};
+
+const SWITCHBOARD_PRICE: Price = Price {
+    value: 10_100,
+    exp: 2,
+};
+
+fn set_pyth_source(ctx: &mut TestContext, data: Vec<u8>) {
+    ctx.set_account(&PYTH_SOURCE, data, &pyth::id());
+}
+
+fn set_switchboard_source(ctx: &mut TestContext, data: Vec<u8>) {
+    ctx.set_account(&SWITCHBOARD_SOURCE, data, &switchboard_v2::id());
+}
+
+async fn refresh_and_get_source(ctx: &mut TestContext, feed: &ScopeFeedDefinition) -> (Price, u8) {
+    let conf = most_recent_conf();
+    operations::refresh_price(ctx, feed, &conf).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let dated_price = data.prices[conf.token];
+    (
+        dated_price.price,
+        most_recent_of::winning_source(&dated_price),
+    )
+}
+
+// With both sources valid the one with the newest timestamp is stored
+#[tokio::test]
+async fn test_most_recent_of_both_valid() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![most_recent_conf()]).await;
+
+    let clock = ctx.get_clock().await;
+    let now = clock.unix_timestamp;
+    set_pyth_source(
+        &mut ctx,
+        pyth::get_account_data_for_price_at(&PYTH_PRICE, now - 10, &clock),
+    );
+    set_switchboard_source(
+        &mut ctx,
+        switchboard_v2::get_account_data_for_price_at(&SWITCHBOARD_PRICE, now - 5, &clock),
+    );
+    assert_eq!(
+        refresh_and_get_source(&mut ctx, &feed).await,
+        (SWITCHBOARD_PRICE, SECOND_SOURCE)
+    );
+
+    ctx.fast_forward_seconds(1).await;
+    set_pyth_source(
+        &mut ctx,
+        pyth::get_account_data_for_price_at(&PYTH_PRICE, now - 1, &clock),
+    );
+    assert_eq!(
+        refresh_and_get_source(&mut ctx, &feed).await,
+        (PYTH_PRICE, FIRST_SOURCE)
+    );
+}
+
+// An invalid switchboard round is ignored even if it is the newest
+#[tokio::test]
+async fn test_most_recent_of_only_first_valid() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![most_recent_conf()]).await;
+
+    let clock = ctx.get_clock().await;
+    let now = clock.unix_timestamp;
+    set_pyth_source(
+        &mut ctx,
+        pyth::get_account_data_for_price_at(&PYTH_PRICE, now - 10, &clock),
+    );
+    // Standard deviation of 10% of the result
+    set_switchboard_source(
+        &mut ctx,
+        switchboard_v2::get_account_data_for_price_with_std_deviation(
+            &SWITCHBOARD_PRICE,
+            &Price {
+                value: 1_010,
+                exp: 2,
+            },
+            &clock,
+        ),
+    );
+    assert_eq!(
+        refresh_and_get_source(&mut ctx, &feed).await,
+        (PYTH_PRICE, FIRST_SOURCE)
+    );
+}
+
+// An invalid pyth price is ignored even if it is the newest
+#[tokio::test]
+async fn test_most_recent_of_only_second_valid() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![most_recent_conf()]).await;
+
+    let clock = ctx.get_clock().await;
+    // Confidence interval of 10% of the price
+    set_pyth_source(
+        &mut ctx,
+        pyth::get_account_data_for_price_and_conf(&PYTH_PRICE, 1_000, &clock),
+    );
+    set_switchboard_source(
+        &mut ctx,
+        switchboard_v2::get_account_data_for_price_at(
+            &SWITCHBOARD_PRICE,
+            clock.unix_timestamp - 10,
+            &clock,
+        ),
+    );
+    assert_eq!(
+        refresh_and_get_source(&mut ctx, &feed).await,
+        (SWITCHBOARD_PRICE, SECOND_SOURCE)
+    );
+
+    // The refresh fails once both sources are invalid
+    ctx.fast_forward_seconds(1).await;
+    set_switchboard_source(&mut ctx, vec![0; 8]);
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &most_recent_conf()).await),
+        ScopeError::PriceNotValid
+    );
+}
+
+// The most recent source is still checked against the max source age of the entry
+#[tokio::test]
+async fn test_most_recent_of_both_stale() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![most_recent_conf()]).await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        most_recent_conf().token,
+        UpdateMappingConfigMode::MaxSourceAge,
+        60_u64.to_le_bytes().to_vec(),
+    )
+    .await;
+
+    let clock = ctx.get_clock().await;
+    let now = clock.unix_timestamp;
+    set_pyth_source(
+        &mut ctx,
+        pyth::get_account_data_for_price_at(&PYTH_PRICE, now - 100, &clock),
+    );
+    set_switchboard_source(
+        &mut ctx,
+        switchboard_v2::get_account_data_for_price_at(&SWITCHBOARD_PRICE, now - 90, &clock),
+    );
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &most_recent_conf()).await),
+        ScopeError::StalePrice
+    );
+}
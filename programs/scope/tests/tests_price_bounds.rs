@@ -0,0 +1,146 @@
+mod common;
+
+use common::*;
+use scope::{OracleMappings, OraclePrices, Price, ScopeError, UpdateMappingConfigMode};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const TEST_PYTH_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+fn price_bounds_value(min: u64, max: u64, exp: u64) -> Vec<u8> {
+    [min, max, exp]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+/// Feed accepting prices between 90.00 and 110.00
+async fn setup_bounded_feed() -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        TEST_PYTH_ORACLE.token,
+        UpdateMappingConfigMode::PriceBounds,
+        price_bounds_value(9_000, 11_000, 2),
+    )
+    .await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        mappings.price_bounds[TEST_PYTH_ORACLE.token],
+        [
+            Price {
+                value: 9_000,
+                exp: 2
+            },
+            Price {
+                value: 11_000,
+                exp: 2
+            }
+        ]
+    );
+    (ctx, feed)
+}
+
+// Prices within the bounds are accepted, including the bounds themselves and prices with
+// another exponent
+#[tokio::test]
+async fn test_price_within_bounds() {
+    let (mut ctx, feed) = setup_bounded_feed().await;
+
+    for price in [
+        Price {
+            value: 9_000,
+            exp: 2,
+        },
+        Price {
+            value: 110_000,
+            exp: 3,
+        },
+        Price {
+            value: 1_000_000,
+            exp: 4,
+        },
+    ] {
+        ctx.fast_forward_seconds(1).await;
+        mock_oracles::set_price(&mut ctx, &feed, &TEST_PYTH_ORACLE, &price).await;
+        operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+
+        let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+        assert_eq!(data.prices[TEST_PYTH_ORACLE.token].price, price);
+    }
+}
+
+// Prices outside of the bounds are rejected, the list refresh skips them
+#[tokio::test]
+async fn test_price_out_of_bounds() {
+    let (mut ctx, feed) = setup_bounded_feed().await;
+
+    for value in [8_999, 11_001] {
+        ctx.fast_forward_seconds(1).await;
+        let price = Price { value, exp: 2 };
+        mock_oracles::set_price(&mut ctx, &feed, &TEST_PYTH_ORACLE, &price).await;
+        assert_eq!(
+            map_scope_error(
+                operations::try_refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await
+            ),
+            ScopeError::PriceOutOfBounds
+        );
+
+        operations::refresh_price_list(&mut ctx, &feed, &[TEST_PYTH_ORACLE])
+            .await
+            .unwrap();
+        let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+        assert_eq!(data.prices[TEST_PYTH_ORACLE.token].price, Price::default());
+    }
+}
+
+// Bounds of 0 and u64::MAX disable the check
+#[tokio::test]
+async fn test_price_bounds_disabled() {
+    let (mut ctx, feed) = setup_bounded_feed().await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        TEST_PYTH_ORACLE.token,
+        UpdateMappingConfigMode::PriceBounds,
+        price_bounds_value(0, u64::MAX, 2),
+    )
+    .await;
+
+    for value in [1, 1_000_000] {
+        ctx.fast_forward_seconds(1).await;
+        let price = Price { value, exp: 2 };
+        mock_oracles::set_price(&mut ctx, &feed, &TEST_PYTH_ORACLE, &price).await;
+        operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+    }
+}
+
+// The lower bound cannot be above the upper bound
+#[tokio::test]
+async fn test_price_bounds_invalid_config() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+
+    for value in [price_bounds_value(11_000, 9_000, 2), vec![0; 16]] {
+        assert_eq!(
+            map_scope_error(
+                operations::try_update_mapping_config(
+                    &mut ctx,
+                    &feed,
+                    TEST_PYTH_ORACLE.token,
+                    UpdateMappingConfigMode::PriceBounds,
+                    value,
+                )
+                .await
+            ),
+            ScopeError::InvalidUpdateValue
+        );
+    }
+}
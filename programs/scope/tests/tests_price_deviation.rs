@@ -0,0 +1,151 @@
+mod common;
+
+use common::*;
+use scope::{
+    OracleMappings, OraclePrices, Price, ScopeError, UpdateMappingConfigMode,
+    PRICE_DEVIATION_WINDOW_SLOTS,
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const TEST_PYTH_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+fn max_deviation_value(max_deviation_bps: u64) -> Vec<u8> {
+    max_deviation_bps.to_le_bytes().to_vec()
+}
+
+/// Feed with a stored price of 100.00 and a max deviation of 5%
+async fn setup_deviation_feed() -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+    mock_oracles::set_price(
+        &mut ctx,
+        &feed,
+        &TEST_PYTH_ORACLE,
+        &Price {
+            value: 10_000,
+            exp: 2,
+        },
+    )
+    .await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        TEST_PYTH_ORACLE.token,
+        UpdateMappingConfigMode::MaxDeviation,
+        max_deviation_value(500),
+    )
+    .await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.max_deviations_bps[TEST_PYTH_ORACLE.token], 500);
+    (ctx, feed)
+}
+
+// A price within the max deviation of the stored one is accepted
+#[tokio::test]
+async fn test_price_deviation_within_threshold() {
+    let (mut ctx, feed) = setup_deviation_feed().await;
+
+    ctx.fast_forward_seconds(10).await;
+    // 104.99 then 100.00 (-4.75%)
+    for value in [10_499, 10_000] {
+        let price = Price { value, exp: 2 };
+        mock_oracles::set_price(&mut ctx, &feed, &TEST_PYTH_ORACLE, &price).await;
+        operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+
+        let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+        assert_eq!(data.prices[TEST_PYTH_ORACLE.token].price, price);
+        ctx.fast_forward_seconds(1).await;
+    }
+}
+
+// A price deviating too much from a recent stored one is rejected
+#[tokio::test]
+async fn test_price_deviation_over_threshold() {
+    let (mut ctx, feed) = setup_deviation_feed().await;
+    let before: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+
+    ctx.fast_forward_seconds(10).await;
+    mock_oracles::set_price(
+        &mut ctx,
+        &feed,
+        &TEST_PYTH_ORACLE,
+        &Price {
+            value: 10_600,
+            exp: 2,
+        },
+    )
+    .await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await),
+        ScopeError::PriceNotValid
+    );
+
+    // The refresh list skips the token
+    operations::refresh_price_list(&mut ctx, &feed, &[TEST_PYTH_ORACLE])
+        .await
+        .unwrap();
+    let after: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        after.prices[TEST_PYTH_ORACLE.token],
+        before.prices[TEST_PYTH_ORACLE.token]
+    );
+
+    // Disabling the check accepts the price
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        TEST_PYTH_ORACLE.token,
+        UpdateMappingConfigMode::MaxDeviation,
+        max_deviation_value(0),
+    )
+    .await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+}
+
+// Any price is accepted once the stored one is older than the deviation window
+#[tokio::test]
+async fn test_price_deviation_stale_stored_price() {
+    let (mut ctx, feed) = setup_deviation_feed().await;
+
+    // 400ms slots
+    ctx.fast_forward_seconds((PRICE_DEVIATION_WINDOW_SLOTS + 1) * 400 / 1000 + 1)
+        .await;
+    let price = Price {
+        value: 20_000,
+        exp: 2,
+    };
+    mock_oracles::set_price(&mut ctx, &feed, &TEST_PYTH_ORACLE, &price).await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[TEST_PYTH_ORACLE.token].price, price);
+}
+
+// The max deviation must fit in a u16
+#[tokio::test]
+async fn test_price_deviation_invalid_config() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_mapping_config(
+                &mut ctx,
+                &feed,
+                TEST_PYTH_ORACLE.token,
+                UpdateMappingConfigMode::MaxDeviation,
+                max_deviation_value(u64::from(u16::MAX) + 1),
+            )
+            .await
+        ),
+        ScopeError::InvalidUpdateValue
+    );
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::pyth;
+use scope::{OracleMappings, OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_ACCOUNT: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const PRICE: Price = Price {
+    value: 10_000,
+    exp: 2,
+};
+
+// 4 seconds of 400ms slots
+const MAX_AGE_SLOTS: u64 = 10;
+
+/// Publish the pyth price at the current slot
+async fn publish_pyth_price(ctx: &mut TestContext) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &PYTH_ACCOUNT,
+        pyth::get_account_data_for_price(&PRICE, &clock),
+        &pyth::id(),
+    );
+}
+
+// A price exactly as old as the max age is accepted, an older one is rejected
+#[tokio::test]
+async fn test_price_max_age_boundary() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    operations::try_update_oracle_mapping_with_max_age(&mut ctx, &feed, &PYTH_CONF, MAX_AGE_SLOTS)
+        .await
+        .unwrap();
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.price_max_ages[PYTH_CONF.token], MAX_AGE_SLOTS);
+
+    publish_pyth_price(&mut ctx).await;
+    let published_slot = ctx.get_clock().await.slot;
+    ctx.fast_forward_seconds(4).await;
+    assert_eq!(ctx.get_clock().await.slot, published_slot + MAX_AGE_SLOTS);
+    operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[PYTH_CONF.token].price, PRICE);
+    assert_eq!(
+        data.prices[PYTH_CONF.token].last_updated_slot,
+        published_slot
+    );
+
+    ctx.fast_forward_seconds(1).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &PYTH_CONF).await),
+        ScopeError::StalePrice
+    );
+}
+
+// Without a max age, the default, any publish slot is accepted
+#[tokio::test]
+async fn test_price_max_age_zero_is_no_limit() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.price_max_ages[PYTH_CONF.token], 0);
+
+    publish_pyth_price(&mut ctx).await;
+    ctx.fast_forward_seconds(600).await;
+    operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+
+    // Setting the max age back to 0 removes the limit
+    operations::try_update_oracle_mapping_with_max_age(&mut ctx, &feed, &PYTH_CONF, MAX_AGE_SLOTS)
+        .await
+        .unwrap();
+    ctx.fast_forward_seconds(1).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &PYTH_CONF).await),
+        ScopeError::StalePrice
+    );
+    operations::update_oracle_mapping(&mut ctx, &feed, &PYTH_CONF).await;
+    ctx.fast_forward_seconds(1).await;
+    operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+}
@@ -0,0 +1,200 @@
+#![cfg(not(feature = "skip_refresh_events"))]
+
+mod common;
+
+use anchor_lang::{
+    prelude::{Clock, Pubkey},
+    InstructionData, ToAccountMetas,
+};
+use common::*;
+use scope::{OraclePrices, Price, PriceRefreshSkipped, PriceRefreshed, ScopeError};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::parse_events;
+
+const TEST_PYTH_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const TEST_PYTH2_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePyth2PriceAccount1111111111111111111111"),
+    token: 3,
+    price_type: TestOracleType::Pyth,
+};
+
+fn expected_event(prices: &OraclePrices, token: usize) -> PriceRefreshed {
+    let price = &prices.prices[token];
+    PriceRefreshed {
+        token: token.try_into().unwrap(),
+        value: price.price.value,
+        exp: price.price.exp,
+        slot: price.last_updated_slot,
+        timestamp: price.unix_timestamp,
+    }
+}
+
+// One event is emitted by a single token refresh, matching the stored price
+#[tokio::test]
+async fn test_refresh_one_emits_event() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+
+    mock_oracles::set_price(
+        &mut ctx,
+        &feed,
+        &TEST_PYTH_ORACLE,
+        &Price {
+            value: 123_456,
+            exp: 4,
+        },
+    )
+    .await;
+
+    let accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
+    };
+    let args = scope::instruction::RefreshOnePrice {
+        token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+
+    let logs = ctx.send_transaction_with_logs(&[ix]).await.unwrap();
+    let events: Vec<PriceRefreshed> = parse_events(&logs);
+
+    let prices: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        events,
+        vec![expected_event(&prices, TEST_PYTH_ORACLE.token)]
+    );
+    assert_eq!(events[0].value, 123_456);
+    assert_eq!(events[0].exp, 4);
+}
+
+// A list refresh emits one event per updated token, unset mappings are skipped
+#[tokio::test]
+async fn test_refresh_list_emits_one_event_per_token() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE]).await;
+
+    for (i, conf) in [TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE].iter().enumerate() {
+        mock_oracles::set_price(
+            &mut ctx,
+            &feed,
+            conf,
+            &Price {
+                value: (i as u64) + 10,
+                exp: 6,
+            },
+        )
+        .await;
+    }
+
+    // Token 1 is not mapped, the default pubkey is provided for it
+    let mut accounts = scope::accounts::RefreshList {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    accounts.append(&mut utils::get_refresh_list_accounts(&mut ctx, &TEST_PYTH_ORACLE).await);
+    accounts.push(AccountMeta::new_readonly(Pubkey::default(), false));
+    accounts.append(&mut utils::get_refresh_list_accounts(&mut ctx, &TEST_PYTH2_ORACLE).await);
+
+    let args = scope::instruction::RefreshPriceList {
+        tokens: vec![
+            TEST_PYTH_ORACLE.token.try_into().unwrap(),
+            1,
+            TEST_PYTH2_ORACLE.token.try_into().unwrap(),
+        ],
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+
+    let logs = ctx.send_transaction_with_logs(&[ix]).await.unwrap();
+    let events: Vec<PriceRefreshed> = parse_events(&logs);
+
+    let prices: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        events,
+        vec![
+            expected_event(&prices, TEST_PYTH_ORACLE.token),
+            expected_event(&prices, TEST_PYTH2_ORACLE.token),
+        ]
+    );
+}
+
+// A token failing validation in a list refresh emits a skipped event with the rejection error
+#[tokio::test]
+async fn test_refresh_list_emits_skipped_event() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE]).await;
+    operations::try_update_oracle_mapping_with_max_age(&mut ctx, &feed, &TEST_PYTH2_ORACLE, 1)
+        .await
+        .unwrap();
+    for conf in [TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE] {
+        mock_oracles::set_price(&mut ctx, &feed, &conf, &Price { value: 10, exp: 6 }).await;
+    }
+    ctx.fast_forward_seconds(4).await;
+
+    let mut accounts = scope::accounts::RefreshList {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    accounts.append(&mut utils::get_refresh_list_accounts(&mut ctx, &TEST_PYTH_ORACLE).await);
+    accounts.append(&mut utils::get_refresh_list_accounts(&mut ctx, &TEST_PYTH2_ORACLE).await);
+
+    let args = scope::instruction::RefreshPriceList {
+        tokens: vec![
+            TEST_PYTH_ORACLE.token.try_into().unwrap(),
+            TEST_PYTH2_ORACLE.token.try_into().unwrap(),
+        ],
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+
+    let logs = ctx.send_transaction_with_logs(&[ix]).await.unwrap();
+
+    let prices: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        parse_events::<PriceRefreshed>(&logs),
+        vec![expected_event(&prices, TEST_PYTH_ORACLE.token)]
+    );
+    assert_eq!(
+        parse_events::<PriceRefreshSkipped>(&logs),
+        vec![PriceRefreshSkipped {
+            token: TEST_PYTH2_ORACLE.token.try_into().unwrap(),
+            reason: u32::from(ScopeError::StalePrice).into(),
+        }]
+    );
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::pyth;
+use scope::{OraclePrices, Price, ScopeError, MAX_SOURCE_SLOT_DRIFT};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_ACCOUNT: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const PRICE: Price = Price {
+    value: 10_000,
+    exp: 2,
+};
+
+/// Publish the pyth price `slots_ahead` slots after the current slot
+async fn publish_pyth_price_ahead(ctx: &mut TestContext, slots_ahead: u64) -> u64 {
+    let mut clock = ctx.get_clock().await;
+    clock.slot += slots_ahead;
+    ctx.set_account(
+        &PYTH_ACCOUNT,
+        pyth::get_account_data_for_price(&PRICE, &clock),
+        &pyth::id(),
+    );
+    clock.slot
+}
+
+// A price published at the current slot or slightly ahead of it is accepted
+#[tokio::test]
+async fn test_price_slot_within_drift() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+
+    for slots_ahead in [0, 1, MAX_SOURCE_SLOT_DRIFT] {
+        ctx.fast_forward_seconds(1).await;
+        let published_slot = publish_pyth_price_ahead(&mut ctx, slots_ahead).await;
+        operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+
+        let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+        assert_eq!(data.prices[PYTH_CONF.token].price, PRICE);
+        assert_eq!(
+            data.prices[PYTH_CONF.token].last_updated_slot,
+            published_slot
+        );
+    }
+}
+
+// A price published too far ahead of the current slot is rejected, the list refresh skips it
+#[tokio::test]
+async fn test_price_slot_too_far_ahead() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+
+    for slots_ahead in [MAX_SOURCE_SLOT_DRIFT + 1, 1_000_000] {
+        ctx.fast_forward_seconds(1).await;
+        publish_pyth_price_ahead(&mut ctx, slots_ahead).await;
+        assert_eq!(
+            map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &PYTH_CONF).await),
+            ScopeError::PriceNotValid
+        );
+
+        operations::refresh_price_list(&mut ctx, &feed, &[PYTH_CONF])
+            .await
+            .unwrap();
+        let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+        assert_eq!(data.prices[PYTH_CONF.token].price, Price::default());
+    }
+}
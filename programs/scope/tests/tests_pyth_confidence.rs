@@ -0,0 +1,161 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::pyth;
+use scope::{
+    oracles::{fixed_price, pyth::ConfidencePolicy},
+    OracleMappings, OraclePrices, Price, ScopeError, UpdateMappingConfigMode,
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_ACCOUNT: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+
+// The same pyth account backs two entries configured with different policies
+const REJECT_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const FLAG_ONLY_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 1,
+    price_type: TestOracleType::Pyth,
+};
+
+const PRICE: Price = Price {
+    value: 10_000,
+    exp: 2,
+};
+
+fn max_confidence_value(max_confidence_bps: u64, policy: u64) -> Vec<u8> {
+    let mut value = max_confidence_bps.to_le_bytes().to_vec();
+    value.extend_from_slice(&policy.to_le_bytes());
+    value
+}
+
+async fn set_pyth_price(ctx: &mut TestContext, conf: u64) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &PYTH_ACCOUNT,
+        pyth::get_account_data_for_price_and_conf(&PRICE, conf, &clock),
+        &pyth::id(),
+    );
+}
+
+async fn setup_policies() -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![REJECT_CONF, FLAG_ONLY_CONF]).await;
+    for (conf, policy) in [
+        (REJECT_CONF, ConfidencePolicy::Reject),
+        (FLAG_ONLY_CONF, ConfidencePolicy::FlagOnly),
+    ] {
+        operations::update_mapping_config(
+            &mut ctx,
+            &feed,
+            conf.token,
+            UpdateMappingConfigMode::MaxConfidence,
+            max_confidence_value(500, policy as u64),
+        )
+        .await;
+    }
+    (ctx, feed)
+}
+
+// Within the configured ratio both policies store the price unflagged, including a ratio the
+// default 2% would reject
+#[tokio::test]
+async fn test_pyth_confidence_within_max() {
+    let (mut ctx, feed) = setup_policies().await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.max_confidences_bps[REJECT_CONF.token], 500);
+    assert_eq!(
+        mappings.confidence_policies[FLAG_ONLY_CONF.token],
+        ConfidencePolicy::FlagOnly as u8
+    );
+
+    // 100.00 +/- 4.00
+    set_pyth_price(&mut ctx, 400).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[REJECT_CONF, FLAG_ONLY_CONF])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    for conf in [REJECT_CONF, FLAG_ONLY_CONF] {
+        let dated_price = data.prices[conf.token];
+        assert_eq!(dated_price.price, PRICE);
+        assert_eq!(dated_price.conf, 400);
+        assert!(!dated_price.is_low_confidence());
+    }
+}
+
+// At the same too wide confidence level, the reject policy fails the refresh while the
+// flag-only policy stores the flagged price
+#[tokio::test]
+async fn test_pyth_confidence_reject_vs_flag_only() {
+    let (mut ctx, feed) = setup_policies().await;
+
+    // 100.00 +/- 6.00
+    set_pyth_price(&mut ctx, 600).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &REJECT_CONF).await),
+        ScopeError::PriceConfidenceTooWide
+    );
+    operations::refresh_price(&mut ctx, &feed, &FLAG_ONLY_CONF).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let flagged = data.prices[FLAG_ONLY_CONF.token];
+    assert_eq!(flagged.price, PRICE);
+    assert_eq!(flagged.conf, 600);
+    assert!(flagged.is_low_confidence());
+
+    // The flag is cleared by the next refresh within the ratio
+    ctx.fast_forward_seconds(1).await;
+    set_pyth_price(&mut ctx, 100).await;
+    operations::refresh_price(&mut ctx, &feed, &FLAG_ONLY_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert!(!data.prices[FLAG_ONLY_CONF.token].is_low_confidence());
+    assert_eq!(data.prices[FLAG_ONLY_CONF.token].conf, 100);
+}
+
+// The max confidence is only available on entries with a confidence interval, must fit in a
+// u16 and have a known policy
+#[tokio::test]
+async fn test_pyth_confidence_invalid_config() {
+    let fixed_conf = OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&PRICE),
+        token: 2,
+        price_type: TestOracleType::FixedPrice,
+    };
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![REJECT_CONF, fixed_conf]).await;
+
+    for (token, value) in [
+        (fixed_conf.token, max_confidence_value(500, 0)),
+        (
+            REJECT_CONF.token,
+            max_confidence_value(u64::from(u16::MAX) + 1, 0),
+        ),
+        (REJECT_CONF.token, max_confidence_value(500, 2)),
+        (REJECT_CONF.token, 500_u64.to_le_bytes().to_vec()),
+    ] {
+        assert_eq!(
+            map_scope_error(
+                operations::try_update_mapping_config(
+                    &mut ctx,
+                    &feed,
+                    token,
+                    UpdateMappingConfigMode::MaxConfidence,
+                    value,
+                )
+                .await
+            ),
+            ScopeError::InvalidUpdateValue
+        );
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::pyth;
+use scope::{OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_ACCOUNT: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+
+// The same pyth account backs a spot entry and both conservative entries
+const SPOT_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const LOWER_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 1,
+    price_type: TestOracleType::PythConservativeLower,
+};
+
+const UPPER_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 2,
+    price_type: TestOracleType::PythConservativeUpper,
+};
+
+async fn set_pyth_price(ctx: &mut TestContext, price: &Price, conf: u64) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &PYTH_ACCOUNT,
+        pyth::get_account_data_for_price_and_conf(price, conf, &clock),
+        &pyth::id(),
+    );
+}
+
+// The conservative entries store the price shifted by the confidence interval
+#[tokio::test]
+async fn test_pyth_conservative_bounds() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![SPOT_CONF, LOWER_CONF, UPPER_CONF]).await;
+
+    // 100.00 +/- 1.50
+    set_pyth_price(
+        &mut ctx,
+        &Price {
+            value: 10_000,
+            exp: 2,
+        },
+        150,
+    )
+    .await;
+    operations::refresh_price_list(&mut ctx, &feed, &[SPOT_CONF, LOWER_CONF, UPPER_CONF])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    for (conf, value) in [
+        (SPOT_CONF, 10_000),
+        (LOWER_CONF, 9_850),
+        (UPPER_CONF, 10_150),
+    ] {
+        assert_eq!(data.prices[conf.token].price, Price { value, exp: 2 });
+    }
+}
+
+// A confidence interval wider than the price has no valid lower bound, it is rejected as too
+// wide before the bound is computed
+#[tokio::test]
+async fn test_pyth_conservative_lower_underflow() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![SPOT_CONF, LOWER_CONF, UPPER_CONF]).await;
+
+    set_pyth_price(&mut ctx, &Price { value: 100, exp: 2 }, 101).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &LOWER_CONF).await),
+        ScopeError::PriceConfidenceTooWide
+    );
+}
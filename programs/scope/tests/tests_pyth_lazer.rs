@@ -0,0 +1,201 @@
+mod common;
+
+use common::*;
+use mock_oracles::pyth_lazer;
+use scope::{OraclePrices, Price, ScopeError, UpdateMappingConfigMode};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::signature::{Keypair, Signer};
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const FEED_ID: u32 = 6;
+
+const PRICE: Price = Price {
+    value: 15_512_345_678,
+    exp: 8,
+};
+
+fn lazer_conf() -> OracleConf {
+    OracleConf {
+        pubkey: Pubkey::new_unique(),
+        token: 0,
+        price_type: TestOracleType::PythLazer,
+    }
+}
+
+/// Write the payload signed by `signer` in the mapped account, dated `age` seconds ago
+async fn set_signed_payload(
+    ctx: &mut TestContext,
+    conf: &OracleConf,
+    signer: &Keypair,
+    age: u64,
+) -> Vec<u8> {
+    let timestamp = ctx.get_now_timestamp().await - age;
+    let payload = pyth_lazer::payload(FEED_ID, &PRICE, timestamp);
+    ctx.set_account(
+        &conf.pubkey,
+        pyth_lazer::get_account_data_for_payload(signer, &payload),
+        &Pubkey::new_unique(),
+    );
+    payload
+}
+
+/// Feed with a Lazer price signed by a pinned trusted signer
+async fn setup_lazer_feed() -> (TestContext, ScopeFeedDefinition, OracleConf, Keypair) {
+    let conf = lazer_conf();
+    let signer = Keypair::new();
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_signed_payload(&mut ctx, &conf, &signer, 0).await;
+    operations::update_oracle_mapping(&mut ctx, &feed, &conf).await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        conf.token,
+        UpdateMappingConfigMode::SourceIdentity,
+        signer.pubkey().to_bytes().to_vec(),
+    )
+    .await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        conf.token,
+        UpdateMappingConfigMode::PythLazerFeedId,
+        u64::from(FEED_ID).to_le_bytes().to_vec(),
+    )
+    .await;
+    (ctx, feed, conf, signer)
+}
+
+/// Refresh the price in a transaction verifying the signature of `payload` by `signer`
+async fn refresh_with_verification(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    conf: &OracleConf,
+    signer: &Keypair,
+    payload: &[u8],
+) -> Result<(), BanksClientError> {
+    let ixs = [
+        pyth_lazer::ed25519_instruction(signer, payload),
+        operations::refresh_price_ix(ctx, feed, conf).await,
+    ];
+    ctx.send_transaction(&ixs).await
+}
+
+#[tokio::test]
+async fn test_refresh_verified_payload() {
+    let (mut ctx, feed, conf, signer) = setup_lazer_feed().await;
+    let payload = set_signed_payload(&mut ctx, &conf, &signer, 1).await;
+
+    refresh_with_verification(&mut ctx, &feed, &conf, &signer, &payload)
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let dated_price = data.prices[conf.token];
+    assert_eq!(dated_price.price, PRICE);
+    assert_eq!(
+        dated_price.unix_timestamp,
+        ctx.get_now_timestamp().await - 1
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_without_signature_verification() {
+    let (mut ctx, feed, conf, signer) = setup_lazer_feed().await;
+    set_signed_payload(&mut ctx, &conf, &signer, 0).await;
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &conf).await),
+        ScopeError::InvalidPayloadSignature
+    );
+
+    // The ed25519 instruction verifies another payload
+    let other_payload = pyth_lazer::payload(FEED_ID, &Price { value: 1, exp: 0 }, 0);
+    assert_eq!(
+        map_scope_error(
+            refresh_with_verification(&mut ctx, &feed, &conf, &signer, &other_payload).await
+        ),
+        ScopeError::InvalidPayloadSignature
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_stale_payload() {
+    let (mut ctx, feed, conf, signer) = setup_lazer_feed().await;
+    let payload = set_signed_payload(
+        &mut ctx,
+        &conf,
+        &signer,
+        scope::oracles::pyth_lazer::DEFAULT_MAX_PAYLOAD_AGE_SECONDS + 1,
+    )
+    .await;
+
+    assert_eq!(
+        map_scope_error(refresh_with_verification(&mut ctx, &feed, &conf, &signer, &payload).await),
+        ScopeError::StalePrice
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_untrusted_signer() {
+    let (mut ctx, feed, conf, _) = setup_lazer_feed().await;
+    let other_signer = Keypair::new();
+    let payload = set_signed_payload(&mut ctx, &conf, &other_signer, 0).await;
+
+    assert_eq!(
+        map_scope_error(
+            refresh_with_verification(&mut ctx, &feed, &conf, &other_signer, &payload).await
+        ),
+        ScopeError::SourceIdentityMismatch
+    );
+
+    // Without pinned signer, no update is trusted
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        conf.token,
+        UpdateMappingConfigMode::SourceIdentity,
+        Pubkey::default().to_bytes().to_vec(),
+    )
+    .await;
+    assert_eq!(
+        map_scope_error(
+            refresh_with_verification(&mut ctx, &feed, &conf, &other_signer, &payload).await
+        ),
+        ScopeError::InvalidPayloadSignature
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_other_feed() {
+    let (mut ctx, feed, conf, signer) = setup_lazer_feed().await;
+    let timestamp = ctx.get_now_timestamp().await;
+    let payload = pyth_lazer::payload(FEED_ID + 1, &PRICE, timestamp);
+    ctx.set_account(
+        &conf.pubkey,
+        pyth_lazer::get_account_data_for_payload(&signer, &payload),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(refresh_with_verification(&mut ctx, &feed, &conf, &signer, &payload).await),
+        ScopeError::PriceNotValid
+    );
+
+    // Without feed id, no update is accepted
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        conf.token,
+        UpdateMappingConfigMode::PythLazerFeedId,
+        0_u64.to_le_bytes().to_vec(),
+    )
+    .await;
+    let payload = set_signed_payload(&mut ctx, &conf, &signer, 0).await;
+    assert_eq!(
+        map_scope_error(refresh_with_verification(&mut ctx, &feed, &conf, &signer, &payload).await),
+        ScopeError::PriceNotValid
+    );
+}
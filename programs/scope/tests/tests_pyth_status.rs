@@ -0,0 +1,119 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::pyth;
+use scope::{oracles::pyth::SourceStatus, OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_ACCOUNT: Pubkey = pubkey!("SomePythPriceAccount11111111111111111111111");
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: PYTH_ACCOUNT,
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const PRICE: Price = Price {
+    value: 10_000,
+    exp: 2,
+};
+
+const NEW_PRICE: Price = Price {
+    value: 12_000,
+    exp: 2,
+};
+
+/// Publish the pyth price at the current slot with the given status
+async fn publish_pyth_price(ctx: &mut TestContext, price: &Price, status: SourceStatus) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &PYTH_ACCOUNT,
+        pyth::get_account_data_for_price_with_status(price, status, &clock),
+        &pyth::id(),
+    );
+}
+
+// A non trading price is skipped but its status is stored with the last valid price
+#[tokio::test]
+async fn test_refresh_list_stores_status_of_skipped_price() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+
+    publish_pyth_price(&mut ctx, &PRICE, SourceStatus::Trading).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[PYTH_CONF])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let refreshed = data.prices[PYTH_CONF.token];
+    assert_eq!(refreshed.price, PRICE);
+    assert_eq!(refreshed.source_status, u16::from(SourceStatus::Trading));
+
+    for status in [SourceStatus::Halted, SourceStatus::Auction] {
+        ctx.fast_forward_seconds(10).await;
+        publish_pyth_price(&mut ctx, &NEW_PRICE, status).await;
+        operations::refresh_price_list(&mut ctx, &feed, &[PYTH_CONF])
+            .await
+            .unwrap();
+        let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+        let kept = data.prices[PYTH_CONF.token];
+        assert_eq!(kept.price, PRICE);
+        assert_eq!(kept.last_updated_slot, refreshed.last_updated_slot);
+        assert_eq!(kept.source_status, u16::from(status));
+    }
+
+    // Trading again
+    ctx.fast_forward_seconds(10).await;
+    publish_pyth_price(&mut ctx, &NEW_PRICE, SourceStatus::Trading).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[PYTH_CONF])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[PYTH_CONF.token].price, NEW_PRICE);
+    assert_eq!(
+        data.prices[PYTH_CONF.token].source_status,
+        u16::from(SourceStatus::Trading)
+    );
+}
+
+// A single refresh of a halted price fails, the stored price is untouched
+#[tokio::test]
+async fn test_refresh_one_halted_price_fails() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+
+    publish_pyth_price(&mut ctx, &PRICE, SourceStatus::Trading).await;
+    operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+
+    ctx.fast_forward_seconds(10).await;
+    publish_pyth_price(&mut ctx, &NEW_PRICE, SourceStatus::Halted).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &PYTH_CONF).await),
+        ScopeError::PriceNotValid
+    );
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[PYTH_CONF.token].price, PRICE);
+    assert_eq!(
+        data.prices[PYTH_CONF.token].source_status,
+        u16::from(SourceStatus::Trading)
+    );
+}
+
+// A price account with a negative timestamp is not valid
+#[tokio::test]
+async fn test_refresh_negative_timestamp() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &PYTH_ACCOUNT,
+        pyth::get_account_data_for_price_at(&PRICE, -1, &clock),
+        &pyth::id(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &PYTH_CONF).await),
+        ScopeError::PriceNotValid
+    );
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::*;
+use mock_oracles::redstone;
+use scope::{OraclePrices, Price, ScopeError, UpdateMappingConfigMode};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const REDSTONE_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeRedStoneEthPriceData1111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::RedStone,
+};
+
+const ETH_PRICE: Price = Price {
+    value: 345_678_901_234,
+    exp: 8,
+};
+
+// The price and the timestamp are the ones of the signed data package
+#[tokio::test]
+async fn test_redstone_refresh() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![REDSTONE_CONF]).await;
+
+    let clock = ctx.get_clock().await;
+    let timestamp = u64::try_from(clock.unix_timestamp).unwrap() - 5;
+    ctx.set_account(
+        &REDSTONE_CONF.pubkey,
+        redstone::get_account_data_for_price_at(&ETH_PRICE, timestamp, &clock),
+        &redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID,
+    );
+    operations::refresh_price(&mut ctx, &feed, &REDSTONE_CONF).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let dated_price = data.prices[REDSTONE_CONF.token];
+    assert_eq!(dated_price.price, ETH_PRICE);
+    assert_eq!(dated_price.unix_timestamp, timestamp);
+}
+
+// A value written recently but signed long ago is stale
+#[tokio::test]
+async fn test_redstone_stale_feed_timestamp() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![REDSTONE_CONF]).await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        REDSTONE_CONF.token,
+        UpdateMappingConfigMode::MaxSourceAge,
+        60_u64.to_le_bytes().to_vec(),
+    )
+    .await;
+
+    let clock = ctx.get_clock().await;
+    let now = u64::try_from(clock.unix_timestamp).unwrap();
+    ctx.set_account(
+        &REDSTONE_CONF.pubkey,
+        redstone::get_account_data_for_price_at(&ETH_PRICE, now - 61, &clock),
+        &redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID,
+    );
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &REDSTONE_CONF).await),
+        ScopeError::StalePrice
+    );
+
+    ctx.fast_forward_seconds(1).await;
+    ctx.set_account(
+        &REDSTONE_CONF.pubkey,
+        redstone::get_account_data_for_price_at(&ETH_PRICE, now - 50, &clock),
+        &redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID,
+    );
+    operations::refresh_price(&mut ctx, &feed, &REDSTONE_CONF).await;
+}
+
+// Only accounts owned by the RedStone price adapter can be mapped
+#[tokio::test]
+async fn test_redstone_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &REDSTONE_CONF.pubkey,
+        redstone::get_account_data_for_price(&ETH_PRICE, &clock),
+        &mock_oracles::pyth::id(),
+    );
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &REDSTONE_CONF).await
+        ),
+        ScopeError::UnexpectedAccount
+    );
+
+    ctx.fast_forward_seconds(1).await;
+    ctx.set_account(
+        &REDSTONE_CONF.pubkey,
+        redstone::get_account_data_for_price(&ETH_PRICE, &clock),
+        &redstone::REDSTONE_PRICE_ADAPTER_PROGRAM_ID,
+    );
+    operations::update_oracle_mapping(&mut ctx, &feed, &REDSTONE_CONF).await;
+}
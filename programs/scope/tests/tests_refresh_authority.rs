@@ -0,0 +1,155 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{Clock, Pubkey},
+    InstructionData, ToAccountMetas,
+};
+use common::*;
+use scope::{OraclePrices, Price, ScopeError};
+use solana_program::{
+    instruction::Instruction,
+    sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey, signature::Signer};
+use types::*;
+
+use crate::utils::{map_anchor_error, map_scope_error, AnchorErrorCode};
+
+const TEST_PYTH_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+/// Refresh of the test token with `refresh_authority` as refresh authority account
+fn refresh_ix(feed: &ScopeFeedDefinition, refresh_authority: Option<Pubkey>) -> Instruction {
+    let accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        price_info: TEST_PYTH_ORACLE.pubkey,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority,
+    };
+    Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::RefreshOnePrice {
+            token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
+        }
+        .data(),
+    }
+}
+
+/// Feed whose refresh authority is the bot
+async fn setup_feed_with_refresh_authority() -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+    let admin = ctx.admin.insecure_clone();
+    let bot = ctx.bot.pubkey();
+    operations::set_refresh_authority(&mut ctx, &feed, &admin, bot)
+        .await
+        .unwrap();
+    mock_oracles::set_price(
+        &mut ctx,
+        &feed,
+        &TEST_PYTH_ORACLE,
+        &Price { value: 42, exp: 6 },
+    )
+    .await;
+    (ctx, feed)
+}
+
+// Only the refresh authority can refresh the prices once set
+#[tokio::test]
+async fn test_refresh_authority_refreshes() {
+    let (mut ctx, feed) = setup_feed_with_refresh_authority().await;
+
+    let bot = ctx.bot.pubkey();
+    ctx.send_transaction_with_bot(&[refresh_ix(&feed, Some(bot))])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[TEST_PYTH_ORACLE.token].price,
+        Price { value: 42, exp: 6 }
+    );
+
+    // Missing authority, signed by the bot or not
+    ctx.fast_forward_seconds(10).await;
+    assert_eq!(
+        map_scope_error(
+            ctx.send_transaction_with_bot(&[refresh_ix(&feed, None)])
+                .await
+        ),
+        ScopeError::UnauthorizedRefresh
+    );
+    assert_eq!(
+        map_scope_error(
+            operations::refresh_price_list_lenient(&mut ctx, &feed, &[TEST_PYTH_ORACLE]).await
+        ),
+        ScopeError::UnauthorizedRefresh
+    );
+}
+
+// The admin cannot refresh the prices and the refresh authority cannot update the mappings
+#[tokio::test]
+async fn test_refresh_authority_and_admin_roles_are_separate() {
+    let (mut ctx, feed) = setup_feed_with_refresh_authority().await;
+
+    let admin = ctx.admin.pubkey();
+    assert_eq!(
+        map_scope_error(
+            ctx.send_transaction(&[refresh_ix(&feed, Some(admin))])
+                .await
+        ),
+        ScopeError::UnauthorizedRefresh
+    );
+
+    let bot = ctx.bot.insecure_clone();
+    assert_eq!(
+        map_anchor_error(
+            operations::set_refresh_authority(&mut ctx, &feed, &bot, Pubkey::default()).await
+        ),
+        AnchorErrorCode::ConstraintHasOne
+    );
+    let accounts = scope::accounts::UpdateOracleMapping {
+        admin: bot.pubkey(),
+        configuration: feed.conf,
+        oracle_mappings: feed.mapping,
+        price_info: Some(TEST_PYTH_ORACLE.pubkey),
+    };
+    let update_mapping_ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: scope::instruction::UpdateMapping {
+            feed_name: feed.feed_name.clone(),
+            token: 1,
+            price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+            price_max_age: 0,
+        }
+        .data(),
+    };
+    assert_eq!(
+        map_anchor_error(ctx.send_transaction_with_bot(&[update_mapping_ix]).await),
+        AnchorErrorCode::ConstraintHasOne
+    );
+}
+
+// Removing the refresh authority lets anyone refresh the prices again
+#[tokio::test]
+async fn test_refresh_authority_removed() {
+    let (mut ctx, feed) = setup_feed_with_refresh_authority().await;
+
+    let admin = ctx.admin.insecure_clone();
+    operations::set_refresh_authority(&mut ctx, &feed, &admin, Pubkey::default())
+        .await
+        .unwrap();
+    operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[TEST_PYTH_ORACLE.token].price,
+        Price { value: 42, exp: 6 }
+    );
+}
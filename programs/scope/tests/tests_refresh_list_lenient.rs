@@ -0,0 +1,148 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    InstructionData,
+};
+use common::*;
+use mock_oracles::pyth;
+use scope::{OraclePrices, Price, ScopeError};
+use solana_program::instruction::Instruction;
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const TEST_ORACLE_CONF: [OracleConf; 4] = [
+    OracleConf {
+        pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+        token: 0,
+        price_type: TestOracleType::Pyth,
+    },
+    OracleConf {
+        pubkey: pubkey!("SomePyth2PriceAccount1111111111111111111111"),
+        token: 1,
+        price_type: TestOracleType::Pyth,
+    },
+    OracleConf {
+        pubkey: pubkey!("SomePyth3PriceAccount1111111111111111111111"),
+        token: 2,
+        price_type: TestOracleType::Pyth,
+    },
+    OracleConf {
+        pubkey: pubkey!("SomePyth4PriceAccount1111111111111111111111"),
+        token: 3,
+        price_type: TestOracleType::Pyth,
+    },
+];
+
+/// Feed with new prices for all the tokens, the price account of token 1 cannot be parsed
+/// and the one of token 3 is replaced by another account in the refresh
+async fn setup_mixed_refresh() -> (TestContext, ScopeFeedDefinition, Instruction, Instruction) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, TEST_ORACLE_CONF.to_vec()).await;
+    for (i, conf) in TEST_ORACLE_CONF.iter().enumerate() {
+        mock_oracles::set_price(
+            &mut ctx,
+            &feed,
+            conf,
+            &Price {
+                value: 100 + i as u64,
+                exp: 6,
+            },
+        )
+        .await;
+    }
+    ctx.set_account(&TEST_ORACLE_CONF[1].pubkey, vec![0; 100], &pyth::id());
+    let fake_price_account = Pubkey::new_unique();
+    ctx.clone_account(&TEST_ORACLE_CONF[3].pubkey, &fake_price_account)
+        .await;
+
+    let mut accounts = operations::get_refresh_list_ix_accounts(&mut ctx, &feed, &[]).await;
+    for conf in &TEST_ORACLE_CONF[..3] {
+        accounts.push(AccountMeta::new_readonly(conf.pubkey, false));
+    }
+    accounts.push(AccountMeta::new_readonly(fake_price_account, false));
+    let tokens: Vec<u16> = TEST_ORACLE_CONF
+        .iter()
+        .map(|conf| conf.token.try_into().unwrap())
+        .collect();
+
+    let strict_ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.clone(),
+        data: scope::instruction::RefreshPriceList {
+            tokens: tokens.clone(),
+        }
+        .data(),
+    };
+    let lenient_ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: scope::instruction::RefreshPriceListLenient { tokens }.data(),
+    };
+    (ctx, feed, strict_ix, lenient_ix)
+}
+
+// An account not matching its token fails the whole strict refresh
+#[tokio::test]
+async fn test_strict_refresh_list_fails_on_wrong_account() {
+    let (mut ctx, feed, strict_ix, _) = setup_mixed_refresh().await;
+    let before: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+
+    assert_eq!(
+        map_scope_error(ctx.send_transaction_with_bot(&[strict_ix]).await),
+        ScopeError::UnexpectedAccount
+    );
+    let after: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(before.prices, after.prices);
+}
+
+// The lenient refresh commits the valid prices before the wrong account
+#[tokio::test]
+async fn test_lenient_refresh_list_keeps_valid_prices() {
+    let (mut ctx, feed, _, lenient_ix) = setup_mixed_refresh().await;
+    let before: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+
+    ctx.send_transaction_with_bot(&[lenient_ix]).await.unwrap();
+
+    let after: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    for token in [0, 2] {
+        assert_eq!(
+            after.prices[token].price,
+            Price {
+                value: 100 + token as u64,
+                exp: 6
+            }
+        );
+    }
+    // Invalid price account and wrong account
+    for token in [1, 3] {
+        assert_eq!(after.prices[token], before.prices[token]);
+    }
+}
+
+// Once an account does not match, the following tokens are skipped
+#[tokio::test]
+async fn test_lenient_refresh_list_skips_after_wrong_account() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, TEST_ORACLE_CONF.to_vec()).await;
+    for conf in &TEST_ORACLE_CONF {
+        mock_oracles::set_price(&mut ctx, &feed, conf, &Price { value: 42, exp: 6 }).await;
+    }
+    let before: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+
+    // Token 0 is given the account of token 1
+    let confs = [
+        OracleConf {
+            pubkey: TEST_ORACLE_CONF[1].pubkey,
+            ..TEST_ORACLE_CONF[0]
+        },
+        TEST_ORACLE_CONF[1],
+    ];
+    operations::refresh_price_list_lenient(&mut ctx, &feed, &confs)
+        .await
+        .unwrap();
+
+    let after: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(after.prices[..2], before.prices[..2]);
+}
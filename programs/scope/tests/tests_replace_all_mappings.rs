@@ -0,0 +1,76 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use scope::{OracleMappings, Price, ScopeError};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+fn pyth_conf(token: usize) -> OracleConf {
+    OracleConf {
+        pubkey: Pubkey::new_unique(),
+        token,
+        price_type: TestOracleType::Pyth,
+    }
+}
+
+fn switchboard_conf(token: usize) -> OracleConf {
+    OracleConf {
+        pubkey: Pubkey::new_unique(),
+        token,
+        price_type: TestOracleType::SwitchboardV2,
+    }
+}
+
+// Replace a full mapping set in one instruction
+#[tokio::test]
+async fn test_replace_all_mappings() {
+    let initial_mapping = vec![pyth_conf(0), pyth_conf(1), pyth_conf(2)];
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, initial_mapping).await;
+
+    // Token 1 is kept with a new account, token 0 and 2 are removed, token 3 and 4 are added
+    let new_mapping = vec![pyth_conf(1), switchboard_conf(3), pyth_conf(4)];
+    for conf in &new_mapping {
+        mock_oracles::set_price(&mut ctx, &feed, conf, &Price::default()).await;
+    }
+
+    operations::replace_all_mappings(&mut ctx, &feed, &new_mapping)
+        .await
+        .unwrap();
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    for conf in &new_mapping {
+        assert_eq!(mappings.price_info_accounts[conf.token], conf.pubkey);
+        assert_eq!(mappings.price_types[conf.token], conf.price_type.to_u8());
+    }
+    for token in [0, 2] {
+        assert_eq!(mappings.price_info_accounts[token], Pubkey::default());
+    }
+}
+
+// An invalid entry leaves the whole mapping untouched
+#[tokio::test]
+async fn test_replace_all_mappings_duplicated_token() {
+    let initial_mapping = vec![pyth_conf(0), pyth_conf(1)];
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, initial_mapping).await;
+    let mappings_before: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+
+    let new_mapping = vec![pyth_conf(2), pyth_conf(3), pyth_conf(2)];
+    for conf in &new_mapping {
+        mock_oracles::set_price(&mut ctx, &feed, conf, &Price::default()).await;
+    }
+
+    assert_eq!(
+        map_scope_error(operations::replace_all_mappings(&mut ctx, &feed, &new_mapping).await),
+        ScopeError::DuplicatedMappingToken
+    );
+
+    let mappings_after: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        mappings_after.price_info_accounts,
+        mappings_before.price_info_accounts
+    );
+    assert_eq!(mappings_after.price_types, mappings_before.price_types);
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use scope::{oracles::fixed_price, DatedPrice, OracleMappings, OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+fn fixed_price_conf(token: usize, value: u64) -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&Price { value, exp: 2 }),
+        token,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+// The mapping and the price of the token are cleared, the other tokens are untouched
+#[tokio::test]
+async fn test_reset_oracle_mapping() {
+    let retired = fixed_price_conf(0, 100);
+    let kept = fixed_price_conf(1, 200);
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![retired, kept]).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[retired, kept])
+        .await
+        .unwrap();
+
+    operations::reset_oracle_mapping(&mut ctx, &feed, 0)
+        .await
+        .unwrap();
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        mappings.price_info_accounts[retired.token],
+        Pubkey::default()
+    );
+    assert_eq!(mappings.price_info_accounts[kept.token], kept.pubkey);
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[retired.token], DatedPrice::default());
+    assert_eq!(data.prices[kept.token].price.value, 200);
+
+    // The retired token is not refreshed anymore
+    ctx.fast_forward_seconds(10).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[retired, kept])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[retired.token], DatedPrice::default());
+}
+
+/// Set every per-token configuration of `token` to a non default value
+fn configure_token(mappings: &mut OracleMappings, token: usize) {
+    let price = Price { value: 1, exp: 2 };
+    mappings.hard_caps[token] = price;
+    mappings.max_source_ages[token] = 1;
+    mappings.source_identities[token] = Pubkey::new_unique();
+    mappings.underlying_prices[token] = [1, 2];
+    mappings.max_aum_ages[token] = 1;
+    mappings.max_std_deviations_bps[token] = 1;
+    mappings.max_deviations_bps[token] = 1;
+    mappings.max_confidences_bps[token] = 1;
+    mappings.confidence_policies[token] = 1;
+    mappings.price_max_ages[token] = 1;
+    mappings.price_bounds[token] = [price; 2];
+    mappings.pyth_lazer_feed_ids[token] = 1;
+}
+
+// The configuration of the retired token is cleared so that a token mapped later at the same
+// index does not inherit it
+#[tokio::test]
+async fn test_reset_oracle_mapping_clears_config() {
+    let retired = fixed_price_conf(0, 100);
+    let kept = fixed_price_conf(1, 200);
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![retired, kept]).await;
+    let mut mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    configure_token(&mut mappings, retired.token);
+    configure_token(&mut mappings, kept.token);
+    let kept_mappings = mappings;
+    ctx.set_zero_copy_account(&feed.mapping, &mappings);
+
+    operations::reset_oracle_mapping(&mut ctx, &feed, 0)
+        .await
+        .unwrap();
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    let token = retired.token;
+    assert_eq!(mappings.price_types[token], 0);
+    assert_eq!(mappings.hard_caps[token], Price::default());
+    assert_eq!(mappings.max_source_ages[token], 0);
+    assert_eq!(mappings.source_identities[token], Pubkey::default());
+    assert_eq!(mappings.underlying_prices[token], [0; 2]);
+    assert_eq!(mappings.max_aum_ages[token], 0);
+    assert_eq!(mappings.max_std_deviations_bps[token], 0);
+    assert_eq!(mappings.max_deviations_bps[token], 0);
+    assert_eq!(mappings.max_confidences_bps[token], 0);
+    assert_eq!(mappings.confidence_policies[token], 0);
+    assert_eq!(mappings.price_max_ages[token], 0);
+    assert_eq!(mappings.price_bounds[token], [Price::default(); 2]);
+    assert_eq!(mappings.pyth_lazer_feed_ids[token], 0);
+
+    // The configuration of the other tokens is untouched
+    let token = kept.token;
+    assert_eq!(
+        mappings.price_types[token],
+        kept_mappings.price_types[token]
+    );
+    assert_eq!(mappings.hard_caps[token], kept_mappings.hard_caps[token]);
+    assert_eq!(
+        mappings.source_identities[token],
+        kept_mappings.source_identities[token]
+    );
+    assert_eq!(
+        mappings.price_bounds[token],
+        kept_mappings.price_bounds[token]
+    );
+}
+
+#[tokio::test]
+async fn test_reset_oracle_mapping_out_of_range() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::reset_oracle_mapping(&mut ctx, &feed, scope::MAX_ENTRIES as u64).await
+        ),
+        ScopeError::BadTokenNb
+    );
+}
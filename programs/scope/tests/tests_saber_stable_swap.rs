@@ -0,0 +1,155 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::{constant_product_lp, saber_stable_swap};
+use scope::{oracles::saber_stable_swap::SwapInfo, OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const LP_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SaberSwap1111111111111111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::SaberStableSwapLp,
+};
+
+// USDC/USDT like pool, all mints with 6 decimals
+const SWAP: SwapInfo = SwapInfo {
+    initial_amp_factor: 100,
+    target_amp_factor: 100,
+    start_ramp_ts: 0,
+    stop_ramp_ts: 0,
+    token_a: pubkey!("VauitA1111111111111111111111111111111111111"),
+    token_b: pubkey!("VauitB1111111111111111111111111111111111111"),
+    pool_mint: pubkey!("LpMint1111111111111111111111111111111111111"),
+    token_a_mint: pubkey!("MintA11111111111111111111111111111111111111"),
+    token_b_mint: pubkey!("MintB11111111111111111111111111111111111111"),
+};
+
+// 2M LP tokens
+const LP_SUPPLY: u64 = 2_000_000_000_000;
+
+fn set_pool(ctx: &mut TestContext, swap: &SwapInfo, reserve_a: u64, reserve_b: u64) {
+    ctx.set_account(
+        &LP_CONF.pubkey,
+        saber_stable_swap::get_swap_info_data(swap),
+        &saber_stable_swap::SABER_STABLE_SWAP_PROGRAM_ID,
+    );
+    let token_program = constant_product_lp::token_program_id();
+    ctx.set_account(
+        &SWAP.token_a,
+        constant_product_lp::get_token_account_data(&SWAP.token_a_mint, reserve_a),
+        &token_program,
+    );
+    ctx.set_account(
+        &SWAP.token_b,
+        constant_product_lp::get_token_account_data(&SWAP.token_b_mint, reserve_b),
+        &token_program,
+    );
+    ctx.set_account(
+        &SWAP.pool_mint,
+        constant_product_lp::get_mint_data(LP_SUPPLY, 6),
+        &token_program,
+    );
+    for mint in [SWAP.token_a_mint, SWAP.token_b_mint] {
+        ctx.set_account(
+            &mint,
+            constant_product_lp::get_mint_data(0, 6),
+            &token_program,
+        );
+    }
+}
+
+// A balanced pool is at par, an imbalanced one is below
+#[tokio::test]
+async fn test_saber_stable_swap_refresh() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, &SWAP, 1_000_000_000_000, 1_000_000_000_000);
+    operations::update_oracle_mapping(&mut ctx, &feed, &LP_CONF).await;
+
+    operations::refresh_price(&mut ctx, &feed, &LP_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let lp = data.prices[LP_CONF.token];
+    assert_eq!(
+        lp.price,
+        Price {
+            value: 1_000_000_000_000,
+            exp: 12
+        }
+    );
+    assert!(lp.last_updated_slot > 0);
+
+    // 95% of the pool in token A
+    ctx.fast_forward_seconds(10).await;
+    set_pool(&mut ctx, &SWAP, 1_900_000_000_000, 100_000_000_000);
+    operations::refresh_price(&mut ctx, &feed, &LP_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[LP_CONF.token].price,
+        Price {
+            value: 980_123_072_199,
+            exp: 12
+        }
+    );
+}
+
+// A pool too imbalanced for the invariant to be computed is not refreshed
+#[tokio::test]
+async fn test_saber_stable_swap_not_converging() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, &SWAP, 1_000_000_000_000, 1_000_000_000_000);
+    operations::update_oracle_mapping(&mut ctx, &feed, &LP_CONF).await;
+
+    let low_amp_swap = SwapInfo {
+        initial_amp_factor: 1,
+        target_amp_factor: 1,
+        ..SWAP
+    };
+    set_pool(&mut ctx, &low_amp_swap, 1_000_000_000_000, 1);
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &LP_CONF).await),
+        ScopeError::InvariantNotConverged
+    );
+}
+
+// Only accounts owned by the Saber stable swap program can be mapped
+#[tokio::test]
+async fn test_saber_stable_swap_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    ctx.set_account(
+        &LP_CONF.pubkey,
+        saber_stable_swap::get_swap_info_data(&SWAP),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &LP_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+// A mapped pool reowned by another program is rejected at refresh
+#[tokio::test]
+async fn test_saber_stable_swap_refresh_reowned_pool() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, &SWAP, 1_000_000_000_000, 1_000_000_000_000);
+    operations::update_oracle_mapping(&mut ctx, &feed, &LP_CONF).await;
+    operations::refresh_price(&mut ctx, &feed, &LP_CONF).await;
+
+    // Same data, different owner
+    ctx.fast_forward_seconds(10).await;
+    ctx.set_account(
+        &LP_CONF.pubkey,
+        saber_stable_swap::get_swap_info_data(&SWAP),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &LP_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::*;
+use scope::{
+    oracles::{derived_price, fixed_price},
+    OraclePrices, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const ST_SOL_SOL: Price = Price {
+    value: 1_100_000_000_000_000,
+    exp: 15,
+};
+const SOL_USD: Price = Price {
+    value: 2_081_000_000,
+    exp: 8,
+};
+
+fn fixed_price_conf(token: usize, price: &Price) -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(price),
+        token,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn derived_conf(
+    token: usize,
+    price_type: TestOracleType,
+    token_a: u16,
+    token_b: u16,
+) -> OracleConf {
+    OracleConf {
+        pubkey: derived_price::sources_to_mapping_pubkey(token_a, token_b),
+        token,
+        price_type,
+    }
+}
+
+fn product_conf(token: usize, token_a: u16, token_b: u16) -> OracleConf {
+    derived_conf(token, TestOracleType::ScopeChainProduct, token_a, token_b)
+}
+
+#[tokio::test]
+async fn test_product_refresh() {
+    let st_sol_sol = fixed_price_conf(0, &ST_SOL_SOL);
+    let sol_usd = fixed_price_conf(1, &SOL_USD);
+    let st_sol_usd = product_conf(2, 0, 1);
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![st_sol_sol, sol_usd, st_sol_usd]).await;
+
+    // Sources are not refreshed yet
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &st_sol_usd).await),
+        ScopeError::PriceNotValid
+    );
+
+    operations::refresh_price(&mut ctx, &feed, &st_sol_sol).await;
+    ctx.fast_forward_seconds(10).await;
+    operations::refresh_price(&mut ctx, &feed, &sol_usd).await;
+    operations::refresh_price(&mut ctx, &feed, &st_sol_usd).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let derived = data.prices[st_sol_usd.token];
+    assert_eq!(
+        derived.price,
+        Price {
+            value: 2_289_100_000_000_000_000,
+            exp: 17
+        }
+    );
+    // The derived price is as old as its oldest source
+    let oldest = data.prices[st_sol_sol.token];
+    assert!(oldest.last_updated_slot < data.prices[sol_usd.token].last_updated_slot);
+    assert_eq!(derived.last_updated_slot, oldest.last_updated_slot);
+    assert_eq!(derived.unix_timestamp, oldest.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_product_self_reference_is_rejected() {
+    let sol_usd = fixed_price_conf(0, &SOL_USD);
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![sol_usd]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &product_conf(1, 0, 1)).await
+        ),
+        ScopeError::CyclicDerivedPrice
+    );
+
+    // Indirect cycle: 2 = 0 * 1 then 1 = 2 * 0
+    operations::update_oracle_mapping(&mut ctx, &feed, &product_conf(2, 0, 1)).await;
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &product_conf(1, 2, 0)).await
+        ),
+        ScopeError::CyclicDerivedPrice
+    );
+}
+
+#[tokio::test]
+async fn test_quotient_and_inverse_refresh_list() {
+    let usdc_usd = fixed_price_conf(
+        0,
+        &Price {
+            value: 1_000_000,
+            exp: 6,
+        },
+    );
+    let sol_usd = fixed_price_conf(1, &SOL_USD);
+    let usdc_sol = derived_conf(2, TestOracleType::ScopeChainQuotient, 0, 1);
+    let usd_sol = derived_conf(3, TestOracleType::ScopeChainInverse, 1, 1);
+    let (mut ctx, feed) = fixtures::setup_scope(
+        DEFAULT_FEED_NAME,
+        vec![usdc_usd, sol_usd, usdc_sol, usd_sol],
+    )
+    .await;
+
+    // Derived prices listed before their sources are rejected
+    assert_eq!(
+        map_scope_error(
+            operations::refresh_price_list(&mut ctx, &feed, &[usdc_sol, usdc_usd, sol_usd]).await
+        ),
+        ScopeError::DerivedPriceBeforeSources
+    );
+
+    // Sources and derived prices refreshed in the same transaction
+    operations::refresh_price_list(&mut ctx, &feed, &[usdc_usd, sol_usd, usdc_sol, usd_sol])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let expected = Price {
+        value: 48_053_820_278_712_157,
+        exp: 18,
+    };
+    assert_eq!(data.prices[usdc_sol.token].price, expected);
+    assert_eq!(data.prices[usd_sol.token].price, expected);
+}
+
+#[tokio::test]
+async fn test_inverse_of_two_tokens_is_rejected() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(
+                &mut ctx,
+                &feed,
+                &derived_conf(2, TestOracleType::ScopeChainInverse, 0, 1)
+            )
+            .await
+        ),
+        ScopeError::UnexpectedAccount
+    );
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::*;
+use scope::{
+    oracles::{fixed_price, median_price},
+    OraclePrices, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+// The same SOL/USD price from three sources with different exponents
+const PYTH_SOL_USD: Price = Price {
+    value: 20_810_000_000,
+    exp: 8,
+};
+const SWITCHBOARD_SOL_USD: Price = Price {
+    value: 20_795,
+    exp: 2,
+};
+const TWAP_SOL_USD: Price = Price {
+    value: 208_300_000,
+    exp: 6,
+};
+
+fn fixed_price_conf(token: usize, price: &Price) -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(price),
+        token,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn median_conf(token: usize, sources: &[u16]) -> OracleConf {
+    OracleConf {
+        pubkey: median_price::sources_to_mapping_pubkey(sources),
+        token,
+        price_type: TestOracleType::ScopeMedian,
+    }
+}
+
+fn sources_confs() -> [OracleConf; 3] {
+    [
+        fixed_price_conf(0, &PYTH_SOL_USD),
+        fixed_price_conf(1, &SWITCHBOARD_SOL_USD),
+        fixed_price_conf(2, &TWAP_SOL_USD),
+    ]
+}
+
+#[tokio::test]
+async fn test_median_of_three_sources() {
+    let [pyth, switchboard, twap] = sources_confs();
+    let median = median_conf(3, &[0, 1, 2]);
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![pyth, switchboard, twap, median]).await;
+
+    operations::refresh_price(&mut ctx, &feed, &twap).await;
+    ctx.fast_forward_seconds(10).await;
+    operations::refresh_price_list(&mut ctx, &feed, &[pyth, switchboard, median])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let median_price = data.prices[median.token];
+    // 207.95 < 208.10 < 208.30, kept with the exponent of its source
+    assert_eq!(median_price.price, PYTH_SOL_USD);
+    // The median is as old as its oldest source
+    let oldest = data.prices[twap.token];
+    assert!(oldest.last_updated_slot < data.prices[pyth.token].last_updated_slot);
+    assert_eq!(median_price.last_updated_slot, oldest.last_updated_slot);
+    assert_eq!(median_price.unix_timestamp, oldest.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_median_requires_two_valid_sources() {
+    let [pyth, switchboard, twap] = sources_confs();
+    let median = median_conf(3, &[0, 1, 2]);
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![pyth, switchboard, twap, median]).await;
+
+    // Only the pyth source is refreshed
+    operations::refresh_price(&mut ctx, &feed, &pyth).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &median).await),
+        ScopeError::PriceNotValid
+    );
+
+    // The twap source is never refreshed, the median is the mean of the two others
+    operations::refresh_price_list(&mut ctx, &feed, &[switchboard, median])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[median.token].price,
+        Price {
+            value: 208_025_000_000,
+            exp: 9
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_median_invalid_sources_are_rejected() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    // Duplicated source
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &median_conf(3, &[0, 1, 0]))
+                .await
+        ),
+        ScopeError::UnexpectedAccount
+    );
+    // Self reference
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &median_conf(3, &[0, 1, 3]))
+                .await
+        ),
+        ScopeError::CyclicDerivedPrice
+    );
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use scope::{
+    oracles::{fixed_price, scope_twap},
+    DatedPrice, OraclePrices, OracleTwaps, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const HALF_LIFE_SLOTS: u64 = 100;
+
+const PREVIOUS_PRICE: Price = Price {
+    value: 1_000_000,
+    exp: 3,
+};
+const SPOT_PRICE: Price = Price {
+    value: 2_000_000,
+    exp: 3,
+};
+
+const SOURCE_TOKEN: usize = 0;
+
+fn source_conf() -> OracleConf {
+    OracleConf {
+        pubkey: fixed_price::price_to_mapping_pubkey(&SPOT_PRICE),
+        token: SOURCE_TOKEN,
+        price_type: TestOracleType::FixedPrice,
+    }
+}
+
+fn ema_conf(token: usize) -> OracleConf {
+    OracleConf {
+        pubkey: scope_twap::source_to_mapping_pubkey(SOURCE_TOKEN.try_into().unwrap()),
+        token,
+        price_type: TestOracleType::ScopeTwap,
+    }
+}
+
+/// Feed whose source EMA was started at `PREVIOUS_PRICE` one half-life ago
+async fn setup_feed_with_ema() -> (TestContext, ScopeFeedDefinition, Pubkey) {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![source_conf(), ema_conf(1)]).await;
+    ctx.fast_forward_seconds(60).await;
+    let clock = ctx.get_clock().await;
+
+    // The TWAP account is too big for the stack, build it in an aligned buffer
+    let size = std::mem::size_of::<OracleTwaps>();
+    let mut words = vec![0_u64; size / 8 + 1];
+    let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+    let oracle_twaps: &mut OracleTwaps = bytemuck::from_bytes_mut(&mut bytes[..size]);
+    oracle_twaps.oracle_prices = feed.prices;
+    let twap_buffer = &mut oracle_twaps.twaps[SOURCE_TOKEN];
+    twap_buffer.reset_ema(HALF_LIFE_SLOTS);
+    twap_buffer
+        .push_sample(&DatedPrice {
+            price: PREVIOUS_PRICE,
+            last_updated_slot: clock.slot - HALF_LIFE_SLOTS,
+            unix_timestamp: clock.unix_timestamp as u64 - 40,
+            ..Default::default()
+        })
+        .unwrap();
+    let oracle_twaps_pk = Pubkey::new_unique();
+    ctx.set_zero_copy_account(&oracle_twaps_pk, oracle_twaps);
+
+    (ctx, feed, oracle_twaps_pk)
+}
+
+#[tokio::test]
+async fn test_ema_price_follows_refreshes() {
+    let (mut ctx, feed, oracle_twaps) = setup_feed_with_ema().await;
+    let ema = ema_conf(1);
+
+    operations::refresh_price_list_with_twaps(
+        &mut ctx,
+        &feed,
+        &oracle_twaps,
+        &[source_conf(), ema],
+    )
+    .await
+    .unwrap();
+
+    let twaps: OracleTwaps = ctx.get_zero_copy_account(&oracle_twaps).await.unwrap();
+    let source_ema = twaps.twaps[SOURCE_TOKEN].ema;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let ema_price = data.prices[ema.token];
+    // The EMA moved towards the refreshed price, about half way after one half-life
+    assert_eq!(ema_price.price, source_ema.price);
+    assert_eq!(ema_price.price.exp, SPOT_PRICE.exp);
+    assert!(ema_price.price.value > PREVIOUS_PRICE.value);
+    assert!(ema_price.price.value < SPOT_PRICE.value);
+    assert_eq!(
+        ema_price.last_updated_slot,
+        data.prices[SOURCE_TOKEN].last_updated_slot
+    );
+
+    // The EMA keeps converging on the next refreshes
+    ctx.fast_forward_seconds(400).await;
+    operations::refresh_price_list_with_twaps(
+        &mut ctx,
+        &feed,
+        &oracle_twaps,
+        &[source_conf(), ema],
+    )
+    .await
+    .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let converged = data.prices[ema.token].price;
+    assert!(converged.value > ema_price.price.value);
+    assert!(SPOT_PRICE.value - converged.value < 1_000);
+}
+
+#[tokio::test]
+async fn test_ema_price_requires_twaps() {
+    let (mut ctx, feed, _) = setup_feed_with_ema().await;
+
+    operations::refresh_price(&mut ctx, &feed, &source_conf()).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &ema_conf(1)).await),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+// Once the feed has a TWAP account, no price can be refreshed without sampling it
+#[tokio::test]
+async fn test_refresh_requires_feed_twaps() {
+    let (mut ctx, feed, oracle_twaps) = setup_feed_with_ema().await;
+    let mut data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    data.oracle_twaps = oracle_twaps;
+    ctx.set_zero_copy_account(&feed.prices, &data);
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &source_conf()).await),
+        ScopeError::UnexpectedAccount
+    );
+    assert_eq!(
+        map_scope_error(operations::refresh_price_list(&mut ctx, &feed, &[source_conf()]).await),
+        ScopeError::UnexpectedAccount
+    );
+
+    // Another TWAP account of the same prices is rejected as well
+    let other_twaps = Pubkey::new_unique();
+    ctx.clone_account(&oracle_twaps, &other_twaps).await;
+    assert_eq!(
+        map_scope_error(
+            operations::refresh_price_list_with_twaps(
+                &mut ctx,
+                &feed,
+                &other_twaps,
+                &[source_conf()]
+            )
+            .await
+        ),
+        ScopeError::UnexpectedAccount
+    );
+
+    operations::refresh_price_list_with_twaps(&mut ctx, &feed, &oracle_twaps, &[source_conf()])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_ema_price_of_itself_is_rejected() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &ema_conf(SOURCE_TOKEN)).await
+        ),
+        ScopeError::CyclicDerivedPrice
+    );
+}
@@ -42,6 +42,7 @@ const TEST_ORACLE_CONF: [OracleConf; 4] = [
 const TEST_ORACLE_CONF: [OracleConf; 2] = [TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE];
 
 // - [x] Wrong oracle mapping
+// - [x] Same account as oracle prices and oracle mapping
 // - [x] Wrong oracle account (copy)
 // - [x] Wrong oracle account (mixing indexes)
 // - [x] Wrong sysvar instruction account
@@ -79,6 +80,8 @@ async fn test_working_refresh_list() {
         oracle_mappings: feed.mapping,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     for conf in TEST_ORACLE_CONF.iter() {
@@ -136,6 +139,8 @@ async fn test_wrong_oracle_mapping() {
         oracle_mappings: fake_mapping_pk,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     for conf in TEST_ORACLE_CONF.iter() {
@@ -159,6 +164,43 @@ async fn test_wrong_oracle_mapping() {
     );
 }
 
+// - [ ] Same account as oracle prices and oracle mapping
+#[tokio::test]
+async fn test_same_prices_and_mapping_account() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, TEST_ORACLE_CONF.to_vec()).await;
+
+    // Refresh
+    let mut accounts = scope::accounts::RefreshList {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.prices,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    for conf in TEST_ORACLE_CONF.iter() {
+        let mut refresh_accounts = utils::get_refresh_list_accounts(&mut ctx, conf).await;
+        accounts.append(&mut refresh_accounts);
+    }
+
+    let args = scope::instruction::RefreshPriceList {
+        tokens: TEST_ORACLE_CONF.map(|conf| conf.token as u16).to_vec(),
+    };
+
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+
+    // Rejected by the typed mapping account before reaching the explicit key check
+    assert_eq!(
+        map_anchor_error(ctx.send_transaction_with_bot(&[ix]).await),
+        AnchorErrorCode::AccountDiscriminatorMismatch,
+    );
+}
+
 // - [ ] Wrong oracle account (copy)
 #[tokio::test]
 async fn test_wrong_oracle_account_with_copy() {
@@ -189,6 +231,8 @@ async fn test_wrong_oracle_account_with_copy() {
         oracle_mappings: feed.mapping,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     for conf in TEST_ORACLE_CONF.iter() {
@@ -243,6 +287,8 @@ async fn test_wrong_index_oracle_account() {
         oracle_mappings: feed.mapping,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
 
@@ -299,6 +345,8 @@ async fn test_wrong_sysvar_instructions() {
         oracle_mappings: feed.mapping,
         clock: Clock::id(),
         instruction_sysvar_account_info: wrong_sysvar_account,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     for conf in TEST_ORACLE_CONF.iter() {
@@ -345,6 +393,8 @@ async fn test_refresh_through_cpi() {
         oracle_mappings: feed.mapping,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     for conf in TEST_ORACLE_CONF.iter() {
@@ -397,6 +447,7 @@ async fn test_refresh_with_unexpected_ix() {
         feed_name: feed.feed_name.clone(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let extra_ix = Instruction {
@@ -411,6 +462,8 @@ async fn test_refresh_with_unexpected_ix() {
         oracle_mappings: feed.mapping,
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
     }
     .to_account_metas(None);
     for conf in TEST_ORACLE_CONF.iter() {
@@ -488,6 +541,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -559,6 +614,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -637,6 +694,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -712,6 +771,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -786,6 +847,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -856,6 +919,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -930,6 +995,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -1001,6 +1068,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -1075,6 +1144,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
@@ -1146,6 +1217,8 @@ mod ktoken_tests {
             oracle_mappings: feed.mapping,
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         for conf in TEST_ORACLE_CONF.iter() {
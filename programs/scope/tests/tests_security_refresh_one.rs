@@ -32,8 +32,10 @@ const TEST_PYTH2_ORACLE: OracleConf = OracleConf {
 };
 
 // - [x] Wrong oracle mapping
+// - [x] Same account as oracle prices and oracle mapping
 // - [x] Wrong oracle account (copy)
 // - [x] Wrong oracle account (mixing indexes)
+// - [x] Out of range token index
 // - [x] Wrong sysvar instruction account
 // - [x] Instruction executed in CPI
 // - [x] Instruction preceded by non ComputeBudget instruction
@@ -66,6 +68,8 @@ async fn test_working_refresh_one() {
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
         price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice {
@@ -111,6 +115,8 @@ async fn test_wrong_oracle_mapping() {
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
         price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice {
@@ -129,6 +135,39 @@ async fn test_wrong_oracle_mapping() {
     );
 }
 
+// - [ ] Same account as oracle prices and oracle mapping
+#[tokio::test]
+async fn test_same_prices_and_mapping_account() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+
+    // Refresh
+    let accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.prices,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
+    };
+
+    let args = scope::instruction::RefreshOnePrice {
+        token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
+    };
+
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+
+    // Rejected by the typed mapping account before reaching the explicit key check
+    assert_eq!(
+        map_anchor_error(ctx.send_transaction_with_bot(&[ix]).await),
+        AnchorErrorCode::AccountDiscriminatorMismatch,
+    );
+}
+
 // - [ ] Wrong oracle account (copy)
 #[tokio::test]
 async fn test_wrong_oracle_account_with_copy() {
@@ -155,6 +194,8 @@ async fn test_wrong_oracle_account_with_copy() {
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
         price_info: fake_price_account,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice {
@@ -195,6 +236,8 @@ async fn test_wrong_index_oracle_account() {
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
         price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice { token: 1 };
@@ -211,6 +254,37 @@ async fn test_wrong_index_oracle_account() {
     );
 }
 
+// - [x] Out of range token index
+#[tokio::test]
+async fn test_out_of_range_token() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+
+    let accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
+    };
+
+    let args = scope::instruction::RefreshOnePrice {
+        token: scope::MAX_ENTRIES.try_into().unwrap(),
+    };
+
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+
+    assert_eq!(
+        map_scope_error(ctx.send_transaction_with_bot(&[ix]).await),
+        ScopeError::BadTokenNb,
+    );
+}
+
 // - [ ] Wrong sysvar instruction account
 #[tokio::test]
 async fn test_wrong_sysvar_instructions() {
@@ -238,6 +312,8 @@ async fn test_wrong_sysvar_instructions() {
         clock: Clock::id(),
         instruction_sysvar_account_info: wrong_sysvar_account,
         price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice {
@@ -276,6 +352,8 @@ async fn test_refresh_through_cpi() {
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
         price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice {
@@ -318,6 +396,7 @@ async fn test_refresh_with_unexpected_ix() {
         feed_name: feed.feed_name.clone(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let extra_ix = Instruction {
@@ -333,6 +412,8 @@ async fn test_refresh_with_unexpected_ix() {
         clock: Clock::id(),
         instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
         price_info: TEST_PYTH_ORACLE.pubkey,
+        oracle_twaps: None,
+        refresh_authority: None,
     };
 
     let args = scope::instruction::RefreshOnePrice {
@@ -390,6 +471,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_ORCA_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -436,6 +519,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_RAYDIUM_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -496,6 +581,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_ORCA_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -554,6 +641,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_RAYDIUM_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -616,6 +705,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_ORCA_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -678,6 +769,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_RAYDIUM_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -736,6 +829,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_ORCA_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -793,6 +888,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_RAYDIUM_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -851,6 +948,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_ORCA_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -909,6 +1008,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_RAYDIUM_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -967,6 +1068,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_ORCA_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -1025,6 +1128,8 @@ mod ktoken_tests {
             clock: Clock::id(),
             instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
             price_info: TEST_RAYDIUM_KTOKEN_ORACLE.pubkey,
+            oracle_twaps: None,
+            refresh_authority: None,
         }
         .to_account_metas(None);
         let mut refresh_accounts =
@@ -2,13 +2,16 @@ mod common;
 
 use anchor_lang::{prelude::Pubkey, InstructionData, ToAccountMetas};
 use common::*;
-use scope::Price;
+use scope::{Price, ScopeError};
 use solana_program::instruction::Instruction;
 use solana_program_test::tokio;
 use solana_sdk::{pubkey, signature::Keypair, signer::Signer};
 use types::*;
 
-use crate::{common::utils::AnchorErrorCode, utils::map_anchor_error};
+use crate::{
+    common::utils::AnchorErrorCode,
+    utils::{map_anchor_error, map_scope_error},
+};
 
 const TEST_PYTH_ORACLE: OracleConf = OracleConf {
     pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
@@ -20,6 +23,7 @@ const TEST_PYTH_ORACLE: OracleConf = OracleConf {
 // - [x] Wrong config account
 // - [x] Wrong mapping account
 // - [x] Wrong admin
+// - [x] Out of range token index
 
 // Working update mapping
 #[tokio::test]
@@ -39,6 +43,7 @@ async fn test_working_update_mapping() {
         feed_name: feed.feed_name.clone(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let ix = Instruction {
@@ -68,6 +73,7 @@ async fn test_wrong_feed_name() {
         feed_name: "randomFeed".to_string(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let ix = Instruction {
@@ -105,6 +111,7 @@ async fn test_wrong_config_account() {
         feed_name: feed.feed_name.clone(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let ix = Instruction {
@@ -142,6 +149,7 @@ async fn test_wrong_mapping_account() {
         feed_name: feed.feed_name.clone(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let ix = Instruction {
@@ -180,6 +188,7 @@ async fn test_wrong_admin() {
         feed_name: feed.feed_name.clone(),
         token: TEST_PYTH_ORACLE.token.try_into().unwrap(),
         price_type: TEST_PYTH_ORACLE.price_type.to_u8(),
+        price_max_age: 0,
     };
 
     let ix = Instruction {
@@ -193,3 +202,23 @@ async fn test_wrong_admin() {
         AnchorErrorCode::ConstraintHasOne,
     );
 }
+
+// Out of range token index
+#[tokio::test]
+async fn test_out_of_range_token() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, Vec::new()).await;
+
+    // Initialize oracle account
+    mock_oracles::set_price(&mut ctx, &feed, &TEST_PYTH_ORACLE, &Price::default()).await;
+
+    let out_of_range_conf = OracleConf {
+        token: scope::MAX_ENTRIES,
+        ..TEST_PYTH_ORACLE
+    };
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &out_of_range_conf).await
+        ),
+        ScopeError::BadTokenNb,
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::share_price;
+use scope::{OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const SHARE_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeShareVau1tTokenAccount11111111111111111"),
+    token: 0,
+    price_type: TestOracleType::SharePrice,
+};
+
+const VAULT_MINT: Pubkey = pubkey!("SomeUnder1yingMint1111111111111111111111111");
+const VAULT_AUTHORITY: Pubkey = pubkey!("SomeVau1tAuthority1111111111111111111111111");
+
+/// Set a vault with 9 decimals underlying tokens and its share mint with 6 decimals
+fn set_vault(ctx: &mut TestContext, vault_amount: u64, share_supply: u64, minter: &Pubkey) {
+    ctx.set_account(
+        &SHARE_CONF.pubkey,
+        share_price::get_vault_data(&VAULT_MINT, &VAULT_AUTHORITY, vault_amount),
+        &share_price::token_program_id(),
+    );
+    ctx.set_account(
+        &VAULT_MINT,
+        mock_oracles::constant_product_lp::get_mint_data(1_000_000_000_000_000, 9),
+        &share_price::token_program_id(),
+    );
+    ctx.set_account(
+        &share_price::share_mint_address(&SHARE_CONF.pubkey),
+        share_price::get_share_mint_data(minter, share_supply, 6),
+        &share_price::token_program_id(),
+    );
+}
+
+// The share price is the vault amount divided by the share supply, in tokens
+#[tokio::test]
+async fn test_share_price_refresh() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    // 1050 tokens for 1000 shares
+    set_vault(&mut ctx, 1_050_000_000_000, 1_000_000_000, &VAULT_AUTHORITY);
+    operations::update_oracle_mapping(&mut ctx, &feed, &SHARE_CONF).await;
+
+    operations::refresh_price(&mut ctx, &feed, &SHARE_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[SHARE_CONF.token].price,
+        Price {
+            value: 1_050_000_000_000_000_000,
+            exp: 18
+        }
+    );
+}
+
+// A share mint without supply has no price
+#[tokio::test]
+async fn test_share_price_zero_supply() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_vault(&mut ctx, 1_050_000_000_000, 0, &VAULT_AUTHORITY);
+    operations::update_oracle_mapping(&mut ctx, &feed, &SHARE_CONF).await;
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &SHARE_CONF).await),
+        ScopeError::PriceNotValid
+    );
+}
+
+// The share mint must be minted by the owner of the vault
+#[tokio::test]
+async fn test_share_price_wrong_share_mint() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_vault(
+        &mut ctx,
+        1_050_000_000_000,
+        1_000_000_000,
+        &Pubkey::new_unique(),
+    );
+    operations::update_oracle_mapping(&mut ctx, &feed, &SHARE_CONF).await;
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &SHARE_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
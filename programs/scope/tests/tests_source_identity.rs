@@ -0,0 +1,127 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::pyth;
+use scope::{OracleMappings, OraclePrices, Price, ScopeError, UpdateMappingConfigMode};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const TEST_PYTH_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const TEST_PYTH2_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePyth2PriceAccount1111111111111111111111"),
+    token: 1,
+    price_type: TestOracleType::Pyth,
+};
+
+const PRODUCT: Pubkey = pubkey!("SomePythProductAccount111111111111111111111");
+const OTHER_PRODUCT: Pubkey = pubkey!("AnotherPythProductAccount111111111111111111");
+
+const PRICE: Price = Price {
+    value: 123_456,
+    exp: 4,
+};
+
+async fn set_pyth_price(ctx: &mut TestContext, conf: &OracleConf, product: &Pubkey) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &conf.pubkey,
+        pyth::get_account_data_for_price_and_product(&PRICE, &clock, product),
+        &pyth::id(),
+    );
+}
+
+async fn pin_identity(
+    ctx: &mut TestContext,
+    feed: &ScopeFeedDefinition,
+    conf: &OracleConf,
+    identity: &Pubkey,
+) {
+    operations::update_mapping_config(
+        ctx,
+        feed,
+        conf.token,
+        UpdateMappingConfigMode::SourceIdentity,
+        identity.to_bytes().to_vec(),
+    )
+    .await;
+}
+
+// A source embedding the pinned identity is refreshed, another one is rejected
+#[tokio::test]
+async fn test_refresh_with_pinned_identity() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE]).await;
+    set_pyth_price(&mut ctx, &TEST_PYTH_ORACLE, &PRODUCT).await;
+    pin_identity(&mut ctx, &feed, &TEST_PYTH_ORACLE, &PRODUCT).await;
+
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(mappings.source_identities[TEST_PYTH_ORACLE.token], PRODUCT);
+
+    operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[TEST_PYTH_ORACLE.token].price, PRICE);
+
+    // The source account now belongs to another product
+    ctx.fast_forward_seconds(10).await;
+    set_pyth_price(&mut ctx, &TEST_PYTH_ORACLE, &OTHER_PRODUCT).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await),
+        ScopeError::SourceIdentityMismatch
+    );
+
+    // Removing the pin allows the refresh again
+    pin_identity(&mut ctx, &feed, &TEST_PYTH_ORACLE, &Pubkey::default()).await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_PYTH_ORACLE).await;
+}
+
+// A list refresh skips the tokens whose source identity mismatches
+#[tokio::test]
+async fn test_refresh_list_skips_mismatched_identity() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE]).await;
+    set_pyth_price(&mut ctx, &TEST_PYTH_ORACLE, &PRODUCT).await;
+    set_pyth_price(&mut ctx, &TEST_PYTH2_ORACLE, &OTHER_PRODUCT).await;
+    pin_identity(&mut ctx, &feed, &TEST_PYTH_ORACLE, &PRODUCT).await;
+    pin_identity(&mut ctx, &feed, &TEST_PYTH2_ORACLE, &PRODUCT).await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[TEST_PYTH_ORACLE, TEST_PYTH2_ORACLE])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[TEST_PYTH_ORACLE.token].price, PRICE);
+    assert_eq!(data.prices[TEST_PYTH2_ORACLE.token].last_updated_slot, 0);
+}
+
+// An identity can only be pinned on oracle types embedding one
+#[tokio::test]
+async fn test_pin_identity_unsupported_type() {
+    let conf = OracleConf {
+        pubkey: pubkey!("SomeSwitchboardPriceAccount1111111111111111"),
+        token: 2,
+        price_type: TestOracleType::SwitchboardV2,
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_mapping_config(
+                &mut ctx,
+                &feed,
+                conf.token,
+                UpdateMappingConfigMode::SourceIdentity,
+                PRODUCT.to_bytes().to_vec(),
+            )
+            .await
+        ),
+        ScopeError::InvalidUpdateValue
+    );
+}
@@ -0,0 +1,122 @@
+// Pyth and Switchboard v2 owners are only checked on mainnet
+#![cfg(not(any(feature = "localnet", feature = "skip_price_validation")))]
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::{pyth, switchboard_v2};
+use scope::{oracles::most_recent_of, OraclePrices, Price, ScopeError};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::Pyth,
+};
+
+const SWITCHBOARD_V2_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeSwitchboardV2Aggregator1111111111111111"),
+    token: 1,
+    price_type: TestOracleType::SwitchboardV2,
+};
+
+const PRICE: Price = Price {
+    value: 123_456,
+    exp: 4,
+};
+
+async fn set_pyth_account(ctx: &mut TestContext, owner: &Pubkey) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &PYTH_CONF.pubkey,
+        pyth::get_account_data_for_price(&PRICE, &clock),
+        owner,
+    );
+}
+
+// A well-formed Pyth account owned by another program cannot be mapped
+#[tokio::test]
+async fn test_pyth_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pyth_account(&mut ctx, &switchboard_v2::id()).await;
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &PYTH_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+
+    set_pyth_account(&mut ctx, &pyth::id()).await;
+    operations::update_oracle_mapping(&mut ctx, &feed, &PYTH_CONF).await;
+}
+
+// A well-formed Switchboard v2 aggregator owned by another program cannot be mapped
+#[tokio::test]
+async fn test_switchboard_v2_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &SWITCHBOARD_V2_CONF.pubkey,
+        switchboard_v2::get_account_data_for_price(&PRICE, &clock),
+        &pyth::id(),
+    );
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_update_oracle_mapping(&mut ctx, &feed, &SWITCHBOARD_V2_CONF).await
+        ),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+// A mapped account taken over by another program is rejected at refresh
+#[tokio::test]
+async fn test_pyth_refresh_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    set_pyth_account(&mut ctx, &pyth::id()).await;
+    operations::refresh_price(&mut ctx, &feed, &PYTH_CONF).await;
+
+    ctx.fast_forward_seconds(10).await;
+    set_pyth_account(&mut ctx, &Pubkey::new_unique()).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &PYTH_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(data.prices[PYTH_CONF.token].price, PRICE);
+}
+
+// A source of a most recent of entry owned by another program fails the refresh even when the
+// other source is valid
+#[tokio::test]
+async fn test_most_recent_of_refresh_wrong_owner() {
+    let conf = OracleConf {
+        pubkey: most_recent_of::mapping_pubkey(&PYTH_CONF.pubkey, &SWITCHBOARD_V2_CONF.pubkey),
+        token: 2,
+        price_type: TestOracleType::MostRecentOf(PYTH_CONF.pubkey, SWITCHBOARD_V2_CONF.pubkey),
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![conf]).await;
+    set_pyth_account(&mut ctx, &pyth::id()).await;
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &SWITCHBOARD_V2_CONF.pubkey,
+        switchboard_v2::get_account_data_for_price(&PRICE, &clock),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &conf).await),
+        ScopeError::UnexpectedAccount
+    );
+
+    ctx.set_account(
+        &SWITCHBOARD_V2_CONF.pubkey,
+        switchboard_v2::get_account_data_for_price(&PRICE, &clock),
+        &switchboard_v2::id(),
+    );
+    operations::refresh_price(&mut ctx, &feed, &conf).await;
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use mock_oracles::spl_stake;
+use scope::{
+    oracles::spl_stake::spl_stake_pool::{AccountType, StakePool},
+    OraclePrices, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const POOL_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SanctumSing1eVa1idatorPoo111111111111111111"),
+    token: 0,
+    price_type: TestOracleType::SplStake,
+};
+
+/// Sanctum single-validator pool with 1.05 SOL per pool token, updated at `epoch`
+fn single_validator_pool(epoch: u64) -> StakePool {
+    let vote_account = Pubkey::new_unique();
+    StakePool {
+        account_type: AccountType::StakePool,
+        preferred_deposit_validator_vote_address: Some(vote_account),
+        preferred_withdraw_validator_vote_address: Some(vote_account),
+        total_lamports: 105_000_000_000_000,
+        pool_token_supply: 100_000_000_000_000,
+        last_update_epoch: epoch,
+        ..Default::default()
+    }
+}
+
+// A Sanctum single-validator pool is priced like a SPL stake pool
+#[tokio::test]
+async fn test_sanctum_single_validator_refresh() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    let epoch = ctx.get_clock().await.epoch;
+    ctx.set_account(
+        &POOL_CONF.pubkey,
+        spl_stake::get_stake_pool_data(&single_validator_pool(epoch)),
+        &spl_stake::SANCTUM_SINGLE_VALIDATOR_PROGRAM_ID,
+    );
+    operations::update_oracle_mapping(&mut ctx, &feed, &POOL_CONF).await;
+
+    operations::refresh_price(&mut ctx, &feed, &POOL_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[POOL_CONF.token].price,
+        Price {
+            value: 1_050_000_000_000_000,
+            exp: 15
+        }
+    );
+}
+
+// Only accounts owned by a stake pool program can be mapped
+#[tokio::test]
+async fn test_spl_stake_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    let epoch = ctx.get_clock().await.epoch;
+    ctx.set_account(
+        &POOL_CONF.pubkey,
+        spl_stake::get_stake_pool_data(&single_validator_pool(epoch)),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &POOL_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+const VALIDATOR_LIST: Pubkey = pubkey!("Va1idatorList111111111111111111111111111111");
+
+const PYTH_CONF: OracleConf = OracleConf {
+    pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+    token: 1,
+    price_type: TestOracleType::Pyth,
+};
+
+/// Feed in the 2nd hour of epoch 10 with a pool updated at epoch 9 and its validator list
+/// updated at the given epochs
+async fn setup_pool_in_update(validator_epochs: &[u64]) -> (TestContext, ScopeFeedDefinition) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![PYTH_CONF]).await;
+    let stake_pool = StakePool {
+        validator_list: VALIDATOR_LIST,
+        ..single_validator_pool(9)
+    };
+    ctx.set_account(
+        &POOL_CONF.pubkey,
+        spl_stake::get_stake_pool_data(&stake_pool),
+        &spl_stake::SPL_STAKE_POOL_PROGRAM_ID,
+    );
+    ctx.set_account(
+        &VALIDATOR_LIST,
+        spl_stake::get_validator_list_data(validator_epochs),
+        &spl_stake::SPL_STAKE_POOL_PROGRAM_ID,
+    );
+    operations::update_oracle_mapping(&mut ctx, &feed, &POOL_CONF).await;
+    ctx.set_epoch(10, 2 * 3600).await;
+    (ctx, feed)
+}
+
+// A pool not updated this epoch is accepted while its validators are being updated
+#[tokio::test]
+async fn test_spl_stake_update_in_progress() {
+    let (mut ctx, feed) = setup_pool_in_update(&[10, 10, 9]).await;
+    mock_oracles::set_price(&mut ctx, &feed, &PYTH_CONF, &Price { value: 20, exp: 0 }).await;
+
+    // The validator list is consumed as extra account in a refresh list
+    operations::refresh_price_list(&mut ctx, &feed, &[POOL_CONF, PYTH_CONF])
+        .await
+        .unwrap();
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_eq!(
+        data.prices[POOL_CONF.token].price,
+        Price {
+            value: 1_050_000_000_000_000,
+            exp: 15
+        }
+    );
+    assert_eq!(
+        data.prices[PYTH_CONF.token].price,
+        Price { value: 20, exp: 0 }
+    );
+}
+
+// A pool not updated this epoch is rejected when its update has not started
+#[tokio::test]
+async fn test_spl_stake_stale() {
+    let (mut ctx, feed) = setup_pool_in_update(&[9, 9, 9]).await;
+
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &POOL_CONF).await),
+        ScopeError::PriceNotValid
+    );
+}
+
+// A late pool is still priced by the lenient type, dated from the epoch of its last update
+#[tokio::test]
+async fn test_spl_stake_lenient_backdated() {
+    let lenient_conf = OracleConf {
+        price_type: TestOracleType::SplStakeLenient,
+        ..POOL_CONF
+    };
+    let strict_conf = OracleConf {
+        token: 1,
+        ..POOL_CONF
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    let epoch = ctx.get_clock().await.epoch + 1;
+    ctx.set_account(
+        &POOL_CONF.pubkey,
+        spl_stake::get_stake_pool_data(&single_validator_pool(epoch)),
+        &spl_stake::SPL_STAKE_POOL_PROGRAM_ID,
+    );
+    operations::update_oracle_mapping(&mut ctx, &feed, &lenient_conf).await;
+    operations::update_oracle_mapping(&mut ctx, &feed, &strict_conf).await;
+
+    // Pool updated in the current epoch
+    ctx.warp_to_epoch(epoch, 2 * 3600).await;
+    operations::refresh_price(&mut ctx, &feed, &lenient_conf).await;
+    let clock = ctx.get_clock().await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let dated_price = data.prices[lenient_conf.token];
+    assert_eq!(dated_price.last_updated_slot, clock.slot);
+    assert_eq!(dated_price.unix_timestamp, clock.unix_timestamp as u64);
+
+    // 2 hours in the next epoch, the pool has not been updated
+    ctx.warp_to_epoch(epoch + 1, 2 * 3600).await;
+    assert_eq!(
+        map_scope_error(operations::try_refresh_price(&mut ctx, &feed, &strict_conf).await),
+        ScopeError::PriceNotValid
+    );
+    operations::refresh_price(&mut ctx, &feed, &lenient_conf).await;
+    let clock = ctx.get_clock().await;
+    let epoch_start_slot = ctx
+        .get_epoch_schedule()
+        .await
+        .get_first_slot_in_epoch(epoch);
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let dated_price = data.prices[lenient_conf.token];
+    assert_eq!(
+        dated_price.price,
+        Price {
+            value: 1_050_000_000_000_000,
+            exp: 15
+        }
+    );
+    assert_eq!(dated_price.last_updated_slot, epoch_start_slot);
+    assert_eq!(
+        dated_price.unix_timestamp,
+        clock.unix_timestamp as u64 - (clock.slot - epoch_start_slot) * 400 / 1000
+    );
+}
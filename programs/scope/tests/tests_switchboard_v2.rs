@@ -0,0 +1,186 @@
+mod common;
+
+use common::*;
+use mock_oracles::switchboard_v2;
+use scope::{
+    oracles::switchboard_v2::MAX_STALENESS_SLOTS, OracleMappings, OraclePrices, Price, ScopeError,
+    UpdateMappingConfigMode,
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const TEST_SWITCHBOARD_V2_ORACLE: OracleConf = OracleConf {
+    pubkey: pubkey!("SomeSwitchboardV2Aggregator1111111111111111"),
+    token: 0,
+    price_type: TestOracleType::SwitchboardV2,
+};
+
+#[tokio::test]
+async fn test_switchboard_v2_refresh() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_SWITCHBOARD_V2_ORACLE]).await;
+
+    let price = Price {
+        value: 123_456_789,
+        exp: 6,
+    };
+    mock_oracles::set_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE, &price).await;
+
+    operations::refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await;
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let dated_price = data.prices[TEST_SWITCHBOARD_V2_ORACLE.token];
+    assert_eq!(dated_price.price, price);
+}
+
+#[tokio::test]
+async fn test_switchboard_v2_stale_round() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_SWITCHBOARD_V2_ORACLE]).await;
+
+    let price = Price {
+        value: 123_456_789,
+        exp: 6,
+    };
+    mock_oracles::set_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE, &price).await;
+
+    // 400ms slots
+    ctx.fast_forward_seconds((MAX_STALENESS_SLOTS + 1) * 400 / 1000 + 1)
+        .await;
+
+    assert_eq!(
+        map_scope_error(
+            operations::try_refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await
+        ),
+        ScopeError::PriceNotValid
+    );
+}
+
+/// Set a round of 100.000 with the given standard deviation (in thousandths)
+async fn set_round_with_std_deviation(ctx: &mut TestContext, std_deviation: u64) {
+    let clock = ctx.get_clock().await;
+    ctx.set_account(
+        &TEST_SWITCHBOARD_V2_ORACLE.pubkey,
+        switchboard_v2::get_account_data_for_price_with_std_deviation(
+            &Price {
+                value: 100_000,
+                exp: 3,
+            },
+            &Price {
+                value: std_deviation,
+                exp: 3,
+            },
+            &clock,
+        ),
+        &switchboard_v2::id(),
+    );
+}
+
+fn max_std_deviation_value(max_std_deviation_bps: u64) -> Vec<u8> {
+    max_std_deviation_bps.to_le_bytes().to_vec()
+}
+
+// Without configuration, rounds with a standard deviation of 2% or more are rejected
+#[tokio::test]
+async fn test_switchboard_v2_default_max_std_deviation() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_SWITCHBOARD_V2_ORACLE]).await;
+
+    set_round_with_std_deviation(&mut ctx, 1_999).await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await;
+
+    ctx.fast_forward_seconds(10).await;
+    set_round_with_std_deviation(&mut ctx, 2_000).await;
+    assert_eq!(
+        map_scope_error(
+            operations::try_refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await
+        ),
+        ScopeError::OracleDisagreement
+    );
+}
+
+// The configured max standard deviation replaces the default one
+#[tokio::test]
+async fn test_switchboard_v2_configured_max_std_deviation() {
+    let (mut ctx, feed) =
+        fixtures::setup_scope(DEFAULT_FEED_NAME, vec![TEST_SWITCHBOARD_V2_ORACLE]).await;
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        TEST_SWITCHBOARD_V2_ORACLE.token,
+        UpdateMappingConfigMode::MaxStdDeviation,
+        max_std_deviation_value(50),
+    )
+    .await;
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        mappings.max_std_deviations_bps[TEST_SWITCHBOARD_V2_ORACLE.token],
+        50
+    );
+
+    // Below the 0.5% bound
+    set_round_with_std_deviation(&mut ctx, 499).await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await;
+
+    // Above the bound, still below the default one
+    ctx.fast_forward_seconds(10).await;
+    set_round_with_std_deviation(&mut ctx, 1_000).await;
+    assert_eq!(
+        map_scope_error(
+            operations::try_refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await
+        ),
+        ScopeError::OracleDisagreement
+    );
+
+    // A looser bound accepts a round the default would reject
+    operations::update_mapping_config(
+        &mut ctx,
+        &feed,
+        TEST_SWITCHBOARD_V2_ORACLE.token,
+        UpdateMappingConfigMode::MaxStdDeviation,
+        max_std_deviation_value(500),
+    )
+    .await;
+    set_round_with_std_deviation(&mut ctx, 4_000).await;
+    operations::refresh_price(&mut ctx, &feed, &TEST_SWITCHBOARD_V2_ORACLE).await;
+}
+
+// The max standard deviation can only be set on Switchboard V2 entries and must fit in a u16
+#[tokio::test]
+async fn test_switchboard_v2_invalid_max_std_deviation_config() {
+    let pyth_conf = OracleConf {
+        pubkey: pubkey!("SomePythPriceAccount11111111111111111111111"),
+        token: 1,
+        price_type: TestOracleType::Pyth,
+    };
+    let (mut ctx, feed) = fixtures::setup_scope(
+        DEFAULT_FEED_NAME,
+        vec![TEST_SWITCHBOARD_V2_ORACLE, pyth_conf],
+    )
+    .await;
+
+    for (token, value) in [
+        (pyth_conf.token, max_std_deviation_value(100)),
+        (
+            TEST_SWITCHBOARD_V2_ORACLE.token,
+            max_std_deviation_value(u64::from(u16::MAX) + 1),
+        ),
+    ] {
+        assert_eq!(
+            map_scope_error(
+                operations::try_update_mapping_config(
+                    &mut ctx,
+                    &feed,
+                    token,
+                    UpdateMappingConfigMode::MaxStdDeviation,
+                    value,
+                )
+                .await
+            ),
+            ScopeError::InvalidUpdateValue
+        );
+    }
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use scope::{
+    oracles::{derived_price, fixed_price},
+    OracleMappings, Price, ScopeError,
+};
+use solana_program_test::tokio;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const FIRST_TOKEN: usize = 3;
+
+/// Mapping of consecutive tokens from `FIRST_TOKEN` with oracles of different kinds
+fn batch_confs() -> Vec<OracleConf> {
+    vec![
+        OracleConf {
+            pubkey: Pubkey::new_unique(),
+            token: FIRST_TOKEN,
+            price_type: TestOracleType::Pyth,
+        },
+        OracleConf {
+            pubkey: Pubkey::new_unique(),
+            token: FIRST_TOKEN + 1,
+            price_type: TestOracleType::SwitchboardV2,
+        },
+        OracleConf {
+            pubkey: fixed_price::price_to_mapping_pubkey(&Price { value: 1, exp: 0 }),
+            token: FIRST_TOKEN + 2,
+            price_type: TestOracleType::FixedPrice,
+        },
+        OracleConf {
+            pubkey: derived_price::sources_to_mapping_pubkey(3, 4),
+            token: FIRST_TOKEN + 3,
+            price_type: TestOracleType::ScopeChainProduct,
+        },
+    ]
+}
+
+async fn setup_feed_with_oracles(
+    confs: &[OracleConf],
+) -> (TestContext, ScopeFeedDefinition, OracleMappings) {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    for conf in confs {
+        mock_oracles::set_price(&mut ctx, &feed, conf, &Price::default()).await;
+    }
+    let mappings: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    (ctx, feed, mappings)
+}
+
+// A batch update leaves the same mapping as updating the tokens one by one
+#[tokio::test]
+async fn test_update_mapping_batch_same_as_one_by_one() {
+    let confs = batch_confs();
+
+    let (mut ctx, feed, _) = setup_feed_with_oracles(&confs).await;
+    for conf in &confs {
+        operations::update_oracle_mapping(&mut ctx, &feed, conf).await;
+    }
+    let one_by_one: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+
+    let (mut ctx, feed, _) = setup_feed_with_oracles(&confs).await;
+    operations::update_mapping_batch(&mut ctx, &feed, &confs)
+        .await
+        .unwrap();
+    let batch: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+
+    assert_eq!(batch.price_info_accounts, one_by_one.price_info_accounts);
+    assert_eq!(batch.price_types, one_by_one.price_types);
+    for conf in &confs {
+        assert_eq!(batch.price_info_accounts[conf.token], conf.pubkey);
+    }
+}
+
+// An invalid entry leaves the whole mapping untouched
+#[tokio::test]
+async fn test_update_mapping_batch_invalid_pyth_account() {
+    let mut confs = batch_confs();
+    let (mut ctx, feed, mappings_before) = setup_feed_with_oracles(&confs).await;
+
+    // The switchboard account is not a valid Pyth price account
+    confs[1].price_type = TestOracleType::Pyth;
+    assert!(operations::update_mapping_batch(&mut ctx, &feed, &confs)
+        .await
+        .is_err());
+
+    let mappings_after: OracleMappings = ctx.get_zero_copy_account(&feed.mapping).await.unwrap();
+    assert_eq!(
+        mappings_after.price_info_accounts,
+        mappings_before.price_info_accounts
+    );
+    assert_eq!(mappings_after.price_types, mappings_before.price_types);
+}
+
+#[tokio::test]
+async fn test_update_mapping_batch_out_of_range() {
+    let confs: Vec<OracleConf> = batch_confs()
+        .into_iter()
+        .enumerate()
+        .map(|(i, conf)| OracleConf {
+            token: scope::MAX_ENTRIES - 2 + i,
+            ..conf
+        })
+        .collect();
+    let (mut ctx, feed, _) = setup_feed_with_oracles(&confs).await;
+
+    assert_eq!(
+        map_scope_error(operations::update_mapping_batch(&mut ctx, &feed, &confs).await),
+        ScopeError::BadTokenNb
+    );
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, Clock, Pubkey},
+    InstructionData, ToAccountMetas,
+};
+use common::*;
+use mock_oracles::{
+    meteora_dlmm::{get_mint_data, token_program_id},
+    whirlpool,
+};
+use scope::{OraclePrices, Price, ScopeError};
+use solana_program::{
+    instruction::Instruction,
+    sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, SysvarId},
+};
+use solana_program_test::tokio;
+use solana_sdk::pubkey;
+use types::*;
+
+use crate::utils::map_scope_error;
+
+const POOL: Pubkey = pubkey!("WhirPoo111111111111111111111111111111111111");
+// SOL like token, 9 decimals
+const MINT_A: Pubkey = pubkey!("MintA11111111111111111111111111111111111111");
+// USDC like token, 6 decimals
+const MINT_B: Pubkey = pubkey!("MintB11111111111111111111111111111111111111");
+
+// 150 B per A: sqrt(150 * 10^6 / 10^9) * 2^64
+const SQRT_PRICE_150: u128 = 7_144_393_258_922_745_604;
+
+const A_TO_B_CONF: OracleConf = OracleConf {
+    pubkey: POOL,
+    token: 0,
+    price_type: TestOracleType::OrcaWhirlpoolAtoB,
+};
+
+const B_TO_A_CONF: OracleConf = OracleConf {
+    pubkey: POOL,
+    token: 1,
+    price_type: TestOracleType::OrcaWhirlpoolBtoA,
+};
+
+fn set_pool(ctx: &mut TestContext, sqrt_price: u128) {
+    ctx.set_account(
+        &POOL,
+        whirlpool::get_whirlpool_data(sqrt_price, &MINT_A, &MINT_B),
+        &whirlpool::WHIRLPOOL_PROGRAM_ID,
+    );
+    ctx.set_account(&MINT_A, get_mint_data(9), &token_program_id());
+    ctx.set_account(&MINT_B, get_mint_data(6), &token_program_id());
+}
+
+fn to_f64(price: &Price) -> f64 {
+    price.value as f64 / 10_f64.powi(price.exp.try_into().unwrap())
+}
+
+fn assert_price_eq(price: &Price, expected: f64) {
+    let rel_diff = (to_f64(price) - expected).abs() / expected;
+    assert!(rel_diff < 1e-12, "price {price:?} != {expected}");
+}
+
+// Both directions of the pool price are refreshed from the same pool account
+#[tokio::test]
+async fn test_whirlpool_refresh() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, SQRT_PRICE_150);
+    operations::update_oracle_mapping(&mut ctx, &feed, &A_TO_B_CONF).await;
+    operations::update_oracle_mapping(&mut ctx, &feed, &B_TO_A_CONF).await;
+
+    operations::refresh_price_list(&mut ctx, &feed, &[A_TO_B_CONF, B_TO_A_CONF])
+        .await
+        .unwrap();
+
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    let a_to_b = data.prices[A_TO_B_CONF.token];
+    assert_price_eq(&a_to_b.price, 150.0);
+    assert!(a_to_b.last_updated_slot > 0);
+    assert_price_eq(&data.prices[B_TO_A_CONF.token].price, 1.0 / 150.0);
+
+    // The sqrt price doubles, the price is multiplied by 4
+    ctx.fast_forward_seconds(10).await;
+    set_pool(&mut ctx, SQRT_PRICE_150 * 2);
+    operations::refresh_price(&mut ctx, &feed, &A_TO_B_CONF).await;
+    let data: OraclePrices = ctx.get_zero_copy_account(&feed.prices).await.unwrap();
+    assert_price_eq(&data.prices[A_TO_B_CONF.token].price, 600.0);
+}
+
+// The mints provided at refresh must be the ones of the pool, in order
+#[tokio::test]
+async fn test_whirlpool_wrong_mints() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, SQRT_PRICE_150);
+    operations::update_oracle_mapping(&mut ctx, &feed, &A_TO_B_CONF).await;
+
+    let mut accounts = scope::accounts::RefreshOne {
+        oracle_prices: feed.prices,
+        oracle_mappings: feed.mapping,
+        price_info: POOL,
+        clock: Clock::id(),
+        instruction_sysvar_account_info: SYSVAR_INSTRUCTIONS_ID,
+        oracle_twaps: None,
+        refresh_authority: None,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new_readonly(MINT_B, false));
+    accounts.push(AccountMeta::new_readonly(MINT_A, false));
+
+    let args = scope::instruction::RefreshOnePrice {
+        token: A_TO_B_CONF.token.try_into().unwrap(),
+    };
+    let ix = Instruction {
+        program_id: scope::id(),
+        accounts,
+        data: args.data(),
+    };
+
+    assert_eq!(
+        map_scope_error(ctx.send_transaction_with_bot(&[ix]).await),
+        ScopeError::UnexpectedAccount
+    );
+}
+
+// Only accounts owned by the Whirlpool program can be mapped
+#[tokio::test]
+async fn test_whirlpool_mapping_wrong_owner() {
+    let (mut ctx, feed) = fixtures::setup_scope(DEFAULT_FEED_NAME, vec![]).await;
+    set_pool(&mut ctx, SQRT_PRICE_150);
+    ctx.set_account(
+        &POOL,
+        whirlpool::get_whirlpool_data(SQRT_PRICE_150, &MINT_A, &MINT_B),
+        &Pubkey::new_unique(),
+    );
+
+    assert_eq!(
+        map_scope_error(operations::try_update_oracle_mapping(&mut ctx, &feed, &A_TO_B_CONF).await),
+        ScopeError::UnexpectedAccount
+    );
+}
@@ -1,6 +1,7 @@
 #![allow(clippy::result_large_err)] //Needed because we can't change Anchor result type
 
 pub mod program_id;
+pub mod reader;
 
 // Reexports to deal with eventual conflicts
 // Local use
@@ -43,10 +44,17 @@ pub struct DatedPrice {
     pub price: Price,
     pub last_updated_slot: u64,
     pub unix_timestamp: u64,
-    pub _reserved: [u64; 2],
-    pub _reserved2: [u16; 3],
+    pub generic_data: [u8; 16],
+    pub flags: u16,
+    /// Last status seen on the source account of the price (0 unknown, 1 trading, 2 halted,
+    /// 3 auction), stored even when the refresh is skipped.
+    pub source_status: u16,
+    pub _reserved2: [u16; 1],
     // Current index of the dated price.
     pub index: u16,
+    /// Confidence interval (Pyth) or standard deviation (Switchboard) of the price, with the
+    /// exponent of `price`. 0 for the oracle types without one.
+    pub conf: u64,
 }
 
 impl Default for DatedPrice {
@@ -55,18 +63,47 @@ impl Default for DatedPrice {
             price: Default::default(),
             last_updated_slot: Default::default(),
             unix_timestamp: Default::default(),
-            _reserved: Default::default(),
+            generic_data: Default::default(),
+            flags: 0,
+            source_status: 0,
             _reserved2: Default::default(),
             index: MAX_ENTRIES_U16,
+            conf: 0,
         }
     }
 }
 
+impl DatedPrice {
+    /// The confidence interval of the price was wider than the max ratio of its entry
+    pub const FLAG_LOW_CONFIDENCE: u16 = 1;
+
+    /// Whether the price was stored with [`DatedPrice::FLAG_LOW_CONFIDENCE`]
+    pub fn is_low_confidence(&self) -> bool {
+        self.flags & Self::FLAG_LOW_CONFIDENCE != 0
+    }
+
+    /// Number of slots elapsed since the price was updated
+    ///
+    /// A price updated in the future of `current_slot` has an age of 0.
+    pub fn age_slots(&self, current_slot: u64) -> u64 {
+        current_slot.saturating_sub(self.last_updated_slot)
+    }
+
+    /// Whether the price was updated more than `max_age_slots` before `current_slot`
+    pub fn is_stale(&self, current_slot: u64, max_age_slots: u64) -> bool {
+        self.age_slots(current_slot) > max_age_slots
+    }
+}
+
 // Account to store dated prices
 #[account(zero_copy)]
 pub struct OraclePrices {
     pub oracle_mappings: Pubkey,
     pub prices: [DatedPrice; MAX_ENTRIES],
+    pub version: u64,
+    pub oracle_twaps: Pubkey,
+    pub max_feed_staleness_slots: u64,
+    pub _reserved: [u64; 59],
 }
 
 // Accounts holding source of prices
@@ -74,7 +111,21 @@ pub struct OraclePrices {
 pub struct OracleMappings {
     pub price_info_accounts: [Pubkey; MAX_ENTRIES],
     pub price_types: [u8; MAX_ENTRIES],
-    pub _reserved2: [u64; MAX_ENTRIES],
+    pub hard_caps: [Price; MAX_ENTRIES],
+    pub max_source_ages: [u64; MAX_ENTRIES],
+    pub source_identities: [Pubkey; MAX_ENTRIES],
+    pub underlying_prices: [[u16; 2]; MAX_ENTRIES],
+    pub max_aum_ages: [u32; MAX_ENTRIES],
+    pub refresh_authority: Pubkey,
+    pub max_std_deviations_bps: [u16; MAX_ENTRIES],
+    pub max_deviations_bps: [u16; MAX_ENTRIES],
+    pub max_confidences_bps: [u16; MAX_ENTRIES],
+    pub confidence_policies: [u8; MAX_ENTRIES],
+    pub price_max_ages: [u64; MAX_ENTRIES],
+    pub price_bounds: [[Price; 2]; MAX_ENTRIES],
+    pub version: u64,
+    pub pyth_lazer_feed_ids: [u32; MAX_ENTRIES],
+    pub _reserved2: [u32; MAX_ENTRIES],
 }
 
 // Configuration account of the program
@@ -153,6 +204,39 @@ pub enum ScopeError {
 
     #[msg("Refresh price instruction preceded by unexpected ixs")]
     RefreshWithUnexpectedIxs,
+
+    #[msg("Invalid token metadata update mode")]
+    InvalidTokenUpdateMode,
+
+    #[msg("Invalid oracle mapping config update mode")]
+    InvalidMappingConfigUpdateMode,
+
+    #[msg("Invalid value for the requested update")]
+    InvalidUpdateValue,
+
+    #[msg("TWAP window size is bigger than the number of samples that can be stored")]
+    BadTwapWindowSize,
+
+    #[msg("The same token is mapped more than once")]
+    DuplicatedMappingToken,
+
+    #[msg("Price is older than the requested max age")]
+    StalePrice,
+
+    #[msg("Caller provided clock is inconsistent with the current clock")]
+    InvalidCallerClock,
+
+    #[msg("Derived price depends on itself")]
+    CyclicDerivedPrice,
+
+    #[msg("Derived price is refreshed before its sources")]
+    DerivedPriceBeforeSources,
+
+    #[msg("Source account identity does not match the pinned identity")]
+    SourceIdentityMismatch,
+
+    #[msg("Stable swap invariant computation did not converge")]
+    InvariantNotConverged,
 }
 
 impl<T> From<TryFromPrimitiveError<T>> for ScopeError
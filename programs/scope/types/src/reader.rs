@@ -0,0 +1,236 @@
+//! Read interface over Scope prices
+//!
+//! [`ScopeReader`] is implemented by the [`OraclePrices`] account data so consumers can read
+//! prices the same way on-chain and off-chain. With the `mock` feature, [`MockScope`] serves
+//! predefined prices from memory, allowing unit tests of price consumers without a validator.
+
+use crate::{DatedPrice, OraclePrices, ScopeError};
+
+pub trait ScopeReader {
+    /// Get the last stored price of a token, whatever its age
+    fn get_dated_price(&self, token: u16) -> Result<DatedPrice, ScopeError>;
+
+    /// Get the price of a token, rejecting it if it was updated more than `max_age_slots` ago
+    fn get_price(
+        &self,
+        token: u16,
+        current_slot: u64,
+        max_age_slots: u64,
+    ) -> Result<DatedPrice, ScopeError> {
+        let price = self.get_dated_price(token)?;
+        if price.is_stale(current_slot, max_age_slots) {
+            return Err(ScopeError::StalePrice);
+        }
+        Ok(price)
+    }
+}
+
+impl ScopeReader for OraclePrices {
+    fn get_dated_price(&self, token: u16) -> Result<DatedPrice, ScopeError> {
+        let price = self
+            .prices
+            .get(usize::from(token))
+            .ok_or(ScopeError::BadTokenNb)?;
+        if price.price.value == 0 {
+            return Err(ScopeError::PriceNotValid);
+        }
+        Ok(*price)
+    }
+}
+
+/// Number of decimals of the EVM style (wei) representation returned by [`to_18_decimals`]
+pub const EVM_DECIMALS: u64 = 18;
+
+/// Scale a price to exactly 18 decimals, as expected by EVM facing consumers
+///
+/// Prices with less than 18 decimals are scaled up, [`ScopeError::MathOverflow`] is returned if the
+/// result does not fit in a `u128` (this cannot happen with a `u64` value, but is checked anyway). Prices with more than 18 decimals are scaled down and the
+/// extra digits are truncated, this precision loss is not reported.
+pub fn to_18_decimals(dated: &DatedPrice) -> Result<u128, ScopeError> {
+    let value = u128::from(dated.price.value);
+    let exp = dated.price.exp;
+    if exp <= EVM_DECIMALS {
+        let factor = pow10(EVM_DECIMALS - exp)?;
+        value.checked_mul(factor).ok_or(ScopeError::MathOverflow)
+    } else {
+        // 10^x with x > 38 is bigger than any u128, the scaled price is then 0
+        Ok(pow10(exp - EVM_DECIMALS).map_or(0, |factor| value / factor))
+    }
+}
+
+fn pow10(exp: u64) -> Result<u128, ScopeError> {
+    let exp = u32::try_from(exp).map_err(|_| ScopeError::MathOverflow)?;
+    10_u128.checked_pow(exp).ok_or(ScopeError::MathOverflow)
+}
+
+#[cfg(any(test, feature = "mock"))]
+pub use mock::MockScope;
+
+#[cfg(any(test, feature = "mock"))]
+mod mock {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{Price, MAX_ENTRIES_U16};
+
+    /// In memory [`ScopeReader`] serving predefined prices
+    #[derive(Debug, Default, Clone)]
+    pub struct MockScope {
+        prices: HashMap<u16, DatedPrice>,
+    }
+
+    impl MockScope {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Set the price of a token as if it was refreshed at `last_updated_slot`
+        pub fn with_price(mut self, token: u16, price: Price, last_updated_slot: u64) -> Self {
+            self.set_price(
+                token,
+                DatedPrice {
+                    price,
+                    last_updated_slot,
+                    index: token,
+                    ..Default::default()
+                },
+            );
+            self
+        }
+
+        pub fn set_price(&mut self, token: u16, price: DatedPrice) {
+            self.prices.insert(token, price);
+        }
+
+        pub fn remove_price(&mut self, token: u16) {
+            self.prices.remove(&token);
+        }
+    }
+
+    impl ScopeReader for MockScope {
+        fn get_dated_price(&self, token: u16) -> Result<DatedPrice, ScopeError> {
+            if token >= MAX_ENTRIES_U16 {
+                return Err(ScopeError::BadTokenNb);
+            }
+            self.prices
+                .get(&token)
+                .copied()
+                .ok_or(ScopeError::PriceNotValid)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Price;
+
+    const SOL_USD: u16 = 0;
+    const ETH_USD: u16 = 1;
+
+    fn price(value: u64, exp: u64) -> Price {
+        Price { value, exp }
+    }
+
+    #[test]
+    fn mock_returns_configured_prices() {
+        let scope = MockScope::new()
+            .with_price(SOL_USD, price(2_000_000, 4), 100)
+            .with_price(ETH_USD, price(180_000, 2), 90);
+
+        let sol = scope.get_dated_price(SOL_USD).unwrap();
+        assert_eq!(sol.price, price(2_000_000, 4));
+        assert_eq!(sol.last_updated_slot, 100);
+        assert_eq!(sol.index, SOL_USD);
+        assert_eq!(
+            scope.get_dated_price(ETH_USD).unwrap().price,
+            price(180_000, 2)
+        );
+    }
+
+    #[test]
+    fn mock_unknown_token() {
+        let scope = MockScope::new();
+        assert_eq!(scope.get_dated_price(2), Err(ScopeError::PriceNotValid));
+        assert_eq!(
+            scope.get_dated_price(crate::MAX_ENTRIES_U16),
+            Err(ScopeError::BadTokenNb)
+        );
+    }
+
+    #[test]
+    fn mock_respects_max_age() {
+        let scope = MockScope::new().with_price(SOL_USD, price(2_000_000, 4), 100);
+
+        // Exactly at max age is still valid
+        assert!(scope.get_price(SOL_USD, 110, 10).is_ok());
+        assert_eq!(
+            scope.get_price(SOL_USD, 111, 10),
+            Err(ScopeError::StalePrice)
+        );
+    }
+
+    fn dated(value: u64, exp: u64) -> DatedPrice {
+        DatedPrice {
+            price: price(value, exp),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn to_18_decimals_scales_up() {
+        // 1.5 with 6, 9 and 15 decimals
+        assert_eq!(
+            to_18_decimals(&dated(1_500_000, 6)),
+            Ok(1_500_000_000_000_000_000)
+        );
+        assert_eq!(
+            to_18_decimals(&dated(1_500_000_000, 9)),
+            Ok(1_500_000_000_000_000_000)
+        );
+        assert_eq!(
+            to_18_decimals(&dated(1_500_000_000_000_000, 15)),
+            Ok(1_500_000_000_000_000_000)
+        );
+        assert_eq!(to_18_decimals(&dated(42, 18)), Ok(42));
+    }
+
+    #[test]
+    fn to_18_decimals_scales_down() {
+        assert_eq!(to_18_decimals(&dated(1_234_567, 20)), Ok(12_345));
+        assert_eq!(to_18_decimals(&dated(u64::MAX, 60)), Ok(0));
+        assert_eq!(to_18_decimals(&dated(u64::MAX, u64::MAX)), Ok(0));
+    }
+
+    #[test]
+    fn to_18_decimals_largest_value() {
+        // The biggest scaled value u64::MAX * 10^18 still fits in a u128
+        assert_eq!(
+            to_18_decimals(&dated(u64::MAX, 0)),
+            Ok(u128::from(u64::MAX) * 10_u128.pow(18))
+        );
+    }
+
+    #[test]
+    fn mock_price_update() {
+        let mut scope = MockScope::new().with_price(SOL_USD, price(2_000_000, 4), 100);
+        scope.set_price(
+            SOL_USD,
+            DatedPrice {
+                price: price(2_100_000, 4),
+                last_updated_slot: 200,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            scope.get_price(SOL_USD, 205, 10).unwrap().price,
+            price(2_100_000, 4)
+        );
+
+        scope.remove_price(SOL_USD);
+        assert_eq!(
+            scope.get_price(SOL_USD, 205, 10),
+            Err(ScopeError::PriceNotValid)
+        );
+    }
+}
@@ -117,7 +117,7 @@ describe('Scope crank bot tests', () => {
 
     await Promise.all(
       fakeAccounts.map(async (fakeOracleAccount, idx): Promise<any> => {
-        await program.rpc.updateMapping(new BN(getRevisedIndex(idx)), fakeOracleAccount.getType(), PRICE_FEED, {
+        await program.rpc.updateMapping(new BN(getRevisedIndex(idx)), fakeOracleAccount.getType(), PRICE_FEED, new BN(0), {
           accounts: {
             admin: admin.publicKey,
             configuration: confAccount,
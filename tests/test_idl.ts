@@ -0,0 +1,240 @@
+import { expect } from 'chai';
+import * as global from './global';
+
+// Check that the IDL generated by `anchor build` exposes the whole program interface
+// Clients are generated from this IDL, any missing item would not be usable from them.
+
+function findByName(items: any[] | undefined, name: string): any {
+  const item = (items ?? []).find((item) => item.name === name);
+  expect(item, `${name} is missing from the IDL`).to.not.be.undefined;
+  return item;
+}
+
+function names(items: any[]): string[] {
+  return items.map((item) => item.name);
+}
+
+describe('Scope IDL', () => {
+  const idl = global.ScopeIdl;
+
+  it('exposes all the instructions', () => {
+    expect(names(idl.instructions)).to.include.members([
+      'initialize',
+      'closeFeed',
+      'migrateFeed',
+      'initializeTokensMetadata',
+      'refreshOnePrice',
+      'refreshPriceList',
+      'refreshPriceListLenient',
+      'updateMapping',
+      'resetOracleMapping',
+      'replaceAllMappings',
+      'updateMappingBatch',
+      'setAllMaxSourceAges',
+      'setRefreshAuthority',
+      'setAdmin',
+      'acceptAdmin',
+      'setMaxFeedStaleness',
+      'initializeOracleIndex',
+      'updateIndexConstituents',
+      'initializeOracleTwaps',
+      'resetTwap',
+      'setEmaHalfLife',
+      'updateTokenMetadata',
+      'updateMappingConfig',
+      'getPrice',
+      'getPrices',
+      'getPriceWithClock',
+      'isPriceUsable',
+      'getPriceRescaled',
+      'getConservativePrice',
+    ]);
+
+    const setAllMaxSourceAges = findByName(idl.instructions, 'setAllMaxSourceAges');
+    expect(names(setAllMaxSourceAges.args)).to.deep.equal(['feedName', 'defaultMaxAge', 'overrides']);
+
+    const updateMappingBatch = findByName(idl.instructions, 'updateMappingBatch');
+    expect(names(updateMappingBatch.args)).to.deep.equal(['feedName', 'firstToken', 'priceTypes']);
+
+    const resetOracleMapping = findByName(idl.instructions, 'resetOracleMapping');
+    expect(names(resetOracleMapping.args)).to.deep.equal(['token', 'feedName']);
+
+    const setRefreshAuthority = findByName(idl.instructions, 'setRefreshAuthority');
+    expect(names(setRefreshAuthority.args)).to.deep.equal(['feedName', 'refreshAuthority']);
+
+    const setAdmin = findByName(idl.instructions, 'setAdmin');
+    expect(names(setAdmin.args)).to.deep.equal(['feedName', 'pendingAdmin']);
+
+    ['refreshOnePrice', 'refreshPriceList', 'refreshPriceListLenient'].forEach((name) => {
+      const refreshAuthority = findByName(findByName(idl.instructions, name).accounts, 'refreshAuthority');
+      expect(refreshAuthority, name).to.include({ isSigner: true, isOptional: true });
+    });
+
+    const setEmaHalfLife = findByName(idl.instructions, 'setEmaHalfLife');
+    expect(names(setEmaHalfLife.args)).to.deep.equal(['token', 'halfLifeSlots', 'feedName']);
+
+    const setMaxFeedStaleness = findByName(idl.instructions, 'setMaxFeedStaleness');
+    expect(names(setMaxFeedStaleness.args)).to.deep.equal(['feedName', 'maxFeedStalenessSlots']);
+
+    const getPrice = findByName(idl.instructions, 'getPrice');
+    expect(names(getPrice.accounts)).to.deep.equal(['oraclePrices']);
+
+    const updateIndexConstituents = findByName(idl.instructions, 'updateIndexConstituents');
+    expect(names(updateIndexConstituents.args)).to.deep.equal([
+      'feedName',
+      'constituents',
+      'maxConstituentAgeSlots',
+    ]);
+
+    const getPrices = findByName(idl.instructions, 'getPrices');
+    expect(names(getPrices.args)).to.deep.equal(['tokens', 'maxAgeSeconds']);
+    expect(getPrices.returns).to.deep.equal({ vec: { defined: 'DatedPrice' } });
+
+    const isPriceUsable = findByName(idl.instructions, 'isPriceUsable');
+    expect(names(isPriceUsable.args)).to.deep.equal(['token', 'maxAgeSeconds']);
+    expect(isPriceUsable.returns).to.equal('bool');
+
+    const getPriceWithClock = findByName(idl.instructions, 'getPriceWithClock');
+    expect(names(getPriceWithClock.args)).to.deep.equal(['token', 'maxAgeSeconds', 'callerClock']);
+    expect(getPriceWithClock.returns).to.deep.equal({ defined: 'DatedPrice' });
+
+    const getPriceRescaled = findByName(idl.instructions, 'getPriceRescaled');
+    expect(names(getPriceRescaled.args)).to.deep.equal(['token', 'maxAgeSeconds', 'targetExp']);
+    expect(getPriceRescaled.returns).to.deep.equal({ defined: 'DatedPrice' });
+
+    const getConservativePrice = findByName(idl.instructions, 'getConservativePrice');
+    expect(names(getConservativePrice.args)).to.deep.equal(['token', 'side', 'window', 'maxAgeSeconds']);
+    expect(names(getConservativePrice.accounts)).to.deep.equal(['oraclePrices', 'oracleMappings', 'oracleTwaps']);
+  });
+
+  it('exposes all the accounts and their fields', () => {
+    expect(names(idl.accounts)).to.include.members([
+      'Configuration',
+      'OracleMappings',
+      'OraclePrices',
+      'OracleTwaps',
+      'OracleIndex',
+      'TokenMetadatas',
+    ]);
+
+    const mappings = findByName(idl.accounts, 'OracleMappings');
+    expect(names(mappings.type.fields)).to.deep.equal([
+      'priceInfoAccounts',
+      'priceTypes',
+      'hardCaps',
+      'maxSourceAges',
+      'sourceIdentities',
+      'underlyingPrices',
+      'maxAumAges',
+      'refreshAuthority',
+      'maxStdDeviationsBps',
+      'maxDeviationsBps',
+      'maxConfidencesBps',
+      'confidencePolicies',
+      'priceMaxAges',
+      'priceBounds',
+      'version',
+      'pythLazerFeedIds',
+      'reserved2',
+    ]);
+
+    const prices = findByName(idl.accounts, 'OraclePrices');
+    expect(names(prices.type.fields)).to.deep.equal([
+      'oracleMappings',
+      'prices',
+      'version',
+      'oracleTwaps',
+      'reserved',
+    ]);
+  });
+
+  it('exposes the instruction argument types', () => {
+    expect(names(idl.types)).to.include.members([
+      'CallerClock',
+      'PriceSide',
+      'IndexConstituent',
+      'MappingEntry',
+      'MaxSourceAgeOverride',
+      'DatedPrice',
+      'Price',
+      'OracleType',
+      'UpdateMappingConfigMode',
+    ]);
+
+    const oracleType = findByName(idl.types, 'OracleType');
+    expect(names(oracleType.type.variants)).to.include.members([
+      'ScopeChainQuotient',
+      'ScopeChainInverse',
+      'MeteoraDlmmXtoY',
+      'MeteoraDlmmYtoX',
+      'ConstantProductLp',
+      'SaberStableSwapLp',
+      'JupiterLp',
+      'PythConservativeLower',
+      'PythConservativeUpper',
+      'IndexWeighted',
+      'RedStone',
+      'SharePrice',
+      'MostRecentOf',
+      'ScopeMedian',
+      'CappedFloored',
+      'ScopeTwap',
+      'SplStakeLenient',
+      'PythLazer',
+      'Discounted',
+      'Alias',
+      'OrcaWhirlpoolAtoB',
+      'OrcaWhirlpoolBtoA',
+    ]);
+
+    const configMode = findByName(idl.types, 'UpdateMappingConfigMode');
+    expect(names(configMode.type.variants)).to.deep.equal([
+      'HardCap',
+      'FixedPrice',
+      'MaxSourceAge',
+      'SourceIdentity',
+      'UnderlyingPrices',
+      'MaxAumAge',
+      'MaxStdDeviation',
+      'MaxDeviation',
+      'MaxConfidence',
+      'PriceBounds',
+      'PythLazerFeedId',
+    ]);
+  });
+
+  it('exposes the events', () => {
+    const priceRefreshed = findByName(idl.events, 'PriceRefreshed');
+    expect(names(priceRefreshed.fields)).to.deep.equal(['token', 'value', 'exp', 'slot', 'timestamp']);
+    const priceRefreshSkipped = findByName(idl.events, 'PriceRefreshSkipped');
+    expect(names(priceRefreshSkipped.fields)).to.deep.equal(['token', 'reason']);
+  });
+
+  it('exposes all the errors with contiguous codes', () => {
+    idl.errors.forEach((error: any, i: number) => {
+      expect(error.code, error.name).to.equal(6000 + i);
+      expect(error.msg, error.name).to.not.be.empty;
+    });
+    expect(names(idl.errors)).to.include.members([
+      'DuplicatedMappingToken',
+      'StalePrice',
+      'InvalidCallerClock',
+      'CyclicDerivedPrice',
+      'DerivedPriceBeforeSources',
+      'SourceIdentityMismatch',
+      'InvariantNotConverged',
+      'UnauthorizedRefresh',
+      'OracleDisagreement',
+      'PrecisionLoss',
+      'TwapWindowMismatch',
+      'InvalidPriceBand',
+      'StaleFeed',
+      'InvalidPayloadSignature',
+      'ChainedAlias',
+      'NotPendingAdmin',
+      'PriceOutOfBounds',
+      'PriceConfidenceTooWide',
+    ]);
+    expect(idl.errors[idl.errors.length - 1].name).to.equal('PriceConfidenceTooWide');
+  });
+});
@@ -104,7 +104,7 @@ describe('Scope tests', () => {
       testTokens.map(async (fakeOracleAccount, idx): Promise<any> => {
         // console.log(`Set mapping of ${fakeOracleAccount.ticker} ${fakeOracleAccount.getType()}`);
 
-        await program.rpc.updateMapping(new BN(idx), fakeOracleAccount.getType(), PRICE_FEED, {
+        await program.rpc.updateMapping(new BN(idx), fakeOracleAccount.getType(), PRICE_FEED, new BN(0), {
           accounts: {
             admin: admin.publicKey,
             configuration: confAccount,
@@ -198,7 +198,7 @@ describe('Scope tests', () => {
     // In this test set the tokens from the end of the mapping for limit testing
     await Promise.all(
       testTokensExtra.map(async (fakeOracleAccount, idx): Promise<any> => {
-        await program.rpc.updateMapping(new BN(global.MAX_NB_TOKENS - idx - 1), OracleType.Pyth, PRICE_FEED, {
+        await program.rpc.updateMapping(new BN(global.MAX_NB_TOKENS - idx - 1), OracleType.Pyth, PRICE_FEED, new BN(0), {
           accounts: {
             admin: admin.publicKey,
             configuration: confAccount,
@@ -98,7 +98,7 @@ describe('Switchboard Scope tests', () => {
       testTokens.map(async (fakeOracleAccount, idx): Promise<any> => {
         // console.log(`Set mapping of ${fakeOracleAccount.ticker}`);
 
-        await program.rpc.updateMapping(new BN(idx), fakeOracleAccount.getType(), PRICE_FEED, {
+        await program.rpc.updateMapping(new BN(idx), fakeOracleAccount.getType(), PRICE_FEED, new BN(0), {
           accounts: {
             admin: admin.publicKey,
             configuration: confAccount,